@@ -1,5 +1,11 @@
 import { type ComponentProps, useCallback, useEffect, useMemo, useReducer, useRef, useState } from 'react'
-import { Excalidraw, convertToExcalidrawElements, serializeAsJSON } from '@excalidraw/excalidraw'
+import {
+  Excalidraw,
+  convertToExcalidrawElements,
+  exportToBlob,
+  exportToSvg,
+  serializeAsJSON,
+} from '@excalidraw/excalidraw'
 import { parseMermaidToExcalidraw } from '@excalidraw/mermaid-to-excalidraw'
 import type {
   BinaryFileData,
@@ -11,6 +17,95 @@ import { getCurrentWindow } from '@tauri-apps/api/window'
 import mermaid from 'mermaid'
 import './App.css'
 
+type NewDocumentEvent = {
+  kind: 'excalidraw' | 'mermaid'
+  title?: string
+  contents: string
+}
+
+// Sent instead of showing a native file dialog where dialogs don't work.
+type DialogUnavailableEvent = {
+  action: 'open' | 'save'
+  filter_name: string
+  extensions: string[]
+  suggested_name: string | null
+  reason: string
+}
+
+// Sent when opening or saving an encrypted document needs a password. Answered with
+// `submit_password`; a prompt left unanswered is given up on by the backend.
+type PasswordPromptEvent = {
+  operation_id: string
+  path: string
+  purpose: 'open' | 'save'
+  error: 'wrong_password' | null
+}
+
+type RenderRequest = {
+  request_id: string
+  kind: 'excalidraw' | 'mermaid'
+  contents: string
+  format: 'svg' | 'png'
+}
+
+type RenderOutput = { text: string } | { bytes: number[] }
+
+const blobToBytes = async (blob: Blob) => Array.from(new Uint8Array(await blob.arrayBuffer()))
+
+// A data: URL, which save_export_file accepts as base64.
+const blobToDataUrl = (blob: Blob) =>
+  new Promise<string>((resolve, reject) => {
+    const reader = new FileReader()
+    reader.onload = () => resolve(reader.result as string)
+    reader.onerror = () => reject(reader.error)
+    reader.readAsDataURL(blob)
+  })
+
+const rasterizeSvg = (svg: string) =>
+  new Promise<Blob>((resolve, reject) => {
+    const image = new Image()
+    image.onload = () => {
+      const canvas = document.createElement('canvas')
+      canvas.width = image.width * 2
+      canvas.height = image.height * 2
+      const context = canvas.getContext('2d')
+      if (!context) {
+        reject(new Error('Unable to create a canvas'))
+        return
+      }
+      context.fillStyle = '#ffffff'
+      context.fillRect(0, 0, canvas.width, canvas.height)
+      context.drawImage(image, 0, 0, canvas.width, canvas.height)
+      canvas.toBlob((blob) => (blob ? resolve(blob) : reject(new Error('Unable to encode PNG'))), 'image/png')
+    }
+    image.onerror = () => reject(new Error('Unable to rasterize SVG'))
+    image.src = `data:image/svg+xml;charset=utf-8,${encodeURIComponent(svg)}`
+  })
+
+// Renders a document for headless export without touching the editor state.
+async function renderDocument(request: RenderRequest): Promise<RenderOutput> {
+  if (request.kind === 'mermaid') {
+    const cleanedText = request.contents.replace(/^\uFEFF/, '').trim()
+    const { svg } = await mermaid.render(`export-${request.request_id}`, cleanedText)
+    if (request.format === 'svg') {
+      return { text: svg }
+    }
+    return { bytes: await blobToBytes(await rasterizeSvg(svg)) }
+  }
+
+  const scene = JSON.parse(request.contents)
+  const input = {
+    elements: scene.elements ?? [],
+    appState: { ...scene.appState, exportBackground: true },
+    files: scene.files ?? {},
+  }
+  if (request.format === 'svg') {
+    const svg = await exportToSvg(input)
+    return { text: svg.outerHTML }
+  }
+  return { bytes: await blobToBytes(await exportToBlob({ ...input, mimeType: 'image/png' })) }
+}
+
 type MermaidHistoryState = {
   text: string
   past: string[]
@@ -56,22 +151,169 @@ type ExcalidrawData = {
 }
 
 type RecentItem = {
-  kind: 'excalidraw' | 'mermaid'
+  kind: 'excalidraw' | 'mermaid' | 'library'
   path: string
+  display_path: string
+  preview?: string | null
   name?: string | null
   updated_at: number
+  pinned: boolean
+  exists: boolean
+}
+
+type RenamedFile = {
+  path: string
+  name: string
+}
+
+type TrashFileResponse = {
+  outcome: 'trashed' | 'deleted' | 'already_gone'
+  recents: RecentItem[]
+}
+
+type SceneSchema = {
+  file_version: number | null
+  source: string | null
+  supported_version: number
+  newer: boolean
 }
 
 type OpenFileResponse = {
   path: string
   name?: string | null
   contents: string
+  schema?: SceneSchema
+  read_only?: boolean
+  writable_dir?: boolean
+  meta?: FileMeta
+  large_file?: boolean
+  compressed?: boolean
+  remote?: boolean
+  embedded_in_png?: boolean
+  valid?: boolean
+  warnings?: string[]
+  encoding?: string
+  block_range?: BlockRange
+  blocks?: MermaidBlock[]
+  mtime?: number | null
+}
+
+// Body lines of a ```mermaid block in a Markdown file, 0-based with `end` exclusive.
+type BlockRange = { start: number; end: number }
+
+type MermaidBlock = { contents: string; range: BlockRange }
+
+// Narrows Markdown with several mermaid blocks to the one the user picks; null when cancelled.
+const chooseMermaidBlock = (file: OpenFileResponse): OpenFileResponse | null => {
+  if (!file.blocks?.length) {
+    return file
+  }
+  const choices = file.blocks
+    .map((block, index) => `${index + 1}. line ${block.range.start + 1}: ${block.contents.split('\n')[0]}`)
+    .join('\n')
+  const answer = window.prompt(`${file.path} has ${file.blocks.length} mermaid diagrams. Which one?\n\n${choices}`, '1')
+  const block = answer === null ? undefined : file.blocks[Number(answer) - 1]
+  if (!block) {
+    return null
+  }
+  return { ...file, contents: block.contents, block_range: block.range, blocks: undefined }
+}
+
+// Status line after opening `file`, warning when it will be re-saved as UTF-8.
+const loadedMessage = (file: OpenFileResponse) =>
+  [
+    `Loaded ${file.path}.`,
+    file.encoding ? `It is in ${file.encoding} and will be saved as UTF-8.` : '',
+    file.valid === false ? `It doesn't look like a valid Excalidraw scene: ${file.warnings?.join('; ')}.` : '',
+    file.writable_dir === false ? "Its folder can't be written to, so save a copy elsewhere with Save As." : '',
+    file.remote ? 'It was fetched from the web, so save a copy with Save As.' : '',
+    file.large_file && file.meta
+      ? `It is ${Math.round(file.meta.size_bytes / (1024 * 1024))} MB, so saving may take a while.`
+      : '',
+  ]
+    .filter(Boolean)
+    .join(' ')
+
+type OpenFilesResponse = {
+  files: OpenFileResponse[]
+  errors: { path: string; error: { code: string; message: string } }[]
+}
+
+type OpenPathResponse = OpenFileResponse & {
+  kind: 'excalidraw' | 'mermaid'
+}
+
+type FileMeta = {
+  size_bytes: number
+  modified_epoch: number
+  created_epoch?: number | null
 }
 
 type SaveFileResponse = {
   path: string
+  name?: string
+  mtime?: number | null
+  open_in_other_windows?: string[]
 }
 
+type SaveFileRequest = {
+  path: string | null
+  name?: string
+  contents: string
+  acknowledge_downgrade?: boolean
+  png_base64?: string
+  block_range?: BlockRange
+  compress?: boolean
+  always_prompt?: boolean
+}
+
+type ConflictError = {
+  code: 'conflict'
+  path: string
+  current_mtime: number
+}
+
+const isConflictError = (error: unknown): error is ConflictError =>
+  typeof error === 'object' && error !== null && (error as { code?: unknown }).code === 'conflict'
+
+type UnwritableError = {
+  code: 'permission_denied' | 'read_only_document'
+  path: string
+  on_disk?: boolean
+}
+
+// The file itself can't be written, so only saving elsewhere can work.
+const isUnwritableError = (error: unknown): error is UnwritableError => {
+  if (typeof error !== 'object' || error === null) {
+    return false
+  }
+  const { code, on_disk: onDisk } = error as { code?: unknown; on_disk?: unknown }
+  return code === 'permission_denied' || (code === 'read_only_document' && onDisk === true)
+}
+
+type AlreadyOpenError = {
+  code: 'already_open'
+  path: string
+  window: string
+}
+
+const isAlreadyOpenError = (error: unknown): error is AlreadyOpenError =>
+  typeof error === 'object' && error !== null && (error as { code?: unknown }).code === 'already_open'
+
+const savedMessage = (response: SaveFileResponse) =>
+  response.open_in_other_windows?.length
+    ? `Saved to ${response.path}. It is also open in another window, whose copy is now out of date.`
+    : `Saved to ${response.path}.`
+
+type PrivacyStatus = {
+  enabled: boolean
+  locked: boolean
+  password_required: boolean
+}
+
+// Input reports to the backend's idle timer are sent at most this often.
+const ACTIVITY_REPORT_INTERVAL_MS = 30_000
+
 type ExcalidrawAutosave = {
   contents: string
   path: string | null
@@ -97,6 +339,20 @@ type MermaidPersistedState = {
 
 type ExcalidrawChangeHandler = NonNullable<ComponentProps<typeof Excalidraw>['onChange']>
 
+type DrawioImport = {
+  source_path: string
+  contents: string
+  report: {
+    pages: number
+    shapes: number
+    arrows: number
+    labels: number
+    dropped: Record<string, number>
+  }
+  suggested_path: string
+  suggested_path_exists: boolean
+}
+
 type ApplyExcalidrawContentsRequest = {
   contents: string
   path: string | null
@@ -108,6 +364,9 @@ type ApplyExcalidrawContentsRequest = {
 
 const EXCALIDRAW_AUTOSAVE_KEY = 'excalibur.excalidraw.autosave.current'
 const EXCALIDRAW_RECOVERY_KEY = 'excalibur.excalidraw.autosave.recovery'
+// Whether the open scene is saved to its file on a timer, besides the draft above.
+const FILE_AUTOSAVE_KEY = 'excalibur.excalidraw.autosave.file'
+const FILE_AUTOSAVE_INTERVAL_SECS = 30
 const INITIAL_MERMAID_TEXT = 'flowchart TD\n  A[Start] --> B{Decision}\n  B -->|Yes| C[Ship it]\n  B -->|No| D[Refine]'
 
 function normalizeExcalidrawName(name?: string | null) {
@@ -118,19 +377,6 @@ function getUnsavedChangesMessage(documentName: string, action: string) {
   return `You have unsaved ${documentName} changes. Save them before you ${action}. Select OK to continue without saving, or Cancel to go back.`
 }
 
-function getExitUnsavedChangesMessage(
-  hasExcalidrawChanges: boolean,
-  hasMermaidChanges: boolean,
-) {
-  if (hasExcalidrawChanges && hasMermaidChanges) {
-    return 'You have unsaved changes in Excalidraw and Mermaid. Save them before you exit. Select OK to exit without saving, or Cancel to go back.'
-  }
-  if (hasExcalidrawChanges) {
-    return 'You have unsaved Excalidraw changes. Save them before you exit. Select OK to exit without saving, or Cancel to go back.'
-  }
-  return 'You have unsaved Mermaid changes. Save them before you exit. Select OK to exit without saving, or Cancel to go back.'
-}
-
 function readStoredExcalidrawAutosave(storageKey: string): ExcalidrawAutosave | null {
   try {
     const raw = window.localStorage.getItem(storageKey)
@@ -156,6 +402,11 @@ function App() {
   const [excalidrawApi, setExcalidrawApiInternal] = useState<ExcalidrawImperativeAPI | null>(null)
   const [tab, setTab] = useState<'excalidraw' | 'mermaid'>('excalidraw')
   const [recents, setRecents] = useState<RecentItem[]>([])
+  const [privacyLock, setPrivacyLock] = useState<{ passwordRequired: boolean } | null>(null)
+  const [privacyPassword, setPrivacyPassword] = useState('')
+  const [privacyError, setPrivacyError] = useState('')
+  const [passwordPrompts, setPasswordPrompts] = useState<PasswordPromptEvent[]>([])
+  const [promptPassword, setPromptPassword] = useState('')
 
   const setExcalidrawApi = useCallback((api: ExcalidrawImperativeAPI | null) => {
     console.log('[excalibur] setExcalidrawApi called:', api ? 'API instance received' : 'null')
@@ -165,13 +416,19 @@ function App() {
   const [excalidrawPath, setExcalidrawPath] = useState<string | null>(null)
   const [excalidrawName, setExcalidrawName] = useState('')
   const [excalidrawMessage, setExcalidrawMessage] = useState('')
+  const [lastExportPath, setLastExportPath] = useState<string | null>(null)
   const [hasUnsavedExcalidrawChanges, setHasUnsavedExcalidrawChanges] = useState(false)
+  const [fileAutosave, setFileAutosave] = useState(
+    () => window.localStorage.getItem(FILE_AUTOSAVE_KEY) === 'on',
+  )
   const [recoverableAutosave, setRecoverableAutosave] = useState<ExcalidrawAutosave | null>(() =>
     readStoredExcalidrawAutosave(EXCALIDRAW_RECOVERY_KEY),
   )
 
   const [mermaidPath, setMermaidPath] = useState<string | null>(null)
   const [mermaidName, setMermaidName] = useState('')
+  // The mermaid block of a Markdown file being edited; saves splice it back in place.
+  const [mermaidBlockRange, setMermaidBlockRange] = useState<BlockRange | null>(null)
   const [hasUnsavedMermaidChanges, setHasUnsavedMermaidChanges] = useState(false)
   const [mermaidHistory, dispatchMermaid] = useReducer(mermaidHistoryReducer, {
     text: INITIAL_MERMAID_TEXT,
@@ -206,11 +463,184 @@ function App() {
     }
   }, [])
 
+  // Blank everything while the privacy lock is on. The documents stay in memory,
+  // so unlocking continues exactly where the user left off.
+  useEffect(() => {
+    const showLock = () =>
+      invoke<PrivacyStatus>('privacy_status').then((status) => {
+        if (status.locked) {
+          setPrivacyLock({ passwordRequired: status.password_required })
+        }
+      })
+    showLock()
+    const listeners = Promise.all([
+      listen('privacy-lock', () => showLock()),
+      listen('privacy-unlock', () => {
+        setPrivacyLock(null)
+        setPrivacyPassword('')
+        setPrivacyError('')
+        invoke<RecentItem[]>('list_recents').then(setRecents)
+      }),
+    ])
+    return () => {
+      listeners.then((unlisteners) => unlisteners.forEach((unlisten) => unlisten()))
+    }
+  }, [])
+
+  useEffect(() => {
+    let lastReport = 0
+    const reportActivity = () => {
+      const now = Date.now()
+      if (now - lastReport >= ACTIVITY_REPORT_INTERVAL_MS) {
+        lastReport = now
+        invoke('report_activity')
+      }
+    }
+    const events = ['pointerdown', 'pointermove', 'keydown', 'wheel'] as const
+    events.forEach((name) => window.addEventListener(name, reportActivity, { passive: true }))
+    return () => events.forEach((name) => window.removeEventListener(name, reportActivity))
+  }, [])
+
+  useEffect(() => {
+    const unlisten = listen<PasswordPromptEvent>('password-required', (event) =>
+      setPasswordPrompts((prompts) => [...prompts, event.payload]),
+    )
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [])
+
+  // Answers the prompt on screen; `null` dismisses it and the open or save is cancelled.
+  const answerPasswordPrompt = useCallback(
+    (password: string | null) => {
+      const prompt = passwordPrompts[0]
+      if (!prompt) return
+      setPasswordPrompts((prompts) => prompts.slice(1))
+      setPromptPassword('')
+      invoke('submit_password', { operationId: prompt.operation_id, password }).catch((error) =>
+        // Already answered in another window, or given up on while it was showing.
+        console.warn('[excalibur] password prompt no longer waiting:', error),
+      )
+    },
+    [passwordPrompts],
+  )
+
+  const handlePrivacyUnlock = useCallback(async () => {
+    try {
+      await invoke('privacy_unlock', { password: privacyPassword || undefined })
+    } catch (error) {
+      const message = (error as { message?: string })?.message
+      setPrivacyError(message ?? 'Unable to unlock.')
+    }
+  }, [privacyPassword])
+
   // Pending file path for the startup race condition (event arrives before excalidrawApi is ready)
-  const pendingOpenFile = useRef<string | null>(null)
+  const pendingOpenFiles = useRef<string[]>([])
   const pendingExcalidrawContentsRef = useRef<ApplyExcalidrawContentsRequest | null>(null)
   const excalidrawPathRef = useRef<string | null>(null)
   const excalidrawNameRef = useRef('')
+  const newerSceneVersionPathRef = useRef<string | null>(null)
+  // Documents opened read-only; the backend refuses to save them until made writable.
+  const [readOnlyPaths, setReadOnlyPaths] = useState<string[]>([])
+  // Documents fetched from a URL; there is no file to save to, so Save becomes Save As.
+  const [remotePaths, setRemotePaths] = useState<string[]>([])
+
+  // Modification time of each open document when it was opened or last saved, sent
+  // with saves so the backend can refuse to overwrite changes made on disk meanwhile.
+  const documentMtimesRef = useRef(new Map<string, number | null>())
+
+  const trackOpenedFile = useCallback((file: OpenFileResponse) => {
+    documentMtimesRef.current.set(file.path, file.mtime ?? null)
+    setReadOnlyPaths((paths) => {
+      const others = paths.filter((path) => path !== file.path)
+      return file.read_only ? [...others, file.path] : others
+    })
+    setRemotePaths((paths) => {
+      const others = paths.filter((path) => path !== file.path)
+      return file.remote ? [...others, file.path] : others
+    })
+  }, [])
+
+  // Asks before saving over a read-only document and lifts the flag if the user agrees.
+  const confirmWritable = useCallback(
+    async (path: string | null, setMessage: (message: string) => void) => {
+      if (path === null || !readOnlyPaths.includes(path)) {
+        return true
+      }
+      if (!window.confirm(`${path} was opened read-only. Make it writable and save over it?`)) {
+        return false
+      }
+      try {
+        await invoke('make_writable', { path })
+      } catch (error) {
+        setMessage((error as { message?: string })?.message ?? String(error))
+        return false
+      }
+      setReadOnlyPaths((paths) => paths.filter((other) => other !== path))
+      return true
+    },
+    [readOnlyPaths],
+  )
+  // Saves through `command`, asking whether to overwrite, save elsewhere or cancel when
+  // the file changed on disk since it was opened. Resolves to null when cancelled.
+  const saveDocument = useCallback(
+    async (command: 'save_excalidraw_file' | 'save_mermaid_file', request: SaveFileRequest) => {
+      const expectedMtime = request.path !== null ? documentMtimesRef.current.get(request.path) : undefined
+      let response: SaveFileResponse
+      try {
+        response = await invoke<SaveFileResponse>(command, {
+          request: { ...request, expected_mtime: expectedMtime ?? undefined },
+        })
+      } catch (error) {
+        if (isUnwritableError(error) && request.path !== null) {
+          if (!window.confirm(`${error.path} can't be written to. Save this version to a new file instead?`)) {
+            return null
+          }
+          response = await invoke<SaveFileResponse>(command, { request: { ...request, always_prompt: true } })
+          documentMtimesRef.current.set(response.path, response.mtime ?? null)
+          return response
+        }
+        if (!isConflictError(error)) {
+          throw error
+        }
+        if (window.confirm(`${error.path} was changed on disk since it was opened. Overwrite it with this version?`)) {
+          response = await invoke<SaveFileResponse>(command, { request: { ...request, force: true } })
+        } else if (window.confirm('Save this version to a new file instead?')) {
+          response = await invoke<SaveFileResponse>(command, { request: { ...request, always_prompt: true } })
+        } else {
+          return null
+        }
+      }
+      documentMtimesRef.current.set(response.path, response.mtime ?? null)
+      return response
+    },
+    [],
+  )
+  // Runs `open`; when the file is already open in another window, offers to switch to
+  // that window, or else to open a second copy here through `openAgain`. Resolves to
+  // null when the user switched windows or cancelled.
+  const resolveAlreadyOpen = useCallback(
+    async <T,>(open: () => Promise<T>, openAgain: (path: string) => Promise<T>) => {
+      try {
+        return await open()
+      } catch (error) {
+        if (!isAlreadyOpenError(error)) {
+          throw error
+        }
+        if (window.confirm(`${error.path} is already open in another window. Switch to that window?`)) {
+          await invoke('focus_window', { label: error.window })
+          return null
+        }
+        if (!window.confirm('Open a second copy in this window? Saving in either window will warn about the other.')) {
+          return null
+        }
+        return await openAgain(error.path)
+      }
+    },
+    [],
+  )
+  // Open documents whose drive was ejected; saving them goes to a save dialog instead.
+  const disconnectedPathsRef = useRef(new Set<string>())
   const excalidrawSceneSnapshotRef = useRef<ExcalidrawSceneSnapshot | null>(null)
   const excalidrawPersistedRef = useRef<ExcalidrawPersistedState | null>(null)
   const autosaveSnapshotRef = useRef<ExcalidrawAutosave | null>(
@@ -221,8 +651,6 @@ function App() {
     name: '',
     text: INITIAL_MERMAID_TEXT,
   })
-  const hasUnsavedExcalidrawChangesRef = useRef(false)
-  const hasUnsavedMermaidChangesRef = useRef(false)
 
   const setExcalidrawDocument = useCallback((path: string | null, name: string) => {
     excalidrawPathRef.current = path
@@ -327,10 +755,22 @@ function App() {
     })
   }, [])
 
+  // Headless export: render what the backend asks for and hand the output back.
   useEffect(() => {
-    hasUnsavedExcalidrawChangesRef.current = hasUnsavedExcalidrawChanges
-    hasUnsavedMermaidChangesRef.current = hasUnsavedMermaidChanges
-  }, [hasUnsavedExcalidrawChanges, hasUnsavedMermaidChanges])
+    const unlisten = listen<RenderRequest>('render-request', async (event) => {
+      const requestId = event.payload.request_id
+      try {
+        const output = await renderDocument(event.payload)
+        await invoke('submit_render', { requestId, ...output })
+      } catch (error) {
+        await invoke('submit_render', { requestId, error: String(error) })
+      }
+    })
+    unlisten.then(() => invoke('render_ready'))
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [])
 
   useEffect(() => {
     let isActive = true
@@ -374,37 +814,6 @@ function App() {
     }
   }, [hasUnsavedExcalidrawChanges, hasUnsavedMermaidChanges])
 
-  useEffect(() => {
-    let isActive = true
-    let unlisten: (() => void) | null = null
-
-    getCurrentWindow()
-      .onCloseRequested((event) => {
-        const hasExcalidrawChanges = hasUnsavedExcalidrawChangesRef.current
-        const hasMermaidChanges = hasUnsavedMermaidChangesRef.current
-
-        if (!hasExcalidrawChanges && !hasMermaidChanges) {
-          return
-        }
-
-        if (!window.confirm(getExitUnsavedChangesMessage(hasExcalidrawChanges, hasMermaidChanges))) {
-          event.preventDefault()
-        }
-      })
-      .then((cleanup) => {
-        if (!isActive) {
-          cleanup()
-          return
-        }
-        unlisten = cleanup
-      })
-
-    return () => {
-      isActive = false
-      unlisten?.()
-    }
-  }, [])
-
   const applyExcalidrawContents = useCallback(
     (request: ApplyExcalidrawContentsRequest) => {
       const {
@@ -528,16 +937,21 @@ function App() {
 
   const applyExcalidrawFile = useCallback(
     (file: OpenFileResponse) => {
+      const newer = file.schema?.newer ? file.schema : null
+      newerSceneVersionPathRef.current = newer ? file.path : null
+      trackOpenedFile(file)
       applyExcalidrawContents({
         contents: file.contents,
         path: file.path,
         name: file.name,
-        message: `Loaded ${file.path}.`,
+        message: newer
+          ? `${loadedMessage(file)} It was saved by a newer Excalidraw (scene version ${newer.file_version}); saving here may drop data.`
+          : loadedMessage(file),
         markDocumentClean: true,
         refreshRecentsOnSuccess: true,
       })
     },
-    [applyExcalidrawContents],
+    [applyExcalidrawContents, trackOpenedFile],
   )
 
   const flushPendingExcalidrawContents = useCallback(() => {
@@ -582,7 +996,10 @@ function App() {
       return
     }
     try {
-      const response = await invoke<OpenFileResponse | null>('open_excalidraw_file')
+      const response = await resolveAlreadyOpen(
+        () => invoke<OpenFileResponse | null>('open_excalidraw_file'),
+        (path) => invoke<OpenFileResponse>('load_excalidraw_path', { path, allowDuplicate: true }),
+      )
       console.log('[excalibur] handleOpenExcalidraw: invoke returned', {
         hasResponse: !!response,
         path: response?.path,
@@ -596,12 +1013,118 @@ function App() {
     } catch (error) {
       console.error('[excalibur] handleOpenExcalidraw: invoke FAILED', error)
     }
-  }, [applyExcalidrawFile, confirmExcalidrawAction])
+  }, [applyExcalidrawFile, confirmExcalidrawAction, resolveAlreadyOpen])
+
+  // Converts a draw.io file, then offers to save the scene next to it as .excalidraw;
+  // declining still loads it, unsaved.
+  const handleImportDrawio = useCallback(async () => {
+    if (!confirmExcalidrawAction('import a draw.io diagram')) {
+      return
+    }
+    try {
+      const imported = await invoke<DrawioImport | null>('import_drawio', { path: null })
+      if (!imported) {
+        return
+      }
+      const { report } = imported
+      const dropped = Object.entries(report.dropped)
+        .map(([feature, count]) => `${feature} (${count})`)
+        .join(', ')
+      const summary = `Converted ${report.shapes} shapes, ${report.arrows} arrows and ${report.labels} labels from ${imported.source_path}.${dropped ? ` Left out: ${dropped}.` : ''}`
+      const replacing = imported.suggested_path_exists ? ', replacing the existing file' : ''
+      const saved = window.confirm(`${summary}\n\nSave it as ${imported.suggested_path}${replacing}?`)
+        ? await saveDocument('save_excalidraw_file', { path: imported.suggested_path, contents: imported.contents })
+        : null
+      applyExcalidrawContents({
+        contents: imported.contents,
+        path: saved?.path ?? null,
+        message: saved ? `${summary} Saved to ${saved.path}.` : `${summary} Not saved yet.`,
+        markDocumentClean: saved !== null,
+        refreshRecentsOnSuccess: saved !== null,
+      })
+    } catch (error) {
+      console.error('[excalibur] import_drawio FAILED', error)
+      setExcalidrawMessage((error as { message?: string })?.message ?? String(error))
+    }
+  }, [applyExcalidrawContents, confirmExcalidrawAction, saveDocument])
 
-  const handleSaveExcalidraw = useCallback(async () => {
+  const handleExportExcalidrawPng = useCallback(async () => {
     if (!excalidrawApi) {
       return
     }
+    try {
+      const blob = await exportToBlob({
+        elements: excalidrawApi.getSceneElements(),
+        appState: { ...excalidrawApi.getAppState(), exportBackground: true },
+        files: excalidrawApi.getFiles(),
+        mimeType: 'image/png',
+      })
+      const baseName = (excalidrawName || 'drawing').replace(/\.(excalidraw|json)$/, '')
+      // Saved scenes export next to themselves, e.g. arch.excalidraw to arch.png.
+      const request = {
+        source_path: excalidrawPath !== null && !remotePaths.includes(excalidrawPath) ? excalidrawPath : null,
+        extension: 'png',
+        data: await blobToDataUrl(blob),
+        suggested_name: `${baseName}.png`,
+      }
+      let saved: SaveFileResponse | null
+      try {
+        saved = await invoke<SaveFileResponse | null>('export_alongside', { request })
+      } catch (error) {
+        const { code, message } = error as { code?: string; message?: string }
+        if (code !== 'file_exists' || !window.confirm(`${message}. Replace it?`)) {
+          throw error
+        }
+        saved = await invoke<SaveFileResponse | null>('export_alongside', { request: { ...request, overwrite: true } })
+      }
+      if (saved) {
+        setExcalidrawMessage(`Exported to ${saved.path}`)
+        setLastExportPath(saved.path)
+      }
+    } catch (error) {
+      console.error('[excalibur] export_alongside FAILED', error)
+      setExcalidrawMessage((error as { message?: string })?.message ?? String(error))
+    }
+  }, [excalidrawApi, excalidrawName, excalidrawPath, remotePaths])
+
+  const handleRevealPath = useCallback(async (path: string) => {
+    try {
+      await invoke('reveal_in_file_manager', { path })
+    } catch (error) {
+      console.error('[excalibur] reveal_in_file_manager FAILED', error)
+      setExcalidrawMessage((error as { message?: string })?.message ?? String(error))
+    }
+  }, [])
+
+  const handleCopyPath = useCallback(async (path: string) => {
+    try {
+      const copied = await invoke<string>('copy_path_to_clipboard', { path })
+      setExcalidrawMessage(`Copied ${copied}`)
+    } catch (error) {
+      console.error('[excalibur] copy_path_to_clipboard FAILED', error)
+      setExcalidrawMessage((error as { message?: string })?.message ?? String(error))
+    }
+  }, [])
+
+  const handleCopyLogs = useCallback(async () => {
+    try {
+      const [logs, logPath] = await Promise.all([
+        invoke<string>('get_recent_logs', { lines: 500 }),
+        invoke<string>('get_log_path'),
+      ])
+      await navigator.clipboard.writeText(logs)
+      setExcalidrawMessage(`Copied recent logs from ${logPath}`)
+    } catch (error) {
+      console.error('[excalibur] get_recent_logs FAILED', error)
+      setExcalidrawMessage((error as { message?: string })?.message ?? String(error))
+    }
+  }, [])
+
+  const handleSaveExcalidraw = useCallback(async (targetPath?: string, saveAs = false) => {
+    if (!excalidrawApi) {
+      return false
+    }
+    saveAs ||= excalidrawPath !== null && remotePaths.includes(excalidrawPath)
     const hasContent = excalidrawApi.getSceneElements().some((element) => !element.isDeleted)
     const serialized = serializeAsJSON(
       excalidrawApi.getSceneElements(),
@@ -609,14 +1132,54 @@ function App() {
       excalidrawApi.getFiles(),
       'local',
     )
-    const response = await invoke<SaveFileResponse>('save_excalidraw_file', {
-      request: {
-        path: excalidrawPath,
-        name: excalidrawName.trim() || undefined,
-        contents: serialized,
-      },
-    })
-    const nextName = excalidrawNameRef.current.trim()
+    const acknowledgeDowngrade = excalidrawPath !== null && newerSceneVersionPathRef.current === excalidrawPath
+    if (
+      acknowledgeDowngrade &&
+      !window.confirm(
+        'This file was saved by a newer version of Excalidraw. Saving will rewrite it in an older format and may drop data. The current version is kept in local history. Save anyway?',
+      )
+    ) {
+      return false
+    }
+    // Save As leaves the original alone, so it doesn't matter if that can't be written.
+    if (!saveAs && !(await confirmWritable(excalidrawPath, setExcalidrawMessage))) {
+      return false
+    }
+    // A scene opened from an `.excalidraw.png` is saved back as the image, with the
+    // scene embedded in it by the backend.
+    const savePath = targetPath ?? excalidrawPath
+    const pngBase64 =
+      savePath !== null && /\.png$/i.test(savePath)
+        ? await blobToDataUrl(
+            await exportToBlob({
+              elements: excalidrawApi.getSceneElements(),
+              appState: { ...excalidrawApi.getAppState(), exportBackground: true },
+              files: excalidrawApi.getFiles(),
+              mimeType: 'image/png',
+            }),
+          )
+        : undefined
+    const response =
+      excalidrawPath !== null && disconnectedPathsRef.current.has(excalidrawPath)
+        ? await invoke<SaveFileResponse>('save_elsewhere', {
+            kind: 'excalidraw',
+            contents: serialized,
+            name: excalidrawName.trim() || undefined,
+            previousPath: excalidrawPath,
+          })
+        : await saveDocument('save_excalidraw_file', {
+            path: savePath,
+            name: excalidrawName.trim() || undefined,
+            contents: serialized,
+            acknowledge_downgrade: acknowledgeDowngrade,
+            png_base64: pngBase64,
+            always_prompt: saveAs,
+          })
+    if (!response) {
+      return false
+    }
+    newerSceneVersionPathRef.current = null
+    const nextName = saveAs && response.name ? response.name : excalidrawNameRef.current.trim()
     const snapshot = {
       contents: serialized,
       hasContent,
@@ -635,18 +1198,71 @@ function App() {
       setCurrentExcalidrawAutosave(null)
     }
     setExcalidrawPersistedState(snapshot, response.path, nextName)
-    setExcalidrawMessage(`Saved to ${response.path}.`)
+    setExcalidrawMessage(savedMessage(response))
     refreshRecents()
+    return true
   }, [
+    confirmWritable,
     excalidrawApi,
     excalidrawName,
     excalidrawPath,
     refreshRecents,
+    remotePaths,
+    saveDocument,
     setCurrentExcalidrawAutosave,
     setExcalidrawDocument,
     setExcalidrawPersistedState,
   ])
 
+  // Renames the open file on disk. A name with folders in it moves the file instead.
+  const handleRenameDocument = useCallback(
+    async (kind: 'excalidraw' | 'mermaid') => {
+      const path = kind === 'excalidraw' ? excalidrawPath : mermaidPath
+      const setMessage = kind === 'excalidraw' ? setExcalidrawMessage : setMermaidMessage
+      if (!path) {
+        setMessage('Save the document before renaming it.')
+        return
+      }
+      const currentName = path.split(/[\\/]/).pop() ?? ''
+      const newName = window.prompt('Rename to:', currentName)
+      if (!newName || newName.trim() === currentName) {
+        return
+      }
+      try {
+        let renamed: RenamedFile
+        try {
+          renamed = await invoke<RenamedFile>('rename_file', { oldPath: path, newName })
+        } catch (error) {
+          if ((error as { code?: string })?.code !== 'file_exists') {
+            throw error
+          }
+          if (!window.confirm(`${(error as { path: string }).path} already exists. Replace it?`)) {
+            return
+          }
+          renamed = await invoke<RenamedFile>('rename_file', { oldPath: path, newName, overwrite: true })
+        }
+        if (kind === 'excalidraw') {
+          if (excalidrawPersistedRef.current) {
+            excalidrawPersistedRef.current = { ...excalidrawPersistedRef.current, path: renamed.path, name: renamed.name }
+          }
+          excalidrawNameRef.current = renamed.name
+          setExcalidrawPath(renamed.path)
+          setExcalidrawName(renamed.name)
+        } else {
+          mermaidPersistedRef.current = { ...mermaidPersistedRef.current, path: renamed.path, name: renamed.name }
+          setMermaidPath(renamed.path)
+          setMermaidName(renamed.name)
+        }
+        setMessage(`Renamed to ${renamed.path}`)
+        refreshRecents()
+      } catch (error) {
+        console.error('[excalibur] rename_file FAILED', error)
+        setMessage((error as { message?: string })?.message ?? String(error))
+      }
+    },
+    [excalidrawPath, mermaidPath, refreshRecents],
+  )
+
   const handleNewExcalidraw = useCallback(() => {
     if (!excalidrawApi) {
       return
@@ -692,42 +1308,417 @@ function App() {
     })
   }, [applyExcalidrawContents, recoverableAutosave])
 
-  const loadExcalidrawPath = useCallback(
+  const applyMermaidFile = useCallback((file: OpenFileResponse) => {
+    const response = chooseMermaidBlock(file)
+    if (!response) {
+      setMermaidMessage(`Did not open ${file.path}.`)
+      return
+    }
+    const nextName = response.name?.replace(/\.[^/.]+$/, '') ?? ''
+    trackOpenedFile(response)
+    setMermaidPath(response.path)
+    setMermaidName(nextName)
+    setMermaidBlockRange(response.block_range ?? null)
+    dispatchMermaid({ type: 'reset', text: response.contents })
+    setMermaidPersistedState(response.contents, nextName, response.path)
+    setMermaidMessage(loadedMessage(response))
+    setTab('mermaid')
+    refreshRecents()
+  }, [refreshRecents, setMermaidPersistedState, trackOpenedFile])
+
+  // Opens a path of either kind; the backend detects which from the extension or contents.
+  const openPath = useCallback(
     async (path: string) => {
-      if (!confirmExcalidrawAction('load another document')) {
+      try {
+        const response = await resolveAlreadyOpen(
+          () => invoke<OpenPathResponse>('open_path', { path }),
+          (path) => invoke<OpenPathResponse>('open_path', { path, allowDuplicate: true }),
+        )
+        if (!response) {
+          return
+        }
+        if (response.kind === 'mermaid') {
+          if (confirmMermaidAction('load another document')) {
+            applyMermaidFile(response)
+          }
+        } else if (confirmExcalidrawAction('load another document')) {
+          applyExcalidrawFile(response)
+        }
+      } catch (error) {
+        console.error('[excalibur] openPath: invoke FAILED', error)
+      }
+    },
+    [applyExcalidrawFile, applyMermaidFile, confirmExcalidrawAction, confirmMermaidAction, resolveAlreadyOpen],
+  )
+
+  // Fetches a document shared as a URL, e.g. a raw GitHub or Gist link.
+  const openUrl = useCallback(
+    async (kind: 'excalidraw' | 'mermaid', url: string) => {
+      const setMessage = kind === 'excalidraw' ? setExcalidrawMessage : setMermaidMessage
+      const confirmAction = kind === 'excalidraw' ? confirmExcalidrawAction : confirmMermaidAction
+      if (!confirmAction('load another document')) {
         return
       }
-      console.log('[excalibur] loadExcalidrawPath: invoking load_excalidraw_path for', path)
       try {
-        const response = await invoke<OpenFileResponse>('load_excalidraw_path', { path })
-        console.log('[excalibur] loadExcalidrawPath: invoke returned', {
-          path: response.path,
-          contentLength: response.contents?.length ?? 0,
-        })
-        applyExcalidrawFile(response)
+        const response = await invoke<OpenFileResponse>(
+          kind === 'excalidraw' ? 'load_excalidraw_url' : 'load_mermaid_url',
+          { url },
+        )
+        if (kind === 'excalidraw') {
+          applyExcalidrawFile(response)
+        } else {
+          applyMermaidFile(response)
+        }
+      } catch (error) {
+        console.error('[excalibur] openUrl FAILED', error)
+        setMessage((error as { message?: string })?.message ?? String(error))
+      }
+    },
+    [applyExcalidrawFile, applyMermaidFile, confirmExcalidrawAction, confirmMermaidAction],
+  )
+
+  const handleOpenUrl = useCallback(
+    (kind: 'excalidraw' | 'mermaid') => {
+      const url = window.prompt('Open from URL (https):')?.trim()
+      if (url) {
+        openUrl(kind, url)
+      }
+    },
+    [openUrl],
+  )
+
+  // Opens a recent file as whatever kind it is now: it may have been renamed (say
+  // from .json to .excalidraw) since it was listed.
+  const openRecent = useCallback(
+    async (item: RecentItem, openReadOnly = false) => {
+      try {
+        if (item.kind === 'library') {
+          // Libraries are merged into the open scene's library rather than opened.
+          const library = await invoke<OpenFileResponse>('load_excalidraw_library_path', { path: item.path })
+          await excalidrawApi?.updateLibrary({
+            libraryItems: JSON.parse(library.contents).libraryItems,
+            merge: true,
+            openLibraryMenu: true,
+          })
+          setExcalidrawMessage(`Loaded library ${library.name ?? library.path}`)
+          return
+        }
+        if (/^https?:\/\//i.test(item.path)) {
+          await openUrl(item.kind, item.path)
+          return
+        }
+        const response = await resolveAlreadyOpen(
+          () => invoke<OpenPathResponse>('open_recent', { kind: item.kind, path: item.path, openReadOnly }),
+          (path) =>
+            invoke<OpenPathResponse>('open_recent', { kind: item.kind, path, openReadOnly, allowDuplicate: true }),
+        )
+        if (!response) {
+          return
+        }
+        if (response.kind === 'mermaid') {
+          if (confirmMermaidAction('load another document')) {
+            applyMermaidFile(response)
+          }
+        } else if (confirmExcalidrawAction('load another document')) {
+          applyExcalidrawFile(response)
+        }
       } catch (error) {
-        console.error('[excalibur] loadExcalidrawPath: invoke FAILED', error)
+        console.error('[excalibur] openRecent: invoke FAILED', error)
+        const message = (error as { message?: string })?.message ?? String(error)
+        setExcalidrawMessage(message)
+        setMermaidMessage(message)
       }
     },
-    [applyExcalidrawFile, confirmExcalidrawAction],
+    [
+      applyExcalidrawFile,
+      applyMermaidFile,
+      confirmExcalidrawAction,
+      confirmMermaidAction,
+      excalidrawApi,
+      openUrl,
+      resolveAlreadyOpen,
+    ],
   )
 
-  // Listen for open-file events from the backend (file association / deep-link)
+  // Copies the saved file to a "copy" next to it and switches the editor to the copy.
+  const handleDuplicateDocument = useCallback(
+    async (kind: 'excalidraw' | 'mermaid') => {
+      const path = kind === 'excalidraw' ? excalidrawPath : mermaidPath
+      const confirmAction = kind === 'excalidraw' ? confirmExcalidrawAction : confirmMermaidAction
+      if (!path || !confirmAction('switch to a copy')) {
+        return
+      }
+      try {
+        const response = await invoke<OpenPathResponse>('duplicate_file', { path })
+        if (response.kind === 'mermaid') {
+          applyMermaidFile(response)
+          setMermaidMessage(`Duplicated to ${response.path}`)
+        } else {
+          applyExcalidrawFile(response)
+          setExcalidrawMessage(`Duplicated to ${response.path}`)
+        }
+        refreshRecents()
+      } catch (error) {
+        console.error('[excalibur] duplicate_file FAILED', error)
+        const message = (error as { message?: string })?.message ?? String(error)
+        if (kind === 'excalidraw') {
+          setExcalidrawMessage(message)
+        } else {
+          setMermaidMessage(message)
+        }
+      }
+    },
+    [
+      applyExcalidrawFile,
+      applyMermaidFile,
+      confirmExcalidrawAction,
+      confirmMermaidAction,
+      excalidrawPath,
+      mermaidPath,
+      refreshRecents,
+    ],
+  )
+
+  // Reload the recents when one's kind was corrected after a rename, or when a listed
+  // file changed on disk (e.g. regenerated by a script) so its preview is rebuilt.
+  useEffect(() => {
+    const refresh = () => {
+      refreshRecents().catch((error) => console.error('[excalibur] refreshRecents FAILED', error))
+    }
+    const listeners = Promise.all([
+      listen('recents-changed', refresh),
+      listen('recent-item-stale', refresh),
+    ])
+    invoke<boolean>('start_recents_watch').catch((error) =>
+      console.error('[excalibur] start_recents_watch FAILED', error),
+    )
+    return () => {
+      listeners.then((unlisteners) => unlisteners.forEach((unlisten) => unlisten()))
+      invoke('stop_recents_watch').catch(() => {})
+    }
+  }, [refreshRecents])
+
+  // Watch the open documents so edits made in other programs (say the same .mmd open
+  // in another editor) are noticed before a save overwrites them.
+  useEffect(() => {
+    const paths = [...new Set([excalidrawPath, mermaidPath])].filter(
+      (path): path is string => path !== null,
+    )
+    const notify = (path: string, message: string) => {
+      if (path === excalidrawPath) {
+        setExcalidrawMessage(message)
+      }
+      if (path === mermaidPath) {
+        setMermaidMessage(message)
+      }
+    }
+    const listeners = Promise.all([
+      listen<{ path: string; modified_at: number | null }>('file-changed-externally', (event) =>
+        notify(
+          event.payload.path,
+          `${event.payload.path} was changed by another program. Reopen it to pick up those changes; saving now overwrites them.`,
+        ),
+      ),
+      listen<{ path: string }>('file-removed', (event) =>
+        notify(
+          event.payload.path,
+          `${event.payload.path} was deleted or moved by another program. Saving writes it again.`,
+        ),
+      ),
+      listen<{ path: string; window: string }>('document-saved-in-other-window', (event) => {
+        if (event.payload.window !== getCurrentWindow().label) {
+          notify(
+            event.payload.path,
+            `${event.payload.path} was saved from another window. Reopen it to pick up those changes; saving here overwrites them.`,
+          )
+        }
+      }),
+    ])
+    paths.forEach((path) =>
+      invoke('watch_file', { path }).catch((error) =>
+        console.error('[excalibur] watch_file FAILED', error),
+      ),
+    )
+    return () => {
+      listeners.then((unlisteners) => unlisteners.forEach((unlisten) => unlisten()))
+      paths.forEach((path) => invoke('unwatch_file', { path }).catch(() => {}))
+    }
+  }, [excalidrawPath, mermaidPath])
+
+  // The backend keeps the autosave timer, since webview timers are throttled while the
+  // window is in the background. Remote, read-only and PNG documents aren't autosaved.
   useEffect(() => {
-    const unlisten = listen<string>('open-file', (event) => {
-      console.log('[excalibur] open-file event received:', event.payload)
+    const windowLabel = getCurrentWindow().label
+    const path = excalidrawPath
+    if (
+      !fileAutosave ||
+      path === null ||
+      remotePaths.includes(path) ||
+      readOnlyPaths.includes(path) ||
+      /\.png$/i.test(path)
+    ) {
+      invoke('disable_autosave', { windowLabel }).catch((error) =>
+        console.error('[excalibur] disable_autosave FAILED', error),
+      )
+      return
+    }
+    invoke('enable_autosave', { windowLabel, path, intervalSecs: FILE_AUTOSAVE_INTERVAL_SECS }).catch(
+      (error) => setExcalidrawMessage((error as { message?: string })?.message ?? String(error)),
+    )
+  }, [excalidrawPath, fileAutosave, readOnlyPaths, remotePaths])
+
+  const autosaveRequestRef = useRef<(path: string) => void>(() => {})
+
+  useEffect(() => {
+    // Answers `request-autosave` with the scene; the backend skips contents it already wrote.
+    autosaveRequestRef.current = async (path) => {
+      if (!excalidrawApi || path !== excalidrawPathRef.current) {
+        return
+      }
+      const snapshot = {
+        contents: serializeAsJSON(
+          excalidrawApi.getSceneElements(),
+          excalidrawApi.getAppState(),
+          excalidrawApi.getFiles(),
+          'local',
+        ),
+        hasContent: excalidrawApi.getSceneElements().some((element) => !element.isDeleted),
+      }
+      try {
+        const response = await invoke<{ written: boolean; mtime: number | null }>('autosave_contents', {
+          windowLabel: getCurrentWindow().label,
+          contents: snapshot.contents,
+        })
+        if (response.written && path === excalidrawPathRef.current) {
+          documentMtimesRef.current.set(path, response.mtime)
+          excalidrawSceneSnapshotRef.current = snapshot
+          setExcalidrawPersistedState(snapshot, path, excalidrawNameRef.current)
+        }
+      } catch (error) {
+        console.error('[excalibur] autosave_contents FAILED', error)
+      }
+    }
+  }, [excalidrawApi, setExcalidrawPersistedState])
+
+  useEffect(() => {
+    const listeners = Promise.all([
+      listen<{ path: string }>('request-autosave', (event) =>
+        autosaveRequestRef.current(event.payload.path),
+      ),
+      listen<{ path: string; message: string }>('autosave-failed', (event) => {
+        if (event.payload.path === excalidrawPathRef.current) {
+          setExcalidrawMessage(`Autosave failed: ${event.payload.message}`)
+        }
+      }),
+    ])
+    return () => {
+      listeners.then((unlisteners) => unlisteners.forEach((unlisten) => unlisten()))
+    }
+  }, [])
+
+  // Handlers for backend events, kept in refs so the listeners below are registered
+  // only once and never miss an event while being re-attached.
+  const openFileEventRef = useRef<(paths: string[]) => void>(() => {})
+  const newDocumentEventRef = useRef<(document: NewDocumentEvent) => void>(() => {})
+  const dialogUnavailableEventRef = useRef<(request: DialogUnavailableEvent) => void>(() => {})
+
+  useEffect(() => {
+    // Files are opened one after another, in the order the OS delivered them.
+    openFileEventRef.current = (paths) => {
+      console.log('[excalibur] open-file event received:', paths)
       if (excalidrawApi) {
-        loadExcalidrawPath(event.payload)
+        void paths.reduce((previous, path) => previous.then(() => openPath(path)), Promise.resolve())
+      } else {
+        pendingOpenFiles.current.push(...paths)
+      }
+    }
+  }, [excalidrawApi, openPath])
+
+  useEffect(() => {
+    // Content piped in with `excalibur --stdin` or sent by an `excalibur://new` link
+    // arrives as an unsaved document.
+    newDocumentEventRef.current = ({ kind, title, contents }) => {
+      const message = title ? `Opened "${title}".` : 'Opened a new document.'
+      if (kind === 'mermaid') {
+        setMermaidPath(null)
+        setMermaidName(title ?? '')
+        setMermaidBlockRange(null)
+        dispatchMermaid({ type: 'reset', text: contents })
+        setMermaidMessage(message)
+        setTab('mermaid')
       } else {
-        pendingOpenFile.current = event.payload
+        applyExcalidrawContents({
+          contents,
+          path: null,
+          name: title ?? '',
+          message,
+          markDocumentClean: false,
+        })
+      }
+    }
+  }, [applyExcalidrawContents])
+
+  // Listen for open-file (file association / deep-link / drag-drop / workspaces),
+  // new-document, deep-link-rejected, open-file-error, volume and operation-report events,
+  // then tell the backend to flush what it queued before now.
+  useEffect(() => {
+    let isActive = true
+    const listeners = Promise.all([
+      listen<string>('open-file', (event) => openFileEventRef.current([event.payload])),
+      listen<string[]>('open-files', (event) => openFileEventRef.current(event.payload)),
+      listen<NewDocumentEvent>('new-document', (event) =>
+        newDocumentEventRef.current(event.payload),
+      ),
+      listen<DialogUnavailableEvent>('dialog-unavailable', (event) =>
+        dialogUnavailableEventRef.current(event.payload),
+      ),
+      listen<{ url: string; reason: string }>('deep-link-rejected', (event) => {
+        console.warn('[excalibur] deep link rejected:', event.payload)
+        const message = `Could not open ${event.payload.url}: ${event.payload.reason}`
+        setExcalidrawMessage(message)
+        setMermaidMessage(message)
+      }),
+      // A file named on the command line that doesn't exist.
+      listen<{ argument: string; path: string; reason: string }>('open-file-error', (event) => {
+        console.warn('[excalibur] launch argument not opened:', event.payload)
+        const message = `Could not open ${event.payload.argument}: ${event.payload.reason}`
+        setExcalidrawMessage(message)
+        setMermaidMessage(message)
+      }),
+      listen<{ path: string; volume: string }>('volume-disconnected', (event) => {
+        disconnectedPathsRef.current.add(event.payload.path)
+        const message = `The drive holding ${event.payload.path} was disconnected. Save to choose a new location, or reconnect the drive.`
+        setExcalidrawMessage(message)
+        setMermaidMessage(message)
+      }),
+      listen<{ path: string; volume: string }>('volume-reconnected', (event) => {
+        disconnectedPathsRef.current.delete(event.payload.path)
+        const message = `${event.payload.path} is available again.`
+        setExcalidrawMessage(message)
+        setMermaidMessage(message)
+      }),
+      // Sent on focus after a background operation posted a system notification.
+      listen<{ operation_id: string; kind: string; summary: string; details: unknown }>(
+        'operation-report',
+        (event) => {
+          console.info('[excalibur] operation report:', event.payload)
+          setExcalidrawMessage(event.payload.summary)
+          setMermaidMessage(event.payload.summary)
+        },
+      ),
+    ])
+    listeners.then(() => {
+      if (isActive) {
+        invoke('frontend_ready')
       }
     })
     return () => {
-      unlisten.then((fn) => fn())
+      isActive = false
+      listeners.then((unlisteners) => unlisteners.forEach((unlisten) => unlisten()))
     }
-  }, [excalidrawApi, loadExcalidrawPath])
+  }, [])
 
-  // When excalidrawApi becomes available, load any pending file (from event or startup)
+  // When excalidrawApi becomes available, load any file from an event that arrived before it
   useEffect(() => {
     if (!excalidrawApi) return
 
@@ -735,22 +1726,12 @@ function App() {
       return
     }
 
-    // Check for a file path queued from an event that arrived before the API was ready
-    if (pendingOpenFile.current) {
-      const path = pendingOpenFile.current
-      pendingOpenFile.current = null
-      loadExcalidrawPath(path)
-      return
+    if (pendingOpenFiles.current.length > 0) {
+      const paths = pendingOpenFiles.current
+      pendingOpenFiles.current = []
+      openFileEventRef.current(paths)
     }
-
-    // Check for a file path stored by the backend at startup (e.g. double-click in Finder)
-    invoke<string | null>('take_pending_file').then((path) => {
-      if (path) {
-        console.log('[excalibur] take_pending_file returned:', path)
-        loadExcalidrawPath(path)
-      }
-    })
-  }, [excalidrawApi, flushPendingExcalidrawContents, loadExcalidrawPath])
+  }, [excalidrawApi, flushPendingExcalidrawContents, openPath])
 
   useEffect(() => {
     flushPendingExcalidrawContents()
@@ -760,50 +1741,177 @@ function App() {
     if (!confirmMermaidAction('load another document')) {
       return
     }
-    const response = await invoke<OpenFileResponse | null>('open_mermaid_file')
+    const response = await resolveAlreadyOpen(
+      () => invoke<OpenFileResponse | null>('open_mermaid_file'),
+      (path) => invoke<OpenFileResponse>('load_mermaid_path', { path, allowDuplicate: true }),
+    )
     if (!response) {
       return
     }
-    const nextName = response.name?.replace(/\.[^/.]+$/, '') ?? ''
-    setMermaidPath(response.path)
-    setMermaidName(nextName)
-    dispatchMermaid({ type: 'reset', text: response.contents })
-    setMermaidPersistedState(response.contents, nextName, response.path)
-    setMermaidMessage(`Loaded ${response.path}.`)
-    setTab('mermaid')
-    refreshRecents()
-  }, [confirmMermaidAction, refreshRecents, setMermaidPersistedState])
-
-  const handleSaveMermaid = useCallback(async () => {
-    const nextName = mermaidName.trim()
-    const response = await invoke<SaveFileResponse>('save_mermaid_file', {
-      request: {
-        path: mermaidPath,
-        name: nextName || undefined,
-        contents: mermaidText,
-      },
-    })
-    setMermaidPath(response.path)
-    setMermaidName(nextName)
-    setMermaidPersistedState(mermaidText, nextName, response.path)
-    setMermaidMessage(`Saved to ${response.path}.`)
-    refreshRecents()
-  }, [mermaidName, mermaidPath, mermaidText, refreshRecents, setMermaidPersistedState])
+    applyMermaidFile(response)
+  }, [applyMermaidFile, confirmMermaidAction, resolveAlreadyOpen])
 
-  const loadMermaidPath = useCallback(async (path: string) => {
+  // Opens every selected file: the first goes into the editor, the rest are added to
+  // recents.
+  const handleOpenMermaidFiles = useCallback(async () => {
     if (!confirmMermaidAction('load another document')) {
       return
     }
-    const response = await invoke<OpenFileResponse>('load_mermaid_path', { path })
-    const nextName = response.name?.replace(/\.[^/.]+$/, '') ?? ''
+    const { files, errors } = await invoke<OpenFilesResponse>('open_mermaid_files')
+    const first = files[0] && chooseMermaidBlock(files[0])
+    if (first) {
+      const nextName = first.name?.replace(/\.[^/.]+$/, '') ?? ''
+      trackOpenedFile(first)
+      setMermaidPath(first.path)
+      setMermaidName(nextName)
+      setMermaidBlockRange(first.block_range ?? null)
+      dispatchMermaid({ type: 'reset', text: first.contents })
+      setMermaidPersistedState(first.contents, nextName, first.path)
+      setTab('mermaid')
+    }
+    const failed = errors.map(({ path, error }) => `${path}: ${error.message}`)
+    if (files.length > 0 || failed.length > 0) {
+      setMermaidMessage(
+        [
+          first ? loadedMessage(first) : '',
+          files.length > 1 ? `${files.length - 1} more added to recents.` : '',
+          ...failed,
+        ]
+          .filter(Boolean)
+          .join('\n'),
+      )
+    }
+    refreshRecents()
+  }, [confirmMermaidAction, refreshRecents, setMermaidPersistedState, trackOpenedFile])
+
+  const handleSaveMermaid = useCallback(async (targetPath?: string, saveAs = false) => {
+    saveAs ||= mermaidPath !== null && remotePaths.includes(mermaidPath)
+    if (!saveAs && !(await confirmWritable(mermaidPath, setMermaidMessage))) {
+      return false
+    }
+    const response =
+      mermaidPath !== null && disconnectedPathsRef.current.has(mermaidPath)
+        ? await invoke<SaveFileResponse>('save_elsewhere', {
+            kind: 'mermaid',
+            contents: mermaidText,
+            name: mermaidName.trim() || undefined,
+            previousPath: mermaidPath,
+          })
+        : await saveDocument('save_mermaid_file', {
+            path: targetPath ?? mermaidPath,
+            name: mermaidName.trim() || undefined,
+            contents: mermaidText,
+            block_range: (targetPath ?? mermaidPath) === mermaidPath ? mermaidBlockRange ?? undefined : undefined,
+            always_prompt: saveAs,
+          })
+    if (!response) {
+      return false
+    }
+    const nextName = saveAs && response.name ? response.name : mermaidName.trim()
+    if (response.path !== mermaidPath) {
+      setMermaidBlockRange(null)
+    }
     setMermaidPath(response.path)
     setMermaidName(nextName)
-    dispatchMermaid({ type: 'reset', text: response.contents })
-    setMermaidPersistedState(response.contents, nextName, response.path)
-    setMermaidMessage(`Loaded ${response.path}.`)
-    setTab('mermaid')
+    setMermaidPersistedState(mermaidText, nextName, response.path)
+    setMermaidMessage(savedMessage(response))
     refreshRecents()
-  }, [confirmMermaidAction, refreshRecents, setMermaidPersistedState])
+    return true
+  }, [
+    confirmWritable,
+    mermaidBlockRange,
+    mermaidName,
+    mermaidPath,
+    mermaidText,
+    refreshRecents,
+    remotePaths,
+    saveDocument,
+    setMermaidPersistedState,
+  ])
+
+  // The backend asks before closing a window with unsaved changes, so keep it told.
+  useEffect(() => {
+    const names = [
+      hasUnsavedExcalidrawChanges ? excalidrawName.trim() || 'Untitled Excalidraw' : '',
+      hasUnsavedMermaidChanges ? mermaidName.trim() || 'Untitled Mermaid' : '',
+    ].filter(Boolean)
+    invoke('set_window_dirty', {
+      windowLabel: getCurrentWindow().label,
+      dirty: names.length > 0,
+      documentName: names.length > 0 ? names.join(' and ') : undefined,
+    }).catch((error) => console.warn('[excalibur] set_window_dirty failed:', error))
+  }, [excalidrawName, hasUnsavedExcalidrawChanges, hasUnsavedMermaidChanges, mermaidName])
+
+  // Title the window after the document in the active tab. A saved file goes by its
+  // file name; an unsaved one by its name, until there is nothing in it to lose.
+  useEffect(() => {
+    const path = tab === 'excalidraw' ? excalidrawPath : mermaidPath
+    const dirty = tab === 'excalidraw' ? hasUnsavedExcalidrawChanges : hasUnsavedMermaidChanges
+    const name = tab === 'excalidraw'
+      ? excalidrawName.trim() || 'Untitled Excalidraw'
+      : mermaidName.trim() || 'Untitled Mermaid'
+    invoke('set_window_title', {
+      name: !path && dirty ? name : undefined,
+      path: path ?? undefined,
+      dirty,
+    }).catch((error) => console.warn('[excalibur] set_window_title failed:', error))
+  }, [
+    excalidrawName,
+    excalidrawPath,
+    hasUnsavedExcalidrawChanges,
+    hasUnsavedMermaidChanges,
+    mermaidName,
+    mermaidPath,
+    tab,
+  ])
+
+  // "Save" in the close prompt: save what is unsaved, then let the window close. A
+  // failed or cancelled save keeps it open.
+  const saveBeforeCloseRef = useRef<() => Promise<void>>(async () => {})
+  useEffect(() => {
+    saveBeforeCloseRef.current = async () => {
+      const excalidrawSaved = !hasUnsavedExcalidrawChanges || (await handleSaveExcalidraw())
+      const mermaidSaved = excalidrawSaved && (!hasUnsavedMermaidChanges || (await handleSaveMermaid()))
+      if (mermaidSaved) {
+        await invoke('close_confirmed', { windowLabel: getCurrentWindow().label })
+      }
+    }
+  }, [handleSaveExcalidraw, handleSaveMermaid, hasUnsavedExcalidrawChanges, hasUnsavedMermaidChanges])
+
+  useEffect(() => {
+    const unlisten = listen('save-before-close', () => {
+      saveBeforeCloseRef.current().catch((error) => console.warn('[excalibur] save before close failed:', error))
+    })
+    return () => {
+      unlisten.then((fn) => fn())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Without a working file dialog the backend asks for the path here instead, and
+    // the open or save is repeated with the typed path.
+    dialogUnavailableEventRef.current = (request) => {
+      const { action, filter_name, extensions, suggested_name, reason } = request
+      console.warn('[excalibur] file dialog unavailable:', reason)
+      const types = extensions.map((extension) => `.${extension}`).join(', ')
+      const path = window
+        .prompt(
+          `The file dialog is unavailable (${reason}).\n${action === 'save' ? 'Save to' : 'Open'} which ${filter_name} file (${types})?`,
+          suggested_name ?? '',
+        )
+        ?.trim()
+      if (!path) {
+        return
+      }
+      if (action === 'open') {
+        openPath(path)
+      } else if (filter_name === 'Mermaid') {
+        handleSaveMermaid(path)
+      } else if (filter_name === 'Excalidraw') {
+        handleSaveExcalidraw(path)
+      }
+    }
+  }, [handleSaveExcalidraw, handleSaveMermaid, openPath])
 
   const handleConvertMermaidToExcalidraw = useCallback(async () => {
     const cleanedText = mermaidText.replace(/^\uFEFF/, '').trim()
@@ -893,6 +2001,41 @@ function App() {
     [mermaidHistory.future, mermaidHistory.past, mermaidName, mermaidPath, updateMermaidDirtyState],
   )
 
+  // Moves a recent file to the system trash. Without one, deleting it for good needs
+  // confirming first.
+  const trashRecent = useCallback(async (item: RecentItem) => {
+    const name = item.name || item.display_path
+    if (!window.confirm(`Move ${name} to the trash?`)) {
+      return
+    }
+    try {
+      let response: TrashFileResponse
+      try {
+        response = await invoke<TrashFileResponse>('trash_file', { path: item.path })
+      } catch (error) {
+        if ((error as { code?: string })?.code !== 'trash_unavailable') {
+          throw error
+        }
+        if (!window.confirm(`${(error as { message: string }).message}. Delete ${name} permanently?`)) {
+          return
+        }
+        response = await invoke<TrashFileResponse>('trash_file', {
+          path: item.path,
+          allowPermanentDelete: true,
+        })
+      }
+      setRecents(response.recents)
+    } catch (error) {
+      console.error('[excalibur] trash_file FAILED', error)
+      const message = (error as { message?: string })?.message ?? String(error)
+      if (item.kind === 'excalidraw') {
+        setExcalidrawMessage(message)
+      } else {
+        setMermaidMessage(message)
+      }
+    }
+  }, [])
+
   const recentList = useMemo(() => {
     if (!recents.length) {
       return <div className="empty">No recent charts yet.</div>
@@ -900,24 +2043,151 @@ function App() {
     return recents.map((item) => (
       <button
         key={`${item.kind}-${item.path}`}
-        className="recent-item"
-        onClick={() => {
-          if (item.kind === 'excalidraw') {
-            loadExcalidrawPath(item.path)
-          } else {
-            loadMermaidPath(item.path)
-          }
+        className={item.exists || /^https?:\/\//i.test(item.path) ? 'recent-item' : 'recent-item missing'}
+        title={item.exists ? 'Right-click to open read-only' : 'This file no longer exists or its drive is disconnected'}
+        onClick={() => openRecent(item)}
+        onContextMenu={(event) => {
+          event.preventDefault()
+          openRecent(item, true)
         }}
       >
-        <span className="recent-type">{item.kind}</span>
+        <span className="recent-type">
+          {item.kind}
+          <span
+            role="button"
+            className={item.pinned ? 'recent-pin pinned' : 'recent-pin'}
+            title={item.pinned ? 'Unpin' : 'Pin so it stays in recents'}
+            onClick={(event) => {
+              event.stopPropagation()
+              invoke(item.pinned ? 'unpin_recent' : 'pin_recent', {
+                kind: item.kind,
+                path: item.path,
+              }).catch((error) => console.error('[excalibur] pin_recent FAILED', error))
+            }}
+          >
+            {item.pinned ? '★' : '☆'}
+          </span>
+          <span
+            role="button"
+            className="recent-remove"
+            title="Remove from recents"
+            onClick={(event) => {
+              event.stopPropagation()
+              invoke<RecentItem[]>('remove_recent', { kind: item.kind, path: item.path })
+                .then(setRecents)
+                .catch((error) => console.error('[excalibur] remove_recent FAILED', error))
+            }}
+          >
+            ×
+          </span>
+          {item.exists && item.kind !== 'library' ? (
+            <span
+              role="button"
+              className="recent-remove"
+              title="Open in a new window"
+              onClick={(event) => {
+                event.stopPropagation()
+                invoke('open_in_new_window', { kind: item.kind, path: item.path }).catch((error) =>
+                  console.error('[excalibur] open_in_new_window FAILED', error),
+                )
+              }}
+            >
+              ⧉
+            </span>
+          ) : null}
+          {item.exists ? (
+            <span
+              role="button"
+              className="recent-remove"
+              title="Move to Trash"
+              onClick={(event) => {
+                event.stopPropagation()
+                trashRecent(item)
+              }}
+            >
+              🗑
+            </span>
+          ) : null}
+        </span>
         <span className="recent-name">{item.name || item.path}</span>
-        <span className="recent-path">{item.path}</span>
+        {item.preview ? <span className="recent-preview">{item.preview}</span> : null}
+        <span className="recent-path" title={item.path}>
+          {item.display_path}
+        </span>
       </button>
     ))
-  }, [recents, loadExcalidrawPath, loadMermaidPath])
+  }, [recents, openRecent, trashRecent])
 
   return (
     <div className="app-shell">
+      {privacyLock ? (
+        <div className="privacy-lock">
+          <form
+            className="privacy-lock-panel"
+            onSubmit={(event) => {
+              event.preventDefault()
+              handlePrivacyUnlock()
+            }}
+          >
+            <div className="privacy-lock-title">Excalibur is locked</div>
+            {privacyLock.passwordRequired ? (
+              <input
+                type="password"
+                aria-label="Password"
+                placeholder="Password of an open encrypted document"
+                value={privacyPassword}
+                onChange={(event) => setPrivacyPassword(event.target.value)}
+                autoFocus
+              />
+            ) : null}
+            {privacyError ? <div className="privacy-lock-error">{privacyError}</div> : null}
+            <button type="submit">Unlock</button>
+          </form>
+        </div>
+      ) : null}
+      {passwordPrompts.length > 0 && !privacyLock ? (
+        <div className="password-prompt">
+          <form
+            className="password-prompt-panel"
+            onSubmit={(event) => {
+              event.preventDefault()
+              answerPasswordPrompt(promptPassword)
+            }}
+            onKeyDown={(event) => {
+              if (event.key === 'Escape') {
+                answerPasswordPrompt(null)
+              }
+            }}
+          >
+            <div className="password-prompt-title">
+              {passwordPrompts[0].purpose === 'save'
+                ? 'Choose a password to encrypt'
+                : 'Enter the password for'}
+            </div>
+            <div className="password-prompt-path" title={passwordPrompts[0].path}>
+              {passwordPrompts[0].path}
+            </div>
+            <input
+              type="password"
+              aria-label="Password"
+              value={promptPassword}
+              onChange={(event) => setPromptPassword(event.target.value)}
+              autoFocus
+            />
+            {passwordPrompts[0].error === 'wrong_password' ? (
+              <div className="password-prompt-error">Wrong password. Try again.</div>
+            ) : null}
+            <div className="password-prompt-actions">
+              <button type="button" onClick={() => answerPasswordPrompt(null)}>
+                Cancel
+              </button>
+              <button type="submit" disabled={!promptPassword}>
+                {passwordPrompts[0].purpose === 'save' ? 'Encrypt' : 'Open'}
+              </button>
+            </div>
+          </form>
+        </div>
+      ) : null}
       <aside className="sidebar">
         <div className="brand">
           <div className="brand-title">Excalibur</div>
@@ -938,9 +2208,47 @@ function App() {
           </button>
         </div>
         <div className="recents">
-          <div className="section-title">Recent (last 10)</div>
+          <div className="section-title">
+            Recent (last 10)
+            {recents.some((item) => !item.exists) ? (
+              <button
+                className="recent-clear"
+                title="Remove files that no longer exist. Files on a disconnected drive are removed too."
+                onClick={() => {
+                  invoke<RecentItem[]>('prune_recents')
+                    .then(setRecents)
+                    .catch((error) => console.error('[excalibur] prune_recents FAILED', error))
+                }}
+              >
+                Remove missing
+              </button>
+            ) : null}
+            {recents.length ? (
+              <button
+                className="recent-clear"
+                onClick={() => {
+                  if (window.confirm('Remove every file from recents, pinned ones included?')) {
+                    invoke<RecentItem[]>('clear_recents', { kind: null })
+                      .then(setRecents)
+                      .catch((error) => console.error('[excalibur] clear_recents FAILED', error))
+                  }
+                }}
+              >
+                Clear
+              </button>
+            ) : null}
+          </div>
           <div className="recent-list">{recentList}</div>
         </div>
+        <div className="sidebar-footer">
+          <button
+            className="diagnostic-logs"
+            title="Copy the latest lines of the log file, to attach to a bug report"
+            onClick={handleCopyLogs}
+          >
+            Copy diagnostic logs
+          </button>
+        </div>
       </aside>
 
       <main className="workspace">
@@ -953,6 +2261,16 @@ function App() {
               </div>
               <div className="status">
                 {excalidrawMessage}
+                {lastExportPath && excalidrawMessage === `Exported to ${lastExportPath}` ? (
+                  <span className="status-links">
+                    <button type="button" onClick={() => handleRevealPath(lastExportPath)}>
+                      Show in Folder
+                    </button>
+                    <button type="button" onClick={() => handleCopyPath(lastExportPath)}>
+                      Copy Path
+                    </button>
+                  </span>
+                ) : null}
                 {recoverableAutosave ? <span className="status-note">Autosave backup available.</span> : null}
               </div>
             </header>
@@ -984,13 +2302,41 @@ function App() {
                   readOnly
                   placeholder="No file loaded"
                 />
+                {excalidrawPath && readOnlyPaths.includes(excalidrawPath) ? (
+                  <span className="read-only-badge">Read-only</span>
+                ) : null}
               </label>
               <div className="actions">
-                <button className="primary" onClick={handleSaveExcalidraw}>
+                <button
+                  className="primary"
+                  onClick={() => handleSaveExcalidraw()}
+                  disabled={excalidrawPath !== null && remotePaths.includes(excalidrawPath)}
+                >
                   Save
                 </button>
+                <button onClick={() => handleSaveExcalidraw(undefined, true)}>Save As…</button>
                 <button onClick={handleOpenExcalidraw}>Open</button>
+                <button onClick={() => handleOpenUrl('excalidraw')}>Open URL…</button>
                 <button onClick={handleNewExcalidraw}>New</button>
+                <button onClick={handleImportDrawio}>Import draw.io</button>
+                <button onClick={handleExportExcalidrawPng}>Export PNG</button>
+                <button onClick={() => handleRenameDocument('excalidraw')} disabled={!excalidrawPath}>
+                  Rename…
+                </button>
+                <button onClick={() => handleDuplicateDocument('excalidraw')} disabled={!excalidrawPath}>
+                  Duplicate
+                </button>
+                <label className="autosave-toggle">
+                  <input
+                    type="checkbox"
+                    checked={fileAutosave}
+                    onChange={(event) => {
+                      window.localStorage.setItem(FILE_AUTOSAVE_KEY, event.target.checked ? 'on' : 'off')
+                      setFileAutosave(event.target.checked)
+                    }}
+                  />
+                  Autosave to file
+                </label>
                 {recoverableAutosave ? (
                   <button className="recover" onClick={handleRecoverExcalidraw}>
                     Recover backup
@@ -1027,12 +2373,28 @@ function App() {
               <label>
                 File
                 <input value={mermaidPath ?? ''} readOnly placeholder="No file loaded" />
+                {mermaidPath && readOnlyPaths.includes(mermaidPath) ? (
+                  <span className="read-only-badge">Read-only</span>
+                ) : null}
               </label>
               <div className="actions">
-                <button className="primary" onClick={handleSaveMermaid}>
+                <button
+                  className="primary"
+                  onClick={() => handleSaveMermaid()}
+                  disabled={mermaidPath !== null && remotePaths.includes(mermaidPath)}
+                >
                   Save
                 </button>
+                <button onClick={() => handleSaveMermaid(undefined, true)}>Save As…</button>
                 <button onClick={handleOpenMermaid}>Open</button>
+                <button onClick={() => handleOpenUrl('mermaid')}>Open URL…</button>
+                <button onClick={handleOpenMermaidFiles}>Open several…</button>
+                <button onClick={() => handleRenameDocument('mermaid')} disabled={!mermaidPath}>
+                  Rename…
+                </button>
+                <button onClick={() => handleDuplicateDocument('mermaid')} disabled={!mermaidPath}>
+                  Duplicate
+                </button>
                 <button onClick={handleConvertMermaidToExcalidraw} disabled={isConvertingMermaid}>
                   {isConvertingMermaid ? 'Saving...' : 'Convert & Save Excalidraw'}
                 </button>
@@ -82,7 +82,17 @@ test.beforeEach(async ({ page }) => {
               contents,
             }
           }
-          case 'take_pending_file':
+          case 'start_recents_watch':
+            return true
+          case 'stop_recents_watch':
+          case 'watch_file':
+          case 'unwatch_file':
+            return null
+          case 'privacy_status':
+            return { enabled: false, locked: false, password_required: false }
+          case 'frontend_ready':
+          case 'render_ready':
+          case 'report_activity':
             return null
           default:
             throw new Error(`Unhandled Tauri invoke: ${cmd}`)
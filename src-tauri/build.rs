@@ -1,3 +1,22 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+  let commit = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+  let build_timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or_default();
+
+  println!("cargo:rustc-env=EXCALIBUR_GIT_COMMIT={commit}");
+  println!("cargo:rustc-env=EXCALIBUR_BUILD_TIMESTAMP={build_timestamp}");
+  println!("cargo:rerun-if-changed=../.git/HEAD");
+
   tauri_build::build()
 }
@@ -0,0 +1,393 @@
+use crate::settings::{load_settings, Settings};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tauri::{AppHandle, Emitter};
+
+/// Modifiers accepted in a keybinding, in the spelling Tauri accelerators use.
+const MODIFIERS: [&str; 7] = [
+    "CmdOrCtrl",
+    "CommandOrControl",
+    "Cmd",
+    "Command",
+    "Ctrl",
+    "Alt",
+    "Shift",
+];
+
+/// What an action's availability check can look at.
+pub struct ActionContext<'a> {
+    pub settings: &'a Settings,
+    pub has_document: bool,
+    pub has_unlocked_encrypted: bool,
+}
+
+/// One entry of the action registry. Keybinding validation and the frontend's
+/// command palette are built from `ACTIONS`; native menus should be too.
+pub struct ActionDef {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub category: &'static str,
+    pub default_keybinding: Option<&'static str>,
+    pub needs_document: bool,
+    /// Returns why the action is unavailable right now, if it is.
+    pub unavailable: fn(&ActionContext) -> Option<&'static str>,
+}
+
+fn always_available(_: &ActionContext) -> Option<&'static str> {
+    None
+}
+
+pub const ACTIONS: &[ActionDef] = &[
+    ActionDef {
+        id: "file.new-excalidraw",
+        title: "New Excalidraw Diagram",
+        category: "File",
+        default_keybinding: Some("CmdOrCtrl+N"),
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.new-mermaid",
+        title: "New Mermaid Diagram",
+        category: "File",
+        default_keybinding: Some("CmdOrCtrl+Shift+N"),
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.open",
+        title: "Open…",
+        category: "File",
+        default_keybinding: Some("CmdOrCtrl+O"),
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.save",
+        title: "Save",
+        category: "File",
+        default_keybinding: Some("CmdOrCtrl+S"),
+        needs_document: true,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.save-as",
+        title: "Save As…",
+        category: "File",
+        default_keybinding: Some("CmdOrCtrl+Shift+S"),
+        needs_document: true,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.save-encrypted",
+        title: "Save Encrypted Copy…",
+        category: "File",
+        default_keybinding: None,
+        needs_document: true,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.undo-save",
+        title: "Undo Last Save",
+        category: "File",
+        default_keybinding: None,
+        needs_document: true,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.redo-save",
+        title: "Redo Last Save",
+        category: "File",
+        default_keybinding: None,
+        needs_document: true,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "file.lock-encrypted",
+        title: "Lock Encrypted Documents",
+        category: "File",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: |context| {
+            (!context.has_unlocked_encrypted).then_some("No encrypted documents are unlocked")
+        },
+    },
+    ActionDef {
+        id: "search.files",
+        title: "Search Files…",
+        category: "Search",
+        default_keybinding: Some("CmdOrCtrl+Shift+F"),
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "search.replace",
+        title: "Replace in Files…",
+        category: "Search",
+        default_keybinding: Some("CmdOrCtrl+Shift+H"),
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "workspace.save",
+        title: "Save Workspace…",
+        category: "Workspace",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "workspace.open",
+        title: "Open Workspace…",
+        category: "Workspace",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "data.backup-now",
+        title: "Back Up Data Now",
+        category: "Data",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "data.export",
+        title: "Export App Data…",
+        category: "Data",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "data.import",
+        title: "Import App Data…",
+        category: "Data",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+    ActionDef {
+        id: "app.check-updates",
+        title: "Check for Updates",
+        category: "Help",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: |context| {
+            context
+                .settings
+                .update_manifest_url
+                .trim()
+                .is_empty()
+                .then_some("No update server is configured")
+        },
+    },
+    ActionDef {
+        id: "app.about",
+        title: "About Excalibur",
+        category: "Help",
+        default_keybinding: None,
+        needs_document: false,
+        unavailable: always_available,
+    },
+];
+
+#[derive(Serialize)]
+pub struct ActionDescriptor {
+    id: &'static str,
+    title: &'static str,
+    category: &'static str,
+    keybinding: Option<String>,
+    enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disabled_reason: Option<&'static str>,
+    needs_document: bool,
+}
+
+pub fn find_action(id: &str) -> Option<&'static ActionDef> {
+    ACTIONS.iter().find(|action| action.id == id)
+}
+
+/// The keybinding in effect for `action`: the user's override (empty = unbound) or
+/// the default.
+pub fn keybinding(settings: &Settings, action: &ActionDef) -> Option<String> {
+    match settings.keybindings.get(action.id) {
+        Some(binding) if binding.is_empty() => None,
+        Some(binding) => Some(binding.clone()),
+        None => action.default_keybinding.map(str::to_string),
+    }
+}
+
+/// Normalises an accelerator so `shift+cmdorctrl+s` and `CmdOrCtrl+Shift+S` compare equal.
+fn normalize_accelerator(binding: &str) -> Result<String, String> {
+    let parts: Vec<&str> = binding.split('+').map(str::trim).collect();
+    let (key, modifiers) = parts
+        .split_last()
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("\"{binding}\" has no key"))?;
+    if MODIFIERS
+        .iter()
+        .any(|modifier| modifier.eq_ignore_ascii_case(key))
+    {
+        return Err(format!("\"{binding}\" has no key after its modifiers"));
+    }
+    let mut normalized: Vec<String> = modifiers
+        .iter()
+        .map(|modifier| {
+            MODIFIERS
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(modifier))
+                .map(|known| match *known {
+                    "CommandOrControl" => "CmdOrCtrl".to_string(),
+                    "Command" => "Cmd".to_string(),
+                    known => known.to_string(),
+                })
+                .ok_or_else(|| format!("\"{modifier}\" in \"{binding}\" is not a modifier"))
+        })
+        .collect::<Result<_, _>>()?;
+    normalized.sort();
+    normalized.dedup();
+    normalized.push(key.to_uppercase());
+    Ok(normalized.join("+"))
+}
+
+/// Checks keybinding overrides: every id must be a registered action, every
+/// binding a modifier list plus one key, and no two actions may share a binding.
+pub fn validate_keybindings(overrides: &BTreeMap<String, String>) -> Result<(), String> {
+    let settings = Settings {
+        keybindings: overrides.clone(),
+        ..Settings::default()
+    };
+    for id in overrides.keys() {
+        if find_action(id).is_none() {
+            return Err(format!("Unknown action \"{id}\""));
+        }
+    }
+    let mut bound: HashMap<String, &str> = HashMap::new();
+    for action in ACTIONS {
+        let Some(binding) = keybinding(&settings, action) else {
+            continue;
+        };
+        let normalized = normalize_accelerator(&binding)?;
+        if let Some(other) = bound.insert(normalized, action.id) {
+            return Err(format!(
+                "\"{binding}\" is bound to both \"{other}\" and \"{}\"",
+                action.id
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn describe_actions(context: &ActionContext) -> Vec<ActionDescriptor> {
+    ACTIONS
+        .iter()
+        .map(|action| {
+            let disabled_reason = if action.needs_document && !context.has_document {
+                Some("Open a document first")
+            } else {
+                (action.unavailable)(context)
+            };
+            ActionDescriptor {
+                id: action.id,
+                title: action.title,
+                category: action.category,
+                keybinding: keybinding(context.settings, action),
+                enabled: disabled_reason.is_none(),
+                disabled_reason,
+                needs_document: action.needs_document,
+            }
+        })
+        .collect()
+}
+
+/// Tells the frontend to fetch `list_actions` again.
+pub fn notify_changed(app: &AppHandle) {
+    let _ = app.emit("actions-changed", ());
+}
+
+/// The action manifest for the command palette. `has_document` is whether the
+/// calling window has a document open.
+#[tauri::command]
+pub fn list_actions(app: AppHandle, has_document: Option<bool>) -> Vec<ActionDescriptor> {
+    let settings = load_settings(&app);
+    describe_actions(&ActionContext {
+        settings: &settings,
+        has_document: has_document.unwrap_or(false),
+        has_unlocked_encrypted: crate::crypto::has_cached_keys(&app),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(id, binding)| (id.to_string(), binding.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn default_registry_is_consistent() {
+        let mut ids: Vec<&str> = ACTIONS.iter().map(|action| action.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), ACTIONS.len());
+        assert_eq!(validate_keybindings(&BTreeMap::new()), Ok(()));
+    }
+
+    #[test]
+    fn validates_keybinding_overrides() {
+        assert_eq!(
+            validate_keybindings(&overrides(&[("file.save-as", "Alt+Shift+S")])),
+            Ok(())
+        );
+        // Unbinding an action frees its default for another one.
+        assert_eq!(
+            validate_keybindings(&overrides(&[
+                ("file.save", ""),
+                ("file.undo-save", "cmdorctrl+s"),
+            ])),
+            Ok(())
+        );
+        assert!(validate_keybindings(&overrides(&[("file.print", "CmdOrCtrl+P")])).is_err());
+        assert!(validate_keybindings(&overrides(&[("file.save-as", "Shift+CmdOrCtrl+o")])).is_ok());
+        assert!(
+            validate_keybindings(&overrides(&[("file.save-as", "shift+cmdorctrl+N")])).is_err()
+        );
+        assert!(validate_keybindings(&overrides(&[("file.save-as", "CmdOrCtrl+")])).is_err());
+        assert!(validate_keybindings(&overrides(&[("file.save-as", "Hyper+S")])).is_err());
+        assert!(validate_keybindings(&overrides(&[("file.save-as", "Ctrl+Shift")])).is_err());
+    }
+
+    #[test]
+    fn describes_availability() {
+        let settings = Settings::default();
+        let actions = describe_actions(&ActionContext {
+            settings: &settings,
+            has_document: false,
+            has_unlocked_encrypted: false,
+        });
+        let save = actions
+            .iter()
+            .find(|action| action.id == "file.save")
+            .unwrap();
+        assert!(!save.enabled);
+        assert_eq!(save.disabled_reason, Some("Open a document first"));
+        assert_eq!(save.keybinding.as_deref(), Some("CmdOrCtrl+S"));
+        let lock = actions
+            .iter()
+            .find(|action| action.id == "file.lock-encrypted")
+            .unwrap();
+        assert!(!lock.enabled);
+        let open = actions
+            .iter()
+            .find(|action| action.id == "file.open")
+            .unwrap();
+        assert!(open.enabled);
+        assert_eq!(open.disabled_reason, None);
+    }
+}
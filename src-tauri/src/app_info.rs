@@ -0,0 +1,172 @@
+use crate::app_data_dir;
+use crate::dialogs::DialogSupport;
+use crate::metrics::CommandSummary;
+use crate::paths::DataSource;
+use crate::trash::TRASH_DIR;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// How deep the data directory walk goes when totalling disk usage.
+const MAX_USAGE_DEPTH: usize = 8;
+
+#[derive(Serialize)]
+pub struct DataPaths {
+    app_data: String,
+    logs: String,
+    drafts: String,
+    history: String,
+    templates: String,
+    trash: String,
+}
+
+#[derive(Serialize)]
+pub struct AppInfo {
+    version: String,
+    git_commit: String,
+    build_timestamp: u64,
+    tauri_version: String,
+    webview_version: Option<String>,
+    os: String,
+    arch: String,
+    portable: bool,
+    data_dir_source: DataSource,
+    paths: DataPaths,
+    data_dir_bytes: u64,
+    /// Part of `data_dir_bytes` held by the trash.
+    trash_bytes: u64,
+    /// Whether native file dialogs work, e.g. not on Linux without a display server.
+    dialogs: DialogSupport,
+    /// Per-command timings and error rates, empty when collection is off.
+    command_metrics: Vec<CommandSummary>,
+}
+
+/// Total size of the files under `path`, following at most `depth` directory levels.
+pub fn directory_size(path: &Path, depth: usize) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && depth > 0 => {
+                directory_size(&entry.path(), depth - 1)
+            }
+            Ok(file_type) if file_type.is_file() => {
+                entry.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Collects version, platform and data-location details for the About dialog and
+/// diagnostics bundle.
+pub fn collect_app_info(app: &AppHandle) -> AppInfo {
+    let data_dir = app_data_dir(app);
+    let path_string = |name: &str| data_dir.join(name).to_string_lossy().to_string();
+
+    AppInfo {
+        version: app.package_info().version.to_string(),
+        git_commit: env!("EXCALIBUR_GIT_COMMIT").to_string(),
+        build_timestamp: env!("EXCALIBUR_BUILD_TIMESTAMP")
+            .parse()
+            .unwrap_or_default(),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().ok(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        portable: crate::paths::is_portable(app),
+        data_dir_source: crate::paths::data_source(app),
+        paths: DataPaths {
+            app_data: data_dir.to_string_lossy().to_string(),
+            logs: path_string("logs"),
+            drafts: path_string("drafts"),
+            history: path_string("history"),
+            templates: path_string("templates"),
+            trash: path_string(TRASH_DIR),
+        },
+        data_dir_bytes: directory_size(&data_dir, MAX_USAGE_DEPTH),
+        trash_bytes: directory_size(&data_dir.join(TRASH_DIR), MAX_USAGE_DEPTH),
+        dialogs: crate::dialogs::support(app),
+        command_metrics: crate::metrics::summaries(app),
+    }
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle) -> AppInfo {
+    collect_app_info(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_keys(value: &serde_json::Value) -> Vec<String> {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn app_info_serializes_every_field() {
+        let info = AppInfo {
+            version: "0.1.0".to_string(),
+            git_commit: "abc1234".to_string(),
+            build_timestamp: 1_700_000_000,
+            tauri_version: "2.0.0".to_string(),
+            webview_version: None,
+            os: "macos".to_string(),
+            arch: "aarch64".to_string(),
+            portable: false,
+            data_dir_source: DataSource::Default,
+            paths: DataPaths {
+                app_data: "/data".to_string(),
+                logs: "/data/logs".to_string(),
+                drafts: "/data/drafts".to_string(),
+                history: "/data/history".to_string(),
+                templates: "/data/templates".to_string(),
+                trash: "/data/trash".to_string(),
+            },
+            data_dir_bytes: 42,
+            trash_bytes: 7,
+            dialogs: DialogSupport::default(),
+            command_metrics: Vec::new(),
+        };
+
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(
+            sorted_keys(&value),
+            [
+                "arch",
+                "build_timestamp",
+                "command_metrics",
+                "data_dir_bytes",
+                "data_dir_source",
+                "dialogs",
+                "git_commit",
+                "os",
+                "paths",
+                "portable",
+                "tauri_version",
+                "trash_bytes",
+                "version",
+                "webview_version",
+            ]
+        );
+        assert_eq!(
+            sorted_keys(&value["paths"]),
+            [
+                "app_data",
+                "drafts",
+                "history",
+                "logs",
+                "templates",
+                "trash"
+            ]
+        );
+        assert_eq!(value["webview_version"], serde_json::Value::Null);
+        assert_eq!(value["data_dir_source"], "default");
+    }
+}
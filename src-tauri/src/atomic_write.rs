@@ -0,0 +1,146 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Where the new contents of `path` are staged: the same folder, so moving them into
+/// place is a rename on one device, e.g. `drawing.excalidraw.tmp-1234`.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".tmp-{}", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Renames `temp` over `target`. Where that is refused (some network and FUSE file
+/// systems can't replace a file by renaming) the contents are copied over instead.
+fn move_into_place(temp: &Path, target: &Path) -> io::Result<()> {
+    if let Err(error) = fs::rename(temp, target) {
+        log::warn!(
+            "write_atomic: rename onto {} failed ({}), copying instead",
+            target.display(),
+            error
+        );
+        fs::copy(temp, target)?;
+        fs::remove_file(temp)?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to `path` so that a crash, a full disk or a failed write leaves
+/// either the previous file or the complete new one, never a truncated mix. The
+/// contents go to a temporary file next to `path`, are flushed to disk, and are then
+/// renamed over it. Symlinks are written through, and the file keeps its permissions;
+/// a read-only file is refused like a plain write would be.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    write_staged(path, contents, move_into_place)
+}
+
+fn write_staged(
+    path: &Path,
+    contents: &[u8],
+    commit: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let target = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let existing = fs::metadata(&target).ok();
+    if existing
+        .as_ref()
+        .is_some_and(|metadata| metadata.permissions().readonly())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is read-only", target.display()),
+        ));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp = temp_path(&target);
+    let staged = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents)?;
+        if let Some(metadata) = &existing {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    });
+    if let Err(error) = staged {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+    // On failure the staged file is kept: if a fallback copy was cut off, it is the
+    // only complete copy of the new contents.
+    commit(&temp, &target).inspect_err(|error| {
+        log::warn!(
+            "write_atomic: failed to replace {}: {}; new contents kept in {}",
+            target.display(),
+            error,
+            temp.display()
+        );
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn replaces_the_file_and_cleans_up() {
+        let dir = temp_dir("atomic", "replace");
+        let path = dir.join("nested/drawing.excalidraw");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!temp_path(&path).exists());
+    }
+
+    #[test]
+    fn a_failed_replace_leaves_the_original_untouched() {
+        let dir = temp_dir("atomic", "fail");
+        let path = dir.join("drawing.excalidraw");
+        fs::write(&path, "original").unwrap();
+
+        let result = write_staged(&path, b"half-finished", |_, _| {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(
+            fs::read_to_string(temp_path(&path)).unwrap(),
+            "half-finished"
+        );
+    }
+
+    #[test]
+    fn read_only_files_are_refused() {
+        let dir = temp_dir("atomic", "read-only");
+        let path = dir.join("reference.mmd");
+        fs::write(&path, "graph TD").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let error = write_atomic(&path, b"graph LR").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "graph TD");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_written_through() {
+        let dir = temp_dir("atomic", "symlink");
+        let real = dir.join("real.mmd");
+        let link = dir.join("link.mmd");
+        fs::write(&real, "graph TD").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        write_atomic(&link, b"graph LR").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "graph LR");
+    }
+}
@@ -0,0 +1,386 @@
+use crate::error::CommandError;
+use crate::{
+    check_conflict, checksums, content_hash, crypto, file_watch, gzip, keep_backup, local_history,
+    modified_millis, paths, read_only, scene_schema, volumes, write_binary_file, write_file,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Shortest interval accepted; anything shorter would rewrite files constantly.
+const MIN_INTERVAL_SECS: u64 = 5;
+
+/// Documents saved in the background, by the label of the window that has them open.
+/// The timers run here rather than in the webview, whose timers are throttled while
+/// the window is in the background.
+#[derive(Default)]
+pub struct Autosaves(Mutex<Schedules<JoinHandle<()>>>);
+
+/// The timer of a schedule, stopped when the schedule is replaced or dropped.
+trait Task {
+    fn stop(&self);
+}
+
+impl Task for JoinHandle<()> {
+    fn stop(&self) {
+        self.abort();
+    }
+}
+
+struct Schedule<T> {
+    path: PathBuf,
+    interval: Duration,
+    task: T,
+    /// Hash of the contents last written; the same contents aren't written again.
+    last_hash: Option<String>,
+    /// The file's mtime after it was last written here or saved by hand. Any other
+    /// mtime means it was changed elsewhere, and autosave won't overwrite that.
+    last_mtime: Option<u64>,
+}
+
+struct Schedules<T> {
+    windows: HashMap<String, Schedule<T>>,
+}
+
+impl<T> Default for Schedules<T> {
+    fn default() -> Self {
+        Schedules {
+            windows: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Task> Schedules<T> {
+    /// Autosaves `path` for window `label` every `interval`, replacing what the window
+    /// had. `start` is only called when the schedule changes, so enabling the same one
+    /// again keeps the timer running. Returns whether a timer was started.
+    fn enable(
+        &mut self,
+        label: &str,
+        path: &Path,
+        interval: Duration,
+        current_mtime: Option<u64>,
+        start: impl FnOnce() -> T,
+    ) -> bool {
+        if self
+            .windows
+            .get(label)
+            .is_some_and(|schedule| schedule.path == path && schedule.interval == interval)
+        {
+            return false;
+        }
+        let previous = self.disable(label);
+        // The same document at another interval still knows what it last wrote.
+        let (last_hash, last_mtime) = match previous.filter(|previous| previous.path == path) {
+            Some(previous) => (previous.last_hash, previous.last_mtime),
+            None => (None, current_mtime),
+        };
+        self.windows.insert(
+            label.to_string(),
+            Schedule {
+                path: path.to_path_buf(),
+                interval,
+                task: start(),
+                last_hash,
+                last_mtime,
+            },
+        );
+        true
+    }
+
+    /// Stops the timer of window `label` and returns its schedule, if it had one.
+    fn disable(&mut self, label: &str) -> Option<Schedule<T>> {
+        let schedule = self.windows.remove(label)?;
+        schedule.task.stop();
+        Some(schedule)
+    }
+
+    /// What window `label` should write for contents hashing to `hash`: its path and
+    /// the mtime the file should still have. `None` when autosave is off for the
+    /// window or the contents were already written.
+    fn pending_write(&self, label: &str, hash: &str) -> Option<(PathBuf, Option<u64>)> {
+        let schedule = self.windows.get(label)?;
+        if schedule.last_hash.as_deref() == Some(hash) {
+            return None;
+        }
+        Some((schedule.path.clone(), schedule.last_mtime))
+    }
+
+    /// Records that `path` now holds contents hashing to `hash`, for every window
+    /// autosaving it.
+    fn written(&mut self, path: &Path, hash: &str, mtime: Option<u64>) {
+        for schedule in self.windows.values_mut() {
+            if schedule.path == path {
+                schedule.last_hash = Some(hash.to_string());
+                schedule.last_mtime = mtime;
+            }
+        }
+    }
+}
+
+fn autosaves(app: &AppHandle) -> MutexGuard<'_, Schedules<JoinHandle<()>>> {
+    app.state::<Autosaves>().inner().0.lock().unwrap()
+}
+
+#[derive(Serialize, Clone)]
+struct AutosaveRequest {
+    path: String,
+}
+
+#[derive(Serialize, Clone)]
+struct AutosaveFailed {
+    path: String,
+    code: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct AutosaveResponse {
+    written: bool,
+    /// The file's mtime after the write, for the next save's conflict check.
+    mtime: Option<u64>,
+}
+
+/// Emits `request-autosave` to window `label` every `interval` until aborted.
+fn start_requests(app: &AppHandle, label: &str, path: &Path, interval: Duration) -> JoinHandle<()> {
+    let app = app.clone();
+    let label = label.to_string();
+    let request = AutosaveRequest {
+        path: path.to_string_lossy().to_string(),
+    };
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let _ = app.emit_to(label.as_str(), "request-autosave", request.clone());
+        }
+    })
+}
+
+/// Writes `contents` to `path` like a save without a dialog, with the same checks:
+/// refused when the scene doesn't validate, the file is read-only, on a disconnected
+/// volume, changed since `expected_mtime` or written by a newer editor, which only a
+/// save by hand can acknowledge. The replaced contents get a backup and a local
+/// history version. Gzipped files stay gzipped. Returns the new mtime.
+fn write(
+    app: &AppHandle,
+    path: &Path,
+    expected_mtime: Option<u64>,
+    contents: &str,
+) -> Result<Option<u64>, CommandError> {
+    scene_schema::check_save(contents)?;
+    if volumes::is_disconnected(app, path) {
+        return Err(volumes::disconnected_error(path));
+    }
+    read_only::ensure_writable(app, path)?;
+    check_conflict(path, expected_mtime, false)?;
+    scene_schema::check_overwrite(path, false)?;
+    keep_backup(path, None)?;
+    let capture = local_history::capture_before_save(app, path);
+    let written = if gzip::has_gz_suffix(path) || gzip::is_gzip_file(path) {
+        gzip::compress(contents.as_bytes())
+            .map_err(|error| CommandError::from_io(error, path))
+            .and_then(|bytes| {
+                file_watch::expect_write(app, path, &bytes);
+                write_binary_file(path, &bytes)
+            })
+    } else {
+        file_watch::expect_write(app, path, contents.as_bytes());
+        write_file(path, contents)
+    };
+    written.map_err(|error| {
+        if volumes::check_failure(app, path) {
+            volumes::disconnected_error(path)
+        } else {
+            error
+        }
+    })?;
+    local_history::finish_save(app, path, capture);
+    checksums::after_save(app, path);
+    Ok(modified_millis(path))
+}
+
+/// Saves the document at `path` in window `window_label` every `interval_secs`: the
+/// window is sent `request-autosave` and answers with `autosave_contents`. Calling
+/// it again with another path or interval reschedules the window's autosave.
+/// Encrypted documents are refused, since saving them may ask for the password, and
+/// so are scenes embedded in PNGs.
+#[tauri::command]
+pub fn enable_autosave(
+    app: AppHandle,
+    window_label: String,
+    path: String,
+    interval_secs: u64,
+) -> Result<(), CommandError> {
+    if interval_secs < MIN_INTERVAL_SECS {
+        return Err(CommandError::InvalidArgument {
+            message: format!("Autosave needs an interval of at least {MIN_INTERVAL_SECS} seconds"),
+        });
+    }
+    let path = paths::normalize(Path::new(&path));
+    if crypto::is_encrypted_path(&path) {
+        return Err(CommandError::InvalidArgument {
+            message: format!(
+                "{} is encrypted; save it by hand, since saving may ask for its password",
+                path.display()
+            ),
+        });
+    }
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+    {
+        return Err(CommandError::InvalidArgument {
+            message: format!(
+                "{} is a PNG image; saving it needs the rendered image",
+                path.display()
+            ),
+        });
+    }
+    let interval = Duration::from_secs(interval_secs);
+    let started = autosaves(&app).enable(
+        &window_label,
+        &path,
+        interval,
+        modified_millis(&path),
+        || start_requests(&app, &window_label, &path, interval),
+    );
+    if started {
+        log::info!(
+            "enable_autosave: {} every {}s in {}",
+            path.display(),
+            interval_secs,
+            window_label
+        );
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn disable_autosave(app: AppHandle, window_label: String) {
+    if let Some(schedule) = autosaves(&app).disable(&window_label) {
+        log::info!(
+            "disable_autosave: {} in {}",
+            schedule.path.display(),
+            window_label
+        );
+    }
+}
+
+/// The answer to `request-autosave`: writes `contents` unless they were written
+/// already. A failed write is logged and emitted to the window as `autosave-failed`
+/// rather than returned, so the frontend never shows a dialog for it.
+#[tauri::command]
+pub async fn autosave_contents(
+    app: AppHandle,
+    window_label: String,
+    contents: String,
+) -> AutosaveResponse {
+    let hash = content_hash(contents.as_bytes());
+    let Some((path, expected_mtime)) = autosaves(&app).pending_write(&window_label, &hash) else {
+        return AutosaveResponse {
+            written: false,
+            mtime: None,
+        };
+    };
+    match write(&app, &path, expected_mtime, &contents) {
+        Ok(mtime) => {
+            autosaves(&app).written(&path, &hash, mtime);
+            log::info!(
+                "autosave_contents: wrote {} bytes to {}",
+                contents.len(),
+                path.display()
+            );
+            AutosaveResponse {
+                written: true,
+                mtime,
+            }
+        }
+        Err(error) => {
+            log::warn!(
+                "autosave_contents: failed for {}: {}",
+                path.display(),
+                error
+            );
+            let _ = app.emit_to(
+                window_label.as_str(),
+                "autosave-failed",
+                AutosaveFailed {
+                    path: path.to_string_lossy().to_string(),
+                    code: error.code(),
+                    message: error.to_string(),
+                },
+            );
+            AutosaveResponse {
+                written: false,
+                mtime: None,
+            }
+        }
+    }
+}
+
+/// Called after `path` was saved by hand with `contents`, so autosaving it goes on
+/// from that save instead of taking it for a change made elsewhere.
+pub fn after_save(app: &AppHandle, path: &Path, contents: &str) {
+    let path = paths::normalize(path);
+    let hash = content_hash(contents.as_bytes());
+    autosaves(app).written(&path, &hash, modified_millis(&path));
+}
+
+/// Stops autosaving for a closed window.
+pub fn forget_window(app: &AppHandle, label: &str) {
+    autosaves(app).disable(label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct FakeTask(Rc<Cell<u32>>);
+
+    impl Task for FakeTask {
+        fn stop(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn rescheduling_replaces_the_timer_and_keeps_what_was_written() {
+        let stopped = Rc::new(Cell::new(0));
+        let task = || FakeTask(stopped.clone());
+        let mut schedules = Schedules::default();
+        let path = Path::new("/work/flow.excalidraw");
+        let every = Duration::from_secs;
+
+        assert!(schedules.enable("main", path, every(30), Some(1), task));
+        assert!(!schedules.enable("main", path, every(30), Some(1), task));
+        assert_eq!(stopped.get(), 0);
+
+        schedules.written(path, "abc", Some(2));
+        assert!(schedules.enable("main", path, every(10), Some(9), task));
+        assert_eq!(stopped.get(), 1);
+        assert_eq!(schedules.windows.len(), 1);
+        assert_eq!(schedules.pending_write("main", "abc"), None);
+        assert_eq!(
+            schedules.pending_write("main", "def"),
+            Some((path.to_path_buf(), Some(2)))
+        );
+
+        // Another document starts from what is on disk now.
+        let other = Path::new("/work/other.excalidraw");
+        assert!(schedules.enable("main", other, every(10), Some(5), task));
+        assert_eq!(
+            schedules.pending_write("main", "abc"),
+            Some((other.to_path_buf(), Some(5)))
+        );
+
+        assert!(schedules.disable("main").is_some());
+        assert_eq!(stopped.get(), 3);
+        assert_eq!(schedules.pending_write("main", "def"), None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::now_epoch;
+use crate::settings::load_settings;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// How much longer stretched tasks wait between runs while the app is idle.
+const IDLE_STRETCH_FACTOR: u32 = 6;
+/// How often paused tasks re-check the idle state without being woken, in case the
+/// setting changed.
+const PAUSED_RECHECK: Duration = Duration::from_secs(5 * 60);
+
+/// What a periodic task does while the app is idle.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdlePolicy {
+    /// Runs on schedule regardless, e.g. lock heartbeats other instances rely on.
+    Keep,
+    /// Waits `IDLE_STRETCH_FACTOR` times longer between runs.
+    Stretch,
+    /// Doesn't run at all until there is activity again.
+    Pause,
+}
+
+/// Periodic background work and whether the app is idle. Every loop that wakes up
+/// on a timer waits through `wait` instead of sleeping, so idle apps stay asleep.
+pub struct BackgroundTasks {
+    inner: Mutex<Inner>,
+    /// Signalled when the app stops being idle, so stretched and paused tasks resume.
+    wake: Condvar,
+}
+
+struct Inner {
+    tasks: BTreeMap<&'static str, TaskState>,
+    last_activity: Instant,
+    /// Labels of the windows that have focus.
+    focused: HashSet<String>,
+    idle_since: Option<u64>,
+}
+
+struct TaskState {
+    interval: Duration,
+    policy: IdlePolicy,
+    /// When the task last came due.
+    last_run_at: Option<u64>,
+}
+
+impl Default for BackgroundTasks {
+    fn default() -> Self {
+        BackgroundTasks {
+            inner: Mutex::new(Inner {
+                tasks: BTreeMap::new(),
+                last_activity: Instant::now(),
+                focused: HashSet::new(),
+                idle_since: None,
+            }),
+            wake: Condvar::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TaskStatus {
+    name: &'static str,
+    interval_secs: u64,
+    policy: IdlePolicy,
+    /// The wait between runs right now; `None` while paused.
+    current_interval_secs: Option<u64>,
+    suspended: bool,
+    last_run_at: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct BackgroundStatus {
+    idle: bool,
+    idle_since: Option<u64>,
+    tasks: Vec<TaskStatus>,
+}
+
+/// The wait between runs of a task, or `None` while it is paused.
+fn current_interval(interval: Duration, policy: IdlePolicy, idle: bool) -> Option<Duration> {
+    match (policy, idle) {
+        (IdlePolicy::Pause, true) => None,
+        (IdlePolicy::Stretch, true) => Some(interval * IDLE_STRETCH_FACTOR),
+        _ => Some(interval),
+    }
+}
+
+/// The app is idle once no window has focus and there has been no activity for
+/// `idle_after`; `None` never counts as idle.
+fn is_idle(inner: &Inner, idle_after: Option<Duration>) -> bool {
+    idle_after.is_some_and(|idle_after| {
+        inner.focused.is_empty() && inner.last_activity.elapsed() >= idle_after
+    })
+}
+
+fn idle_after(app: &AppHandle) -> Option<Duration> {
+    match load_settings(app).background_idle_minutes {
+        0 => None,
+        minutes => Some(Duration::from_secs(minutes * 60)),
+    }
+}
+
+impl BackgroundTasks {
+    /// Re-evaluates the idle state, logging and waking tasks when it changes.
+    fn refresh(&self, inner: &mut MutexGuard<Inner>, idle_after: Option<Duration>) {
+        let idle = is_idle(inner, idle_after);
+        if idle == inner.idle_since.is_some() {
+            return;
+        }
+        if idle {
+            inner.idle_since = Some(now_epoch());
+            log::debug!("background tasks: idle, stretching and pausing timers");
+        } else {
+            inner.idle_since = None;
+            log::debug!("background tasks: active again, resuming timers");
+            self.wake.notify_all();
+        }
+    }
+}
+
+/// Waits until the task `name` is next due: `interval` from now while the app is in
+/// use, longer or indefinitely while it is idle, as `policy` says. Activity cuts a
+/// stretched or paused wait short once the normal interval has passed.
+pub fn wait(app: &AppHandle, name: &'static str, interval: Duration, policy: IdlePolicy) {
+    let state = app.state::<BackgroundTasks>();
+    let started = Instant::now();
+    {
+        let mut inner = state.inner.lock().unwrap();
+        let task = inner.tasks.entry(name).or_insert(TaskState {
+            interval,
+            policy,
+            last_run_at: None,
+        });
+        task.interval = interval;
+        task.policy = policy;
+    }
+    loop {
+        let idle_after = idle_after(app);
+        let mut inner = state.inner.lock().unwrap();
+        state.refresh(&mut inner, idle_after);
+        let elapsed = started.elapsed();
+        let timeout = match current_interval(interval, policy, inner.idle_since.is_some()) {
+            Some(current) if elapsed >= current => {
+                if let Some(task) = inner.tasks.get_mut(name) {
+                    task.last_run_at = Some(now_epoch());
+                }
+                return;
+            }
+            Some(current) => current - elapsed,
+            None => PAUSED_RECHECK,
+        };
+        // While in use, wake when the app would turn idle to log the transition.
+        let timeout = match idle_after {
+            Some(idle_after) if inner.idle_since.is_none() && inner.focused.is_empty() => {
+                timeout.min(idle_after.saturating_sub(inner.last_activity.elapsed()))
+            }
+            _ => timeout,
+        }
+        .max(Duration::from_secs(1));
+        drop(state.wake.wait_timeout(inner, timeout).unwrap());
+    }
+}
+
+/// Records user activity; the frontend reports it through `report_activity`.
+pub fn record_activity(app: &AppHandle) {
+    let state = app.state::<BackgroundTasks>();
+    let mut inner = state.inner.lock().unwrap();
+    inner.last_activity = Instant::now();
+    // Whatever the setting, the app isn't idle right after activity.
+    state.refresh(&mut inner, None);
+}
+
+/// Tracks window focus from `WindowEvent::Focused`. Focusing any window ends idleness.
+pub fn set_focused(app: &AppHandle, label: &str, focused: bool) {
+    let state = app.state::<BackgroundTasks>();
+    let mut inner = state.inner.lock().unwrap();
+    if focused {
+        inner.focused.insert(label.to_string());
+        inner.last_activity = Instant::now();
+        state.refresh(&mut inner, None);
+    } else {
+        inner.focused.remove(label);
+    }
+}
+
+/// Every registered background task, its interval and whether it is held back by
+/// idleness right now, for the debug view.
+#[tauri::command]
+pub fn get_background_task_status(app: AppHandle) -> BackgroundStatus {
+    let idle_after = idle_after(&app);
+    let state = app.state::<BackgroundTasks>();
+    let mut inner = state.inner.lock().unwrap();
+    state.refresh(&mut inner, idle_after);
+    let idle = inner.idle_since.is_some();
+    BackgroundStatus {
+        idle,
+        idle_since: inner.idle_since,
+        tasks: inner
+            .tasks
+            .iter()
+            .map(|(name, task)| {
+                let current = current_interval(task.interval, task.policy, idle);
+                TaskStatus {
+                    name,
+                    interval_secs: task.interval.as_secs(),
+                    policy: task.policy,
+                    current_interval_secs: current.map(|current| current.as_secs()),
+                    suspended: current != Some(task.interval),
+                    last_run_at: task.last_run_at,
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inner(focused: &[&str], inactive_for: Duration) -> Inner {
+        Inner {
+            tasks: BTreeMap::new(),
+            last_activity: Instant::now() - inactive_for,
+            focused: focused.iter().map(|label| label.to_string()).collect(),
+            idle_since: None,
+        }
+    }
+
+    #[test]
+    fn idle_needs_no_focus_and_no_recent_activity() {
+        let five_minutes = Some(Duration::from_secs(300));
+        assert!(is_idle(&inner(&[], Duration::from_secs(301)), five_minutes));
+        assert!(!is_idle(
+            &inner(&["main"], Duration::from_secs(301)),
+            five_minutes
+        ));
+        assert!(!is_idle(&inner(&[], Duration::from_secs(10)), five_minutes));
+        assert!(!is_idle(&inner(&[], Duration::from_secs(301)), None));
+    }
+
+    #[test]
+    fn idle_stretches_or_pauses_by_policy() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(
+            current_interval(interval, IdlePolicy::Stretch, true),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(current_interval(interval, IdlePolicy::Pause, true), None);
+        assert_eq!(
+            current_interval(interval, IdlePolicy::Keep, true),
+            Some(interval)
+        );
+        assert_eq!(
+            current_interval(interval, IdlePolicy::Pause, false),
+            Some(interval)
+        );
+    }
+}
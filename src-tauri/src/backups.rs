@@ -0,0 +1,325 @@
+use crate::background::{wait, IdlePolicy};
+use crate::data_transfer::{
+    restore_archive_into, sibling_dir, write_data_archive, ImportResponse, CACHE_DIRS, LOGS_DIR,
+};
+use crate::error::CommandError;
+use crate::operations::Operation;
+use crate::recents_store::RecentsStore;
+use crate::settings::load_settings;
+use crate::{app_data_dir, now_epoch, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// How often the background task checks whether a backup is due.
+const SCHEDULE_TICK: Duration = Duration::from_secs(60 * 60);
+const BACKUP_PREFIX: &str = "excalibur-backup-";
+const THUMBNAILS_DIR: &str = "thumbnails";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct BackupStatus {
+    last_attempt_at: Option<u64>,
+    last_success_at: Option<u64>,
+    /// "ok", "skipped" (backup location unavailable), "cancelled" or "failed".
+    last_result: Option<String>,
+    message: Option<String>,
+    last_archive: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DataBackup {
+    path: String,
+    name: String,
+    size: u64,
+    created_at: u64,
+}
+
+fn status_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("backup-status.json")
+}
+
+fn load_status(app: &AppHandle) -> BackupStatus {
+    fs::read_to_string(status_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_status(app: &AppHandle, status: &BackupStatus) {
+    if let Ok(contents) = serde_json::to_string_pretty(status) {
+        let _ = write_file(&status_path(app), &contents);
+    }
+}
+
+/// The configured backup folder, or a sibling of the data directory so backups
+/// never end up inside what they back up.
+fn backup_dir(app: &AppHandle) -> PathBuf {
+    if let Some(location) = load_settings(app).backup_location {
+        return PathBuf::from(location);
+    }
+    sibling_dir(&app_data_dir(app), "backups")
+}
+
+fn list_archives(dir: &Path) -> Vec<DataBackup> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<DataBackup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(BACKUP_PREFIX) || !name.ends_with(".zip") {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some(DataBackup {
+                path: entry.path().to_string_lossy().to_string(),
+                name,
+                size: metadata.len(),
+                created_at: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    // Names embed the timestamp, so this is newest first.
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    backups
+}
+
+/// Writes one backup archive and prunes old ones. A backup location whose parent
+/// folder is missing (unplugged drive, dropped share) is skipped, not created.
+fn run_backup(
+    app: &AppHandle,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> BackupStatus {
+    let mut status = load_status(app);
+    let now = now_epoch();
+    status.last_attempt_at = Some(now);
+
+    let dir = backup_dir(app);
+    let reachable = dir.is_dir() || dir.parent().is_some_and(Path::is_dir);
+    if !reachable {
+        status.last_result = Some("skipped".to_string());
+        status.message = Some(format!("{} is not available", dir.display()));
+        save_status(app, &status);
+        return status;
+    }
+
+    let target = dir.join(format!(
+        "{BACKUP_PREFIX}{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let mut excluded: Vec<&str> = CACHE_DIRS.to_vec();
+    excluded.extend([LOGS_DIR, THUMBNAILS_DIR]);
+    let operation =
+        Operation::start(app, "backup", operation_id, true).notify_when_done(notify_when_done);
+    let outcome = write_data_archive(app, &target, &excluded, &operation);
+    operation.finish(
+        &outcome,
+        outcome.as_ref().map_or(0, |(files, _)| *files as u64),
+    );
+    match outcome {
+        Ok((files, _)) => {
+            log::info!("run_backup: wrote {} files to {}", files, target.display());
+            status.last_success_at = Some(now);
+            status.last_result = Some("ok".to_string());
+            status.message = None;
+            status.last_archive = Some(target.to_string_lossy().to_string());
+
+            let keep = load_settings(app).backup_keep.max(1);
+            for old in list_archives(&dir).into_iter().skip(keep) {
+                let _ = fs::remove_file(&old.path);
+            }
+        }
+        Err(CommandError::Cancelled) => {
+            status.last_result = Some("cancelled".to_string());
+            status.message = None;
+        }
+        Err(error) => {
+            log::warn!("run_backup: failed: {}", error);
+            status.last_result = Some("failed".to_string());
+            status.message = Some(error.to_string());
+        }
+    }
+    let summary = match status.last_result.as_deref() {
+        Some("ok") => "Backup finished".to_string(),
+        Some("cancelled") => "Backup was cancelled".to_string(),
+        _ => format!(
+            "Backup failed: {}",
+            status.message.as_deref().unwrap_or("unknown error")
+        ),
+    };
+    operation.notify_done(&summary, &status);
+    save_status(app, &status);
+    status
+}
+
+/// Background task that runs a backup whenever the configured frequency says one
+/// is due. Skipped or failed backups are retried on the next hourly tick.
+pub fn run_backup_schedule(app: &AppHandle) {
+    loop {
+        let interval = match load_settings(app).backup_frequency.as_str() {
+            "daily" => Some(24 * 60 * 60),
+            "weekly" => Some(7 * 24 * 60 * 60),
+            _ => None,
+        };
+        if let Some(interval) = interval {
+            let last_success = load_status(app).last_success_at.unwrap_or(0);
+            if now_epoch().saturating_sub(last_success) >= interval {
+                run_backup(app, None, Some(false));
+            }
+        }
+        wait(app, "backup-schedule", SCHEDULE_TICK, IdlePolicy::Stretch);
+    }
+}
+
+/// Backs up the data directory now. Progress is reported as `operation-progress`
+/// events and `cancel_operation` stops it without leaving a partial archive.
+#[tauri::command]
+pub async fn run_backup_now(
+    app: AppHandle,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> Result<BackupStatus, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || run_backup(&app, operation_id, notify_when_done))
+        .await
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })
+}
+
+#[tauri::command]
+pub fn get_backup_status(app: AppHandle) -> BackupStatus {
+    load_status(&app)
+}
+
+#[tauri::command]
+pub fn list_data_backups(app: AppHandle) -> Vec<DataBackup> {
+    list_archives(&backup_dir(&app))
+}
+
+/// Restores `archive` into `data_dir`, moving the current data (except logs) to a
+/// `<data dir>-before-restore-<time>` folder so a bad restore can be undone by hand.
+/// Nothing is moved until the archive has been read and checked in full, and a
+/// failed swap puts the current data back.
+fn restore(data_dir: &Path, archive: &Path) -> Result<(ImportResponse, PathBuf), CommandError> {
+    let aside = sibling_dir(
+        data_dir,
+        &format!(
+            "before-restore-{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ),
+    );
+    match restore_archive_into(data_dir, archive, &aside) {
+        Ok(response) => Ok((response, aside)),
+        // Only left behind when something couldn't be moved back.
+        Err(error) if aside.exists() => Err(CommandError::Io {
+            message: format!(
+                "Restore failed ({error}); some of the previous data is in {}",
+                aside.display()
+            ),
+        }),
+        Err(error) => Err(error),
+    }
+}
+
+#[tauri::command]
+pub fn restore_data_backup(app: AppHandle, path: String) -> Result<ImportResponse, CommandError> {
+    let (response, aside) = restore(&app_data_dir(&app), Path::new(&path))?;
+    log::info!(
+        "restore_data_backup: moved current data to {}",
+        aside.display()
+    );
+    app.state::<RecentsStore>().reload();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    /// A test directory holding a `data` directory with a settings file in it.
+    fn with_data(name: &str) -> PathBuf {
+        let dir = temp_dir("backups", name);
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::write(dir.join("data/settings.json"), "current").unwrap();
+        dir
+    }
+
+    /// The data directory is as it was and nothing was set aside next to it.
+    fn assert_untouched(dir: &Path) {
+        assert_eq!(
+            fs::read_to_string(dir.join("data/settings.json")).unwrap(),
+            "current"
+        );
+        let set_aside: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("data-"))
+            .collect();
+        assert!(set_aside.is_empty(), "{set_aside:?}");
+    }
+
+    #[test]
+    fn restores_the_archive_and_keeps_the_previous_data() {
+        let dir = with_data("restore");
+        let archive = dir.join("backup.zip");
+        let mut writer = ZipWriter::new(fs::File::create(&archive).unwrap());
+        for (name, contents) in [
+            ("settings.json", "restored"),
+            (
+                "excalibur-export.json",
+                r#"{"format_version":1,"app_version":"1.0.0","exported_at":0,"schema_versions":{},"files":["settings.json"]}"#,
+            ),
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let (_, aside) = restore(&dir.join("data"), &archive).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("data/settings.json")).unwrap(),
+            "restored"
+        );
+        assert_eq!(
+            fs::read_to_string(aside.join("settings.json")).unwrap(),
+            "current"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_corrupt_archive_changes_nothing() {
+        let dir = with_data("restore-corrupt");
+        let archive = dir.join("backup.zip");
+        fs::write(&archive, "not a zip file").unwrap();
+
+        assert!(restore(&dir.join("data"), &archive).is_err());
+        assert_untouched(&dir);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_missing_archive_changes_nothing() {
+        let dir = with_data("restore-missing");
+
+        assert!(restore(&dir.join("data"), &dir.join("mistyped.zip")).is_err());
+        assert_untouched(&dir);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
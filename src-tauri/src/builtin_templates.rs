@@ -0,0 +1,275 @@
+use crate::error::CommandError;
+use crate::templates::{template_file, template_path, templates_dir, write_template_meta};
+use crate::{content_hash, write_file};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+struct BuiltinTemplate {
+    name: &'static str,
+    kind: &'static str,
+    title: &'static str,
+    description: &'static str,
+    contents: &'static str,
+}
+
+/// Starter templates bundled with the app and seeded into the templates directory.
+const BUILTIN_TEMPLATES: [BuiltinTemplate; 4] = [
+    BuiltinTemplate {
+        name: "flowchart",
+        kind: "mermaid",
+        title: "Basic flowchart",
+        description: "A decision loop to start a flowchart from.",
+        contents: include_str!("../templates/flowchart.mmd"),
+    },
+    BuiltinTemplate {
+        name: "sequence",
+        kind: "mermaid",
+        title: "Sequence diagram",
+        description: "A user, app and API exchanging a request.",
+        contents: include_str!("../templates/sequence.mmd"),
+    },
+    BuiltinTemplate {
+        name: "architecture",
+        kind: "excalidraw",
+        title: "Architecture overview",
+        description: "Client, API and database boxes wired together.",
+        contents: include_str!("../templates/architecture.excalidraw"),
+    },
+    BuiltinTemplate {
+        name: "wireframe",
+        kind: "excalidraw",
+        title: "Page wireframe",
+        description: "A frame with header, navigation, content and footer areas.",
+        contents: include_str!("../templates/wireframe.excalidraw"),
+    },
+];
+
+/// A built-in template as it was last written by the app.
+#[derive(Serialize, Deserialize, Clone)]
+struct SeededTemplate {
+    kind: String,
+    hash: String,
+}
+
+/// Records which built-ins were seeded and the hash of what was written, so user
+/// edits can be told apart from older built-in versions.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct BuiltinIndex {
+    templates: BTreeMap<String, SeededTemplate>,
+}
+
+#[derive(Serialize, Clone)]
+struct BuiltinTemplateUpdate {
+    name: String,
+    kind: String,
+    title: String,
+}
+
+#[derive(Serialize)]
+pub struct UpdateBuiltinTemplatesResponse {
+    updated: Vec<String>,
+    /// Templates that were not refreshed because they were modified, removed or unknown.
+    skipped: Vec<String>,
+}
+
+fn index_path(app: &AppHandle) -> PathBuf {
+    templates_dir(app).join("builtin-index.json")
+}
+
+fn load_index(app: &AppHandle) -> BuiltinIndex {
+    fs::read_to_string(index_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(app: &AppHandle, index: &BuiltinIndex) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(index).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    write_file(&index_path(app), &contents)?;
+    Ok(())
+}
+
+/// Returns true when the file at `path` still holds exactly what we seeded.
+fn is_unmodified(path: &Path, seeded: &SeededTemplate) -> bool {
+    fs::read(path).is_ok_and(|bytes| content_hash(&bytes) == seeded.hash)
+}
+
+fn write_builtin(
+    app: &AppHandle,
+    builtin: &BuiltinTemplate,
+) -> Result<SeededTemplate, CommandError> {
+    let path = template_path(app, builtin.kind, builtin.name)?;
+    write_file(&path, builtin.contents)?;
+    write_template_meta(&path, builtin.title, Some(builtin.description.to_string()))?;
+    Ok(SeededTemplate {
+        kind: builtin.kind.to_string(),
+        hash: content_hash(builtin.contents.as_bytes()),
+    })
+}
+
+/// Seeds missing built-in templates and reports built-ins that have a newer version.
+///
+/// A built-in is only written when it was never seeded and no file of that name
+/// exists. Templates the user edited or deleted are never touched; unmodified ones
+/// with a newer bundled version are announced via `templates-updates-available`.
+pub fn seed_builtin_templates(app: &AppHandle) {
+    let mut index = load_index(app);
+    let mut index_changed = false;
+    let mut updates = Vec::new();
+
+    for builtin in &BUILTIN_TEMPLATES {
+        let Ok(path) = template_path(app, builtin.kind, builtin.name) else {
+            continue;
+        };
+        let bundled_hash = content_hash(builtin.contents.as_bytes());
+
+        match index.templates.get(builtin.name) {
+            None if !path.exists() => match write_builtin(app, builtin) {
+                Ok(seeded) => {
+                    log::info!("seed_builtin_templates: seeded {}", builtin.name);
+                    index.templates.insert(builtin.name.to_string(), seeded);
+                    index_changed = true;
+                }
+                Err(error) => {
+                    log::warn!(
+                        "seed_builtin_templates: failed to seed {}: {}",
+                        builtin.name,
+                        error
+                    );
+                }
+            },
+            None => {}
+            Some(seeded) if seeded.hash == bundled_hash => {}
+            Some(seeded) if path.exists() && is_unmodified(&path, seeded) => {
+                updates.push(BuiltinTemplateUpdate {
+                    name: builtin.name.to_string(),
+                    kind: builtin.kind.to_string(),
+                    title: builtin.title.to_string(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    if index_changed {
+        if let Err(error) = save_index(app, &index) {
+            log::warn!("seed_builtin_templates: failed to save index: {}", error);
+        }
+    }
+    if !updates.is_empty() {
+        log::info!(
+            "seed_builtin_templates: {} built-in template update(s) available",
+            updates.len()
+        );
+        let _ = app.emit("templates-updates-available", updates);
+    }
+}
+
+/// The built-in template `name` if it may be refreshed: it was seeded into `dir` and
+/// its file still holds exactly what was seeded.
+fn refreshable(
+    dir: &Path,
+    index: &BuiltinIndex,
+    name: &str,
+) -> Result<Option<&'static BuiltinTemplate>, CommandError> {
+    let builtin = BUILTIN_TEMPLATES
+        .iter()
+        .find(|builtin| builtin.name == name);
+    let (Some(builtin), Some(seeded)) = (builtin, index.templates.get(name)) else {
+        return Ok(None);
+    };
+    let path = template_file(dir, builtin.kind, builtin.name)?;
+    Ok(is_unmodified(&path, seeded).then_some(builtin))
+}
+
+/// Refreshes the named built-in templates to the bundled version.
+///
+/// Only templates that still match what was originally seeded are rewritten, so a
+/// user-modified template is never overwritten even if it is named here.
+#[tauri::command]
+pub fn update_builtin_templates(
+    app: AppHandle,
+    names: Vec<String>,
+) -> Result<UpdateBuiltinTemplatesResponse, CommandError> {
+    let mut index = load_index(&app);
+    let mut updated = Vec::new();
+    let mut skipped = Vec::new();
+
+    for name in names {
+        let Some(builtin) = refreshable(&templates_dir(&app), &index, &name)? else {
+            skipped.push(name);
+            continue;
+        };
+
+        let seeded = write_builtin(&app, builtin)?;
+        index.templates.insert(name.clone(), seeded);
+        updated.push(name);
+    }
+
+    save_index(&app, &index)?;
+    Ok(UpdateBuiltinTemplatesResponse { updated, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unmodified_seeded_templates_are_refreshed() {
+        let dir = std::env::temp_dir().join(format!(
+            "excalibur-builtin-templates-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut index = BuiltinIndex::default();
+        for builtin in &BUILTIN_TEMPLATES[..2] {
+            let path = template_file(&dir, builtin.kind, builtin.name).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, builtin.contents).unwrap();
+            index.templates.insert(
+                builtin.name.to_string(),
+                SeededTemplate {
+                    kind: builtin.kind.to_string(),
+                    hash: content_hash(builtin.contents.as_bytes()),
+                },
+            );
+        }
+        let name = |builtin: Option<&BuiltinTemplate>| builtin.map(|builtin| builtin.name);
+
+        assert_eq!(
+            name(refreshable(&dir, &index, "flowchart").unwrap()),
+            Some("flowchart")
+        );
+        // The user's edits are theirs to keep.
+        let sequence = template_file(&dir, "mermaid", "sequence").unwrap();
+        fs::write(&sequence, "sequenceDiagram\n  Me->>You: mine\n").unwrap();
+        assert_eq!(name(refreshable(&dir, &index, "sequence").unwrap()), None);
+        // So is a template of their own under a built-in's name, never seeded here.
+        let architecture = template_file(&dir, "excalidraw", "architecture").unwrap();
+        fs::create_dir_all(architecture.parent().unwrap()).unwrap();
+        fs::write(&architecture, BUILTIN_TEMPLATES[2].contents).unwrap();
+        assert_eq!(
+            name(refreshable(&dir, &index, "architecture").unwrap()),
+            None
+        );
+        assert_eq!(name(refreshable(&dir, &index, "unknown").unwrap()), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bundled_templates_are_valid_documents() {
+        for builtin in &BUILTIN_TEMPLATES {
+            assert!(!builtin.contents.trim().is_empty(), "{}", builtin.name);
+            if builtin.kind == "excalidraw" {
+                let scene: serde_json::Value = serde_json::from_str(builtin.contents).unwrap();
+                assert_eq!(scene["type"], "excalidraw", "{}", builtin.name);
+            }
+        }
+    }
+}
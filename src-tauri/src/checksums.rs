@@ -0,0 +1,208 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{content_hash, write_file};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+#[derive(Serialize, Clone)]
+struct ChecksumMismatch {
+    path: String,
+    expected: String,
+    actual: String,
+}
+
+/// `<file name>.sha256` next to the file.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Formats a sidecar line the way `sha256sum` does, so `sha256sum -c` can check it.
+fn sidecar_line(hash: &str, path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{hash}  {name}\n")
+}
+
+/// Reads the expected hash from a sidecar; the first field of the first line.
+fn read_sidecar(path: &Path) -> Option<String> {
+    let contents = fs::read_to_string(sidecar_path(path)).ok()?;
+    let hash = contents.split_whitespace().next()?;
+    Some(hash.to_lowercase())
+}
+
+fn write_sidecar(path: &Path) -> Result<(), CommandError> {
+    let bytes = fs::read(path).map_err(|error| CommandError::from_io(error, path))?;
+    write_file(
+        &sidecar_path(path),
+        &sidecar_line(&content_hash(&bytes), path),
+    )
+}
+
+/// Brings the sidecar of a just-saved file up to date: written when `write_checksums`
+/// is on, and rewritten whenever one exists, so turning checksums off never leaves a
+/// sidecar that no longer matches.
+fn refresh(path: &Path, write_checksums: bool) -> Result<(), CommandError> {
+    if write_checksums || sidecar_path(path).is_file() {
+        write_sidecar(path)?;
+    }
+    Ok(())
+}
+
+/// Updates the sidecar of a just-saved file; see `refresh`.
+pub fn after_save(app: &AppHandle, path: &Path) {
+    if let Err(error) = refresh(path, load_settings(app).write_checksums) {
+        log::warn!(
+            "after_save: failed to write checksum for {}: {}",
+            path.display(),
+            error
+        );
+    }
+}
+
+/// Moves the sidecar of a file renamed from `old` to `new` along with it, naming the
+/// new file inside. A sidecar already at `new` belonged to the file the rename
+/// replaced, so without one to move it is removed.
+pub fn move_sidecar(old: &Path, new: &Path) -> Result<(), CommandError> {
+    let (from, to) = (sidecar_path(old), sidecar_path(new));
+    if !from.is_file() {
+        return match fs::remove_file(&to) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                Err(CommandError::from_io(error, &to))
+            }
+            _ => Ok(()),
+        };
+    }
+    let hash = read_sidecar(old);
+    fs::rename(&from, &to).map_err(|error| CommandError::from_io(error, &to))?;
+    match hash {
+        Some(hash) => write_file(&to, &sidecar_line(&hash, new)),
+        None => Ok(()),
+    }
+}
+
+/// Compares a file with its sidecar: "ok", "mismatch" with what differs, or
+/// "missing" when checksums are on but the file has none.
+fn check(path: &Path, write_checksums: bool) -> Option<(&'static str, Option<ChecksumMismatch>)> {
+    let Some(expected) = read_sidecar(path) else {
+        return write_checksums.then_some(("missing", None));
+    };
+    let actual = content_hash(&fs::read(path).ok()?);
+    if actual == expected {
+        return Some(("ok", None));
+    }
+    let mismatch = ChecksumMismatch {
+        path: path.to_string_lossy().to_string(),
+        expected,
+        actual,
+    };
+    Some(("mismatch", Some(mismatch)))
+}
+
+/// Checks an opened file against its sidecar: "ok", "mismatch", or "missing" when
+/// checksums are on but the file has none. `None` when there is nothing to report.
+/// A mismatch emits `checksum-mismatch` but never blocks opening.
+pub fn verify_on_open(app: &AppHandle, path: &Path) -> Option<&'static str> {
+    let (status, mismatch) = check(path, load_settings(app).write_checksums)?;
+    if let Some(mismatch) = mismatch {
+        log::warn!("verify_on_open: checksum mismatch for {}", path.display());
+        let _ = app.emit("checksum-mismatch", mismatch);
+    }
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn sidecars_are_in_sha256sum_format() {
+        let path = Path::new("/work/flow.mmd");
+        assert_eq!(sidecar_path(path), Path::new("/work/flow.mmd.sha256"));
+        assert_eq!(sidecar_line("ab12", path), "ab12  flow.mmd\n");
+
+        let dir = temp_dir("checksums", "format");
+        let flow = dir.join("flow.mmd");
+        fs::write(sidecar_path(&flow), "AB12  flow.mmd\nffff  other.mmd\n").unwrap();
+        assert_eq!(read_sidecar(&flow).as_deref(), Some("ab12"));
+        fs::write(sidecar_path(&flow), "\n").unwrap();
+        assert_eq!(read_sidecar(&flow), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saves_keep_existing_sidecars_current() {
+        let dir = temp_dir("checksums", "refresh");
+        let flow = dir.join("flow.mmd");
+        fs::write(&flow, "graph TD\n").unwrap();
+
+        refresh(&flow, false).unwrap();
+        assert!(!sidecar_path(&flow).exists());
+        refresh(&flow, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(sidecar_path(&flow)).unwrap(),
+            sidecar_line(&content_hash(b"graph TD\n"), &flow)
+        );
+
+        // With checksums turned off since, the sidecar still follows the file.
+        fs::write(&flow, "graph LR\n").unwrap();
+        refresh(&flow, false).unwrap();
+        assert_eq!(read_sidecar(&flow), Some(content_hash(b"graph LR\n")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn opened_files_are_ok_mismatched_or_missing_a_sidecar() {
+        let dir = temp_dir("checksums", "check");
+        let flow = dir.join("flow.mmd");
+        fs::write(&flow, "graph TD\n").unwrap();
+
+        assert!(check(&flow, false).is_none());
+        assert_eq!(
+            check(&flow, true).map(|(status, _)| status),
+            Some("missing")
+        );
+
+        write_sidecar(&flow).unwrap();
+        assert_eq!(check(&flow, false).map(|(status, _)| status), Some("ok"));
+
+        fs::write(&flow, "graph LR\n").unwrap();
+        let (status, mismatch) = check(&flow, false).unwrap();
+        assert_eq!(status, "mismatch");
+        let mismatch = mismatch.unwrap();
+        assert_eq!(mismatch.expected, content_hash(b"graph TD\n"));
+        assert_eq!(mismatch.actual, content_hash(b"graph LR\n"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn renamed_files_take_their_sidecar_along() {
+        let dir = temp_dir("checksums", "move");
+        let old = dir.join("flow.mmd");
+        let new = dir.join("checkout.mmd");
+        fs::write(&old, "graph TD\n").unwrap();
+        write_sidecar(&old).unwrap();
+        fs::rename(&old, &new).unwrap();
+
+        move_sidecar(&old, &new).unwrap();
+        assert!(!sidecar_path(&old).exists());
+        assert_eq!(
+            fs::read_to_string(sidecar_path(&new)).unwrap(),
+            sidecar_line(&content_hash(b"graph TD\n"), &new)
+        );
+        assert_eq!(check(&new, false).map(|(status, _)| status), Some("ok"));
+
+        // Moving a file without a sidecar over one that had it drops the stale sidecar.
+        fs::write(&old, "graph LR\n").unwrap();
+        fs::rename(&old, &new).unwrap();
+        move_sidecar(&old, &new).unwrap();
+        assert!(!sidecar_path(&new).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
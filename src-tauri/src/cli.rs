@@ -0,0 +1,455 @@
+use crate::error::CommandError;
+use crate::operations::Operation;
+use crate::{detect, render, scan};
+use serde::Serialize;
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// How long the hidden window gets to load the frontend.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a single document gets to render.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+/// Largest document accepted on stdin.
+const MAX_STDIN_BYTES: u64 = 10 * 1024 * 1024;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NO_WEBVIEW: i32 = 3;
+
+pub const USAGE: &str = "usage:
+  excalibur export --input <file> --output <file> [--format svg|png]
+  excalibur export --batch <dir> --out-dir <dir> [--format svg|png]
+  excalibur --stdin [--kind excalidraw|mermaid] < diagram
+  excalibur [--new-instance] [file...]";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportTarget {
+    File { input: PathBuf, output: PathBuf },
+    Batch { dir: PathBuf, out_dir: PathBuf },
+}
+
+/// A headless `excalibur export` invocation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportCommand {
+    pub target: ExportTarget,
+    pub format: String,
+}
+
+/// Parses the process arguments. Returns `Ok(None)` for a normal GUI launch, so
+/// file paths handed over by the OS keep working.
+pub fn parse_args(args: &[String]) -> Result<Option<ExportCommand>, String> {
+    if args.get(1).map(String::as_str) != Some("export") {
+        return Ok(None);
+    }
+
+    let mut input = None;
+    let mut output = None;
+    let mut batch = None;
+    let mut out_dir = None;
+    let mut format = None;
+    let mut rest = args[2..].iter();
+    while let Some(flag) = rest.next() {
+        // Data location flags apply to every launch; paths::DataRoot reads them.
+        if flag == crate::paths::PORTABLE_FLAG
+            || flag.starts_with(&format!("{}=", crate::paths::DATA_DIR_FLAG))
+        {
+            continue;
+        }
+        if flag == crate::paths::DATA_DIR_FLAG {
+            rest.next();
+            continue;
+        }
+        let slot = match flag.as_str() {
+            "--input" => &mut input,
+            "--output" => &mut output,
+            "--batch" => &mut batch,
+            "--out-dir" => &mut out_dir,
+            "--format" => &mut format,
+            _ => return Err(format!("unknown argument \"{flag}\"")),
+        };
+        let value = rest.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        *slot = Some(value.clone());
+    }
+
+    let target = match (input, output, batch, out_dir) {
+        (Some(input), Some(output), None, None) => ExportTarget::File {
+            input: PathBuf::from(input),
+            output: PathBuf::from(output),
+        },
+        (None, None, Some(dir), Some(out_dir)) => ExportTarget::Batch {
+            dir: PathBuf::from(dir),
+            out_dir: PathBuf::from(out_dir),
+        },
+        _ => return Err("use either --input with --output, or --batch with --out-dir".to_string()),
+    };
+
+    // Without --format, a single export follows the output extension.
+    let format = match (format, &target) {
+        (Some(format), _) => format.to_lowercase(),
+        (None, ExportTarget::File { output, .. }) => output
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "svg".to_string()),
+        (None, ExportTarget::Batch { .. }) => "svg".to_string(),
+    };
+    if !render::RENDER_FORMATS.contains(&format.as_str()) {
+        return Err(format!(
+            "unsupported format \"{format}\" (expected svg or png)"
+        ));
+    }
+
+    Ok(Some(ExportCommand { target, format }))
+}
+
+/// Explains why no webview can be started here, e.g. on a server without a display.
+pub fn headless_error() -> Option<String> {
+    if cfg!(target_os = "linux")
+        && std::env::var_os("DISPLAY").is_none()
+        && std::env::var_os("WAYLAND_DISPLAY").is_none()
+    {
+        return Some(
+            "no display available; export renders in a webview and needs X11 or Wayland \
+             (try running it under xvfb-run)"
+                .to_string(),
+        );
+    }
+    tauri::webview_version()
+        .err()
+        .map(|error| format!("no webview runtime available: {error}"))
+}
+
+/// Runs the export and returns the process exit code. Prints each written path to
+/// stdout and every failure to stderr.
+pub fn run_export(app: &AppHandle, command: &ExportCommand) -> i32 {
+    if !render::wait_until_ready(app, READY_TIMEOUT) {
+        eprintln!("excalibur: the renderer did not start");
+        return EXIT_FAILED;
+    }
+
+    let jobs = match &command.target {
+        ExportTarget::File { input, output } => vec![(input.clone(), output.clone())],
+        ExportTarget::Batch { dir, out_dir } => {
+            if !dir.is_dir() {
+                eprintln!("excalibur: {} is not a directory", dir.display());
+                return EXIT_USAGE;
+            }
+            scan::scan_workspace(dir)
+                .into_iter()
+                .map(|file| {
+                    let relative = file.path.strip_prefix(dir).unwrap_or(&file.path);
+                    let output = out_dir.join(relative).with_extension(&command.format);
+                    (file.path, output)
+                })
+                .collect()
+        }
+    };
+
+    let operation = Operation::start(app, "export", None, true);
+    let report = export_jobs(&jobs, &operation, |kind, contents| {
+        render::render(app, kind, contents, &command.format, RENDER_TIMEOUT)
+            .map_err(|error| error.to_string())
+    });
+    for output in &report.written {
+        println!("{}", output.display());
+    }
+    for (input, message) in &report.failed {
+        eprintln!("excalibur: {}: {message}", input.display());
+    }
+    if report.cancelled {
+        eprintln!("excalibur: export cancelled");
+    }
+    if report.failed.is_empty() && !report.cancelled {
+        EXIT_OK
+    } else {
+        EXIT_FAILED
+    }
+}
+
+/// Outcome of an export run.
+#[derive(Default)]
+pub struct ExportReport {
+    pub written: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+    pub cancelled: bool,
+}
+
+/// Exports each `(input, output)` job with `render`, which turns a document's kind
+/// and contents into the output bytes.
+///
+/// Outputs are written to a hidden `.partial` sibling and renamed into place, so a
+/// failed or cancelled run never leaves a half-written file behind; files finished
+/// before a cancellation are kept.
+pub fn export_jobs(
+    jobs: &[(PathBuf, PathBuf)],
+    operation: &Operation,
+    render: impl Fn(&str, &str) -> Result<Vec<u8>, String>,
+) -> ExportReport {
+    let mut report = ExportReport::default();
+    let total = jobs.len() as u64;
+    for (done, (input, output)) in jobs.iter().enumerate() {
+        if operation.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+        operation.progress(
+            "exporting",
+            done as u64,
+            Some(total),
+            Some(&input.to_string_lossy()),
+        );
+        let rendered = fs::read_to_string(input)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                let kind = detect::detect_kind(input, &contents)
+                    .ok_or_else(|| "not an Excalidraw or Mermaid document".to_string())?;
+                render(kind, &contents)
+            });
+        // A render that finished after cancellation is discarded, not written.
+        if operation.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+        match rendered.and_then(|bytes| write_output(output, &bytes)) {
+            Ok(()) => report.written.push(output.clone()),
+            Err(message) => report.failed.push((input.clone(), message)),
+        }
+    }
+
+    let outcome = if report.cancelled {
+        Err(CommandError::Cancelled)
+    } else {
+        Ok(())
+    };
+    operation.finish(&outcome, report.written.len() as u64);
+    report
+}
+
+fn partial_path(output: &Path) -> PathBuf {
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output.with_file_name(format!(".{name}.partial"))
+}
+
+fn write_output(output: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let partial = partial_path(output);
+    let written = fs::write(&partial, bytes).and_then(|()| fs::rename(&partial, output));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(error.to_string());
+    }
+    Ok(())
+}
+
+/// Payload of the `new-document` event: an unsaved document from `--stdin` or an
+/// `excalibur://new` link.
+#[derive(Serialize, Clone, Debug)]
+pub struct NewDocument {
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub contents: String,
+}
+
+/// Reads the document piped in when launched with `--stdin [--kind <kind>]`.
+///
+/// Returns `Ok(None)` without the flag. Fails when nothing is piped, the input is
+/// binary or larger than the cap, or its kind can't be worked out.
+pub fn read_stdin_document(args: &[String]) -> Result<Option<NewDocument>, String> {
+    if !args.iter().any(|arg| arg == "--stdin") {
+        return Ok(None);
+    }
+    let requested_kind = match args.iter().position(|arg| arg == "--kind") {
+        Some(index) => match args.get(index + 1).map(String::as_str) {
+            Some("excalidraw") => Some("excalidraw"),
+            Some("mermaid") => Some("mermaid"),
+            Some(kind) => return Err(format!("unknown kind \"{kind}\"")),
+            None => return Err("--kind needs a value".to_string()),
+        },
+        None => None,
+    };
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err("--stdin needs content piped in".to_string());
+    }
+    let mut bytes = Vec::new();
+    stdin
+        .lock()
+        .take(MAX_STDIN_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|error| format!("could not read stdin: {error}"))?;
+    if bytes.len() as u64 > MAX_STDIN_BYTES {
+        return Err(format!(
+            "stdin is larger than the {} MiB limit",
+            MAX_STDIN_BYTES / 1024 / 1024
+        ));
+    }
+    if bytes.contains(&0) {
+        return Err("stdin looks like binary data, not a diagram".to_string());
+    }
+    let contents = String::from_utf8(bytes)
+        .map_err(|_| "stdin looks like binary data, not a diagram".to_string())?;
+    if contents.trim().is_empty() {
+        return Err("stdin is empty".to_string());
+    }
+    let kind = requested_kind
+        .or_else(|| detect::detect_content_kind(&contents))
+        .ok_or_else(|| "could not tell what kind of diagram stdin is; pass --kind".to_string())?;
+    Ok(Some(NewDocument {
+        kind,
+        title: None,
+        contents,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{CancellationToken, OperationProgress};
+    use crate::test_support::temp_dir;
+    use std::sync::{Arc, Mutex};
+
+    fn batch_jobs(dir: &Path, count: usize) -> Vec<(PathBuf, PathBuf)> {
+        (0..count)
+            .map(|index| {
+                let input = dir.join("in").join(format!("diagram-{index}.mmd"));
+                fs::create_dir_all(input.parent().unwrap()).unwrap();
+                fs::write(&input, "graph TD\n  A --> B\n").unwrap();
+                let output = dir.join("out").join(format!("diagram-{index}.svg"));
+                (input, output)
+            })
+            .collect()
+    }
+
+    fn recording_operation(
+        token: &CancellationToken,
+    ) -> (Operation, Arc<Mutex<Vec<OperationProgress>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let operation = Operation::detached("export", token.clone(), move |progress| {
+            recorded.lock().unwrap().push(progress.clone())
+        });
+        (operation, events)
+    }
+
+    fn out_dir_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir.join("out"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn parses_single_and_batch_exports() {
+        let args: Vec<String> = [
+            "excalibur",
+            "export",
+            "--input",
+            "a.mmd",
+            "--output",
+            "a.png",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let command = parse_args(&args).unwrap().unwrap();
+        assert_eq!(command.format, "png");
+
+        let args: Vec<String> = [
+            "excalibur",
+            "export",
+            "--batch",
+            "docs",
+            "--out-dir",
+            "build",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let command = parse_args(&args).unwrap().unwrap();
+        assert_eq!(command.format, "svg");
+        assert!(matches!(command.target, ExportTarget::Batch { .. }));
+
+        assert_eq!(parse_args(&["excalibur".to_string()]).unwrap(), None);
+    }
+
+    #[test]
+    fn exports_every_job() {
+        let dir = temp_dir("cli", "complete");
+        let jobs = batch_jobs(&dir, 3);
+        let (operation, events) = recording_operation(&CancellationToken::default());
+
+        let report = export_jobs(&jobs, &operation, |_, contents| {
+            Ok(format!("<svg>{contents}</svg>").into_bytes())
+        });
+
+        assert!(!report.cancelled);
+        assert_eq!(report.written.len(), 3);
+        assert_eq!(
+            out_dir_names(&dir),
+            ["diagram-0.svg", "diagram-1.svg", "diagram-2.svg"]
+        );
+        let events = events.lock().unwrap();
+        assert_eq!(events.last().unwrap().phase, "finished");
+        assert!(events.iter().all(|event| event.total == Some(3)));
+    }
+
+    #[test]
+    fn cancelling_midway_leaves_no_partial_files() {
+        let dir = temp_dir("cli", "cancel");
+        let jobs = batch_jobs(&dir, 4);
+        let token = CancellationToken::default();
+        let (operation, events) = recording_operation(&token);
+        let calls = Mutex::new(0);
+
+        let report = export_jobs(&jobs, &operation, |_, contents| {
+            let mut calls = calls.lock().unwrap();
+            *calls += 1;
+            // The second render is in flight when the user cancels.
+            if *calls == 2 {
+                token.cancel();
+            }
+            Ok(format!("<svg>{contents}</svg>").into_bytes())
+        });
+
+        assert!(report.cancelled);
+        assert_eq!(*calls.lock().unwrap(), 2);
+        assert_eq!(report.written, [jobs[0].1.clone()]);
+        assert_eq!(out_dir_names(&dir), ["diagram-0.svg"]);
+        assert_eq!(
+            fs::read_to_string(&jobs[0].1).unwrap(),
+            "<svg>graph TD\n  A --> B\n</svg>"
+        );
+        assert_eq!(events.lock().unwrap().last().unwrap().phase, "cancelled");
+    }
+
+    #[test]
+    fn failed_writes_clean_up_their_partial_file() {
+        let dir = temp_dir("cli", "failed");
+        let jobs = batch_jobs(&dir, 1);
+        // A directory where the output should go makes the final rename fail.
+        fs::create_dir_all(jobs[0].1.join("occupied")).unwrap();
+        let (operation, _) = recording_operation(&CancellationToken::default());
+
+        let report = export_jobs(&jobs, &operation, |_, _| Ok(b"<svg/>".to_vec()));
+
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(out_dir_names(&dir), ["diagram-0.svg"]);
+        assert!(jobs[0].1.is_dir());
+    }
+}
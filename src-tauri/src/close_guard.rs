@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use tauri::{AppHandle, CloseRequestApi, Emitter, ExitRequestApi, Manager, Window};
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+
+const SAVE: &str = "Save";
+const DONT_SAVE: &str = "Don't Save";
+const CANCEL: &str = "Cancel";
+
+/// Which windows have unsaved changes, so closing them or quitting asks first.
+#[derive(Default)]
+pub struct CloseGuard(Mutex<GuardState>);
+
+#[derive(Default)]
+struct GuardState {
+    /// Windows with unsaved changes, with the name of the document to ask about.
+    dirty: HashMap<String, Option<String>>,
+    /// Windows the user let go: saved, or chose not to save.
+    confirmed: HashSet<String>,
+    /// While quitting, the windows still to ask about, in order.
+    quit_queue: Option<VecDeque<String>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Answer {
+    Save,
+    DontSave,
+    Cancel,
+}
+
+impl GuardState {
+    fn set_dirty(&mut self, label: &str, dirty: bool, document_name: Option<String>) {
+        if dirty {
+            self.dirty.insert(label.to_string(), document_name);
+        } else {
+            self.dirty.remove(label);
+        }
+        // Changes made after the user let the window go need asking about again.
+        self.confirmed.remove(label);
+    }
+
+    fn needs_prompt(&self, label: &str) -> bool {
+        self.dirty.contains_key(label) && !self.confirmed.contains(label)
+    }
+
+    fn forget(&mut self, label: &str) {
+        self.dirty.remove(label);
+        self.confirmed.remove(label);
+        if let Some(queue) = &mut self.quit_queue {
+            queue.retain(|queued| queued != label);
+        }
+    }
+
+    /// Starts quitting with the windows `labels`; returns the first one to ask about,
+    /// or `None` when none has unsaved changes and the app can quit right away.
+    fn start_quit(&mut self, labels: &[String]) -> Option<String> {
+        let queue = labels
+            .iter()
+            .filter(|label| self.needs_prompt(label))
+            .cloned()
+            .collect();
+        self.quit_queue = Some(queue);
+        self.next_to_ask()
+    }
+
+    /// The next window of the quit to ask about. `None` ends the quit: everything
+    /// was answered.
+    fn next_to_ask(&mut self) -> Option<String> {
+        let mut queue = self.quit_queue.take()?;
+        while let Some(label) = queue.pop_front() {
+            if self.needs_prompt(&label) {
+                self.quit_queue = Some(queue);
+                return Some(label);
+            }
+        }
+        None
+    }
+
+    fn is_quitting(&self) -> bool {
+        self.quit_queue.is_some()
+    }
+}
+
+fn answer(result: &MessageDialogResult) -> Answer {
+    match result {
+        MessageDialogResult::Yes => Answer::Save,
+        MessageDialogResult::No => Answer::DontSave,
+        MessageDialogResult::Custom(label) if label == SAVE => Answer::Save,
+        MessageDialogResult::Custom(label) if label == DONT_SAVE => Answer::DontSave,
+        _ => Answer::Cancel,
+    }
+}
+
+fn prompt_message(document_name: Option<&str>) -> String {
+    let document = document_name
+        .map(|name| format!("\"{name}\""))
+        .unwrap_or_else(|| "this document".to_string());
+    format!(
+        "Do you want to save the changes you made to {document}?\n\n\
+         Your changes will be lost if you don't save them."
+    )
+}
+
+/// Asks whether to save window `label`'s changes, bringing the window to the front.
+fn ask(app: &AppHandle, label: &str) {
+    let Some(window) = app.get_webview_window(label) else {
+        // Gone in the meantime; nothing left to save there.
+        app.state::<CloseGuard>().0.lock().unwrap().forget(label);
+        return proceed(app, label);
+    };
+    let document_name = app
+        .state::<CloseGuard>()
+        .0
+        .lock()
+        .unwrap()
+        .dirty
+        .get(label)
+        .cloned()
+        .flatten();
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+    let handle = app.clone();
+    let label = label.to_string();
+    app.dialog()
+        .message(prompt_message(document_name.as_deref()))
+        .title("Unsaved Changes")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            SAVE.to_string(),
+            DONT_SAVE.to_string(),
+            CANCEL.to_string(),
+        ))
+        .parent(&window)
+        .show_with_result(move |result| answered(&handle, &label, answer(&result)));
+}
+
+fn answered(app: &AppHandle, label: &str, answer: Answer) {
+    log::info!("close_guard: {} answered {:?}", label, answer);
+    let state = app.state::<CloseGuard>();
+    match answer {
+        // The frontend saves and calls `close_confirmed`; a failed or cancelled save
+        // leaves the window open.
+        Answer::Save => {
+            let _ = app.emit_to(label, "save-before-close", ());
+        }
+        Answer::DontSave => {
+            state.0.lock().unwrap().confirmed.insert(label.to_string());
+            proceed(app, label);
+        }
+        Answer::Cancel => state.0.lock().unwrap().quit_queue = None,
+    }
+}
+
+/// Goes on after window `label` was let go: closes it, or while quitting asks about
+/// the next window and quits once none is left.
+fn proceed(app: &AppHandle, label: &str) {
+    let state = app.state::<CloseGuard>();
+    if state.0.lock().unwrap().is_quitting() {
+        let next = state.0.lock().unwrap().next_to_ask();
+        match next {
+            Some(next) => ask(app, &next),
+            None => app.exit(0),
+        }
+    } else if let Some(window) = app.get_webview_window(label) {
+        let _ = window.close();
+    }
+}
+
+/// Handles `WindowEvent::CloseRequested`: a window with unsaved changes stays open
+/// until the user answers whether to save them.
+pub fn on_close_requested(window: &Window, api: &CloseRequestApi) {
+    let app = window.app_handle();
+    if !app
+        .state::<CloseGuard>()
+        .0
+        .lock()
+        .unwrap()
+        .needs_prompt(window.label())
+    {
+        return;
+    }
+    api.prevent_close();
+    ask(app, window.label());
+}
+
+/// Handles `RunEvent::ExitRequested`: quitting asks about each window with unsaved
+/// changes in turn, and only quits once all of them were answered.
+pub fn on_exit_requested(app: &AppHandle, api: &ExitRequestApi) {
+    let mut labels: Vec<String> = app.webview_windows().into_keys().collect();
+    labels.sort();
+    let state = app.state::<CloseGuard>();
+    if state.0.lock().unwrap().is_quitting() {
+        // Still asking about an earlier request.
+        api.prevent_exit();
+        return;
+    }
+    let first = state.0.lock().unwrap().start_quit(&labels);
+    if let Some(first) = first {
+        api.prevent_exit();
+        ask(app, &first);
+    }
+}
+
+/// Forgets a closed window.
+pub fn forget_window(app: &AppHandle, label: &str) {
+    app.state::<CloseGuard>().0.lock().unwrap().forget(label);
+}
+
+/// Records whether window `window_label` has unsaved changes, and the name of the
+/// document to mention when asking about them.
+#[tauri::command]
+pub fn set_window_dirty(
+    app: AppHandle,
+    window_label: String,
+    dirty: bool,
+    document_name: Option<String>,
+) {
+    app.state::<CloseGuard>()
+        .0
+        .lock()
+        .unwrap()
+        .set_dirty(&window_label, dirty, document_name);
+}
+
+/// Called by the frontend once it saved in answer to `save-before-close`: the window
+/// closes, or the quit moves on to the next window.
+#[tauri::command]
+pub fn close_confirmed(app: AppHandle, window_label: String) {
+    app.state::<CloseGuard>()
+        .0
+        .lock()
+        .unwrap()
+        .confirmed
+        .insert(window_label.clone());
+    proceed(&app, &window_label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn only_unconfirmed_dirty_windows_are_asked_about() {
+        let mut state = GuardState::default();
+        assert!(!state.needs_prompt("main"));
+        state.set_dirty("main", true, Some("flow.mmd".to_string()));
+        assert!(state.needs_prompt("main"));
+        state.confirmed.insert("main".to_string());
+        assert!(!state.needs_prompt("main"));
+
+        // Editing again after letting the window go asks again.
+        state.set_dirty("main", true, Some("flow.mmd".to_string()));
+        assert!(state.needs_prompt("main"));
+        state.set_dirty("main", false, None);
+        assert!(!state.needs_prompt("main"));
+    }
+
+    #[test]
+    fn quitting_asks_about_each_dirty_window_in_turn() {
+        let mut state = GuardState::default();
+        state.set_dirty("doc-2", true, None);
+        state.set_dirty("main", true, None);
+        state.set_dirty("doc-3", true, None);
+        let windows = labels(&["doc-1", "doc-2", "doc-3", "main"]);
+
+        assert_eq!(state.start_quit(&windows).as_deref(), Some("doc-2"));
+        state.confirmed.insert("doc-2".to_string());
+        // doc-3 was closed while the first prompt was up.
+        state.forget("doc-3");
+        assert_eq!(state.next_to_ask().as_deref(), Some("main"));
+        state.confirmed.insert("main".to_string());
+        assert_eq!(state.next_to_ask(), None);
+        assert!(!state.is_quitting());
+
+        // Nothing left to ask about: the next quit goes through at once.
+        assert_eq!(state.start_quit(&windows), None);
+    }
+
+    #[test]
+    fn dialog_buttons_map_to_answers() {
+        assert_eq!(
+            answer(&MessageDialogResult::Custom(SAVE.to_string())),
+            Answer::Save
+        );
+        assert_eq!(answer(&MessageDialogResult::Yes), Answer::Save);
+        assert_eq!(
+            answer(&MessageDialogResult::Custom(DONT_SAVE.to_string())),
+            Answer::DontSave
+        );
+        assert_eq!(answer(&MessageDialogResult::No), Answer::DontSave);
+        assert_eq!(answer(&MessageDialogResult::Cancel), Answer::Cancel);
+        assert_eq!(
+            answer(&MessageDialogResult::Custom(CANCEL.to_string())),
+            Answer::Cancel
+        );
+    }
+}
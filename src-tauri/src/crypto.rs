@@ -0,0 +1,756 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{
+    file_name, update_recents, write_file, OpenFileResponse, OpenPathResponse, SaveFileResponse,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::{channel, Sender};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+const MAGIC: &[u8; 6] = b"EXCENC";
+const FORMAT_VERSION: u8 = 1;
+/// Extension appended to the plain file name, e.g. `design.excalidraw.enc`.
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_CHECK_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 12 + SALT_LEN + NONCE_LEN + KEY_CHECK_LEN;
+/// Wrong passwords allowed per file before attempts start being delayed.
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_ATTEMPT_DELAY: Duration = Duration::from_secs(60);
+/// How long `prompt_password` waits for the user before cancelling the operation.
+const PASSWORD_PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Argon2id cost parameters: memory in KiB, iterations and lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+pub const DEFAULT_KDF_PARAMS: KdfParams = KdfParams {
+    memory_kib: 19 * 1024,
+    iterations: 2,
+    parallelism: 1,
+};
+
+/// The most a file header may ask for. Headers are read from untrusted files, so
+/// these bound how much memory and time opening one can cost.
+const MAX_KDF_PARAMS: KdfParams = KdfParams {
+    memory_kib: 4 * DEFAULT_KDF_PARAMS.memory_kib,
+    iterations: 4 * DEFAULT_KDF_PARAMS.iterations,
+    parallelism: 4,
+};
+
+/// A derived key together with the salt and parameters it was derived with.
+#[derive(Clone)]
+pub struct DerivedKey {
+    salt: [u8; SALT_LEN],
+    params: KdfParams,
+    key: [u8; 32],
+}
+
+struct Header {
+    kind: &'static str,
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    key_check: [u8; KEY_CHECK_LEN],
+}
+
+/// Per-session encryption state. Keys are only ever held in memory.
+#[derive(Default)]
+pub struct EncryptionSessions {
+    keys: Mutex<HashMap<PathBuf, DerivedKey>>,
+    prompts: Mutex<HashMap<String, Sender<String>>>,
+    failures: Mutex<HashMap<PathBuf, (u32, Instant)>>,
+    next_operation: AtomicU64,
+}
+
+#[derive(Serialize, Clone)]
+struct PasswordPrompt {
+    operation_id: String,
+    path: String,
+    /// "open" or "save".
+    purpose: &'static str,
+    /// Set when re-prompting after a failed attempt.
+    error: Option<&'static str>,
+}
+
+pub fn is_encrypted_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(ENCRYPTED_EXTENSION))
+}
+
+pub fn is_encrypted_bytes(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn kind_byte(kind: &str) -> Result<u8, CommandError> {
+    match kind {
+        "excalidraw" => Ok(0),
+        "mermaid" => Ok(1),
+        _ => Err(CommandError::InvalidKind {
+            kind: kind.to_string(),
+        }),
+    }
+}
+
+fn kind_from_byte(byte: u8) -> Option<&'static str> {
+    match byte {
+        0 => Some("excalidraw"),
+        1 => Some("mermaid"),
+        _ => None,
+    }
+}
+
+fn derive_key(
+    password: &str,
+    salt: [u8; SALT_LEN],
+    params: KdfParams,
+) -> Result<DerivedKey, CommandError> {
+    let argon_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(|error| CommandError::InvalidDocument {
+        message: format!("Invalid key derivation parameters: {error}"),
+    })?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+        .hash_password_into(password.as_bytes(), &salt, &mut key)
+        .map_err(|error| CommandError::InvalidArgument {
+            message: error.to_string(),
+        })?;
+    Ok(DerivedKey { salt, params, key })
+}
+
+/// Derives a key with a fresh salt, off the async runtime since that takes a second
+/// or more.
+async fn new_key(password: String) -> Result<DerivedKey, CommandError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    tauri::async_runtime::spawn_blocking(move || derive_key(&password, salt, DEFAULT_KDF_PARAMS))
+        .await
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })?
+}
+
+/// A short digest of the derived key stored in the header. It lets a wrong password
+/// be told apart from a damaged or tampered file, which AEAD alone can't do.
+fn key_check(key: &[u8; 32]) -> [u8; KEY_CHECK_LEN] {
+    let digest = Sha256::new()
+        .chain_update(b"excalibur-key-check")
+        .chain_update(key)
+        .finalize();
+    let mut check = [0u8; KEY_CHECK_LEN];
+    check.copy_from_slice(&digest[..KEY_CHECK_LEN]);
+    check
+}
+
+/// Encrypts a document into the container format:
+///
+/// `magic | version | kind | memory, iterations, lanes (u32 LE) | salt | nonce | key check | ciphertext`
+///
+/// The header is authenticated as associated data. Every call uses a fresh nonce,
+/// so a cached key can be reused for later saves.
+pub fn encrypt_with_key(
+    kind: &str,
+    plaintext: &str,
+    key: &DerivedKey,
+) -> Result<Vec<u8>, CommandError> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut output = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    output.extend_from_slice(MAGIC);
+    output.push(FORMAT_VERSION);
+    output.push(kind_byte(kind)?);
+    output.extend_from_slice(&key.params.memory_kib.to_le_bytes());
+    output.extend_from_slice(&key.params.iterations.to_le_bytes());
+    output.extend_from_slice(&key.params.parallelism.to_le_bytes());
+    output.extend_from_slice(&key.salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&key_check(&key.key));
+
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key.key));
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: &output,
+            },
+        )
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })?;
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header, CommandError> {
+    if bytes.len() < HEADER_LEN || !is_encrypted_bytes(bytes) {
+        return Err(CommandError::InvalidDocument {
+            message: "Not an encrypted Excalibur file".to_string(),
+        });
+    }
+    let version = bytes[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(CommandError::InvalidDocument {
+            message: format!("Unsupported encrypted file version {version}"),
+        });
+    }
+    let kind = kind_from_byte(bytes[MAGIC.len() + 1]).ok_or(CommandError::Tampered)?;
+    let mut offset = MAGIC.len() + 2;
+    let params = KdfParams {
+        memory_kib: read_u32(bytes, offset),
+        iterations: read_u32(bytes, offset + 4),
+        parallelism: read_u32(bytes, offset + 8),
+    };
+    if params.memory_kib > MAX_KDF_PARAMS.memory_kib
+        || params.iterations > MAX_KDF_PARAMS.iterations
+        || params.parallelism > MAX_KDF_PARAMS.parallelism
+    {
+        return Err(CommandError::InvalidDocument {
+            message: format!(
+                "Key derivation parameters out of range (memory {} KiB, {} iterations, {} lanes)",
+                params.memory_kib, params.iterations, params.parallelism
+            ),
+        });
+    }
+    offset += 12;
+    let mut header = Header {
+        kind,
+        params,
+        salt: [0; SALT_LEN],
+        nonce: [0; NONCE_LEN],
+        key_check: [0; KEY_CHECK_LEN],
+    };
+    header
+        .salt
+        .copy_from_slice(&bytes[offset..offset + SALT_LEN]);
+    offset += SALT_LEN;
+    header
+        .nonce
+        .copy_from_slice(&bytes[offset..offset + NONCE_LEN]);
+    offset += NONCE_LEN;
+    header
+        .key_check
+        .copy_from_slice(&bytes[offset..offset + KEY_CHECK_LEN]);
+    Ok(header)
+}
+
+/// The document kind recorded in an encrypted container's header.
+pub fn encrypted_kind(bytes: &[u8]) -> Result<&'static str, CommandError> {
+    Ok(parse_header(bytes)?.kind)
+}
+
+fn decrypt_with_key(
+    bytes: &[u8],
+    header: &Header,
+    key: &DerivedKey,
+) -> Result<String, CommandError> {
+    if key.salt != header.salt
+        || key.params != header.params
+        || key_check(&key.key) != header.key_check
+    {
+        return Err(CommandError::WrongPassword);
+    }
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key.key));
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(&header.nonce),
+            Payload {
+                msg: &bytes[HEADER_LEN..],
+                aad: &bytes[..HEADER_LEN],
+            },
+        )
+        .map_err(|_| CommandError::Tampered)?;
+    String::from_utf8(plaintext).map_err(|_| CommandError::Tampered)
+}
+
+/// Decrypts a container, returning the document kind and contents along with the
+/// key, so the caller can cache it for later saves.
+pub fn decrypt(
+    bytes: &[u8],
+    password: &str,
+) -> Result<(&'static str, String, DerivedKey), CommandError> {
+    let header = parse_header(bytes)?;
+    let key = derive_key(password, header.salt, header.params)?;
+    let contents = decrypt_with_key(bytes, &header, &key)?;
+    Ok((header.kind, contents, key))
+}
+
+impl EncryptionSessions {
+    fn cached_key(&self, path: &Path) -> Option<DerivedKey> {
+        self.keys.lock().unwrap().get(path).cloned()
+    }
+
+    fn remember(&self, app: &AppHandle, path: &Path, key: DerivedKey) {
+        if load_settings(app).remember_encryption_keys {
+            let mut keys = self.keys.lock().unwrap();
+            let first = keys.is_empty();
+            keys.insert(path.to_path_buf(), key);
+            drop(keys);
+            if first {
+                crate::actions::notify_changed(app);
+            }
+        }
+    }
+
+    /// Refuses an attempt while a file is in its back-off window after repeated
+    /// wrong passwords. The delay doubles with each failure past `FREE_ATTEMPTS`.
+    fn check_rate_limit(&self, path: &Path) -> Result<(), CommandError> {
+        let failures = self.failures.lock().unwrap();
+        let Some((count, last)) = failures.get(path) else {
+            return Ok(());
+        };
+        if *count < FREE_ATTEMPTS {
+            return Ok(());
+        }
+        let delay =
+            Duration::from_secs(1 << (count - FREE_ATTEMPTS + 1).min(6)).min(MAX_ATTEMPT_DELAY);
+        let elapsed = last.elapsed();
+        if elapsed < delay {
+            return Err(CommandError::RateLimited {
+                retry_after_secs: (delay - elapsed).as_secs().max(1),
+            });
+        }
+        Ok(())
+    }
+
+    fn record_failure(&self, path: &Path) {
+        let mut failures = self.failures.lock().unwrap();
+        let entry = failures
+            .entry(path.to_path_buf())
+            .or_insert((0, Instant::now()));
+        entry.0 += 1;
+        entry.1 = Instant::now();
+    }
+
+    fn record_success(&self, path: &Path) {
+        self.failures.lock().unwrap().remove(path);
+    }
+}
+
+/// Asks the frontend for a password by emitting `password-required` and waits for
+/// the matching `submit_password` call. A dismissed prompt returns `Cancelled`, and so
+/// does one nobody answers within `PASSWORD_PROMPT_TIMEOUT`, e.g. because no window is
+/// listening.
+async fn prompt_password(
+    app: &AppHandle,
+    path: &Path,
+    purpose: &'static str,
+    error: Option<&'static str>,
+) -> Result<String, CommandError> {
+    let sessions = app.state::<EncryptionSessions>();
+    let operation_id = format!(
+        "password-{}",
+        sessions.next_operation.fetch_add(1, Ordering::SeqCst)
+    );
+    let (sender, mut receiver) = channel(1);
+    sessions
+        .prompts
+        .lock()
+        .unwrap()
+        .insert(operation_id.clone(), sender);
+    let _ = app.emit(
+        "password-required",
+        PasswordPrompt {
+            operation_id: operation_id.clone(),
+            path: path.to_string_lossy().to_string(),
+            purpose,
+            error,
+        },
+    );
+    let password = tokio::time::timeout(PASSWORD_PROMPT_TIMEOUT, receiver.recv()).await;
+    sessions.prompts.lock().unwrap().remove(&operation_id);
+    match password {
+        Ok(password) => password.ok_or(CommandError::Cancelled),
+        Err(_) => {
+            log::warn!(
+                "prompt_password: no answer for {} within {} s, giving up",
+                path.display(),
+                PASSWORD_PROMPT_TIMEOUT.as_secs()
+            );
+            Err(CommandError::Cancelled)
+        }
+    }
+}
+
+/// Reads a document from disk, decrypting it when it is an encrypted container.
+///
+/// The key comes from the session cache, then `password`, then a prompt that repeats
+/// after wrong passwords until the user cancels or attempts are rate limited.
+/// Returns the contents and whether the file was encrypted.
+pub async fn read_document(
+    app: &AppHandle,
+    path: &Path,
+    password: Option<String>,
+) -> Result<(String, bool), CommandError> {
+    let bytes = crate::large_files::read_decompressed(app, path).await?;
+    if !is_encrypted_bytes(&bytes) {
+        let (contents, _) = crate::text_encoding::decode(path, bytes)?;
+        return Ok((contents, false));
+    }
+
+    let header = parse_header(&bytes)?;
+    let sessions = app.state::<EncryptionSessions>();
+    if let Some(key) = sessions.cached_key(path) {
+        if let Ok(contents) = decrypt_with_key(&bytes, &header, &key) {
+            return Ok((contents, true));
+        }
+    }
+
+    let mut password = password;
+    let mut error = None;
+    loop {
+        sessions.check_rate_limit(path)?;
+        let (attempt, prompted) = match password.take() {
+            Some(password) => (password, false),
+            None => (prompt_password(app, path, "open", error).await?, true),
+        };
+        // Derivation takes a second or more, too long to hold up the async runtime.
+        let (salt, params) = (header.salt, header.params);
+        let result =
+            tauri::async_runtime::spawn_blocking(move || derive_key(&attempt, salt, params))
+                .await
+                .map_err(|error| CommandError::Io {
+                    message: error.to_string(),
+                })?
+                .and_then(|key| Ok((decrypt_with_key(&bytes, &header, &key)?, key)));
+        match result {
+            Ok((contents, key)) => {
+                sessions.record_success(path);
+                sessions.remember(app, path, key);
+                return Ok((contents, true));
+            }
+            Err(CommandError::WrongPassword) => {
+                log::info!("read_document: wrong password for {}", path.display());
+                sessions.record_failure(path);
+                // A password passed in by the caller is answered directly, not re-prompted.
+                if !prompted {
+                    return Err(CommandError::WrongPassword);
+                }
+                error = Some("wrong_password");
+            }
+            Err(other) => return Err(other),
+        }
+    }
+}
+
+/// Writes a document, re-encrypting it when `path` is an encrypted container. Uses
+/// the session's cached key for the file, prompting for a password if there is none.
+/// Other documents are gzipped when `compress` is set.
+pub async fn write_document(
+    app: &AppHandle,
+    kind: &str,
+    path: &Path,
+    contents: &str,
+    compress: bool,
+) -> Result<(), CommandError> {
+    if !is_encrypted_path(path) && compress {
+        let bytes = crate::gzip::compress(contents.as_bytes())
+            .map_err(|error| CommandError::from_io(error, path))?;
+        crate::file_watch::expect_write(app, path, &bytes);
+        crate::write_binary_file(path, &bytes)?;
+        return Ok(());
+    }
+    if !is_encrypted_path(path) {
+        crate::file_watch::expect_write(app, path, contents.as_bytes());
+        write_file(path, contents)?;
+        return Ok(());
+    }
+    let sessions = app.state::<EncryptionSessions>();
+    let key = match sessions.cached_key(path) {
+        Some(key) => key,
+        None => new_key(prompt_password(app, path, "save", None).await?).await?,
+    };
+    let bytes = encrypt_with_key(kind, contents, &key)?;
+    crate::file_watch::expect_write(app, path, &bytes);
+    crate::atomic_write::write_atomic(path, &bytes)?;
+    sessions.remember(app, path, key);
+    Ok(())
+}
+
+/// Encrypts `contents` with a key derived from `password` and writes it to `path`,
+/// adding the `.enc` extension if it's missing. The plaintext is never written.
+/// Like other saves it is refused when the file changed since `expected_mtime`
+/// unless `force`, and keeps the replaced file as a backup unless `keep_backup` is
+/// `false`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn save_encrypted_file(
+    app: AppHandle,
+    kind: String,
+    contents: String,
+    password: String,
+    path: String,
+    expected_mtime: Option<u64>,
+    force: Option<bool>,
+    keep_backup: Option<bool>,
+) -> Result<SaveFileResponse, CommandError> {
+    if password.is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "An encrypted file needs a password".to_string(),
+        });
+    }
+    let mut path = PathBuf::from(path);
+    if !is_encrypted_path(&path) {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{ENCRYPTED_EXTENSION}"));
+        path = PathBuf::from(name);
+    }
+
+    crate::read_only::ensure_writable(&app, &path)?;
+    crate::check_conflict(&path, expected_mtime, force.unwrap_or(false))?;
+
+    let key = new_key(password).await?;
+    let bytes = encrypt_with_key(&kind, &contents, &key)?;
+    crate::keep_backup(&path, keep_backup)?;
+    let capture = crate::local_history::capture_before_save(&app, &path);
+    crate::file_watch::expect_write(&app, &path, &bytes);
+    crate::atomic_write::write_atomic(&path, &bytes)?;
+    crate::local_history::finish_save(&app, &path, capture);
+    crate::checksums::after_save(&app, &path);
+    app.state::<EncryptionSessions>().remember(&app, &path, key);
+    let path_string = path.to_string_lossy().to_string();
+    let name = file_name(&path);
+    update_recents(&app, &kind, &path_string, name.clone());
+    Ok(SaveFileResponse {
+        path: path_string,
+        name,
+        mtime: crate::modified_millis(&path),
+        open_in_other_windows: Vec::new(),
+    })
+}
+
+#[tauri::command]
+pub async fn open_encrypted_file(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenPathResponse, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    crate::open_documents::check_not_open_elsewhere(
+        &app,
+        window.label(),
+        &path_buf,
+        allow_duplicate.unwrap_or(false),
+    )?;
+    let kind = encrypted_kind(&crate::large_files::read(&app, &path_buf).await?)?;
+    let (contents, encrypted) = read_document(&app, &path_buf, password).await?;
+    let name = file_name(&path_buf);
+    update_recents(&app, kind, &path, name.clone());
+    crate::volumes::track(&app, &path_buf);
+    crate::open_documents::register(&app, window.label(), kind, &path_buf);
+    let schema = match kind {
+        "excalidraw" => crate::scene_schema::check_opened(&app, &path, &contents),
+        _ => None,
+    };
+    Ok(OpenPathResponse {
+        kind,
+        file: OpenFileResponse {
+            read_only: crate::read_only::on_open(&app, &path_buf, open_read_only),
+            writable_dir: Some(crate::read_only::dir_writable(&path_buf)),
+            meta: crate::file_info::file_meta(&path_buf),
+            large_file: crate::large_files::is_large(&app, &path_buf),
+            compressed: crate::gzip::is_gzip_file(&path_buf),
+            mtime: crate::modified_millis(&path_buf),
+            path,
+            name,
+            contents,
+            encrypted,
+            schema,
+            ..Default::default()
+        },
+    })
+}
+
+/// Answers a `password-required` prompt. `None` dismisses it.
+#[tauri::command]
+pub fn submit_password(
+    app: AppHandle,
+    operation_id: String,
+    password: Option<String>,
+) -> Result<(), CommandError> {
+    let sender = app
+        .state::<EncryptionSessions>()
+        .prompts
+        .lock()
+        .unwrap()
+        .remove(&operation_id)
+        .ok_or_else(|| CommandError::InvalidArgument {
+            message: format!("No password prompt \"{operation_id}\" is waiting"),
+        })?;
+    if let Some(password) = password {
+        let _ = sender.try_send(password);
+    }
+    Ok(())
+}
+
+/// Whether any encrypted document's key is cached, i.e. there is something to lock.
+pub fn has_cached_keys(app: &AppHandle) -> bool {
+    !app.state::<EncryptionSessions>()
+        .keys
+        .lock()
+        .unwrap()
+        .is_empty()
+}
+
+/// Whether `password` derives any of `keys` with that key's own salt and parameters.
+fn matches_any_key(password: &str, keys: &[DerivedKey]) -> Result<bool, CommandError> {
+    for key in keys {
+        if derive_key(password, key.salt, key.params)?.key == key.key {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Checks `password` against the keys cached this session, with the same back-off
+/// after repeated failures as opening a file. Callers check `has_cached_keys` first;
+/// with nothing cached there is no password to compare against.
+pub fn verify_session_password(app: &AppHandle, password: &str) -> Result<(), CommandError> {
+    let sessions = app.state::<EncryptionSessions>();
+    // Failures are counted under a name no real document can have.
+    let attempts = Path::new("\0session-password");
+    sessions.check_rate_limit(attempts)?;
+    let keys: Vec<DerivedKey> = sessions.keys.lock().unwrap().values().cloned().collect();
+    if matches_any_key(password, &keys)? {
+        sessions.record_success(attempts);
+        Ok(())
+    } else {
+        sessions.record_failure(attempts);
+        Err(CommandError::WrongPassword)
+    }
+}
+
+/// Forgets every cached key, so encrypted documents need their password again.
+#[tauri::command]
+pub fn lock_encrypted_documents(app: AppHandle) {
+    app.state::<EncryptionSessions>()
+        .keys
+        .lock()
+        .unwrap()
+        .clear();
+    crate::actions::notify_changed(&app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn test_key(password: &str) -> DerivedKey {
+        derive_key(password, [7; SALT_LEN], TEST_PARAMS).unwrap()
+    }
+
+    #[test]
+    fn session_password_matches_any_cached_key() {
+        let other = derive_key("correct horse", [9; SALT_LEN], TEST_PARAMS).unwrap();
+        let keys = [test_key("hunter2"), other];
+        assert!(matches_any_key("hunter2", &keys).unwrap());
+        assert!(matches_any_key("correct horse", &keys).unwrap());
+        assert!(!matches_any_key("hunter3", &keys).unwrap());
+        assert!(!matches_any_key("hunter2", &[]).unwrap());
+    }
+
+    #[test]
+    fn headers_asking_for_too_much_work_are_refused() {
+        let bytes = encrypt_with_key("mermaid", "flowchart TD\n", &test_key("hunter2")).unwrap();
+        let params_at = MAGIC.len() + 2;
+        for (offset, value) in [
+            (0, MAX_KDF_PARAMS.memory_kib + 1),
+            (4, MAX_KDF_PARAMS.iterations + 1),
+            (8, u32::MAX),
+        ] {
+            let mut crafted = bytes.clone();
+            crafted[params_at + offset..params_at + offset + 4]
+                .copy_from_slice(&value.to_le_bytes());
+            assert_eq!(
+                decrypt(&crafted, "hunter2").err().unwrap().code(),
+                "invalid_document"
+            );
+        }
+        assert!(parse_header(&bytes).is_ok());
+    }
+
+    #[test]
+    fn round_trips() {
+        let bytes = encrypt_with_key("mermaid", "flowchart TD\n", &test_key("hunter2")).unwrap();
+        let (kind, contents, _) = decrypt(&bytes, "hunter2").unwrap();
+        assert_eq!(kind, "mermaid");
+        assert_eq!(contents, "flowchart TD\n");
+    }
+
+    #[test]
+    fn cached_key_decrypts_later_saves() {
+        let key = test_key("pw");
+        let first = encrypt_with_key("excalidraw", "{}", &key).unwrap();
+        let second = encrypt_with_key("excalidraw", "{\"a\":1}", &key).unwrap();
+        assert_ne!(first[..HEADER_LEN], second[..HEADER_LEN]);
+        let header = parse_header(&second).unwrap();
+        assert_eq!(
+            decrypt_with_key(&second, &header, &key).unwrap(),
+            "{\"a\":1}"
+        );
+    }
+
+    #[test]
+    fn wrong_password_is_reported_as_such() {
+        let bytes = encrypt_with_key("excalidraw", "{}", &test_key("right")).unwrap();
+        assert!(matches!(
+            decrypt(&bytes, "wrong"),
+            Err(CommandError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_reported_as_such() {
+        let mut bytes =
+            encrypt_with_key("excalidraw", "{\"elements\":[]}", &test_key("pw")).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        assert!(matches!(decrypt(&bytes, "pw"), Err(CommandError::Tampered)));
+    }
+
+    #[test]
+    fn tampered_header_is_detected() {
+        let mut bytes = encrypt_with_key("mermaid", "graph LR", &test_key("pw")).unwrap();
+        bytes[MAGIC.len() + 1] = 0;
+        assert!(matches!(decrypt(&bytes, "pw"), Err(CommandError::Tampered)));
+    }
+}
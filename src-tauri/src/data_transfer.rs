@@ -0,0 +1,778 @@
+use crate::dialogs::pick_save_path;
+use crate::error::CommandError;
+use crate::operations::Operation;
+use crate::recents_store::RecentsStore;
+use crate::settings::load_settings;
+use crate::templates::{meta_path, template_path};
+use crate::{app_data_dir, modify_recents, now_epoch, RecentItem};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Version of the export archive layout itself.
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "excalibur-export.json";
+/// Most an archive may unpack to, so a small, hostile zip can't fill memory.
+const MAX_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+pub const LOGS_DIR: &str = "logs";
+const SECRETS_DIR: &str = "secrets";
+/// Caches that are rebuilt on demand and tied to paths on the exporting machine.
+pub const CACHE_DIRS: [&str; 1] = ["search-index"];
+/// On-disk schema version of each kind of data in the data directory. Bump the
+/// matching entry whenever a file format changes incompatibly.
+const SCHEMA_VERSIONS: [(&str, u32); 5] = [
+    ("recents", 1),
+    ("settings", 1),
+    ("templates", 1),
+    ("search_history", 1),
+    ("workspaces", crate::workspaces::WORKSPACES_VERSION),
+];
+
+/// A file read from an export: its path relative to the data directory, and bytes.
+type ArchiveFile = (String, Vec<u8>);
+
+#[derive(Serialize, Deserialize)]
+struct ExportManifest {
+    format_version: u32,
+    app_version: String,
+    exported_at: u64,
+    schema_versions: BTreeMap<String, u32>,
+    files: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ExportResponse {
+    path: String,
+    files: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct RenamedEntry {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportResponse {
+    imported: Vec<String>,
+    /// Templates that clashed with a different local template of the same name.
+    renamed: Vec<RenamedEntry>,
+    /// Files that already exist locally and were kept as they are.
+    skipped: Vec<String>,
+    /// Set when live state was rewritten and the app should be restarted.
+    restart_required: bool,
+}
+
+fn zip_error(error: zip::result::ZipError) -> CommandError {
+    CommandError::Io {
+        message: error.to_string(),
+    }
+}
+
+fn relative_name(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn write_bytes(path: &Path, contents: &[u8]) -> Result<(), CommandError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Lists the files under `dir` relative to `root`, skipping excluded top-level directories.
+fn collect_files(root: &Path, dir: &Path, excluded: &[&str], files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let name = relative_name(relative);
+        if excluded.contains(&name.as_str()) {
+            continue;
+        }
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => collect_files(root, &path, excluded, files),
+            Ok(file_type) if file_type.is_file() => files.push(name),
+            _ => {}
+        }
+    }
+}
+
+/// Zips the data directory, with a manifest, into a file chosen by the user.
+///
+/// Logs and stored secrets are left out unless explicitly included; search
+/// indexes are always left out since they are rebuilt on demand. A cancelled
+/// export removes the partly written archive.
+#[tauri::command]
+pub async fn export_app_data(
+    app: AppHandle,
+    target: Option<String>,
+    include_logs: Option<bool>,
+    include_secrets: Option<bool>,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> Result<ExportResponse, CommandError> {
+    let target = match target {
+        Some(target) => PathBuf::from(target),
+        None => {
+            let suggested_name = format!(
+                "excalibur-data-{}.zip",
+                chrono::Local::now().format("%Y-%m-%d")
+            );
+            pick_save_path(&app, "Excalibur data", &["zip"], suggested_name)
+                .await?
+                .ok_or(CommandError::Cancelled)?
+        }
+    };
+
+    let mut excluded: Vec<&str> = CACHE_DIRS.to_vec();
+    if !include_logs.unwrap_or(false) {
+        excluded.push(LOGS_DIR);
+    }
+    if !include_secrets.unwrap_or(false) {
+        excluded.push(SECRETS_DIR);
+    }
+    let operation = Operation::start(&app, "data-export", operation_id, true)
+        .notify_when_done(notify_when_done);
+    let outcome = write_data_archive(&app, &target, &excluded, &operation);
+    operation.finish(
+        &outcome,
+        outcome.as_ref().map_or(0, |(files, _)| *files as u64),
+    );
+    let summary = match &outcome {
+        Ok((files, _)) => format!("Exported {files} files to {}", target.display()),
+        Err(CommandError::Cancelled) => "Data export was cancelled".to_string(),
+        Err(error) => format!("Data export failed: {error}"),
+    };
+    operation.notify_done(&summary, target.to_string_lossy());
+    let (files, bytes) = outcome?;
+
+    log::info!(
+        "export_app_data: wrote {} files to {}",
+        files,
+        target.display()
+    );
+    Ok(ExportResponse {
+        path: target.to_string_lossy().to_string(),
+        files,
+        bytes,
+    })
+}
+
+/// Zips the data directory, minus the `excluded` top-level entries, into `target`
+/// together with a manifest. Returns the number of files and their total size.
+///
+/// If writing fails or `operation` is cancelled, the partial archive is removed.
+pub fn write_data_archive(
+    app: &AppHandle,
+    target: &Path,
+    excluded: &[&str],
+    operation: &Operation,
+) -> Result<(usize, u64), CommandError> {
+    let outcome = write_archive_contents(app, target, excluded, operation);
+    if outcome.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    outcome
+}
+
+fn write_archive_contents(
+    app: &AppHandle,
+    target: &Path,
+    excluded: &[&str],
+    operation: &Operation,
+) -> Result<(usize, u64), CommandError> {
+    let data_dir = app_data_dir(app);
+    let mut files = Vec::new();
+    collect_files(&data_dir, &data_dir, excluded, &mut files);
+    // The archive may be written into the data directory itself.
+    if let Ok(relative) = target.strip_prefix(&data_dir) {
+        let relative = relative_name(relative);
+        files.retain(|file| *file != relative);
+    }
+    files.sort();
+
+    let manifest = ExportManifest {
+        format_version: FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        exported_at: now_epoch(),
+        schema_versions: SCHEMA_VERSIONS
+            .iter()
+            .map(|(name, version)| (name.to_string(), *version))
+            .collect(),
+        files: files.clone(),
+    };
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = ZipWriter::new(fs::File::create(target)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut bytes = 0;
+    let total = files.len() as u64;
+    for (done, file) in files.iter().enumerate() {
+        operation.check()?;
+        operation.progress("writing", done as u64, Some(total), Some(file));
+        let contents = fs::read(data_dir.join(file))?;
+        bytes += contents.len() as u64;
+        writer
+            .start_file(file.as_str(), options)
+            .map_err(zip_error)?;
+        writer.write_all(&contents)?;
+    }
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    writer
+        .start_file(MANIFEST_NAME, options)
+        .map_err(zip_error)?;
+    writer.write_all(&manifest)?;
+    writer.finish().map_err(zip_error)?;
+    Ok((files.len(), bytes))
+}
+
+fn validate_manifest(manifest: &ExportManifest) -> Result<(), CommandError> {
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(CommandError::InvalidArgument {
+            message: format!(
+                "This export was made by a newer version of Excalibur ({}); update before importing it",
+                manifest.app_version
+            ),
+        });
+    }
+    for (name, supported) in SCHEMA_VERSIONS {
+        if let Some(version) = manifest.schema_versions.get(name) {
+            if *version > supported {
+                return Err(CommandError::InvalidArgument {
+                    message: format!(
+                        "This export stores {name} in a newer format (v{version}) than this version of Excalibur supports (v{supported})"
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the manifest and every file in the archive, so nothing is touched on
+/// disk until the whole export is known to be readable. Archives unpacking to more
+/// than `max_bytes` in all are refused with `FileTooLarge`.
+fn read_archive(
+    path: &Path,
+    max_bytes: u64,
+) -> Result<(ExportManifest, Vec<ArchiveFile>), CommandError> {
+    let too_large = |size| CommandError::FileTooLarge {
+        path: path.to_string_lossy().to_string(),
+        size,
+        limit: max_bytes,
+    };
+    let mut archive = ZipArchive::new(fs::File::open(path)?).map_err(zip_error)?;
+    let manifest: ExportManifest = {
+        let entry = archive
+            .by_name(MANIFEST_NAME)
+            .map_err(|_| CommandError::InvalidArgument {
+                message: "Not an Excalibur data export (missing manifest)".to_string(),
+            })?;
+        serde_json::from_reader(entry.take(max_bytes)).map_err(|error| {
+            CommandError::InvalidArgument {
+                message: format!("Unreadable export manifest: {error}"),
+            }
+        })?
+    };
+    validate_manifest(&manifest)?;
+
+    let mut files = Vec::new();
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(zip_error)?;
+        if entry.is_dir() {
+            continue;
+        }
+        // `enclosed_name` rejects absolute paths and `..` components.
+        let Some(relative) = entry.enclosed_name() else {
+            log::warn!("import_app_data: skipping unsafe entry {}", entry.name());
+            continue;
+        };
+        let relative = relative_name(&relative);
+        if relative == MANIFEST_NAME {
+            continue;
+        }
+        // Sizes in the zip's headers can lie; count what actually comes out.
+        let mut contents = Vec::new();
+        (&mut entry)
+            .take(max_bytes - total + 1)
+            .read_to_end(&mut contents)?;
+        total += contents.len() as u64;
+        if total > max_bytes {
+            return Err(too_large(total));
+        }
+        files.push((relative, contents));
+    }
+    Ok((manifest, files))
+}
+
+/// Merges imported recents into the local list, keeping the newer entry when both
+/// sides have the same kind and path.
+fn merge_recents(app: &AppHandle, contents: &[u8]) -> Result<(), CommandError> {
+    let imported: Vec<RecentItem> =
+        serde_json::from_slice(contents).map_err(|error| CommandError::InvalidDocument {
+            message: format!("recents.json: {error}"),
+        })?;
+    let limit = load_settings(app).recents_limit;
+    modify_recents(app, |recents| merge_recent_items(recents, imported, limit));
+    Ok(())
+}
+
+fn merge_recent_items(recents: &mut Vec<RecentItem>, imported: Vec<RecentItem>, limit: usize) {
+    let mut merged: HashMap<(String, String), RecentItem> = HashMap::new();
+    for item in recents.drain(..).chain(imported) {
+        let key = (item.kind.clone(), item.path.clone());
+        match merged.get(&key) {
+            Some(existing) if existing.updated_at >= item.updated_at => {}
+            _ => {
+                merged.insert(key, item);
+            }
+        }
+    }
+    recents.extend(merged.into_values());
+    recents.sort_by_key(|item| std::cmp::Reverse(item.updated_at));
+    crate::recent_pins::cap(recents, limit);
+}
+
+/// Splits `templates/<kind>/<name>.<ext>` into kind and name. Sidecar metadata
+/// files and unknown kinds return `None`.
+fn template_entry(relative: &str) -> Option<(&str, &str)> {
+    let mut parts = relative.split('/');
+    let (Some("templates"), Some(kind), Some(file), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    if !matches!(kind, "excalidraw" | "mermaid") || file.ends_with(".meta.json") {
+        return None;
+    }
+    let (name, _) = file.rsplit_once('.')?;
+    Some((kind, name))
+}
+
+/// The first of `name-2`, `name-3`, ... that isn't `taken`.
+fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    (2..)
+        .map(|suffix| format!("{name}-{suffix}"))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn merge_template(
+    app: &AppHandle,
+    kind: &str,
+    name: &str,
+    contents: &[u8],
+    meta: Option<&[u8]>,
+    response: &mut ImportResponse,
+    relative: &str,
+) -> Result<(), CommandError> {
+    let mut target = template_path(app, kind, name)?;
+    if target.is_file() {
+        if fs::read(&target)? == contents {
+            response.skipped.push(relative.to_string());
+            return Ok(());
+        }
+        let free = free_name(name, |candidate| {
+            template_path(app, kind, candidate).is_ok_and(|path| path.exists())
+        });
+        target = template_path(app, kind, &free)?;
+        response.renamed.push(RenamedEntry {
+            from: name.to_string(),
+            to: target
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        });
+    }
+    write_bytes(&target, contents)?;
+    if let Some(meta) = meta {
+        write_bytes(&meta_path(&target), meta)?;
+    }
+    response.imported.push(relative.to_string());
+    Ok(())
+}
+
+fn merge_import(
+    app: &AppHandle,
+    data_dir: &Path,
+    files: &[ArchiveFile],
+) -> Result<ImportResponse, CommandError> {
+    let mut response = ImportResponse::default();
+    let by_name: HashMap<&str, &[u8]> = files
+        .iter()
+        .map(|(name, contents)| (name.as_str(), contents.as_slice()))
+        .collect();
+    // Sidecars are written together with their template, under its final name.
+    let template_metas: HashSet<String> = files
+        .iter()
+        .filter(|(relative, _)| template_entry(relative).is_some())
+        .map(|(relative, _)| relative_name(&meta_path(Path::new(relative))))
+        .collect();
+
+    for (relative, contents) in files {
+        if relative == "recents.json" {
+            merge_recents(app, contents)?;
+            response.imported.push(relative.clone());
+            continue;
+        }
+        if let Some((kind, name)) = template_entry(relative) {
+            let meta_name = relative_name(&meta_path(Path::new(relative)));
+            let meta = by_name.get(meta_name.as_str()).copied();
+            merge_template(app, kind, name, contents, meta, &mut response, relative)?;
+            continue;
+        }
+        if template_metas.contains(relative) {
+            continue;
+        }
+
+        let target = data_dir.join(relative);
+        if target.exists() {
+            response.skipped.push(relative.clone());
+        } else {
+            write_bytes(&target, contents)?;
+            response.imported.push(relative.clone());
+        }
+    }
+    Ok(response)
+}
+
+/// `<data dir>-<suffix>` next to the data directory, on the same volume so entries
+/// move between them by renaming.
+pub fn sibling_dir(data_dir: &Path, suffix: &str) -> PathBuf {
+    let name = data_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "excalibur".to_string());
+    data_dir.with_file_name(format!("{name}-{suffix}"))
+}
+
+fn is_log_entry(relative: &str) -> bool {
+    relative
+        .split('/')
+        .next()
+        .is_some_and(|first| first == LOGS_DIR)
+}
+
+/// Moves every entry of `from` except logs into `to`, recording each name moved.
+fn move_entries(from: &Path, to: &Path, moved: &mut Vec<OsString>) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let name = entry?.file_name();
+        if name == LOGS_DIR {
+            continue;
+        }
+        fs::rename(from.join(&name), to.join(&name))?;
+        moved.push(name);
+    }
+    Ok(())
+}
+
+fn remove_entry(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Replaces the data directory (except logs) with `files`. They are written to a
+/// staging folder first; only once all of them are there is the current data moved
+/// to `aside` and the staged data moved in. If that swap fails, the data directory
+/// is put back as it was. Logs in the archive are not imported, since the current
+/// log is in use.
+fn replace_import(
+    data_dir: &Path,
+    files: &[ArchiveFile],
+    aside: &Path,
+) -> Result<ImportResponse, CommandError> {
+    let staging = sibling_dir(data_dir, &format!("import-{}", crate::now_epoch_millis()));
+    let outcome = stage_and_swap(data_dir, &staging, files, aside);
+    let _ = fs::remove_dir_all(&staging);
+    if outcome.is_err() {
+        // Empty unless something set aside couldn't be moved back.
+        let _ = fs::remove_dir(aside);
+    }
+    outcome
+}
+
+fn stage_and_swap(
+    data_dir: &Path,
+    staging: &Path,
+    files: &[ArchiveFile],
+    aside: &Path,
+) -> Result<ImportResponse, CommandError> {
+    let mut response = ImportResponse {
+        restart_required: true,
+        ..Default::default()
+    };
+    fs::create_dir_all(staging)?;
+    for (relative, contents) in files {
+        if is_log_entry(relative) {
+            response.skipped.push(relative.clone());
+            continue;
+        }
+        write_bytes(&staging.join(relative), contents)?;
+        response.imported.push(relative.clone());
+    }
+
+    fs::create_dir_all(data_dir)?;
+    let mut set_aside = Vec::new();
+    let mut moved_in = Vec::new();
+    let swapped = move_entries(data_dir, aside, &mut set_aside)
+        .and_then(|()| move_entries(staging, data_dir, &mut moved_in));
+    if let Err(error) = swapped {
+        for name in &moved_in {
+            let _ = remove_entry(&data_dir.join(name));
+        }
+        for name in &set_aside {
+            let _ = fs::rename(aside.join(name), data_dir.join(name));
+        }
+        return Err(error.into());
+    }
+    Ok(response)
+}
+
+/// Replaces the contents of `data_dir` (except logs) with an archive written by
+/// `write_data_archive`, moving the current data to `aside`. The archive is read
+/// and checked in full first, so a missing or damaged one changes nothing.
+pub fn restore_archive_into(
+    data_dir: &Path,
+    path: &Path,
+    aside: &Path,
+) -> Result<ImportResponse, CommandError> {
+    let (_, files) = read_archive(path, MAX_ARCHIVE_BYTES)?;
+    replace_import(data_dir, &files, aside)
+}
+
+/// Imports an archive written by `export_app_data`.
+///
+/// `"merge"` keeps local data and adds what's missing: recents are merged by
+/// kind and path (newer wins) and clashing templates are imported under a
+/// suffixed name. `"replace"` swaps the data directory (except logs) for the
+/// archive's contents and asks for a restart.
+#[tauri::command]
+pub fn import_app_data(
+    app: AppHandle,
+    path: String,
+    mode: String,
+) -> Result<ImportResponse, CommandError> {
+    let (manifest, files) = read_archive(Path::new(&path), MAX_ARCHIVE_BYTES)?;
+    let data_dir = app_data_dir(&app);
+    log::info!(
+        "import_app_data: {} files from {} (exported by {}) in {} mode",
+        files.len(),
+        path,
+        manifest.app_version,
+        mode
+    );
+
+    match mode.as_str() {
+        "merge" => merge_import(&app, &data_dir, &files),
+        "replace" => {
+            let aside = sibling_dir(&data_dir, &format!("replaced-{}", now_epoch()));
+            let response = replace_import(&data_dir, &files, &aside)?;
+            // The replaced data was only kept in case the swap had to be undone.
+            let _ = fs::remove_dir_all(&aside);
+            app.state::<RecentsStore>().reload();
+            Ok(response)
+        }
+        other => Err(CommandError::InvalidArgument {
+            message: format!("Unknown import mode \"{other}\"; expected \"merge\" or \"replace\""),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn manifest(format_version: u32, schema_versions: &[(&str, u32)]) -> ExportManifest {
+        ExportManifest {
+            format_version,
+            app_version: "9.9.9".to_string(),
+            exported_at: 0,
+            schema_versions: schema_versions
+                .iter()
+                .map(|(name, version)| (name.to_string(), *version))
+                .collect(),
+            files: Vec::new(),
+        }
+    }
+
+    fn recent(path: &str, updated_at: u64) -> RecentItem {
+        RecentItem {
+            kind: "mermaid".to_string(),
+            path: path.to_string(),
+            name: Some(format!("{path}@{updated_at}")),
+            updated_at,
+            root: None,
+            recorded_size: None,
+            recorded_modified_at: None,
+            encrypted: false,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn newer_exports_are_rejected() {
+        assert!(validate_manifest(&manifest(FORMAT_VERSION, &[("recents", 1)])).is_ok());
+        // Kinds of data this version doesn't know about are ignored.
+        assert!(validate_manifest(&manifest(FORMAT_VERSION, &[("sketches", 7)])).is_ok());
+        for newer in [
+            manifest(FORMAT_VERSION + 1, &[]),
+            manifest(FORMAT_VERSION, &[("settings", 2)]),
+        ] {
+            assert_eq!(
+                validate_manifest(&newer).unwrap_err().code(),
+                "invalid_argument"
+            );
+        }
+    }
+
+    #[test]
+    fn archives_without_a_manifest_are_rejected() {
+        let dir = temp_dir("transfer", "no-manifest");
+        let archive = dir.join("data.zip");
+        let mut writer = ZipWriter::new(fs::File::create(&archive).unwrap());
+        writer
+            .start_file("settings.json", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"{}").unwrap();
+        writer.finish().unwrap();
+
+        let error = read_archive(&archive, MAX_ARCHIVE_BYTES).err().unwrap();
+        assert_eq!(error.code(), "invalid_argument");
+        assert!(error.to_string().contains("missing manifest"), "{error}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn archives_that_unpack_too_far_are_rejected() {
+        let dir = temp_dir("transfer", "too-large");
+        let archive = dir.join("data.zip");
+        let mut writer = ZipWriter::new(fs::File::create(&archive).unwrap());
+        writer
+            .start_file(MANIFEST_NAME, SimpleFileOptions::default())
+            .unwrap();
+        serde_json::to_writer(&mut writer, &manifest(FORMAT_VERSION, &[])).unwrap();
+        for name in ["templates/mermaid/a.mmd", "templates/mermaid/b.mmd"] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&[b'a'; 4096]).unwrap();
+        }
+        writer.finish().unwrap();
+        assert!(fs::metadata(&archive).unwrap().len() < 4096);
+
+        let (_, files) = read_archive(&archive, 8192).unwrap();
+        assert_eq!(files.len(), 2);
+        match read_archive(&archive, 8191) {
+            Err(CommandError::FileTooLarge { size, limit, .. }) => {
+                assert_eq!((size, limit), (8192, 8191))
+            }
+            other => panic!("expected FileTooLarge, got {:?}", other.err()),
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn merged_recents_keep_the_newer_entry() {
+        let mut recents = vec![recent("/work/a.mmd", 10), recent("/work/b.mmd", 30)];
+        let imported = vec![
+            recent("/work/a.mmd", 20),
+            recent("/work/b.mmd", 5),
+            recent("/work/c.mmd", 1),
+        ];
+        merge_recent_items(&mut recents, imported, 10);
+
+        let names: Vec<&str> = recents
+            .iter()
+            .map(|item| item.name.as_deref().unwrap())
+            .collect();
+        assert_eq!(names, ["/work/b.mmd@30", "/work/a.mmd@20", "/work/c.mmd@1"]);
+    }
+
+    #[test]
+    fn clashing_templates_get_the_next_free_suffix() {
+        let taken = ["flow", "flow-2"];
+        assert_eq!(free_name("flow", |name| taken.contains(&name)), "flow-3");
+        assert_eq!(free_name("plan", |name| taken.contains(&name)), "plan-2");
+    }
+
+    #[test]
+    fn replacing_swaps_in_the_staged_data_and_keeps_logs() {
+        let dir = temp_dir("transfer", "replace");
+        let data_dir = dir.join("data");
+        write_bytes(&data_dir.join("settings.json"), b"old").unwrap();
+        write_bytes(&data_dir.join("templates/mermaid/old.mmd"), b"graph TD").unwrap();
+        write_bytes(&data_dir.join("logs/excalibur.log"), b"live").unwrap();
+        let aside = dir.join("aside");
+
+        let files = vec![
+            ("settings.json".to_string(), b"new".to_vec()),
+            ("logs/excalibur.log".to_string(), b"exported".to_vec()),
+        ];
+        let response = replace_import(&data_dir, &files, &aside).unwrap();
+
+        assert_eq!(response.imported, ["settings.json"]);
+        assert_eq!(response.skipped, ["logs/excalibur.log"]);
+        assert_eq!(fs::read(data_dir.join("settings.json")).unwrap(), b"new");
+        assert!(!data_dir.join("templates").exists());
+        assert_eq!(
+            fs::read(data_dir.join("logs/excalibur.log")).unwrap(),
+            b"live"
+        );
+        assert_eq!(fs::read(aside.join("settings.json")).unwrap(), b"old");
+        assert!(aside.join("templates/mermaid/old.mmd").is_file());
+        // Nothing is left staged next to the data directory.
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(leftovers.len(), 2, "{leftovers:?}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_failed_staging_leaves_the_data_alone() {
+        let dir = temp_dir("transfer", "replace-failed");
+        let data_dir = dir.join("data");
+        write_bytes(&data_dir.join("settings.json"), b"old").unwrap();
+        let aside = dir.join("aside");
+
+        // A file and a folder with the same name can't both be staged.
+        let files = vec![
+            ("templates".to_string(), b"a file".to_vec()),
+            ("templates/mermaid/a.mmd".to_string(), b"graph TD".to_vec()),
+        ];
+        assert!(replace_import(&data_dir, &files, &aside).is_err());
+
+        assert_eq!(fs::read(data_dir.join("settings.json")).unwrap(), b"old");
+        assert!(!aside.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,539 @@
+use crate::cli::NewDocument;
+use crate::detect::detect_content_kind;
+use crate::error::CommandError;
+use crate::event_queue::emit_when_ready;
+use base64::Engine;
+use serde::Serialize;
+use tauri::AppHandle;
+
+/// Longest URL accepted from the OS; anything longer is rejected unparsed.
+const MAX_URL_LEN: usize = 8 * 1024;
+/// How much of a rejected URL is echoed back in logs and events.
+const MAX_REPORTED_URL_LEN: usize = 256;
+/// Largest document an `excalibur://new` link may create, inline or fetched.
+const MAX_NEW_CONTENT_BYTES: usize = 2 * 1024 * 1024;
+/// Longest title kept from an `excalibur://new` link.
+const MAX_TITLE_CHARS: usize = 200;
+
+#[derive(Serialize, Clone)]
+struct DeepLinkRejected {
+    url: String,
+    reason: String,
+}
+
+/// Decodes `%XX` escapes. Malformed escapes are kept literally, as browsers do.
+fn percent_decode(input: &str) -> Result<String, String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| input.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| "the path is not valid UTF-8".to_string())
+}
+
+fn is_drive_letter(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && (bytes[1] == b':' || bytes[1] == b'|')
+}
+
+/// Converts a `file://` URL to a local path.
+///
+/// `#` and `?` have no meaning in file URLs, so an unescaped one is taken to be
+/// part of the file name and kept. Hosts other than `localhost` are UNC shares,
+/// which only Windows can open.
+fn file_url_to_path(url: &url::Url, windows: bool) -> Result<String, String> {
+    let mut raw = url.path().to_string();
+    if let Some(query) = url.query() {
+        raw.push('?');
+        raw.push_str(query);
+    }
+    if let Some(fragment) = url.fragment() {
+        raw.push('#');
+        raw.push_str(fragment);
+    }
+    let path = percent_decode(&raw)?;
+    if path.contains('\0') {
+        return Err("the path contains a NUL character".to_string());
+    }
+
+    let host = url
+        .host_str()
+        .filter(|host| !host.is_empty() && !host.eq_ignore_ascii_case("localhost"));
+    if !windows {
+        if host.is_some() {
+            return Err(
+                "file URLs on another host (UNC shares) can only be opened on Windows".to_string(),
+            );
+        }
+        if path == "/" || path.is_empty() {
+            return Err("the URL has no file path".to_string());
+        }
+        return Ok(path);
+    }
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    if let Some(host) = host {
+        return Ok(format!("\\\\{host}\\{}", segments.join("\\")));
+    }
+    match segments.first() {
+        Some(drive) if is_drive_letter(drive) => {
+            let drive = format!("{}:", &drive[..1]);
+            let rest = &segments[1..];
+            Ok(format!("{drive}\\{}", rest.join("\\")))
+        }
+        _ => Err("the URL has no drive letter".to_string()),
+    }
+}
+
+/// Works out the file a deep link points at: a `file://` URL, or
+/// `excalibur://open?path=<encoded path>`.
+pub fn path_from_url(raw: &str, windows: bool) -> Result<String, String> {
+    if raw.len() > MAX_URL_LEN {
+        return Err(format!(
+            "the URL is {} characters long; the limit is {MAX_URL_LEN}",
+            raw.len()
+        ));
+    }
+    let url = url::Url::parse(raw).map_err(|error| format!("not a valid URL: {error}"))?;
+    match url.scheme() {
+        "file" => file_url_to_path(&url, windows),
+        "excalibur" => {
+            if url.host_str() != Some("open") {
+                return Err(format!(
+                    "unknown excalibur:// action \"{}\"",
+                    url.host_str().unwrap_or_default()
+                ));
+            }
+            let path = url
+                .query_pairs()
+                .find(|(key, _)| key == "path")
+                .map(|(_, value)| value.into_owned())
+                .filter(|path| !path.is_empty())
+                .ok_or_else(|| "excalibur://open needs a path parameter".to_string())?;
+            if path.contains('\0') {
+                return Err("the path contains a NUL character".to_string());
+            }
+            Ok(path)
+        }
+        scheme => Err(format!("unsupported URL scheme \"{scheme}\"")),
+    }
+}
+
+/// Where the contents of an `excalibur://new` document come from.
+#[derive(Debug, PartialEq)]
+pub enum NewContent {
+    Inline(String),
+    Remote(url::Url),
+}
+
+/// A parsed `excalibur://new?kind=..&title=..&content=..` link.
+#[derive(Debug, PartialEq)]
+pub struct NewDocumentLink {
+    pub kind: Option<&'static str>,
+    pub title: Option<String>,
+    pub content: NewContent,
+}
+
+fn is_new_document_url(url: &url::Url) -> bool {
+    url.scheme() == "excalibur" && url.host_str() == Some("new")
+}
+
+/// Parses an `excalibur://new` link. `content` is URL-encoded text, or base64 with
+/// `encoding=base64`; `content_url` points at an http(s) resource to fetch instead.
+pub fn parse_new_document_url(url: &url::Url) -> Result<NewDocumentLink, String> {
+    // Base64 of the largest document plus room for the other parameters.
+    if url.as_str().len() > MAX_NEW_CONTENT_BYTES * 2 {
+        return Err("the link is too long".to_string());
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let kind = match param("kind").as_deref() {
+        None | Some("") => None,
+        Some("excalidraw") => Some("excalidraw"),
+        Some("mermaid") => Some("mermaid"),
+        Some(kind) => return Err(format!("unknown document kind \"{kind}\"")),
+    };
+    let title = param("title")
+        .map(|title| {
+            title
+                .chars()
+                .filter(|ch| !ch.is_control())
+                .take(MAX_TITLE_CHARS)
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .filter(|title| !title.is_empty());
+
+    let content = match (param("content"), param("content_url")) {
+        (Some(_), Some(_)) => return Err("use either content or content_url, not both".to_string()),
+        (None, None) => return Err("the link has no content or content_url".to_string()),
+        (None, Some(content_url)) => {
+            let remote = url::Url::parse(&content_url)
+                .map_err(|error| format!("content_url is not a valid URL: {error}"))?;
+            if !matches!(remote.scheme(), "https" | "http") {
+                return Err("content_url must be an http or https URL".to_string());
+            }
+            NewContent::Remote(remote)
+        }
+        (Some(content), None) => {
+            let content = match param("encoding").as_deref() {
+                None | Some("") | Some("url") => content,
+                Some("base64") => decode_base64(&content)?,
+                Some(encoding) => return Err(format!("unknown content encoding \"{encoding}\"")),
+            };
+            NewContent::Inline(content)
+        }
+    };
+    Ok(NewDocumentLink {
+        kind,
+        title,
+        content,
+    })
+}
+
+fn decode_base64(content: &str) -> Result<String, String> {
+    // `+` turns into a space when the query is decoded; put it back.
+    let content: String = content
+        .chars()
+        .filter(|ch| !ch.is_whitespace() || *ch == ' ')
+        .map(|ch| if ch == ' ' { '+' } else { ch })
+        .collect();
+    let engine = if content.contains(['-', '_']) {
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD
+    } else {
+        &base64::engine::general_purpose::STANDARD_NO_PAD
+    };
+    let bytes = engine
+        .decode(content.trim_end_matches('='))
+        .map_err(|error| format!("content is not valid base64: {error}"))?;
+    String::from_utf8(bytes).map_err(|_| "content is not UTF-8 text".to_string())
+}
+
+/// Size-caps `contents` and checks it against the declared kind. Content that
+/// doesn't look like either kind is trusted when a kind was declared.
+pub fn validate_new_document(
+    kind: Option<&'static str>,
+    title: Option<String>,
+    contents: String,
+) -> Result<NewDocument, String> {
+    if contents.len() > MAX_NEW_CONTENT_BYTES {
+        return Err(format!(
+            "the content is larger than the {} MiB limit",
+            MAX_NEW_CONTENT_BYTES / 1024 / 1024
+        ));
+    }
+    if contents.contains('\0') {
+        return Err("the content looks like binary data".to_string());
+    }
+    if contents.trim().is_empty() {
+        return Err("the content is empty".to_string());
+    }
+    let detected = detect_content_kind(&contents);
+    let kind = match (kind, detected) {
+        (Some(declared), Some(detected)) if declared != detected => {
+            return Err(format!(
+                "the content looks like a {detected} document, not {declared}"
+            ))
+        }
+        (Some(declared), _) => declared,
+        (None, Some(detected)) => detected,
+        (None, None) => {
+            return Err(
+                "could not tell what kind of document the content is; add kind=".to_string(),
+            )
+        }
+    };
+    Ok(NewDocument {
+        kind,
+        title,
+        contents,
+    })
+}
+
+/// Fetches a `content_url` like any remote document, but capped at
+/// `MAX_NEW_CONTENT_BYTES`.
+async fn fetch_content(app: &AppHandle, url: &url::Url) -> Result<String, String> {
+    let bytes = crate::remote::fetch(app, url, MAX_NEW_CONTENT_BYTES as u64)
+        .await
+        .map_err(|error| match error {
+            CommandError::FileTooLarge { .. } => format!(
+                "content_url is larger than the {} MiB limit",
+                MAX_NEW_CONTENT_BYTES / 1024 / 1024
+            ),
+            error => format!("could not fetch content_url: {error}"),
+        })?;
+    String::from_utf8(bytes).map_err(|_| "content_url did not return UTF-8 text".to_string())
+}
+
+async fn new_document_from_url(app: &AppHandle, url: &url::Url) -> Result<NewDocument, String> {
+    let link = parse_new_document_url(url)?;
+    let contents = match link.content {
+        NewContent::Inline(contents) => contents,
+        NewContent::Remote(remote) => fetch_content(app, &remote).await?,
+    };
+    validate_new_document(link.kind, link.title, contents)
+}
+
+fn reject(app: &AppHandle, url: &url::Url, reason: String) {
+    let url: String = url.as_str().chars().take(MAX_REPORTED_URL_LEN).collect();
+    log::warn!("deep link rejected: {}: {}", url, reason);
+    emit_when_ready(
+        app,
+        None,
+        "deep-link-rejected",
+        DeepLinkRejected { url, reason },
+    );
+}
+
+/// The file paths among `urls`, in the order given, and the reason each other URL
+/// can't be opened. `excalibur://new` links are left to the caller.
+fn file_paths(urls: &[url::Url], windows: bool) -> (Vec<String>, Vec<(&url::Url, String)>) {
+    let mut paths = Vec::new();
+    let mut rejected = Vec::new();
+    for url in urls.iter().filter(|url| !is_new_document_url(url)) {
+        match path_from_url(url.as_str(), windows) {
+            Ok(path) => paths.push(path),
+            Err(reason) => rejected.push((url, reason)),
+        }
+    }
+    (paths, rejected)
+}
+
+/// Acts on every URL the OS handed over: the files are sent to the frontend as one
+/// `open-files` event, in the order they came, and each `excalibur://new` link
+/// creates a document. A URL that can't be used is logged and reported as
+/// `deep-link-rejected`; the others are still handled.
+pub fn handle_urls(app: &AppHandle, urls: &[url::Url]) {
+    for url in urls.iter().filter(|url| is_new_document_url(url)) {
+        let app = app.clone();
+        let url = url.clone();
+        tauri::async_runtime::spawn(async move {
+            match new_document_from_url(&app, &url).await {
+                Ok(document) => {
+                    log::info!(
+                        "deep link: new {} document {:?}",
+                        document.kind,
+                        document.title
+                    );
+                    emit_when_ready(&app, None, "new-document", document);
+                }
+                Err(reason) => reject(&app, &url, reason),
+            }
+        });
+    }
+    let (paths, rejected) = file_paths(urls, cfg!(windows));
+    for (url, reason) in rejected {
+        reject(app, url, reason);
+    }
+    if !paths.is_empty() {
+        log::info!("deep link: opening {:?}", paths);
+        emit_when_ready(app, None, "open-files", paths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_file_urls() {
+        let cases = [
+            ("file:///tmp/plain.mmd", "/tmp/plain.mmd"),
+            ("file:///tmp/with%20space.mmd", "/tmp/with space.mmd"),
+            ("file://localhost/tmp/local.mmd", "/tmp/local.mmd"),
+            (
+                "file:///home/j%C3%BCrgen/%C3%9Cbersicht.excalidraw",
+                "/home/jürgen/Übersicht.excalidraw",
+            ),
+            (
+                "file:///Users/me/%E5%9B%B3%E8%A1%A8.mmd",
+                "/Users/me/図表.mmd",
+            ),
+            ("file:///tmp/%E2%9C%93-done.mmd", "/tmp/✓-done.mmd"),
+            ("file:///tmp/issue%23123.mmd", "/tmp/issue#123.mmd"),
+            ("file:///tmp/issue#123.mmd", "/tmp/issue#123.mmd"),
+            ("file:///tmp/what?.mmd", "/tmp/what?.mmd"),
+            ("file:///tmp/100%25.mmd", "/tmp/100%.mmd"),
+            ("file:///tmp/bad%zzescape.mmd", "/tmp/bad%zzescape.mmd"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(path_from_url(url, false).as_deref(), Ok(expected), "{url}");
+        }
+    }
+
+    #[test]
+    fn windows_file_urls() {
+        let cases = [
+            (
+                "file:///C:/Users/me/diagram.mmd",
+                "C:\\Users\\me\\diagram.mmd",
+            ),
+            (
+                "file:///c:/My%20Documents/flow.mmd",
+                "c:\\My Documents\\flow.mmd",
+            ),
+            (
+                "file:///D|/data/scene.excalidraw",
+                "D:\\data\\scene.excalidraw",
+            ),
+            (
+                "file:///C:/Users/%E5%B1%B1%E7%94%B0/%E5%9B%B3.mmd",
+                "C:\\Users\\山田\\図.mmd",
+            ),
+            (
+                "file://server/share/team/plan.mmd",
+                "\\\\server\\share\\team\\plan.mmd",
+            ),
+            ("file://localhost/C:/temp/x.mmd", "C:\\temp\\x.mmd"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(path_from_url(url, true).as_deref(), Ok(expected), "{url}");
+        }
+        assert!(path_from_url("file:///no-drive/x.mmd", true).is_err());
+    }
+
+    #[test]
+    fn excalibur_scheme() {
+        assert_eq!(
+            path_from_url("excalibur://open?path=%2Ftmp%2Fa%20b%23c.mmd", false).as_deref(),
+            Ok("/tmp/a b#c.mmd")
+        );
+        assert_eq!(
+            path_from_url(
+                "excalibur://open?focus=x&path=C%3A%5Cdocs%5C%C3%BC.mmd",
+                true
+            )
+            .as_deref(),
+            Ok("C:\\docs\\ü.mmd")
+        );
+        assert!(path_from_url("excalibur://open", false).is_err());
+        assert!(path_from_url("excalibur://delete?path=%2Ftmp%2Fa.mmd", false).is_err());
+    }
+
+    fn new_link(query: &str) -> Result<NewDocumentLink, String> {
+        parse_new_document_url(&url::Url::parse(&format!("excalibur://new?{query}")).unwrap())
+    }
+
+    #[test]
+    fn new_document_links() {
+        let link =
+            new_link("kind=mermaid&title=Checkout%20Flow&content=graph%20TD%0A%20%20A%20--%3E%20B")
+                .unwrap();
+        assert_eq!(link.kind, Some("mermaid"));
+        assert_eq!(link.title.as_deref(), Some("Checkout Flow"));
+        assert_eq!(
+            link.content,
+            NewContent::Inline("graph TD\n  A --> B".to_string())
+        );
+
+        // Padding is optional.
+        for encoded in ["Z3JhcGggVEQKICBBIC0tPiBC", "Z3JhcGggVEQKICBBIC0tPiBC%3D%3D"] {
+            let link = new_link(&format!("encoding=base64&content={encoded}")).unwrap();
+            assert_eq!(
+                link.content,
+                NewContent::Inline("graph TD\n  A --> B".to_string())
+            );
+        }
+        // A `+` left unescaped in the query, and the URL-safe alphabet.
+        assert_eq!(
+            new_link("encoding=base64&content=Pj4+").unwrap().content,
+            NewContent::Inline(">>>".to_string())
+        );
+        assert_eq!(
+            new_link("encoding=base64&content=Pj4-").unwrap().content,
+            NewContent::Inline(">>>".to_string())
+        );
+
+        let link = new_link("content_url=https%3A%2F%2Fportal.example%2Fd.mmd").unwrap();
+        assert_eq!(
+            link.content,
+            NewContent::Remote(url::Url::parse("https://portal.example/d.mmd").unwrap())
+        );
+
+        assert!(new_link("kind=visio&content=x").is_err());
+        assert!(new_link("title=Empty").is_err());
+        assert!(new_link("content=a&content_url=https%3A%2F%2Fx").is_err());
+        assert!(new_link("content_url=file%3A%2F%2F%2Fetc%2Fpasswd").is_err());
+        assert!(new_link("encoding=base64&content=%%%").is_err());
+    }
+
+    #[test]
+    fn new_document_validation() {
+        let mermaid = "flowchart LR\n  A --> B".to_string();
+        let scene = r#"{"type":"excalidraw","elements":[]}"#.to_string();
+
+        assert_eq!(
+            validate_new_document(None, None, mermaid.clone())
+                .unwrap()
+                .kind,
+            "mermaid"
+        );
+        assert_eq!(
+            validate_new_document(Some("excalidraw"), None, scene.clone())
+                .unwrap()
+                .kind,
+            "excalidraw"
+        );
+        // Undetectable content is trusted when the kind is declared.
+        assert!(validate_new_document(Some("mermaid"), None, "%% draft".to_string()).is_ok());
+        assert!(validate_new_document(Some("mermaid"), None, scene).is_err());
+        assert!(validate_new_document(None, None, "hello".to_string()).is_err());
+        assert!(validate_new_document(Some("mermaid"), None, "  ".to_string()).is_err());
+        let oversized = format!("graph TD\n%% {}", "x".repeat(MAX_NEW_CONTENT_BYTES));
+        assert!(validate_new_document(Some("mermaid"), None, oversized).is_err());
+    }
+
+    #[test]
+    fn rejects_unusable_urls() {
+        let long = format!("file:///tmp/{}.mmd", "a".repeat(MAX_URL_LEN));
+        let rejected = [
+            long.as_str(),
+            "not a url",
+            "https://example.com/diagram.mmd",
+            "file://server/share/plan.mmd",
+            "file:///tmp/%FF%FE.mmd",
+            "file:///tmp/nul%00.mmd",
+            "file:///",
+        ];
+        for url in rejected {
+            assert!(path_from_url(url, false).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn opens_every_file_in_delivery_order() {
+        let urls: Vec<url::Url> = [
+            "file:///work/b.excalidraw",
+            "https://example.com/not-a-file",
+            "excalibur://new?kind=mermaid&content=graph%20TD",
+            "file:///work/a.mmd",
+            "excalibur://open?path=%2Fwork%2Fc.mmd",
+        ]
+        .iter()
+        .map(|url| url::Url::parse(url).unwrap())
+        .collect();
+        let (paths, rejected) = file_paths(&urls, false);
+        assert_eq!(paths, ["/work/b.excalidraw", "/work/a.mmd", "/work/c.mmd"]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0.as_str(), "https://example.com/not-a-file");
+    }
+}
@@ -0,0 +1,244 @@
+use crate::scan::kind_for_extension;
+use serde_json::Value;
+use std::path::Path;
+
+/// Diagram types a mermaid source can start with.
+const MERMAID_KEYWORDS: [&str; 27] = [
+    "graph",
+    "flowchart",
+    "sequenceDiagram",
+    "classDiagram",
+    "stateDiagram",
+    "stateDiagram-v2",
+    "erDiagram",
+    "journey",
+    "gantt",
+    "pie",
+    "quadrantChart",
+    "requirementDiagram",
+    "gitGraph",
+    "C4Context",
+    "C4Container",
+    "C4Component",
+    "C4Dynamic",
+    "C4Deployment",
+    "mindmap",
+    "timeline",
+    "zenuml",
+    "sankey-beta",
+    "xychart-beta",
+    "block-beta",
+    "packet-beta",
+    "kanban",
+    "architecture-beta",
+];
+
+fn is_excalidraw_json(contents: &str) -> bool {
+    if !contents.trim_start().starts_with('{') {
+        return false;
+    }
+    let Ok(Value::Object(scene)) = serde_json::from_str::<Value>(contents) else {
+        return false;
+    };
+    scene.get("type").and_then(Value::as_str) == Some("excalidraw")
+        || scene.get("elements").is_some_and(Value::is_array)
+}
+
+/// First meaningful line of a mermaid source, skipping blank lines, `%%` comments
+/// and a leading `---` front-matter block.
+fn first_mermaid_line(contents: &str) -> Option<&str> {
+    let mut lines = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("%%"))
+        .peekable();
+    if lines.peek() == Some(&"---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line == "---" {
+                break;
+            }
+        }
+    }
+    lines.next()
+}
+
+/// The diagram type a mermaid source declares on its first line, e.g. "sequenceDiagram".
+pub fn mermaid_diagram_type(contents: &str) -> Option<&'static str> {
+    let keyword = first_mermaid_line(contents)?
+        .split(|ch: char| ch.is_whitespace() || ch == ';')
+        .next()?;
+    MERMAID_KEYWORDS
+        .iter()
+        .find(|known| **known == keyword)
+        .copied()
+}
+
+fn starts_with_mermaid_keyword(contents: &str) -> bool {
+    mermaid_diagram_type(contents).is_some()
+}
+
+fn has_mermaid_fence(contents: &str) -> bool {
+    contents
+        .lines()
+        .any(|line| line.trim_start().starts_with("```mermaid"))
+}
+
+/// Works out whether a file is an excalidraw scene or a mermaid diagram.
+///
+/// Unambiguous extensions (`.excalidraw`, `.mmd`, `.mermaid`) decide on their own;
+/// anything else (`.json`, `.md`, `.txt`, no extension) is sniffed from its contents.
+pub fn detect_kind(path: &Path, contents: &str) -> Option<&'static str> {
+    kind_for_extension(path).or_else(|| detect_content_kind(contents))
+}
+
+/// Sniffs the document kind from contents alone, e.g. for text piped in on stdin.
+pub fn detect_content_kind(contents: &str) -> Option<&'static str> {
+    if is_excalidraw_json(contents) {
+        Some("excalidraw")
+    } else if starts_with_mermaid_keyword(contents) || has_mermaid_fence(contents) {
+        Some("mermaid")
+    } else {
+        None
+    }
+}
+
+/// Re-detects a file recents list as a `stored` document, since it may have been
+/// renamed (e.g. `.json` to `.excalidraw`) outside the app. Returns the kind to open
+/// it as, or `Err` with the kind its extension claims when it can't be trusted to be
+/// either: nothing recognises it, or a changed extension and the contents disagree.
+/// Mermaid in `.md`/`.txt` needn't start with a keyword, so those stay mermaid.
+pub fn redetect_kind(
+    path: &Path,
+    stored: &str,
+    contents: &str,
+) -> Result<&'static str, Option<&'static str>> {
+    let by_extension = kind_for_extension(path);
+    if let Some(kind) = by_extension.filter(|kind| *kind == stored) {
+        return Ok(kind);
+    }
+    let is_json = crate::gzip::document_extension(path)
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    match (by_extension, detect_content_kind(contents)) {
+        (Some(extension), Some(content)) if content != extension => Err(Some(extension)),
+        (Some(extension), _) => Ok(extension),
+        (None, Some(content)) => Ok(content),
+        (None, None) if stored == "mermaid" && !is_json => Ok("mermaid"),
+        (None, None) => Err(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_wins_over_contents() {
+        assert_eq!(
+            detect_kind(Path::new("a.excalidraw"), "flowchart TD"),
+            Some("excalidraw")
+        );
+        assert_eq!(detect_kind(Path::new("a.mmd"), "{}"), Some("mermaid"));
+    }
+
+    #[test]
+    fn json_is_sniffed() {
+        let scene = r#"{"type": "excalidraw", "version": 2, "elements": []}"#;
+        assert_eq!(detect_kind(Path::new("a.json"), scene), Some("excalidraw"));
+        assert_eq!(
+            detect_kind(Path::new("a.json"), r#"{"elements": [{"id": "x"}]}"#),
+            Some("excalidraw")
+        );
+        assert_eq!(
+            detect_kind(Path::new("package.json"), r#"{"name": "app"}"#),
+            None
+        );
+        assert_eq!(detect_kind(Path::new("a.json"), "{ not json"), None);
+    }
+
+    #[test]
+    fn plain_text_starting_with_keyword_is_mermaid() {
+        assert_eq!(
+            detect_kind(
+                Path::new("a.txt"),
+                "\n%% comment\nsequenceDiagram\n  A->>B: hi"
+            ),
+            Some("mermaid")
+        );
+        assert_eq!(
+            detect_kind(Path::new("a.txt"), "---\ntitle: Flow\n---\ngraph LR;A-->B"),
+            Some("mermaid")
+        );
+        assert_eq!(detect_kind(Path::new("notes.txt"), "graphs are fun"), None);
+    }
+
+    #[test]
+    fn reads_the_declared_diagram_type() {
+        assert_eq!(
+            mermaid_diagram_type("%% title\nsequenceDiagram\n  A->>B: hi"),
+            Some("sequenceDiagram")
+        );
+        assert_eq!(mermaid_diagram_type("graph LR;A-->B"), Some("graph"));
+        assert_eq!(mermaid_diagram_type("not a diagram"), None);
+    }
+
+    #[test]
+    fn markdown_needs_a_mermaid_fence() {
+        let doc = "# Design\n\nSome prose.\n\n```mermaid\nflowchart TD\n  A --> B\n```\n";
+        assert_eq!(detect_kind(Path::new("design.md"), doc), Some("mermaid"));
+        assert_eq!(
+            detect_kind(
+                Path::new("readme.md"),
+                "# Title\n\n```js\nconst a = 1\n```\n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn markdown_with_excalidraw_json_is_not_misread() {
+        assert_eq!(
+            detect_kind(
+                Path::new("scene.md"),
+                "{\"type\": \"excalidraw\", \"elements\": []}"
+            ),
+            Some("excalidraw")
+        );
+    }
+
+    #[test]
+    fn renamed_recents_are_redetected() {
+        let scene = r#"{"type": "excalidraw", "elements": []}"#;
+        assert_eq!(
+            redetect_kind(Path::new("design.excalidraw"), "excalidraw", scene),
+            Ok("excalidraw")
+        );
+        // Renamed from design.excalidraw, or back again.
+        assert_eq!(
+            redetect_kind(Path::new("design.json"), "excalidraw", scene),
+            Ok("excalidraw")
+        );
+        assert_eq!(
+            redetect_kind(Path::new("flow.mmd"), "excalidraw", "graph TD"),
+            Ok("mermaid")
+        );
+        assert_eq!(
+            redetect_kind(Path::new("notes.txt"), "mermaid", "no keyword yet"),
+            Ok("mermaid")
+        );
+        // A .json that isn't a scene, and an extension the contents contradict.
+        assert_eq!(
+            redetect_kind(
+                Path::new("package.json"),
+                "excalidraw",
+                r#"{"name": "app"}"#
+            ),
+            Err(None)
+        );
+        assert_eq!(
+            redetect_kind(Path::new("flow.excalidraw"), "mermaid", "graph TD"),
+            Err(Some("excalidraw"))
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::async_runtime::channel;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, FilePath};
+
+/// Whether native file dialogs can be shown, as reported by `get_app_info`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct DialogSupport {
+    available: bool,
+    /// Why they can't, when they can't.
+    reason: Option<String>,
+}
+
+/// Detected at startup, and switched off when a dialog never answers, e.g. on Linux
+/// without a working xdg-desktop-portal, which can't be detected up front.
+pub struct Dialogs(Mutex<DialogSupport>);
+
+impl Default for Dialogs {
+    fn default() -> Self {
+        Dialogs(Mutex::new(detect_support(std::env::consts::OS, |name| {
+            std::env::var(name).ok()
+        })))
+    }
+}
+
+/// Payload of `dialog-unavailable`: what the frontend should ask the user for
+/// before calling the matching command that takes a path.
+#[derive(Serialize, Clone)]
+struct PathRequest {
+    /// "open" or "save".
+    action: &'static str,
+    filter_name: String,
+    extensions: Vec<String>,
+    suggested_name: Option<String>,
+    reason: String,
+}
+
+enum Outcome {
+    Answered(Option<Vec<FilePath>>),
+    TimedOut,
+}
+
+enum Mode {
+    Open,
+    OpenMany,
+    /// Save, starting from the suggested file name, in `directory` when given.
+    Save {
+        name: String,
+        directory: Option<PathBuf>,
+    },
+}
+
+/// On Linux and the BSDs dialogs need an X11 or Wayland display.
+fn detect_support(os: &str, var: impl Fn(&str) -> Option<String>) -> DialogSupport {
+    let needs_display = matches!(os, "linux" | "freebsd" | "openbsd" | "netbsd" | "dragonfly");
+    let has_display = ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| var(name).is_some_and(|value| !value.is_empty()));
+    if needs_display && !has_display {
+        DialogSupport {
+            available: false,
+            reason: Some("no display server (DISPLAY and WAYLAND_DISPLAY are unset)".to_string()),
+        }
+    } else {
+        DialogSupport {
+            available: true,
+            reason: None,
+        }
+    }
+}
+
+pub fn support(app: &AppHandle) -> DialogSupport {
+    app.state::<Dialogs>().0.lock().unwrap().clone()
+}
+
+/// Asks the frontend for the path through `dialog-unavailable` and returns the
+/// error the command fails with meanwhile.
+fn fall_back(app: &AppHandle, mut request: PathRequest, reason: String) -> CommandError {
+    request.reason = reason.clone();
+    let _ = app.emit("dialog-unavailable", request);
+    CommandError::DialogUnavailable { reason }
+}
+
+/// Shows the dialog for `mode` and returns the chosen paths, none when the user
+/// cancelled. A dialog that doesn't answer within `dialog_timeout_secs` is given up
+/// on, and dialogs are not tried again this session.
+async fn pick(
+    app: &AppHandle,
+    filter_name: &str,
+    extensions: &[&str],
+    mode: Mode,
+) -> Result<Vec<PathBuf>, CommandError> {
+    let suggested_name = match &mode {
+        Mode::Save { name, .. } => Some(name.clone()),
+        Mode::Open | Mode::OpenMany => None,
+    };
+    let request = PathRequest {
+        action: if suggested_name.is_some() {
+            "save"
+        } else {
+            "open"
+        },
+        filter_name: filter_name.to_string(),
+        extensions: extensions
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+        suggested_name,
+        reason: String::new(),
+    };
+    let current = support(app);
+    if !current.available {
+        return Err(fall_back(app, request, current.reason.unwrap_or_default()));
+    }
+
+    let (sender, mut receiver) = channel(1);
+    let settings = load_settings(app);
+    let timeout_secs = settings.dialog_timeout_secs;
+    if timeout_secs > 0 {
+        let watchdog = sender.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(timeout_secs));
+            let _ = watchdog.try_send(Outcome::TimedOut);
+        });
+    }
+    let dialog = app.dialog().file().add_filter(filter_name, extensions);
+    let answer = move |file_paths| {
+        let _ = sender.try_send(Outcome::Answered(file_paths));
+    };
+    match mode {
+        Mode::Open => dialog.pick_file(move |file_path| answer(file_path.map(|path| vec![path]))),
+        Mode::OpenMany => dialog.pick_files(answer),
+        Mode::Save { name, directory } => {
+            let directory = directory
+                .filter(|dir| dir.is_dir())
+                .or(settings.default_save_dir.filter(|dir| dir.is_dir()));
+            let dialog = match directory {
+                Some(dir) => dialog.set_directory(dir),
+                None => dialog,
+            };
+            dialog
+                .set_file_name(name)
+                .save_file(move |file_path| answer(file_path.map(|path| vec![path])))
+        }
+    }
+
+    let reason = match receiver.recv().await {
+        Some(Outcome::Answered(file_paths)) => {
+            return file_paths
+                .unwrap_or_default()
+                .into_iter()
+                .map(|file_path| {
+                    file_path.into_path().map_err(|error| {
+                        log::warn!("dialogs: unusable path from the dialog: {}", error);
+                        CommandError::Io {
+                            message: error.to_string(),
+                        }
+                    })
+                })
+                .collect();
+        }
+        Some(Outcome::TimedOut) => {
+            format!("the file dialog didn't respond within {timeout_secs} s")
+        }
+        None => "the dialog plugin dropped the request without answering".to_string(),
+    };
+    log::warn!(
+        "dialogs: {} dialog failed: {}; asking the frontend for a path from now on",
+        request.action,
+        reason
+    );
+    *app.state::<Dialogs>().0.lock().unwrap() = DialogSupport {
+        available: false,
+        reason: Some(reason.clone()),
+    };
+    Err(fall_back(app, request, reason))
+}
+
+/// Lets the user choose a file to open.
+pub async fn pick_open_path(
+    app: &AppHandle,
+    filter_name: &str,
+    extensions: &[&str],
+) -> Result<Option<PathBuf>, CommandError> {
+    Ok(pick(app, filter_name, extensions, Mode::Open)
+        .await?
+        .into_iter()
+        .next())
+}
+
+/// Lets the user choose any number of files to open; none when they cancelled.
+pub async fn pick_open_paths(
+    app: &AppHandle,
+    filter_name: &str,
+    extensions: &[&str],
+) -> Result<Vec<PathBuf>, CommandError> {
+    pick(app, filter_name, extensions, Mode::OpenMany).await
+}
+
+/// Lets the user choose where to save, starting from `suggested_name`.
+pub async fn pick_save_path(
+    app: &AppHandle,
+    filter_name: &str,
+    extensions: &[&str],
+    suggested_name: String,
+) -> Result<Option<PathBuf>, CommandError> {
+    pick_save_path_in(app, filter_name, extensions, suggested_name, None).await
+}
+
+/// Like `pick_save_path`, starting in `directory` rather than the default save
+/// folder or wherever the OS last was.
+pub async fn pick_save_path_in(
+    app: &AppHandle,
+    filter_name: &str,
+    extensions: &[&str],
+    suggested_name: String,
+    directory: Option<PathBuf>,
+) -> Result<Option<PathBuf>, CommandError> {
+    let mode = Mode::Save {
+        name: suggested_name,
+        directory,
+    };
+    Ok(pick(app, filter_name, extensions, mode)
+        .await?
+        .into_iter()
+        .next())
+}
+
+/// Characters no file name may contain on some platform we run on.
+const ILLEGAL_NAME_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// The file name a save dialog should suggest for a document called `name`, or
+/// `default_name` when it has none: path separators and characters illegal in file
+/// names become `-`, and the first of `extensions` is added unless the name already
+/// ends in one of them.
+pub fn suggested_file_name(name: Option<&str>, default_name: &str, extensions: &[&str]) -> String {
+    let sanitize = |name: &str| {
+        let name: String = name
+            .chars()
+            .map(|ch| {
+                if ch.is_control() || ILLEGAL_NAME_CHARS.contains(&ch) {
+                    '-'
+                } else {
+                    ch
+                }
+            })
+            .collect();
+        // Windows drops trailing dots and spaces, and a name of only dots is no name.
+        let name = name.trim().trim_end_matches(['.', ' ']);
+        (!name.is_empty()).then(|| name.to_string())
+    };
+    let base_name = name
+        .and_then(sanitize)
+        .or_else(|| sanitize(default_name))
+        .unwrap_or_else(|| "Untitled".to_string());
+    let known = Path::new(&base_name).extension().is_some_and(|extension| {
+        extensions
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    });
+    match extensions.first() {
+        Some(extension) if !known => format!("{base_name}.{extension}"),
+        _ => base_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linux_needs_a_display() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(!detect_support("linux", env(&[])).available);
+        assert!(!detect_support("linux", env(&[("DISPLAY", "")])).available);
+        assert!(detect_support("linux", env(&[("DISPLAY", ":0")])).available);
+        assert!(detect_support("linux", env(&[("WAYLAND_DISPLAY", "wayland-0")])).available);
+        assert!(detect_support("windows", env(&[])).available);
+    }
+
+    #[test]
+    fn suggested_names_are_sanitized_and_get_an_extension() {
+        let excalidraw = &["excalidraw", "json"];
+        let suggest = |name| suggested_file_name(name, "drawing", excalidraw);
+        assert_eq!(suggest(None), "drawing.excalidraw");
+        assert_eq!(suggest(Some("  ")), "drawing.excalidraw");
+        assert_eq!(
+            suggest(Some("network-topology.excalidraw")),
+            "network-topology.excalidraw"
+        );
+        assert_eq!(suggest(Some("Scene.JSON")), "Scene.JSON");
+        assert_eq!(suggest(Some("plan v1.2")), "plan v1.2.excalidraw");
+        assert_eq!(suggest(Some("a/b\\c: d?")), "a-b-c- d-.excalidraw");
+        assert_eq!(suggest(Some("notes. ")), "notes.excalidraw");
+        assert_eq!(suggest(Some("..")), "drawing.excalidraw");
+        assert_eq!(
+            suggested_file_name(Some("flow.md"), "diagram", &["mmd", "md"]),
+            "flow.md"
+        );
+        assert_eq!(suggested_file_name(None, "", &["mmd"]), "Untitled.mmd");
+    }
+}
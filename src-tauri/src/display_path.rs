@@ -0,0 +1,296 @@
+use crate::settings::load_settings;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const ELLIPSIS: char = '…';
+
+/// Shortens absolute paths for list UIs: relative to the registered root or home
+/// folder they are under, then middle-ellipsized to `max_len` characters.
+pub struct PathShortener {
+    home: Option<String>,
+    /// Registered roots as (path, display name), deepest first.
+    roots: Vec<(String, String)>,
+    max_len: usize,
+    windows: bool,
+}
+
+/// A path split into the part that is always shown (`~/`, `C:\`, `Diagrams/`) and
+/// its remaining components.
+struct Split {
+    head: String,
+    parts: Vec<String>,
+    /// The head names a root or the home folder rather than a drive or `/`.
+    named: bool,
+}
+
+impl PathShortener {
+    pub fn new(app: &AppHandle) -> PathShortener {
+        let roots = crate::roots::load_roots(app)
+            .into_iter()
+            .map(|root| {
+                let name = root.name.clone().unwrap_or_else(|| root.path.clone());
+                (root.path, name)
+            })
+            .collect();
+        PathShortener::with(
+            app.path()
+                .home_dir()
+                .ok()
+                .map(|home| home.to_string_lossy().to_string()),
+            roots,
+            load_settings(app).display_path_max_len,
+            cfg!(windows),
+        )
+    }
+
+    fn with(
+        home: Option<String>,
+        mut roots: Vec<(String, String)>,
+        max_len: usize,
+        windows: bool,
+    ) -> PathShortener {
+        roots.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+        PathShortener {
+            home,
+            roots,
+            max_len,
+            windows,
+        }
+    }
+
+    fn separator(&self) -> char {
+        if self.windows {
+            '\\'
+        } else {
+            '/'
+        }
+    }
+
+    fn components<'a>(&self, path: &'a str) -> Vec<&'a str> {
+        path.split(|ch| ch == '/' || (self.windows && ch == '\\'))
+            .filter(|part| !part.is_empty())
+            .collect()
+    }
+
+    /// The components of `path` below `base`, if `path` is `base` or inside it.
+    /// Windows paths compare case-insensitively.
+    fn strip_base(&self, path: &str, base: &str) -> Option<Vec<String>> {
+        let path_parts = self.components(path);
+        let base_parts = self.components(base);
+        if base_parts.is_empty() || path_parts.len() < base_parts.len() {
+            return None;
+        }
+        let same = path_parts.iter().zip(&base_parts).all(|(a, b)| {
+            if self.windows {
+                a.eq_ignore_ascii_case(b)
+            } else {
+                a == b
+            }
+        });
+        same.then(|| {
+            path_parts[base_parts.len()..]
+                .iter()
+                .map(|part| part.to_string())
+                .collect()
+        })
+    }
+
+    fn split(&self, path: &str) -> Split {
+        let separator = self.separator();
+        for (root, name) in &self.roots {
+            if let Some(parts) = self.strip_base(path, root) {
+                return Split {
+                    head: format!("{name}{separator}"),
+                    parts,
+                    named: true,
+                };
+            }
+        }
+        if let Some(parts) = self
+            .home
+            .as_deref()
+            .and_then(|home| self.strip_base(path, home))
+        {
+            return Split {
+                head: format!("~{separator}"),
+                parts,
+                named: true,
+            };
+        }
+
+        let mut parts: Vec<String> = self
+            .components(path)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let unc = self.windows && (path.starts_with("\\\\") || path.starts_with("//"));
+        let head = if unc && parts.len() >= 2 {
+            let share: Vec<String> = parts.drain(..2).collect();
+            format!("{separator}{separator}{}{separator}", share.join("\\"))
+        } else if self.windows && parts.first().is_some_and(|part| part.ends_with(':')) {
+            format!("{}{separator}", parts.remove(0))
+        } else if path.starts_with(['/', '\\']) {
+            separator.to_string()
+        } else {
+            String::new()
+        };
+        Split {
+            head,
+            parts,
+            named: false,
+        }
+    }
+
+    /// Shortened form of `path` for display. Non-UTF-8 components are shown lossily.
+    pub fn shorten(&self, path: &Path) -> String {
+        self.shorten_str(&path.to_string_lossy())
+    }
+
+    fn shorten_str(&self, path: &str) -> String {
+        let separator = self.separator().to_string();
+        let Split { head, parts, named } = self.split(path);
+        let full = if named && parts.is_empty() {
+            // A root or the home folder itself shows as just its name.
+            head.trim_end_matches(self.separator()).to_string()
+        } else {
+            format!("{head}{}", parts.join(&separator))
+        };
+        if full.chars().count() <= self.max_len {
+            return full;
+        }
+
+        // Keep the head and as many trailing components as fit around the ellipsis.
+        let mut tail: Vec<&str> = Vec::new();
+        let mut length = head.chars().count() + 2;
+        for part in parts.iter().rev() {
+            let added = part.chars().count() + usize::from(!tail.is_empty());
+            if length + added > self.max_len {
+                break;
+            }
+            length += added;
+            tail.insert(0, part);
+        }
+        if !tail.is_empty() && tail.len() < parts.len() {
+            return format!("{head}{ELLIPSIS}{separator}{}", tail.join(&separator));
+        }
+        middle_ellipsis(&full, self.max_len)
+    }
+}
+
+/// Cuts characters out of the middle of `text` so it is `max_len` characters long.
+fn middle_ellipsis(text: &str, max_len: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_len {
+        return text.to_string();
+    }
+    if max_len == 0 {
+        return String::new();
+    }
+    let keep = max_len - 1;
+    let front = keep.div_ceil(2);
+    let back = keep - front;
+    let mut shortened: String = chars[..front].iter().collect();
+    shortened.push(ELLIPSIS);
+    shortened.extend(&chars[chars.len() - back..]);
+    shortened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unix(max_len: usize) -> PathShortener {
+        PathShortener::with(
+            Some("/home/sam".to_string()),
+            vec![("/home/sam/src/diagrams".to_string(), "Diagrams".to_string())],
+            max_len,
+            false,
+        )
+    }
+
+    fn windows(max_len: usize) -> PathShortener {
+        PathShortener::with(
+            Some("C:\\Users\\sam".to_string()),
+            vec![("D:\\work".to_string(), "Work".to_string())],
+            max_len,
+            true,
+        )
+    }
+
+    #[test]
+    fn prefers_the_root_then_home() {
+        let shortener = unix(80);
+        assert_eq!(
+            shortener.shorten_str("/home/sam/src/diagrams/flows/login.mmd"),
+            "Diagrams/flows/login.mmd"
+        );
+        assert_eq!(
+            shortener.shorten_str("/home/sam/notes/a.mmd"),
+            "~/notes/a.mmd"
+        );
+        assert_eq!(shortener.shorten_str("/home/sam"), "~");
+        assert_eq!(shortener.shorten_str("/home/sam/src/diagrams"), "Diagrams");
+        assert_eq!(
+            shortener.shorten_str("/home/samuel/a.mmd"),
+            "/home/samuel/a.mmd"
+        );
+        assert_eq!(shortener.shorten_str("/tmp/a.mmd"), "/tmp/a.mmd");
+    }
+
+    #[test]
+    fn ellipsizes_the_middle_keeping_the_file_name() {
+        let path = "/var/data/projects/2024/q3/flows/login.mmd";
+        assert_eq!(unix(path.len()).shorten_str(path), path);
+        assert_eq!(
+            unix(path.len() - 1).shorten_str(path),
+            "/…/data/projects/2024/q3/flows/login.mmd"
+        );
+        assert_eq!(unix(20).shorten_str(path), "/…/flows/login.mmd");
+        assert_eq!(
+            unix(30).shorten_str("/home/sam/a/b/c/d/e/f/g/h/i/j/k/l/m/diagram.mmd"),
+            "~/…/g/h/i/j/k/l/m/diagram.mmd"
+        );
+        // A file name too long to fit is cut in the middle itself.
+        assert_eq!(
+            unix(12).shorten_str("/tmp/a-very-long-diagram-name.mmd"),
+            "/tmp/a…e.mmd"
+        );
+    }
+
+    #[test]
+    fn windows_drive_roots_and_shares() {
+        let shortener = windows(80);
+        assert_eq!(
+            shortener.shorten_str("c:\\users\\SAM\\Docs\\a.excalidraw"),
+            "~\\Docs\\a.excalidraw"
+        );
+        assert_eq!(shortener.shorten_str("D:\\work\\a.mmd"), "Work\\a.mmd");
+        assert_eq!(shortener.shorten_str("E:\\"), "E:\\");
+        assert_eq!(
+            windows(24).shorten_str("E:\\archive\\2019\\old\\flows\\a.mmd"),
+            "E:\\…\\old\\flows\\a.mmd"
+        );
+        assert_eq!(
+            windows(25).shorten_str("\\\\server\\share\\team\\x\\y\\a.mmd"),
+            "\\\\server\\share\\…\\y\\a.mmd"
+        );
+        // Forward slashes are separators on Windows too.
+        assert_eq!(shortener.shorten_str("C:/Users/sam/a.mmd"), "~\\a.mmd");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_components_are_shown_lossily() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"/home/sam/caf\xe9/a.mmd"));
+        assert_eq!(unix(80).shorten(path), "~/caf\u{fffd}/a.mmd");
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(middle_ellipsis("ééééé", 5), "ééééé");
+        assert_eq!(middle_ellipsis("abcdefgh", 5), "ab…gh");
+        assert_eq!(middle_ellipsis("abcdefgh", 1), "…");
+    }
+}
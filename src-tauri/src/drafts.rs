@@ -0,0 +1,239 @@
+use crate::error::CommandError;
+use crate::paths::comparison_key;
+use crate::{app_data_dir, content_hash, modified_millis, now_epoch_millis, write_file};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const DRAFTS_DIR: &str = "drafts";
+
+/// Hash of the contents last written per draft id this session, so the frontend's
+/// timer can call `write_draft` unconditionally without rewriting unchanged drafts.
+#[derive(Default)]
+pub struct Drafts(Mutex<HashMap<String, String>>);
+
+/// Unsaved editor contents kept in `drafts/<id>.json`, for recovery after a crash.
+#[derive(Serialize, Deserialize)]
+pub struct Draft {
+    id: String,
+    kind: String,
+    /// The file the document was opened from or last saved to; `None` when untitled.
+    path: Option<String>,
+    /// Epoch milliseconds of the last write.
+    updated_at: u64,
+    contents: String,
+}
+
+/// A draft as `list_drafts` returns it, without its contents.
+#[derive(Serialize)]
+pub struct DraftInfo {
+    id: String,
+    kind: String,
+    path: Option<String>,
+    updated_at: u64,
+    bytes: usize,
+}
+
+fn drafts_dir(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join(DRAFTS_DIR)
+}
+
+/// Ids become file names, so only letters, digits, `-` and `_` are accepted, e.g.
+/// the `untitled-mermaid-1` ids from `new_*_file`.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+fn draft_path(app: &AppHandle, id: &str) -> Result<PathBuf, CommandError> {
+    if !is_valid_id(id) {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{id:?} is not a valid draft id"),
+        });
+    }
+    Ok(drafts_dir(app).join(format!("{id}.json")))
+}
+
+fn read_draft_file(path: &Path) -> Option<Draft> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Whether `draft` has changes its saved file doesn't: always for an untitled
+/// document or one whose file is gone.
+fn is_newer_than_saved(draft: &Draft) -> bool {
+    match draft
+        .path
+        .as_deref()
+        .and_then(|path| modified_millis(Path::new(path)))
+    {
+        Some(saved_at) => draft.updated_at > saved_at,
+        None => true,
+    }
+}
+
+fn all_drafts(app: &AppHandle) -> Vec<(PathBuf, Draft)> {
+    let Ok(entries) = fs::read_dir(drafts_dir(app)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| Some((path.clone(), read_draft_file(&path)?)))
+        .collect()
+}
+
+/// Keeps the editor's current contents for `document_id` as a draft. Unchanged
+/// contents aren't written again; returns whether the draft was written.
+#[tauri::command]
+pub fn write_draft(
+    app: AppHandle,
+    document_id: String,
+    kind: String,
+    contents: String,
+    path: Option<String>,
+) -> Result<bool, CommandError> {
+    if !matches!(kind.as_str(), "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind { kind });
+    }
+    let file = draft_path(&app, &document_id)?;
+    let hash = content_hash(
+        format!(
+            "{kind}\0{}\0{contents}",
+            path.as_deref().unwrap_or_default()
+        )
+        .as_bytes(),
+    );
+    let drafts = app.state::<Drafts>();
+    if drafts.0.lock().unwrap().get(&document_id) == Some(&hash) {
+        return Ok(false);
+    }
+    let draft = Draft {
+        id: document_id.clone(),
+        kind,
+        path,
+        updated_at: now_epoch_millis() as u64,
+        contents,
+    };
+    write_file(&file, &serde_json::to_string(&draft)?)?;
+    drafts.0.lock().unwrap().insert(document_id, hash);
+    Ok(true)
+}
+
+/// The drafts worth recovering: those with changes their saved file doesn't have,
+/// newest first.
+#[tauri::command]
+pub fn list_drafts(app: AppHandle) -> Result<Vec<DraftInfo>, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    let mut drafts: Vec<DraftInfo> = all_drafts(&app)
+        .into_iter()
+        .map(|(_, draft)| draft)
+        .filter(is_newer_than_saved)
+        .map(|draft| DraftInfo {
+            bytes: draft.contents.len(),
+            id: draft.id,
+            kind: draft.kind,
+            path: draft.path,
+            updated_at: draft.updated_at,
+        })
+        .collect();
+    drafts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(drafts)
+}
+
+#[tauri::command]
+pub fn read_draft(app: AppHandle, id: String) -> Result<Draft, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    let file = draft_path(&app, &id)?;
+    let contents =
+        fs::read_to_string(&file).map_err(|error| CommandError::from_io(error, &file))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Deletes the draft; a draft that is already gone is not an error.
+#[tauri::command]
+pub fn discard_draft(app: AppHandle, id: String) -> Result<(), CommandError> {
+    let file = draft_path(&app, &id)?;
+    app.state::<Drafts>().0.lock().unwrap().remove(&id);
+    match fs::remove_file(&file) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            Err(CommandError::from_io(error, &file))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Called after a document was saved to `path`: its drafts, by path or by the
+/// untitled id it had before its first save, are no longer needed. Their hashes are
+/// kept, so the next timed `write_draft` of the just-saved contents is skipped.
+pub fn after_save(app: &AppHandle, untitled_id: Option<&str>, path: &Path) {
+    let key = comparison_key(path);
+    for (file, draft) in all_drafts(app) {
+        let saved = Some(draft.id.as_str()) == untitled_id
+            || draft
+                .path
+                .as_deref()
+                .is_some_and(|draft_path| comparison_key(Path::new(draft_path)) == key);
+        if !saved {
+            continue;
+        }
+        if let Err(error) = fs::remove_file(&file) {
+            log::warn!("drafts: failed to remove {}: {}", file.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft(path: Option<&Path>, updated_at: u64) -> Draft {
+        Draft {
+            id: "untitled-mermaid-1".to_string(),
+            kind: "mermaid".to_string(),
+            path: path.map(|path| path.to_string_lossy().to_string()),
+            updated_at,
+            contents: "graph TD".to_string(),
+        }
+    }
+
+    #[test]
+    fn only_file_name_safe_ids_are_accepted() {
+        assert!(is_valid_id("untitled-mermaid-1"));
+        assert!(is_valid_id("doc_42"));
+        for id in [
+            "",
+            "../recents",
+            "a/b",
+            "a b",
+            "draft.json",
+            &"x".repeat(129),
+        ] {
+            assert!(!is_valid_id(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn drafts_older_than_their_saved_file_are_not_listed() {
+        let dir = std::env::temp_dir().join(format!("excalibur-drafts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let saved = dir.join("flow.mmd");
+        fs::write(&saved, "graph TD").unwrap();
+        let saved_at = modified_millis(&saved).unwrap();
+
+        assert!(is_newer_than_saved(&draft(Some(&saved), saved_at + 1)));
+        assert!(!is_newer_than_saved(&draft(Some(&saved), saved_at)));
+        assert!(is_newer_than_saved(&draft(Some(&dir.join("gone.mmd")), 1)));
+        assert!(is_newer_than_saved(&draft(None, 1)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1104 @@
+use crate::error::CommandError;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use percent_encoding::percent_decode_str;
+use quick_xml::escape::{resolve_html5_entity, resolve_predefined_entity, unescape_with};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Extensions offered when picking a draw.io file to import.
+const DRAWIO_EXTENSIONS: &[&str] = &["drawio", "xml"];
+/// draw.io's label size when a cell's style doesn't set `fontSize`.
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+/// Space between a shape's border and its label.
+const LABEL_PADDING: f64 = 5.0;
+
+/// What a conversion did, shown to the user before the scene is saved.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ConversionReport {
+    /// Pages in the file; only the first one is converted.
+    pages: usize,
+    shapes: usize,
+    arrows: usize,
+    labels: usize,
+    /// Features without an excalidraw equivalent that were left out, with how many
+    /// times each occurred, e.g. `{"embedded images": 2}`.
+    dropped: BTreeMap<String, usize>,
+}
+
+impl ConversionReport {
+    fn record_dropped(&mut self, feature: &str) {
+        *self.dropped.entry(feature.to_string()).or_insert(0) += 1;
+    }
+}
+
+#[derive(Serialize)]
+pub struct DrawioImport {
+    source_path: String,
+    /// The generated excalidraw scene.
+    contents: String,
+    report: ConversionReport,
+    /// `<stem>.excalidraw` next to the source, where the frontend offers to save it.
+    suggested_path: String,
+    /// Saving to `suggested_path` would replace an existing file.
+    suggested_path_exists: bool,
+}
+
+/// An XML element with its attributes (unescaped) and text content.
+#[derive(Debug, Default)]
+struct Node {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// How errors name the element, e.g. `<mxCell id="4">`.
+    fn describe(&self) -> String {
+        match self.attribute("id") {
+            Some(id) => format!("<{} id=\"{}\">", self.name, id),
+            None => format!("<{}>", self.name),
+        }
+    }
+}
+
+fn failed(node: impl Into<String>, message: impl Into<String>) -> CommandError {
+    CommandError::ConversionFailed {
+        node: node.into(),
+        message: message.into(),
+    }
+}
+
+fn element(start: &BytesStart) -> Result<Node, CommandError> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+    let mut node = Node {
+        name,
+        ..Default::default()
+    };
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|error| failed(node.describe(), error.to_string()))?;
+        let value = attribute
+            .unescape_value()
+            .map_err(|error| failed(node.describe(), error.to_string()))?;
+        node.attributes.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).to_string(),
+            value.to_string(),
+        ));
+    }
+    Ok(node)
+}
+
+/// Reads `xml` into a tree under a `<document>` node.
+fn parse_xml(xml: &str) -> Result<Node, CommandError> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack = vec![Node {
+        name: "document".to_string(),
+        ..Default::default()
+    }];
+    loop {
+        let event = reader.read_event().map_err(|error| {
+            failed(
+                stack.last().map(Node::describe).unwrap_or_default(),
+                format!(
+                    "malformed XML at byte {}: {}",
+                    reader.error_position(),
+                    error
+                ),
+            )
+        })?;
+        let current = stack.last_mut().expect("the document node is never popped");
+        match event {
+            Event::Start(start) => {
+                let node = element(&start)?;
+                stack.push(node);
+            }
+            Event::Empty(start) => current.children.push(element(&start)?),
+            Event::End(_) => {
+                let node = stack.pop().expect("checked by the reader");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Err(failed(node.describe(), "closes an element never opened")),
+                }
+            }
+            Event::Text(text) => {
+                let text = text
+                    .decode()
+                    .map_err(|error| failed(current.describe(), error.to_string()))?;
+                current.text.push_str(&text);
+            }
+            Event::CData(data) => {
+                let data = data
+                    .decode()
+                    .map_err(|error| failed(current.describe(), error.to_string()))?;
+                current.text.push_str(&data);
+            }
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref() {
+                    Ok(Some(character)) => Some(character.to_string()),
+                    _ => reference
+                        .decode()
+                        .ok()
+                        .and_then(|name| resolve_predefined_entity(&name))
+                        .map(str::to_string),
+                };
+                let Some(resolved) = resolved else {
+                    return Err(failed(current.describe(), "contains an unknown entity"));
+                };
+                current.text.push_str(&resolved);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if stack.len() > 1 {
+        let unclosed = stack.pop().expect("just checked");
+        return Err(failed(unclosed.describe(), "is never closed"));
+    }
+    Ok(stack.pop().expect("just checked"))
+}
+
+/// Decodes a compressed page: base64 of raw deflate of the URL-encoded model XML.
+fn inflate(text: &str) -> Result<String, String> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let compressed = base64::engine::general_purpose::STANDARD
+        .decode(compact)
+        .map_err(|error| format!("compressed page is not base64: {error}"))?;
+    let mut inflated = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_string(&mut inflated)
+        .map_err(|error| format!("compressed page doesn't inflate: {error}"))?;
+    percent_decode_str(&inflated)
+        .decode_utf8()
+        .map(|decoded| decoded.to_string())
+        .map_err(|error| format!("compressed page is not UTF-8: {error}"))
+}
+
+/// The `<mxGraphModel>` of the first page, and how many pages there are. Files are
+/// either an `<mxfile>` of `<diagram>` pages, each holding a model or its compressed
+/// form, or a bare `<mxGraphModel>`.
+fn first_page(document: Node) -> Result<(Node, usize), CommandError> {
+    let Some(root) = document.children.into_iter().next() else {
+        return Err(failed("<document>", "is empty"));
+    };
+    match root.name.as_str() {
+        "mxGraphModel" => Ok((root, 1)),
+        "mxfile" => {
+            let description = root.describe();
+            let mut pages = root
+                .children
+                .into_iter()
+                .filter(|child| child.name == "diagram");
+            let Some(page) = pages.next() else {
+                return Err(failed(description, "has no <diagram> pages"));
+            };
+            let count = 1 + pages.count();
+            let description = page.describe();
+            if let Some(model) = page
+                .children
+                .into_iter()
+                .find(|child| child.name == "mxGraphModel")
+            {
+                return Ok((model, count));
+            }
+            if page.text.trim().is_empty() {
+                return Err(failed(description, "is empty"));
+            }
+            let xml = inflate(&page.text).map_err(|message| failed(&description, message))?;
+            match parse_xml(&xml)?.children.into_iter().next() {
+                Some(model) if model.name == "mxGraphModel" => Ok((model, count)),
+                _ => Err(failed(description, "doesn't hold an <mxGraphModel>")),
+            }
+        }
+        _ => Err(failed(
+            root.describe(),
+            "is not a draw.io diagram; expected <mxfile> or <mxGraphModel>",
+        )),
+    }
+}
+
+/// A cell's `style`: `shape;key=value;...`, where bare words like `ellipse` or
+/// `text` name the shape.
+#[derive(Debug, Default)]
+struct Style {
+    tokens: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl Style {
+    fn parse(style: &str) -> Style {
+        let mut parsed = Style::default();
+        for part in style
+            .split(';')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((key, value)) => {
+                    parsed.values.insert(key.to_string(), value.to_string());
+                }
+                None => parsed.tokens.push(part.to_string()),
+            }
+        }
+        parsed
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.get(key) == Some("1")
+    }
+
+    fn has(&self, token: &str) -> bool {
+        self.tokens.iter().any(|other| other == token)
+    }
+
+    fn shape(&self) -> Option<&str> {
+        self.get("shape")
+            .or(self.tokens.first().map(String::as_str))
+    }
+
+    fn number(&self, key: &str) -> Option<f64> {
+        self.get(key)?
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+    }
+
+    /// A draw.io color as excalidraw takes it; `none` is transparent.
+    fn color(&self, key: &str) -> Option<String> {
+        match self.get(key)? {
+            "none" => Some("transparent".to_string()),
+            "default" | "inherit" | "" => None,
+            color => Some(color.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Bounds {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Bounds {
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// Where a line from the center towards `toward` leaves the box.
+    fn exit_toward(&self, toward: (f64, f64)) -> (f64, f64) {
+        let (center_x, center_y) = self.center();
+        let (dx, dy) = (toward.0 - center_x, toward.1 - center_y);
+        let scale_x = if dx == 0.0 {
+            f64::INFINITY
+        } else {
+            self.width / 2.0 / dx.abs()
+        };
+        let scale_y = if dy == 0.0 {
+            f64::INFINITY
+        } else {
+            self.height / 2.0 / dy.abs()
+        };
+        let scale = scale_x.min(scale_y).min(1.0);
+        (center_x + dx * scale, center_y + dy * scale)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Geometry {
+    bounds: Bounds,
+    source_point: Option<(f64, f64)>,
+    target_point: Option<(f64, f64)>,
+    points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug)]
+struct Cell {
+    description: String,
+    id: String,
+    parent: Option<String>,
+    label: String,
+    link: Option<String>,
+    style: Style,
+    vertex: bool,
+    edge: bool,
+    visible: bool,
+    source: Option<String>,
+    target: Option<String>,
+    geometry: Option<Geometry>,
+    /// A `<UserObject>` carried data properties besides its label and link.
+    custom_properties: bool,
+}
+
+fn number(node: &Node, name: &str, owner: &str) -> Result<f64, CommandError> {
+    let Some(value) = node.attribute(name) else {
+        return Ok(0.0);
+    };
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| {
+            failed(
+                format!("{} in {}", node.describe(), owner),
+                format!("{name}=\"{value}\" is not a number"),
+            )
+        })
+}
+
+fn point(node: &Node, owner: &str) -> Result<(f64, f64), CommandError> {
+    Ok((number(node, "x", owner)?, number(node, "y", owner)?))
+}
+
+fn read_geometry(node: &Node, owner: &str) -> Result<Geometry, CommandError> {
+    let mut geometry = Geometry {
+        bounds: Bounds {
+            x: number(node, "x", owner)?,
+            y: number(node, "y", owner)?,
+            width: number(node, "width", owner)?,
+            height: number(node, "height", owner)?,
+        },
+        ..Default::default()
+    };
+    for child in &node.children {
+        match (child.name.as_str(), child.attribute("as")) {
+            ("mxPoint", Some("sourcePoint")) => geometry.source_point = Some(point(child, owner)?),
+            ("mxPoint", Some("targetPoint")) => geometry.target_point = Some(point(child, owner)?),
+            ("Array", Some("points")) => {
+                for waypoint in child.children.iter().filter(|node| node.name == "mxPoint") {
+                    geometry.points.push(point(waypoint, owner)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(geometry)
+}
+
+/// Attributes of a `<UserObject>` that are part of drawing it rather than data.
+const USER_OBJECT_ATTRIBUTES: [&str; 5] = ["id", "label", "link", "placeholders", "tooltip"];
+
+/// Reads an `<mxCell>`, or the `<UserObject>`/`<object>` wrapping one, which then
+/// holds the id, label and link.
+fn read_cell(node: &Node) -> Result<Cell, CommandError> {
+    let (cell, wrapper) = match node.name.as_str() {
+        "mxCell" => (node, None),
+        "UserObject" | "object" => (
+            node.child("mxCell")
+                .ok_or_else(|| failed(node.describe(), "has no <mxCell>"))?,
+            Some(node),
+        ),
+        _ => return Err(failed(node.describe(), "is not a cell")),
+    };
+    let holder = wrapper.unwrap_or(cell);
+    let description = holder.describe();
+    let id = holder
+        .attribute("id")
+        .ok_or_else(|| failed(&description, "has no id"))?
+        .to_string();
+    let vertex = cell.attribute("vertex") == Some("1");
+    let edge = cell.attribute("edge") == Some("1");
+    let geometry = match cell.child("mxGeometry") {
+        Some(geometry) => Some(read_geometry(geometry, &description)?),
+        None if vertex => return Err(failed(&description, "is a shape without <mxGeometry>")),
+        None => None,
+    };
+    Ok(Cell {
+        id,
+        parent: cell.attribute("parent").map(str::to_string),
+        label: holder
+            .attribute(if wrapper.is_some() { "label" } else { "value" })
+            .unwrap_or_default()
+            .to_string(),
+        link: wrapper
+            .and_then(|wrapper| wrapper.attribute("link"))
+            .map(str::to_string),
+        style: Style::parse(cell.attribute("style").unwrap_or_default()),
+        vertex,
+        edge,
+        visible: cell.attribute("visible") != Some("0"),
+        source: cell.attribute("source").map(str::to_string),
+        target: cell.attribute("target").map(str::to_string),
+        geometry,
+        custom_properties: wrapper.is_some_and(|wrapper| {
+            wrapper
+                .attributes
+                .iter()
+                .any(|(key, _)| !USER_OBJECT_ATTRIBUTES.contains(&key.as_str()))
+        }),
+        description,
+    })
+}
+
+/// Turns a label into plain text. HTML labels keep their line breaks; any other
+/// markup is dropped, which the returned flag reports.
+fn plain_label(value: &str, html: bool) -> (String, bool) {
+    if !html {
+        return (value.trim().to_string(), false);
+    }
+    let mut text = String::new();
+    let mut formatted = false;
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        match name {
+            "br" => text.push('\n'),
+            "div" | "p" | "li" => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            _ => formatted = true,
+        }
+        if tag.contains("style=") {
+            formatted = true;
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    let text = unescape_with(&text, resolve_html5_entity)
+        .map(|text| text.to_string())
+        .unwrap_or(text);
+    let lines: Vec<&str> = text.split('\n').map(str::trim).collect();
+    (
+        lines.join("\n").replace('\u{a0}', " ").trim().to_string(),
+        formatted,
+    )
+}
+
+/// Approximate size of `text` in excalidraw's Helvetica.
+fn text_size(text: &str, font_size: f64) -> (f64, f64) {
+    let lines = text.split('\n');
+    let widest = lines
+        .clone()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    (
+        widest as f64 * font_size * 0.6,
+        lines.count() as f64 * font_size * 1.25,
+    )
+}
+
+/// Converted elements, with their positions by id so bindings can be added later.
+#[derive(Default)]
+struct Scene {
+    elements: Vec<Map<String, Value>>,
+    index: HashMap<String, usize>,
+}
+
+impl Scene {
+    /// The properties every excalidraw element has, with the defaults of the
+    /// built-in templates, but drawn without roughness so diagrams keep draw.io's
+    /// clean look.
+    fn base(&self, id: &str, kind: &str, bounds: Bounds) -> Map<String, Value> {
+        let number = self.elements.len() as u64 + 1;
+        let element = json!({
+            "id": id,
+            "type": kind,
+            "x": bounds.x,
+            "y": bounds.y,
+            "width": bounds.width,
+            "height": bounds.height,
+            "angle": 0,
+            "strokeColor": "#1e1e1e",
+            "backgroundColor": "transparent",
+            "fillStyle": "solid",
+            "strokeWidth": 1,
+            "strokeStyle": "solid",
+            "roughness": 0,
+            "opacity": 100,
+            "groupIds": [],
+            "frameId": null,
+            "roundness": null,
+            "seed": number * 7919 % 2_147_483_647,
+            "version": 1,
+            "versionNonce": number * 104_729 % 2_147_483_647,
+            "isDeleted": false,
+            "boundElements": [],
+            "updated": 1,
+            "link": null,
+            "locked": false,
+        });
+        match element {
+            Value::Object(map) => map,
+            _ => unreachable!("json! of an object literal"),
+        }
+    }
+
+    fn add(&mut self, element: Map<String, Value>) {
+        let id = element["id"].as_str().unwrap_or_default().to_string();
+        self.index.insert(id, self.elements.len());
+        self.elements.push(element);
+    }
+
+    /// Lists element `id` in the `boundElements` of `container`.
+    fn bind(&mut self, container: &str, kind: &str, id: &str) {
+        if let Some(Value::Array(bound)) = self
+            .index
+            .get(container)
+            .and_then(|&index| self.elements[index].get_mut("boundElements"))
+        {
+            bound.push(json!({ "type": kind, "id": id }));
+        }
+    }
+
+    fn add_text(
+        &mut self,
+        id: &str,
+        text: &str,
+        bounds: Bounds,
+        style: &Style,
+        container: Option<&str>,
+    ) {
+        let mut element = self.base(id, "text", bounds);
+        element.insert(
+            "strokeColor".into(),
+            json!(style.color("fontColor").unwrap_or_else(|| "#1e1e1e".into())),
+        );
+        element.insert("text".into(), json!(text));
+        element.insert("originalText".into(), json!(text));
+        element.insert(
+            "fontSize".into(),
+            json!(style.number("fontSize").unwrap_or(DEFAULT_FONT_SIZE)),
+        );
+        element.insert("fontFamily".into(), json!(2));
+        element.insert(
+            "textAlign".into(),
+            json!(match style.get("align") {
+                Some("left") => "left",
+                Some("right") => "right",
+                _ => "center",
+            }),
+        );
+        element.insert(
+            "verticalAlign".into(),
+            json!(match style.get("verticalAlign") {
+                Some("top") => "top",
+                Some("bottom") => "bottom",
+                _ => "middle",
+            }),
+        );
+        element.insert("containerId".into(), json!(container));
+        element.insert("lineHeight".into(), json!(1.25));
+        element.insert("autoResize".into(), json!(container.is_some()));
+        self.add(element);
+        if let Some(container) = container {
+            self.bind(container, "text", id);
+        }
+    }
+}
+
+/// Where a label sits inside `bounds`, following its vertical alignment.
+fn label_bounds(text: &str, bounds: Bounds, style: &Style) -> Bounds {
+    let font_size = style.number("fontSize").unwrap_or(DEFAULT_FONT_SIZE);
+    let (width, height) = text_size(text, font_size);
+    let width = width.min((bounds.width - 2.0 * LABEL_PADDING).max(font_size));
+    let (center_x, center_y) = bounds.center();
+    let y = match style.get("verticalAlign") {
+        Some("top") => bounds.y + LABEL_PADDING,
+        Some("bottom") => bounds.y + bounds.height - height - LABEL_PADDING,
+        _ => center_y - height / 2.0,
+    };
+    Bounds {
+        x: center_x - width / 2.0,
+        y,
+        width,
+        height,
+    }
+}
+
+/// Excalidraw's arrowhead closest to a draw.io `startArrow`/`endArrow`.
+fn arrowhead(style: &Style, key: &str, default: &str) -> Value {
+    match style.get(key).unwrap_or(default) {
+        "none" | "" => Value::Null,
+        "open" | "openThin" | "openAsync" => json!("arrow"),
+        "oval" | "circle" => json!("dot"),
+        "dash" | "ERmandOne" | "ERone" => json!("bar"),
+        _ => json!("triangle"),
+    }
+}
+
+/// Converts the first page of a draw.io file into an excalidraw scene. Shapes
+/// become rectangles, ellipses, diamonds or text with their labels, edges become
+/// arrows bound to their shapes, and positions are kept. Files that can't be read
+/// fail with `ConversionFailed` naming the first element that couldn't be parsed.
+fn convert(xml: &str) -> Result<(Value, ConversionReport), CommandError> {
+    let (model, pages) = first_page(parse_xml(xml)?)?;
+    let root = model
+        .child("root")
+        .ok_or_else(|| failed(model.describe(), "has no <root>"))?;
+    let cells = root
+        .children
+        .iter()
+        .map(read_cell)
+        .collect::<Result<Vec<_>, _>>()?;
+    let by_id: HashMap<&str, &Cell> = cells.iter().map(|cell| (cell.id.as_str(), cell)).collect();
+
+    let mut report = ConversionReport {
+        pages,
+        ..Default::default()
+    };
+    for _ in 1..pages {
+        report.record_dropped("additional pages");
+    }
+
+    // Geometry of shapes inside groups and containers is relative to their parent.
+    let absolute = |cell: &Cell| -> Bounds {
+        let mut bounds = cell.geometry.as_ref().map(|g| g.bounds).unwrap_or_default();
+        let mut parent = cell.parent.as_deref();
+        for _ in 0..cells.len() {
+            let Some(owner) = parent.and_then(|id| by_id.get(id)) else {
+                break;
+            };
+            if owner.vertex {
+                if let Some(geometry) = &owner.geometry {
+                    bounds.x += geometry.bounds.x;
+                    bounds.y += geometry.bounds.y;
+                }
+            }
+            parent = owner.parent.as_deref();
+        }
+        bounds
+    };
+    let is_edge = |id: Option<&str>| {
+        id.and_then(|id| by_id.get(id))
+            .is_some_and(|cell| cell.edge)
+    };
+
+    let mut scene = Scene::default();
+    let mut shape_bounds: HashMap<&str, Bounds> = HashMap::new();
+    let mut edge_labels: HashMap<&str, Vec<String>> = HashMap::new();
+
+    for cell in cells.iter().filter(|cell| cell.vertex) {
+        if !cell.visible {
+            report.record_dropped("hidden shapes");
+            continue;
+        }
+        let (label, formatted) = plain_label(&cell.label, cell.style.flag("html"));
+        if formatted {
+            report.record_dropped("rich text formatting");
+        }
+        if cell.custom_properties {
+            report.record_dropped("custom properties");
+        }
+        // Labels placed along an edge are children of it.
+        if is_edge(cell.parent.as_deref()) || cell.style.has("edgeLabel") {
+            if let Some(edge) = cell.parent.as_deref().filter(|&id| is_edge(Some(id))) {
+                if !label.is_empty() {
+                    edge_labels.entry(edge).or_default().push(label);
+                }
+            }
+            continue;
+        }
+
+        let bounds = absolute(cell);
+        shape_bounds.insert(&cell.id, bounds);
+        let shape = cell.style.shape().unwrap_or("rectangle");
+        if cell.style.get("image").is_some() || shape == "image" {
+            report.record_dropped("embedded images");
+            continue;
+        }
+        if shape == "group" {
+            continue;
+        }
+        if shape == "text" {
+            if !label.is_empty() {
+                scene.add_text(&cell.id, &label, bounds, &cell.style, None);
+                report.labels += 1;
+            }
+            continue;
+        }
+        let kind = match shape {
+            "ellipse" | "doubleEllipse" => "ellipse",
+            "rhombus" => "diamond",
+            "rectangle" | "rect" | "label" | "swimlane" => "rectangle",
+            custom => {
+                log::info!(
+                    "drawio: {} has shape {}, drawn as a rectangle",
+                    cell.description,
+                    custom
+                );
+                report.record_dropped("custom shapes");
+                "rectangle"
+            }
+        };
+        let mut element = scene.base(&cell.id, kind, bounds);
+        if let Some(color) = cell.style.color("strokeColor") {
+            element.insert("strokeColor".into(), json!(color));
+        }
+        if let Some(color) = cell.style.color("fillColor") {
+            element.insert("backgroundColor".into(), json!(color));
+        }
+        if let Some(width) = cell.style.number("strokeWidth") {
+            element.insert("strokeWidth".into(), json!(width));
+        }
+        if cell.style.flag("dashed") {
+            element.insert("strokeStyle".into(), json!("dashed"));
+        }
+        if let Some(opacity) = cell.style.number("opacity") {
+            element.insert("opacity".into(), json!(opacity));
+        }
+        if let Some(rotation) = cell.style.number("rotation") {
+            element.insert("angle".into(), json!(rotation.to_radians()));
+        }
+        if cell.style.flag("rounded") && kind == "rectangle" {
+            element.insert("roundness".into(), json!({ "type": 3 }));
+        }
+        if let Some(link) = &cell.link {
+            element.insert("link".into(), json!(link));
+        }
+        if cell.style.flag("shadow") {
+            report.record_dropped("shadows");
+        }
+        if cell
+            .style
+            .get("gradientColor")
+            .is_some_and(|color| color != "none")
+        {
+            report.record_dropped("gradients");
+        }
+        scene.add(element);
+        report.shapes += 1;
+        if !label.is_empty() {
+            let label_id = format!("{}-label", cell.id);
+            let placed = label_bounds(&label, bounds, &cell.style);
+            scene.add_text(&label_id, &label, placed, &cell.style, Some(&cell.id));
+            report.labels += 1;
+        }
+    }
+
+    for cell in cells.iter().filter(|cell| cell.edge) {
+        if !cell.visible {
+            report.record_dropped("hidden arrows");
+            continue;
+        }
+        let geometry = cell.geometry.as_ref();
+        let offset = absolute(cell);
+        let shift = |(x, y): (f64, f64)| (x + offset.x, y + offset.y);
+        let source = cell.source.as_deref().and_then(|id| shape_bounds.get(id));
+        let target = cell.target.as_deref().and_then(|id| shape_bounds.get(id));
+        let start = source
+            .map(Bounds::center)
+            .or_else(|| geometry.and_then(|g| g.source_point).map(shift))
+            .ok_or_else(|| {
+                failed(
+                    &cell.description,
+                    "is an edge with neither a source nor a sourcePoint",
+                )
+            })?;
+        let end = target
+            .map(Bounds::center)
+            .or_else(|| geometry.and_then(|g| g.target_point).map(shift))
+            .ok_or_else(|| {
+                failed(
+                    &cell.description,
+                    "is an edge with neither a target nor a targetPoint",
+                )
+            })?;
+        let mut path = vec![start];
+        path.extend(
+            geometry
+                .iter()
+                .flat_map(|g| g.points.iter().copied().map(shift)),
+        );
+        path.push(end);
+        if let Some(source) = source {
+            path[0] = source.exit_toward(path[1]);
+        }
+        if let Some(target) = target {
+            let last = path.len() - 1;
+            path[last] = target.exit_toward(path[last - 1]);
+        }
+
+        let (min_x, max_x) = path.iter().fold((f64::MAX, f64::MIN), |(low, high), p| {
+            (low.min(p.0), high.max(p.0))
+        });
+        let (min_y, max_y) = path.iter().fold((f64::MAX, f64::MIN), |(low, high), p| {
+            (low.min(p.1), high.max(p.1))
+        });
+        let start = path[0];
+        let mut element = scene.base(
+            &cell.id,
+            "arrow",
+            Bounds {
+                x: start.0,
+                y: start.1,
+                width: max_x - min_x,
+                height: max_y - min_y,
+            },
+        );
+        element.insert(
+            "points".into(),
+            json!(path
+                .iter()
+                .map(|(x, y)| [x - start.0, y - start.1])
+                .collect::<Vec<_>>()),
+        );
+        if let Some(color) = cell.style.color("strokeColor") {
+            element.insert("strokeColor".into(), json!(color));
+        }
+        if let Some(width) = cell.style.number("strokeWidth") {
+            element.insert("strokeWidth".into(), json!(width));
+        }
+        if cell.style.flag("dashed") {
+            element.insert("strokeStyle".into(), json!("dashed"));
+        }
+        if cell.style.flag("curved") {
+            element.insert("roundness".into(), json!({ "type": 2 }));
+        }
+        let binding = |shape: Option<&str>| {
+            shape
+                .filter(|id| shape_bounds.contains_key(id))
+                .map(|id| json!({ "elementId": id, "focus": 0, "gap": 1 }))
+                .unwrap_or(Value::Null)
+        };
+        element.insert("startBinding".into(), binding(cell.source.as_deref()));
+        element.insert("endBinding".into(), binding(cell.target.as_deref()));
+        element.insert(
+            "startArrowhead".into(),
+            arrowhead(&cell.style, "startArrow", "none"),
+        );
+        element.insert(
+            "endArrowhead".into(),
+            arrowhead(&cell.style, "endArrow", "classic"),
+        );
+        element.insert("lastCommittedPoint".into(), Value::Null);
+        element.insert("elbowed".into(), json!(false));
+        if let Some(link) = &cell.link {
+            element.insert("link".into(), json!(link));
+        }
+        scene.add(element);
+        report.arrows += 1;
+        for shape in [cell.source.as_deref(), cell.target.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|id| shape_bounds.contains_key(id))
+        {
+            scene.bind(shape, "arrow", &cell.id);
+        }
+
+        let (label, formatted) = plain_label(&cell.label, cell.style.flag("html"));
+        if formatted {
+            report.record_dropped("rich text formatting");
+        }
+        let mut labels: Vec<String> = Some(label)
+            .filter(|label| !label.is_empty())
+            .into_iter()
+            .collect();
+        labels.extend(edge_labels.remove(cell.id.as_str()).unwrap_or_default());
+        if !labels.is_empty() {
+            let text = labels.join("\n");
+            let middle = path.len() / 2;
+            let (x, y) = if path.len() % 2 == 1 {
+                path[middle]
+            } else {
+                let (a, b) = (path[middle - 1], path[middle]);
+                ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+            };
+            let font_size = cell.style.number("fontSize").unwrap_or(DEFAULT_FONT_SIZE);
+            let (width, height) = text_size(&text, font_size);
+            let bounds = Bounds {
+                x: x - width / 2.0,
+                y: y - height / 2.0,
+                width,
+                height,
+            };
+            scene.add_text(
+                &format!("{}-label", cell.id),
+                &text,
+                bounds,
+                &cell.style,
+                Some(&cell.id),
+            );
+            report.labels += 1;
+        }
+    }
+
+    let scene = json!({
+        "type": "excalidraw",
+        "version": 2,
+        "source": "excalibur",
+        "elements": scene.elements,
+        "appState": { "viewBackgroundColor": "#ffffff" },
+        "files": {},
+    });
+    Ok((scene, report))
+}
+
+/// Converts a draw.io file into an excalidraw scene, or lets the user pick one when
+/// `path` is `None`; `None` comes back when they cancel. Nothing is written: the
+/// frontend shows the report and offers to save the scene at `suggested_path`.
+#[tauri::command]
+pub async fn import_drawio(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<Option<DrawioImport>, CommandError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match crate::dialogs::pick_open_path(&app, "draw.io", DRAWIO_EXTENSIONS).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+    let xml = fs::read_to_string(&path)?;
+    let (scene, report) = convert(&xml)?;
+    let contents = serde_json::to_string_pretty(&scene).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    let suggested_path = path.with_extension("excalidraw");
+    log::info!(
+        "import_drawio: {} -> {} shapes, {} arrows, {} labels, dropped {:?}",
+        path.display(),
+        report.shapes,
+        report.arrows,
+        report.labels,
+        report.dropped
+    );
+    Ok(Some(DrawioImport {
+        source_path: path.to_string_lossy().to_string(),
+        contents,
+        report,
+        suggested_path_exists: suggested_path.exists(),
+        suggested_path: suggested_path.to_string_lossy().to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = include_str!("../tests/fixtures/drawio/basic.drawio");
+    const COMPRESSED: &str = include_str!("../tests/fixtures/drawio/compressed.drawio");
+    const MALFORMED: &str = include_str!("../tests/fixtures/drawio/malformed.drawio");
+
+    fn elements(scene: &Value) -> &Vec<Value> {
+        scene["elements"].as_array().unwrap()
+    }
+
+    fn find<'a>(scene: &'a Value, id: &str) -> &'a Value {
+        elements(scene)
+            .iter()
+            .find(|element| element["id"] == id)
+            .unwrap_or_else(|| panic!("no element {id}"))
+    }
+
+    #[test]
+    fn converts_shapes_edges_and_labels() {
+        let (scene, report) = convert(BASIC).unwrap();
+        assert_eq!(report.pages, 2);
+        assert_eq!(report.shapes, 3);
+        assert_eq!(report.arrows, 2);
+        assert_eq!(report.labels, 6);
+        assert_eq!(
+            report.dropped,
+            BTreeMap::from([
+                ("additional pages".to_string(), 1),
+                ("custom properties".to_string(), 1),
+                ("embedded images".to_string(), 1),
+                ("rich text formatting".to_string(), 1),
+            ])
+        );
+
+        let start = find(&scene, "start");
+        assert_eq!(start["type"], "rectangle");
+        assert_eq!(start["x"], 120.0);
+        assert_eq!(start["backgroundColor"], "#dae8fc");
+        assert_eq!(start["roundness"]["type"], 3);
+        assert_eq!(start["link"], "https://example.com/start");
+        assert_eq!(find(&scene, "start-label")["text"], "Start here");
+        assert_eq!(find(&scene, "start-label")["containerId"], "start");
+
+        assert_eq!(find(&scene, "decide")["type"], "diamond");
+        // Inside a group at (300, 100).
+        let done = find(&scene, "done");
+        assert_eq!(done["type"], "ellipse");
+        assert_eq!(
+            (done["x"].as_f64(), done["y"].as_f64()),
+            (Some(320.0), Some(120.0))
+        );
+        assert_eq!(find(&scene, "done-label")["text"], "Done\nfor now");
+        assert_eq!(find(&scene, "note")["text"], "A note");
+
+        let arrow = find(&scene, "flow");
+        assert_eq!(arrow["startBinding"]["elementId"], "start");
+        assert_eq!(arrow["endBinding"]["elementId"], "decide");
+        assert_eq!(arrow["endArrowhead"], "triangle");
+        // Leaves the right edge of "start" and goes through the waypoint.
+        assert_eq!(arrow["x"], 240.0);
+        assert_eq!(arrow["points"].as_array().unwrap().len(), 3);
+        assert_eq!(find(&scene, "flow-label")["text"], "yes");
+        assert_eq!(find(&scene, "flow-label")["containerId"], "flow");
+        let bound = start["boundElements"].as_array().unwrap();
+        assert!(bound.contains(&json!({ "type": "arrow", "id": "flow" })));
+
+        let loose = find(&scene, "loose");
+        assert_eq!(loose["startBinding"], Value::Null);
+        assert_eq!(loose["endArrowhead"], Value::Null);
+        assert_eq!(
+            (loose["x"].as_f64(), loose["y"].as_f64()),
+            (Some(40.0), Some(300.0))
+        );
+    }
+
+    #[test]
+    fn inflates_compressed_pages() {
+        let (scene, report) = convert(COMPRESSED).unwrap();
+        assert_eq!(report.shapes, 2);
+        assert_eq!(report.arrows, 1);
+        assert_eq!(find(&scene, "a-label")["text"], "Alpha & beta");
+    }
+
+    #[test]
+    fn names_the_first_unparseable_node() {
+        match convert(MALFORMED) {
+            Err(CommandError::ConversionFailed { node, message }) => {
+                assert_eq!(node, "<mxGeometry> in <mxCell id=\"broken\">");
+                assert_eq!(message, "width=\"wide\" is not a number");
+            }
+            other => panic!("expected ConversionFailed, got {other:?}"),
+        }
+        match convert("<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>") {
+            Err(CommandError::ConversionFailed { node, .. }) => assert_eq!(node, "<svg>"),
+            other => panic!("expected ConversionFailed, got {other:?}"),
+        }
+        match convert("<mxfile><diagram id=\"p1\"><mxGraphModel><root>") {
+            Err(CommandError::ConversionFailed { node, .. }) => assert_eq!(node, "<root>"),
+            other => panic!("expected ConversionFailed, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::CommandError;
+use crate::{load_recents, open_path, open_recent, paths, OpenPathResponse};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, WebviewWindow};
+
+/// Extensions made of two parts, kept whole when naming a copy.
+const COMPOUND_EXTENSIONS: [&str; 1] = [".excalidraw.png"];
+
+/// Splits a file name into its base and extension (with the dot), the extension
+/// being what follows the last dot, or a compound one like `.excalidraw.png`.
+fn split_name(name: &str) -> (&str, &str) {
+    for extension in COMPOUND_EXTENSIONS {
+        let Some(dot) = name.len().checked_sub(extension.len()) else {
+            continue;
+        };
+        if dot > 0 && name.is_char_boundary(dot) && name[dot..].eq_ignore_ascii_case(extension) {
+            return name.split_at(dot);
+        }
+    }
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+/// `base` without a " copy" or " copy N" suffix, so copies of a copy are numbered
+/// rather than piling up "copy copy".
+fn original_base(base: &str) -> &str {
+    let Some((original, suffix)) = base.rsplit_once(" copy") else {
+        return base;
+    };
+    let number = suffix.strip_prefix(' ').unwrap_or(suffix);
+    if suffix.is_empty() || (suffix.starts_with(' ') && number.parse::<u32>().is_ok()) {
+        original
+    } else {
+        base
+    }
+}
+
+/// Names to try for a copy of `path`, next to it: "name copy.ext", then
+/// "name copy 2.ext" and up.
+fn copy_candidates(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (base, extension) = split_name(&name);
+    let base = original_base(base).to_string();
+    let extension = extension.to_string();
+    (1..).map(move |number| {
+        let suffix = match number {
+            1 => " copy".to_string(),
+            _ => format!(" copy {number}"),
+        };
+        path.with_file_name(format!("{base}{suffix}{extension}"))
+    })
+}
+
+/// Copies `path` byte for byte to the first free copy name and returns it. The copy
+/// is created exclusively, so a file appearing in the meantime is never replaced.
+fn copy_to_free_name(path: &Path) -> Result<PathBuf, CommandError> {
+    let mut source = fs::File::open(path).map_err(|error| CommandError::from_io(error, path))?;
+    for candidate in copy_candidates(path) {
+        let mut target = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(target) => target,
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(CommandError::from_io(error, &candidate)),
+        };
+        if let Err(error) = io::copy(&mut source, &mut target) {
+            let _ = fs::remove_file(&candidate);
+            return Err(CommandError::from_io(error, &candidate));
+        }
+        return Ok(candidate);
+    }
+    unreachable!("copy names are endless")
+}
+
+/// Copies the document at `path` to a sibling "copy" file and opens the copy, which
+/// also adds it to recents. Its kind comes from the original's recents entry, or is
+/// detected like any opened file.
+#[tauri::command]
+pub async fn duplicate_file(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+) -> Result<OpenPathResponse, CommandError> {
+    let source = paths::normalize(Path::new(&path));
+    let copy = copy_to_free_name(&source)?;
+    let copy_path = copy.to_string_lossy().to_string();
+    log::info!("duplicate_file: {} -> {}", path, copy_path);
+    let source_path = source.to_string_lossy().to_string();
+    let recent_kind = load_recents(&app)
+        .into_iter()
+        .find(|item| item.path == source_path)
+        .map(|item| item.kind);
+    match recent_kind {
+        Some(kind) => open_recent(app, window, kind, copy_path, None, None, None).await,
+        None => open_path(app, window, copy_path, None, None, None, None).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_are_numbered_after_existing_ones() {
+        let dir = std::env::temp_dir().join(format!("excalibur-duplicate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("flow.excalidraw");
+        let bytes = b"{\"type\":\"excalidraw\"}\r\n\xef\xbb\xbf";
+        fs::write(&original, bytes).unwrap();
+        fs::write(dir.join("flow copy.excalidraw"), "taken").unwrap();
+
+        let copy = copy_to_free_name(&original).unwrap();
+        assert_eq!(copy, dir.join("flow copy 2.excalidraw"));
+        assert_eq!(fs::read(&copy).unwrap(), bytes);
+        assert_eq!(
+            copy_to_free_name(&copy).unwrap(),
+            dir.join("flow copy 3.excalidraw")
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn copy_names_keep_the_extension() {
+        let first = |name: &str| {
+            copy_candidates(Path::new(name))
+                .next()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+        assert_eq!(first("plan.mmd"), "plan copy.mmd");
+        assert_eq!(first("Flow.Excalidraw.png"), "Flow copy.Excalidraw.png");
+        assert_eq!(first("v1.2 plan.md"), "v1.2 plan copy.md");
+        assert_eq!(first(".notes"), ".notes copy");
+        assert_eq!(first("README"), "README copy");
+        assert_eq!(first("plan copy 4.mmd"), "plan copy.mmd");
+        assert_eq!(first("copycat.mmd"), "copycat copy.mmd");
+        assert_eq!(first("plan copy x.mmd"), "plan copy x copy.mmd");
+    }
+}
@@ -0,0 +1,349 @@
+use crate::error::CommandError;
+use crate::operations::Operation;
+use crate::scan::{kind_for_extension, scan_workspace};
+use crate::{content_hash, file_status};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Scenes larger than this are left out of the near-duplicate pass.
+const MAX_SCENE_BYTES: u64 = 32 * 1024 * 1024;
+/// Element fields that change on every save without changing the drawing.
+const VOLATILE_ELEMENT_FIELDS: [&str; 3] = ["version", "versionNonce", "updated"];
+
+/// Which files `find_duplicate_diagrams` compares.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DuplicateScope {
+    /// Recent files and favorites.
+    Recents,
+    /// Every diagram under the registered roots.
+    Roots,
+    /// Every diagram under one folder.
+    Folder { path: String },
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateFile {
+    path: String,
+    size: u64,
+    modified_at: Option<u64>,
+}
+
+/// Files with the same content hash, oldest first so the original is listed first.
+#[derive(Serialize, Debug)]
+pub struct DuplicateGroup {
+    hash: String,
+    files: Vec<DuplicateFile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateReport {
+    files_checked: usize,
+    /// Byte-for-byte identical files.
+    identical: Vec<DuplicateGroup>,
+    /// Excalidraw scenes that differ on disk but draw the same elements, e.g. because
+    /// only the view (`appState`) changed. Their `hash` is of the normalized scene.
+    similar: Vec<DuplicateGroup>,
+}
+
+/// SHA-256 of a file, read in chunks so large files aren't loaded whole.
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash of a scene's live elements, ignoring `appState`, embedded files' metadata and
+/// the per-save bookkeeping fields. `None` for anything that isn't a scene.
+fn normalized_scene_hash(contents: &str) -> Option<String> {
+    let scene: Value = serde_json::from_str(contents).ok()?;
+    let elements: Vec<Value> = scene
+        .get("elements")?
+        .as_array()?
+        .iter()
+        .filter(|element| element.get("isDeleted").and_then(Value::as_bool) != Some(true))
+        .map(|element| {
+            let mut element = element.clone();
+            if let Some(fields) = element.as_object_mut() {
+                for field in VOLATILE_ELEMENT_FIELDS {
+                    fields.remove(field);
+                }
+            }
+            element
+        })
+        .collect();
+    // serde_json objects keep their keys sorted, so this is canonical.
+    let canonical = serde_json::to_string(&elements).ok()?;
+    Some(content_hash(canonical.as_bytes()))
+}
+
+fn duplicate_file(path: &Path, size: u64) -> DuplicateFile {
+    DuplicateFile {
+        path: path.to_string_lossy().to_string(),
+        size,
+        modified_at: file_status(path).modified_at,
+    }
+}
+
+/// Turns hash -> files into groups of two or more, oldest file first and the largest
+/// groups first.
+fn into_groups(by_hash: HashMap<String, Vec<DuplicateFile>>) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, mut files)| {
+            files.sort_by(|a, b| a.modified_at.cmp(&b.modified_at).then(a.path.cmp(&b.path)));
+            DuplicateGroup { hash, files }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.files
+            .len()
+            .cmp(&a.files.len())
+            .then(a.files[0].path.cmp(&b.files[0].path))
+    });
+    groups
+}
+
+/// Groups identical files: sizes first, so only files sharing a size get hashed, then
+/// a second pass over excalidraw scenes for ones that only differ outside their elements.
+fn find_duplicates(
+    files: Vec<PathBuf>,
+    operation: &Operation,
+) -> Result<DuplicateReport, CommandError> {
+    let files: Vec<(u64, PathBuf)> = files
+        .into_iter()
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.len(), path)))
+        .collect();
+    let mut size_counts: HashMap<u64, usize> = HashMap::new();
+    for (size, _) in &files {
+        *size_counts.entry(*size).or_default() += 1;
+    }
+    let same_size: Vec<&(u64, PathBuf)> = files
+        .iter()
+        .filter(|(size, _)| size_counts[size] > 1)
+        .collect();
+
+    let total = same_size.len() as u64;
+    let mut identical: HashMap<String, Vec<DuplicateFile>> = HashMap::new();
+    for (done, (size, path)) in same_size.into_iter().enumerate() {
+        operation.check()?;
+        let item = path.to_string_lossy();
+        operation.progress("hashing", done as u64, Some(total), Some(&item));
+        match hash_file(path) {
+            Ok(hash) => identical
+                .entry(hash)
+                .or_default()
+                .push(duplicate_file(path, *size)),
+            Err(error) => log::info!("find_duplicates: skipping {}: {}", item, error),
+        }
+    }
+
+    // One file per identical group is enough for the scene comparison, since
+    // `similar` only reports scenes whose bytes differ.
+    let copies: HashSet<PathBuf> = identical
+        .values()
+        .flat_map(|group| group.iter().skip(1))
+        .map(|file| PathBuf::from(&file.path))
+        .collect();
+    let scenes: Vec<&(u64, PathBuf)> = files
+        .iter()
+        .filter(|(size, path)| {
+            *size <= MAX_SCENE_BYTES
+                && kind_for_extension(path) == Some("excalidraw")
+                && !copies.contains(path)
+        })
+        .collect();
+    let total = scenes.len() as u64;
+    let mut similar: HashMap<String, Vec<DuplicateFile>> = HashMap::new();
+    for (done, (size, path)) in scenes.into_iter().enumerate() {
+        operation.check()?;
+        let item = path.to_string_lossy();
+        operation.progress("comparing", done as u64, Some(total), Some(&item));
+        if let Some(hash) = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| normalized_scene_hash(&contents))
+        {
+            similar
+                .entry(hash)
+                .or_default()
+                .push(duplicate_file(path, *size));
+        }
+    }
+
+    Ok(DuplicateReport {
+        files_checked: files.len(),
+        identical: into_groups(identical),
+        similar: into_groups(similar),
+    })
+}
+
+/// The diagram files a scope covers, without repeats.
+fn scope_files(app: &AppHandle, scope: &DuplicateScope) -> Result<Vec<PathBuf>, CommandError> {
+    let paths: Vec<PathBuf> = match scope {
+        DuplicateScope::Recents => crate::load_recents(app)
+            .into_iter()
+            .map(|item| item.path)
+            .chain(crate::favorites::favorite_paths(app))
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect(),
+        DuplicateScope::Roots => crate::roots::load_roots(app)
+            .iter()
+            .flat_map(|root| scan_workspace(Path::new(&root.path)))
+            .map(|file| file.path)
+            .collect(),
+        DuplicateScope::Folder { path } => {
+            if !Path::new(path).is_dir() {
+                return Err(CommandError::InvalidArgument {
+                    message: format!("\"{path}\" is not a folder"),
+                });
+            }
+            scan_workspace(Path::new(path))
+                .into_iter()
+                .map(|file| file.path)
+                .collect()
+        }
+    };
+    let mut seen = HashSet::new();
+    Ok(paths
+        .into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .collect())
+}
+
+/// Finds diagrams with identical contents, and excalidraw scenes that draw the same
+/// elements, within `scope`. Reports progress as `operation-progress` events and stops
+/// on `cancel_operation`. Nothing is deleted; the UI decides which copies to keep.
+#[tauri::command]
+pub async fn find_duplicate_diagrams(
+    app: AppHandle,
+    scope: DuplicateScope,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> Result<DuplicateReport, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = Operation::start(&app, "duplicates", operation_id, true)
+            .notify_when_done(notify_when_done);
+        operation.progress("scanning", 0, None, None);
+        let outcome =
+            scope_files(&app, &scope).and_then(|files| find_duplicates(files, &operation));
+        let checked = outcome.as_ref().map_or(0, |report| report.files_checked);
+        operation.finish(&outcome, checked as u64);
+        let summary = match &outcome {
+            Ok(report) => format!(
+                "Found {} sets of identical diagrams in {} files",
+                report.identical.len(),
+                report.files_checked
+            ),
+            Err(CommandError::Cancelled) => "Duplicate search was cancelled".to_string(),
+            Err(error) => format!("Duplicate search failed: {error}"),
+        };
+        operation.notify_done(&summary, outcome.as_ref().ok());
+        outcome
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::CancellationToken;
+    use crate::test_support::temp_dir;
+    use std::collections::BTreeMap;
+
+    fn scene(app_state: &str, version: u32) -> String {
+        format!(
+            r#"{{"type":"excalidraw","elements":[{{"id":"a","type":"rectangle","version":{version}}}],"appState":{app_state}}}"#
+        )
+    }
+
+    fn paths(group: &DuplicateGroup) -> Vec<&str> {
+        let mut paths: Vec<&str> = group.files.iter().map(|file| file.path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn groups_identical_and_similar_files() {
+        let dir = temp_dir("duplicates", "groups");
+        let files: BTreeMap<&str, String> = [
+            ("a/flow.mmd", "graph TD; A-->B".to_string()),
+            ("b/flow.mmd", "graph TD; A-->B".to_string()),
+            ("c/other.mmd", "graph TD; A-->C".to_string()),
+            ("one.excalidraw", scene(r#"{"zoom":1}"#, 1)),
+            ("two.excalidraw", scene(r#"{"zoom":2.5,"scrollX":40}"#, 7)),
+            ("three.excalidraw", scene(r#"{"zoom":1}"#, 1)),
+        ]
+        .into_iter()
+        .collect();
+        for (name, contents) in &files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let inputs: Vec<PathBuf> = files.keys().map(|name| dir.join(name)).collect();
+        let operation = Operation::detached("duplicates", CancellationToken::default(), |_| {});
+
+        let report = find_duplicates(inputs, &operation).unwrap();
+        assert_eq!(report.files_checked, 6);
+        assert_eq!(report.identical.len(), 2);
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let identical: Vec<Vec<&str>> = report.identical.iter().map(paths).collect();
+        assert!(identical.contains(&vec![
+            path("a/flow.mmd").as_str(),
+            path("b/flow.mmd").as_str()
+        ]));
+        assert!(identical.contains(&vec![
+            path("one.excalidraw").as_str(),
+            path("three.excalidraw").as_str()
+        ]));
+
+        // `three` is a byte copy of `one`, so only one of them is compared with `two`.
+        assert_eq!(report.similar.len(), 1);
+        assert_eq!(report.similar[0].files.len(), 2);
+        assert!(paths(&report.similar[0]).contains(&path("two.excalidraw").as_str()));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let dir = temp_dir("duplicates", "cancel");
+        for name in ["a.mmd", "b.mmd"] {
+            fs::write(dir.join(name), "graph TD").unwrap();
+        }
+        let token = CancellationToken::default();
+        token.cancel();
+        let operation = Operation::detached("duplicates", token, |_| {});
+        assert!(matches!(
+            find_duplicates(vec![dir.join("a.mmd"), dir.join("b.mmd")], &operation),
+            Err(CommandError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn deleted_elements_and_bookkeeping_do_not_count() {
+        let live = r#"{"elements":[{"id":"a","type":"text","version":1,"updated":5}]}"#;
+        let edited = r#"{"elements":[{"id":"a","type":"text","version":9,"updated":8},{"id":"b","isDeleted":true}],"appState":{"theme":"dark"}}"#;
+        let moved = r#"{"elements":[{"id":"a","type":"text","x":10}]}"#;
+        assert_eq!(normalized_scene_hash(live), normalized_scene_hash(edited));
+        assert_ne!(normalized_scene_hash(live), normalized_scene_hash(moved));
+        assert_eq!(normalized_scene_hash("graph TD"), None);
+    }
+}
@@ -0,0 +1,799 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Error returned by commands, so the frontend can tell failures apart by `code`.
+///
+/// Serializes as `{ "code": "...", "message": "..." }` plus any variant-specific
+/// fields, so existing error toasts can keep showing `message`.
+#[derive(Debug)]
+pub enum CommandError {
+    Cancelled,
+    NotFound {
+        path: String,
+    },
+    PermissionDenied {
+        path: String,
+    },
+    /// The file isn't UTF-8 text, so it can't be a document.
+    InvalidUtf8 {
+        path: String,
+    },
+    /// A text file in an encoding that can't be read, e.g. UTF-32; `encoding` names
+    /// what it looks like.
+    InvalidEncoding {
+        path: String,
+        encoding: String,
+    },
+    /// A PNG image opened as a document that doesn't carry an Excalidraw scene.
+    NoEmbeddedScene {
+        path: String,
+    },
+    /// A file starting like gzip whose stream is damaged or cut short.
+    InvalidGzip {
+        path: String,
+        message: String,
+    },
+    /// A Markdown file opened as Mermaid has no ```` ```mermaid ```` block.
+    NoMermaidBlock {
+        path: String,
+    },
+    /// Malformed JSON; `line` and `column` are 1-based, as in serde_json.
+    InvalidJson {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    InvalidKind {
+        kind: String,
+    },
+    TemplateNotFound {
+        kind: String,
+        name: String,
+    },
+    KindMismatch {
+        name: String,
+        requested: String,
+        actual: String,
+    },
+    TemplateExists {
+        kind: String,
+        name: String,
+    },
+    InvalidTemplateName {
+        name: String,
+    },
+    WorkspaceNotFound {
+        name: String,
+    },
+    /// No captured save to undo or redo for the file.
+    NothingToRestore {
+        path: String,
+        action: String,
+    },
+    /// No `.bak<index>` copy next to the file.
+    BackupNotFound {
+        path: String,
+        index: usize,
+    },
+    /// The file is larger than the `max_file_bytes` setting allows opening; sizes
+    /// are in bytes.
+    FileTooLarge {
+        path: String,
+        size: u64,
+        limit: u64,
+    },
+    /// A document couldn't be fetched from `url`: it answered with a non-2xx
+    /// `status`, didn't answer in time, or couldn't be reached at all.
+    RemoteFetchFailed {
+        url: String,
+        status: Option<u16>,
+        timed_out: bool,
+        message: String,
+    },
+    /// No local history version of the file with that timestamp.
+    VersionNotFound {
+        path: String,
+        timestamp: u64,
+    },
+    /// The file changed on disk since the save being undone or redone.
+    ChangedExternally {
+        path: String,
+    },
+    /// The file was modified on disk since the document was opened or last saved;
+    /// mtimes are in epoch milliseconds. Saving again with `force` overwrites it.
+    Conflict {
+        path: String,
+        expected_mtime: u64,
+        current_mtime: u64,
+    },
+    /// The file is open in another window; focus `window` instead, or open it again
+    /// with `allow_duplicate`.
+    AlreadyOpen {
+        path: String,
+        window: String,
+    },
+    /// The scene was saved by a newer Excalidraw than this app supports; saving again
+    /// with `acknowledge_downgrade` overwrites it in the older format.
+    DowngradeRequired {
+        path: String,
+        file_version: u64,
+        supported_version: u64,
+    },
+    /// A recent file no longer matches its stored kind and can't be re-detected:
+    /// nothing recognises it, or its extension (`detected`) and contents disagree.
+    AmbiguousKind {
+        path: String,
+        stored: String,
+        detected: Option<String>,
+    },
+    InvalidDocument {
+        message: String,
+    },
+    /// A file being imported from another format couldn't be read; `node` names the
+    /// first element that couldn't be parsed, e.g. `<mxCell id="4">`.
+    ConversionFailed {
+        node: String,
+        message: String,
+    },
+    InvalidArgument {
+        message: String,
+    },
+    /// The password doesn't match the one an encrypted file was saved with.
+    WrongPassword,
+    /// An encrypted file failed authentication: it was damaged or modified.
+    Tampered,
+    /// Too many wrong passwords; try again after the delay.
+    RateLimited {
+        retry_after_secs: u64,
+    },
+    /// No trash entry with this id; it was restored, emptied or expired.
+    TrashEntryNotFound {
+        id: String,
+    },
+    /// Something already exists where a trashed file would be restored to.
+    RestoreConflict {
+        path: String,
+    },
+    /// A rename or move would replace another file; repeat it with `overwrite` to
+    /// replace it.
+    FileExists {
+        path: String,
+    },
+    /// The system trash can't take the file, e.g. on a minimal Linux desktop; trash
+    /// it again with `allow_permanent_delete` to delete it instead.
+    TrashUnavailable {
+        path: String,
+        reason: String,
+    },
+    /// The privacy lock is on; `privacy_unlock` first.
+    Locked,
+    /// The document was opened read-only; `make_writable` first. `on_disk` when the
+    /// file itself can't be written.
+    ReadOnlyDocument {
+        path: String,
+        on_disk: bool,
+    },
+    /// Native file dialogs don't work here (no display server, or the dialog never
+    /// answered); the frontend was asked for a path through `dialog-unavailable`.
+    DialogUnavailable {
+        reason: String,
+    },
+    /// A save was refused because the copy kept of what it would overwrite, its
+    /// `.bak` backup or local history version, couldn't be made.
+    BackupFailed {
+        path: String,
+        message: String,
+    },
+    /// The removable drive or share the document is on was disconnected.
+    VolumeDisconnected {
+        path: String,
+    },
+    Io {
+        message: String,
+    },
+}
+
+impl CommandError {
+    /// Classifies an error reading or writing `path`, so the frontend can tell a
+    /// missing file from one it may not touch.
+    pub fn from_io(error: io::Error, path: &Path) -> Self {
+        let path = path.to_string_lossy().to_string();
+        match error.kind() {
+            io::ErrorKind::NotFound => CommandError::NotFound { path },
+            io::ErrorKind::PermissionDenied => CommandError::PermissionDenied { path },
+            // What `read_to_string` reports for bytes that aren't UTF-8.
+            io::ErrorKind::InvalidData => CommandError::InvalidUtf8 { path },
+            _ => CommandError::Io {
+                message: error.to_string(),
+            },
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::Cancelled => "cancelled",
+            CommandError::NotFound { .. } => "not_found",
+            CommandError::PermissionDenied { .. } => "permission_denied",
+            CommandError::InvalidUtf8 { .. } => "invalid_utf8",
+            CommandError::InvalidEncoding { .. } => "invalid_encoding",
+            CommandError::NoEmbeddedScene { .. } => "no_embedded_scene",
+            CommandError::InvalidGzip { .. } => "invalid_gzip",
+            CommandError::NoMermaidBlock { .. } => "no_mermaid_block",
+            CommandError::InvalidJson { .. } => "invalid_json",
+            CommandError::InvalidKind { .. } => "invalid_kind",
+            CommandError::TemplateNotFound { .. } => "template_not_found",
+            CommandError::KindMismatch { .. } => "kind_mismatch",
+            CommandError::TemplateExists { .. } => "template_exists",
+            CommandError::InvalidTemplateName { .. } => "invalid_template_name",
+            CommandError::WorkspaceNotFound { .. } => "workspace_not_found",
+            CommandError::NothingToRestore { .. } => "nothing_to_restore",
+            CommandError::BackupNotFound { .. } => "backup_not_found",
+            CommandError::VersionNotFound { .. } => "version_not_found",
+            CommandError::FileTooLarge { .. } => "file_too_large",
+            CommandError::RemoteFetchFailed { .. } => "remote_fetch_failed",
+            CommandError::DialogUnavailable { .. } => "dialog_unavailable",
+            CommandError::ChangedExternally { .. } => "changed_externally",
+            CommandError::Conflict { .. } => "conflict",
+            CommandError::AlreadyOpen { .. } => "already_open",
+            CommandError::DowngradeRequired { .. } => "downgrade_required",
+            CommandError::AmbiguousKind { .. } => "ambiguous_kind",
+            CommandError::InvalidDocument { .. } => "invalid_document",
+            CommandError::ConversionFailed { .. } => "conversion_failed",
+            CommandError::InvalidArgument { .. } => "invalid_argument",
+            CommandError::WrongPassword => "wrong_password",
+            CommandError::Tampered => "tampered",
+            CommandError::RateLimited { .. } => "rate_limited",
+            CommandError::TrashEntryNotFound { .. } => "trash_entry_not_found",
+            CommandError::RestoreConflict { .. } => "restore_conflict",
+            CommandError::TrashUnavailable { .. } => "trash_unavailable",
+            CommandError::FileExists { .. } => "file_exists",
+            CommandError::Locked => "locked",
+            CommandError::ReadOnlyDocument { .. } => "read_only_document",
+            CommandError::BackupFailed { .. } => "backup_failed",
+            CommandError::VolumeDisconnected { .. } => "volume_disconnected",
+            CommandError::Io { .. } => "io",
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Cancelled => write!(f, "Operation cancelled"),
+            CommandError::NotFound { path } => write!(f, "{path} doesn't exist"),
+            CommandError::PermissionDenied { path } => {
+                write!(f, "Excalibur isn't allowed to access {path}")
+            }
+            CommandError::InvalidUtf8 { path } => write!(f, "{path} isn't UTF-8 text"),
+            CommandError::InvalidEncoding { path, encoding } => {
+                write!(f, "{path} is in {encoding}; save it as UTF-8 to open it")
+            }
+            CommandError::NoEmbeddedScene { path } => {
+                write!(f, "{path} is an image without an embedded Excalidraw scene")
+            }
+            CommandError::InvalidGzip { path, message } => {
+                write!(f, "{path} is a damaged gzip file: {message}")
+            }
+            CommandError::NoMermaidBlock { path } => {
+                write!(f, "{path} has no ```mermaid code block")
+            }
+            CommandError::InvalidJson { message, .. } => write!(f, "Invalid JSON: {message}"),
+            CommandError::WrongPassword => write!(f, "Wrong password"),
+            CommandError::Locked => write!(f, "Excalibur is locked; unlock it to continue"),
+            CommandError::Tampered => {
+                write!(f, "The encrypted file is damaged or has been modified")
+            }
+            CommandError::RateLimited { retry_after_secs } => {
+                write!(
+                    f,
+                    "Too many wrong passwords; try again in {retry_after_secs}s"
+                )
+            }
+            CommandError::InvalidKind { kind } => write!(f, "Unknown document kind \"{kind}\""),
+            CommandError::TemplateNotFound { kind, name } => {
+                write!(f, "No {kind} template named \"{name}\"")
+            }
+            CommandError::KindMismatch {
+                name,
+                requested,
+                actual,
+            } => write!(
+                f,
+                "Template \"{name}\" is a {actual} template, not a {requested} template"
+            ),
+            CommandError::TemplateExists { kind, name } => {
+                write!(f, "A {kind} template named \"{name}\" already exists")
+            }
+            CommandError::InvalidTemplateName { name } => {
+                write!(f, "\"{name}\" cannot be used as a template name")
+            }
+            CommandError::WorkspaceNotFound { name } => {
+                write!(f, "No workspace named \"{name}\"")
+            }
+            CommandError::NothingToRestore { path, action } => {
+                write!(f, "There is no save to {action} for {path}")
+            }
+            CommandError::BackupNotFound { path, index } => {
+                write!(f, "There is no backup {index} of {path}")
+            }
+            CommandError::VersionNotFound { path, timestamp } => {
+                write!(f, "There is no version of {path} from {timestamp}")
+            }
+            CommandError::FileTooLarge { path, size, limit } => write!(
+                f,
+                "{path} is {} MB, more than the {} MB that can be opened",
+                size / (1024 * 1024),
+                limit / (1024 * 1024)
+            ),
+            CommandError::RemoteFetchFailed {
+                url,
+                status: Some(status),
+                ..
+            } => write!(f, "{url} answered with HTTP {status}"),
+            CommandError::RemoteFetchFailed {
+                url,
+                timed_out: true,
+                ..
+            } => write!(f, "{url} didn't answer in time"),
+            CommandError::RemoteFetchFailed { url, message, .. } => {
+                write!(f, "Could not fetch {url}: {message}")
+            }
+            CommandError::ChangedExternally { path } => write!(
+                f,
+                "{path} was changed outside Excalibur since it was saved; reload it first"
+            ),
+            CommandError::Conflict { path, .. } => write!(
+                f,
+                "{path} was changed on disk since it was opened; overwrite it or save elsewhere"
+            ),
+            CommandError::AlreadyOpen { path, .. } => {
+                write!(f, "{path} is already open in another window")
+            }
+            CommandError::DowngradeRequired {
+                path,
+                file_version,
+                supported_version,
+            } => write!(
+                f,
+                "{path} was saved by a newer version of Excalidraw (scene version {file_version}, \
+                 this app supports {supported_version}). Saving would drop what this version \
+                 doesn't understand; confirm the downgrade to overwrite it."
+            ),
+            CommandError::TrashEntryNotFound { id } => {
+                write!(f, "Trash entry {id} no longer exists")
+            }
+            CommandError::RestoreConflict { path } => {
+                write!(f, "Cannot restore because {path} already exists")
+            }
+            CommandError::DialogUnavailable { reason } => {
+                write!(f, "The file dialog is unavailable: {reason}")
+            }
+            CommandError::FileExists { path } => {
+                write!(f, "{path} already exists; replace it or choose another name")
+            }
+            CommandError::TrashUnavailable { path, reason } => write!(
+                f,
+                "{path} can't be moved to the trash ({reason}); it can only be deleted permanently"
+            ),
+            CommandError::BackupFailed { path, message } => {
+                write!(f, "Could not keep a copy of {path} ({message}); not overwriting it")
+            }
+            CommandError::VolumeDisconnected { path } => write!(
+                f,
+                "The drive {path} is on was disconnected; save it elsewhere or reconnect the drive"
+            ),
+            CommandError::ReadOnlyDocument {
+                path,
+                on_disk: true,
+            } => write!(f, "{path} is read-only on disk; save a copy elsewhere"),
+            CommandError::ReadOnlyDocument {
+                path,
+                on_disk: false,
+            } => write!(
+                f,
+                "{path} was opened read-only; make it writable before saving"
+            ),
+            CommandError::AmbiguousKind {
+                path,
+                stored,
+                detected: Some(detected),
+            } => write!(
+                f,
+                "{path} was a {stored} document; its extension now says {detected} but its contents don't"
+            ),
+            CommandError::AmbiguousKind {
+                path,
+                stored,
+                detected: None,
+            } => write!(
+                f,
+                "{path} was a {stored} document but is no longer an Excalidraw scene or a Mermaid diagram"
+            ),
+            CommandError::InvalidDocument { message } => write!(f, "Invalid document: {message}"),
+            CommandError::ConversionFailed { node, message } => {
+                write!(f, "Could not convert {node}: {message}")
+            }
+            CommandError::InvalidArgument { message } | CommandError::Io { message } => {
+                write!(f, "{message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        CommandError::Io {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Syntax errors become `InvalidJson` with their position; JSON of the wrong shape is
+/// an `InvalidDocument`.
+impl From<serde_json::Error> for CommandError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_syntax() || error.is_eof() {
+            CommandError::InvalidJson {
+                line: error.line(),
+                column: error.column(),
+                message: error.to_string(),
+            }
+        } else {
+            CommandError::InvalidDocument {
+                message: error.to_string(),
+            }
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Io { message }
+    }
+}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            CommandError::InvalidKind { kind } => {
+                map.serialize_entry("kind", kind)?;
+            }
+            CommandError::TemplateNotFound { kind, name }
+            | CommandError::TemplateExists { kind, name } => {
+                map.serialize_entry("kind", kind)?;
+                map.serialize_entry("name", name)?;
+            }
+            CommandError::InvalidTemplateName { name }
+            | CommandError::WorkspaceNotFound { name } => {
+                map.serialize_entry("name", name)?;
+            }
+            CommandError::KindMismatch {
+                name,
+                requested,
+                actual,
+            } => {
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("requested", requested)?;
+                map.serialize_entry("actual", actual)?;
+            }
+            CommandError::RateLimited { retry_after_secs } => {
+                map.serialize_entry("retry_after_secs", retry_after_secs)?;
+            }
+            CommandError::NothingToRestore { path, action } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("action", action)?;
+            }
+            CommandError::BackupNotFound { path, index } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("index", index)?;
+            }
+            CommandError::VersionNotFound { path, timestamp } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("timestamp", timestamp)?;
+            }
+            CommandError::FileTooLarge { path, size, limit } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("size", size)?;
+                map.serialize_entry("limit", limit)?;
+            }
+            CommandError::RemoteFetchFailed {
+                url,
+                status,
+                timed_out,
+                ..
+            } => {
+                map.serialize_entry("url", url)?;
+                map.serialize_entry("status", status)?;
+                map.serialize_entry("timed_out", timed_out)?;
+            }
+            CommandError::InvalidEncoding { path, encoding } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("encoding", encoding)?;
+            }
+            CommandError::NotFound { path }
+            | CommandError::PermissionDenied { path }
+            | CommandError::InvalidUtf8 { path }
+            | CommandError::NoEmbeddedScene { path }
+            | CommandError::NoMermaidBlock { path }
+            | CommandError::ChangedExternally { path }
+            | CommandError::RestoreConflict { path }
+            | CommandError::FileExists { path }
+            | CommandError::VolumeDisconnected { path } => {
+                map.serialize_entry("path", path)?;
+            }
+            CommandError::Conflict {
+                path,
+                expected_mtime,
+                current_mtime,
+            } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("expected_mtime", expected_mtime)?;
+                map.serialize_entry("current_mtime", current_mtime)?;
+            }
+            CommandError::AlreadyOpen { path, window } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("window", window)?;
+            }
+            CommandError::DowngradeRequired {
+                path,
+                file_version,
+                supported_version,
+            } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("file_version", file_version)?;
+                map.serialize_entry("supported_version", supported_version)?;
+            }
+            CommandError::InvalidJson { line, column, .. } => {
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("column", column)?;
+            }
+            CommandError::TrashEntryNotFound { id } => {
+                map.serialize_entry("id", id)?;
+            }
+            CommandError::ConversionFailed { node, .. } => {
+                map.serialize_entry("node", node)?;
+            }
+            CommandError::InvalidGzip { path, .. } | CommandError::BackupFailed { path, .. } => {
+                map.serialize_entry("path", path)?;
+            }
+            CommandError::DialogUnavailable { reason } => {
+                map.serialize_entry("reason", reason)?;
+            }
+            CommandError::TrashUnavailable { path, reason } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            CommandError::ReadOnlyDocument { path, on_disk } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("on_disk", on_disk)?;
+            }
+            CommandError::AmbiguousKind {
+                path,
+                stored,
+                detected,
+            } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("stored", stored)?;
+                map.serialize_entry("detected", detected)?;
+            }
+            CommandError::Cancelled
+            | CommandError::WrongPassword
+            | CommandError::Locked
+            | CommandError::Tampered
+            | CommandError::InvalidDocument { .. }
+            | CommandError::InvalidArgument { .. }
+            | CommandError::Io { .. } => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn path() -> String {
+        "/work/flow.mmd".to_string()
+    }
+
+    fn every_variant() -> Vec<CommandError> {
+        vec![
+            CommandError::Cancelled,
+            CommandError::NotFound { path: path() },
+            CommandError::PermissionDenied { path: path() },
+            CommandError::InvalidUtf8 { path: path() },
+            CommandError::InvalidEncoding {
+                path: path(),
+                encoding: "UTF-32LE".to_string(),
+            },
+            CommandError::NoEmbeddedScene { path: path() },
+            CommandError::InvalidGzip {
+                path: path(),
+                message: "unexpected end of file".to_string(),
+            },
+            CommandError::NoMermaidBlock { path: path() },
+            CommandError::InvalidJson {
+                line: 3,
+                column: 7,
+                message: "expected value at line 3 column 7".to_string(),
+            },
+            CommandError::InvalidKind {
+                kind: "svg".to_string(),
+            },
+            CommandError::TemplateNotFound {
+                kind: "mermaid".to_string(),
+                name: "flow".to_string(),
+            },
+            CommandError::KindMismatch {
+                name: "flow".to_string(),
+                requested: "excalidraw".to_string(),
+                actual: "mermaid".to_string(),
+            },
+            CommandError::TemplateExists {
+                kind: "mermaid".to_string(),
+                name: "flow".to_string(),
+            },
+            CommandError::InvalidTemplateName {
+                name: "..".to_string(),
+            },
+            CommandError::WorkspaceNotFound {
+                name: "docs".to_string(),
+            },
+            CommandError::NothingToRestore {
+                path: path(),
+                action: "undo".to_string(),
+            },
+            CommandError::BackupNotFound {
+                path: path(),
+                index: 2,
+            },
+            CommandError::VersionNotFound {
+                path: path(),
+                timestamp: 1_700_000_000_000,
+            },
+            CommandError::FileTooLarge {
+                path: path(),
+                size: 300 * 1024 * 1024,
+                limit: 200 * 1024 * 1024,
+            },
+            CommandError::RemoteFetchFailed {
+                url: "https://example.com/flow.mmd".to_string(),
+                status: Some(404),
+                timed_out: false,
+                message: "404 Not Found".to_string(),
+            },
+            CommandError::ChangedExternally { path: path() },
+            CommandError::Conflict {
+                path: path(),
+                expected_mtime: 1,
+                current_mtime: 2,
+            },
+            CommandError::AlreadyOpen {
+                path: path(),
+                window: "main".to_string(),
+            },
+            CommandError::DowngradeRequired {
+                path: path(),
+                file_version: 3,
+                supported_version: 2,
+            },
+            CommandError::AmbiguousKind {
+                path: path(),
+                stored: "mermaid".to_string(),
+                detected: None,
+            },
+            CommandError::InvalidDocument {
+                message: "expected a JSON object".to_string(),
+            },
+            CommandError::ConversionFailed {
+                node: "<mxCell id=\"4\">".to_string(),
+                message: "missing geometry".to_string(),
+            },
+            CommandError::InvalidArgument {
+                message: "A saved search needs a name and a query".to_string(),
+            },
+            CommandError::WrongPassword,
+            CommandError::Tampered,
+            CommandError::RateLimited {
+                retry_after_secs: 30,
+            },
+            CommandError::TrashEntryNotFound {
+                id: "abc".to_string(),
+            },
+            CommandError::RestoreConflict { path: path() },
+            CommandError::FileExists { path: path() },
+            CommandError::TrashUnavailable {
+                path: path(),
+                reason: "no trash directory".to_string(),
+            },
+            CommandError::Locked,
+            CommandError::ReadOnlyDocument {
+                path: path(),
+                on_disk: true,
+            },
+            CommandError::DialogUnavailable {
+                reason: "no display".to_string(),
+            },
+            CommandError::BackupFailed {
+                path: path(),
+                message: "disk full".to_string(),
+            },
+            CommandError::VolumeDisconnected { path: path() },
+            CommandError::Io {
+                message: "disk full".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn every_variant_serializes_a_distinct_code_and_its_message() {
+        let mut codes = std::collections::HashSet::new();
+        for error in every_variant() {
+            let value = serde_json::to_value(&error).unwrap();
+            assert_eq!(value["code"], error.code());
+            assert_eq!(value["message"], error.to_string());
+            assert!(!error.to_string().is_empty(), "{}", error.code());
+            assert!(codes.insert(error.code()), "{} is used twice", error.code());
+        }
+    }
+
+    #[test]
+    fn serializes_variant_fields() {
+        let value = serde_json::to_value(CommandError::InvalidJson {
+            line: 3,
+            column: 7,
+            message: "expected value at line 3 column 7".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "code": "invalid_json",
+                "message": "Invalid JSON: expected value at line 3 column 7",
+                "line": 3,
+                "column": 7,
+            })
+        );
+
+        let value = serde_json::to_value(CommandError::NotFound { path: path() }).unwrap();
+        assert_eq!(value["path"], "/work/flow.mmd");
+        let value = serde_json::to_value(CommandError::Cancelled).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), 2);
+        assert_eq!(value["code"], Value::from("cancelled"));
+    }
+
+    #[test]
+    fn classifies_io_errors_by_kind() {
+        let file = Path::new("/work/flow.mmd");
+        let from = |kind| CommandError::from_io(io::Error::new(kind, "boom"), file).code();
+        assert_eq!(from(io::ErrorKind::NotFound), "not_found");
+        assert_eq!(from(io::ErrorKind::PermissionDenied), "permission_denied");
+        assert_eq!(from(io::ErrorKind::InvalidData), "invalid_utf8");
+        assert_eq!(from(io::ErrorKind::Other), "io");
+    }
+
+    #[test]
+    fn json_syntax_errors_keep_their_position() {
+        let error: CommandError = serde_json::from_str::<Value>("{\n  \"a\": ,\n}")
+            .unwrap_err()
+            .into();
+        assert!(matches!(
+            error,
+            CommandError::InvalidJson {
+                line: 2,
+                column: 8,
+                ..
+            }
+        ));
+        let error: CommandError = serde_json::from_str::<Vec<u32>>("{}").unwrap_err().into();
+        assert_eq!(error.code(), "invalid_document");
+    }
+}
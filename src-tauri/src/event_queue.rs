@@ -0,0 +1,220 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+const MAIN_WINDOW: &str = "main";
+
+struct QueuedEvent {
+    /// Window label the event is for; `None` leaves the choice to `recipient`.
+    target: Option<String>,
+    event: String,
+    payload: Value,
+}
+
+/// Events for the frontend that arrived before any window was listening.
+///
+/// A window counts as ready from its `frontend_ready` call until its page starts
+/// loading again, so a reload queues events instead of losing them. This covers the
+/// cold start too: the file a double-click in Finder launched the app with is
+/// handled in `setup`, before the webview has registered its `open-file` listener.
+#[derive(Default)]
+pub struct EventQueue(Mutex<QueueState>);
+
+#[derive(Default)]
+struct QueueState {
+    ready: HashSet<String>,
+    queued: Vec<QueuedEvent>,
+}
+
+impl QueueState {
+    /// Returns `event` when its target is ready to receive it now; otherwise queues
+    /// it and returns `None`.
+    fn offer(&mut self, event: QueuedEvent) -> Option<QueuedEvent> {
+        let deliverable = match &event.target {
+            Some(label) => self.ready.contains(label),
+            None => !self.ready.is_empty(),
+        };
+        if deliverable {
+            return Some(event);
+        }
+        self.queued.push(event);
+        None
+    }
+
+    /// Marks `label` ready and takes, in order, the queued events for it and the
+    /// untargeted ones.
+    fn mark_ready(&mut self, label: &str) -> Vec<QueuedEvent> {
+        self.ready.insert(label.to_string());
+        let (flush, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut self.queued)
+            .into_iter()
+            .partition(|event| event.target.as_deref().is_none_or(|target| target == label));
+        self.queued = keep;
+        flush
+    }
+
+    /// The one window an untargeted event goes to: `focused` when it is ready, else
+    /// the main window, else the first ready one, so a file opened from outside the
+    /// app doesn't open in every window.
+    fn recipient(&self, focused: Option<&str>) -> Option<String> {
+        focused
+            .filter(|label| self.ready.contains(*label))
+            .or(Some(MAIN_WINDOW).filter(|label| self.ready.contains(*label)))
+            .or_else(|| self.ready.iter().map(String::as_str).min())
+            .map(str::to_string)
+    }
+}
+
+fn focused_window(app: &AppHandle) -> Option<String> {
+    app.webview_windows()
+        .into_iter()
+        .find(|(_, window)| window.is_focused().unwrap_or(false))
+        .map(|(label, _)| label)
+}
+
+/// Emits `event` right away when its target is ready, otherwise queues it until
+/// `frontend_ready`. Untargeted events go to a single window, the focused one if
+/// any.
+pub fn emit_when_ready<S: Serialize>(
+    app: &AppHandle,
+    target: Option<&str>,
+    event: &str,
+    payload: S,
+) {
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(error) => {
+            log::warn!("emit_when_ready: cannot serialize {}: {}", event, error);
+            return;
+        }
+    };
+    let focused = focused_window(app);
+    let queue = app.state::<EventQueue>();
+    // Holding the lock while emitting keeps a concurrent `frontend_ready` from
+    // flushing before this event is queued, or after it was delivered.
+    let mut state = queue.0.lock().unwrap();
+    let offered = state.offer(QueuedEvent {
+        target: target.map(str::to_string),
+        event: event.to_string(),
+        payload,
+    });
+    let Some(offered) = offered else {
+        log::debug!(
+            "emit_when_ready: queueing {} for {}",
+            event,
+            target.unwrap_or("the first ready window")
+        );
+        return;
+    };
+    if let Some(label) = offered
+        .target
+        .or_else(|| state.recipient(focused.as_deref()))
+    {
+        let _ = app.emit_to(label.as_str(), &offered.event, offered.payload);
+    }
+}
+
+/// Marks a window as no longer listening, because its page is (re)loading or it closed.
+pub fn mark_not_ready(app: &AppHandle, label: &str) {
+    app.state::<EventQueue>()
+        .0
+        .lock()
+        .unwrap()
+        .ready
+        .remove(label);
+}
+
+/// Called by the frontend once its listeners are registered. Flushes, in order,
+/// the queued events for this window and any untargeted ones. Safe to call again,
+/// e.g. after a reload.
+#[tauri::command]
+pub fn frontend_ready(app: AppHandle, window: WebviewWindow) {
+    let label = window.label().to_string();
+    let queue = app.state::<EventQueue>();
+    let mut state = queue.0.lock().unwrap();
+    for event in state.mark_ready(&label) {
+        log::debug!("frontend_ready: flushing {} to {}", event.event, label);
+        let _ = window.emit_to(label.as_str(), &event.event, event.payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(target: Option<&str>, path: &str) -> QueuedEvent {
+        QueuedEvent {
+            target: target.map(str::to_string),
+            event: "open-file".to_string(),
+            payload: json!(path),
+        }
+    }
+
+    fn payloads(events: &[QueuedEvent]) -> Vec<&Value> {
+        events.iter().map(|event| &event.payload).collect()
+    }
+
+    #[test]
+    fn files_opened_before_the_frontend_listens_are_delivered_once_ready() {
+        let mut state = QueueState::default();
+        // Cold start: `setup` handles the launch URL, then another file is
+        // double-clicked while the page is still loading.
+        assert!(state
+            .offer(event(None, "/work/launch.excalidraw"))
+            .is_none());
+        assert!(state.offer(event(None, "/work/second.mmd")).is_none());
+
+        let flushed = state.mark_ready("main");
+        assert_eq!(
+            payloads(&flushed),
+            vec!["/work/launch.excalidraw", "/work/second.mmd"]
+        );
+        assert!(state.queued.is_empty());
+
+        // From then on events pass straight through.
+        let delivered = state.offer(event(None, "/work/later.mmd")).unwrap();
+        assert_eq!(delivered.payload, json!("/work/later.mmd"));
+        assert!(state.queued.is_empty());
+    }
+
+    #[test]
+    fn targeted_events_wait_for_their_window_and_reloads_queue_again() {
+        let mut state = QueueState::default();
+        assert!(state
+            .offer(event(Some("workspace-2"), "/work/b.mmd"))
+            .is_none());
+        assert!(state.mark_ready("main").is_empty());
+        assert!(state
+            .offer(event(Some("workspace-2"), "/work/c.mmd"))
+            .is_none());
+        assert_eq!(
+            payloads(&state.mark_ready("workspace-2")),
+            vec!["/work/b.mmd", "/work/c.mmd"]
+        );
+
+        // The main window reloads: until it calls `frontend_ready` again, its
+        // events are queued instead of lost.
+        state.ready.remove("main");
+        assert!(state.offer(event(Some("main"), "/work/d.mmd")).is_none());
+        assert_eq!(payloads(&state.mark_ready("main")), vec!["/work/d.mmd"]);
+    }
+
+    #[test]
+    fn untargeted_events_go_to_one_window() {
+        let mut state = QueueState::default();
+        assert_eq!(state.recipient(None), None);
+        state.mark_ready("document-2");
+        state.mark_ready("document-1");
+        assert_eq!(state.recipient(None).as_deref(), Some("document-1"));
+        state.mark_ready("main");
+        assert_eq!(state.recipient(None).as_deref(), Some("main"));
+        assert_eq!(
+            state.recipient(Some("document-2")).as_deref(),
+            Some("document-2")
+        );
+        // A focused window that is still loading doesn't get it.
+        assert_eq!(state.recipient(Some("document-3")).as_deref(), Some("main"));
+    }
+}
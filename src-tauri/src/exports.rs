@@ -0,0 +1,262 @@
+use crate::dialogs::pick_save_path;
+use crate::error::CommandError;
+use crate::{file_name, modified_millis, write_binary_file, SaveFileResponse};
+use base64::Engine;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Deserialize)]
+pub struct SaveExportRequest {
+    /// File name the save dialog starts with, e.g. `flow.png`.
+    suggested_name: String,
+    /// The rendered file, base64-encoded; a `data:` URL prefix is ignored.
+    data_base64: String,
+    /// Extensions for the dialog filter, without dots, e.g. `["png"]`.
+    filter_extensions: Vec<String>,
+    /// Where the previous export went; set, the dialog is skipped.
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportAlongsideRequest {
+    /// The saved document being exported. Unset for one that was never saved, which
+    /// asks where to export instead.
+    #[serde(default)]
+    source_path: Option<String>,
+    /// Extension of the export, e.g. `png` or `svg`.
+    extension: String,
+    /// The rendered file: base64 like `SaveExportRequest::data_base64`, or the text
+    /// itself when `text` is set.
+    data: String,
+    #[serde(default)]
+    text: bool,
+    /// Replace an earlier export; otherwise an existing file is `FileExists`.
+    #[serde(default)]
+    overwrite: bool,
+    /// File name the save dialog starts with when there is no `source_path`.
+    #[serde(default)]
+    suggested_name: Option<String>,
+}
+
+/// Decodes an export payload. Anything that isn't valid base64 is refused rather
+/// than written as a corrupt file.
+pub fn decode_payload(data: &str) -> Result<Vec<u8>, CommandError> {
+    let data = match data.split_once(";base64,") {
+        Some((prefix, rest)) if prefix.starts_with("data:") => rest,
+        _ => data,
+    };
+    let data: String = data.chars().filter(|ch| !ch.is_whitespace()).collect();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(&data)
+        .map_err(|error| CommandError::InvalidArgument {
+            message: format!("The export data is not valid base64: {error}"),
+        })?;
+    if bytes.is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "The export is empty".to_string(),
+        });
+    }
+    Ok(bytes)
+}
+
+/// Writes a rendered export (PNG, SVG, ...) to `request.path`, or to a path picked
+/// in the save dialog. `None` when the dialog was cancelled.
+#[tauri::command]
+pub async fn save_export_file(
+    app: AppHandle,
+    request: SaveExportRequest,
+) -> Result<Option<SaveFileResponse>, CommandError> {
+    let extensions: Vec<&str> = request
+        .filter_extensions
+        .iter()
+        .map(|extension| extension.trim_start_matches('.'))
+        .filter(|extension| !extension.is_empty())
+        .collect();
+    let Some(first) = extensions.first().copied() else {
+        return Err(CommandError::InvalidArgument {
+            message: "An export needs at least one file extension".to_string(),
+        });
+    };
+    // Decoded before the dialog, so a bad payload doesn't ask for a path first.
+    let bytes = decode_payload(&request.data_base64)?;
+
+    let path = match request.path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let filter_name = first.to_uppercase();
+            let Some(mut path) =
+                pick_save_path(&app, &filter_name, &extensions, request.suggested_name).await?
+            else {
+                log::info!("save_export_file: user cancelled dialog");
+                return Ok(None);
+            };
+            if path.extension().is_none() {
+                path.set_extension(first);
+            }
+            path
+        }
+    };
+
+    write_binary_file(&path, &bytes)?;
+    log::info!(
+        "save_export_file: wrote {} bytes to {}",
+        bytes.len(),
+        path.display()
+    );
+    Ok(Some(SaveFileResponse {
+        path: path.to_string_lossy().to_string(),
+        name: None,
+        mtime: modified_millis(&path),
+        open_in_other_windows: Vec::new(),
+    }))
+}
+
+/// The export of `source` with `extension`: `arch.excalidraw` gives `arch.png`,
+/// and a gzipped `arch.excalidraw.gz` too. An export that would be the document
+/// itself, as a PNG of `arch.excalidraw.png` would, is refused.
+fn alongside(source: &Path, extension: &str) -> Result<PathBuf, CommandError> {
+    let extension = extension.trim().trim_start_matches('.');
+    if extension.is_empty() || extension.contains(['/', '\\', '.']) {
+        return Err(CommandError::InvalidArgument {
+            message: format!("\"{extension}\" is not a file extension"),
+        });
+    }
+    let source = if crate::gzip::has_gz_suffix(source) {
+        source.with_extension("")
+    } else {
+        source.to_path_buf()
+    };
+    let path = source.with_extension(extension);
+    if path == source {
+        return Err(CommandError::InvalidArgument {
+            message: format!(
+                "Exporting {} as {extension} would replace the document itself",
+                path.display()
+            ),
+        });
+    }
+    Ok(path)
+}
+
+/// Writes a rendered export next to the document it was made from, named after it
+/// with the export's extension, so `arch.png` and `arch.svg` sit beside
+/// `arch.excalidraw`. An earlier export is only replaced with `overwrite`. Without
+/// a `source_path` the save dialog asks where, and `None` means it was cancelled.
+#[tauri::command]
+pub async fn export_alongside(
+    app: AppHandle,
+    request: ExportAlongsideRequest,
+) -> Result<Option<SaveFileResponse>, CommandError> {
+    let bytes = if request.text {
+        request.data.into_bytes()
+    } else {
+        decode_payload(&request.data)?
+    };
+    let path = match request.source_path.as_deref() {
+        Some(source) => {
+            let path = alongside(Path::new(source), &request.extension)?;
+            if !request.overwrite && path.exists() {
+                return Err(CommandError::FileExists {
+                    path: path.to_string_lossy().to_string(),
+                });
+            }
+            path
+        }
+        None => {
+            let extension = alongside(Path::new("export"), &request.extension)?
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let suggested_name = request
+                .suggested_name
+                .unwrap_or_else(|| format!("export.{extension}"));
+            let Some(mut path) = pick_save_path(
+                &app,
+                &extension.to_uppercase(),
+                &[extension.as_str()],
+                suggested_name,
+            )
+            .await?
+            else {
+                log::info!("export_alongside: user cancelled dialog");
+                return Ok(None);
+            };
+            if path.extension().is_none() {
+                path.set_extension(&extension);
+            }
+            path
+        }
+    };
+
+    write_binary_file(&path, &bytes)?;
+    log::info!(
+        "export_alongside: wrote {} bytes to {}",
+        bytes.len(),
+        path.display()
+    );
+    Ok(Some(SaveFileResponse {
+        path: path.to_string_lossy().to_string(),
+        name: file_name(&path),
+        mtime: modified_millis(&path),
+        open_in_other_windows: Vec::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_plain_and_data_url_payloads() {
+        assert_eq!(
+            decode_payload("iVBORw0KGgo=").unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+        assert_eq!(
+            decode_payload("data:image/png;base64,iVBO\nRw0KGgo=").unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+    }
+
+    #[test]
+    fn exports_are_named_after_the_source() {
+        let export = |source, extension| {
+            alongside(Path::new(source), extension).map_err(|error| error.code())
+        };
+        assert_eq!(
+            export("/work/arch.excalidraw", "png"),
+            Ok(PathBuf::from("/work/arch.png"))
+        );
+        assert_eq!(
+            export("/work/arch.excalidraw", ".svg"),
+            Ok(PathBuf::from("/work/arch.svg"))
+        );
+        assert_eq!(
+            export("/work/flow.mmd.gz", "svg"),
+            Ok(PathBuf::from("/work/flow.svg"))
+        );
+        assert_eq!(
+            export("/work/arch.excalidraw.png", "svg"),
+            Ok(PathBuf::from("/work/arch.excalidraw.svg"))
+        );
+        assert_eq!(
+            export("/work/arch.excalidraw.png", "png"),
+            Err("invalid_argument")
+        );
+        assert_eq!(
+            export("/work/arch.excalidraw", "../png"),
+            Err("invalid_argument")
+        );
+        assert_eq!(export("/work/arch.excalidraw", ""), Err("invalid_argument"));
+    }
+
+    #[test]
+    fn refuses_invalid_or_empty_payloads() {
+        for data in ["not base64!", "iVBORw0KGgo", ""] {
+            let error = decode_payload(data).unwrap_err();
+            assert_eq!(error.code(), "invalid_argument", "{data}");
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::display_path::PathShortener;
+use crate::error::CommandError;
+use crate::{app_data_dir, file_status, now_epoch, write_file, FileStatus};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Favorite {
+    kind: String,
+    path: String,
+    label: Option<String>,
+    added_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct FavoriteEntry {
+    #[serde(flatten)]
+    favorite: Favorite,
+    #[serde(flatten)]
+    status: FileStatus,
+    display_path: String,
+}
+
+fn favorites_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("favorites.json")
+}
+
+fn load_favorites(app: &AppHandle) -> Vec<Favorite> {
+    fs::read_to_string(favorites_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the favorites and returns them enriched for display.
+fn save_favorites(
+    app: &AppHandle,
+    favorites: Vec<Favorite>,
+) -> Result<Vec<FavoriteEntry>, CommandError> {
+    let contents = serde_json::to_string_pretty(&favorites).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    write_file(&favorites_path(app), &contents)?;
+    Ok(enrich(app, favorites))
+}
+
+fn enrich(app: &AppHandle, favorites: Vec<Favorite>) -> Vec<FavoriteEntry> {
+    let shortener = PathShortener::new(app);
+    favorites
+        .into_iter()
+        .map(|favorite| FavoriteEntry {
+            status: file_status(Path::new(&favorite.path)),
+            display_path: shortener.shorten(Path::new(&favorite.path)),
+            favorite,
+        })
+        .collect()
+}
+
+fn favorite_index(favorites: &[Favorite], path: &str) -> Result<usize, CommandError> {
+    favorites
+        .iter()
+        .position(|favorite| favorite.path == path)
+        .ok_or_else(|| CommandError::InvalidArgument {
+            message: format!("{path} is not a favorite"),
+        })
+}
+
+pub fn favorite_paths(app: &AppHandle) -> Vec<String> {
+    load_favorites(app)
+        .into_iter()
+        .map(|favorite| favorite.path)
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_favorites(app: AppHandle) -> Result<Vec<FavoriteEntry>, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    Ok(enrich(&app, load_favorites(&app)))
+}
+
+/// Adds a favorite at the end of the list. Adding an existing favorite again only
+/// updates its label, so its position is kept.
+#[tauri::command]
+pub fn add_favorite(
+    app: AppHandle,
+    kind: String,
+    path: String,
+    label: Option<String>,
+) -> Result<Vec<FavoriteEntry>, CommandError> {
+    if !matches!(kind.as_str(), "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind { kind });
+    }
+    let label = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+
+    let mut favorites = load_favorites(&app);
+    match favorites.iter_mut().find(|favorite| favorite.path == path) {
+        Some(existing) => {
+            existing.kind = kind;
+            existing.label = label;
+        }
+        None => favorites.push(Favorite {
+            kind,
+            path,
+            label,
+            added_at: now_epoch(),
+        }),
+    }
+    save_favorites(&app, favorites)
+}
+
+#[tauri::command]
+pub fn remove_favorite(app: AppHandle, path: String) -> Result<Vec<FavoriteEntry>, CommandError> {
+    let mut favorites = load_favorites(&app);
+    favorites.remove(favorite_index(&favorites, &path)?);
+    save_favorites(&app, favorites)
+}
+
+/// Moves a favorite to `new_index`, clamped to the end of the list.
+#[tauri::command]
+pub fn move_favorite(
+    app: AppHandle,
+    path: String,
+    new_index: usize,
+) -> Result<Vec<FavoriteEntry>, CommandError> {
+    let mut favorites = load_favorites(&app);
+    let favorite = favorites.remove(favorite_index(&favorites, &path)?);
+    let new_index = new_index.min(favorites.len());
+    favorites.insert(new_index, favorite);
+    save_favorites(&app, favorites)
+}
+
+/// Points the favorite for `old` (if there is one) at `new`. When `new` is already
+/// a favorite the entry for `old` is dropped instead.
+pub fn replace_path(app: &AppHandle, old: &str, new: &str) -> Result<(), CommandError> {
+    let mut favorites = load_favorites(app);
+    let Ok(index) = favorite_index(&favorites, old) else {
+        return Ok(());
+    };
+    if favorites.iter().any(|favorite| favorite.path == new) {
+        favorites.remove(index);
+    } else {
+        favorites[index].path = new.to_string();
+    }
+    save_favorites(app, favorites)?;
+    Ok(())
+}
+
+/// Points a favorite at the file's new location, keeping its label and position.
+#[tauri::command]
+pub fn relocate_favorite(
+    app: AppHandle,
+    old: String,
+    new: String,
+) -> Result<Vec<FavoriteEntry>, CommandError> {
+    let mut favorites = load_favorites(&app);
+    let index = favorite_index(&favorites, &old)?;
+    if old != new && favorites.iter().any(|favorite| favorite.path == new) {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{new} is already a favorite"),
+        });
+    }
+    favorites[index].path = new;
+    save_favorites(&app, favorites)
+}
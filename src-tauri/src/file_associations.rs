@@ -0,0 +1,495 @@
+use crate::error::CommandError;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+/// Extensions Excalibur can be the default app for, with the MIME type each is
+/// registered under on Linux. They match `bundle.fileAssociations` in
+/// `tauri.conf.json`, which is what macOS goes by.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("excalidraw", "application/vnd.excalidraw+json"),
+    ("mmd", "text/vnd.mermaid"),
+    ("mermaid", "text/vnd.mermaid"),
+];
+
+/// Who opens files with one extension, as reported by `file_association_status`.
+#[derive(Serialize)]
+pub struct AssociationStatus {
+    extension: String,
+    /// Whether Excalibur is the default app; `None` when the system couldn't be asked.
+    is_default: Option<bool>,
+    /// The current default as the system names it: a `.desktop` file, a ProgID or
+    /// a bundle identifier.
+    handler: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Registered,
+    /// Registered, but the system only lets the user make Excalibur the default;
+    /// `message` says where.
+    NeedsUser,
+    /// Writing the registration needs administrator rights.
+    NeedsElevation,
+    /// Not an extension Excalibur opens, or not something this platform allows.
+    Unsupported,
+    Failed,
+}
+
+/// What `register_file_associations` did for one extension.
+#[derive(Serialize)]
+pub struct Registration {
+    extension: String,
+    outcome: Outcome,
+    message: Option<String>,
+}
+
+impl Registration {
+    fn new(extension: &str, outcome: Outcome, message: Option<String>) -> Self {
+        Registration {
+            extension: extension.to_string(),
+            outcome,
+            message,
+        }
+    }
+}
+
+/// What the platform code needs to know about the running app.
+struct AppIdentity {
+    executable: PathBuf,
+    /// The bundle identifier from `tauri.conf.json`, e.g. `com.excalibur.canvas`.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    identifier: String,
+    /// Where per-user application data lives, e.g. `~/.local/share` on Linux.
+    #[cfg_attr(any(target_os = "macos", target_os = "windows"), allow(dead_code))]
+    data_dir: Option<PathBuf>,
+}
+
+/// `.MMD` and `mmd` alike become `mmd`.
+fn normalize_extension(extension: &str) -> String {
+    extension
+        .trim()
+        .trim_start_matches('.')
+        .to_ascii_lowercase()
+}
+
+fn mime_type(extension: &str) -> Option<&'static str> {
+    EXTENSIONS
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| *mime)
+}
+
+/// Runs `command` and returns its trimmed standard output, or why it failed.
+fn run(command: &mut Command) -> Result<String, String> {
+    let output = command.output().map_err(|error| error.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// The value of the first `REG_SZ` line in `reg query` output, e.g. `Excalibur.mmd`
+/// from `    (Default)    REG_SZ    Excalibur.mmd`.
+#[cfg(any(target_os = "windows", test))]
+fn reg_value(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (_, value) = line.split_once("REG_SZ")?;
+        Some(value.trim().to_string()).filter(|value| !value.is_empty())
+    })
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+
+    const CLASSES: &str = r"HKCU\Software\Classes";
+
+    fn prog_id(extension: &str) -> String {
+        format!("Excalibur.{extension}")
+    }
+
+    fn reg_query(key: &str, value: Option<&str>) -> Option<String> {
+        let mut command = Command::new("reg");
+        command.args(["query", key]);
+        match value {
+            Some(value) => command.args(["/v", value]),
+            None => command.arg("/ve"),
+        };
+        reg_value(&run(&mut command).ok()?)
+    }
+
+    /// The ProgID the user picked in Explorer, which wins over the class registration.
+    fn user_choice(extension: &str) -> Option<String> {
+        reg_query(
+            &format!(
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\FileExts\.{extension}\UserChoice"
+            ),
+            Some("ProgId"),
+        )
+    }
+
+    pub fn default_handler(
+        _identity: &AppIdentity,
+        extension: &str,
+    ) -> Result<Option<String>, String> {
+        Ok(user_choice(extension).or_else(|| reg_query(&format!(r"HKCR\.{extension}"), None)))
+    }
+
+    pub fn is_ours(_identity: &AppIdentity, handler: &str) -> bool {
+        handler.starts_with("Excalibur.")
+    }
+
+    fn reg_add(key: &str, name: Option<&str>, data: &str) -> Result<(), String> {
+        let mut command = Command::new("reg");
+        command.args(["add", key]);
+        match name {
+            Some(name) => command.args(["/v", name]),
+            None => command.arg("/ve"),
+        };
+        run(command.args(["/t", "REG_SZ", "/d", data, "/f"])).map(|_| ())
+    }
+
+    /// Registers a ProgID for the extension under the current user's classes, which
+    /// needs no elevation. Explorer keeps a choice the user already made elsewhere,
+    /// protected by a hash only it can write, so that one the user has to change.
+    pub fn claim(identity: &AppIdentity, extension: &str, _mime: &str) -> Registration {
+        let prog_id = prog_id(extension);
+        let command = format!("\"{}\" \"%1\"", identity.executable.display());
+        let written = reg_add(
+            &format!(r"{CLASSES}\{prog_id}\shell\open\command"),
+            None,
+            &command,
+        )
+        .and_then(|()| reg_add(&format!(r"{CLASSES}\.{extension}"), None, &prog_id))
+        .and_then(|()| {
+            reg_add(
+                &format!(r"{CLASSES}\.{extension}\OpenWithProgids"),
+                Some(&prog_id),
+                "",
+            )
+        });
+        match written {
+            Err(message) if message.contains("denied") => {
+                Registration::new(extension, Outcome::NeedsElevation, Some(message))
+            }
+            Err(message) => Registration::new(extension, Outcome::Failed, Some(message)),
+            Ok(()) => match user_choice(extension) {
+                Some(choice) if choice != prog_id => Registration::new(
+                    extension,
+                    Outcome::NeedsUser,
+                    Some(format!(
+                        "Windows keeps .{extension} with {choice}; choose Excalibur in Settings > Apps > Default apps"
+                    )),
+                ),
+                _ => Registration::new(extension, Outcome::Registered, None),
+            },
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    const LSREGISTER: &str = "/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister";
+
+    /// Asks LaunchServices, through JavaScript for Automation, which app opens files
+    /// with the extension; prints its bundle identifier.
+    const DEFAULT_HANDLER_SCRIPT: &str = r#"
+ObjC.import('AppKit');
+ObjC.import('UniformTypeIdentifiers');
+function run(argv) {
+  const type = $.UTType.typeWithFilenameExtension(argv[0]);
+  if (type.isNil()) return '';
+  const url = $.NSWorkspace.sharedWorkspace.URLForApplicationToOpenContentType(type);
+  if (url.isNil()) return '';
+  const bundle = $.NSBundle.bundleWithURL(url);
+  return bundle.isNil() ? url.path.js : bundle.bundleIdentifier.js;
+}
+"#;
+
+    pub fn default_handler(
+        _identity: &AppIdentity,
+        extension: &str,
+    ) -> Result<Option<String>, String> {
+        let handler = run(Command::new("osascript").args([
+            "-l",
+            "JavaScript",
+            "-e",
+            DEFAULT_HANDLER_SCRIPT,
+            extension,
+        ]))?;
+        Ok(Some(handler).filter(|handler| !handler.is_empty()))
+    }
+
+    pub fn is_ours(identity: &AppIdentity, handler: &str) -> bool {
+        handler == identity.identifier
+    }
+
+    /// The `.app` bundle the executable runs from; none in development builds.
+    fn app_bundle(identity: &AppIdentity) -> Option<PathBuf> {
+        identity
+            .executable
+            .ancestors()
+            .find(|path| path.extension().is_some_and(|extension| extension == "app"))
+            .map(PathBuf::from)
+    }
+
+    /// Re-registers the app bundle with LaunchServices, so the file types it declares
+    /// are known. Only the user can then make it the default, from Finder.
+    pub fn claim(identity: &AppIdentity, extension: &str, _mime: &str) -> Registration {
+        let Some(bundle) = app_bundle(identity) else {
+            return Registration::new(
+                extension,
+                Outcome::Unsupported,
+                Some("Excalibur isn't running from an app bundle".to_string()),
+            );
+        };
+        if let Err(message) = run(Command::new(LSREGISTER).arg("-f").arg(&bundle)) {
+            return Registration::new(extension, Outcome::Failed, Some(message));
+        }
+        match default_handler(identity, extension) {
+            Ok(Some(handler)) if is_ours(identity, &handler) => {
+                Registration::new(extension, Outcome::Registered, None)
+            }
+            _ => Registration::new(
+                extension,
+                Outcome::NeedsUser,
+                Some(format!(
+                    "Select a .{extension} file in Finder, choose File > Get Info, pick Excalibur under \"Open with\" and click Change All…"
+                )),
+            ),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::*;
+    use std::fs;
+
+    /// The desktop entry Excalibur registers itself under for the current user.
+    const DESKTOP_ID: &str = "excalibur.desktop";
+
+    pub fn default_handler(
+        _identity: &AppIdentity,
+        extension: &str,
+    ) -> Result<Option<String>, String> {
+        let mime = mime_type(extension).unwrap_or_default();
+        let handler = run(Command::new("xdg-mime").args(["query", "default", mime]))?;
+        Ok(Some(handler).filter(|handler| !handler.is_empty()))
+    }
+
+    /// Ours, or the entry a package installed, whatever it is called.
+    pub fn is_ours(_identity: &AppIdentity, handler: &str) -> bool {
+        handler.to_ascii_lowercase().contains("excalibur")
+    }
+
+    /// Writes the MIME types and the desktop entry to the user's data folder and
+    /// refreshes the caches built from them; the refreshes are best-effort.
+    fn install(identity: &AppIdentity) -> Result<(), String> {
+        let data_dir = identity
+            .data_dir
+            .as_ref()
+            .ok_or("there is no data folder to register in")?;
+        let write = |path: PathBuf, contents: String| {
+            fs::create_dir_all(path.parent().unwrap_or(&path))
+                .and_then(|()| fs::write(&path, contents))
+                .map_err(|error| format!("could not write {}: {error}", path.display()))
+        };
+        write(
+            data_dir.join("mime").join("packages").join("excalibur.xml"),
+            mime_package(),
+        )?;
+        write(
+            data_dir.join("applications").join(DESKTOP_ID),
+            desktop_entry(&identity.executable.to_string_lossy()),
+        )?;
+        let _ = run(Command::new("update-mime-database").arg(data_dir.join("mime")));
+        let _ = run(Command::new("update-desktop-database").arg(data_dir.join("applications")));
+        Ok(())
+    }
+
+    /// Makes Excalibur's desktop entry the default for the extension's MIME type in
+    /// the user's `mimeapps.list`.
+    pub fn claim(identity: &AppIdentity, extension: &str, mime: &str) -> Registration {
+        let claimed = install(identity)
+            .and_then(|()| run(Command::new("xdg-mime").args(["default", DESKTOP_ID, mime])));
+        match claimed {
+            Ok(_) => Registration::new(extension, Outcome::Registered, None),
+            Err(message) => Registration::new(extension, Outcome::Failed, Some(message)),
+        }
+    }
+}
+
+/// A shared-mime-info package declaring each MIME type with its file name globs.
+#[cfg(any(not(any(target_os = "macos", target_os = "windows")), test))]
+fn mime_package() -> String {
+    let mut mime_types: Vec<&str> = EXTENSIONS.iter().map(|(_, mime)| *mime).collect();
+    mime_types.dedup();
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n",
+    );
+    for mime in mime_types {
+        xml.push_str(&format!("  <mime-type type=\"{mime}\">\n"));
+        for (extension, _) in EXTENSIONS.iter().filter(|(_, other)| *other == mime) {
+            xml.push_str(&format!("    <glob pattern=\"*.{extension}\"/>\n"));
+        }
+        xml.push_str("  </mime-type>\n");
+    }
+    xml.push_str("</mime-info>\n");
+    xml
+}
+
+/// A desktop entry that opens files with `executable`, quoted as the Desktop Entry
+/// spec asks for `Exec`: reserved characters are escaped inside the quotes, and
+/// every backslash is escaped again because the value is a string.
+#[cfg(any(not(any(target_os = "macos", target_os = "windows")), test))]
+fn desktop_entry(executable: &str) -> String {
+    let mut quoted = String::from('"');
+    for ch in executable.chars() {
+        if matches!(ch, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    let quoted = quoted.replace('\\', "\\\\");
+    let mut mime_types: Vec<&str> = EXTENSIONS.iter().map(|(_, mime)| *mime).collect();
+    mime_types.dedup();
+    format!(
+        "[Desktop Entry]\nType=Application\nName=Excalibur\nExec={} %F\nTerminal=false\nCategories=Graphics;\nMimeType={};\n",
+        // `%` is a field code in Exec, so a literal one is doubled.
+        quoted.replace('%', "%%"),
+        mime_types.join(";")
+    )
+}
+
+fn identity(app: &AppHandle) -> Result<AppIdentity, CommandError> {
+    Ok(AppIdentity {
+        executable: std::env::current_exe().map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })?,
+        identifier: app.config().identifier.clone(),
+        data_dir: app.path().data_dir().ok(),
+    })
+}
+
+/// Runs `work` off the command thread; the system tools it calls can be slow.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, CommandError> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })
+}
+
+/// Whether Excalibur opens each extension it handles by default, and what does
+/// otherwise: the Windows registry, LaunchServices or `xdg-mime` is asked.
+#[tauri::command]
+pub async fn file_association_status(
+    app: AppHandle,
+) -> Result<Vec<AssociationStatus>, CommandError> {
+    let identity = identity(&app)?;
+    blocking(move || {
+        EXTENSIONS
+            .iter()
+            .map(|(extension, _)| {
+                let handler = platform::default_handler(&identity, extension);
+                if let Err(message) = &handler {
+                    log::info!("file_association_status: .{extension}: {message}");
+                }
+                let handler = handler.ok();
+                AssociationStatus {
+                    extension: extension.to_string(),
+                    is_default: handler.as_ref().map(|handler| {
+                        handler
+                            .as_deref()
+                            .is_some_and(|handler| platform::is_ours(&identity, handler))
+                    }),
+                    handler: handler.flatten(),
+                }
+            })
+            .collect()
+    })
+    .await
+}
+
+/// Tries to make Excalibur the default app for `extensions`, all it handles when
+/// none are given, and reports per extension how far that got.
+#[tauri::command]
+pub async fn register_file_associations(
+    app: AppHandle,
+    extensions: Option<Vec<String>>,
+) -> Result<Vec<Registration>, CommandError> {
+    let identity = identity(&app)?;
+    let extensions = extensions.unwrap_or_else(|| {
+        EXTENSIONS
+            .iter()
+            .map(|(extension, _)| extension.to_string())
+            .collect()
+    });
+    blocking(move || {
+        extensions
+            .iter()
+            .map(|extension| {
+                let extension = normalize_extension(extension);
+                let Some(mime) = mime_type(&extension) else {
+                    return Registration::new(
+                        &extension,
+                        Outcome::Unsupported,
+                        Some(format!("Excalibur doesn't open .{extension} files")),
+                    );
+                };
+                let registration = platform::claim(&identity, &extension, mime);
+                log::info!(
+                    "register_file_associations: .{}: {:?}",
+                    extension,
+                    registration.outcome
+                );
+                registration
+            })
+            .collect()
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_are_matched_without_dot_or_case() {
+        assert_eq!(normalize_extension(".MMD"), "mmd");
+        assert_eq!(
+            mime_type(&normalize_extension(" Excalidraw")),
+            Some("application/vnd.excalidraw+json")
+        );
+        assert_eq!(mime_type("txt"), None);
+    }
+
+    #[test]
+    fn reads_the_value_from_reg_query_output() {
+        let output =
+            "\r\nHKEY_CLASSES_ROOT\\.mmd\r\n    (Default)    REG_SZ    Excalibur.mmd\r\n\r\n";
+        assert_eq!(reg_value(output).as_deref(), Some("Excalibur.mmd"));
+        assert_eq!(reg_value("HKEY_CLASSES_ROOT\\.mmd\r\n"), None);
+    }
+
+    #[test]
+    fn linux_registration_files_list_every_type() {
+        let package = mime_package();
+        assert_eq!(package.matches("<mime-type ").count(), 2);
+        assert!(package.contains("<glob pattern=\"*.mermaid\"/>"));
+        let entry = desktop_entry("/opt/My Apps/$excalibur 100%");
+        assert!(entry.contains("Exec=\"/opt/My Apps/\\\\$excalibur 100%%\" %F\n"));
+        assert!(entry.contains("MimeType=application/vnd.excalidraw+json;text/vnd.mermaid;\n"));
+    }
+}
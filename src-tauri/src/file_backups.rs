@@ -0,0 +1,154 @@
+use crate::error::CommandError;
+use crate::{file_name, file_status, OpenFileResponse};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Previous versions kept next to a file, from `.bak1` (the newest) to `.bak3`.
+const MAX_BACKUPS: usize = 3;
+
+#[derive(Serialize, Debug)]
+pub struct FileBackup {
+    /// 1 for the version replaced by the most recent save.
+    index: usize,
+    path: String,
+    size: Option<u64>,
+    modified_at: Option<u64>,
+}
+
+/// `drawing.excalidraw` → `drawing.excalidraw.bak2`.
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak{index}"));
+    path.with_file_name(name)
+}
+
+/// Shifts the existing backups of `path` down one place, dropping the oldest, and
+/// copies `path` to `.bak1`. Returns `false` when `path` doesn't exist yet.
+fn rotate(path: &Path) -> io::Result<bool> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    for index in (1..MAX_BACKUPS).rev() {
+        let older = backup_path(path, index);
+        if older.exists() {
+            fs::rename(&older, backup_path(path, index + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(true)
+}
+
+fn backups(path: &Path) -> Vec<FileBackup> {
+    (1..=MAX_BACKUPS)
+        .map(|index| (index, backup_path(path, index)))
+        .filter(|(_, backup)| backup.is_file())
+        .map(|(index, backup)| {
+            let status = file_status(&backup);
+            FileBackup {
+                index,
+                path: backup.to_string_lossy().to_string(),
+                size: status.size,
+                modified_at: status.modified_at,
+            }
+        })
+        .collect()
+}
+
+/// Keeps the current contents of `path` as `.bak1` before a save overwrites it,
+/// unless the request turned backups off. New files have nothing to keep.
+pub fn before_save(path: &Path, keep_backup: Option<bool>) -> io::Result<()> {
+    if keep_backup.unwrap_or(true) {
+        rotate(path)?;
+    }
+    Ok(())
+}
+
+/// The `.bak` copies next to `path`, newest first.
+#[tauri::command]
+pub fn list_backups(path: String) -> Vec<FileBackup> {
+    backups(Path::new(&path))
+}
+
+/// Puts backup `index` back in place of `path`. The contents being replaced become
+/// the newest backup first, and can also be undone like a save.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    path: String,
+    index: usize,
+) -> Result<OpenFileResponse, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    if crate::volumes::is_disconnected(&app, &path_buf) {
+        return Err(crate::volumes::disconnected_error(&path_buf));
+    }
+    crate::read_only::ensure_writable(&app, &path_buf)?;
+    let not_found = || CommandError::BackupNotFound {
+        path: path.clone(),
+        index,
+    };
+    if !(1..=MAX_BACKUPS).contains(&index) {
+        return Err(not_found());
+    }
+    let restored = fs::read(backup_path(&path_buf, index)).map_err(|_| not_found())?;
+
+    let capture = crate::local_history::capture_before_save(&app, &path_buf);
+    rotate(&path_buf)?;
+    crate::file_watch::expect_write(&app, &path_buf, &restored);
+    crate::atomic_write::write_atomic(&path_buf, &restored)?;
+    crate::local_history::finish_save(&app, &path_buf, capture);
+    crate::checksums::after_save(&app, &path_buf);
+    log::info!("restore_backup: {} from .bak{}", path, index);
+
+    let (contents, encrypted) = crate::crypto::read_document(&app, &path_buf, None).await?;
+    Ok(OpenFileResponse {
+        name: file_name(&path_buf),
+        mtime: crate::modified_millis(&path_buf),
+        path,
+        contents,
+        encrypted,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn saves_rotate_through_three_backups() {
+        let dir = temp_dir("bak", "rotate");
+        let path = dir.join("flow.mmd");
+        assert!(!rotate(&path).unwrap());
+        assert!(backups(&path).is_empty());
+
+        for version in 1..=5 {
+            fs::write(&path, format!("v{version}")).unwrap();
+            rotate(&path).unwrap();
+        }
+        let kept: Vec<_> = backups(&path)
+            .iter()
+            .map(|backup| fs::read_to_string(&backup.path).unwrap())
+            .collect();
+        assert_eq!(kept, ["v5", "v4", "v3"]);
+        assert_eq!(backups(&path)[0].index, 1);
+        assert!(!backup_path(&path, 4).exists());
+    }
+
+    #[test]
+    fn backups_can_be_turned_off() {
+        let dir = temp_dir("bak", "off");
+        let path = dir.join("board.excalidraw");
+        fs::write(&path, "{}").unwrap();
+        before_save(&path, Some(false)).unwrap();
+        assert!(backups(&path).is_empty());
+        before_save(&path, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("board.excalidraw.bak1")).unwrap(),
+            "{}"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::detect::{detect_kind, mermaid_diagram_type};
+use crate::error::CommandError;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Scenes larger than this are not parsed for `scene_stats`.
+pub const MAX_STATS_BYTES: u64 = 8 * 1024 * 1024;
+/// How much of a larger file is read to work out its kind.
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Element counts for an excalidraw scene.
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct SceneStats {
+    pub elements: usize,
+    pub deleted_elements: usize,
+    /// Live elements per type, e.g. `{"arrow": 3, "rectangle": 5}`.
+    pub by_type: BTreeMap<String, usize>,
+    pub embedded_files: usize,
+}
+
+/// Everything the document info panel shows. Fields the platform can't provide are `None`.
+#[derive(Serialize)]
+pub struct FileInfo {
+    path: String,
+    name: Option<String>,
+    /// Logical length in bytes.
+    size: u64,
+    /// Space actually allocated, which differs for sparse or compressed files.
+    size_on_disk: Option<u64>,
+    created_at: Option<u64>,
+    modified_at: Option<u64>,
+    accessed_at: Option<u64>,
+    owner: Option<String>,
+    owner_id: Option<u32>,
+    group: Option<String>,
+    group_id: Option<u32>,
+    read_only: bool,
+    symlink: bool,
+    symlink_target: Option<String>,
+    kind: Option<&'static str>,
+    /// Mermaid diagram type, e.g. "sequenceDiagram".
+    diagram_type: Option<&'static str>,
+    encrypted: bool,
+    /// Only for plain excalidraw scenes up to `MAX_STATS_BYTES`.
+    scene_stats: Option<SceneStats>,
+}
+
+/// The size and times a document header shows, in epoch seconds.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FileMeta {
+    pub size_bytes: u64,
+    pub modified_epoch: u64,
+    /// `None` where the platform or file system doesn't record creation times.
+    pub created_epoch: Option<u64>,
+}
+
+fn epoch_secs(time: std::io::Result<SystemTime>) -> Option<u64> {
+    Some(time.ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+fn meta_of(metadata: &fs::Metadata) -> FileMeta {
+    FileMeta {
+        size_bytes: metadata.len(),
+        modified_epoch: epoch_secs(metadata.modified()).unwrap_or_default(),
+        created_epoch: epoch_secs(metadata.created()),
+    }
+}
+
+/// `FileMeta` for an opened document; `None` if it can't be read any more.
+pub fn file_meta(path: &Path) -> Option<FileMeta> {
+    fs::metadata(path).ok().map(|metadata| meta_of(&metadata))
+}
+
+/// Looks up the name for `id` in `/etc/passwd` or `/etc/group` style contents,
+/// where the id is the third colon-separated field.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn name_for_id(contents: &str, id: u32) -> Option<String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let line_id = fields.nth(1)?.parse::<u32>().ok()?;
+            (line_id == id && !name.is_empty()).then(|| name.to_string())
+        })
+}
+
+#[cfg(unix)]
+fn lookup_name(database: &str, id: u32) -> Option<String> {
+    name_for_id(&fs::read_to_string(database).ok()?, id)
+}
+
+pub fn scene_stats(contents: &str) -> Option<SceneStats> {
+    let scene: Value = serde_json::from_str(contents).ok()?;
+    let mut stats = SceneStats::default();
+    for element in scene.get("elements")?.as_array()? {
+        if element.get("isDeleted").and_then(Value::as_bool) == Some(true) {
+            stats.deleted_elements += 1;
+            continue;
+        }
+        stats.elements += 1;
+        let element_type = element
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        *stats.by_type.entry(element_type.to_string()).or_default() += 1;
+    }
+    stats.embedded_files = scene
+        .get("files")
+        .and_then(Value::as_object)
+        .map_or(0, |files| files.len());
+    Some(stats)
+}
+
+/// Details for the document info panel. Only files up to `MAX_STATS_BYTES` are read
+/// in full; larger ones are sniffed from their first bytes and get no scene stats.
+#[tauri::command]
+pub fn file_info(path: String) -> Result<FileInfo, CommandError> {
+    let path_buf = Path::new(&path);
+    let link_metadata = fs::symlink_metadata(path_buf)?;
+    let metadata = fs::metadata(path_buf)?;
+    let symlink = link_metadata.file_type().is_symlink();
+    if !metadata.is_file() {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{path} is not a file"),
+        });
+    }
+
+    let complete = metadata.len() <= MAX_STATS_BYTES;
+    let mut bytes = Vec::new();
+    File::open(path_buf)?
+        .take(if complete {
+            MAX_STATS_BYTES
+        } else {
+            SNIFF_BYTES
+        })
+        .read_to_end(&mut bytes)?;
+    let encrypted = crate::crypto::is_encrypted_bytes(&bytes);
+    let (kind, diagram_type, stats) = if encrypted {
+        (crate::crypto::encrypted_kind(&bytes).ok(), None, None)
+    } else {
+        let text = String::from_utf8_lossy(&bytes);
+        let kind = detect_kind(path_buf, &text);
+        let diagram_type = match kind {
+            Some("mermaid") => mermaid_diagram_type(&text),
+            _ => None,
+        };
+        let stats = match kind {
+            Some("excalidraw") if complete => scene_stats(&text),
+            _ => None,
+        };
+        (kind, diagram_type, stats)
+    };
+
+    #[cfg(unix)]
+    let (owner_id, group_id, size_on_disk) = {
+        use std::os::unix::fs::MetadataExt;
+        (
+            Some(metadata.uid()),
+            Some(metadata.gid()),
+            Some(metadata.blocks() * 512),
+        )
+    };
+    #[cfg(not(unix))]
+    let (owner_id, group_id, size_on_disk): (Option<u32>, Option<u32>, Option<u64>) =
+        (None, None, None);
+    #[cfg(unix)]
+    let (owner, group) = (
+        owner_id.and_then(|id| lookup_name("/etc/passwd", id)),
+        group_id.and_then(|id| lookup_name("/etc/group", id)),
+    );
+    #[cfg(not(unix))]
+    let (owner, group) = (None, None);
+
+    Ok(FileInfo {
+        name: crate::file_name(path_buf),
+        size: metadata.len(),
+        size_on_disk,
+        created_at: epoch_secs(metadata.created()),
+        modified_at: epoch_secs(metadata.modified()),
+        accessed_at: epoch_secs(metadata.accessed()),
+        owner,
+        owner_id,
+        group,
+        group_id,
+        read_only: metadata.permissions().readonly(),
+        symlink,
+        symlink_target: fs::read_link(path_buf)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string()),
+        kind,
+        diagram_type,
+        encrypted,
+        scene_stats: stats,
+        path,
+    })
+}
+
+/// Size and times of the file at `path`, to refresh a document header without
+/// reading the file again.
+#[tauri::command]
+pub fn stat_file(path: String) -> Result<FileMeta, CommandError> {
+    let path_buf = Path::new(&path);
+    let metadata =
+        fs::metadata(path_buf).map_err(|error| CommandError::from_io(error, path_buf))?;
+    Ok(meta_of(&metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_reports_size_and_times() {
+        let path = std::env::temp_dir().join(format!("excalibur-meta-{}.mmd", std::process::id()));
+        fs::write(&path, "graph TD").unwrap();
+        let meta = stat_file(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(meta.size_bytes, 8);
+        assert!(meta.modified_epoch > 0);
+        assert_eq!(file_meta(&path), Some(meta));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(file_meta(&path), None);
+        assert_eq!(
+            stat_file(path.to_string_lossy().to_string())
+                .unwrap_err()
+                .code(),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn counts_live_elements_by_type() {
+        let scene = r#"{
+            "type": "excalidraw",
+            "elements": [
+                {"type": "rectangle"},
+                {"type": "rectangle"},
+                {"type": "arrow"},
+                {"type": "text", "isDeleted": true}
+            ],
+            "files": {"a": {}, "b": {}}
+        }"#;
+        let stats = scene_stats(scene).unwrap();
+        assert_eq!(stats.elements, 3);
+        assert_eq!(stats.deleted_elements, 1);
+        assert_eq!(stats.by_type["rectangle"], 2);
+        assert_eq!(stats.by_type.get("text"), None);
+        assert_eq!(stats.embedded_files, 2);
+        assert_eq!(scene_stats("graph TD"), None);
+    }
+
+    #[test]
+    fn resolves_names_from_account_databases() {
+        let passwd = "# comment\nroot:x:0:0:root:/root:/bin/sh\nsam:x:501:20::/home/sam:/bin/zsh\n";
+        assert_eq!(name_for_id(passwd, 501), Some("sam".to_string()));
+        assert_eq!(name_for_id(passwd, 0), Some("root".to_string()));
+        assert_eq!(name_for_id(passwd, 7), None);
+        assert_eq!(
+            name_for_id("staff:*:20:sam\n", 20),
+            Some("staff".to_string())
+        );
+    }
+}
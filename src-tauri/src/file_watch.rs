@@ -0,0 +1,183 @@
+use crate::error::CommandError;
+use crate::{content_hash, file_status};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Watches on the documents open in the editors, so changes made by other programs
+/// are noticed before a save overwrites them.
+#[derive(Default)]
+pub struct FileWatches {
+    watchers: Mutex<HashMap<PathBuf, RecommendedWatcher>>,
+    /// Hash of the contents each watched file is known to have; `None` once it was
+    /// removed. Kept apart from `watchers` so event callbacks never wait on a watcher
+    /// being dropped.
+    known: Mutex<HashMap<PathBuf, Option<String>>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Modified,
+    Removed,
+}
+
+#[derive(Serialize, Clone)]
+struct FileChanged {
+    path: String,
+    modified_at: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+struct FileRemoved {
+    path: String,
+}
+
+fn current_hash(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+/// Compares a file's contents now with what they were known to be, remembering the
+/// new state. Editors and our own saves fire several events per write; only the
+/// first one with different contents counts.
+fn detect_change(known: &mut Option<String>, current: Option<String>) -> Option<Change> {
+    if *known == current {
+        return None;
+    }
+    let change = if current.is_some() {
+        Change::Modified
+    } else {
+        Change::Removed
+    };
+    *known = current;
+    Some(change)
+}
+
+fn on_event(app: &AppHandle, path: &Path) {
+    let current = current_hash(path);
+    let change = {
+        let state = app.state::<FileWatches>();
+        let mut known = state.known.lock().unwrap();
+        let Some(entry) = known.get_mut(path) else {
+            return;
+        };
+        detect_change(entry, current)
+    };
+    let path_string = path.to_string_lossy().to_string();
+    match change {
+        Some(Change::Modified) => {
+            log::info!("file_watch: {} changed on disk", path_string);
+            let _ = app.emit(
+                "file-changed-externally",
+                FileChanged {
+                    path: path_string,
+                    modified_at: file_status(path).modified_at,
+                },
+            );
+        }
+        Some(Change::Removed) => {
+            log::info!("file_watch: {} was removed", path_string);
+            let _ = app.emit("file-removed", FileRemoved { path: path_string });
+        }
+        None => {}
+    }
+}
+
+/// Records `contents` as what a watched `path` is about to hold, so the events of
+/// writing it aren't reported as outside changes. Called by document saves and
+/// restores just before they write; a write that then fails only means the next
+/// real change is reported.
+pub fn expect_write(app: &AppHandle, path: &Path, contents: &[u8]) {
+    if let Some(entry) = app
+        .state::<FileWatches>()
+        .known
+        .lock()
+        .unwrap()
+        .get_mut(path)
+    {
+        *entry = Some(content_hash(contents));
+    }
+}
+
+/// Starts reporting changes other programs make to `path`: `file-changed-externally`
+/// with the new mtime when it is modified, `file-removed` when it is deleted or
+/// renamed away. The folder is watched rather than the file, so the watch survives
+/// editors that save by replacing the file. Watching a path again resets it.
+#[tauri::command]
+pub fn watch_file(app: AppHandle, path: String) -> Result<(), CommandError> {
+    let target = PathBuf::from(&path);
+    let Some(folder) = target.parent().filter(|folder| folder.is_dir()) else {
+        return Err(CommandError::InvalidArgument {
+            message: format!("Cannot watch {path}: its folder doesn't exist"),
+        });
+    };
+
+    let handle = app.clone();
+    let watched = target.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.paths.contains(&watched) => on_event(&handle, &watched),
+            Ok(_) => {}
+            Err(error) => log::warn!(
+                "file_watch: error watching {}: {}",
+                watched.display(),
+                error
+            ),
+        })
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })?;
+    watcher
+        .watch(folder, RecursiveMode::NonRecursive)
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })?;
+
+    let state = app.state::<FileWatches>();
+    state
+        .known
+        .lock()
+        .unwrap()
+        .insert(target.clone(), current_hash(&target));
+    // A watcher this replaces is dropped here, outside the lock.
+    let _replaced = state.watchers.lock().unwrap().insert(target, watcher);
+    log::info!("watch_file: {}", path);
+    Ok(())
+}
+
+/// Stops watching `path`, e.g. when the editor switches to another document.
+#[tauri::command]
+pub fn unwatch_file(app: AppHandle, path: String) {
+    let target = PathBuf::from(&path);
+    let state = app.state::<FileWatches>();
+    state.known.lock().unwrap().remove(&target);
+    let watcher = state.watchers.lock().unwrap().remove(&target);
+    if watcher.is_some() {
+        log::info!("unwatch_file: {}", path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_change_once() {
+        let mut known = Some("a".to_string());
+        assert_eq!(detect_change(&mut known, Some("a".to_string())), None);
+        assert_eq!(
+            detect_change(&mut known, Some("b".to_string())),
+            Some(Change::Modified)
+        );
+        assert_eq!(detect_change(&mut known, Some("b".to_string())), None);
+        assert_eq!(detect_change(&mut known, None), Some(Change::Removed));
+        assert_eq!(detect_change(&mut known, None), None);
+        assert_eq!(
+            detect_change(&mut known, Some("c".to_string())),
+            Some(Change::Modified)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::CommandError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// First bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Whether the file at `path` is gzipped, from its first bytes.
+pub fn is_gzip_file(path: &Path) -> bool {
+    let mut start = [0; GZIP_MAGIC.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok_and(|()| is_gzip(&start))
+}
+
+pub fn has_gz_suffix(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// The extension of the document inside the file: `mmd` for both `flow.mmd` and
+/// `flow.mmd.gz`.
+pub fn document_extension(path: &Path) -> Option<&OsStr> {
+    if !has_gz_suffix(path) {
+        return path.extension();
+    }
+    Path::new(path.file_stem()?).extension()
+}
+
+/// Decompresses `bytes` read from `path` when they are gzipped, and returns anything
+/// else as it is. A damaged stream is `InvalidGzip`, and one that unpacks to more
+/// than `max_bytes` is `FileTooLarge`, so a small file can't expand without bound.
+pub fn decompress(path: &Path, bytes: Vec<u8>, max_bytes: u64) -> Result<Vec<u8>, CommandError> {
+    if !is_gzip(&bytes) {
+        return Ok(bytes);
+    }
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes.as_slice())
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|error| CommandError::InvalidGzip {
+            path: path.to_string_lossy().to_string(),
+            message: error.to_string(),
+        })?;
+    if decompressed.len() as u64 > max_bytes {
+        return Err(CommandError::FileTooLarge {
+            path: path.to_string_lossy().to_string(),
+            size: decompressed.len() as u64,
+            limit: max_bytes,
+        });
+    }
+    Ok(decompressed)
+}
+
+pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// Opens `path` for reading what it holds, decompressing it on the way if it is
+/// gzipped.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    if is_gzip_file(path) {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_passes_plain_bytes_through() {
+        let path = Path::new("flow.mmd.gz");
+        let compressed = compress(b"graph TD\n  a --> b").unwrap();
+        assert!(is_gzip(&compressed));
+        assert_eq!(
+            decompress(path, compressed.clone(), 1024).unwrap(),
+            b"graph TD\n  a --> b"
+        );
+        assert_eq!(
+            decompress(path, b"graph TD".to_vec(), 1024).unwrap(),
+            b"graph TD"
+        );
+
+        let mut damaged = compressed;
+        damaged.truncate(damaged.len() / 2);
+        assert_eq!(
+            decompress(path, damaged, 1024).unwrap_err().code(),
+            "invalid_gzip"
+        );
+    }
+
+    #[test]
+    fn decompressing_stops_at_the_size_limit() {
+        let path = Path::new("bomb.mmd.gz");
+        let compressed = compress(&vec![b'a'; 64 * 1024]).unwrap();
+        assert!(compressed.len() < 1024);
+        assert_eq!(
+            decompress(path, compressed.clone(), 64 * 1024)
+                .unwrap()
+                .len(),
+            64 * 1024
+        );
+        match decompress(path, compressed, 1024) {
+            Err(CommandError::FileTooLarge { size, limit, .. }) => {
+                assert_eq!((size, limit), (1025, 1024))
+            }
+            other => panic!("expected FileTooLarge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn the_document_extension_is_inside_the_gz_suffix() {
+        let extension = |name| document_extension(Path::new(name)).and_then(OsStr::to_str);
+        assert_eq!(extension("flow.mmd"), Some("mmd"));
+        assert_eq!(extension("flow.mmd.gz"), Some("mmd"));
+        assert_eq!(extension("Scene.Excalidraw.GZ"), Some("Excalidraw"));
+        assert_eq!(extension("notes.gz"), None);
+    }
+}
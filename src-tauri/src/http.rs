@@ -0,0 +1,24 @@
+use crate::settings::Settings;
+use std::time::Duration;
+
+/// Redirects followed before a request is abandoned.
+const MAX_REDIRECTS: usize = 5;
+
+/// Builds the HTTP client used for every outgoing request, honouring the proxy
+/// configured in settings.
+pub fn http_client(settings: &Settings, timeout: Duration) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("excalibur/", env!("CARGO_PKG_VERSION")))
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS));
+    if let Some(proxy) = settings
+        .proxy_url
+        .as_deref()
+        .filter(|proxy| !proxy.is_empty())
+    {
+        let proxy =
+            reqwest::Proxy::all(proxy).map_err(|error| format!("Invalid proxy: {error}"))?;
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|error| error.to_string())
+}
@@ -0,0 +1,88 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Default for the `large_file_threshold_bytes` setting.
+pub const DEFAULT_THRESHOLD_BYTES: u64 = 20 * 1024 * 1024;
+/// Default for the `max_file_bytes` setting.
+pub const DEFAULT_MAX_BYTES: u64 = 200 * 1024 * 1024;
+
+/// Whether a file of `size` bytes counts as large, or `FileTooLarge` when it is over
+/// `max`.
+fn classify(path: &Path, size: u64, threshold: u64, max: u64) -> Result<bool, CommandError> {
+    if size > max {
+        return Err(CommandError::FileTooLarge {
+            path: path.to_string_lossy().to_string(),
+            size,
+            limit: max,
+        });
+    }
+    Ok(size > threshold)
+}
+
+/// Checks the size of the document at `path` against the settings: `FileTooLarge`
+/// above `max_file_bytes`, otherwise whether it is above the large file threshold.
+/// A file that can't be looked at isn't large; reading it reports why.
+pub fn check_size(app: &AppHandle, path: &Path) -> Result<bool, CommandError> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(false);
+    };
+    let settings = load_settings(app);
+    classify(
+        path,
+        metadata.len(),
+        settings.large_file_threshold_bytes,
+        settings.max_file_bytes,
+    )
+}
+
+/// Whether the document at `path` is over the large file threshold, for
+/// `OpenFileResponse::large_file`.
+pub fn is_large(app: &AppHandle, path: &Path) -> bool {
+    check_size(app, path).unwrap_or(true)
+}
+
+/// Reads a document's bytes, refusing files over `max_file_bytes`. Large files are
+/// read on a blocking thread, so a slow disk doesn't hold up other commands.
+pub async fn read(app: &AppHandle, path: &Path) -> Result<Vec<u8>, CommandError> {
+    if !check_size(app, path)? {
+        return fs::read(path).map_err(|error| CommandError::from_io(error, path));
+    }
+    log::debug!("read: reading {} on a blocking thread", path.display());
+    let path = path.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        fs::read(&path).map_err(|error| CommandError::from_io(error, &path))
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?
+}
+
+/// Reads a document like `read` and decompresses it when it is gzipped, refusing
+/// contents that unpack to more than `max_file_bytes`.
+pub async fn read_decompressed(app: &AppHandle, path: &Path) -> Result<Vec<u8>, CommandError> {
+    let bytes = read(app, path).await?;
+    crate::gzip::decompress(path, bytes, load_settings(app).max_file_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_classified_against_both_limits() {
+        let path = Path::new("scene.excalidraw");
+        assert!(!classify(path, 20, 20, 200).unwrap());
+        assert!(classify(path, 21, 20, 200).unwrap());
+        assert!(classify(path, 200, 20, 200).unwrap());
+        match classify(path, 201, 20, 200) {
+            Err(CommandError::FileTooLarge { size, limit, .. }) => {
+                assert_eq!((size, limit), (201, 200))
+            }
+            other => panic!("expected FileTooLarge, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::event_queue::emit_when_ready;
+use crate::{deep_links, paths};
+use serde::Serialize;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Flags whose value is the next argument, so it isn't taken for a file.
+const FLAGS_WITH_VALUE: &[&str] = &[paths::DATA_DIR_FLAG, "--kind"];
+
+/// A file named on the command line: the argument as given and the absolute path
+/// it resolves to.
+#[derive(Debug, PartialEq)]
+struct FileArgument {
+    argument: String,
+    path: String,
+}
+
+/// Sent for a file argument that can't be opened.
+#[derive(Serialize, Clone)]
+struct OpenFileError {
+    argument: String,
+    path: String,
+    reason: String,
+}
+
+/// Splits a launch's arguments into files, resolved against the `cwd` the launch
+/// was started in, and URLs (e.g. `excalibur://` links on Windows, which arrive as
+/// arguments). The program name and flags, known or not, are skipped.
+fn parse(argv: &[String], cwd: &Path) -> (Vec<FileArgument>, Vec<url::Url>) {
+    let mut files = Vec::new();
+    let mut urls = Vec::new();
+    let mut rest = argv.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        if FLAGS_WITH_VALUE.contains(&arg.as_str()) {
+            rest.next();
+            continue;
+        }
+        if arg.starts_with('-') || arg.is_empty() {
+            continue;
+        }
+        // `C:\plan.mmd` parses as a URL with scheme `c`; real schemes are longer.
+        match url::Url::parse(arg) {
+            Ok(url) if url.scheme().len() > 1 => urls.push(url),
+            _ => files.push(FileArgument {
+                argument: arg.clone(),
+                path: paths::normalize(&cwd.join(arg))
+                    .to_string_lossy()
+                    .to_string(),
+            }),
+        }
+    }
+    (files, urls)
+}
+
+/// Splits `files` into the paths of those that are files and the error to report
+/// for each of the others.
+fn check_files(files: Vec<FileArgument>) -> (Vec<String>, Vec<OpenFileError>) {
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let reason = match std::fs::metadata(&file.path) {
+            Ok(metadata) if metadata.is_file() => {
+                paths.push(file.path);
+                continue;
+            }
+            Ok(_) => "it is not a file".to_string(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                "the file does not exist".to_string()
+            }
+            Err(error) => error.to_string(),
+        };
+        errors.push(OpenFileError {
+            argument: file.argument,
+            path: file.path,
+            reason,
+        });
+    }
+    (paths, errors)
+}
+
+/// Opens the files named in `argv` with one `open-files` event, the way deep links
+/// are opened, and reports each argument that isn't a file as `open-file-error`.
+/// URLs are only handled when `with_urls` is set; at startup the deep-link plugin
+/// already delivers them.
+pub fn open(app: &AppHandle, argv: &[String], cwd: &Path, with_urls: bool) {
+    let (files, urls) = parse(argv, cwd);
+    if with_urls && !urls.is_empty() {
+        deep_links::handle_urls(app, &urls);
+    }
+    let (paths, errors) = check_files(files);
+    for error in errors {
+        log::warn!(
+            "launch: can't open argument {:?}: {}",
+            error.argument,
+            error.reason
+        );
+        emit_when_ready(app, None, "open-file-error", error);
+    }
+    if !paths.is_empty() {
+        log::info!("launch: opening {:?}", paths);
+        emit_when_ready(app, None, "open-files", paths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_files_against_the_launch_directory() {
+        let (files, urls) = parse(
+            &args(&[
+                "/usr/bin/excalibur",
+                "flow.mmd",
+                "--data-dir",
+                "data",
+                "../shared/plan.excalidraw",
+                "--portable",
+                "--unknown-flag",
+                "-psn_0_12345",
+                "/abs/notes.mmd",
+                "excalibur://new?kind=mermaid",
+            ]),
+            Path::new("/home/ada/work"),
+        );
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/home/ada/work/flow.mmd",
+                "/home/ada/shared/plan.excalidraw",
+                "/abs/notes.mmd",
+            ]
+        );
+        assert_eq!(files[1].argument, "../shared/plan.excalidraw");
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls[0].scheme(), "excalibur");
+    }
+
+    #[test]
+    fn reports_arguments_that_are_not_files() {
+        let dir = std::env::temp_dir().join(format!("excalibur-launch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("flow.mmd"), "graph TD").unwrap();
+        let argv = args(&["excalibur", "flow.mmd", "gone.mmd", "."]);
+
+        let (files, _) = parse(&argv, &dir);
+        let (paths, errors) = check_files(files);
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("flow.mmd"));
+        let reported: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|error| (error.argument.as_str(), error.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                ("gone.mmd", "the file does not exist"),
+                (".", "it is not a file"),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
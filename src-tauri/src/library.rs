@@ -0,0 +1,164 @@
+use crate::error::CommandError;
+use crate::{
+    check_conflict, dialogs, file_name, gzip, keep_backup, large_files, modified_millis, paths,
+    read_only, save_as_start, text_encoding, update_recents, write_binary_file, write_file,
+    OpenFileResponse, SaveFileRequest, SaveFileResponse,
+};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Recents kind of Excalidraw libraries. They are capped apart from documents; see
+/// `recent_pins::cap`.
+pub const LIBRARY_KIND: &str = "library";
+const LIBRARY_EXTENSIONS: &[&str] = &["excalidrawlib", "json", "gz"];
+
+/// Checks the top level of a library file: `"type": "excalidrawlib"` and a
+/// `libraryItems` array. The items themselves are left to Excalidraw.
+fn validate(contents: &str) -> Result<(), CommandError> {
+    let invalid = |message: &str| CommandError::InvalidDocument {
+        message: message.to_string(),
+    };
+    let library: Value =
+        serde_json::from_str(contents).map_err(|error| invalid(&format!("not JSON: {error}")))?;
+    if library.get("type").and_then(Value::as_str) != Some("excalidrawlib") {
+        return Err(invalid(
+            "not an Excalidraw library (type is not \"excalidrawlib\")",
+        ));
+    }
+    if !library.get("libraryItems").is_some_and(Value::is_array) {
+        return Err(invalid("the library has no libraryItems array"));
+    }
+    Ok(())
+}
+
+/// Reads and checks the library at `path`, lists it in recents and returns it like
+/// an opened document.
+async fn open(app: &AppHandle, path: &Path) -> Result<OpenFileResponse, CommandError> {
+    let bytes = large_files::read_decompressed(app, path).await?;
+    let (contents, _) = text_encoding::decode(path, bytes)?;
+    validate(&contents)?;
+    let name = file_name(path);
+    let path_string = path.to_string_lossy().to_string();
+    update_recents(app, LIBRARY_KIND, &path_string, name.clone());
+    Ok(OpenFileResponse {
+        writable_dir: Some(read_only::dir_writable(path)),
+        meta: crate::file_info::file_meta(path),
+        large_file: large_files::is_large(app, path),
+        compressed: gzip::is_gzip_file(path),
+        encoding: text_encoding::reported(path),
+        mtime: modified_millis(path),
+        path: path_string,
+        name,
+        contents,
+        ..Default::default()
+    })
+}
+
+#[tauri::command]
+pub async fn open_excalidraw_library(
+    app: AppHandle,
+) -> Result<Option<OpenFileResponse>, CommandError> {
+    let Some(path) =
+        dialogs::pick_open_path(&app, "Excalidraw Library", LIBRARY_EXTENSIONS).await?
+    else {
+        return Ok(None);
+    };
+    log::info!("open_excalidraw_library: {}", path.display());
+    open(&app, &path).await.map(Some)
+}
+
+#[tauri::command]
+pub async fn load_excalidraw_library_path(
+    app: AppHandle,
+    path: String,
+) -> Result<OpenFileResponse, CommandError> {
+    log::info!("load_excalidraw_library_path: {}", path);
+    open(&app, &paths::normalize(Path::new(&path))).await
+}
+
+/// Saves a library like `save_excalidraw_file` saves a scene: asks where when there
+/// is no `path` or for Save As, and refuses to overwrite changes made on disk since
+/// `expected_mtime`. Libraries are not kept in local history.
+#[tauri::command]
+pub async fn save_excalidraw_library(
+    app: AppHandle,
+    request: SaveFileRequest,
+) -> Result<SaveFileResponse, CommandError> {
+    validate(&request.contents)?;
+    let current = request.path.as_deref().map(PathBuf::from);
+    let (directory, current_name) = current.as_deref().map(save_as_start).unwrap_or_default();
+    let path = match current.clone().filter(|_| !request.always_prompt) {
+        Some(path) => path,
+        None => {
+            let name = dialogs::suggested_file_name(
+                request.name.as_deref().or(current_name.as_deref()),
+                "library",
+                LIBRARY_EXTENSIONS,
+            );
+            dialogs::pick_save_path_in(
+                &app,
+                "Excalidraw Library",
+                LIBRARY_EXTENSIONS,
+                name,
+                directory,
+            )
+            .await?
+            .ok_or(CommandError::Cancelled)?
+        }
+    };
+    let saved_as = current.is_some_and(|current| current != path);
+    let compress = request
+        .compress
+        .unwrap_or_else(|| gzip::has_gz_suffix(&path) || (!saved_as && gzip::is_gzip_file(&path)));
+
+    read_only::ensure_writable(&app, &path)?;
+    if !saved_as {
+        check_conflict(&path, request.expected_mtime, request.force)?;
+    }
+    keep_backup(&path, request.keep_backup)?;
+    if compress {
+        let bytes = gzip::compress(request.contents.as_bytes())
+            .map_err(|error| CommandError::from_io(error, &path))?;
+        crate::file_watch::expect_write(&app, &path, &bytes);
+        write_binary_file(&path, &bytes)?;
+    } else {
+        crate::file_watch::expect_write(&app, &path, request.contents.as_bytes());
+        write_file(&path, &request.contents)?;
+    }
+    log::info!("save_excalidraw_library: {}", path.display());
+    let name = request
+        .name
+        .filter(|_| !saved_as)
+        .or_else(|| file_name(&path));
+    let path_string = path.to_string_lossy().to_string();
+    update_recents(&app, LIBRARY_KIND, &path_string, name.clone());
+    Ok(SaveFileResponse {
+        path: path_string,
+        name,
+        mtime: modified_millis(&path),
+        open_in_other_windows: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libraries_need_the_type_and_an_items_array() {
+        assert!(validate(r#"{"type":"excalidrawlib","version":2,"libraryItems":[]}"#).is_ok());
+        for contents in [
+            "not json",
+            r#"{"type":"excalidraw","elements":[]}"#,
+            r#"{"type":"excalidrawlib","library":[]}"#,
+            r#"{"type":"excalidrawlib","libraryItems":{}}"#,
+        ] {
+            assert_eq!(
+                validate(contents).unwrap_err().code(),
+                "invalid_document",
+                "{contents}"
+            );
+        }
+    }
+}
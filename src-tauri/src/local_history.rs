@@ -0,0 +1,563 @@
+use crate::error::CommandError;
+use crate::trash::{move_to_trash, TrashKind};
+use crate::{
+    app_data_dir, content_hash, file_name, now_epoch_millis, paths, update_recents,
+    OpenFileResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Saves that can be undone per file; older captures are dropped with their snapshots.
+const MAX_UNDO_DEPTH: usize = 20;
+
+/// Earlier versions kept per file unless the settings say otherwise.
+pub const DEFAULT_VERSIONS_LIMIT: usize = 50;
+
+/// Versions are `<timestamp>.snapshot`, next to the undo stack's `.snap` files.
+const VERSION_EXTENSION: &str = "snapshot";
+
+/// A captured copy of a file's bytes, plus the hash the file must still have for the
+/// capture to be restored over it.
+#[derive(Serialize, Deserialize, Clone)]
+struct SaveCapture {
+    snapshot: String,
+    expected_hash: String,
+    created_at: u128,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SaveStack {
+    undo: Vec<SaveCapture>,
+    redo: Vec<SaveCapture>,
+}
+
+/// Pre-save bytes taken by `capture_before_save`, waiting for the save to finish.
+pub struct PendingCapture {
+    snapshot: String,
+    hash: String,
+}
+
+/// An earlier version of a file, as it was before a save replaced it.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FileVersion {
+    /// Epoch milliseconds of the save that replaced it; names the version.
+    timestamp: u64,
+    size: u64,
+}
+
+fn path_hash(path: &Path) -> String {
+    content_hash(path.to_string_lossy().as_bytes())[..16].to_string()
+}
+
+/// Name of the folder under `history/` holding `path`'s snapshots. The folder part
+/// of the path is normalized first, so the same file reached through a symlink, as a
+/// relative path or with another drive-letter case shares one history, and the key
+/// doesn't change when the file itself comes into existence.
+pub fn history_key(path: &Path) -> String {
+    let normalized = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            paths::normalize(parent).join(name)
+        }
+        _ => paths::normalize(path),
+    };
+    path_hash(&normalized)
+}
+
+/// Snapshots for one document live under `history/<hash of its path>/`. A history
+/// recorded under the path exactly as it was given, before keys were normalized, is
+/// moved over the first time it is looked up.
+fn history_dir(app: &AppHandle, path: &Path) -> PathBuf {
+    let root = app_data_dir(app).join("history");
+    let dir = root.join(history_key(path));
+    let legacy = root.join(path_hash(path));
+    if legacy != dir && legacy.is_dir() && !dir.exists() {
+        if let Err(error) = fs::rename(&legacy, &dir) {
+            log::warn!(
+                "history_dir: failed to move {}: {}",
+                legacy.display(),
+                error
+            );
+        }
+    }
+    dir
+}
+
+fn load_stack(app: &AppHandle, path: &Path) -> SaveStack {
+    read_stack(&history_dir(app, path))
+}
+
+fn read_stack(dir: &Path) -> SaveStack {
+    fs::read_to_string(dir.join("saves.json"))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Moves a snapshot that is no longer reachable through undo or redo to the trash.
+fn discard_snapshot(app: &AppHandle, path: &Path, capture: SaveCapture) {
+    let snapshot = history_dir(app, path).join(capture.snapshot);
+    let label = path.to_string_lossy();
+    if let Err(error) = move_to_trash(
+        app,
+        TrashKind::HistorySnapshot,
+        &label,
+        Some(path),
+        &[snapshot],
+    ) {
+        log::warn!("discard_snapshot: failed for {}: {}", path.display(), error);
+    }
+}
+
+fn save_stack(app: &AppHandle, path: &Path, stack: &mut SaveStack) -> Result<(), CommandError> {
+    let dir = history_dir(app, path);
+    while stack.undo.len() > MAX_UNDO_DEPTH {
+        let dropped = stack.undo.remove(0);
+        discard_snapshot(app, path, dropped);
+    }
+    write_stack(&dir, stack)
+}
+
+fn write_stack(dir: &Path, stack: &SaveStack) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(stack).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("saves.json"), contents)?;
+    Ok(())
+}
+
+/// Hash of the file as Excalibur last wrote it, from the newest capture on either
+/// side of its stack.
+pub fn last_saved_hash(app: &AppHandle, path: &Path) -> Option<String> {
+    let stack = load_stack(app, path);
+    stack
+        .undo
+        .last()
+        .into_iter()
+        .chain(stack.redo.last())
+        .max_by_key(|capture| capture.created_at)
+        .map(|capture| capture.expected_hash.clone())
+}
+
+/// Carries a file's save history over when it is found at a new path. Histories
+/// already recorded for the new path are kept.
+pub fn move_history(app: &AppHandle, old: &Path, new: &Path) {
+    let from = history_dir(app, old);
+    let to = history_dir(app, new);
+    if from.exists() && !to.exists() {
+        if let Err(error) = fs::rename(&from, &to) {
+            log::warn!("move_history: failed for {}: {}", old.display(), error);
+        }
+    }
+}
+
+/// Copies `bytes` into the document's history folder and returns the snapshot name.
+fn write_snapshot(app: &AppHandle, path: &Path, bytes: &[u8]) -> Result<String, CommandError> {
+    write_snapshot_in(&history_dir(app, path), bytes)
+}
+
+fn write_snapshot_in(dir: &Path, bytes: &[u8]) -> Result<String, CommandError> {
+    fs::create_dir_all(dir)?;
+    let mut millis = now_epoch_millis();
+    while dir.join(format!("{millis}.snap")).exists() {
+        millis += 1;
+    }
+    let name = format!("{millis}.snap");
+    fs::write(dir.join(&name), bytes)?;
+    Ok(name)
+}
+
+fn current_hash(path: &Path) -> Result<String, CommandError> {
+    Ok(content_hash(&fs::read(path)?))
+}
+
+fn version_name(timestamp: u64) -> String {
+    format!("{timestamp}.{VERSION_EXTENSION}")
+}
+
+/// The versions in a history folder, newest first.
+fn versions_in(dir: &Path) -> Vec<FileVersion> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<FileVersion> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != VERSION_EXTENSION {
+                return None;
+            }
+            Some(FileVersion {
+                timestamp: path.file_stem()?.to_str()?.parse().ok()?,
+                size: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect();
+    versions.sort_by_key(|version| std::cmp::Reverse(version.timestamp));
+    versions
+}
+
+/// Keeps the undo snapshot `snapshot` as a version too, then prunes the oldest
+/// versions beyond `limit`. Versions share the snapshot's bytes where the file
+/// system allows a hard link.
+fn add_version(dir: &Path, snapshot: &Path, limit: usize) -> io::Result<u64> {
+    let mut timestamp = now_epoch_millis() as u64;
+    while dir.join(version_name(timestamp)).exists() {
+        timestamp += 1;
+    }
+    let version = dir.join(version_name(timestamp));
+    if fs::hard_link(snapshot, &version).is_err() {
+        fs::copy(snapshot, &version)?;
+    }
+    for pruned in versions_in(dir).iter().skip(limit.max(1)) {
+        fs::remove_file(dir.join(version_name(pruned.timestamp)))?;
+    }
+    Ok(timestamp)
+}
+
+/// Snapshots a file's current bytes before it is overwritten by a save. Returns
+/// `None` for new files, which have nothing to go back to.
+pub fn capture_before_save(app: &AppHandle, path: &Path) -> Option<PendingCapture> {
+    let bytes = fs::read(path).ok()?;
+    let hash = content_hash(&bytes);
+    match write_snapshot(app, path, &bytes) {
+        Ok(snapshot) => Some(PendingCapture { snapshot, hash }),
+        Err(error) => {
+            log::warn!(
+                "capture_before_save: failed for {}: {}",
+                path.display(),
+                error
+            );
+            None
+        }
+    }
+}
+
+/// Records a finished save so `undo_last_save` can restore the captured bytes, and
+/// keeps them as a version unless the save left the file unchanged. A new save clears
+/// anything that could be redone.
+pub fn finish_save(app: &AppHandle, path: &Path, capture: Option<PendingCapture>) {
+    let Some(capture) = capture else {
+        return;
+    };
+    let Ok(expected_hash) = current_hash(path) else {
+        return;
+    };
+    if expected_hash != capture.hash {
+        let dir = history_dir(app, path);
+        let limit = crate::settings::load_settings(app).history_versions_limit;
+        if let Err(error) = add_version(&dir, &dir.join(&capture.snapshot), limit) {
+            log::warn!(
+                "finish_save: failed to keep a version of {}: {}",
+                path.display(),
+                error
+            );
+        }
+    }
+    let mut stack = load_stack(app, path);
+    for dropped in std::mem::take(&mut stack.redo) {
+        discard_snapshot(app, path, dropped);
+    }
+    stack.undo.push(SaveCapture {
+        snapshot: capture.snapshot,
+        expected_hash,
+        created_at: now_epoch_millis(),
+    });
+    if let Err(error) = save_stack(app, path, &mut stack) {
+        log::warn!("finish_save: failed for {}: {}", path.display(), error);
+    }
+}
+
+/// Pops a capture from one side of the stack in `dir`, hands its bytes to `write` to
+/// put back at `path`, and pushes the replaced contents onto the other side.
+fn swap_capture(
+    dir: &Path,
+    path: &Path,
+    undo: bool,
+    write: impl FnOnce(&[u8]) -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    let mut stack = read_stack(dir);
+    let nothing = || CommandError::NothingToRestore {
+        path: path.to_string_lossy().to_string(),
+        action: if undo { "undo" } else { "redo" }.to_string(),
+    };
+    let capture = if undo {
+        stack.undo.last().cloned()
+    } else {
+        stack.redo.last().cloned()
+    }
+    .ok_or_else(nothing)?;
+
+    let restored = fs::read(dir.join(&capture.snapshot)).map_err(|_| nothing())?;
+    let current = fs::read(path)?;
+    if content_hash(&current) != capture.expected_hash {
+        return Err(CommandError::ChangedExternally {
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+
+    let replaced = SaveCapture {
+        snapshot: write_snapshot_in(dir, &current)?,
+        expected_hash: content_hash(&restored),
+        created_at: now_epoch_millis(),
+    };
+    write(&restored)?;
+    if undo {
+        stack.undo.pop();
+        stack.redo.push(replaced);
+    } else {
+        stack.redo.pop();
+        stack.undo.push(replaced);
+    }
+    let _ = fs::remove_file(dir.join(&capture.snapshot));
+    // Moving captures between the sides never grows the stack past `MAX_UNDO_DEPTH`.
+    write_stack(dir, &stack)
+}
+
+/// Undoes or redoes the last save of `path`; see `swap_capture`.
+async fn restore_capture(
+    app: &AppHandle,
+    kind: &str,
+    path: &Path,
+    undo: bool,
+) -> Result<OpenFileResponse, CommandError> {
+    if !matches!(kind, "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind {
+            kind: kind.to_string(),
+        });
+    }
+    if crate::volumes::is_disconnected(app, path) {
+        return Err(crate::volumes::disconnected_error(path));
+    }
+    crate::read_only::ensure_writable(app, path)?;
+    swap_capture(&history_dir(app, path), path, undo, |restored| {
+        crate::file_watch::expect_write(app, path, restored);
+        crate::atomic_write::write_atomic(path, restored)?;
+        crate::checksums::after_save(app, path);
+        Ok(())
+    })?;
+
+    let (contents, encrypted) = crate::crypto::read_document(app, path, None).await?;
+    let path_string = path.to_string_lossy().to_string();
+    let name = file_name(path);
+    update_recents(app, kind, &path_string, name.clone());
+    Ok(OpenFileResponse {
+        mtime: crate::modified_millis(path),
+        path: path_string,
+        name,
+        contents,
+        encrypted,
+        ..Default::default()
+    })
+}
+
+/// Restores the contents a file had before its most recent save. The contents being
+/// replaced are captured first, so `redo_last_save` can put them back.
+///
+/// Refuses when there is no save to undo, or when the file was changed by something
+/// else since that save.
+#[tauri::command]
+pub async fn undo_last_save(
+    app: AppHandle,
+    kind: String,
+    path: String,
+) -> Result<OpenFileResponse, CommandError> {
+    restore_capture(&app, &kind, Path::new(&path), true).await
+}
+
+/// Re-applies the save most recently reverted by `undo_last_save`.
+#[tauri::command]
+pub async fn redo_last_save(
+    app: AppHandle,
+    kind: String,
+    path: String,
+) -> Result<OpenFileResponse, CommandError> {
+    restore_capture(&app, &kind, Path::new(&path), false).await
+}
+
+fn version_path(app: &AppHandle, path: &Path, timestamp: u64) -> Result<PathBuf, CommandError> {
+    let version = history_dir(app, path).join(version_name(timestamp));
+    if !version.is_file() {
+        return Err(CommandError::VersionNotFound {
+            path: path.to_string_lossy().to_string(),
+            timestamp,
+        });
+    }
+    Ok(version)
+}
+
+/// The earlier versions of the file at `path` kept by local history, newest first.
+#[tauri::command]
+pub fn list_versions(app: AppHandle, path: String) -> Vec<FileVersion> {
+    versions_in(&history_dir(&app, Path::new(&path)))
+}
+
+/// The contents of the file at `path` as they were in version `timestamp`. A scene
+/// kept as a PNG comes back as its embedded scene; an encrypted version asks for its
+/// password like opening the file does.
+#[tauri::command]
+pub async fn read_version(
+    app: AppHandle,
+    path: String,
+    timestamp: u64,
+    password: Option<String>,
+) -> Result<String, CommandError> {
+    let version = version_path(&app, Path::new(&path), timestamp)?;
+    if crate::png_scene::is_png_file(&version) {
+        let bytes = fs::read(&version)?;
+        return crate::png_scene::extract_scene(&version, &bytes);
+    }
+    let (contents, _) = crate::crypto::read_document(&app, &version, password).await?;
+    Ok(contents)
+}
+
+/// Writes version `timestamp` back over the file at `path`. The contents being
+/// replaced are kept as a new version first, and the restore can be undone like a
+/// save.
+#[tauri::command]
+pub async fn restore_version(
+    app: AppHandle,
+    path: String,
+    timestamp: u64,
+) -> Result<OpenFileResponse, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    if crate::volumes::is_disconnected(&app, &path_buf) {
+        return Err(crate::volumes::disconnected_error(&path_buf));
+    }
+    crate::read_only::ensure_writable(&app, &path_buf)?;
+    let restored = fs::read(version_path(&app, &path_buf, timestamp)?)?;
+
+    let capture = capture_before_save(&app, &path_buf);
+    crate::file_watch::expect_write(&app, &path_buf, &restored);
+    crate::atomic_write::write_atomic(&path_buf, &restored)?;
+    finish_save(&app, &path_buf, capture);
+    crate::checksums::after_save(&app, &path_buf);
+    log::info!("restore_version: {} from {}", path, timestamp);
+
+    let (contents, encrypted) = crate::crypto::read_document(&app, &path_buf, None).await?;
+    Ok(OpenFileResponse {
+        name: file_name(&path_buf),
+        mtime: crate::modified_millis(&path_buf),
+        path,
+        contents,
+        encrypted,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_listed_newest_first_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("excalibur-versions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let snapshot = dir.join("1.snap");
+        fs::write(&snapshot, "graph TD").unwrap();
+        fs::write(dir.join("saves.json"), "{}").unwrap();
+
+        let timestamps: Vec<u64> = (0..4)
+            .map(|_| add_version(&dir, &snapshot, 3).unwrap())
+            .collect();
+        let versions = versions_in(&dir);
+        let listed: Vec<u64> = versions.iter().map(|version| version.timestamp).collect();
+        assert_eq!(listed, [timestamps[3], timestamps[2], timestamps[1]]);
+        assert!(versions.iter().all(|version| version.size == 8));
+        assert!(!dir.join(version_name(timestamps[0])).exists());
+
+        // Versions outlive the undo snapshot they were taken from.
+        fs::remove_file(&snapshot).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(version_name(timestamps[3]))).unwrap(),
+            "graph TD"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn history_keys_ignore_how_the_folder_is_spelled() {
+        let dir = std::env::temp_dir().join(format!("excalibur-key-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let direct = dir.join("flow.mmd");
+        let roundabout = dir.join("sub").join("..").join("flow.mmd");
+        assert_eq!(history_key(&direct), history_key(&roundabout));
+        fs::write(&direct, "graph TD").unwrap();
+        assert_eq!(history_key(&direct), history_key(&roundabout));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// A history in `dir` as if `path` had just been saved over `before`.
+    fn saved_over(dir: &Path, path: &Path, before: &str, after: &str) {
+        let snapshot = write_snapshot_in(dir, before.as_bytes()).unwrap();
+        fs::write(path, after).unwrap();
+        let stack = SaveStack {
+            undo: vec![SaveCapture {
+                snapshot,
+                expected_hash: content_hash(after.as_bytes()),
+                created_at: now_epoch_millis(),
+            }],
+            redo: Vec::new(),
+        };
+        write_stack(dir, &stack).unwrap();
+    }
+
+    fn write_to(path: &Path) -> impl FnOnce(&[u8]) -> Result<(), CommandError> + '_ {
+        move |bytes| Ok(fs::write(path, bytes)?)
+    }
+
+    #[test]
+    fn undo_and_redo_swap_the_saved_contents() {
+        let root = std::env::temp_dir().join(format!("excalibur-undo-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("history");
+        fs::create_dir_all(&dir).unwrap();
+        let path = root.join("flow.mmd");
+        saved_over(&dir, &path, "graph TD", "graph LR");
+
+        swap_capture(&dir, &path, true, write_to(&path)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "graph TD");
+        let stack = read_stack(&dir);
+        assert!(stack.undo.is_empty());
+        assert_eq!(stack.redo.len(), 1);
+
+        swap_capture(&dir, &path, false, write_to(&path)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "graph LR");
+        let stack = read_stack(&dir);
+        assert_eq!(stack.undo.len(), 1);
+        assert!(stack.redo.is_empty());
+        // Only the snapshot the stack still points at is kept.
+        let snapshots = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("snap".as_ref()))
+            .count();
+        assert_eq!(snapshots, 1);
+
+        let error = swap_capture(&dir, &path, false, write_to(&path)).unwrap_err();
+        assert_eq!(error.code(), "nothing_to_restore");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn files_changed_since_the_save_are_not_restored_over() {
+        let root =
+            std::env::temp_dir().join(format!("excalibur-undo-changed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("history");
+        fs::create_dir_all(&dir).unwrap();
+        let path = root.join("flow.mmd");
+        saved_over(&dir, &path, "graph TD", "graph LR");
+        fs::write(&path, "graph BT").unwrap();
+
+        let error = swap_capture(&dir, &path, true, write_to(&path)).unwrap_err();
+        assert_eq!(error.code(), "changed_externally");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "graph BT");
+        assert_eq!(read_stack(&dir).undo.len(), 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
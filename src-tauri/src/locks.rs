@@ -0,0 +1,205 @@
+use crate::background::{wait, IdlePolicy};
+use crate::error::CommandError;
+use crate::{now_epoch, now_epoch_millis, write_file};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// How often held locks get a fresh heartbeat.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// Locks without a heartbeat for this long belong to a crashed or vanished instance.
+const STALE_AFTER_SECS: u64 = 2 * HEARTBEAT_INTERVAL.as_secs();
+
+/// Identifies this running process in lock files, plus the documents it holds locks for.
+pub struct DocumentLocks {
+    instance_id: String,
+    held: Mutex<HashSet<PathBuf>>,
+}
+
+impl Default for DocumentLocks {
+    fn default() -> Self {
+        let seed = format!("{}:{}", std::process::id(), now_epoch_millis());
+        DocumentLocks {
+            instance_id: crate::content_hash(seed.as_bytes())[..16].to_string(),
+            held: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+/// Contents of the `.<file name>.excalibur-lock` sidecar next to an open document.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LockInfo {
+    instance_id: String,
+    pid: u32,
+    host: Option<String>,
+    opened_at: u64,
+    heartbeat_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct LockResponse {
+    acquired: bool,
+    /// The other instance's lock when `acquired` is false.
+    held_by: Option<LockInfo>,
+}
+
+/// Lock files sit beside the document so instances on other machines sharing the
+/// drive see them too.
+fn lock_path(document: &Path) -> PathBuf {
+    let name = document
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    document.with_file_name(format!(".{name}.excalibur-lock"))
+}
+
+fn read_lock(document: &Path) -> Option<LockInfo> {
+    fs::read_to_string(lock_path(document))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+}
+
+fn host_name() -> Option<String> {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok())
+        .filter(|name| !name.is_empty())
+}
+
+fn is_live(lock: &LockInfo) -> bool {
+    now_epoch().saturating_sub(lock.heartbeat_at) < STALE_AFTER_SECS
+}
+
+fn write_lock(document: &Path, lock: &LockInfo) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(lock)?;
+    write_file(&lock_path(document), &contents)
+}
+
+/// Takes the lock for `document` unless another instance holds a live one, in which
+/// case that instance's lock is returned so the UI can offer read-only mode.
+/// Stale locks are reclaimed.
+pub fn acquire(app: &AppHandle, document: &Path) -> Option<LockInfo> {
+    let locks = app.state::<DocumentLocks>();
+    let existing = read_lock(document);
+    if let Some(existing) = &existing {
+        if existing.instance_id != locks.instance_id && is_live(existing) {
+            log::info!(
+                "acquire_lock: {} is locked by instance {}",
+                document.display(),
+                existing.instance_id
+            );
+            return Some(existing.clone());
+        }
+    }
+
+    let now = now_epoch();
+    let lock = LockInfo {
+        instance_id: locks.instance_id.clone(),
+        pid: std::process::id(),
+        host: host_name(),
+        opened_at: existing
+            .filter(|existing| existing.instance_id == locks.instance_id)
+            .map_or(now, |existing| existing.opened_at),
+        heartbeat_at: now,
+    };
+    if let Err(error) = write_lock(document, &lock) {
+        // Read-only folders can't hold a lock; opening still works.
+        log::warn!(
+            "acquire_lock: failed to write lock for {}: {}",
+            document.display(),
+            error
+        );
+        return None;
+    }
+    // Another instance may have written its lock at the same moment.
+    if let Some(winner) = read_lock(document) {
+        if winner.instance_id != locks.instance_id {
+            return Some(winner);
+        }
+    }
+    locks.held.lock().unwrap().insert(document.to_path_buf());
+    None
+}
+
+/// Documents this instance currently holds the lock for, i.e. has open.
+pub fn held_documents(app: &AppHandle) -> Vec<PathBuf> {
+    app.state::<DocumentLocks>()
+        .held
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Removes this instance's lock on `document`; locks held by others are left alone.
+pub fn release(app: &AppHandle, document: &Path) {
+    let locks = app.state::<DocumentLocks>();
+    locks.held.lock().unwrap().remove(document);
+    if read_lock(document).is_some_and(|lock| lock.instance_id == locks.instance_id) {
+        let _ = fs::remove_file(lock_path(document));
+    }
+}
+
+/// Best-effort release of every held lock, for app exit.
+pub fn release_all(app: &AppHandle) {
+    for document in held_documents(app) {
+        release(app, &document);
+    }
+}
+
+/// Refreshes the heartbeat of every held lock every `HEARTBEAT_INTERVAL`. Runs on its
+/// own thread for the life of the app.
+pub fn run_heartbeat(app: &AppHandle) {
+    loop {
+        // Kept on schedule while idle, or other instances would take the lock as stale.
+        wait(app, "lock-heartbeat", HEARTBEAT_INTERVAL, IdlePolicy::Keep);
+        let locks = app.state::<DocumentLocks>();
+        let held: Vec<PathBuf> = locks.held.lock().unwrap().iter().cloned().collect();
+        for document in held {
+            // Paused until the volume comes back; writing would only fail again.
+            if crate::volumes::is_disconnected(app, &document) {
+                continue;
+            }
+            match read_lock(&document) {
+                Some(mut lock) if lock.instance_id == locks.instance_id => {
+                    lock.heartbeat_at = now_epoch();
+                    if let Err(error) = write_lock(&document, &lock) {
+                        if crate::volumes::check_failure(app, &document) {
+                            continue;
+                        }
+                        log::warn!(
+                            "run_heartbeat: failed for {}: {}",
+                            document.display(),
+                            error
+                        );
+                    }
+                }
+                // The volume went away with the lock on it; keep it for when it returns.
+                None if crate::volumes::check_failure(app, &document) => {}
+                // Lost the lock (deleted, or reclaimed after we stalled); stop refreshing it.
+                _ => {
+                    locks.held.lock().unwrap().remove(&document);
+                }
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn acquire_document_lock(app: AppHandle, path: String) -> LockResponse {
+    let held_by = acquire(&app, Path::new(&path));
+    LockResponse {
+        acquired: held_by.is_none(),
+        held_by,
+    }
+}
+
+#[tauri::command]
+pub fn release_document_lock(app: AppHandle, path: String) {
+    release(&app, Path::new(&path));
+}
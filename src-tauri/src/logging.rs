@@ -0,0 +1,255 @@
+use crate::error::CommandError;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+pub const LOG_FILE: &str = "excalibur.log";
+/// Size at which `excalibur.log` is rotated to `excalibur.log.1`.
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+/// Log files kept, the current one included; the oldest is dropped on rotation.
+const KEPT_FILES: usize = 3;
+/// Most lines `get_recent_logs` returns, however many are asked for.
+const MAX_RECENT_LINES: usize = 10_000;
+/// Records from our own modules have targets starting with this.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Where log lines go once the data folder is known; until then only stderr.
+struct Output {
+    dir: Option<PathBuf>,
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+}
+
+struct Logger {
+    output: Mutex<Output>,
+}
+
+static LOGGER: Logger = Logger {
+    output: Mutex::new(Output {
+        dir: None,
+        file: None,
+        size: 0,
+        max_bytes: MAX_LOG_BYTES,
+    }),
+};
+
+fn max_level() -> LevelFilter {
+    if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    }
+}
+
+/// Installs the logger. Called first in `main`, so startup messages reach stderr
+/// even before the log file can be opened.
+pub fn install() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(max_level());
+    }
+}
+
+fn log_dir(app: &AppHandle) -> PathBuf {
+    crate::app_data_dir(app).join("logs")
+}
+
+/// Starts writing to `logs/excalibur.log` in the data folder, once it is resolved.
+pub fn start_file(app: &AppHandle) {
+    let mut output = LOGGER
+        .output
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    output.dir = Some(log_dir(app));
+    output.file = None;
+}
+
+/// `excalibur.log` for the current file, `excalibur.log.<n>` for the older ones.
+fn log_file_name(generation: usize) -> String {
+    match generation {
+        0 => LOG_FILE.to_string(),
+        generation => format!("{LOG_FILE}.{generation}"),
+    }
+}
+
+/// Shifts every log file one generation older, dropping the oldest.
+fn rotate(dir: &Path) -> io::Result<()> {
+    for generation in (1..KEPT_FILES).rev() {
+        match fs::rename(
+            dir.join(log_file_name(generation - 1)),
+            dir.join(log_file_name(generation)),
+        ) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl Output {
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        if self.file.is_some() && self.size + line.len() as u64 > self.max_bytes {
+            self.file = None;
+            rotate(dir)?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                fs::create_dir_all(dir)?;
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(dir.join(LOG_FILE))?;
+                self.size = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+        file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// `2026-03-01 14:02:11.348 INFO  library: save_excalidraw_library: /work/a.excalidrawlib`
+fn format_line(time: &str, level: Level, target: &str, message: &str) -> String {
+    let module = target
+        .strip_prefix(CRATE_TARGET)
+        .map(|rest| rest.trim_start_matches("::"))
+        .unwrap_or(target);
+    let module = if module.is_empty() { "main" } else { module };
+    format!("{time} {level:<5} {module}: {message}\n")
+}
+
+impl Log for Logger {
+    /// Everything of ours down to the build's level; only warnings from libraries.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if metadata.target().starts_with(CRATE_TARGET) {
+            metadata.level() <= max_level()
+        } else {
+            metadata.level() <= Level::Warn
+        }
+    }
+
+    /// Failing to write a line is ignored: logging must never fail a command.
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time = chrono::Local::now()
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string();
+        let line = format_line(
+            &time,
+            record.level(),
+            record.target(),
+            &record.args().to_string(),
+        );
+        let mut output = self
+            .output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if cfg!(debug_assertions) || output.dir.is_none() {
+            let _ = io::stderr().write_all(line.as_bytes());
+        }
+        if output.write(&line).is_err() {
+            // Try a fresh file next time, e.g. after the folder was removed.
+            output.file = None;
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The last `lines` lines across the current and rotated log files, oldest first.
+fn recent_lines(dir: &Path, lines: usize) -> Vec<String> {
+    let mut recent = Vec::new();
+    for generation in 0..KEPT_FILES {
+        if recent.len() >= lines {
+            break;
+        }
+        let Ok(contents) = fs::read(dir.join(log_file_name(generation))) else {
+            continue;
+        };
+        let contents = String::from_utf8_lossy(&contents);
+        let older: Vec<String> = contents
+            .lines()
+            .rev()
+            .take(lines - recent.len())
+            .map(str::to_string)
+            .collect();
+        recent.extend(older);
+    }
+    recent.reverse();
+    recent
+}
+
+/// The newest `lines` log lines, for pasting into a bug report.
+#[tauri::command]
+pub fn get_recent_logs(app: AppHandle, lines: usize) -> Result<String, CommandError> {
+    let lines = lines.min(MAX_RECENT_LINES);
+    Ok(recent_lines(&log_dir(&app), lines).join("\n"))
+}
+
+/// Where the current log file is, whether or not anything was logged yet.
+#[tauri::command]
+pub fn get_log_path(app: AppHandle) -> String {
+    log_dir(&app).join(LOG_FILE).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_carry_time_level_and_module() {
+        let target = format!("{CRATE_TARGET}::library");
+        assert_eq!(
+            format_line(
+                "2026-03-01 14:02:11.348",
+                Level::Warn,
+                &target,
+                "save failed"
+            ),
+            "2026-03-01 14:02:11.348 WARN  library: save failed\n"
+        );
+        assert_eq!(
+            format_line("t", Level::Info, CRATE_TARGET, "started"),
+            "t INFO  main: started\n"
+        );
+        assert_eq!(
+            format_line("t", Level::Error, "reqwest::connect", "reset"),
+            "t ERROR reqwest::connect: reset\n"
+        );
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_the_newest_lines() {
+        let dir = std::env::temp_dir().join(format!("excalibur-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut output = Output {
+            dir: Some(dir.clone()),
+            file: None,
+            size: 0,
+            max_bytes: 20,
+        };
+        for index in 0..8 {
+            output.write(&format!("line {index}\n")).unwrap();
+        }
+
+        // Three 7-byte lines don't fit in 20 bytes, so every file holds two.
+        assert!(!dir.join(log_file_name(KEPT_FILES)).exists());
+        assert_eq!(
+            fs::read_to_string(dir.join(LOG_FILE)).unwrap(),
+            "line 6\nline 7\n"
+        );
+        assert_eq!(recent_lines(&dir, 3), vec!["line 5", "line 6", "line 7"]);
+        assert_eq!(recent_lines(&dir, 100).len(), 6);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,16 +1,102 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{async_runtime::channel, AppHandle, Emitter, Manager};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 use tauri_plugin_deep_link::DeepLinkExt;
-use tauri_plugin_dialog::DialogExt;
 
-/// Holds the file path from startup (e.g. double-click in Finder) until the frontend is ready.
-struct PendingFile(Mutex<Option<String>>);
+mod actions;
+mod app_info;
+mod atomic_write;
+mod autosave;
+mod background;
+mod backups;
+mod builtin_templates;
+mod checksums;
+mod cli;
+mod close_guard;
+mod crypto;
+mod data_transfer;
+mod deep_links;
+mod detect;
+mod dialogs;
+mod display_path;
+mod drafts;
+mod drawio;
+mod duplicate_file;
+mod duplicates;
+mod error;
+mod event_queue;
+mod exports;
+mod favorites;
+mod file_associations;
+mod file_backups;
+mod file_info;
+mod file_watch;
+mod gzip;
+mod http;
+mod large_files;
+mod launch_args;
+mod library;
+mod local_history;
+mod locks;
+mod logging;
+mod markdown_blocks;
+mod metrics;
+mod open_documents;
+mod open_files;
+mod operations;
+mod path_completion;
+mod paths;
+mod png_scene;
+mod privacy;
+mod read_only;
+mod recent_menu;
+mod recent_pins;
+mod recent_previews;
+mod recents_store;
+mod recents_watch;
+mod relocate;
+mod remote;
+mod rename;
+mod render;
+mod replace;
+mod reveal;
+mod roots;
+mod scan;
+mod scene_schema;
+mod search;
+mod search_history;
+mod search_index;
+mod session;
+mod settings;
+mod single_instance;
+mod storage;
+mod system_trash;
+mod template_vars;
+mod templates;
+#[cfg(test)]
+mod test_support;
+mod text_encoding;
+mod trash;
+mod untitled;
+mod updates;
+mod volumes;
+mod windows;
+mod workspace_report;
+mod workspaces;
+
+/// `gz` is for gzipped documents such as `flow.excalidraw.gz`.
+const EXCALIDRAW_EXTENSIONS: &[&str] = &["excalidraw", "json", "gz"];
+/// What the open dialog offers for Excalidraw: also images with an embedded scene.
+const EXCALIDRAW_OPEN_EXTENSIONS: &[&str] = &["excalidraw", "json", "png", "gz"];
+const MERMAID_EXTENSIONS: &[&str] = &["mmd", "mermaid", "md", "txt", "gz"];
+/// How long `prune_recents` waits for a path on a slow or unresponsive drive.
+const PRUNE_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[derive(Serialize, Deserialize, Clone)]
 struct RecentItem {
@@ -18,18 +104,131 @@ struct RecentItem {
     path: String,
     name: Option<String>,
     updated_at: u64,
+    /// The same path relative to a registered root, preferred over `path` when
+    /// that root is still registered so moved or re-cloned folders keep working.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root: Option<roots::RootRelative>,
+    /// File size and mtime when the item was last opened or saved, to spot outside changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recorded_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recorded_modified_at: Option<u64>,
+    /// Password-protected file; nothing derived from its contents is cached.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    encrypted: bool,
+    /// Kept however many other files are opened; see `recent_pins`.
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// Whether a listed file is still there, so the start screen can flag stale entries.
+#[derive(Serialize, Default, Clone)]
+struct FileStatus {
+    exists: bool,
+    size: Option<u64>,
+    modified_at: Option<u64>,
 }
 
 #[derive(Serialize)]
+struct RecentEntry {
+    #[serde(flatten)]
+    item: RecentItem,
+    #[serde(flatten)]
+    status: FileStatus,
+    /// `path` shortened for display, e.g. `~/projects/flow.mmd`.
+    display_path: String,
+    /// The diagram declaration and title for mermaid, or the document or first frame
+    /// name for excalidraw, e.g. "sequenceDiagram — Checkout flow".
+    preview: Option<String>,
+}
+
+#[derive(Serialize, Default)]
 struct OpenFileResponse {
     path: String,
     name: Option<String>,
     contents: String,
+    /// Elements the frontend should scroll/zoom to after opening, echoed from the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    focus_element_ids: Option<Vec<String>>,
+    /// Another instance's lock on the file, if it has the file open; the UI should
+    /// offer read-only mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked_by: Option<locks::LockInfo>,
+    /// Opened from an encrypted container; saving to the same path re-encrypts.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    encrypted: bool,
+    /// The scene came from a PNG image (`.excalidraw.png`); saves to the same path
+    /// must send the rendered image as `png_base64`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    embedded_in_png: bool,
+    /// Whether an excalidraw file has the shape of a scene; see
+    /// `scene_schema::validate_excalidraw`. Invalid files still open, so they can be
+    /// rescued, with the problems in `warnings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// The file's encoding when it isn't plain UTF-8, e.g. "UTF-16LE"; saving
+    /// rewrites it as UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+    /// For Markdown opened as Mermaid: the lines of the block `contents` is, to send
+    /// back as `block_range` when saving.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_range: Option<markdown_blocks::BlockRange>,
+    /// For Markdown with several mermaid blocks, each of them to pick from.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<markdown_blocks::MermaidBlock>,
+    /// Result of checking the `.sha256` sidecar: "ok", "mismatch" or "missing".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<&'static str>,
+    /// Scene version details for excalidraw files; `newer` means saving may drop data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<scene_schema::SceneSchema>,
+    /// Opened read-only, on request or because the file can't be written; saves are
+    /// refused until `make_writable`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    read_only: bool,
+    /// Whether the file's folder takes new files, which saving needs (see
+    /// `read_only::dir_writable`); when it doesn't, only Save As can work. Set when a
+    /// file is opened.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    writable_dir: Option<bool>,
+    /// Size and times of the file when it was opened; `stat_file` refreshes them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<file_info::FileMeta>,
+    /// Over the `large_file_threshold_bytes` setting; such files open slowly.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    large_file: bool,
+    /// The file is gzipped; saving it in place keeps it that way.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    compressed: bool,
+    /// Fetched from the URL in `path`; there is no file to save to, only Save As.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    remote: bool,
+    /// Modification time in epoch milliseconds, sent back as `expected_mtime` when
+    /// saving so changes made on disk in the meantime aren't overwritten.
+    mtime: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct OpenPathResponse {
+    kind: &'static str,
+    #[serde(flatten)]
+    file: OpenFileResponse,
 }
 
 #[derive(Serialize)]
 struct SaveFileResponse {
     path: String,
+    /// The name the document was saved under, which changes with Save As.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Modification time after the save, for the next save's `expected_mtime`.
+    mtime: Option<u64>,
+    /// Other windows that have the file open too; their copies are now out of date.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    open_in_other_windows: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -37,6 +236,44 @@ struct SaveFileRequest {
     path: Option<String>,
     name: Option<String>,
     contents: String,
+    /// The user agreed to overwrite a scene written by a newer Excalidraw.
+    #[serde(default)]
+    acknowledge_downgrade: bool,
+    /// Set on the first save of a document from `new_*_file`.
+    #[serde(default)]
+    untitled_id: Option<String>,
+    /// Keep the replaced contents as `.bak1` next to the file; on unless `false`.
+    #[serde(default)]
+    keep_backup: Option<bool>,
+    /// `mtime` from when the document was opened or last saved; the save is refused
+    /// with a `conflict` error when the file was modified since.
+    #[serde(default)]
+    expected_mtime: Option<u64>,
+    /// Overwrite even when the file changed since `expected_mtime`.
+    #[serde(default)]
+    force: bool,
+    /// The scene rendered as PNG, base64-encoded; set, `contents` is embedded in the
+    /// image and the image is written instead of a scene file. Excalidraw only.
+    #[serde(default)]
+    png_base64: Option<String>,
+    /// The mermaid block of a Markdown file being edited, from `OpenFileResponse`;
+    /// set, `contents` replaces that block and the rest of the file is kept.
+    #[serde(default)]
+    block_range: Option<markdown_blocks::BlockRange>,
+    /// Gzip the saved file. Unset, files with a `.gz` suffix are gzipped, and so is a
+    /// gzipped file saved in place.
+    #[serde(default)]
+    compress: Option<bool>,
+    /// Save As: always ask where to save, starting from `path`, which is left as it
+    /// was when another location is picked.
+    #[serde(default)]
+    always_prompt: bool,
+}
+
+/// Where the save dialog starts for a document known to be at `path`: its folder,
+/// and its name unless the request gives one.
+fn save_as_start(path: &Path) -> (Option<PathBuf>, Option<String>) {
+    (path.parent().map(Path::to_path_buf), file_name(path))
 }
 
 fn now_epoch() -> u64 {
@@ -46,72 +283,170 @@ fn now_epoch() -> u64 {
         .as_secs()
 }
 
+fn now_epoch_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn file_status(path: &Path) -> FileStatus {
+    let Ok(metadata) = fs::metadata(path) else {
+        return FileStatus::default();
+    };
+    FileStatus {
+        exists: true,
+        size: Some(metadata.len()),
+        modified_at: metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs()),
+    }
+}
+
+/// Modification time of `path` in epoch milliseconds; seconds are too coarse to tell
+/// a save from an edit made right after it.
+fn modified_millis(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+}
+
+/// Hex-encoded SHA-256 of `bytes`, used to compare file contents cheaply.
+fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
 fn app_data_dir(app: &AppHandle) -> PathBuf {
-    app.path()
-        .app_data_dir()
-        .unwrap_or_else(|_| PathBuf::from("."))
+    paths::data_dir(app)
 }
 
 fn recents_path(app: &AppHandle) -> PathBuf {
     app_data_dir(app).join("recents.json")
 }
 
+/// Points items with a root-relative form at where that root is registered now.
+fn resolve_recent_roots(app: &AppHandle, recents: &mut [RecentItem]) {
+    let registered = roots::load_roots(app);
+    for item in recents {
+        if let Some(resolved) = item
+            .root
+            .as_ref()
+            .and_then(|relative| roots::resolve_relative(&registered, relative))
+        {
+            item.path = resolved.to_string_lossy().to_string();
+        }
+    }
+}
+
+/// A copy of the recents; see `recents_store`.
 fn load_recents(app: &AppHandle) -> Vec<RecentItem> {
-    let path = recents_path(app);
-    let Ok(contents) = fs::read_to_string(path) else {
-        return Vec::new();
-    };
-    serde_json::from_str(&contents).unwrap_or_default()
+    let mut recents = app.state::<recents_store::RecentsStore>().snapshot();
+    resolve_recent_roots(app, &mut recents);
+    recents
+}
+
+/// Changes the recents in place and saves them. Read-modify-write goes through here
+/// rather than `load_recents`, so concurrent changes can't drop each other's entries.
+/// The Open Recent menu is rebuilt afterwards.
+fn modify_recents<R>(app: &AppHandle, change: impl FnOnce(&mut Vec<RecentItem>) -> R) -> R {
+    let result = app
+        .state::<recents_store::RecentsStore>()
+        .update(|recents| {
+            resolve_recent_roots(app, recents);
+            change(recents)
+        });
+    recent_menu::refresh(app);
+    result
 }
 
-fn save_recents(app: &AppHandle, recents: &[RecentItem]) {
-    if let Ok(contents) = serde_json::to_string_pretty(recents) {
-        let _ = fs::create_dir_all(app_data_dir(app));
-        let _ = fs::write(recents_path(app), contents);
+/// A recents entry for `path` as it is on disk now, opened just now.
+fn new_recent_item(app: &AppHandle, kind: &str, path: &str, name: Option<String>) -> RecentItem {
+    let status = file_status(Path::new(path));
+    RecentItem {
+        kind: kind.to_string(),
+        path: path.to_string(),
+        name,
+        updated_at: now_epoch(),
+        root: roots::relative_to_roots(&roots::load_roots(app), Path::new(path)),
+        recorded_size: status.size,
+        recorded_modified_at: status.modified_at,
+        encrypted: crypto::is_encrypted_path(Path::new(path)),
+        pinned: false,
     }
 }
 
 fn update_recents(app: &AppHandle, kind: &str, path: &str, name: Option<String>) {
-    let mut recents = load_recents(app);
-    recents.retain(|item| !(item.kind == kind && item.path == path));
-    recents.insert(
-        0,
-        RecentItem {
-            kind: kind.to_string(),
-            path: path.to_string(),
-            name,
-            updated_at: now_epoch(),
-        },
-    );
-    if recents.len() > 10 {
-        recents.truncate(10);
-    }
-    save_recents(app, &recents);
+    let path = if remote::is_url(path) {
+        path.to_string()
+    } else {
+        paths::normalize(Path::new(path))
+            .to_string_lossy()
+            .to_string()
+    };
+    let mut item = new_recent_item(app, kind, &path, name);
+    let limit = settings::load_settings(app).recents_limit;
+    modify_recents(app, |recents| {
+        recents.retain(|existing| {
+            let same = existing.kind == kind && existing.path == path;
+            item.pinned |= same && existing.pinned;
+            !same
+        });
+        recents.insert(0, item);
+        recent_pins::cap(recents, limit);
+    });
 }
 
-fn read_file(path: &Path) -> Result<String, String> {
-    eprintln!("[excalibur] read_file: attempting to read {:?}", path);
-    match fs::read_to_string(path) {
-        Ok(contents) => {
-            eprintln!(
-                "[excalibur] read_file: success, read {} bytes from {:?}",
-                contents.len(),
-                path
-            );
-            Ok(contents)
+/// Points recents at a file's new location, re-deriving its root-relative form.
+fn relocate_recents(app: &AppHandle, old_path: &str, new_path: &str) {
+    let root = roots::relative_to_roots(&roots::load_roots(app), Path::new(new_path));
+    let status = file_status(Path::new(new_path));
+    let encrypted = crypto::is_encrypted_path(Path::new(new_path));
+    modify_recents(app, |recents| {
+        for item in recents.iter_mut().filter(|item| item.path == old_path) {
+            item.path = new_path.to_string();
+            item.root = root.clone();
+            item.recorded_size = status.size;
+            item.recorded_modified_at = status.modified_at;
+            item.encrypted = encrypted;
         }
-        Err(error) => {
-            eprintln!("[excalibur] read_file: FAILED to read {:?}: {}", path, error);
-            Err(error.to_string())
-        }
-    }
+        // The file may already have been listed under its new path too.
+        let mut seen = std::collections::HashSet::new();
+        recents.retain(|item| seen.insert((item.kind.clone(), item.path.clone())));
+    });
 }
 
-fn write_file(path: &Path, contents: &str) -> Result<(), String> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|error| error.to_string())?;
-    }
-    fs::write(path, contents).map_err(|error| error.to_string())
+/// Reads a text file; a BOM is dropped and UTF-16 transcoded, see
+/// `text_encoding::decode`.
+fn read_file(path: &Path) -> Result<String, error::CommandError> {
+    log::debug!("read_file: attempting to read {:?}", path);
+    let bytes = fs::read(path).map_err(|error| {
+        log::warn!("read_file: failed to read {:?}: {}", path, error);
+        error::CommandError::from_io(error, path)
+    })?;
+    let (contents, encoding) = text_encoding::decode(path, bytes)?;
+    log::debug!(
+        "read_file: success, read {} bytes of {} from {:?}",
+        contents.len(),
+        encoding.name(),
+        path
+    );
+    Ok(contents)
+}
+
+/// Writes via a temporary file and a rename, so a crash mid-write can't leave `path`
+/// truncated; see `atomic_write::write_atomic`.
+fn write_file(path: &Path, contents: &str) -> Result<(), error::CommandError> {
+    write_binary_file(path, contents.as_bytes())
+}
+
+/// `write_file` for contents that aren't text, e.g. a PNG export.
+fn write_binary_file(path: &Path, contents: &[u8]) -> Result<(), error::CommandError> {
+    atomic_write::write_atomic(path, contents)
+        .map_err(|error| error::CommandError::from_io(error, path))
 }
 
 fn file_name(path: &Path) -> Option<String> {
@@ -119,273 +454,1100 @@ fn file_name(path: &Path) -> Option<String> {
         .map(|name| name.to_string_lossy().to_string())
 }
 
-fn default_excalidraw_file_name(name: Option<&str>) -> String {
-    let base_name = name
-        .map(str::trim)
-        .filter(|name| !name.is_empty())
-        .unwrap_or("drawing");
+/// The name the save dialog suggests for a scene: its own `name`, or else the
+/// `default_name` from the settings.
+fn default_excalidraw_file_name(name: Option<&str>, default_name: &str) -> String {
+    dialogs::suggested_file_name(name, default_name, EXCALIDRAW_EXTENSIONS)
+}
 
-    if base_name.ends_with(".excalidraw") || base_name.ends_with(".json") {
-        base_name.to_string()
-    } else {
-        format!("{base_name}.excalidraw")
+/// The name the save dialog suggests for a scene saved as an `.excalidraw.png`.
+fn default_excalidraw_png_name(name: Option<&str>, default_name: &str) -> String {
+    let name = name.map(|name| {
+        let trimmed = name.trim();
+        match trimmed.len().checked_sub(".png".len()) {
+            Some(dot)
+                if trimmed.is_char_boundary(dot) && trimmed[dot..].eq_ignore_ascii_case(".png") =>
+            {
+                &trimmed[..dot]
+            }
+            _ => trimmed,
+        }
+    });
+    format!("{}.png", default_excalidraw_file_name(name, default_name))
+}
+
+/// The name the save dialog suggests for a diagram: its own `name`, or else the
+/// `default_name` from the settings.
+fn default_mermaid_file_name(name: Option<&str>, default_name: &str) -> String {
+    dialogs::suggested_file_name(name, default_name, MERMAID_EXTENSIONS)
+}
+
+/// Returns the save/open dialog filter used for documents of `kind`.
+fn kind_dialog_filter(kind: &str) -> (&'static str, &'static [&'static str]) {
+    match kind {
+        "mermaid" => ("Mermaid", MERMAID_EXTENSIONS),
+        _ => ("Excalidraw", EXCALIDRAW_EXTENSIONS),
     }
 }
 
+/// The recents as the start screen lists them, with file status and preview.
+fn recent_entries(app: &AppHandle) -> Vec<RecentEntry> {
+    let shortener = display_path::PathShortener::new(app);
+    let mut recents = load_recents(app);
+    recent_pins::sort_for_listing(&mut recents);
+    let recents: Vec<(RecentItem, FileStatus)> = recents
+        .into_iter()
+        .map(|item| {
+            let status = file_status(Path::new(&item.path));
+            (item, status)
+        })
+        .collect();
+    let previews = recent_previews::previews(
+        app,
+        &recents
+            .iter()
+            .map(|(item, status)| (item.kind.as_str(), item.path.as_str(), status))
+            .collect::<Vec<_>>(),
+    );
+    recents
+        .into_iter()
+        .zip(previews)
+        .map(|((item, status), preview)| RecentEntry {
+            status,
+            display_path: shortener.shorten(Path::new(&item.path)),
+            preview,
+            item,
+        })
+        .collect()
+}
+
 #[tauri::command]
-fn list_recents(app: AppHandle) -> Vec<RecentItem> {
-    load_recents(&app)
+fn list_recents(app: AppHandle) -> Result<Vec<RecentEntry>, error::CommandError> {
+    privacy::ensure_unlocked(&app)?;
+    Ok(recent_entries(&app))
+}
+
+/// Drops the recents `forget` matches and returns the updated list.
+/// Listing rewrites the preview cache without the removed files, so nothing derived
+/// from them is left behind.
+fn forget_recents(
+    app: &AppHandle,
+    forget: impl Fn(&RecentItem) -> bool,
+) -> Result<Vec<RecentEntry>, error::CommandError> {
+    privacy::ensure_unlocked(app)?;
+    let changed = modify_recents(app, |recents| {
+        let before = recents.len();
+        recents.retain(|item| !forget(item));
+        recents.len() != before
+    });
+    if changed {
+        let _ = app.emit("recents-changed", ());
+    }
+    Ok(recent_entries(app))
 }
 
+/// Removes one entry, pinned or not. A path that isn't listed is not an error.
 #[tauri::command]
-async fn open_excalidraw_file(app: AppHandle) -> Result<Option<OpenFileResponse>, String> {
-    eprintln!("[excalibur] open_excalidraw_file: opening file dialog");
-    let (sender, mut receiver) = channel(1);
-    app.dialog()
-        .file()
-        .add_filter("Excalidraw", &["excalidraw", "json"])
-        .pick_file(move |file_path| {
-            eprintln!("[excalibur] open_excalidraw_file: file dialog callback received");
-            let _ = sender.try_send(file_path);
-        });
+fn remove_recent(
+    app: AppHandle,
+    kind: String,
+    path: String,
+) -> Result<Vec<RecentEntry>, error::CommandError> {
+    log::info!("remove_recent: {} {}", kind, path);
+    forget_recents(&app, |item| item.kind == kind && item.path == path)
+}
 
-    eprintln!("[excalibur] open_excalidraw_file: waiting for file dialog response");
-    let Some(file_path) = receiver.recv().await else {
-        eprintln!("[excalibur] open_excalidraw_file: receiver closed, returning None");
-        return Ok(None);
-    };
-    let Some(file) = file_path else {
-        eprintln!("[excalibur] open_excalidraw_file: user cancelled dialog, returning None");
+/// Removes every entry of `kind`, or all of them when `kind` is `None`, pinned ones
+/// included.
+#[tauri::command]
+fn clear_recents(
+    app: AppHandle,
+    kind: Option<String>,
+) -> Result<Vec<RecentEntry>, error::CommandError> {
+    if let Some(kind) = kind.as_deref() {
+        if !matches!(kind, "excalidraw" | "mermaid" | library::LIBRARY_KIND) {
+            return Err(error::CommandError::InvalidKind {
+                kind: kind.to_string(),
+            });
+        }
+    }
+    log::info!("clear_recents: {}", kind.as_deref().unwrap_or("all"));
+    forget_recents(&app, |item| {
+        kind.as_deref().is_none_or(|kind| item.kind == kind)
+    })
+}
+
+/// Removes the recents whose files no longer exist and returns the rest. Files on a
+/// drive that is only unmounted count as gone, so this runs only when the user asks;
+/// paths that don't answer within `PRUNE_TIMEOUT` are kept, and so are URLs.
+#[tauri::command]
+async fn prune_recents(app: AppHandle) -> Result<Vec<RecentEntry>, error::CommandError> {
+    privacy::ensure_unlocked(&app)?;
+    let mut recents = load_recents(&app);
+    recents.retain(|item| !remote::is_url(&item.path));
+    let targets: Vec<PathBuf> = recents
+        .iter()
+        .map(|item| PathBuf::from(&item.path))
+        .collect();
+    let exists =
+        tauri::async_runtime::spawn_blocking(move || paths::exist_within(&targets, PRUNE_TIMEOUT))
+            .await
+            .map_err(|error| error::CommandError::Io {
+                message: error.to_string(),
+            })?;
+    let missing: std::collections::HashSet<(String, String)> = recents
+        .into_iter()
+        .zip(exists)
+        .filter(|(_, exists)| *exists == Some(false))
+        .map(|(item, _)| (item.kind, item.path))
+        .collect();
+    log::info!("prune_recents: removing {} missing", missing.len());
+    forget_recents(&app, |item| {
+        missing.contains(&(item.kind.clone(), item.path.clone()))
+    })
+}
+
+#[tauri::command]
+async fn open_excalidraw_file(
+    app: AppHandle,
+    window: WebviewWindow,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<Option<OpenFileResponse>, error::CommandError> {
+    log::debug!("open_excalidraw_file: opening file dialog");
+    let Some(path) =
+        dialogs::pick_open_path(&app, "Excalidraw", EXCALIDRAW_OPEN_EXTENSIONS).await?
+    else {
+        log::info!("open_excalidraw_file: user cancelled dialog, returning None");
         return Ok(None);
     };
-    let path = file.into_path().map_err(|e| {
-        eprintln!("[excalibur] open_excalidraw_file: failed to convert path: {}", e);
-        e.to_string()
-    })?;
-    eprintln!("[excalibur] open_excalidraw_file: selected path = {:?}", path);
+    log::debug!("open_excalidraw_file: selected path = {:?}", path);
+    open_documents::check_not_open_elsewhere(
+        &app,
+        window.label(),
+        &path,
+        allow_duplicate.unwrap_or(false),
+    )?;
 
-    let contents = read_file(&path)?;
+    let mut timer = metrics::Timer::start(&app, "open_excalidraw_file", 0);
+    let (contents, encrypted, embedded_in_png) =
+        read_excalidraw_document(&app, &path, None).await?;
+    let warnings = scene_schema::validate_excalidraw(&contents)
+        .err()
+        .unwrap_or_default();
+    timer.payload_bytes(contents.len());
     let name = file_name(&path);
     let path_string = path.to_string_lossy().to_string();
 
-    eprintln!(
-        "[excalibur] open_excalidraw_file: updating recents for path={}, name={:?}",
-        path_string, name
+    log::debug!(
+        "open_excalidraw_file: updating recents for path={}, name={:?}",
+        path_string,
+        name
     );
     update_recents(&app, "excalidraw", &path_string, name.clone());
 
-    eprintln!(
-        "[excalibur] open_excalidraw_file: returning response with {} bytes of content",
+    log::debug!(
+        "open_excalidraw_file: returning response with {} bytes of content",
         contents.len()
     );
-    Ok(Some(OpenFileResponse {
+    volumes::track(&app, &path);
+    open_documents::register(&app, window.label(), "excalidraw", &path);
+    timer.finish(Ok(Some(OpenFileResponse {
+        read_only: read_only::on_open(&app, &path, open_read_only),
+        writable_dir: Some(read_only::dir_writable(&path)),
+        meta: file_info::file_meta(&path),
+        large_file: large_files::is_large(&app, &path),
+        compressed: gzip::is_gzip_file(&path),
+        locked_by: locks::acquire(&app, &path),
+        checksum: checksums::verify_on_open(&app, &path),
+        schema: scene_schema::check_opened(&app, &path_string, &contents),
+        valid: Some(warnings.is_empty()),
+        warnings,
+        encoding: text_encoding::reported(&path),
+        mtime: modified_millis(&path),
         path: path_string,
         name,
         contents,
-    }))
+        encrypted,
+        embedded_in_png,
+        ..Default::default()
+    })))
 }
 
 #[tauri::command]
-fn load_excalidraw_path(app: AppHandle, path: String) -> Result<OpenFileResponse, String> {
-    eprintln!("[excalibur] load_excalidraw_path: loading from path={}", path);
-    let path_buf = PathBuf::from(&path);
+async fn load_excalidraw_path(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    focus_element_ids: Option<Vec<String>>,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenFileResponse, error::CommandError> {
+    log::debug!("load_excalidraw_path: loading from path={}", path);
+    let path_buf = paths::normalize(Path::new(&path));
+    open_documents::check_not_open_elsewhere(
+        &app,
+        window.label(),
+        &path_buf,
+        allow_duplicate.unwrap_or(false),
+    )?;
 
-    let contents = read_file(&path_buf)?;
+    let (contents, encrypted, embedded_in_png) =
+        read_excalidraw_document(&app, &path_buf, password).await?;
+    let warnings = scene_schema::validate_excalidraw(&contents)
+        .err()
+        .unwrap_or_default();
     let name = file_name(&path_buf);
     let path_string = path_buf.to_string_lossy().to_string();
 
-    eprintln!(
-        "[excalibur] load_excalidraw_path: updating recents for path={}, name={:?}",
-        path_string, name
+    log::debug!(
+        "load_excalidraw_path: updating recents for path={}, name={:?}",
+        path_string,
+        name
     );
     update_recents(&app, "excalidraw", &path_string, name.clone());
 
-    eprintln!(
-        "[excalibur] load_excalidraw_path: returning response with {} bytes of content",
+    log::debug!(
+        "load_excalidraw_path: returning response with {} bytes of content",
         contents.len()
     );
+    volumes::track(&app, &path_buf);
+    open_documents::register(&app, window.label(), "excalidraw", &path_buf);
     Ok(OpenFileResponse {
+        read_only: read_only::on_open(&app, &path_buf, open_read_only),
+        writable_dir: Some(read_only::dir_writable(&path_buf)),
+        meta: file_info::file_meta(&path_buf),
+        large_file: large_files::is_large(&app, &path_buf),
+        compressed: gzip::is_gzip_file(&path_buf),
+        locked_by: locks::acquire(&app, &path_buf),
+        checksum: checksums::verify_on_open(&app, &path_buf),
+        schema: scene_schema::check_opened(&app, &path_string, &contents),
+        valid: Some(warnings.is_empty()),
+        warnings,
+        encoding: text_encoding::reported(&path_buf),
+        mtime: modified_millis(&path_buf),
         path: path_string,
         name,
         contents,
+        focus_element_ids,
+        encrypted,
+        embedded_in_png,
+        remote: false,
+    })
+}
+
+/// Reads an Excalidraw document, which may also be a PNG image with the scene
+/// embedded. Returns the scene, whether it was encrypted and whether it was a PNG.
+async fn read_excalidraw_document(
+    app: &AppHandle,
+    path: &Path,
+    password: Option<String>,
+) -> Result<(String, bool, bool), error::CommandError> {
+    if !png_scene::is_png_file(path) {
+        let (contents, encrypted) = crypto::read_document(app, path, password).await?;
+        return Ok((contents, encrypted, false));
+    }
+    let bytes = large_files::read(app, path).await?;
+    Ok((png_scene::extract_scene(path, &bytes)?, false, true))
+}
+
+/// Keeps the contents a save is about to replace as a `.bak` copy. A save is refused
+/// when the copy can't be made, rather than overwriting without one.
+fn keep_backup(path: &Path, keep: Option<bool>) -> Result<(), error::CommandError> {
+    file_backups::before_save(path, keep).map_err(|error| error::CommandError::BackupFailed {
+        path: path.to_string_lossy().to_string(),
+        message: error.to_string(),
+    })
+}
+
+/// Refuses to overwrite `path` when it was modified since the document was opened or
+/// last saved at `expected_mtime`. A file that is gone by now is simply written.
+fn check_conflict(
+    path: &Path,
+    expected_mtime: Option<u64>,
+    force: bool,
+) -> Result<(), error::CommandError> {
+    let (Some(expected_mtime), Some(current_mtime)) = (expected_mtime, modified_millis(path))
+    else {
+        return Ok(());
+    };
+    if force || expected_mtime == current_mtime {
+        return Ok(());
+    }
+    log::info!(
+        "check_conflict: {} changed on disk (expected mtime {}, now {})",
+        path.display(),
+        expected_mtime,
+        current_mtime
+    );
+    Err(error::CommandError::Conflict {
+        path: path.to_string_lossy().to_string(),
+        expected_mtime,
+        current_mtime,
     })
 }
 
 #[tauri::command]
 async fn save_excalidraw_file(
     app: AppHandle,
+    window: WebviewWindow,
     request: SaveFileRequest,
-) -> Result<SaveFileResponse, String> {
-    let suggested_name = default_excalidraw_file_name(request.name.as_deref());
-    let path = if let Some(path) = request.path {
-        PathBuf::from(path)
-    } else {
-        let (sender, mut receiver) = channel(1);
-        app.dialog()
-            .file()
-            .add_filter("Excalidraw", &["excalidraw", "json"])
-            .set_file_name(suggested_name)
-            .save_file(move |file_path| {
-                let _ = sender.try_send(file_path);
-            });
-        let target = receiver
-            .recv()
-            .await
-            .ok_or_else(|| "Save cancelled".to_string())?;
-        target
-            .ok_or_else(|| "Save cancelled".to_string())?
-            .into_path()
-            .map_err(|e| e.to_string())?
+) -> Result<SaveFileResponse, error::CommandError> {
+    scene_schema::check_save(&request.contents)?;
+    // Decoded before the dialog, so a bad image doesn't ask for a path first.
+    let png = request
+        .png_base64
+        .as_deref()
+        .map(exports::decode_payload)
+        .transpose()?;
+    let current = request.path.as_deref().map(PathBuf::from);
+    let (directory, current_name) = current.as_deref().map(save_as_start).unwrap_or_default();
+    let path = match current.clone().filter(|_| !request.always_prompt) {
+        Some(path) => path,
+        None => {
+            let name = request.name.as_deref().or(current_name.as_deref());
+            let default_name = settings::load_settings(&app).default_excalidraw_name;
+            let (filter_name, extensions, name) = match &png {
+                Some(_) => (
+                    "PNG",
+                    &["png"][..],
+                    default_excalidraw_png_name(name, &default_name),
+                ),
+                None => (
+                    "Excalidraw",
+                    EXCALIDRAW_EXTENSIONS,
+                    default_excalidraw_file_name(name, &default_name),
+                ),
+            };
+            dialogs::pick_save_path_in(&app, filter_name, extensions, name, directory)
+                .await?
+                .ok_or(error::CommandError::Cancelled)?
+        }
     };
+    let saved_as = current.is_some_and(|current| current != path);
+    let compress = request
+        .compress
+        .unwrap_or_else(|| gzip::has_gz_suffix(&path) || (!saved_as && gzip::is_gzip_file(&path)));
+    let is_png_path = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    if is_png_path && png.is_none() {
+        // Writing the scene JSON would turn the image into a file no viewer opens.
+        return Err(error::CommandError::InvalidArgument {
+            message: format!(
+                "{} is a PNG image; saving it needs the rendered image in png_base64",
+                path.display()
+            ),
+        });
+    }
 
-    write_file(&path, &request.contents)?;
-    let name = request.name.or_else(|| file_name(&path));
+    let timer = metrics::Timer::start(&app, "save_excalidraw_file", request.contents.len());
+    if volumes::is_disconnected(&app, &path) {
+        return Err(volumes::disconnected_error(&path));
+    }
+    read_only::ensure_writable(&app, &path)?;
+    if !saved_as {
+        check_conflict(&path, request.expected_mtime, request.force)?;
+    }
+    scene_schema::check_overwrite(&path, request.acknowledge_downgrade)?;
+    keep_backup(&path, request.keep_backup)?;
+    let capture = local_history::capture_before_save(&app, &path);
+    if request.acknowledge_downgrade && capture.is_none() && path.exists() {
+        // The newer original must be recoverable before it is rewritten.
+        return Err(error::CommandError::BackupFailed {
+            path: path.to_string_lossy().to_string(),
+            message: "local history couldn't store it".to_string(),
+        });
+    }
+    let written = match &png {
+        Some(png) => png_scene::embed_scene(png, &request.contents).and_then(|image| {
+            file_watch::expect_write(&app, &path, &image);
+            write_binary_file(&path, &image)
+        }),
+        None => {
+            crypto::write_document(&app, "excalidraw", &path, &request.contents, compress).await
+        }
+    };
+    written.map_err(|error| {
+        if volumes::check_failure(&app, &path) {
+            volumes::disconnected_error(&path)
+        } else {
+            error
+        }
+    })?;
+    local_history::finish_save(&app, &path, capture);
+    checksums::after_save(&app, &path);
+    if png.is_none() {
+        autosave::after_save(&app, &path, &request.contents);
+    }
+    let name = request
+        .name
+        .filter(|_| !saved_as)
+        .or_else(|| file_name(&path));
     let path_string = path.to_string_lossy().to_string();
-    update_recents(&app, "excalidraw", &path_string, name);
+    update_recents(&app, "excalidraw", &path_string, name.clone());
+    volumes::track(&app, &path);
+    untitled::after_save(&app, request.untitled_id.as_deref(), &path);
+    drafts::after_save(&app, request.untitled_id.as_deref(), &path);
+    let open_in_other_windows =
+        open_documents::after_save(&app, window.label(), "excalidraw", &path);
 
-    Ok(SaveFileResponse { path: path_string })
+    timer.finish(Ok(SaveFileResponse {
+        path: path_string,
+        name,
+        mtime: modified_millis(&path),
+        open_in_other_windows,
+    }))
 }
 
 #[tauri::command]
-async fn open_mermaid_file(app: AppHandle) -> Result<Option<OpenFileResponse>, String> {
-    let (sender, mut receiver) = channel(1);
-    app.dialog()
-        .file()
-        .add_filter("Mermaid", &["mmd", "mermaid", "md", "txt"])
-        .pick_file(move |file_path| {
-            let _ = sender.try_send(file_path);
-        });
+fn new_excalidraw_file(app: AppHandle) -> Result<templates::UntitledDocument, error::CommandError> {
+    templates::new_document(&app, "excalidraw")
+}
 
-    let Some(file_path) = receiver.recv().await else {
-        return Ok(None);
-    };
-    let Some(file) = file_path else {
+#[tauri::command]
+fn new_mermaid_file(app: AppHandle) -> Result<templates::UntitledDocument, error::CommandError> {
+    templates::new_document(&app, "mermaid")
+}
+
+#[tauri::command]
+async fn open_mermaid_file(
+    app: AppHandle,
+    window: WebviewWindow,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<Option<OpenFileResponse>, error::CommandError> {
+    let Some(path) = dialogs::pick_open_path(&app, "Mermaid", MERMAID_EXTENSIONS).await? else {
         return Ok(None);
     };
-    let path = file.into_path().map_err(|e| e.to_string())?;
-    let contents = read_file(&path)?;
+    open_documents::check_not_open_elsewhere(
+        &app,
+        window.label(),
+        &path,
+        allow_duplicate.unwrap_or(false),
+    )?;
+    let mut timer = metrics::Timer::start(&app, "open_mermaid_file", 0);
+    let (contents, encrypted) = crypto::read_document(&app, &path, None).await?;
+    timer.payload_bytes(contents.len());
+    let source = markdown_blocks::mermaid_source(&path, contents)?;
     let name = file_name(&path);
     let path_string = path.to_string_lossy().to_string();
     update_recents(&app, "mermaid", &path_string, name.clone());
 
-    Ok(Some(OpenFileResponse {
+    volumes::track(&app, &path);
+    open_documents::register(&app, window.label(), "mermaid", &path);
+    timer.finish(Ok(Some(OpenFileResponse {
+        read_only: read_only::on_open(&app, &path, open_read_only),
+        writable_dir: Some(read_only::dir_writable(&path)),
+        meta: file_info::file_meta(&path),
+        large_file: large_files::is_large(&app, &path),
+        compressed: gzip::is_gzip_file(&path),
+        locked_by: locks::acquire(&app, &path),
+        checksum: checksums::verify_on_open(&app, &path),
+        encoding: text_encoding::reported(&path),
+        mtime: modified_millis(&path),
         path: path_string,
         name,
-        contents,
-    }))
+        contents: source.contents,
+        block_range: source.block_range,
+        blocks: source.blocks,
+        encrypted,
+        ..Default::default()
+    })))
 }
 
 #[tauri::command]
-fn load_mermaid_path(app: AppHandle, path: String) -> Result<OpenFileResponse, String> {
-    let path_buf = PathBuf::from(path);
-    let contents = read_file(&path_buf)?;
+async fn load_mermaid_path(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenFileResponse, error::CommandError> {
+    let path_buf = paths::normalize(Path::new(&path));
+    open_documents::check_not_open_elsewhere(
+        &app,
+        window.label(),
+        &path_buf,
+        allow_duplicate.unwrap_or(false),
+    )?;
+    let (contents, encrypted) = crypto::read_document(&app, &path_buf, password).await?;
+    let source = markdown_blocks::mermaid_source(&path_buf, contents)?;
     let name = file_name(&path_buf);
     let path_string = path_buf.to_string_lossy().to_string();
     update_recents(&app, "mermaid", &path_string, name.clone());
 
+    volumes::track(&app, &path_buf);
+    open_documents::register(&app, window.label(), "mermaid", &path_buf);
     Ok(OpenFileResponse {
+        read_only: read_only::on_open(&app, &path_buf, open_read_only),
+        writable_dir: Some(read_only::dir_writable(&path_buf)),
+        meta: file_info::file_meta(&path_buf),
+        large_file: large_files::is_large(&app, &path_buf),
+        compressed: gzip::is_gzip_file(&path_buf),
+        locked_by: locks::acquire(&app, &path_buf),
+        checksum: checksums::verify_on_open(&app, &path_buf),
+        encoding: text_encoding::reported(&path_buf),
+        mtime: modified_millis(&path_buf),
         path: path_string,
         name,
-        contents,
+        contents: source.contents,
+        block_range: source.block_range,
+        blocks: source.blocks,
+        encrypted,
+        ..Default::default()
     })
 }
 
 #[tauri::command]
 async fn save_mermaid_file(
     app: AppHandle,
+    window: WebviewWindow,
     request: SaveFileRequest,
-) -> Result<SaveFileResponse, String> {
-    let path = if let Some(path) = request.path {
-        PathBuf::from(path)
-    } else {
-        let (sender, mut receiver) = channel(1);
-        app.dialog()
-            .file()
-            .add_filter("Mermaid", &["mmd", "mermaid", "md", "txt"])
-            .set_file_name("diagram.mmd")
-            .save_file(move |file_path| {
-                let _ = sender.try_send(file_path);
-            });
-        let target = receiver
-            .recv()
-            .await
-            .ok_or_else(|| "Save cancelled".to_string())?;
-        target
-            .ok_or_else(|| "Save cancelled".to_string())?
-            .into_path()
-            .map_err(|e| e.to_string())?
+) -> Result<SaveFileResponse, error::CommandError> {
+    let current = request.path.as_deref().map(PathBuf::from);
+    let path = match current.clone().filter(|_| !request.always_prompt) {
+        Some(path) => path,
+        None => {
+            let (directory, current_name) =
+                current.as_deref().map(save_as_start).unwrap_or_default();
+            let name = default_mermaid_file_name(
+                request.name.as_deref().or(current_name.as_deref()),
+                &settings::load_settings(&app).default_mermaid_name,
+            );
+            dialogs::pick_save_path_in(&app, "Mermaid", MERMAID_EXTENSIONS, name, directory)
+                .await?
+                .ok_or(error::CommandError::Cancelled)?
+        }
     };
+    let saved_as = current.as_ref().is_some_and(|current| *current != path);
+    let compress = request
+        .compress
+        .unwrap_or_else(|| gzip::has_gz_suffix(&path) || (!saved_as && gzip::is_gzip_file(&path)));
+    // Saving under a new name writes the diagram alone.
+    let block_range = request
+        .block_range
+        .filter(|_| current.is_some() && !saved_as);
 
-    write_file(&path, &request.contents)?;
-    let name = request.name.or_else(|| file_name(&path));
+    let timer = metrics::Timer::start(&app, "save_mermaid_file", request.contents.len());
+    if volumes::is_disconnected(&app, &path) {
+        return Err(volumes::disconnected_error(&path));
+    }
+    read_only::ensure_writable(&app, &path)?;
+    if !saved_as {
+        check_conflict(&path, request.expected_mtime, request.force)?;
+    }
+    keep_backup(&path, request.keep_backup)?;
+    let contents = match block_range {
+        Some(range) => {
+            let (markdown, _) = crypto::read_document(&app, &path, None).await?;
+            markdown_blocks::splice(&path, &markdown, range, &request.contents)?
+        }
+        None => request.contents,
+    };
+    let capture = local_history::capture_before_save(&app, &path);
+    crypto::write_document(&app, "mermaid", &path, &contents, compress)
+        .await
+        .map_err(|error| {
+            if volumes::check_failure(&app, &path) {
+                volumes::disconnected_error(&path)
+            } else {
+                error
+            }
+        })?;
+    local_history::finish_save(&app, &path, capture);
+    checksums::after_save(&app, &path);
+    autosave::after_save(&app, &path, &contents);
+    let name = request
+        .name
+        .filter(|_| !saved_as)
+        .or_else(|| file_name(&path));
     let path_string = path.to_string_lossy().to_string();
-    update_recents(&app, "mermaid", &path_string, name);
+    update_recents(&app, "mermaid", &path_string, name.clone());
+    volumes::track(&app, &path);
+    untitled::after_save(&app, request.untitled_id.as_deref(), &path);
+    drafts::after_save(&app, request.untitled_id.as_deref(), &path);
+    let open_in_other_windows = open_documents::after_save(&app, window.label(), "mermaid", &path);
+
+    timer.finish(Ok(SaveFileResponse {
+        path: path_string,
+        name,
+        mtime: modified_millis(&path),
+        open_in_other_windows,
+    }))
+}
+
+/// Rewrites the kind of the recents at `path`, e.g. after `design.json` was renamed
+/// to `design.excalidraw` outside the app, and emits `recents-changed`.
+fn correct_recent_kind(app: &AppHandle, path: &str, kind: &str) {
+    let changed = modify_recents(app, |recents| {
+        let mut changed = false;
+        for item in recents
+            .iter_mut()
+            .filter(|item| item.path == path && item.kind != kind)
+        {
+            log::info!(
+                "correct_recent_kind: {} is now {} (was {})",
+                path,
+                kind,
+                item.kind
+            );
+            item.kind = kind.to_string();
+            changed = true;
+        }
+        let mut seen = std::collections::HashSet::new();
+        recents.retain(|item| seen.insert((item.kind.clone(), item.path.clone())));
+        changed
+    });
+    if changed {
+        let _ = app.emit("recents-changed", ());
+    }
+}
 
-    Ok(SaveFileResponse { path: path_string })
+async fn open_as(
+    window: WebviewWindow,
+    kind: &'static str,
+    path: String,
+    focus_element_ids: Option<Vec<String>>,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenPathResponse, error::CommandError> {
+    let app = window.app_handle().clone();
+    let file = match kind {
+        "excalidraw" => {
+            load_excalidraw_path(
+                app,
+                window,
+                path,
+                focus_element_ids,
+                password,
+                open_read_only,
+                allow_duplicate,
+            )
+            .await?
+        }
+        _ => {
+            load_mermaid_path(app, window, path, password, open_read_only, allow_duplicate).await?
+        }
+    };
+    Ok(OpenPathResponse { kind, file })
 }
 
-/// Returns (and clears) the file path that was pending from app startup.
+/// Opens a file from the recents list. The file is detected again instead of
+/// trusting the stored `kind`, since it may have been renamed since; a changed kind
+/// is written back to recents and returned, so the frontend picks the right editor.
+/// Files that can't be re-detected fail with `CommandError::AmbiguousKind`.
 #[tauri::command]
-fn take_pending_file(app: AppHandle) -> Option<String> {
-    let state = app.state::<PendingFile>();
-    let path = state.0.lock().unwrap().take();
-    eprintln!("[excalibur] take_pending_file: {:?}", path);
-    path
+async fn open_recent(
+    app: AppHandle,
+    window: WebviewWindow,
+    kind: String,
+    path: String,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenPathResponse, error::CommandError> {
+    let mut timer = metrics::Timer::start(&app, "open_recent", 0);
+    let bytes = large_files::read_decompressed(&app, Path::new(&path)).await?;
+    timer.payload_bytes(bytes.len());
+    let detected = if crypto::is_encrypted_bytes(&bytes) {
+        crypto::encrypted_kind(&bytes)?
+    } else if png_scene::is_png(&bytes) {
+        "excalidraw"
+    } else {
+        detect::redetect_kind(
+            Path::new(&path),
+            &kind,
+            &text_encoding::decode_for_detection(&bytes),
+        )
+        .map_err(|detected| error::CommandError::AmbiguousKind {
+            path: path.clone(),
+            stored: kind.clone(),
+            detected: detected.map(str::to_string),
+        })?
+    };
+    if detected != kind {
+        correct_recent_kind(&app, &path, detected);
+    }
+    timer.finish(
+        open_as(
+            window,
+            detected,
+            path,
+            None,
+            password,
+            open_read_only,
+            allow_duplicate,
+        )
+        .await,
+    )
 }
 
-fn file_path_from_url(url: &url::Url) -> Option<String> {
-    url.to_file_path()
-        .ok()
-        .map(|p| p.to_string_lossy().to_string())
+/// Opens a file of either kind, working out which from its extension or contents.
+///
+/// Deep links, drag-and-drop and the pending startup file all go through this, so
+/// `.json`/`.md`/`.txt` files are detected the same way everywhere. Encrypted
+/// containers carry their kind in the header and may prompt for a password.
+#[tauri::command]
+async fn open_path(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    focus_element_ids: Option<Vec<String>>,
+    password: Option<String>,
+    open_read_only: Option<bool>,
+    allow_duplicate: Option<bool>,
+) -> Result<OpenPathResponse, error::CommandError> {
+    let mut timer = metrics::Timer::start(&app, "open_path", 0);
+    let bytes = large_files::read_decompressed(&app, Path::new(&path)).await?;
+    timer.payload_bytes(bytes.len());
+    let kind = if crypto::is_encrypted_bytes(&bytes) {
+        Some(crypto::encrypted_kind(&bytes)?)
+    } else if png_scene::is_png(&bytes) {
+        // Only Excalidraw embeds its scene in an image.
+        Some("excalidraw")
+    } else {
+        detect::detect_kind(
+            Path::new(&path),
+            &text_encoding::decode_for_detection(&bytes),
+        )
+    };
+    let Some(kind) = kind else {
+        return Err(error::CommandError::InvalidDocument {
+            message: format!("{path} is not an Excalidraw scene or a Mermaid diagram"),
+        });
+    };
+    log::info!("open_path: {} detected as {}", path, kind);
+    timer.finish(
+        open_as(
+            window,
+            kind,
+            path,
+            focus_element_ids,
+            password,
+            open_read_only,
+            allow_duplicate,
+        )
+        .await,
+    )
 }
 
 fn main() {
-    tauri::Builder::default()
+    logging::install();
+    // `excalibur export ...` runs headless: parse it before any window exists.
+    let args: Vec<String> = std::env::args().collect();
+    let export_command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("excalibur: {message}\n{}", cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+    let stdin_document = match cli::read_stdin_document(&args) {
+        Ok(document) => document,
+        Err(message) => {
+            eprintln!("excalibur: {message}");
+            std::process::exit(cli::EXIT_FAILED);
+        }
+    };
+    let mut context = tauri::generate_context!();
+    if export_command.is_some() {
+        if let Some(message) = cli::headless_error() {
+            eprintln!("excalibur: {message}");
+            std::process::exit(cli::EXIT_NO_WEBVIEW);
+        }
+        for window in &mut context.config_mut().app.windows {
+            window.visible = false;
+        }
+    }
+
+    // A second launch hands its files to the running instance and exits, unless
+    // it was asked for a window of its own.
+    let mut builder = tauri::Builder::default();
+    if single_instance::is_enabled(&args, export_command.is_some(), stdin_document.is_some()) {
+        builder = builder.plugin(tauri_plugin_single_instance::init(
+            single_instance::on_second_launch,
+        ));
+    }
+    builder
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_deep_link::init())
-        .manage(PendingFile(Mutex::new(None)))
+        .plugin(tauri_plugin_notification::init())
+        .manage(event_queue::EventQueue::default())
+        .manage(operations::Operations::default())
+        .manage(search_index::SearchIndexes::default())
+        .manage(locks::DocumentLocks::default())
+        .manage(recents_watch::RecentsWatch::default())
+        .manage(crypto::EncryptionSessions::default())
+        .manage(render::Renderer::default())
+        .manage(volumes::Volumes::default())
+        .manage(privacy::PrivacyState::default())
+        .manage(background::BackgroundTasks::default())
+        .manage(read_only::ReadOnlyDocuments::default())
+        .manage(untitled::UntitledDocuments::default())
+        .manage(dialogs::Dialogs::default())
+        .manage(metrics::CommandMetrics::default())
+        .manage(file_watch::FileWatches::default())
+        .manage(open_documents::OpenDocuments::default())
+        .manage(drafts::Drafts::default())
+        .manage(recent_menu::RecentMenu::default())
+        .manage(close_guard::CloseGuard::default())
+        .manage(autosave::Autosaves::default())
+        .manage(templates::ReportedMissingDefaults(Mutex::new(
+            Default::default(),
+        )))
         .invoke_handler(tauri::generate_handler![
             list_recents,
+            remove_recent,
+            clear_recents,
+            prune_recents,
             open_excalidraw_file,
             load_excalidraw_path,
             save_excalidraw_file,
+            new_excalidraw_file,
             open_mermaid_file,
             load_mermaid_path,
             save_mermaid_file,
-            take_pending_file
+            new_mermaid_file,
+            open_path,
+            open_recent,
+            settings::get_settings,
+            settings::update_settings,
+            actions::list_actions,
+            templates::list_templates,
+            templates::new_from_template,
+            templates::save_as_template,
+            templates::delete_template,
+            builtin_templates::update_builtin_templates,
+            search::search_files,
+            search::cancel_search,
+            search_index::rebuild_search_index,
+            search_history::get_search_history,
+            search_history::clear_search_history,
+            search_history::save_search,
+            search_history::list_saved_searches,
+            search_history::delete_saved_search,
+            replace::replace_in_files,
+            updates::check_for_updates,
+            app_info::get_app_info,
+            data_transfer::export_app_data,
+            data_transfer::import_app_data,
+            workspaces::save_workspace,
+            workspaces::list_workspaces,
+            workspaces::open_workspace,
+            workspaces::delete_workspace,
+            favorites::list_favorites,
+            favorites::add_favorite,
+            favorites::remove_favorite,
+            favorites::move_favorite,
+            favorites::relocate_favorite,
+            roots::register_root,
+            roots::list_roots,
+            roots::unregister_root,
+            locks::acquire_document_lock,
+            locks::release_document_lock,
+            recent_pins::pin_recent,
+            recent_pins::unpin_recent,
+            recents_watch::start_recents_watch,
+            recents_watch::stop_recents_watch,
+            backups::run_backup_now,
+            backups::get_backup_status,
+            backups::list_data_backups,
+            backups::restore_data_backup,
+            crypto::save_encrypted_file,
+            crypto::open_encrypted_file,
+            crypto::submit_password,
+            crypto::lock_encrypted_documents,
+            local_history::undo_last_save,
+            local_history::redo_last_save,
+            local_history::list_versions,
+            local_history::read_version,
+            local_history::restore_version,
+            path_completion::complete_path,
+            path_completion::home_dir,
+            path_completion::validate_openable,
+            render::render_ready,
+            render::submit_render,
+            event_queue::frontend_ready,
+            operations::cancel_operation,
+            trash::list_internal_trash,
+            trash::restore_from_trash,
+            trash::empty_internal_trash,
+            relocate::relocate_file,
+            relocate::confirm_relocation,
+            file_info::file_info,
+            file_info::stat_file,
+            duplicates::find_duplicate_diagrams,
+            workspace_report::export_workspace_report,
+            volumes::save_elsewhere,
+            privacy::report_activity,
+            privacy::privacy_status,
+            privacy::privacy_lock,
+            privacy::privacy_unlock,
+            storage::get_storage_breakdown,
+            storage::clean_storage,
+            background::get_background_task_status,
+            read_only::make_writable,
+            untitled::set_dirty,
+            untitled::update_window_title,
+            untitled::set_window_title,
+            untitled::discard_untitled,
+            file_backups::list_backups,
+            file_backups::restore_backup,
+            metrics::get_command_metrics,
+            file_watch::watch_file,
+            file_watch::unwatch_file,
+            open_documents::focus_window,
+            drawio::import_drawio,
+            exports::save_export_file,
+            exports::export_alongside,
+            open_files::open_excalidraw_files,
+            open_files::open_mermaid_files,
+            drafts::write_draft,
+            drafts::list_drafts,
+            drafts::read_draft,
+            drafts::discard_draft,
+            session::save_session,
+            session::load_session,
+            close_guard::set_window_dirty,
+            close_guard::close_confirmed,
+            reveal::reveal_in_file_manager,
+            reveal::copy_path_to_clipboard,
+            system_trash::trash_file,
+            rename::rename_file,
+            duplicate_file::duplicate_file,
+            windows::open_in_new_window,
+            library::open_excalidraw_library,
+            library::load_excalidraw_library_path,
+            library::save_excalidraw_library,
+            remote::load_excalidraw_url,
+            remote::load_mermaid_url,
+            file_associations::file_association_status,
+            file_associations::register_file_associations,
+            logging::get_recent_logs,
+            logging::get_log_path,
+            autosave::enable_autosave,
+            autosave::disable_autosave,
+            autosave::autosave_contents
         ])
-        .setup(|app| {
+        .setup(move |app| {
+            let data_root = paths::DataRoot::resolve(app.handle()).unwrap_or_else(|message| {
+                eprintln!("excalibur: {message}");
+                std::process::exit(cli::EXIT_FAILED);
+            });
+            app.manage(data_root);
+            logging::start_file(app.handle());
+            app.manage(recents_store::RecentsStore::load(recents_path(
+                app.handle(),
+            )));
+            metrics::apply_settings(app.handle(), &settings::load_settings(app.handle()));
+            if let Some(command) = export_command {
+                let handle = app.handle().clone();
+                std::thread::spawn(move || {
+                    let code = cli::run_export(&handle, &command);
+                    handle.exit(code);
+                });
+                return Ok(());
+            }
+            if let Err(error) = recent_menu::install(app.handle()) {
+                log::warn!("failed to install the application menu: {error}");
+            }
+            builtin_templates::seed_builtin_templates(app.handle());
+            let index_handle = app.handle().clone();
+            std::thread::spawn(move || search_index::watch_registered_roots(&index_handle));
+            updates::spawn_startup_update_check(app.handle());
+            let lock_handle = app.handle().clone();
+            std::thread::spawn(move || locks::run_heartbeat(&lock_handle));
+            let backup_handle = app.handle().clone();
+            std::thread::spawn(move || backups::run_backup_schedule(&backup_handle));
+            let trash_handle = app.handle().clone();
+            std::thread::spawn(move || trash::run_trash_sweep(&trash_handle));
+            let privacy_handle = app.handle().clone();
+            std::thread::spawn(move || privacy::run_privacy_monitor(&privacy_handle));
+
+            // Content piped in with --stdin opens as an untitled document.
+            if let Some(document) = stdin_document {
+                event_queue::emit_when_ready(app.handle(), None, "new-document", document);
+            }
+
+            // Files named on the command line, e.g. `excalibur ~/diagrams/arch.mmd`.
+            let cwd = std::env::current_dir().unwrap_or_default();
+            launch_args::open(app.handle(), &args, &cwd, false);
+
             // Check for a file opened at launch (e.g. double-click in Finder).
-            // Store it in state so the frontend can retrieve it when ready.
+            // The event is queued until the frontend says it is listening.
             if let Ok(Some(urls)) = app.deep_link().get_current() {
-                eprintln!("[excalibur] deep_link startup URLs: {:?}", urls);
-                for url in &urls {
-                    if let Some(path) = file_path_from_url(url) {
-                        eprintln!("[excalibur] storing pending file for startup: {}", path);
-                        let state = app.state::<PendingFile>();
-                        *state.0.lock().unwrap() = Some(path);
-                        break;
-                    }
-                }
+                log::info!("deep_link startup URLs: {:?}", urls);
+                deep_links::handle_urls(app.handle(), &urls);
             }
 
             // Listen for files opened while the app is already running.
-            // At this point the frontend is loaded, so emitting an event is fine.
+            // The webview may still be loading, so these go through the queue too.
             let handle = app.handle().clone();
             app.deep_link().on_open_url(move |event| {
                 let urls = event.urls();
-                eprintln!("[excalibur] deep_link on_open_url: {:?}", urls);
-                for url in &urls {
-                    if let Some(path) = file_path_from_url(url) {
-                        eprintln!("[excalibur] emitting open-file for runtime path: {}", path);
-                        let _ = handle.emit("open-file", path);
-                        break;
-                    }
-                }
+                log::info!("deep_link on_open_url: {:?}", urls);
+                deep_links::handle_urls(&handle, &urls);
             });
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_menu_event(|app, event| recent_menu::handle_menu_event(app, event.id().as_ref()))
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                event_queue::mark_not_ready(webview.app_handle(), webview.label());
+            }
+        })
+        .on_window_event(|window, event| {
+            // Dropped files take the same route as deep links.
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                if !paths.is_empty() {
+                    let paths: Vec<String> = paths
+                        .iter()
+                        .map(|path| path.to_string_lossy().to_string())
+                        .collect();
+                    log::info!("emitting open-files for dropped paths: {:?}", paths);
+                    event_queue::emit_when_ready(
+                        window.app_handle(),
+                        Some(window.label()),
+                        "open-files",
+                        paths,
+                    );
+                }
+            }
+            if let tauri::WindowEvent::Focused(focused) = event {
+                background::set_focused(window.app_handle(), window.label(), *focused);
+                if *focused {
+                    operations::deliver_reports(window);
+                }
+            }
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                close_guard::on_close_requested(window, api);
+            }
+            if let tauri::WindowEvent::Destroyed = event {
+                close_guard::forget_window(window.app_handle(), window.label());
+                autosave::forget_window(window.app_handle(), window.label());
+                background::set_focused(window.app_handle(), window.label(), false);
+                recents_watch::unsubscribe(window.app_handle(), window.label());
+                open_documents::forget_window(window.app_handle(), window.label());
+                event_queue::mark_not_ready(window.app_handle(), window.label());
+            }
+        })
+        .build(context)
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::ExitRequested { api, .. } = &event {
+                close_guard::on_exit_requested(app, api);
+            }
+            if let tauri::RunEvent::Exit = event {
+                locks::release_all(app);
+                if let Some(recents) = app.try_state::<recents_store::RecentsStore>() {
+                    recents.flush();
+                }
+            }
+        });
 }
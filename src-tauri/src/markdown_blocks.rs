@@ -0,0 +1,256 @@
+use crate::detect::mermaid_diagram_type;
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The body of a fenced block: 0-based line indices, `end` exclusive, so an empty
+/// block has `start == end`. The fences themselves are outside the range.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A ```` ```mermaid ```` block found in a Markdown file.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MermaidBlock {
+    pub contents: String,
+    pub range: BlockRange,
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    crate::gzip::document_extension(path).is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+    })
+}
+
+/// The fence a line opens, as its character and length, when it opens a mermaid
+/// block: up to three spaces of indent, three or more backticks or tildes, and an
+/// info string whose first word is `mermaid`.
+fn mermaid_fence(line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let fence = line.chars().next().filter(|ch| *ch == '`' || *ch == '~')?;
+    let length = line.len() - line.trim_start_matches(fence).len();
+    if length < 3 {
+        return None;
+    }
+    let info = line[length..].trim();
+    (info.split_whitespace().next() == Some("mermaid")).then_some((fence, length))
+}
+
+/// Whether `line` closes a block opened with `length` × `fence`.
+fn closes(line: &str, (fence, length): (char, usize)) -> bool {
+    let trimmed = line.trim();
+    line.len() - line.trim_start_matches(' ').len() <= 3
+        && trimmed.len() >= length
+        && trimmed.chars().all(|ch| ch == fence)
+}
+
+/// Lines of `text`, each without its line ending.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n')
+        .map(|line| line.trim_end_matches('\n').trim_end_matches('\r'))
+        .collect()
+}
+
+/// The mermaid blocks of a Markdown document, in order. A block left open runs to
+/// the end of the document, as in CommonMark.
+pub fn find_blocks(markdown: &str) -> Vec<MermaidBlock> {
+    let lines = lines(markdown);
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let Some(fence) = mermaid_fence(lines[index]) else {
+            index += 1;
+            continue;
+        };
+        let start = index + 1;
+        let end = (start..lines.len())
+            .find(|line| closes(lines[*line], fence))
+            .unwrap_or(lines.len());
+        let mut contents = lines[start..end].join("\n");
+        if end > start {
+            contents.push('\n');
+        }
+        blocks.push(MermaidBlock {
+            contents,
+            range: BlockRange { start, end },
+        });
+        index = end + 1;
+    }
+    blocks
+}
+
+/// What opening a file as Mermaid gives the editor.
+#[derive(Debug, PartialEq)]
+pub struct MermaidSource {
+    pub contents: String,
+    /// The block `contents` came from; saves send it back as `block_range`.
+    pub block_range: Option<BlockRange>,
+    /// Every block, when there are several to choose from; `contents` is then the
+    /// whole document.
+    pub blocks: Vec<MermaidBlock>,
+}
+
+/// The diagram to edit from a file opened as Mermaid. Markdown files give the body
+/// of their only mermaid block, or all of them to choose from; one with no block
+/// fails with `NoMermaidBlock`, unless it is a bare diagram that merely has an `.md`
+/// extension. Other files are the diagram as they are.
+pub fn mermaid_source(path: &Path, contents: String) -> Result<MermaidSource, CommandError> {
+    let whole = |contents| MermaidSource {
+        contents,
+        block_range: None,
+        blocks: Vec::new(),
+    };
+    if !is_markdown(path) {
+        return Ok(whole(contents));
+    }
+    let mut blocks = find_blocks(&contents);
+    match blocks.len() {
+        0 if mermaid_diagram_type(&contents).is_some() => Ok(whole(contents)),
+        0 => Err(CommandError::NoMermaidBlock {
+            path: path.to_string_lossy().to_string(),
+        }),
+        1 => {
+            let block = blocks.remove(0);
+            Ok(MermaidSource {
+                contents: block.contents,
+                block_range: Some(block.range),
+                blocks: Vec::new(),
+            })
+        }
+        _ => Ok(MermaidSource {
+            contents,
+            block_range: None,
+            blocks,
+        }),
+    }
+}
+
+/// `markdown` with the body of the block at `range` replaced by `diagram`, leaving
+/// everything around it as it was. The block must still be where it was opened,
+/// otherwise the file changed in between and the save is refused.
+pub fn splice(
+    path: &Path,
+    markdown: &str,
+    range: BlockRange,
+    diagram: &str,
+) -> Result<String, CommandError> {
+    if !find_blocks(markdown)
+        .iter()
+        .any(|block| block.range == range)
+    {
+        return Err(CommandError::InvalidArgument {
+            message: format!(
+                "lines {}-{} of {} are no longer a mermaid block",
+                range.start + 1,
+                range.end,
+                path.display()
+            ),
+        });
+    }
+    let line_ending = if markdown.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut spliced: Vec<String> = Vec::new();
+    let mut source = markdown.split_inclusive('\n');
+    spliced.extend(source.by_ref().take(range.start).map(str::to_string));
+    let diagram = diagram.trim_end_matches(['\r', '\n']);
+    if !diagram.is_empty() {
+        for line in lines(diagram) {
+            spliced.push(format!("{line}{line_ending}"));
+        }
+    }
+    spliced.extend(source.skip(range.end - range.start).map(str::to_string));
+    Ok(spliced.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = "# Design\n\nSome prose.\n\n```mermaid\nflowchart LR\n  A --> B\n```\n\n```js\nconst a = 1\n```\n\n~~~~ mermaid {theme: dark}\nsequenceDiagram\n  A->>B: hi\n~~~~\n\nThe end.\n";
+
+    #[test]
+    fn finds_each_mermaid_block_with_its_lines() {
+        let blocks = find_blocks(NOTES);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].contents, "flowchart LR\n  A --> B\n");
+        assert_eq!(blocks[0].range, BlockRange { start: 5, end: 7 });
+        assert_eq!(blocks[1].contents, "sequenceDiagram\n  A->>B: hi\n");
+        assert_eq!(blocks[1].range, BlockRange { start: 14, end: 16 });
+
+        assert!(find_blocks("```mermaidjs\ngraph TD\n```\n    ```mermaid\n").is_empty());
+        let unclosed = find_blocks("```mermaid\r\ngraph TD\r\n");
+        assert_eq!(unclosed[0].contents, "graph TD\n");
+        assert_eq!(unclosed[0].range, BlockRange { start: 1, end: 2 });
+        assert_eq!(find_blocks("```mermaid\n```\n")[0].contents, "");
+    }
+
+    #[test]
+    fn markdown_opens_as_its_mermaid_blocks() {
+        let path = Path::new("/work/notes.md");
+        let one = mermaid_source(path, "Intro\n```mermaid\ngraph TD\n```\n".to_string()).unwrap();
+        assert_eq!(one.contents, "graph TD\n");
+        assert_eq!(one.block_range, Some(BlockRange { start: 2, end: 3 }));
+
+        let several = mermaid_source(path, NOTES.to_string()).unwrap();
+        assert_eq!(
+            (several.contents.as_str(), several.blocks.len()),
+            (NOTES, 2)
+        );
+        assert_eq!(several.block_range, None);
+
+        let bare = mermaid_source(path, "flowchart LR\n  A --> B\n".to_string()).unwrap();
+        assert_eq!(bare.contents, "flowchart LR\n  A --> B\n");
+        match mermaid_source(path, "# Just prose\n".to_string()) {
+            Err(CommandError::NoMermaidBlock { path }) => assert_eq!(path, "/work/notes.md"),
+            other => panic!("expected NoMermaidBlock, got {other:?}"),
+        }
+        // Only Markdown is searched for blocks.
+        let text = mermaid_source(Path::new("/work/notes.txt"), NOTES.to_string()).unwrap();
+        assert!(text.blocks.is_empty());
+    }
+
+    #[test]
+    fn splices_a_block_without_touching_the_prose() {
+        let path = Path::new("/work/notes.md");
+        let range = BlockRange { start: 14, end: 16 };
+        let saved = splice(
+            path,
+            NOTES,
+            range,
+            "sequenceDiagram\n  A->>B: hi\n  B->>A: hello",
+        )
+        .unwrap();
+        assert_eq!(
+            saved,
+            NOTES.replace("  A->>B: hi\n~~~~", "  A->>B: hi\n  B->>A: hello\n~~~~")
+        );
+        assert_eq!(
+            find_blocks(&saved)[1].range,
+            BlockRange { start: 14, end: 17 }
+        );
+
+        let crlf = "Intro\r\n```mermaid\r\ngraph TD\r\n```\r\nOutro";
+        assert_eq!(
+            splice(
+                path,
+                crlf,
+                BlockRange { start: 2, end: 3 },
+                "graph LR\n  A --> B\n"
+            )
+            .unwrap(),
+            "Intro\r\n```mermaid\r\ngraph LR\r\n  A --> B\r\n```\r\nOutro"
+        );
+
+        let stale = splice(path, NOTES, BlockRange { start: 2, end: 4 }, "graph TD");
+        assert_eq!(stale.unwrap_err().code(), "invalid_argument");
+    }
+}
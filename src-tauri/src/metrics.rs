@@ -0,0 +1,255 @@
+use crate::now_epoch;
+use crate::settings::Settings;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+/// How many of the most recent command invocations are kept.
+const RING_SIZE: usize = 500;
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CommandSample {
+    command: &'static str,
+    /// When the command started, in epoch seconds.
+    at: u64,
+    duration_micros: u64,
+    /// Document bytes read or written.
+    payload_bytes: u64,
+    ok: bool,
+}
+
+/// Timings of recent commands, for troubleshooting reports like "saving is slow".
+/// Recording takes a slot in a ring allocated up front, so it costs a lock and a copy.
+pub struct CommandMetrics {
+    enabled: AtomicBool,
+    ring: Mutex<Ring>,
+}
+
+struct Ring {
+    samples: Vec<CommandSample>,
+    /// Slot the next sample overwrites once the ring is full.
+    next: usize,
+}
+
+impl Default for CommandMetrics {
+    fn default() -> Self {
+        CommandMetrics {
+            enabled: AtomicBool::new(true),
+            ring: Mutex::new(Ring {
+                samples: Vec::with_capacity(RING_SIZE),
+                next: 0,
+            }),
+        }
+    }
+}
+
+impl Ring {
+    fn push(&mut self, sample: CommandSample) {
+        if self.samples.len() < RING_SIZE {
+            self.samples.push(sample);
+        } else {
+            self.samples[self.next] = sample;
+            self.next = (self.next + 1) % RING_SIZE;
+        }
+    }
+
+    /// Oldest first.
+    fn ordered(&self) -> Vec<CommandSample> {
+        let (newer, older) = self.samples.split_at(self.next);
+        older.iter().chain(newer).copied().collect()
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CommandSummary {
+    command: &'static str,
+    count: usize,
+    errors: usize,
+    error_rate: f64,
+    p50_micros: u64,
+    p95_micros: u64,
+}
+
+#[derive(Serialize)]
+pub struct CommandMetricsReport {
+    enabled: bool,
+    commands: Vec<CommandSummary>,
+    /// Oldest first.
+    recent: Vec<CommandSample>,
+}
+
+/// Nearest-rank percentile of sorted `durations`.
+fn percentile(durations: &[u64], percent: usize) -> u64 {
+    if durations.is_empty() {
+        return 0;
+    }
+    let rank = (durations.len() * percent).div_ceil(100).max(1);
+    durations[rank - 1]
+}
+
+fn summarize(samples: &[CommandSample]) -> Vec<CommandSummary> {
+    let mut by_command: BTreeMap<&'static str, Vec<&CommandSample>> = BTreeMap::new();
+    for sample in samples {
+        by_command.entry(sample.command).or_default().push(sample);
+    }
+    by_command
+        .into_iter()
+        .map(|(command, samples)| {
+            let mut durations: Vec<u64> = samples
+                .iter()
+                .map(|sample| sample.duration_micros)
+                .collect();
+            durations.sort_unstable();
+            let errors = samples.iter().filter(|sample| !sample.ok).count();
+            CommandSummary {
+                command,
+                count: samples.len(),
+                errors,
+                error_rate: errors as f64 / samples.len() as f64,
+                p50_micros: percentile(&durations, 50),
+                p95_micros: percentile(&durations, 95),
+            }
+        })
+        .collect()
+}
+
+/// Times one command invocation and records it when dropped. It counts as failed
+/// unless it ends through `finish` with an `Ok`, so early returns through `?` are
+/// recorded as errors.
+pub struct Timer {
+    app: AppHandle,
+    command: &'static str,
+    at: u64,
+    started: Instant,
+    payload_bytes: usize,
+    ok: bool,
+}
+
+impl Timer {
+    pub fn start(app: &AppHandle, command: &'static str, payload_bytes: usize) -> Self {
+        Timer {
+            app: app.clone(),
+            command,
+            at: now_epoch(),
+            started: Instant::now(),
+            payload_bytes,
+            ok: false,
+        }
+    }
+
+    /// Sets the payload size once it is known, e.g. the bytes an open read.
+    pub fn payload_bytes(&mut self, payload_bytes: usize) {
+        self.payload_bytes = payload_bytes;
+    }
+
+    /// Records the command's outcome and passes its result through.
+    pub fn finish<T, E>(mut self, result: Result<T, E>) -> Result<T, E> {
+        self.ok = result.is_ok();
+        result
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let metrics = self.app.state::<CommandMetrics>();
+        if !metrics.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let sample = CommandSample {
+            command: self.command,
+            at: self.at,
+            duration_micros: self.started.elapsed().as_micros() as u64,
+            payload_bytes: self.payload_bytes as u64,
+            ok: self.ok,
+        };
+        metrics.ring.lock().unwrap().push(sample);
+    }
+}
+
+/// Follows the `collect_command_metrics` setting. Turning it off also drops what
+/// was collected.
+pub fn apply_settings(app: &AppHandle, settings: &Settings) {
+    let metrics = app.state::<CommandMetrics>();
+    metrics
+        .enabled
+        .store(settings.collect_command_metrics, Ordering::Relaxed);
+    if !settings.collect_command_metrics {
+        let mut ring = metrics.ring.lock().unwrap();
+        ring.samples.clear();
+        ring.next = 0;
+    }
+}
+
+/// Per-command aggregates for the diagnostics in `get_app_info`.
+pub fn summaries(app: &AppHandle) -> Vec<CommandSummary> {
+    summarize(&app.state::<CommandMetrics>().ring.lock().unwrap().samples)
+}
+
+/// Count, median and 95th percentile duration, and error rate per command over the
+/// last invocations, plus the invocations themselves.
+#[tauri::command]
+pub fn get_command_metrics(app: AppHandle) -> CommandMetricsReport {
+    let metrics = app.state::<CommandMetrics>();
+    let recent = metrics.ring.lock().unwrap().ordered();
+    CommandMetricsReport {
+        enabled: metrics.enabled.load(Ordering::Relaxed),
+        commands: summarize(&recent),
+        recent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(command: &'static str, duration_micros: u64, ok: bool) -> CommandSample {
+        CommandSample {
+            command,
+            at: 0,
+            duration_micros,
+            payload_bytes: 0,
+            ok,
+        }
+    }
+
+    #[test]
+    fn the_ring_keeps_the_latest_invocations_in_order() {
+        let mut ring = Ring {
+            samples: Vec::with_capacity(RING_SIZE),
+            next: 0,
+        };
+        for duration in 0..RING_SIZE as u64 + 2 {
+            ring.push(sample("open_path", duration, true));
+        }
+        assert_eq!(ring.samples.capacity(), RING_SIZE);
+        let ordered = ring.ordered();
+        assert_eq!(ordered.len(), RING_SIZE);
+        assert_eq!(ordered[0].duration_micros, 2);
+        assert_eq!(ordered[RING_SIZE - 1].duration_micros, RING_SIZE as u64 + 1);
+    }
+
+    #[test]
+    fn summarizes_durations_and_errors_per_command() {
+        let mut samples: Vec<CommandSample> = (1..=20)
+            .map(|duration| sample("save_mermaid_file", duration * 1000, duration != 7))
+            .collect();
+        samples.push(sample("open_path", 500, true));
+
+        let summaries = summarize(&samples);
+        assert_eq!(summaries[0].command, "open_path");
+        assert_eq!(
+            summaries[1],
+            CommandSummary {
+                command: "save_mermaid_file",
+                count: 20,
+                errors: 1,
+                error_rate: 0.05,
+                p50_micros: 10_000,
+                p95_micros: 19_000,
+            }
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::error::CommandError;
+use crate::paths::comparison_key;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// The documents each window has open, so a file isn't edited in two windows that
+/// would overwrite each other's saves without anyone noticing.
+#[derive(Default)]
+pub struct OpenDocuments(Mutex<Registry>);
+
+/// Per window label, the file open in each editor, by kind. A window shows one
+/// document per kind, so opening or saving another replaces the entry.
+#[derive(Default)]
+struct Registry {
+    windows: HashMap<String, HashMap<String, PathBuf>>,
+}
+
+/// Payload of `document-saved-in-other-window`.
+#[derive(Serialize, Clone)]
+struct SavedInOtherWindow {
+    path: String,
+    window: String,
+}
+
+impl Registry {
+    /// Windows other than `label` that have the file with comparison key `key` open.
+    fn holders(&self, key: &Path, label: &str) -> Vec<String> {
+        let mut holders: Vec<String> = self
+            .windows
+            .iter()
+            .filter(|(other, documents)| {
+                *other != label && documents.values().any(|open| open == key)
+            })
+            .map(|(other, _)| other.clone())
+            .collect();
+        holders.sort();
+        holders
+    }
+
+    fn open(&mut self, label: &str, kind: &str, key: PathBuf) {
+        self.windows
+            .entry(label.to_string())
+            .or_default()
+            .insert(kind.to_string(), key);
+    }
+}
+
+/// Fails with `AlreadyOpen` when another window has `path` open, unless
+/// `allow_duplicate`; then the frontend offers to focus that window instead.
+/// Paths are compared by `paths::comparison_key`, so a symlink or a differently
+/// cased path to the same file counts.
+pub fn check_not_open_elsewhere(
+    app: &AppHandle,
+    label: &str,
+    path: &Path,
+    allow_duplicate: bool,
+) -> Result<(), CommandError> {
+    let holders = app
+        .state::<OpenDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .holders(&comparison_key(path), label);
+    match holders.into_iter().next() {
+        Some(window) if !allow_duplicate => Err(CommandError::AlreadyOpen {
+            path: path.to_string_lossy().to_string(),
+            window,
+        }),
+        Some(window) => {
+            log::info!(
+                "open_documents: {} is open in {} too; opening it again in {}",
+                path.display(),
+                window,
+                label
+            );
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Records that window `label` shows `path` in its `kind` editor, after an open
+/// succeeded.
+pub fn register(app: &AppHandle, label: &str, kind: &str, path: &Path) {
+    app.state::<OpenDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .open(label, kind, comparison_key(path));
+}
+
+/// Called after window `label` saved `path`. Every other window with the file open
+/// gets `document-saved-in-other-window`, since its copy is now out of date; their
+/// labels are returned so the saving window can warn as well.
+pub fn after_save(app: &AppHandle, label: &str, kind: &str, path: &Path) -> Vec<String> {
+    let key = comparison_key(path);
+    let holders = {
+        let mut registry = app.state::<OpenDocuments>().0.lock().unwrap();
+        registry.open(label, kind, key.clone());
+        registry.holders(&key, label)
+    };
+    let path_string = path.to_string_lossy().to_string();
+    for holder in &holders {
+        let _ = app.emit_to(
+            holder.as_str(),
+            "document-saved-in-other-window",
+            SavedInOtherWindow {
+                path: path_string.clone(),
+                window: label.to_string(),
+            },
+        );
+    }
+    holders
+}
+
+/// Forgets what a closed window had open.
+pub fn forget_window(app: &AppHandle, label: &str) {
+    app.state::<OpenDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .windows
+        .remove(label);
+}
+
+/// Brings window `label` to the front, e.g. the one an `AlreadyOpen` error named.
+#[tauri::command]
+pub fn focus_window(app: AppHandle, label: String) -> Result<(), CommandError> {
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| CommandError::InvalidArgument {
+            message: format!("No window \"{label}\""),
+        })?;
+    let _ = window.unminimize();
+    window.set_focus().map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_other_windows_holding_a_file() {
+        let mut registry = Registry::default();
+        let flow = PathBuf::from("/work/flow.mmd");
+        registry.open("main", "mermaid", flow.clone());
+        registry.open("main", "excalidraw", PathBuf::from("/work/a.excalidraw"));
+        assert!(registry.holders(&flow, "main").is_empty());
+
+        registry.open("workspace-2", "mermaid", flow.clone());
+        assert_eq!(registry.holders(&flow, "main"), vec!["workspace-2"]);
+        assert_eq!(registry.holders(&flow, "workspace-2"), vec!["main"]);
+
+        // Opening another mermaid file in a window replaces the one it had.
+        registry.open("workspace-2", "mermaid", PathBuf::from("/work/other.mmd"));
+        assert!(registry.holders(&flow, "main").is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::CommandError;
+use crate::{
+    dialogs, load_excalidraw_path, load_mermaid_path, OpenFileResponse, EXCALIDRAW_OPEN_EXTENSIONS,
+    MERMAID_EXTENSIONS,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{AppHandle, WebviewWindow};
+
+#[derive(Serialize, Default)]
+pub struct OpenFilesResponse {
+    /// The files that opened, in the order they were selected.
+    files: Vec<OpenFileResponse>,
+    /// The selected files that couldn't be opened.
+    errors: Vec<OpenFileFailure>,
+}
+
+#[derive(Serialize)]
+struct OpenFileFailure {
+    path: String,
+    error: CommandError,
+}
+
+/// Opens each of `paths` as `kind`. One failing doesn't stop the others; its error
+/// is reported next to the files that did open.
+async fn open_all(
+    app: AppHandle,
+    window: WebviewWindow,
+    kind: &str,
+    paths: Vec<PathBuf>,
+) -> OpenFilesResponse {
+    let mut response = OpenFilesResponse::default();
+    // Opened last to first: each open moves its file to the top of recents, so the
+    // first selected file ends up most recent.
+    for path in paths.into_iter().rev() {
+        let path = path.to_string_lossy().to_string();
+        let opened = match kind {
+            "excalidraw" => {
+                load_excalidraw_path(
+                    app.clone(),
+                    window.clone(),
+                    path.clone(),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+            }
+            _ => {
+                load_mermaid_path(app.clone(), window.clone(), path.clone(), None, None, None).await
+            }
+        };
+        match opened {
+            Ok(file) => response.files.push(file),
+            Err(error) => {
+                log::warn!("open_files: {} failed: {}", path, error);
+                response.errors.push(OpenFileFailure { path, error });
+            }
+        }
+    }
+    response.files.reverse();
+    response.errors.reverse();
+    response
+}
+
+/// Lets the user pick several Excalidraw files and opens them all. Cancelling the
+/// dialog returns no files.
+#[tauri::command]
+pub async fn open_excalidraw_files(
+    app: AppHandle,
+    window: WebviewWindow,
+) -> Result<OpenFilesResponse, CommandError> {
+    let paths = dialogs::pick_open_paths(&app, "Excalidraw", EXCALIDRAW_OPEN_EXTENSIONS).await?;
+    Ok(open_all(app, window, "excalidraw", paths).await)
+}
+
+/// Lets the user pick several Mermaid files and opens them all. Cancelling the
+/// dialog returns no files.
+#[tauri::command]
+pub async fn open_mermaid_files(
+    app: AppHandle,
+    window: WebviewWindow,
+) -> Result<OpenFilesResponse, CommandError> {
+    let paths = dialogs::pick_open_paths(&app, "Mermaid", MERMAID_EXTENSIONS).await?;
+    Ok(open_all(app, window, "mermaid", paths).await)
+}
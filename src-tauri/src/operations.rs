@@ -0,0 +1,262 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use tauri_plugin_notification::NotificationExt;
+
+/// Event every long-running operation reports its progress through.
+pub const PROGRESS_EVENT: &str = "operation-progress";
+/// Operations quicker than this never post a notification.
+const MIN_NOTIFY_DURATION: Duration = Duration::from_secs(5);
+
+/// Shared flag a running operation polls to find out it should stop.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Payload of the `operation-progress` event.
+///
+/// `phase` is operation specific ("scanning", "writing", ...) until the final event,
+/// whose phase is "finished", "cancelled" or "failed". `total` is `None` while the
+/// amount of work isn't known yet.
+#[derive(Serialize, Clone, Debug)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    pub kind: &'static str,
+    pub phase: String,
+    pub done: u64,
+    pub total: Option<u64>,
+    pub current_item: Option<String>,
+    pub cancellable: bool,
+}
+
+/// Result of an operation that finished in the background, handed to the frontend
+/// as `operation-report` when a window is focused again.
+#[derive(Serialize, Clone)]
+pub struct OperationReport {
+    operation_id: String,
+    kind: &'static str,
+    summary: String,
+    details: Value,
+}
+
+/// Cancellation tokens of the operations that are still running, keyed by id, and
+/// reports of the ones that finished while the app was in the background.
+#[derive(Default)]
+pub struct Operations {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+    reports: Mutex<Vec<OperationReport>>,
+    next_id: AtomicU64,
+}
+
+type Reporter = Box<dyn Fn(&OperationProgress) + Send + Sync>;
+
+/// Handle a long-running task uses to report progress and notice cancellation.
+/// Dropping it unregisters the operation.
+pub struct Operation {
+    pub id: String,
+    pub kind: &'static str,
+    cancellable: bool,
+    token: CancellationToken,
+    reporter: Reporter,
+    app: Option<AppHandle>,
+    started_at: Instant,
+    notify: bool,
+}
+
+impl Operation {
+    /// Registers an operation and emits its progress as `operation-progress` events.
+    /// A caller-supplied `id` lets the frontend cancel it before the first event.
+    pub fn start(
+        app: &AppHandle,
+        kind: &'static str,
+        id: Option<String>,
+        cancellable: bool,
+    ) -> Operation {
+        let operations = app.state::<Operations>();
+        let id = id.unwrap_or_else(|| {
+            format!(
+                "{kind}-{}",
+                operations.next_id.fetch_add(1, Ordering::Relaxed)
+            )
+        });
+        let token = CancellationToken::default();
+        operations
+            .tokens
+            .lock()
+            .unwrap()
+            .insert(id.clone(), token.clone());
+
+        let emitter = app.clone();
+        Operation {
+            id,
+            kind,
+            cancellable,
+            token,
+            reporter: Box::new(move |progress| {
+                let _ = emitter.emit(PROGRESS_EVENT, progress);
+            }),
+            app: Some(app.clone()),
+            started_at: Instant::now(),
+            notify: false,
+        }
+    }
+
+    /// Posts a notification when the operation finishes while no window has focus.
+    /// `None` falls back to the `notify_when_done` setting.
+    pub fn notify_when_done(mut self, requested: Option<bool>) -> Operation {
+        self.notify = match &self.app {
+            Some(app) => requested.unwrap_or_else(|| load_settings(app).notify_when_done),
+            None => false,
+        };
+        self
+    }
+
+    /// An operation that isn't registered with the app, reporting to `reporter`.
+    #[cfg(test)]
+    pub fn detached(
+        kind: &'static str,
+        token: CancellationToken,
+        reporter: impl Fn(&OperationProgress) + Send + Sync + 'static,
+    ) -> Operation {
+        Operation {
+            id: format!("{kind}-detached"),
+            kind,
+            cancellable: true,
+            token,
+            reporter: Box::new(reporter),
+            app: None,
+            started_at: Instant::now(),
+            notify: false,
+        }
+    }
+
+    pub fn progress(&self, phase: &str, done: u64, total: Option<u64>, current_item: Option<&str>) {
+        (self.reporter)(&OperationProgress {
+            operation_id: self.id.clone(),
+            kind: self.kind,
+            phase: phase.to_string(),
+            done,
+            total,
+            current_item: current_item.map(str::to_string),
+            cancellable: self.cancellable,
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellable && self.token.is_cancelled()
+    }
+
+    /// Returns `CommandError::Cancelled` once cancellation has been requested.
+    pub fn check(&self) -> Result<(), CommandError> {
+        if self.is_cancelled() {
+            Err(CommandError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Emits the final event for `outcome`.
+    pub fn finish<T>(&self, outcome: &Result<T, CommandError>, done: u64) {
+        let phase = match outcome {
+            Ok(_) => "finished",
+            Err(CommandError::Cancelled) => "cancelled",
+            Err(_) => "failed",
+        };
+        self.progress(phase, done, Some(done), None);
+    }
+
+    /// Tells the user about a finished operation with a system notification showing
+    /// `summary`, if they asked for one, it ran for a while, do-not-disturb is off and
+    /// no window has focus. `details` are kept for `operation-report`.
+    pub fn notify_done(&self, summary: &str, details: impl Serialize) {
+        let Some(app) = &self.app else {
+            return;
+        };
+        if !self.notify
+            || self.started_at.elapsed() < MIN_NOTIFY_DURATION
+            || load_settings(app).do_not_disturb
+        {
+            return;
+        }
+        let focused = app
+            .webview_windows()
+            .values()
+            .any(|window| window.is_focused().unwrap_or(false));
+        if focused {
+            return;
+        }
+
+        app.state::<Operations>()
+            .reports
+            .lock()
+            .unwrap()
+            .push(OperationReport {
+                operation_id: self.id.clone(),
+                kind: self.kind,
+                summary: summary.to_string(),
+                details: serde_json::to_value(details).unwrap_or_default(),
+            });
+        if let Err(error) = app
+            .notification()
+            .builder()
+            .title("Excalibur")
+            .body(summary)
+            .show()
+        {
+            log::warn!("notify_done: notification failed: {}", error);
+        }
+    }
+}
+
+/// Sends the reports of operations that finished in the background to a window that
+/// just gained focus, e.g. because the user clicked the notification.
+pub fn deliver_reports(window: &Window) {
+    let app = window.app_handle();
+    let reports = std::mem::take(&mut *app.state::<Operations>().reports.lock().unwrap());
+    for report in reports {
+        let _ = window.emit_to(window.label(), "operation-report", report);
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if let Some(app) = &self.app {
+            app.state::<Operations>()
+                .tokens
+                .lock()
+                .unwrap()
+                .remove(&self.id);
+        }
+    }
+}
+
+/// Asks a running operation to stop. Returns false when no operation with that id
+/// is running. The operation cleans up its partial output before it finishes.
+#[tauri::command]
+pub fn cancel_operation(app: AppHandle, id: String) -> bool {
+    let operations = app.state::<Operations>();
+    let tokens = operations.tokens.lock().unwrap();
+    match tokens.get(&id) {
+        Some(token) => {
+            log::info!("cancel_operation: {}", id);
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
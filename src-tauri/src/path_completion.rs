@@ -0,0 +1,208 @@
+use crate::crypto::{encrypted_kind, is_encrypted_bytes, is_encrypted_path};
+use crate::detect::detect_kind;
+use crate::scan::kind_for_extension;
+use crate::{EXCALIDRAW_EXTENSIONS, MERMAID_EXTENSIONS};
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use tauri::{AppHandle, Manager};
+
+/// Most completions returned for one request.
+const MAX_COMPLETIONS: usize = 50;
+/// Directory entries looked at before giving up, so huge folders stay fast.
+const MAX_SCANNED_ENTRIES: usize = 5000;
+
+#[derive(Serialize)]
+pub struct PathEntry {
+    name: String,
+    path: String,
+    is_dir: bool,
+    kind: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct PathCompletion {
+    /// The directory that was listed, after `~` expansion.
+    directory: String,
+    entries: Vec<PathEntry>,
+    /// More entries matched (or remained unscanned) than were returned.
+    truncated: bool,
+    /// The directory could not be read for lack of permission.
+    permission_denied: bool,
+}
+
+#[derive(Serialize)]
+pub struct OpenableCheck {
+    path: String,
+    openable: bool,
+    kind: Option<&'static str>,
+    reason: Option<String>,
+}
+
+fn home(app: &AppHandle) -> Option<PathBuf> {
+    app.path().home_dir().ok()
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand_tilde(app: &AppHandle, partial: &str) -> PathBuf {
+    let rest = match partial.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(partial),
+    };
+    match home(app) {
+        Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(partial),
+    }
+}
+
+fn is_diagram_file(path: &Path) -> bool {
+    if is_encrypted_path(path) {
+        return true;
+    }
+    let Some(extension) = path.extension() else {
+        return false;
+    };
+    let extension = extension.to_string_lossy().to_lowercase();
+    EXCALIDRAW_EXTENSIONS
+        .iter()
+        .chain(MERMAID_EXTENSIONS)
+        .any(|candidate| *candidate == extension)
+}
+
+/// Lists the entries of the typed path's directory that start with the typed name,
+/// directories first. Matching is case-insensitive and dotfiles only show up once
+/// the typed name starts with a dot.
+#[tauri::command]
+pub fn complete_path(app: AppHandle, partial: String, kinds_only: bool) -> PathCompletion {
+    let expanded = expand_tilde(&app, &partial);
+    let ends_with_separator = partial.ends_with(['/', '\\']) || partial == "~";
+    let (directory, prefix) = if ends_with_separator {
+        (expanded, String::new())
+    } else {
+        let prefix = expanded
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let directory = expanded
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(MAIN_SEPARATOR.to_string()));
+        (directory, prefix)
+    };
+    let directory = if directory.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        directory
+    };
+
+    let mut completion = PathCompletion {
+        directory: directory.to_string_lossy().to_string(),
+        entries: Vec::new(),
+        truncated: false,
+        permission_denied: false,
+    };
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            completion.permission_denied = error.kind() == ErrorKind::PermissionDenied;
+            return completion;
+        }
+    };
+
+    let prefix = prefix.to_lowercase();
+    let show_hidden = prefix.starts_with('.');
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for (scanned, entry) in entries.flatten().enumerate() {
+        if scanned >= MAX_SCANNED_ENTRIES {
+            completion.truncated = true;
+            break;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.to_lowercase().starts_with(&prefix) || (name.starts_with('.') && !show_hidden) {
+            continue;
+        }
+        let path = entry.path();
+        // `metadata` follows a symlink one hop; a link loop just errors and is skipped.
+        // Nothing here recurses, so cycles can't trap the listing.
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        let is_dir = metadata.is_dir();
+        if !is_dir && kinds_only && !is_diagram_file(&path) {
+            continue;
+        }
+        let entry = PathEntry {
+            kind: if is_dir {
+                None
+            } else {
+                kind_for_extension(&path)
+            },
+            path: path.to_string_lossy().to_string(),
+            name,
+            is_dir,
+        };
+        if is_dir {
+            dirs.push(entry);
+        } else {
+            files.push(entry);
+        }
+    }
+
+    dirs.sort_by_key(|entry| entry.name.to_lowercase());
+    files.sort_by_key(|entry| entry.name.to_lowercase());
+    let total = dirs.len() + files.len();
+    completion.truncated |= total > MAX_COMPLETIONS;
+    completion.entries = dirs
+        .into_iter()
+        .chain(files)
+        .take(MAX_COMPLETIONS)
+        .collect();
+    completion
+}
+
+#[tauri::command]
+pub fn home_dir(app: AppHandle) -> Option<String> {
+    home(&app).map(|home| home.to_string_lossy().to_string())
+}
+
+/// Checks that a typed path can be opened, and as which kind, before trying to open it.
+#[tauri::command]
+pub fn validate_openable(app: AppHandle, path: String) -> OpenableCheck {
+    let expanded = expand_tilde(&app, &path);
+    let mut check = OpenableCheck {
+        path: expanded.to_string_lossy().to_string(),
+        openable: false,
+        kind: None,
+        reason: None,
+    };
+    match fs::metadata(&expanded) {
+        Ok(metadata) if metadata.is_dir() => {
+            check.reason = Some("This is a folder".to_string());
+            return check;
+        }
+        Ok(_) => {}
+        Err(error) => {
+            check.reason = Some(error.to_string());
+            return check;
+        }
+    }
+    let bytes = match fs::read(&expanded) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            check.reason = Some(error.to_string());
+            return check;
+        }
+    };
+    check.kind = if is_encrypted_bytes(&bytes) {
+        encrypted_kind(&bytes).ok()
+    } else {
+        detect_kind(&expanded, &String::from_utf8_lossy(&bytes))
+    };
+    check.openable = check.kind.is_some();
+    if !check.openable {
+        check.reason = Some("Not an Excalidraw scene or a Mermaid diagram".to_string());
+    }
+    check
+}
@@ -0,0 +1,489 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// File next to the executable that switches the app into portable mode.
+pub const PORTABLE_MARKER: &str = "portable";
+/// Command-line flag that switches the app into portable mode.
+pub const PORTABLE_FLAG: &str = "--portable";
+/// Environment variable that switches the app into portable mode when set to anything but "0".
+pub const PORTABLE_ENV: &str = "EXCALIBUR_PORTABLE";
+/// Directory created beside the executable to hold all data in portable mode.
+pub const PORTABLE_DATA_DIR: &str = "excalibur-data";
+
+/// Where the app keeps its data for this run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataMode {
+    /// The platform app data directory (e.g. `%APPDATA%`, `~/Library/Application Support`).
+    Standard,
+    /// `<exe dir>/excalibur-data`, for running from removable media.
+    Portable { exe_dir: PathBuf },
+}
+
+impl DataMode {
+    /// Portable mode is enabled by a marker file beside the executable, the
+    /// `--portable` flag, or the `EXCALIBUR_PORTABLE` environment variable.
+    pub fn detect(exe_dir: Option<&Path>, args: &[String], env_value: Option<&str>) -> DataMode {
+        let Some(exe_dir) = exe_dir else {
+            return DataMode::Standard;
+        };
+        let requested = exe_dir.join(PORTABLE_MARKER).is_file()
+            || args.iter().any(|arg| arg == PORTABLE_FLAG)
+            || env_value.is_some_and(|value| !value.is_empty() && value != "0");
+        if requested {
+            DataMode::Portable {
+                exe_dir: exe_dir.to_path_buf(),
+            }
+        } else {
+            DataMode::Standard
+        }
+    }
+
+    /// Resolves the data root for this mode. `standard_dir` is the platform app
+    /// data directory, which is ignored in portable mode.
+    pub fn data_root(&self, standard_dir: Option<PathBuf>) -> PathBuf {
+        match self {
+            DataMode::Standard => standard_dir.unwrap_or_else(|| PathBuf::from(".")),
+            DataMode::Portable { exe_dir } => exe_dir.join(PORTABLE_DATA_DIR),
+        }
+    }
+
+    pub fn is_portable(&self) -> bool {
+        matches!(self, DataMode::Portable { .. })
+    }
+}
+
+/// Command-line flag that points the app at an alternate data directory.
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+/// Environment variable that points the app at an alternate data directory.
+pub const DATA_DIR_ENV: &str = "EXCALIBUR_DATA_DIR";
+
+/// Which setting decided the data directory, in order of precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataSource {
+    Flag,
+    Env,
+    Portable,
+    Default,
+}
+
+impl DataSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DataSource::Flag => "flag",
+            DataSource::Env => "env",
+            DataSource::Portable => "portable",
+            DataSource::Default => "default",
+        }
+    }
+}
+
+/// The value of `--data-dir <path>` or `--data-dir=<path>`, if given.
+pub fn data_dir_flag(args: &[String]) -> Option<&str> {
+    args.iter().enumerate().find_map(|(index, arg)| {
+        if arg == DATA_DIR_FLAG {
+            args.get(index + 1).map(String::as_str)
+        } else {
+            arg.strip_prefix(DATA_DIR_FLAG)
+                .and_then(|rest| rest.strip_prefix('='))
+        }
+    })
+}
+
+/// Picks the data directory: the `--data-dir` flag, then `EXCALIBUR_DATA_DIR`, then
+/// portable mode, then the platform default. Relative overrides resolve against `cwd`.
+pub fn choose_data_root(
+    args: &[String],
+    env_data_dir: Option<&str>,
+    mode: &DataMode,
+    standard_dir: Option<PathBuf>,
+    cwd: &Path,
+) -> (DataSource, PathBuf) {
+    let absolute = |path: &str| cwd.join(path);
+    if let Some(path) = data_dir_flag(args).filter(|path| !path.is_empty()) {
+        return (DataSource::Flag, absolute(path));
+    }
+    if let Some(path) = env_data_dir.filter(|path| !path.is_empty()) {
+        return (DataSource::Env, absolute(path));
+    }
+    let source = if mode.is_portable() {
+        DataSource::Portable
+    } else {
+        DataSource::Default
+    };
+    (source, mode.data_root(standard_dir))
+}
+
+/// Creates `path` if needed and checks a file can be written inside it.
+pub fn ensure_writable(path: &Path) -> Result<(), String> {
+    fs::create_dir_all(path)
+        .map_err(|error| format!("cannot create data directory {}: {error}", path.display()))?;
+    let probe = path.join(".excalibur-write-test");
+    fs::write(&probe, b"")
+        .map_err(|error| format!("data directory {} is not writable: {error}", path.display()))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// The data root resolved at startup. Every data path is built from this, so it
+/// stays fixed for the life of the process.
+pub struct DataRoot {
+    pub source: DataSource,
+    pub path: PathBuf,
+}
+
+impl DataRoot {
+    pub fn resolve(app: &AppHandle) -> Result<DataRoot, String> {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let args: Vec<String> = std::env::args().collect();
+        let portable_env = std::env::var(PORTABLE_ENV).ok();
+        let mode = DataMode::detect(exe_dir.as_deref(), &args, portable_env.as_deref());
+        let env_data_dir = std::env::var(DATA_DIR_ENV).ok();
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let (source, path) = choose_data_root(
+            &args,
+            env_data_dir.as_deref(),
+            &mode,
+            app.path().app_data_dir().ok(),
+            &cwd,
+        );
+
+        ensure_writable(&path)?;
+        log::info!(
+            "data directory: {} (from {})",
+            path.display(),
+            source.as_str()
+        );
+        Ok(DataRoot { source, path })
+    }
+}
+
+/// The single resolver for the app data directory.
+pub fn data_dir(app: &AppHandle) -> PathBuf {
+    match app.try_state::<DataRoot>() {
+        Some(root) => root.path.clone(),
+        None => DataMode::Standard.data_root(app.path().app_data_dir().ok()),
+    }
+}
+
+/// Which setting decided the data directory for this run.
+pub fn data_source(app: &AppHandle) -> DataSource {
+    app.try_state::<DataRoot>()
+        .map_or(DataSource::Default, |root| root.source)
+}
+
+/// Whether this run stores its data beside the executable.
+pub fn is_portable(app: &AppHandle) -> bool {
+    data_source(app) == DataSource::Portable
+}
+
+/// Key under which paths to the same file compare equal: the canonical path, so
+/// symlinks and `..` are resolved, lowercased on Windows and macOS, whose file
+/// systems ignore case by default. A path that doesn't exist is its own key.
+pub fn comparison_key(path: &Path) -> PathBuf {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        PathBuf::from(canonical.to_string_lossy().to_lowercase())
+    } else {
+        canonical
+    }
+}
+
+/// The form paths are stored in, e.g. in recents, so a file opened as
+/// `~/notes/flow.mmd`, through a symlink or as `./flow.mmd` is listed once: `~` is
+/// expanded, the path made absolute and canonicalized. When canonicalizing fails (the
+/// file is gone, or a network share refuses it) `.` and `..` are resolved lexically
+/// instead. On Windows separators become backslashes, the drive letter is uppercased
+/// and the `\\?\` prefix canonicalizing adds is dropped.
+pub fn normalize(path: &Path) -> PathBuf {
+    let expanded = match path.strip_prefix("~") {
+        Ok(rest) => std::env::home_dir().map_or_else(|| path.to_path_buf(), |home| home.join(rest)),
+        Err(_) => path.to_path_buf(),
+    };
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        std::env::current_dir()
+            .map(|dir| dir.join(&expanded))
+            .unwrap_or(expanded)
+    };
+    let normalized = fs::canonicalize(&absolute).unwrap_or_else(|_| lexical_normalize(&absolute));
+    if cfg!(windows) {
+        PathBuf::from(windows_form(&normalized.to_string_lossy()))
+    } else {
+        normalized
+    }
+}
+
+/// Resolves `.` and `..` without touching the file system; `..` never climbs above
+/// the root.
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `C:\Users\me\flow.mmd` for `\\?\c:/Users/me/flow.mmd`; verbatim UNC paths
+/// become plain `\\server\share` ones.
+fn windows_form(path: &str) -> String {
+    let path = path.replace('/', "\\");
+    let path = if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{share}")
+    } else {
+        path.strip_prefix(r"\\?\").unwrap_or(&path).to_string()
+    };
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) => format!(
+            "{}{}",
+            drive.to_ascii_uppercase(),
+            &path[drive.len_utf8()..]
+        ),
+        _ => path,
+    }
+}
+
+/// Whether each path exists, checked in parallel. A path on an unresponsive network
+/// drive can block `exists` for a long time; those still unanswered after `timeout`
+/// are `None`, and their threads are left to finish on their own.
+pub fn exist_within(paths: &[PathBuf], timeout: Duration) -> Vec<Option<bool>> {
+    let (sender, receiver) = mpsc::channel();
+    for (index, path) in paths.iter().enumerate() {
+        let sender = sender.clone();
+        let path = path.clone();
+        thread::spawn(move || {
+            let _ = sender.send((index, path.exists()));
+        });
+    }
+    drop(sender);
+
+    let deadline = Instant::now() + timeout;
+    let mut results = vec![None; paths.len()];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok((index, exists)) => results[index] = Some(exists),
+            // Every path answered, or the time is up.
+            Err(_) => break,
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn standard_mode_uses_platform_dir() {
+        let exe_dir = temp_dir("paths", "standard");
+        let mode = DataMode::detect(Some(&exe_dir), &[], None);
+        assert_eq!(mode, DataMode::Standard);
+        assert_eq!(
+            mode.data_root(Some(PathBuf::from("/home/me/.local/share/excalibur"))),
+            PathBuf::from("/home/me/.local/share/excalibur")
+        );
+    }
+
+    #[test]
+    fn marker_file_enables_portable_mode() {
+        let exe_dir = temp_dir("paths", "marker");
+        fs::write(exe_dir.join(PORTABLE_MARKER), "").unwrap();
+        let mode = DataMode::detect(Some(&exe_dir), &[], None);
+        assert!(mode.is_portable());
+        assert_eq!(
+            mode.data_root(Some(PathBuf::from("/roaming/excalibur"))),
+            exe_dir.join(PORTABLE_DATA_DIR)
+        );
+    }
+
+    #[test]
+    fn flag_and_env_enable_portable_mode() {
+        let exe_dir = temp_dir("paths", "flag");
+        let args = vec!["excalibur".to_string(), PORTABLE_FLAG.to_string()];
+        assert!(DataMode::detect(Some(&exe_dir), &args, None).is_portable());
+        assert!(DataMode::detect(Some(&exe_dir), &[], Some("1")).is_portable());
+        assert!(!DataMode::detect(Some(&exe_dir), &[], Some("0")).is_portable());
+        assert!(!DataMode::detect(Some(&exe_dir), &[], Some("")).is_portable());
+    }
+
+    #[test]
+    fn portable_paths_stay_under_exe_dir() {
+        let exe_dir = temp_dir("paths", "nested");
+        let mode = DataMode::Portable {
+            exe_dir: exe_dir.clone(),
+        };
+        let root = mode.data_root(Some(PathBuf::from("/roaming/excalibur")));
+        for name in [
+            "recents.json",
+            "settings.json",
+            "logs",
+            "drafts",
+            "history",
+            "templates",
+        ] {
+            assert!(root.join(name).starts_with(&exe_dir));
+        }
+    }
+
+    #[test]
+    fn overrides_take_precedence_in_order() {
+        let exe_dir = temp_dir("paths", "overrides");
+        let portable = DataMode::Portable {
+            exe_dir: exe_dir.clone(),
+        };
+        let standard = Some(PathBuf::from("/roaming/excalibur"));
+        let cwd = Path::new("/work");
+        let flag_args = vec![
+            "excalibur".to_string(),
+            DATA_DIR_FLAG.to_string(),
+            "/tmp/profile-a".to_string(),
+        ];
+
+        assert_eq!(
+            choose_data_root(
+                &flag_args,
+                Some("/tmp/profile-b"),
+                &portable,
+                standard.clone(),
+                cwd
+            ),
+            (DataSource::Flag, PathBuf::from("/tmp/profile-a"))
+        );
+        assert_eq!(
+            choose_data_root(
+                &[],
+                Some("/tmp/profile-b"),
+                &portable,
+                standard.clone(),
+                cwd
+            ),
+            (DataSource::Env, PathBuf::from("/tmp/profile-b"))
+        );
+        assert_eq!(
+            choose_data_root(&[], Some(""), &portable, standard.clone(), cwd),
+            (DataSource::Portable, exe_dir.join(PORTABLE_DATA_DIR))
+        );
+        assert_eq!(
+            choose_data_root(&[], None, &DataMode::Standard, standard, cwd),
+            (DataSource::Default, PathBuf::from("/roaming/excalibur"))
+        );
+    }
+
+    #[test]
+    fn data_dir_flag_accepts_both_forms() {
+        let args = vec!["excalibur".to_string(), "--data-dir=profile".to_string()];
+        assert_eq!(data_dir_flag(&args), Some("profile"));
+        let (_, path) =
+            choose_data_root(&args, None, &DataMode::Standard, None, Path::new("/work"));
+        assert_eq!(path, PathBuf::from("/work/profile"));
+        assert_eq!(data_dir_flag(&["excalibur".to_string()]), None);
+    }
+
+    #[test]
+    fn ensure_writable_creates_the_directory() {
+        let dir = temp_dir("paths", "writable").join("nested").join("data");
+        ensure_writable(&dir).unwrap();
+        assert!(dir.is_dir());
+        assert!(!dir.join(".excalibur-write-test").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn comparison_key_sees_through_symlinks() {
+        let dir = temp_dir("paths", "comparison-key");
+        let file = dir.join("flow.mmd");
+        fs::write(&file, "graph TD").unwrap();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let link = dir.join("link.mmd");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        assert_eq!(comparison_key(&link), comparison_key(&file));
+        assert_eq!(
+            comparison_key(&dir.join("nested").join("..").join("flow.mmd")),
+            comparison_key(&file)
+        );
+    }
+
+    #[test]
+    fn normalize_resolves_dots_in_missing_paths() {
+        let dir = temp_dir("paths", "normalize");
+        let canonical = fs::canonicalize(&dir).unwrap();
+        assert_eq!(
+            normalize(&dir.join("a").join(".").join("..").join("gone.mmd")),
+            canonical.join("gone.mmd")
+        );
+        assert_eq!(
+            lexical_normalize(Path::new("/work/./a/../../../flow.mmd")),
+            PathBuf::from("/flow.mmd")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalize_sees_through_symlinks_and_relative_paths() {
+        let dir = temp_dir("paths", "normalize-links");
+        let file = dir.join("flow.mmd");
+        fs::write(&file, "graph TD").unwrap();
+        let link = dir.join("link.mmd");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        let canonical = fs::canonicalize(&file).unwrap();
+        assert_eq!(normalize(&link), canonical);
+
+        let cwd = std::env::current_dir().unwrap();
+        let relative = relative_to(&file, &cwd);
+        assert_eq!(normalize(&relative), canonical);
+    }
+
+    /// `target` relative to `base`, both absolute.
+    fn relative_to(target: &Path, base: &Path) -> PathBuf {
+        let mut relative: PathBuf = base.components().skip(1).map(|_| "..").collect();
+        relative.push(target.strip_prefix("/").unwrap());
+        relative
+    }
+
+    #[test]
+    fn windows_form_unifies_prefixes_separators_and_drive_case() {
+        assert_eq!(
+            windows_form(r"\\?\c:\Users\me\flow.mmd"),
+            r"C:\Users\me\flow.mmd"
+        );
+        assert_eq!(windows_form("d:/work/flow.mmd"), r"D:\work\flow.mmd");
+        assert_eq!(
+            windows_form(r"\\?\UNC\server\share\flow.mmd"),
+            r"\\server\share\flow.mmd"
+        );
+        assert_eq!(
+            windows_form(r"\\server\share\flow.mmd"),
+            r"\\server\share\flow.mmd"
+        );
+    }
+
+    #[test]
+    fn exist_within_checks_every_path() {
+        let dir = temp_dir("paths", "exist-within");
+        let file = dir.join("flow.mmd");
+        fs::write(&file, "graph TD").unwrap();
+        let paths = vec![file, dir.join("deleted.mmd"), dir.clone()];
+        assert_eq!(
+            exist_within(&paths, Duration::from_secs(5)),
+            vec![Some(true), Some(false), Some(true)]
+        );
+        assert!(exist_within(&[], Duration::from_secs(5)).is_empty());
+    }
+}
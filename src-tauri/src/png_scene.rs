@@ -0,0 +1,238 @@
+use crate::error::CommandError;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+/// Keyword of the `tEXt` chunk Excalidraw keeps an exported scene in.
+const KEYWORD: &str = "application/vnd.excalidraw+json";
+
+/// One chunk of a PNG file; `data` excludes the length, type and CRC.
+struct Chunk<'a> {
+    kind: &'a [u8],
+    data: &'a [u8],
+}
+
+pub fn is_png(bytes: &[u8]) -> bool {
+    bytes.starts_with(SIGNATURE)
+}
+
+/// Whether `path` starts with the PNG signature, without reading the whole file.
+pub fn is_png_file(path: &Path) -> bool {
+    let mut signature = [0; 8];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok()
+        && is_png(&signature)
+}
+
+fn invalid(message: impl Into<String>) -> CommandError {
+    CommandError::InvalidDocument {
+        message: message.into(),
+    }
+}
+
+/// The chunks up to and including `IEND`. CRCs aren't checked; a damaged chunk
+/// surfaces as a scene that doesn't parse.
+fn chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>, CommandError> {
+    if !is_png(bytes) {
+        return Err(invalid("not a PNG image"));
+    }
+    let mut chunks = Vec::new();
+    let mut rest = &bytes[SIGNATURE.len()..];
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let Some(end) = length.checked_add(12).filter(|end| *end <= rest.len()) else {
+            break;
+        };
+        let chunk = Chunk {
+            kind: &rest[4..8],
+            data: &rest[8..8 + length],
+        };
+        let last = chunk.kind == b"IEND";
+        chunks.push(chunk);
+        rest = &rest[end..];
+        if last {
+            return Ok(chunks);
+        }
+    }
+    Err(invalid("the PNG image is cut off"))
+}
+
+/// The text of the chunk holding the scene, if there is one. `tEXt` is Latin-1.
+fn scene_text(chunk: &Chunk) -> Option<String> {
+    if chunk.kind != b"tEXt" {
+        return None;
+    }
+    let separator = chunk.data.iter().position(|byte| *byte == 0)?;
+    if &chunk.data[..separator] != KEYWORD.as_bytes() {
+        return None;
+    }
+    Some(
+        chunk.data[separator + 1..]
+            .iter()
+            .map(|byte| char::from(*byte))
+            .collect(),
+    )
+}
+
+/// Bytes of a "byte string", Excalidraw's one-character-per-byte encoding.
+fn byte_string(text: &str) -> Result<Vec<u8>, CommandError> {
+    text.chars()
+        .map(|ch| u8::try_from(u32::from(ch)))
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid("the embedded scene is not a byte string"))
+}
+
+/// Unwraps the chunk text: `{ "encoding": "bstring", "compressed": true, "encoded": ... }`
+/// around zlib-compressed scene JSON, or the scene JSON itself in older exports.
+fn decode_text(text: &str) -> Result<String, CommandError> {
+    let wrapper: Value = serde_json::from_str(text)?;
+    let Some(encoded) = wrapper.get("encoded") else {
+        return Ok(text.to_string());
+    };
+    if wrapper.get("encoding").and_then(Value::as_str) != Some("bstring") {
+        return Err(invalid(format!(
+            "unknown scene encoding {}",
+            wrapper.get("encoding").unwrap_or(&Value::Null)
+        )));
+    }
+    let bytes = byte_string(encoded.as_str().unwrap_or_default())?;
+    let scene = if wrapper.get("compressed").and_then(Value::as_bool) == Some(true) {
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(bytes.as_slice())
+            .read_to_end(&mut inflated)
+            .map_err(|error| invalid(format!("the embedded scene doesn't inflate: {error}")))?;
+        inflated
+    } else {
+        bytes
+    };
+    String::from_utf8(scene).map_err(|_| invalid("the embedded scene is not UTF-8"))
+}
+
+/// The scene JSON embedded in an image exported with Excalidraw's "Embed scene".
+/// `NoEmbeddedScene` when the PNG doesn't carry one.
+pub fn extract_scene(path: &Path, bytes: &[u8]) -> Result<String, CommandError> {
+    let text = chunks(bytes)?.iter().find_map(scene_text).ok_or_else(|| {
+        CommandError::NoEmbeddedScene {
+            path: path.to_string_lossy().to_string(),
+        }
+    })?;
+    decode_text(&text)
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+/// `png` with `scene` embedded the way Excalidraw does it, so Excalidraw opens the
+/// image as an editable scene. A scene the image already carried is replaced.
+pub fn embed_scene(png: &[u8], scene: &str) -> Result<Vec<u8>, CommandError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(scene.as_bytes())?;
+    let compressed = encoder.finish()?;
+    let wrapper = json!({
+        "version": "1",
+        "encoding": "bstring",
+        "compressed": true,
+        "encoded": compressed.iter().map(|byte| char::from(*byte)).collect::<String>(),
+    });
+    let mut text = KEYWORD.as_bytes().to_vec();
+    text.push(0);
+    text.extend(byte_string(&serde_json::to_string(&wrapper)?)?);
+
+    let mut embedded = SIGNATURE.to_vec();
+    for chunk in chunks(png)? {
+        if scene_text(&chunk).is_some() {
+            continue;
+        }
+        if chunk.kind == b"IEND" {
+            write_chunk(&mut embedded, b"tEXt", &text);
+        }
+        write_chunk(&mut embedded, chunk.kind, chunk.data);
+    }
+    Ok(embedded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 PNG; the pixel data doesn't matter here.
+    fn image() -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        write_chunk(
+            &mut png,
+            b"IDAT",
+            b"\x78\x9c\x63\x60\x00\x00\x00\x02\x00\x01",
+        );
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn writes_valid_chunk_crcs() {
+        let png = image();
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn embedded_scenes_round_trip() {
+        let path = Path::new("/work/flow.excalidraw.png");
+        let scene = r#"{"type":"excalidraw","elements":[{"text":"naïve → ok"}]}"#;
+        let embedded = embed_scene(&image(), scene).unwrap();
+        assert!(is_png(&embedded));
+        assert_eq!(extract_scene(path, &embedded).unwrap(), scene);
+
+        // Saving again replaces the scene instead of adding a second one.
+        let resaved = embed_scene(&embedded, r#"{"type":"excalidraw","elements":[]}"#).unwrap();
+        let scenes = chunks(&resaved)
+            .unwrap()
+            .iter()
+            .filter_map(scene_text)
+            .count();
+        assert_eq!(scenes, 1);
+        assert_eq!(
+            extract_scene(path, &resaved).unwrap(),
+            r#"{"type":"excalidraw","elements":[]}"#
+        );
+        assert_eq!(chunks(&resaved).unwrap().last().unwrap().kind, b"IEND");
+    }
+
+    #[test]
+    fn reads_uncompressed_and_legacy_scenes() {
+        assert_eq!(
+            decode_text(r#"{"version":"1","encoding":"bstring","compressed":false,"encoded":"{\"elements\":[]}"}"#)
+                .unwrap(),
+            r#"{"elements":[]}"#
+        );
+        let legacy = r#"{"type":"excalidraw","elements":[]}"#;
+        assert_eq!(decode_text(legacy).unwrap(), legacy);
+    }
+
+    #[test]
+    fn plain_images_have_no_scene() {
+        match extract_scene(Path::new("/work/photo.png"), &image()) {
+            Err(CommandError::NoEmbeddedScene { path }) => assert_eq!(path, "/work/photo.png"),
+            other => panic!("expected NoEmbeddedScene, got {other:?}"),
+        }
+        let cut_off = &image()[..20];
+        assert_eq!(
+            extract_scene(Path::new("/work/photo.png"), cut_off)
+                .unwrap_err()
+                .code(),
+            "invalid_document"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::background::{wait, IdlePolicy};
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the idle timer and the session lock state are checked.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the privacy lock is engaged and when the frontend last saw activity.
+pub struct PrivacyState {
+    locked: AtomicBool,
+    last_activity: Mutex<Instant>,
+}
+
+impl Default for PrivacyState {
+    fn default() -> Self {
+        PrivacyState {
+            locked: AtomicBool::new(false),
+            last_activity: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct PrivacyLockEvent {
+    /// "session", "idle" or "manual".
+    reason: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct PrivacyStatus {
+    enabled: bool,
+    locked: bool,
+    /// `privacy_unlock` needs a password: one of the unlocked encrypted documents'.
+    password_required: bool,
+}
+
+/// Refuses with `CommandError::Locked` while the privacy lock is engaged. Commands
+/// that would reveal file names or contents call this first.
+pub fn ensure_unlocked(app: &AppHandle) -> Result<(), CommandError> {
+    if app.state::<PrivacyState>().locked.load(Ordering::SeqCst) {
+        Err(CommandError::Locked)
+    } else {
+        Ok(())
+    }
+}
+
+fn engage(app: &AppHandle, reason: &'static str) {
+    if app
+        .state::<PrivacyState>()
+        .locked
+        .swap(true, Ordering::SeqCst)
+    {
+        return;
+    }
+    log::info!("privacy lock engaged ({reason})");
+    crate::recent_menu::refresh(app);
+    let _ = app.emit("privacy-lock", PrivacyLockEvent { reason });
+}
+
+fn password_required(app: &AppHandle) -> bool {
+    load_settings(app).privacy_unlock_password && crate::crypto::has_cached_keys(app)
+}
+
+/// Whether the desktop session is locked, from logind's `LockedHint`. `None` where
+/// that can't be asked, which leaves only the idle timer.
+#[cfg(target_os = "linux")]
+fn session_locked() -> Option<bool> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+    let output = std::process::Command::new("loginctl")
+        .args(["show-session", &session, "--property=LockedHint", "--value"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn session_locked() -> Option<bool> {
+    None
+}
+
+/// Whether `idle` is long enough to lock after `idle_minutes`; 0 never locks.
+fn idle_expired(idle: Duration, idle_minutes: u64) -> bool {
+    idle_minutes > 0 && idle >= Duration::from_secs(idle_minutes * 60)
+}
+
+/// Engages the privacy lock when the session locks or the frontend has reported no
+/// activity for `privacy_idle_minutes`. Runs on its own thread for the life of the
+/// app and does nothing while the setting is off.
+pub fn run_privacy_monitor(app: &AppHandle) {
+    // Stop asking once the session lock state turns out to be unavailable.
+    let mut session_lock_available = true;
+    loop {
+        // Kept on schedule while idle: idleness is exactly when the lock must engage.
+        wait(app, "privacy-monitor", MONITOR_INTERVAL, IdlePolicy::Keep);
+        let settings = load_settings(app);
+        let state = app.state::<PrivacyState>();
+        if !settings.privacy_lock || state.locked.load(Ordering::SeqCst) {
+            continue;
+        }
+        if session_lock_available {
+            match session_locked() {
+                Some(true) => {
+                    engage(app, "session");
+                    continue;
+                }
+                Some(false) => {}
+                None => session_lock_available = false,
+            }
+        }
+        let idle = state.last_activity.lock().unwrap().elapsed();
+        if idle_expired(idle, settings.privacy_idle_minutes) {
+            engage(app, "idle");
+        }
+    }
+}
+
+/// Resets the idle timer and wakes suspended background tasks. The frontend calls
+/// this, throttled, on input.
+#[tauri::command]
+pub fn report_activity(app: AppHandle) {
+    *app.state::<PrivacyState>().last_activity.lock().unwrap() = Instant::now();
+    crate::background::record_activity(&app);
+}
+
+#[tauri::command]
+pub fn privacy_status(app: AppHandle) -> PrivacyStatus {
+    PrivacyStatus {
+        enabled: load_settings(&app).privacy_lock,
+        locked: ensure_unlocked(&app).is_err(),
+        password_required: password_required(&app),
+    }
+}
+
+/// Engages the privacy lock right away, e.g. from a "Lock now" button.
+#[tauri::command]
+pub fn privacy_lock(app: AppHandle) {
+    engage(&app, "manual");
+}
+
+/// Lifts the privacy lock and emits `privacy-unlock`. When encrypted documents are
+/// unlocked and `privacy_unlock_password` is on, `password` must be one of theirs.
+/// Nothing is discarded while locked, so the frontend picks up where it left off.
+#[tauri::command]
+pub async fn privacy_unlock(app: AppHandle, password: Option<String>) -> Result<(), CommandError> {
+    if ensure_unlocked(&app).is_ok() {
+        return Ok(());
+    }
+    if password_required(&app) {
+        let password = password.ok_or(CommandError::WrongPassword)?;
+        let verify_app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            crate::crypto::verify_session_password(&verify_app, &password)
+        })
+        .await
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })??;
+    }
+    let state = app.state::<PrivacyState>();
+    *state.last_activity.lock().unwrap() = Instant::now();
+    state.locked.store(false, Ordering::SeqCst);
+    log::info!("privacy lock lifted");
+    crate::recent_menu::refresh(&app);
+    let _ = app.emit("privacy-unlock", ());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_timer_respects_the_setting() {
+        assert!(!idle_expired(Duration::from_secs(599), 10));
+        assert!(idle_expired(Duration::from_secs(600), 10));
+        assert!(!idle_expired(Duration::from_secs(86_400), 0));
+    }
+}
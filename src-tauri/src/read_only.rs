@@ -0,0 +1,156 @@
+use crate::error::CommandError;
+use crate::file_status;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Documents opened read-only. Saves to them are refused until `make_writable`.
+#[derive(Default)]
+pub struct ReadOnlyDocuments(Mutex<HashSet<PathBuf>>);
+
+#[derive(Serialize)]
+pub struct WritableResponse {
+    /// On-disk mtime when the flag was cleared, to tell later outside changes apart.
+    modified_at: Option<u64>,
+    size: Option<u64>,
+}
+
+fn refused(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Whether the file itself can't be written: the read-only attribute on Windows, no
+/// write permission bits on Unix, or opening it for writing is refused, as on a
+/// mounted disk image or a share that only grants read access. Opening for writing
+/// without truncating leaves the file as it is.
+fn read_only_on_disk(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    metadata.permissions().readonly()
+        || (metadata.is_file()
+            && OpenOptions::new()
+                .write(true)
+                .open(path)
+                .is_err_and(|error| refused(&error)))
+}
+
+/// Whether new files can be created next to `path`. Saves write a temporary file
+/// there and rename it over the document, so a folder that refuses this can't be
+/// saved to even when the file itself is writable. Probed by creating and removing
+/// a file, which is the only check that holds for ACLs, read-only volumes and shares.
+pub fn dir_writable(path: &Path) -> bool {
+    let Some(dir) = path.parent() else {
+        return false;
+    };
+    let probe = dir.join(format!(".excalibur-write-check-{}", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(error) => !refused(&error) && error.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Records how `path` was opened and returns whether it is read-only: because the
+/// caller asked for it or because the file can't be written anyway. Opening it again
+/// normally clears a flag left from an earlier read-only open.
+pub fn on_open(app: &AppHandle, path: &Path, requested: Option<bool>) -> bool {
+    let read_only = requested.unwrap_or(false) || read_only_on_disk(path);
+    let mut documents = app.state::<ReadOnlyDocuments>().0.lock().unwrap();
+    if read_only {
+        documents.insert(path.to_path_buf());
+    } else {
+        documents.remove(path);
+    }
+    read_only
+}
+
+/// Refuses with `CommandError::ReadOnlyDocument` when `path` was opened read-only,
+/// or has become read-only on disk since; replacing it through a rename would
+/// otherwise succeed regardless. Every command that writes a document over its
+/// original calls this first.
+pub fn ensure_writable(app: &AppHandle, path: &Path) -> Result<(), CommandError> {
+    let opened_read_only = app
+        .state::<ReadOnlyDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .contains(path);
+    if opened_read_only || read_only_on_disk(path) {
+        Err(CommandError::ReadOnlyDocument {
+            path: path.to_string_lossy().to_string(),
+            on_disk: read_only_on_disk(path),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Clears the read-only flag of `path` so it can be saved again, returning its size
+/// and mtime as they are now. Fails while the file itself is read-only on disk,
+/// since saving would fail anyway.
+#[tauri::command]
+pub fn make_writable(app: AppHandle, path: String) -> Result<WritableResponse, CommandError> {
+    let path_buf = PathBuf::from(&path);
+    if read_only_on_disk(&path_buf) {
+        return Err(CommandError::ReadOnlyDocument {
+            path,
+            on_disk: true,
+        });
+    }
+    app.state::<ReadOnlyDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(&path_buf);
+    log::info!("make_writable: {}", path);
+    let status = file_status(&path_buf);
+    Ok(WritableResponse {
+        modified_at: status.modified_at,
+        size: status.size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_files_without_write_permission() {
+        let path =
+            std::env::temp_dir().join(format!("excalibur-read-only-{}.mmd", std::process::id()));
+        fs::write(&path, "graph TD").unwrap();
+        assert!(!read_only_on_disk(&path));
+
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions.clone()).unwrap();
+        assert!(read_only_on_disk(&path));
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&path, permissions).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!read_only_on_disk(&path));
+    }
+
+    #[test]
+    fn probes_whether_the_folder_takes_new_files() {
+        let dir = std::env::temp_dir().join(format!("excalibur-dir-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let document = dir.join("flow.mmd");
+        assert!(dir_writable(&document));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        assert!(!dir_writable(&dir.join("missing").join("flow.mmd")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
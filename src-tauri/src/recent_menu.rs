@@ -0,0 +1,194 @@
+use crate::display_path::PathShortener;
+use crate::event_queue::emit_when_ready;
+use crate::{clear_recents, file_name, load_recents, privacy, RecentItem};
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, MenuItemKind, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Manager, Wry};
+
+const OPEN_RECENT_ID: &str = "open-recent";
+const ENTRY_ID_PREFIX: &str = "open-recent:";
+const CLEAR_ID: &str = "open-recent:clear";
+/// Entries listed per kind; the start screen shows the rest.
+const MAX_ENTRIES_PER_KIND: usize = 10;
+/// Longest document name kept in an entry's label.
+const MAX_NAME_CHARS: usize = 60;
+
+/// The native File → Open Recent submenu, once installed, and the path each of its
+/// entries opens, indexed by the number in the entry's menu id.
+#[derive(Default)]
+pub struct RecentMenu {
+    submenu: Mutex<Option<Submenu<Wry>>>,
+    paths: Mutex<Vec<String>>,
+}
+
+/// Label of an entry: the recents name, or the file name without one, and the
+/// shortened path. `&` is doubled so it isn't taken for a mnemonic.
+fn entry_label(name: Option<&str>, file_name: &str, display_path: &str) -> String {
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(file_name);
+    let name = if name.chars().count() > MAX_NAME_CHARS {
+        let kept: String = name.chars().take(MAX_NAME_CHARS - 1).collect();
+        format!("{kept}…")
+    } else {
+        name.to_string()
+    };
+    format!("{name} — {display_path}").replace('&', "&&")
+}
+
+/// Sets the application menu: the default one, with Open Recent at the top of its
+/// File menu.
+pub fn install(app: &AppHandle) -> tauri::Result<()> {
+    let menu = Menu::default(app)?;
+    let open_recent = Submenu::with_id(app, OPEN_RECENT_ID, "Open Recent", true)?;
+    let file_menu = menu.items()?.into_iter().find_map(|item| match item {
+        MenuItemKind::Submenu(submenu) if submenu.text().is_ok_and(|text| text == "File") => {
+            Some(submenu)
+        }
+        _ => None,
+    });
+    match file_menu {
+        Some(file_menu) => file_menu.prepend(&open_recent)?,
+        None => menu.prepend(&Submenu::with_items(app, "File", true, &[&open_recent])?)?,
+    }
+    app.set_menu(menu)?;
+    *app.state::<RecentMenu>().submenu.lock().unwrap() = Some(open_recent);
+    refresh(app);
+    Ok(())
+}
+
+/// Rebuilds Open Recent from the recents. Called whenever they change and when the
+/// privacy lock engages or lifts; while locked the menu lists nothing.
+pub fn refresh(app: &AppHandle) {
+    let Some(state) = app.try_state::<RecentMenu>() else {
+        return;
+    };
+    let Some(submenu) = state.submenu.lock().unwrap().clone() else {
+        return;
+    };
+    let recents = if privacy::ensure_unlocked(app).is_ok() {
+        load_recents(app)
+    } else {
+        Vec::new()
+    };
+    let mut paths = state.paths.lock().unwrap();
+    if let Err(error) = rebuild(app, &submenu, &recents, &mut paths) {
+        log::warn!("recent_menu: failed to rebuild: {error}");
+    }
+}
+
+/// Fills `submenu` with up to `MAX_ENTRIES_PER_KIND` entries of each kind, each kind
+/// under its own heading, then "Clear Menu".
+fn rebuild(
+    app: &AppHandle,
+    submenu: &Submenu<Wry>,
+    recents: &[RecentItem],
+    paths: &mut Vec<String>,
+) -> tauri::Result<()> {
+    for item in submenu.items()? {
+        submenu.remove(item.as_ref())?;
+    }
+    paths.clear();
+    let shortener = PathShortener::new(app);
+    for (kind, heading) in [("excalidraw", "Excalidraw"), ("mermaid", "Mermaid")] {
+        let items: Vec<&RecentItem> = recents
+            .iter()
+            .filter(|item| item.kind == kind)
+            .take(MAX_ENTRIES_PER_KIND)
+            .collect();
+        if items.is_empty() {
+            continue;
+        }
+        if !paths.is_empty() {
+            submenu.append(&PredefinedMenuItem::separator(app)?)?;
+        }
+        submenu.append(&MenuItem::new(app, heading, false, None::<&str>)?)?;
+        for item in items {
+            let path = Path::new(&item.path);
+            let label = entry_label(
+                item.name.as_deref(),
+                &file_name(path).unwrap_or_default(),
+                &shortener.shorten(path),
+            );
+            let id = format!("{ENTRY_ID_PREFIX}{}", paths.len());
+            submenu.append(&MenuItem::with_id(app, id, label, true, None::<&str>)?)?;
+            paths.push(item.path.clone());
+        }
+    }
+    if paths.is_empty() {
+        submenu.append(&MenuItem::new(app, "No Recent Files", false, None::<&str>)?)?;
+    }
+    submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    submenu.append(&MenuItem::with_id(
+        app,
+        CLEAR_ID,
+        "Clear Menu",
+        !paths.is_empty(),
+        None::<&str>,
+    )?)?;
+    Ok(())
+}
+
+/// Handles a click in the application menu: an entry is opened like a file handed
+/// over by the OS, through `open-file`, and "Clear Menu" clears the recents.
+pub fn handle_menu_event(app: &AppHandle, id: &str) {
+    if id == CLEAR_ID {
+        if let Err(error) = clear_recents(app.clone(), None) {
+            log::warn!("recent_menu: failed to clear recents: {error}");
+        }
+        return;
+    }
+    let Some(index) = id
+        .strip_prefix(ENTRY_ID_PREFIX)
+        .and_then(|index| index.parse::<usize>().ok())
+    else {
+        return;
+    };
+    let path = app
+        .state::<RecentMenu>()
+        .paths
+        .lock()
+        .unwrap()
+        .get(index)
+        .cloned();
+    if let Some(path) = path {
+        log::info!("recent_menu: opening {}", path);
+        emit_when_ready(app, None, "open-file", path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_prefer_the_name_and_stay_short() {
+        assert_eq!(
+            entry_label(
+                Some("Architecture"),
+                "arch.excalidraw",
+                "~/work/arch.excalidraw"
+            ),
+            "Architecture — ~/work/arch.excalidraw"
+        );
+        assert_eq!(
+            entry_label(None, "flow.mmd", "~/work/flow.mmd"),
+            "flow.mmd — ~/work/flow.mmd"
+        );
+        assert_eq!(
+            entry_label(Some(" "), "flow.mmd", "~/flow.mmd"),
+            "flow.mmd — ~/flow.mmd"
+        );
+
+        let long = entry_label(Some(&"n".repeat(200)), "a.mmd", "~/a.mmd");
+        assert_eq!(
+            long,
+            format!("{}… — ~/a.mmd", "n".repeat(MAX_NAME_CHARS - 1))
+        );
+        assert_eq!(
+            entry_label(Some("R&D"), "rd.mmd", "~/R&D/rd.mmd"),
+            "R&&D — ~/R&&D/rd.mmd"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::CommandError;
+use crate::library::LIBRARY_KIND;
+use crate::{file_name, modify_recents, new_recent_item, RecentItem};
+use std::cmp::Reverse;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+/// How many unpinned items recents keeps by default; pinned ones don't count. See
+/// the `recents_limit` setting.
+pub const DEFAULT_RECENTS_LIMIT: usize = 10;
+
+/// Drops the unpinned items past `limit`, keeping the first ones in `recents`'
+/// current order. Pinned items are never evicted. Libraries have a `limit` of their
+/// own, so opening a few doesn't push documents out.
+pub fn cap(recents: &mut Vec<RecentItem>, limit: usize) {
+    let (mut documents, mut libraries) = (0, 0);
+    recents.retain(|item| {
+        if item.pinned {
+            return true;
+        }
+        let unpinned = if item.kind == LIBRARY_KIND {
+            &mut libraries
+        } else {
+            &mut documents
+        };
+        *unpinned += 1;
+        *unpinned <= limit
+    });
+}
+
+/// Pinned items first, then the rest; newest first within each.
+pub fn sort_for_listing(recents: &mut [RecentItem]) {
+    recents.sort_by_key(|item| (!item.pinned, Reverse(item.updated_at)));
+}
+
+fn set_pinned(app: &AppHandle, kind: &str, path: &str, pinned: bool) -> Result<(), CommandError> {
+    crate::privacy::ensure_unlocked(app)?;
+    if !matches!(kind, "excalidraw" | "mermaid" | LIBRARY_KIND) {
+        return Err(CommandError::InvalidKind {
+            kind: kind.to_string(),
+        });
+    }
+    let path = crate::paths::normalize(Path::new(path))
+        .to_string_lossy()
+        .to_string();
+    let path = path.as_str();
+    let limit = crate::settings::load_settings(app).recents_limit;
+    let changed = modify_recents(app, |recents| {
+        match recents
+            .iter_mut()
+            .find(|item| item.kind == kind && item.path == path)
+        {
+            Some(item) if item.pinned == pinned => return false,
+            Some(item) => item.pinned = pinned,
+            None if !pinned => return false,
+            None => {
+                let mut item = new_recent_item(app, kind, path, file_name(Path::new(path)));
+                item.pinned = true;
+                recents.insert(0, item);
+            }
+        }
+        // Unpinning can leave one unpinned item too many.
+        cap(recents, limit);
+        true
+    });
+    if !changed {
+        return Ok(());
+    }
+    log::info!(
+        "{}: {}",
+        if pinned { "pin_recent" } else { "unpin_recent" },
+        path
+    );
+    let _ = app.emit("recents-changed", ());
+    Ok(())
+}
+
+/// Keeps `path` in recents however many other files are opened after it, adding it
+/// when it isn't listed.
+#[tauri::command]
+pub fn pin_recent(app: AppHandle, kind: String, path: String) -> Result<(), CommandError> {
+    set_pinned(&app, &kind, &path, true)
+}
+
+/// Lets `path` fall off recents again like any other item.
+#[tauri::command]
+pub fn unpin_recent(app: AppHandle, kind: String, path: String) -> Result<(), CommandError> {
+    set_pinned(&app, &kind, &path, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, updated_at: u64, pinned: bool) -> RecentItem {
+        RecentItem {
+            kind: "mermaid".to_string(),
+            path: format!("/work/{name}.mmd"),
+            name: Some(name.to_string()),
+            updated_at,
+            root: None,
+            recorded_size: None,
+            recorded_modified_at: None,
+            encrypted: false,
+            pinned,
+        }
+    }
+
+    fn names(recents: &[RecentItem]) -> Vec<&str> {
+        recents
+            .iter()
+            .map(|item| item.name.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pinned_items_are_never_evicted() {
+        let mut recents = vec![item("pinned-new", 100, true)];
+        recents.extend((0..12).map(|index| item(&format!("scratch-{index}"), 50 - index, false)));
+        recents.push(item("pinned-old", 1, true));
+        cap(&mut recents, DEFAULT_RECENTS_LIMIT);
+
+        assert_eq!(recents.len(), DEFAULT_RECENTS_LIMIT + 2);
+        assert_eq!(recents[0].name.as_deref(), Some("pinned-new"));
+        assert_eq!(recents[10].name.as_deref(), Some("scratch-9"));
+        assert_eq!(recents[11].name.as_deref(), Some("pinned-old"));
+    }
+
+    #[test]
+    fn libraries_are_capped_apart_from_documents() {
+        let mut recents: Vec<RecentItem> = (0..3)
+            .map(|index| RecentItem {
+                kind: LIBRARY_KIND.to_string(),
+                ..item(&format!("library-{index}"), 90 - index, false)
+            })
+            .collect();
+        recents.extend((0..3).map(|index| item(&format!("flow-{index}"), 50 - index, false)));
+        cap(&mut recents, 2);
+
+        assert_eq!(
+            names(&recents),
+            vec!["library-0", "library-1", "flow-0", "flow-1"]
+        );
+    }
+
+    #[test]
+    fn lists_pinned_items_first() {
+        let mut recents = vec![
+            item("today", 30, false),
+            item("pinned-old", 10, true),
+            item("yesterday", 20, false),
+            item("pinned-new", 15, true),
+        ];
+        sort_for_listing(&mut recents);
+        assert_eq!(
+            names(&recents),
+            vec!["pinned-new", "pinned-old", "today", "yesterday"]
+        );
+    }
+
+    #[test]
+    fn old_recents_files_load_unpinned() {
+        let recents: Vec<RecentItem> = serde_json::from_str(
+            r#"[{"kind":"mermaid","path":"/work/flow.mmd","name":null,"updated_at":1}]"#,
+        )
+        .unwrap();
+        assert!(!recents[0].pinned);
+    }
+}
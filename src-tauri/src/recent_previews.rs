@@ -0,0 +1,284 @@
+use crate::{app_data_dir, write_file, FileStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// How much of each recent file is read to build its preview.
+const PREVIEW_BYTES: u64 = 16 * 1024;
+/// Previews longer than this many characters are cut off with an ellipsis.
+const MAX_PREVIEW_CHARS: usize = 120;
+
+/// A preview together with the size and mtime of the file it was built from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct CachedPreview {
+    size: u64,
+    modified_at: u64,
+    preview: Option<String>,
+}
+
+fn cache_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("recent-previews.json")
+}
+
+fn load_cache(app: &AppHandle) -> BTreeMap<String, CachedPreview> {
+    fs::read_to_string(cache_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(app: &AppHandle, cache: &BTreeMap<String, CachedPreview>) {
+    if let Ok(contents) = serde_json::to_string(cache) {
+        if let Err(error) = write_file(&cache_path(app), &contents) {
+            log::warn!("previews: failed to save cache: {}", error);
+        }
+    }
+}
+
+/// Reads at most `PREVIEW_BYTES` of `path`, decompressed if it is gzipped. The flag
+/// is true when that was the whole file.
+pub fn read_head(path: &Path) -> Option<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    crate::gzip::open(path)
+        .ok()?
+        .take(PREVIEW_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let complete = bytes.len() as u64 <= PREVIEW_BYTES;
+    bytes.truncate(PREVIEW_BYTES as usize);
+    Some((bytes, complete))
+}
+
+fn clip(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= MAX_PREVIEW_CHARS {
+        return Some(text.to_string());
+    }
+    let mut clipped: String = text.chars().take(MAX_PREVIEW_CHARS - 1).collect();
+    clipped.push('…');
+    Some(clipped)
+}
+
+/// The diagram declaration, e.g. "sequenceDiagram", followed by the title from the
+/// front matter or a `title` line when there is one. Comments, directives and blank
+/// lines are skipped. A line cut off by the bounded read is ignored.
+fn mermaid_preview(text: &str, complete: bool) -> Option<String> {
+    let mut lines: Vec<&str> = text.lines().collect();
+    if !complete && !text.ends_with('\n') {
+        lines.pop();
+    }
+    let mut lines = lines.into_iter().map(str::trim).peekable();
+    let mut title = None;
+    if lines.peek() == Some(&"---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line == "---" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("title:") {
+                title = Some(value.trim().trim_matches(['"', '\'']).to_string());
+            }
+        }
+    }
+
+    let mut declaration = None;
+    for line in lines {
+        if line.is_empty() || line.starts_with("%%") {
+            continue;
+        }
+        if declaration.is_none() {
+            declaration = Some(line);
+        } else if let Some(value) = line.strip_prefix("title ") {
+            title.get_or_insert_with(|| value.trim().to_string());
+        }
+        if declaration.is_some() && title.is_some() {
+            break;
+        }
+    }
+    match (declaration?, title.filter(|title| !title.is_empty())) {
+        (declaration, Some(title)) => clip(&format!("{declaration} — {title}")),
+        (declaration, None) => clip(declaration),
+    }
+}
+
+fn frame_name(element: &Value) -> Option<&str> {
+    let frame = matches!(
+        element.get("type").and_then(Value::as_str),
+        Some("frame" | "magicframe")
+    );
+    let deleted = element.get("isDeleted").and_then(Value::as_bool) == Some(true);
+    if !frame || deleted {
+        return None;
+    }
+    element
+        .get("name")
+        .and_then(Value::as_str)
+        .filter(|name| !name.trim().is_empty())
+}
+
+/// `appState.name`, or else the name of the first frame. A scene larger than the
+/// bounded read can't be parsed whole, so its elements are read one at a time until
+/// the cut-off; `appState` comes after them and is only seen in complete files.
+fn excalidraw_preview(text: &str, complete: bool) -> Option<String> {
+    if complete {
+        let scene: Value = serde_json::from_str(text).ok()?;
+        let app_state_name = scene
+            .pointer("/appState/name")
+            .and_then(Value::as_str)
+            .filter(|name| !name.trim().is_empty());
+        return app_state_name
+            .or_else(|| {
+                scene
+                    .get("elements")?
+                    .as_array()?
+                    .iter()
+                    .find_map(frame_name)
+            })
+            .and_then(clip);
+    }
+
+    let start = text.find("\"elements\"")?;
+    let mut rest = &text[start..];
+    rest = &rest[rest.find('[')? + 1..];
+    loop {
+        rest = rest.trim_start_matches(|ch: char| ch.is_whitespace() || ch == ',');
+        if !rest.starts_with('{') {
+            return None;
+        }
+        let mut elements = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        let element = elements.next()?.ok()?;
+        if let Some(name) = frame_name(&element) {
+            return clip(name);
+        }
+        rest = &rest[elements.byte_offset()..];
+    }
+}
+
+pub fn build_preview(kind: &str, path: &Path) -> Option<String> {
+    let (bytes, complete) = read_head(path)?;
+    if crate::crypto::is_encrypted_bytes(&bytes) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    match kind {
+        "mermaid" => mermaid_preview(&text, complete),
+        "excalidraw" => excalidraw_preview(&text, complete),
+        _ => None,
+    }
+}
+
+/// Previews for the recent files, in the order given. Files whose size and mtime
+/// match the cache reuse the cached preview; the rest are read, and the cache is
+/// rewritten (dropping files no longer listed) only when something changed.
+pub fn previews(app: &AppHandle, items: &[(&str, &str, &FileStatus)]) -> Vec<Option<String>> {
+    let cache = load_cache(app);
+    let mut next = BTreeMap::new();
+    let mut results = Vec::with_capacity(items.len());
+    for (kind, path, status) in items {
+        let (Some(size), Some(modified_at)) = (status.size, status.modified_at) else {
+            results.push(None);
+            continue;
+        };
+        let entry = match cache.get(*path) {
+            Some(cached) if cached.size == size && cached.modified_at == modified_at => {
+                cached.clone()
+            }
+            _ => CachedPreview {
+                size,
+                modified_at,
+                preview: build_preview(kind, Path::new(path)),
+            },
+        };
+        results.push(entry.preview.clone());
+        next.insert(path.to_string(), entry);
+    }
+
+    if next != cache {
+        save_cache(app, &next);
+    }
+    results
+}
+
+/// Drops the cached preview of `path` after it changed on disk outside the app. Only
+/// the cache index is touched; the file is read again when `previews` next asks.
+pub fn invalidate(app: &AppHandle, path: &str) {
+    let mut cache = load_cache(app);
+    if cache.remove(path).is_some() {
+        save_cache(app, &cache);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mermaid_preview_skips_comments_and_adds_the_title() {
+        assert_eq!(
+            mermaid_preview(
+                "%% checkout\n\nsequenceDiagram\n  title Checkout flow\n  A->>B: pay\n",
+                true
+            ),
+            Some("sequenceDiagram — Checkout flow".to_string())
+        );
+        assert_eq!(
+            mermaid_preview(
+                "---\ntitle: \"Login\"\n---\n%%{init: {}}%%\nflowchart TD\n  A-->B\n",
+                true
+            ),
+            Some("flowchart TD — Login".to_string())
+        );
+        assert_eq!(
+            mermaid_preview("graph LR\n  A-->B", true),
+            Some("graph LR".to_string())
+        );
+        assert_eq!(mermaid_preview("%% only a comment\n", true), None);
+        // The last line of a cut-off read may be partial.
+        assert_eq!(mermaid_preview("\n\nsequenceDia", false), None);
+    }
+
+    #[test]
+    fn excalidraw_preview_prefers_the_document_name() {
+        let scene = r#"{"type":"excalidraw","elements":[
+            {"type":"frame","name":"Deleted","isDeleted":true},
+            {"type":"rectangle"},
+            {"type":"frame","name":"Overview"}
+        ],"appState":{"name":"Architecture"}}"#;
+        assert_eq!(
+            excalidraw_preview(scene, true),
+            Some("Architecture".to_string())
+        );
+        let unnamed = scene.replace(r#""name":"Architecture""#, r#""name":"""#);
+        assert_eq!(
+            excalidraw_preview(&unnamed, true),
+            Some("Overview".to_string())
+        );
+        assert_eq!(excalidraw_preview("{ not json", true), None);
+    }
+
+    #[test]
+    fn cut_off_scenes_are_read_element_by_element() {
+        let head = r#"{"type":"excalidraw","elements":[{"type":"text","text":"a, b"}, {"type":"frame","name":"Checkout"},{"type":"rect"#;
+        assert_eq!(
+            excalidraw_preview(head, false),
+            Some("Checkout".to_string())
+        );
+        let no_frame = r#"{"type":"excalidraw","elements":[{"type":"text"},{"type":"rect"#;
+        assert_eq!(excalidraw_preview(no_frame, false), None);
+    }
+
+    #[test]
+    fn long_previews_are_clipped() {
+        let long = format!("flowchart TD — {}", "x".repeat(200));
+        let clipped = clip(&long).unwrap();
+        assert_eq!(clipped.chars().count(), MAX_PREVIEW_CHARS);
+        assert!(clipped.ends_with('…'));
+    }
+}
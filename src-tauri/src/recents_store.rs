@@ -0,0 +1,221 @@
+use crate::paths::normalize;
+use crate::remote::is_url;
+use crate::{write_file, RecentItem};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Longest `flush` waits for the writer thread, e.g. on exit.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The recents list, read from `recents.json` once at startup and kept in memory.
+/// Every change is made under one lock, so two commands updating recents at the same
+/// time can't drop each other's entries. A background thread writes the list back,
+/// folding a burst of changes (e.g. autosaves) into one write.
+pub struct RecentsStore {
+    shared: Arc<Shared>,
+    wake: Sender<()>,
+}
+
+struct Shared {
+    path: PathBuf,
+    recents: Mutex<Versioned>,
+    /// The last version the writer thread wrote, or tried to.
+    written: Mutex<u64>,
+    written_changed: Condvar,
+}
+
+struct Versioned {
+    items: Vec<RecentItem>,
+    /// Bumped by every change, so `flush` knows what it waits for.
+    version: u64,
+}
+
+impl RecentsStore {
+    /// Loads `path`; a missing or corrupt file starts an empty list.
+    pub fn load(path: PathBuf) -> Self {
+        let items = read_items(&path);
+        let shared = Arc::new(Shared {
+            path,
+            recents: Mutex::new(Versioned { items, version: 0 }),
+            written: Mutex::new(0),
+            written_changed: Condvar::new(),
+        });
+        let (wake, woken) = mpsc::channel();
+        let writer = Arc::clone(&shared);
+        thread::spawn(move || write_changes(&writer, &woken));
+        RecentsStore { shared, wake }
+    }
+
+    /// Re-reads the file after something other than the store replaced it, e.g. a
+    /// data import in replace mode.
+    pub fn reload(&self) {
+        let items = read_items(&self.shared.path);
+        self.shared.recents.lock().unwrap().items = items;
+    }
+
+    pub fn snapshot(&self) -> Vec<RecentItem> {
+        self.shared.recents.lock().unwrap().items.clone()
+    }
+
+    /// Applies `change` to the list under the lock and schedules a write.
+    pub fn update<R>(&self, change: impl FnOnce(&mut Vec<RecentItem>) -> R) -> R {
+        let result = {
+            let mut recents = self.shared.recents.lock().unwrap();
+            let result = change(&mut recents.items);
+            recents.version += 1;
+            result
+        };
+        let _ = self.wake.send(());
+        result
+    }
+
+    /// Waits until every change made so far is on disk, e.g. before the app exits.
+    pub fn flush(&self) {
+        let version = self.shared.recents.lock().unwrap().version;
+        let written = self.shared.written.lock().unwrap();
+        let _ = self
+            .shared
+            .written_changed
+            .wait_timeout_while(written, FLUSH_TIMEOUT, |written| *written < version)
+            .unwrap();
+    }
+}
+
+/// Reads the list, normalizing paths written before recents stored them
+/// normalized; see `paths::normalize`. Remote recents keep their URL as it is. Entries that turn out to be the same file are
+/// merged into the first, which stays pinned if any of them was.
+fn read_items(path: &Path) -> Vec<RecentItem> {
+    let items: Vec<RecentItem> = fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    let mut merged: Vec<RecentItem> = Vec::with_capacity(items.len());
+    for mut item in items {
+        if !is_url(&item.path) {
+            item.path = normalize(Path::new(&item.path))
+                .to_string_lossy()
+                .to_string();
+        }
+        match merged
+            .iter_mut()
+            .find(|existing| existing.kind == item.kind && existing.path == item.path)
+        {
+            Some(existing) => existing.pinned |= item.pinned,
+            None => merged.push(item),
+        }
+    }
+    merged
+}
+
+/// Writes the list each time the store is woken, until the store is dropped.
+fn write_changes(shared: &Shared, woken: &Receiver<()>) {
+    while woken.recv().is_ok() {
+        // Later changes are already in the list; one write covers them all.
+        while woken.try_recv().is_ok() {}
+        let (contents, version) = {
+            let recents = shared.recents.lock().unwrap();
+            (
+                serde_json::to_string_pretty(&recents.items),
+                recents.version,
+            )
+        };
+        let written = contents
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                write_file(&shared.path, &contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = written {
+            log::warn!("recents_store: failed to save recents: {}", error);
+        }
+        *shared.written.lock().unwrap() = version;
+        shared.written_changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(index: usize) -> RecentItem {
+        RecentItem {
+            kind: "mermaid".to_string(),
+            path: format!("/work/flow-{index}.mmd"),
+            name: None,
+            updated_at: index as u64,
+            root: None,
+            recorded_size: None,
+            recorded_modified_at: None,
+            encrypted: false,
+            pinned: false,
+        }
+    }
+
+    fn paths(recents: &[RecentItem]) -> Vec<String> {
+        let mut paths: Vec<String> = recents.iter().map(|item| item.path.clone()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn concurrent_updates_are_all_kept_and_written() {
+        let dir = std::env::temp_dir().join(format!("excalibur-recents-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("recents.json");
+        let store = Arc::new(RecentsStore::load(file.clone()));
+        assert!(store.snapshot().is_empty());
+
+        let threads: Vec<_> = (0..16)
+            .map(|worker| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    for index in 0..25 {
+                        store.update(|recents| recents.push(item(worker * 100 + index)));
+                    }
+                })
+            })
+            .collect();
+        for worker in threads {
+            worker.join().unwrap();
+        }
+        store.flush();
+
+        let in_memory = store.snapshot();
+        assert_eq!(in_memory.len(), 16 * 25);
+        let on_disk: Vec<RecentItem> =
+            serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(paths(&on_disk), paths(&in_memory));
+        assert_eq!(
+            paths(&RecentsStore::load(file).snapshot()),
+            paths(&in_memory)
+        );
+    }
+
+    #[test]
+    fn urls_survive_a_reload_unchanged() {
+        let dir =
+            std::env::temp_dir().join(format!("excalibur-recents-urls-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("recents.json");
+        let url = "https://example.com/diagrams/../flow.mmd?raw=1".to_string();
+        let store = RecentsStore::load(file.clone());
+        store.update(|recents| {
+            recents.push(RecentItem {
+                path: url.clone(),
+                ..item(1)
+            });
+            recents.push(item(2));
+        });
+        store.flush();
+
+        let reloaded = RecentsStore::load(file).snapshot();
+        assert_eq!(reloaded[0].path, url);
+        assert_eq!(reloaded[1].path, item(2).path);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::background::{wait, IdlePolicy};
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{file_status, load_recents, FileStatus};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Longest wait between checks of a path on a volume that isn't reachable.
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Start-screen windows that want `recent-item-stale` events, and whether the
+/// poller thread is running.
+#[derive(Default)]
+pub struct RecentsWatch {
+    windows: Mutex<HashSet<String>>,
+    running: AtomicBool,
+}
+
+#[derive(Serialize, Clone)]
+struct StaleRecent {
+    kind: String,
+    path: String,
+    #[serde(flatten)]
+    status: FileStatus,
+}
+
+/// When a path may next be checked, and how long the last back-off was.
+struct Backoff {
+    next_check: Instant,
+    delay: Duration,
+}
+
+/// True when the file's folder can't be reached at all (unmounted volume, dropped
+/// network share) as opposed to the file simply having been deleted.
+fn is_unreachable(path: &Path) -> bool {
+    match std::fs::metadata(path) {
+        Ok(_) => false,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            path.parent().is_some_and(|parent| !parent.exists())
+        }
+        Err(_) => true,
+    }
+}
+
+fn poll_once(
+    app: &AppHandle,
+    last_seen: &mut HashMap<String, (Option<u64>, Option<u64>)>,
+    backoff: &mut HashMap<String, Backoff>,
+) {
+    // Stale events would reveal file names behind the privacy lock.
+    if crate::privacy::ensure_unlocked(app).is_err() {
+        return;
+    }
+    let now = Instant::now();
+    for item in load_recents(app) {
+        if backoff
+            .get(&item.path)
+            .is_some_and(|backoff| backoff.next_check > now)
+        {
+            continue;
+        }
+        let path = Path::new(&item.path);
+        if is_unreachable(path) {
+            let delay = backoff.get(&item.path).map_or(POLL_INTERVAL, |backoff| {
+                (backoff.delay * 2).min(MAX_BACKOFF)
+            });
+            backoff.insert(
+                item.path.clone(),
+                Backoff {
+                    next_check: now + delay,
+                    delay,
+                },
+            );
+            continue;
+        }
+        backoff.remove(&item.path);
+
+        let status = file_status(path);
+        let current = (status.size, status.modified_at);
+        let known = *last_seen
+            .entry(item.path.clone())
+            .or_insert((item.recorded_size, item.recorded_modified_at));
+        if current != known {
+            last_seen.insert(item.path.clone(), current);
+            // Rebuilt lazily by the next `list_recents`, not here.
+            crate::recent_previews::invalidate(app, &item.path);
+            let _ = app.emit(
+                "recent-item-stale",
+                StaleRecent {
+                    kind: item.kind,
+                    path: item.path,
+                    status,
+                },
+            );
+        }
+    }
+}
+
+/// Re-stats the recents every `POLL_INTERVAL` while any start screen is subscribed.
+fn run_poller(app: AppHandle) {
+    let mut last_seen = HashMap::new();
+    let mut backoff = HashMap::new();
+    loop {
+        let watch = app.state::<RecentsWatch>();
+        if watch.windows.lock().unwrap().is_empty() {
+            watch.running.store(false, Ordering::SeqCst);
+            // A window may have subscribed between the check and the store.
+            if watch.windows.lock().unwrap().is_empty()
+                || watch.running.swap(true, Ordering::SeqCst)
+            {
+                return;
+            }
+        }
+        poll_once(&app, &mut last_seen, &mut backoff);
+        // Nobody looks at the start screen while idle; resume with activity.
+        wait(&app, "recents-watch", POLL_INTERVAL, IdlePolicy::Pause);
+    }
+}
+
+/// Drops a window's subscription, e.g. when it is closed.
+pub fn unsubscribe(app: &AppHandle, label: &str) {
+    app.state::<RecentsWatch>()
+        .windows
+        .lock()
+        .unwrap()
+        .remove(label);
+}
+
+/// Subscribes the calling start screen to `recent-item-stale` events, sent when a
+/// listed file changes outside the app; its cached preview is dropped at the same
+/// time. Returns false when watching is turned off in settings.
+#[tauri::command]
+pub fn start_recents_watch(app: AppHandle, window: WebviewWindow) -> Result<bool, CommandError> {
+    if !load_settings(&app).watch_recent_files {
+        return Ok(false);
+    }
+    let watch = app.state::<RecentsWatch>();
+    watch
+        .windows
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string());
+    if !watch.running.swap(true, Ordering::SeqCst) {
+        let poller_app = app.clone();
+        std::thread::spawn(move || run_poller(poller_app));
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn stop_recents_watch(app: AppHandle, window: WebviewWindow) {
+    unsubscribe(&app, window.label());
+}
@@ -0,0 +1,322 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{content_hash, file_status, OpenPathResponse};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// How deep below a registered root or the Documents folder the search goes.
+const ROOT_SEARCH_DEPTH: usize = 8;
+/// Folders a single search may visit before it gives up on the rest.
+const MAX_VISITED_DIRS: usize = 20_000;
+const MAX_CANDIDATES: usize = 20;
+
+/// Directory names never worth descending into, like in `scan::scan_workspace`.
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "dist"];
+
+/// A file that might be the one that went missing.
+#[derive(Serialize, Debug)]
+pub struct RelocationCandidate {
+    path: String,
+    /// "same_content" when the bytes match what was last saved, "same_size" when
+    /// only the size matches, otherwise "same_name".
+    confidence: &'static str,
+    /// Where it was found: "nearby" (the old folder's tree), "root" (a registered
+    /// root) or "documents" (the default save folder).
+    source: &'static str,
+    size: Option<u64>,
+    modified_at: Option<u64>,
+}
+
+/// Collects files named `name` below `root`, at most `max_depth` folders down.
+/// Hidden and build folders are skipped and symlinked folders aren't followed.
+fn find_named(
+    root: &Path,
+    name: &str,
+    max_depth: usize,
+    visited: &mut usize,
+    found: &mut Vec<PathBuf>,
+) {
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if *visited >= MAX_VISITED_DIRS {
+            return;
+        }
+        *visited += 1;
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            if file_type.is_dir() {
+                if depth < max_depth
+                    && !entry_name.starts_with('.')
+                    && !SKIPPED_DIRS.contains(&entry_name.as_str())
+                {
+                    pending.push((entry.path(), depth + 1));
+                }
+            } else if file_type.is_file() && entry_name == name {
+                found.push(entry.path());
+            }
+        }
+    }
+}
+
+/// The folders to search, in order of preference: the old folder's tree `climb`
+/// levels up, then registered roots, then the default save folder.
+fn search_locations(
+    old_path: &Path,
+    climb: usize,
+    roots: &[PathBuf],
+    documents: Option<PathBuf>,
+) -> Vec<(PathBuf, usize, &'static str)> {
+    let mut locations = Vec::new();
+    if let Some(nearby) = old_path.ancestors().skip(1).take(climb + 1).last() {
+        locations.push((nearby.to_path_buf(), climb + 2, "nearby"));
+    }
+    for root in roots {
+        locations.push((root.clone(), ROOT_SEARCH_DEPTH, "root"));
+    }
+    if let Some(documents) = documents {
+        locations.push((documents, ROOT_SEARCH_DEPTH, "documents"));
+    }
+    locations
+}
+
+/// Number of leading path components two paths share.
+fn shared_components(a: &Path, b: &Path) -> usize {
+    a.components()
+        .zip(b.components())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Ranks same-named files: matching content first, then matching size, then the
+/// ones closest to the old location, then the most recently modified. Files are
+/// only hashed when their size matches the recorded one (or no size was recorded).
+fn rank_candidates(
+    old_path: &Path,
+    found: Vec<(PathBuf, &'static str)>,
+    recorded_size: Option<u64>,
+    recorded_hash: Option<&str>,
+) -> Vec<RelocationCandidate> {
+    let mut ranked: Vec<(u8, usize, RelocationCandidate)> = found
+        .into_iter()
+        .map(|(path, source)| {
+            let status = file_status(&path);
+            let size_matches = recorded_size.is_some() && recorded_size == status.size;
+            let content_matches = recorded_hash.is_some_and(|hash| {
+                (size_matches || recorded_size.is_none())
+                    && fs::read(&path).is_ok_and(|bytes| content_hash(&bytes) == hash)
+            });
+            let (rank, confidence) = if content_matches {
+                (0, "same_content")
+            } else if size_matches {
+                (1, "same_size")
+            } else {
+                (2, "same_name")
+            };
+            let shared = shared_components(old_path, &path);
+            let candidate = RelocationCandidate {
+                path: path.to_string_lossy().to_string(),
+                confidence,
+                source,
+                size: status.size,
+                modified_at: status.modified_at,
+            };
+            (rank, shared, candidate)
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.cmp(&a.1))
+            .then(b.2.modified_at.cmp(&a.2.modified_at))
+    });
+    ranked
+        .into_iter()
+        .map(|(_, _, candidate)| candidate)
+        .take(MAX_CANDIDATES)
+        .collect()
+}
+
+/// Looks for a missing recent or favorite in the places it most likely moved to,
+/// returning same-named files best match first. Nothing is changed until the user
+/// picks one with `confirm_relocation`.
+#[tauri::command]
+pub async fn relocate_file(
+    app: AppHandle,
+    kind: String,
+    old_path: String,
+) -> Result<Vec<RelocationCandidate>, CommandError> {
+    if !matches!(kind.as_str(), "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind { kind });
+    }
+    let old = PathBuf::from(&old_path);
+    let Some(name) = crate::file_name(&old) else {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{old_path} has no file name"),
+        });
+    };
+    let recorded_size = crate::load_recents(&app)
+        .into_iter()
+        .find(|item| item.kind == kind && item.path == old_path)
+        .and_then(|item| item.recorded_size);
+    let recorded_hash = crate::local_history::last_saved_hash(&app, &old);
+    let roots: Vec<PathBuf> = crate::roots::load_roots(&app)
+        .into_iter()
+        .map(|root| PathBuf::from(root.path))
+        .collect();
+    let locations = search_locations(
+        &old,
+        load_settings(&app).relocate_search_depth,
+        &roots,
+        app.path().document_dir().ok(),
+    );
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut visited = 0;
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for (location, depth, source) in locations {
+            let mut matches = Vec::new();
+            find_named(&location, &name, depth, &mut visited, &mut matches);
+            for path in matches {
+                if path != old && seen.insert(path.clone()) {
+                    found.push((path, source));
+                }
+            }
+        }
+        log::info!(
+            "relocate_file: {} candidates for {} after {} folders",
+            found.len(),
+            old_path,
+            visited
+        );
+        rank_candidates(&old, found, recorded_size, recorded_hash.as_deref())
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })
+}
+
+/// Points every record of `old_path` at `new_path`: recents (including their
+/// root-relative form), favorites, saved workspaces and the save history. Then opens
+/// the file at its new location, re-detecting a recent's kind in case the file was
+/// renamed to another extension as well as moved.
+#[tauri::command]
+pub async fn confirm_relocation(
+    app: AppHandle,
+    old_path: String,
+    new_path: String,
+) -> Result<OpenPathResponse, CommandError> {
+    if !Path::new(&new_path).is_file() {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{new_path} is not a file"),
+        });
+    }
+    log::info!("confirm_relocation: {} -> {}", old_path, new_path);
+    let recent_kind = crate::load_recents(&app)
+        .into_iter()
+        .find(|item| item.path == old_path)
+        .map(|item| item.kind);
+    crate::relocate_recents(&app, &old_path, &new_path);
+    crate::favorites::replace_path(&app, &old_path, &new_path)?;
+    crate::workspaces::replace_document_path(&app, &old_path, &new_path)?;
+    crate::local_history::move_history(&app, Path::new(&old_path), Path::new(&new_path));
+    match recent_kind {
+        Some(kind) => crate::open_recent(app, kind, new_path, None, None).await,
+        None => crate::open_path(app, new_path, None, None, None).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn finds_same_named_files_and_skips_hidden_folders() {
+        let dir = temp_dir("relocate", "find");
+        write(&dir.join("a/flow.mmd"), "graph TD");
+        write(&dir.join("b/c/flow.mmd"), "graph TD");
+        write(&dir.join(".git/flow.mmd"), "graph TD");
+        write(&dir.join("node_modules/flow.mmd"), "graph TD");
+        write(&dir.join("a/other.mmd"), "graph TD");
+
+        let mut visited = 0;
+        let mut found = Vec::new();
+        find_named(&dir, "flow.mmd", 8, &mut visited, &mut found);
+        found.sort();
+        assert_eq!(found, [dir.join("a/flow.mmd"), dir.join("b/c/flow.mmd")]);
+
+        let mut shallow = Vec::new();
+        find_named(&dir, "flow.mmd", 1, &mut visited, &mut shallow);
+        assert_eq!(shallow, [dir.join("a/flow.mmd")]);
+    }
+
+    #[test]
+    fn nearby_search_climbs_the_configured_levels() {
+        let old = Path::new("/work/project/docs/flows/login.mmd");
+        let locations = search_locations(old, 2, &[PathBuf::from("/vault")], None);
+        assert_eq!(
+            locations
+                .iter()
+                .map(|(path, _, source)| (path.as_path(), *source))
+                .collect::<Vec<_>>(),
+            [
+                (Path::new("/work/project"), "nearby"),
+                (Path::new("/vault"), "root"),
+            ]
+        );
+        assert_eq!(
+            search_locations(old, 0, &[], None)[0].0,
+            Path::new("/work/project/docs/flows")
+        );
+    }
+
+    #[test]
+    fn ranks_matching_content_before_proximity() {
+        let dir = temp_dir("relocate", "rank");
+        let old = dir.join("docs/flows/login.mmd");
+        let near = dir.join("docs/login.mmd");
+        let same_size = dir.join("elsewhere/login.mmd");
+        let same_content = dir.join("far/away/login.mmd");
+        write(&near, "graph TD; A-->B; B-->C");
+        write(&same_size, "graph LR");
+        write(&same_content, "graph TD");
+
+        let ranked = rank_candidates(
+            &old,
+            vec![
+                (near.clone(), "nearby"),
+                (same_size.clone(), "nearby"),
+                (same_content.clone(), "root"),
+            ],
+            Some(8),
+            Some(&content_hash(b"graph TD")),
+        );
+        let order: Vec<(&str, &str)> = ranked
+            .iter()
+            .map(|candidate| (candidate.path.as_str(), candidate.confidence))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (same_content.to_str().unwrap(), "same_content"),
+                (same_size.to_str().unwrap(), "same_size"),
+                (near.to_str().unwrap(), "same_name"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::CommandError;
+use crate::http::http_client;
+use crate::settings::load_settings;
+use crate::{
+    gzip, markdown_blocks, png_scene, scene_schema, text_encoding, update_recents, OpenFileResponse,
+};
+use percent_encoding::percent_decode_str;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether a recents entry or `open-file` path is a URL rather than a file.
+pub fn is_url(path: &str) -> bool {
+    ["https://", "http://"].iter().any(|scheme| {
+        path.get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// Parses a URL documents may be fetched from: `https`, or `http` too when
+/// `allow_http` is set.
+fn parse_url(raw: &str, allow_http: bool) -> Result<url::Url, CommandError> {
+    let url = url::Url::parse(raw.trim()).map_err(|error| CommandError::InvalidArgument {
+        message: format!("{raw} is not a valid URL: {error}"),
+    })?;
+    match url.scheme() {
+        "https" => Ok(url),
+        "http" if allow_http => Ok(url),
+        "http" => Err(CommandError::InvalidArgument {
+            message: format!("{url} is not https; allow http URLs in the settings to open it"),
+        }),
+        scheme => Err(CommandError::InvalidArgument {
+            message: format!("Documents can't be opened from {scheme} URLs"),
+        }),
+    }
+}
+
+/// What the document at `url` is called and detected by: the URL without its query
+/// and fragment, so `flow.md?raw=1` is still Markdown.
+fn document_path(url: &url::Url) -> PathBuf {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    PathBuf::from(url.as_str())
+}
+
+/// The last segment of the URL's path with its escapes undone, e.g. `My Flow.md`.
+fn url_file_name(url: &url::Url) -> Option<String> {
+    url.path_segments()?
+        .next_back()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+}
+
+fn fetch_failed(url: &url::Url, error: reqwest::Error) -> CommandError {
+    CommandError::RemoteFetchFailed {
+        url: url.to_string(),
+        status: error.status().map(|status| status.as_u16()),
+        timed_out: error.is_timeout(),
+        message: error.to_string(),
+    }
+}
+
+/// Fetches the bytes at `url`, following a few redirects; a response that ends up
+/// at a scheme `parse_url` refuses is not used. Bodies over `limit` bytes are
+/// refused with `FileTooLarge`, without downloading the rest.
+pub async fn fetch(app: &AppHandle, url: &url::Url, limit: u64) -> Result<Vec<u8>, CommandError> {
+    let settings = load_settings(app);
+    let client = http_client(&settings, FETCH_TIMEOUT).map_err(|message| {
+        CommandError::RemoteFetchFailed {
+            url: url.to_string(),
+            status: None,
+            timed_out: false,
+            message,
+        }
+    })?;
+    let mut response = client
+        .get(url.as_str())
+        .send()
+        .await
+        .map_err(|error| fetch_failed(url, error))?;
+    parse_url(response.url().as_str(), settings.allow_http_urls)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CommandError::RemoteFetchFailed {
+            url: url.to_string(),
+            status: Some(status.as_u16()),
+            timed_out: false,
+            message: status.to_string(),
+        });
+    }
+    let too_large = |size| CommandError::FileTooLarge {
+        path: url.to_string(),
+        size,
+        limit,
+    };
+    if let Some(length) = response.content_length().filter(|length| *length > limit) {
+        return Err(too_large(length));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|error| fetch_failed(url, error))?
+    {
+        let size = (bytes.len() + chunk.len()) as u64;
+        if size > limit {
+            return Err(too_large(size));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Fetches a document and returns it decompressed, with the path it goes by and
+/// whether it was gzipped.
+async fn fetch_document(
+    app: &AppHandle,
+    raw_url: &str,
+) -> Result<(url::Url, PathBuf, Vec<u8>, bool), CommandError> {
+    let settings = load_settings(app);
+    let url = parse_url(raw_url, settings.allow_http_urls)?;
+    log::info!("remote: fetching {}", url);
+    let bytes = fetch(app, &url, settings.max_file_bytes).await?;
+    let path = document_path(&url);
+    let compressed = gzip::is_gzip(&bytes);
+    let bytes = gzip::decompress(&path, bytes, settings.max_file_bytes)?;
+    Ok((url, path, bytes, compressed))
+}
+
+/// Decodes fetched text, and names its encoding when it isn't plain UTF-8.
+fn decode(path: &Path, bytes: Vec<u8>) -> Result<(String, Option<&'static str>), CommandError> {
+    let (contents, encoding) = text_encoding::decode(path, bytes)?;
+    let reported = (encoding != text_encoding::Encoding::Utf8).then(|| encoding.name());
+    Ok((contents, reported))
+}
+
+/// Opens an Excalidraw scene from an `https` URL, checked like a local file. The
+/// response's `path` is the URL and `remote` is set: there is nothing to save back
+/// to, so only Save As works. The URL is listed in recents to open it again.
+#[tauri::command]
+pub async fn load_excalidraw_url(
+    app: AppHandle,
+    url: String,
+) -> Result<OpenFileResponse, CommandError> {
+    let (url, path, bytes, compressed) = fetch_document(&app, &url).await?;
+    let (contents, encoding, embedded_in_png) = if png_scene::is_png(&bytes) {
+        (png_scene::extract_scene(&path, &bytes)?, None, true)
+    } else {
+        let (contents, encoding) = decode(&path, bytes)?;
+        (contents, encoding, false)
+    };
+    let warnings = scene_schema::validate_excalidraw(&contents)
+        .err()
+        .unwrap_or_default();
+    let name = url_file_name(&url);
+    update_recents(&app, "excalidraw", url.as_str(), name.clone());
+    Ok(OpenFileResponse {
+        schema: scene_schema::check_opened(&app, url.as_str(), &contents),
+        valid: Some(warnings.is_empty()),
+        warnings,
+        encoding,
+        compressed,
+        embedded_in_png,
+        remote: true,
+        path: url.to_string(),
+        name,
+        contents,
+        ..Default::default()
+    })
+}
+
+/// Opens a Mermaid diagram from an `https` URL; see `load_excalidraw_url`. Markdown
+/// is narrowed to its mermaid blocks as for local files.
+#[tauri::command]
+pub async fn load_mermaid_url(
+    app: AppHandle,
+    url: String,
+) -> Result<OpenFileResponse, CommandError> {
+    let (url, path, bytes, compressed) = fetch_document(&app, &url).await?;
+    let (contents, encoding) = decode(&path, bytes)?;
+    let source = markdown_blocks::mermaid_source(&path, contents)?;
+    let name = url_file_name(&url);
+    update_recents(&app, "mermaid", url.as_str(), name.clone());
+    Ok(OpenFileResponse {
+        encoding,
+        compressed,
+        remote: true,
+        path: url.to_string(),
+        name,
+        contents: source.contents,
+        block_range: source.block_range,
+        blocks: source.blocks,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_https_is_accepted_unless_http_is_allowed() {
+        let code = |raw, allow_http| parse_url(raw, allow_http).map_err(|error| error.code());
+        assert!(code("https://gist.githubusercontent.com/a/raw/flow.mmd", false).is_ok());
+        assert_eq!(
+            code("http://example.com/flow.mmd", false),
+            Err("invalid_argument")
+        );
+        assert!(code("http://example.com/flow.mmd", true).is_ok());
+        assert_eq!(code("file:///etc/passwd", true), Err("invalid_argument"));
+        assert_eq!(code("not a url", true), Err("invalid_argument"));
+    }
+
+    #[test]
+    fn documents_are_named_by_the_url_path() {
+        let url = url::Url::parse("https://example.com/raw/My%20Flow.md?raw=1#top").unwrap();
+        assert_eq!(url_file_name(&url).as_deref(), Some("My Flow.md"));
+        assert_eq!(
+            document_path(&url),
+            PathBuf::from("https://example.com/raw/My%20Flow.md")
+        );
+        let url = url::Url::parse("https://example.com/").unwrap();
+        assert_eq!(url_file_name(&url), None);
+        assert!(is_url("HTTPS://example.com/flow.mmd"));
+        assert!(!is_url("/work/https/flow.mmd"));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::CommandError;
+use crate::trash::move_file;
+use crate::{file_name, modify_recents, now_epoch, paths, relocate_recents};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+#[derive(Serialize)]
+pub struct RenamedFile {
+    path: String,
+    name: String,
+}
+
+/// Where renaming `old` to `new_name` puts it. A bare name stays in the same folder;
+/// a path, relative to that folder or absolute, moves the file. Without an extension
+/// the file keeps its own.
+fn rename_target(old: &Path, new_name: &str) -> Result<PathBuf, CommandError> {
+    let new_name = new_name.trim();
+    let given = Path::new(new_name);
+    let named = matches!(given.components().next_back(), Some(Component::Normal(_)));
+    if new_name.is_empty() || !named {
+        return Err(CommandError::InvalidArgument {
+            message: format!("\"{new_name}\" can't be used as a file name"),
+        });
+    }
+    let mut target = old.parent().unwrap_or(Path::new("")).join(given);
+    if target.extension().is_none() {
+        if let Some(extension) = old.extension() {
+            target.set_extension(extension);
+        }
+    }
+    Ok(paths::normalize(&target))
+}
+
+/// Whether `a` and `b` are the same file, as in a rename that only changes case on
+/// a case-insensitive volume.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Moves `old` to `target`, refusing to replace another file unless `overwrite`.
+fn move_document(old: &Path, target: &Path, overwrite: bool) -> Result<(), CommandError> {
+    if !old.is_file() {
+        return Err(match fs::metadata(old) {
+            Err(error) => CommandError::from_io(error, old),
+            Ok(_) => CommandError::InvalidArgument {
+                message: format!("{} is not a file", old.display()),
+            },
+        });
+    }
+    if target.exists() && !same_file(old, target) && (!overwrite || target.is_dir()) {
+        return Err(CommandError::FileExists {
+            path: target.to_string_lossy().to_string(),
+        });
+    }
+    if let Some(parent) = target.parent() {
+        if !parent.is_dir() {
+            return Err(CommandError::NotFound {
+                path: parent.to_string_lossy().to_string(),
+            });
+        }
+    }
+    move_file(old, target).map_err(|error| CommandError::from_io(error, target))
+}
+
+/// Renames the document at `old_path` to `new_name`, or moves it when `new_name` is a
+/// path, and points its recents, favorites, workspaces, save history and checksum
+/// sidecar at the new location. Replacing an existing file needs `overwrite`.
+#[tauri::command]
+pub fn rename_file(
+    app: AppHandle,
+    old_path: String,
+    new_name: String,
+    overwrite: Option<bool>,
+) -> Result<RenamedFile, CommandError> {
+    let old = paths::normalize(Path::new(&old_path));
+    let target = rename_target(&old, &new_name)?;
+    move_document(&old, &target, overwrite.unwrap_or(false))?;
+    let old_path = old.to_string_lossy().to_string();
+    let path = target.to_string_lossy().to_string();
+    let name = file_name(&target).unwrap_or_default();
+    log::info!("rename_file: {} -> {}", old_path, path);
+    if let Err(error) = crate::checksums::move_sidecar(&old, &target) {
+        log::warn!(
+            "rename_file: failed to move the checksum of {}: {}",
+            old_path,
+            error
+        );
+    }
+
+    relocate_recents(&app, &old_path, &path);
+    modify_recents(&app, |recents| {
+        for item in recents.iter_mut().filter(|item| item.path == path) {
+            item.name = Some(name.clone());
+            item.updated_at = now_epoch();
+        }
+    });
+    let _ = app.emit("recents-changed", ());
+    crate::favorites::replace_path(&app, &old_path, &path)?;
+    crate::workspaces::replace_document_path(&app, &old_path, &path)?;
+    crate::local_history::move_history(&app, &old, &target);
+    Ok(RenamedFile { path, name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn targets_stay_in_the_folder_and_keep_the_extension() {
+        let old = Path::new("/work/diagrams/flow.excalidraw");
+        let target = |name| rename_target(old, name).unwrap();
+        assert_eq!(
+            target("checkout"),
+            Path::new("/work/diagrams/checkout.excalidraw")
+        );
+        assert_eq!(
+            target(" checkout.json "),
+            Path::new("/work/diagrams/checkout.json")
+        );
+        assert_eq!(
+            target("../archive/flow"),
+            Path::new("/work/archive/flow.excalidraw")
+        );
+        assert_eq!(
+            target("/shared/flow v2"),
+            Path::new("/shared/flow v2.excalidraw")
+        );
+        for name in ["", "  ", "..", "archive/.."] {
+            assert_eq!(
+                rename_target(old, name).unwrap_err().code(),
+                "invalid_argument",
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn existing_files_are_only_replaced_with_overwrite() {
+        let dir = std::env::temp_dir().join(format!("excalibur-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("flow.mmd");
+        let taken = dir.join("taken.mmd");
+        fs::write(&old, "graph TD").unwrap();
+        fs::write(&taken, "graph LR").unwrap();
+
+        let refused = move_document(&old, &taken, false).unwrap_err();
+        assert_eq!(refused.code(), "file_exists");
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "graph LR");
+
+        move_document(&old, &taken, true).unwrap();
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "graph TD");
+
+        // Renaming onto itself is not a conflict.
+        move_document(&taken, &taken, false).unwrap();
+        assert_eq!(
+            move_document(&old, &dir.join("new.mmd"), false)
+                .unwrap_err()
+                .code(),
+            "not_found"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::CommandError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Export formats the frontend knows how to render.
+pub const RENDER_FORMATS: [&str; 2] = ["svg", "png"];
+
+/// Renders documents by handing them to the webview, which owns the Excalidraw and
+/// Mermaid renderers, and waiting for `submit_render` to bring the output back.
+#[derive(Default)]
+pub struct Renderer {
+    ready: Mutex<bool>,
+    ready_signal: Condvar,
+    pending: Mutex<HashMap<String, mpsc::Sender<Result<Vec<u8>, String>>>>,
+    next_request: AtomicU64,
+}
+
+#[derive(Serialize, Clone)]
+struct RenderRequest<'a> {
+    request_id: &'a str,
+    kind: &'a str,
+    contents: &'a str,
+    format: &'a str,
+}
+
+/// Blocks until the frontend has registered its `render-request` listener.
+pub fn wait_until_ready(app: &AppHandle, timeout: Duration) -> bool {
+    let renderer = app.state::<Renderer>();
+    let ready = renderer.ready.lock().unwrap();
+    let (ready, _) = renderer
+        .ready_signal
+        .wait_timeout_while(ready, timeout, |ready| !*ready)
+        .unwrap();
+    *ready
+}
+
+/// Asks the frontend to render `contents` as `format` and blocks until it answers.
+///
+/// Must not be called from the main thread: the webview needs it to run the render.
+pub fn render(
+    app: &AppHandle,
+    kind: &str,
+    contents: &str,
+    format: &str,
+    timeout: Duration,
+) -> Result<Vec<u8>, CommandError> {
+    if !RENDER_FORMATS.contains(&format) {
+        return Err(CommandError::InvalidArgument {
+            message: format!("Unsupported export format \"{format}\""),
+        });
+    }
+    let renderer = app.state::<Renderer>();
+    let request_id = format!(
+        "render-{}",
+        renderer.next_request.fetch_add(1, Ordering::Relaxed)
+    );
+    let (sender, receiver) = mpsc::channel();
+    renderer
+        .pending
+        .lock()
+        .unwrap()
+        .insert(request_id.clone(), sender);
+
+    let emitted = app.emit(
+        "render-request",
+        RenderRequest {
+            request_id: &request_id,
+            kind,
+            contents,
+            format,
+        },
+    );
+    let result = match emitted {
+        Ok(()) => receiver
+            .recv_timeout(timeout)
+            .map_err(|_| CommandError::Io {
+                message: format!("Timed out rendering {kind} document"),
+            }),
+        Err(error) => Err(CommandError::Io {
+            message: error.to_string(),
+        }),
+    };
+    renderer.pending.lock().unwrap().remove(&request_id);
+    result?.map_err(|message| CommandError::Io { message })
+}
+
+/// Called by the frontend once it listens for `render-request` events.
+#[tauri::command]
+pub fn render_ready(app: AppHandle) {
+    let renderer = app.state::<Renderer>();
+    *renderer.ready.lock().unwrap() = true;
+    renderer.ready_signal.notify_all();
+}
+
+/// Completes a render request with SVG `text`, PNG `bytes` or an `error`.
+#[tauri::command]
+pub fn submit_render(
+    app: AppHandle,
+    request_id: String,
+    text: Option<String>,
+    bytes: Option<Vec<u8>>,
+    error: Option<String>,
+) -> Result<(), CommandError> {
+    let sender = app
+        .state::<Renderer>()
+        .pending
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .ok_or_else(|| CommandError::InvalidArgument {
+            message: format!("No pending render request \"{request_id}\""),
+        })?;
+    let result = match (error, text, bytes) {
+        (Some(error), _, _) => Err(error),
+        (None, Some(text), _) => Ok(text.into_bytes()),
+        (None, None, Some(bytes)) => Ok(bytes),
+        (None, None, None) => Err("The renderer returned no output".to_string()),
+    };
+    // The requester may have timed out and dropped its receiver already.
+    let _ = sender.send(result);
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use crate::error::CommandError;
+use crate::search::{search_candidates, snippet, Candidate, SearchFileError};
+use crate::{checksums, content_hash, file_watch, keep_backup, local_history, volumes, write_file};
+use regex::{NoExpand, Regex};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Snippets returned per file in a dry run.
+const MAX_SNIPPETS_PER_FILE: usize = 5;
+
+#[derive(Serialize)]
+pub struct ReplaceFileResult {
+    path: String,
+    match_count: usize,
+    snippets: Vec<String>,
+    /// Hash of the contents the matches were found in. Pass it back as `expected`
+    /// when applying so files edited in the meantime are reported as conflicts.
+    hash: String,
+}
+
+#[derive(Serialize)]
+pub struct ReplaceResponse {
+    dry_run: bool,
+    files: Vec<ReplaceFileResult>,
+    /// Files that changed on disk since the dry run and were left untouched.
+    conflicts: Vec<String>,
+    errors: Vec<SearchFileError>,
+    /// Excalidraw files in scope that were not searched; blind text replacement
+    /// would corrupt their JSON.
+    skipped_excalidraw: usize,
+    total_matches: usize,
+    files_changed: usize,
+}
+
+/// Replaces what `matcher` finds in the mermaid files among `candidates`; see
+/// `replace_in_files`. `expand` lets `replacement` refer to capture groups, and
+/// `save` writes the updated contents of a file, or refuses to.
+fn replace_in(
+    candidates: Vec<Candidate>,
+    matcher: &Regex,
+    replacement: &str,
+    expand: bool,
+    dry_run: bool,
+    expected: &HashMap<String, String>,
+    save: impl Fn(&Path, &str) -> Result<(), CommandError>,
+) -> ReplaceResponse {
+    let mut response = ReplaceResponse {
+        dry_run,
+        files: Vec::new(),
+        conflicts: Vec::new(),
+        errors: Vec::new(),
+        skipped_excalidraw: 0,
+        total_matches: 0,
+        files_changed: 0,
+    };
+
+    for candidate in candidates {
+        if candidate.kind != "mermaid" {
+            response.skipped_excalidraw += 1;
+            continue;
+        }
+        let path = candidate.path.to_string_lossy().to_string();
+        let contents = match fs::read_to_string(&candidate.path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                response.errors.push(SearchFileError {
+                    path,
+                    message: error.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let match_count = matcher.find_iter(&contents).count();
+        if match_count == 0 {
+            continue;
+        }
+        let hash = content_hash(contents.as_bytes());
+
+        if !dry_run {
+            // A file the dry run didn't report wasn't previewed, so it counts as changed.
+            if expected.get(&path) != Some(&hash) {
+                response.conflicts.push(path);
+                continue;
+            }
+            let updated = if expand {
+                matcher.replace_all(&contents, replacement)
+            } else {
+                matcher.replace_all(&contents, NoExpand(replacement))
+            };
+            if let Err(error) = save(&candidate.path, &updated) {
+                response.errors.push(SearchFileError {
+                    path,
+                    message: error.to_string(),
+                });
+                continue;
+            }
+            response.files_changed += 1;
+        }
+
+        let snippets = matcher
+            .find_iter(&contents)
+            .take(MAX_SNIPPETS_PER_FILE)
+            .map(|found| snippet(&contents, found.start(), found.end()))
+            .collect();
+        response.total_matches += match_count;
+        response.files.push(ReplaceFileResult {
+            path,
+            match_count,
+            snippets,
+            hash,
+        });
+    }
+
+    response
+}
+
+/// Writes the result of a replacement to `path` like a save: refused when the file is
+/// read-only or on a disconnected volume, and the replaced contents get a backup and
+/// a local history version.
+fn save(app: &AppHandle, path: &Path, contents: &str) -> Result<(), CommandError> {
+    if volumes::is_disconnected(app, path) {
+        return Err(volumes::disconnected_error(path));
+    }
+    crate::read_only::ensure_writable(app, path)?;
+    keep_backup(path, None)?;
+    let capture = local_history::capture_before_save(app, path);
+    file_watch::expect_write(app, path, contents.as_bytes());
+    write_file(path, contents)?;
+    local_history::finish_save(app, path, capture);
+    checksums::after_save(app, path);
+    Ok(())
+}
+
+/// Finds and replaces text across the mermaid files in `scope`.
+///
+/// With `dry_run` set nothing is written and each file's matches are reported.
+/// Otherwise `expected` (path → hash from the dry run) is required, and only files
+/// whose contents still match it are rewritten; the rest are skipped and returned
+/// as conflicts.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_in_files(
+    app: AppHandle,
+    scope: String,
+    root: Option<String>,
+    query: String,
+    replacement: String,
+    regex: bool,
+    dry_run: bool,
+    expected: Option<HashMap<String, String>>,
+) -> Result<ReplaceResponse, CommandError> {
+    if query.is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "Search text is empty".to_string(),
+        });
+    }
+    let pattern = if regex {
+        query.clone()
+    } else {
+        regex::escape(&query)
+    };
+    let matcher = Regex::new(&pattern).map_err(|error| CommandError::InvalidArgument {
+        message: format!("Invalid regular expression: {error}"),
+    })?;
+    let expected = match expected {
+        Some(expected) => expected,
+        None if dry_run => HashMap::new(),
+        None => {
+            return Err(CommandError::InvalidArgument {
+                message: "Replacing needs the file hashes from a dry run".to_string(),
+            })
+        }
+    };
+    log::info!(
+        "replace_in_files: scope={}, root={:?}, regex={}, dry_run={}",
+        scope,
+        root,
+        regex,
+        dry_run
+    );
+
+    let candidates = search_candidates(&app, &scope, root.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        replace_in(
+            candidates,
+            &matcher,
+            &replacement,
+            regex,
+            dry_run,
+            &expected,
+            |path, contents| save(&app, path, contents),
+        )
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn candidate(path: &Path, kind: &str) -> Candidate {
+        Candidate {
+            path: path.to_path_buf(),
+            kind: kind.to_string(),
+        }
+    }
+
+    fn write(path: &Path, contents: &str) -> Result<(), CommandError> {
+        write_file(path, contents)
+    }
+
+    /// What a dry run would report as `expected` for `path` as it is now.
+    fn previewed(path: &Path) -> HashMap<String, String> {
+        let hash = content_hash(&fs::read(path).unwrap());
+        HashMap::from([(path.to_string_lossy().to_string(), hash)])
+    }
+
+    #[test]
+    fn a_dry_run_reports_matches_and_writes_nothing() {
+        let dir = temp_dir("replace", "dry-run");
+        let flow = dir.join("flow.mmd");
+        let original = "flowchart TD\n  A[api v1] --> B[api v1 docs]\n";
+        fs::write(&flow, original).unwrap();
+        let scene = dir.join("scene.excalidraw");
+        fs::write(&scene, r#"{"elements":[{"text":"api v1"}]}"#).unwrap();
+        let matcher = Regex::new(&regex::escape("api v1")).unwrap();
+        let candidates = || vec![candidate(&flow, "mermaid"), candidate(&scene, "excalidraw")];
+
+        let dry = replace_in(
+            candidates(),
+            &matcher,
+            "api v2",
+            false,
+            true,
+            &HashMap::new(),
+            write,
+        );
+        assert_eq!(dry.total_matches, 2);
+        assert_eq!(dry.files_changed, 0);
+        assert_eq!(dry.skipped_excalidraw, 1);
+        assert_eq!(dry.files[0].snippets.len(), 2);
+        assert_eq!(fs::read_to_string(&flow).unwrap(), original);
+
+        // Applying with the dry run's hashes writes the file; the scene stays as it was.
+        let expected = HashMap::from([(
+            flow.to_string_lossy().to_string(),
+            dry.files[0].hash.clone(),
+        )]);
+        let applied = replace_in(
+            candidates(),
+            &matcher,
+            "api v2",
+            false,
+            false,
+            &expected,
+            write,
+        );
+        assert_eq!(applied.files_changed, 1);
+        assert_eq!(
+            fs::read_to_string(&flow).unwrap(),
+            "flowchart TD\n  A[api v2] --> B[api v2 docs]\n"
+        );
+        assert!(fs::read_to_string(&scene).unwrap().contains("api v1"));
+
+        // The file changed since that dry run, so the stale hash is a conflict.
+        let stale = replace_in(
+            candidates(),
+            &Regex::new("v2").unwrap(),
+            "v3",
+            false,
+            false,
+            &expected,
+            write,
+        );
+        assert_eq!(stale.conflicts, [flow.to_string_lossy()]);
+        assert_eq!(stale.files_changed, 0);
+
+        // Nor is a file the dry run never reported written.
+        let unseen = replace_in(
+            candidates(),
+            &Regex::new("v2").unwrap(),
+            "v3",
+            false,
+            false,
+            &HashMap::new(),
+            write,
+        );
+        assert_eq!(unseen.conflicts, [flow.to_string_lossy()]);
+        assert!(fs::read_to_string(&flow).unwrap().contains("api v2"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn only_regex_replacements_expand_capture_groups() {
+        let dir = temp_dir("replace", "groups");
+        let flow = dir.join("flow.mmd");
+        let matcher = Regex::new(r"(\w+) --> (\w+)").unwrap();
+
+        fs::write(&flow, "A --> B\n").unwrap();
+        replace_in(
+            vec![candidate(&flow, "mermaid")],
+            &matcher,
+            "$2 --> $1",
+            true,
+            false,
+            &previewed(&flow),
+            write,
+        );
+        assert_eq!(fs::read_to_string(&flow).unwrap(), "B --> A\n");
+
+        let refused = replace_in(
+            vec![candidate(&flow, "mermaid")],
+            &matcher,
+            "$2 --> $1",
+            false,
+            false,
+            &previewed(&flow),
+            |path, _| {
+                Err(CommandError::ReadOnlyDocument {
+                    path: path.to_string_lossy().to_string(),
+                    on_disk: true,
+                })
+            },
+        );
+        assert_eq!(refused.errors.len(), 1);
+        assert_eq!(refused.files_changed, 0);
+
+        replace_in(
+            vec![candidate(&flow, "mermaid")],
+            &matcher,
+            "$2 --> $1",
+            false,
+            false,
+            &previewed(&flow),
+            write,
+        );
+        assert_eq!(fs::read_to_string(&flow).unwrap(), "$2 --> $1\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
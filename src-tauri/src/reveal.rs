@@ -0,0 +1,109 @@
+use crate::error::CommandError;
+use crate::paths;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// The absolute form of `path`, which must exist.
+fn existing(path: &str) -> Result<PathBuf, CommandError> {
+    let path = Path::new(path);
+    std::fs::metadata(path).map_err(|error| CommandError::from_io(error, path))?;
+    Ok(paths::normalize(path))
+}
+
+/// Runs the file manager. Arguments go to it as they are, without a shell, so
+/// spaces and non-ASCII names need no quoting.
+fn spawn(command: &mut Command) -> Result<(), CommandError> {
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|error| CommandError::Io {
+            message: format!("couldn't open the file manager: {error}"),
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn reveal(path: &Path) -> Result<(), CommandError> {
+    spawn(Command::new("open").arg("-R").arg(path))
+}
+
+#[cfg(target_os = "windows")]
+fn reveal(path: &Path) -> Result<(), CommandError> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line and only takes `/select,` joined to a
+    // quoted path, which the usual argument escaping would break apart.
+    spawn(Command::new("explorer").raw_arg(format!("/select,\"{}\"", path.display())))
+}
+
+/// Asks the file manager to select the file over the FileManager1 D-Bus interface,
+/// which Nautilus, Dolphin, Nemo and others implement; without it, opens the folder.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal(path: &Path) -> Result<(), CommandError> {
+    if let Ok(uri) = url::Url::from_file_path(path) {
+        let selected = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+                &format!("array:string:{uri}"),
+                "string:",
+            ])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if selected {
+            return Ok(());
+        }
+    }
+    let folder = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    spawn(Command::new("xdg-open").arg(folder))
+}
+
+/// Shows `path` in the system file manager with the file selected: Finder, Explorer,
+/// or on Linux whichever file manager handles folders.
+#[tauri::command]
+pub fn reveal_in_file_manager(path: String) -> Result<(), CommandError> {
+    let path = existing(&path)?;
+    log::info!("reveal: {}", path.display());
+    reveal(&path)
+}
+
+/// Puts the absolute form of `path` on the system clipboard.
+#[tauri::command]
+pub fn copy_path_to_clipboard(app: AppHandle, path: String) -> Result<String, CommandError> {
+    let path = existing(&path)?.to_string_lossy().to_string();
+    app.clipboard()
+        .write_text(path.clone())
+        .map_err(|error| CommandError::Io {
+            message: format!("couldn't copy to the clipboard: {error}"),
+        })?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_existing_paths_are_revealed() {
+        let dir = std::env::temp_dir().join(format!("excalibur-reveal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("plan ✓.mmd");
+        std::fs::write(&file, "graph TD").unwrap();
+
+        let found = existing(&dir.join(".").join("plan ✓.mmd").to_string_lossy()).unwrap();
+        assert_eq!(found, paths::normalize(&file));
+        assert!(found.is_absolute());
+        match existing(&dir.join("gone.mmd").to_string_lossy()) {
+            Err(CommandError::NotFound { path }) => assert!(path.ends_with("gone.mmd")),
+            other => panic!("expected NotFound, got {other:?}"),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::error::CommandError;
+use crate::{app_data_dir, content_hash, now_epoch, now_epoch_millis, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// A folder the user works in (a workspace or vault), under a stable id so paths
+/// below it can be stored relative to it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RegisteredRoot {
+    pub id: String,
+    pub path: String,
+    pub name: Option<String>,
+    registered_at: u64,
+}
+
+/// A path stored relative to a registered root, with `/` separators.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct RootRelative {
+    pub root_id: String,
+    pub relative: String,
+}
+
+fn roots_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("roots.json")
+}
+
+pub fn load_roots(app: &AppHandle) -> Vec<RegisteredRoot> {
+    fs::read_to_string(roots_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_roots(app: &AppHandle, roots: &[RegisteredRoot]) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(roots).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    write_file(&roots_path(app), &contents)?;
+    Ok(())
+}
+
+fn normalize(path: &str) -> String {
+    fs::canonicalize(path)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Splits `path` against the deepest root that contains it.
+pub fn relative_to_roots(roots: &[RegisteredRoot], path: &Path) -> Option<RootRelative> {
+    roots
+        .iter()
+        .filter_map(|root| {
+            let relative = path.strip_prefix(&root.path).ok()?;
+            Some((root, relative))
+        })
+        .max_by_key(|(root, _)| root.path.len())
+        .map(|(root, relative)| RootRelative {
+            root_id: root.id.clone(),
+            relative: relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        })
+}
+
+/// Resolves a relative path against the root currently registered under its id.
+pub fn resolve_relative(roots: &[RegisteredRoot], relative: &RootRelative) -> Option<PathBuf> {
+    let root = roots.iter().find(|root| root.id == relative.root_id)?;
+    let mut path = PathBuf::from(&root.path);
+    path.extend(relative.relative.split('/').filter(|part| !part.is_empty()));
+    Some(path)
+}
+
+/// Registers `path` as a root, or returns the existing registration for it.
+///
+/// Passing the `id` of an existing root moves that root to `path`; anything stored
+/// relative to it then resolves against the new location.
+pub fn register_root_path(
+    app: &AppHandle,
+    path: &str,
+    id: Option<String>,
+    name: Option<String>,
+) -> Result<RegisteredRoot, CommandError> {
+    let path = normalize(path);
+    if !Path::new(&path).is_dir() {
+        return Err(CommandError::InvalidArgument {
+            message: format!("\"{path}\" is not a folder"),
+        });
+    }
+
+    let mut roots = load_roots(app);
+    if let Some(id) = id {
+        if let Some(root) = roots.iter_mut().find(|root| root.id == id) {
+            log::info!(
+                "register_root: moving {} from {} to {}",
+                id,
+                root.path,
+                path
+            );
+            root.path = path;
+            if name.is_some() {
+                root.name = name;
+            }
+            let root = root.clone();
+            save_roots(app, &roots)?;
+            return Ok(root);
+        }
+    }
+    if let Some(root) = roots.iter().find(|root| root.path == path) {
+        return Ok(root.clone());
+    }
+
+    let seed = format!("{path}:{}", now_epoch_millis());
+    let root = RegisteredRoot {
+        id: content_hash(seed.as_bytes())[..12].to_string(),
+        name: name.or_else(|| {
+            Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        }),
+        path,
+        registered_at: now_epoch(),
+    };
+    roots.push(root.clone());
+    save_roots(app, &roots)?;
+    Ok(root)
+}
+
+#[tauri::command]
+pub fn register_root(
+    app: AppHandle,
+    path: String,
+    id: Option<String>,
+    name: Option<String>,
+) -> Result<RegisteredRoot, CommandError> {
+    register_root_path(&app, &path, id, name)
+}
+
+#[tauri::command]
+pub fn list_roots(app: AppHandle) -> Vec<RegisteredRoot> {
+    load_roots(&app)
+}
+
+#[tauri::command]
+pub fn unregister_root(app: AppHandle, id: String) -> Result<Vec<RegisteredRoot>, CommandError> {
+    let mut roots = load_roots(&app);
+    roots.retain(|root| root.id != id);
+    save_roots(&app, &roots)?;
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(id: &str, path: &str) -> RegisteredRoot {
+        RegisteredRoot {
+            id: id.to_string(),
+            path: path.to_string(),
+            name: None,
+            registered_at: 0,
+        }
+    }
+
+    #[test]
+    fn picks_the_deepest_root() {
+        let roots = [root("outer", "/repos"), root("inner", "/repos/diagrams")];
+        assert_eq!(
+            relative_to_roots(&roots, Path::new("/repos/diagrams/flows/login.mmd")),
+            Some(RootRelative {
+                root_id: "inner".to_string(),
+                relative: "flows/login.mmd".to_string(),
+            })
+        );
+        assert_eq!(
+            relative_to_roots(&roots, Path::new("/elsewhere/a.mmd")),
+            None
+        );
+    }
+
+    #[test]
+    fn moved_root_resolves_to_new_location() {
+        let relative = RootRelative {
+            root_id: "docs".to_string(),
+            relative: "flows/login.mmd".to_string(),
+        };
+        let roots = [root("docs", "/home/sam/src/diagrams")];
+        assert_eq!(
+            resolve_relative(&roots, &relative),
+            Some(PathBuf::from("/home/sam/src/diagrams/flows/login.mmd"))
+        );
+        assert_eq!(resolve_relative(&[], &relative), None);
+    }
+}
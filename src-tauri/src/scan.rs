@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How deep `scan_workspace` descends below the root.
+const MAX_SCAN_DEPTH: usize = 12;
+
+/// Directory names that never contain diagrams worth indexing.
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "dist"];
+
+#[derive(Clone)]
+pub struct ScannedFile {
+    pub path: PathBuf,
+    pub kind: &'static str,
+}
+
+/// Maps a diagram file extension to its document kind; a `.gz` suffix is looked
+/// through.
+pub fn kind_for_extension(path: &Path) -> Option<&'static str> {
+    let extension = crate::gzip::document_extension(path)?
+        .to_string_lossy()
+        .to_lowercase();
+    match extension.as_str() {
+        "excalidraw" => Some("excalidraw"),
+        "mmd" | "mermaid" => Some("mermaid"),
+        _ => None,
+    }
+}
+
+/// Recursively lists the diagram files under `root`.
+///
+/// Hidden directories and common build/dependency folders are skipped, and symlinked
+/// directories are not followed so link cycles can't trap the walk.
+pub fn scan_workspace(root: &Path) -> Vec<ScannedFile> {
+    let mut files = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if depth < MAX_SCAN_DEPTH
+                    && !name.starts_with('.')
+                    && !SKIPPED_DIRS.contains(&name.as_str())
+                {
+                    pending.push((path, depth + 1));
+                }
+            } else if file_type.is_file() {
+                if let Some(kind) = kind_for_extension(&path) {
+                    files.push(ScannedFile { path, kind });
+                }
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
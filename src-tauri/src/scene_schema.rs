@@ -0,0 +1,236 @@
+use crate::error::CommandError;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+/// Newest excalidraw scene `version` the bundled editor reads and writes without
+/// losing anything.
+pub const SUPPORTED_SCENE_VERSION: u64 = 2;
+
+/// The schema details of an opened scene, returned as `schema` in `OpenFileResponse`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SceneSchema {
+    /// The scene's `version`; `None` when the file doesn't record one.
+    file_version: Option<u64>,
+    /// The app that wrote the scene, e.g. "https://excalidraw.com".
+    source: Option<String>,
+    supported_version: u64,
+    /// Saving would rewrite the file in an older format and may drop data.
+    newer: bool,
+}
+
+#[derive(Serialize, Clone)]
+struct NewerSceneEvent {
+    path: String,
+    #[serde(flatten)]
+    schema: SceneSchema,
+}
+
+/// Reads the schema fields of a scene. `None` when `contents` isn't a JSON object.
+pub fn inspect(contents: &str) -> Option<SceneSchema> {
+    let scene: Value = serde_json::from_str(contents).ok()?;
+    let scene = scene.as_object()?;
+    let file_version = scene.get("version").and_then(Value::as_u64);
+    Some(SceneSchema {
+        file_version,
+        source: scene
+            .get("source")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        supported_version: SUPPORTED_SCENE_VERSION,
+        newer: file_version.is_some_and(|version| version > SUPPORTED_SCENE_VERSION),
+    })
+}
+
+/// Checks that `contents` has the top-level shape of an Excalidraw scene: a JSON
+/// object with `type` "excalidraw", a numeric `version` and an `elements` array.
+/// Returns what's wrong otherwise, one entry per problem.
+pub fn validate_excalidraw(contents: &str) -> Result<(), Vec<String>> {
+    let scene: Value = match serde_json::from_str(contents) {
+        Ok(scene) => scene,
+        Err(error) => return Err(vec![format!("not valid JSON: {error}")]),
+    };
+    let Some(scene) = scene.as_object() else {
+        return Err(vec!["not a JSON object".to_string()]);
+    };
+    let mut problems = Vec::new();
+    match scene.get("type") {
+        Some(Value::String(kind)) if kind == "excalidraw" => {}
+        Some(other) => problems.push(format!("`type` is {other}, not \"excalidraw\"")),
+        None => problems.push("`type` is missing".to_string()),
+    }
+    match scene.get("version") {
+        Some(Value::Number(_)) => {}
+        Some(other) => problems.push(format!("`version` is {other}, not a number")),
+        None => problems.push("`version` is missing".to_string()),
+    }
+    match scene.get("elements") {
+        Some(Value::Array(_)) => {}
+        Some(_) => problems.push("`elements` is not an array".to_string()),
+        None => problems.push("`elements` is missing".to_string()),
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+/// Refuses to save `contents` as an Excalidraw file unless it validates, so a bug
+/// upstream can't write something Excalidraw won't open.
+pub fn check_save(contents: &str) -> Result<(), CommandError> {
+    validate_excalidraw(contents).map_err(|problems| CommandError::InvalidDocument {
+        message: format!(
+            "Not saving: this is not a valid Excalidraw scene ({})",
+            problems.join("; ")
+        ),
+    })
+}
+
+/// Inspects a scene being opened and emits `newer-scene-version` when it was written
+/// by a newer editor.
+pub fn check_opened(app: &AppHandle, path: &str, contents: &str) -> Option<SceneSchema> {
+    let schema = inspect(contents)?;
+    if schema.newer {
+        log::info!(
+            "check_opened: {} has scene version {:?}, newer than {}",
+            path,
+            schema.file_version,
+            SUPPORTED_SCENE_VERSION
+        );
+        let _ = app.emit(
+            "newer-scene-version",
+            NewerSceneEvent {
+                path: path.to_string(),
+                schema: schema.clone(),
+            },
+        );
+    }
+    Some(schema)
+}
+
+/// Refuses to overwrite a scene written by a newer editor unless the user agreed to
+/// the downgrade. Encrypted files can't be read here without their key, so for them
+/// only the frontend's confirmation after the open-time warning applies.
+pub fn check_overwrite(path: &Path, acknowledge_downgrade: bool) -> Result<(), CommandError> {
+    if acknowledge_downgrade || crate::crypto::is_encrypted_path(path) {
+        return Ok(());
+    }
+    let mut contents = String::new();
+    let read = crate::gzip::open(path).and_then(|mut file| file.read_to_string(&mut contents));
+    let Some(schema) = read.ok().and_then(|_| inspect(&contents)) else {
+        return Ok(());
+    };
+    if !schema.newer {
+        return Ok(());
+    }
+    Err(CommandError::DowngradeRequired {
+        path: path.to_string_lossy().to_string(),
+        file_version: schema.file_version.unwrap_or_default(),
+        supported_version: SUPPORTED_SCENE_VERSION,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn flags_scenes_newer_than_supported() {
+        let current = inspect(
+            r#"{"type":"excalidraw","version":2,"source":"https://excalidraw.com","elements":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(current.file_version, Some(2));
+        assert_eq!(current.source.as_deref(), Some("https://excalidraw.com"));
+        assert!(!current.newer);
+
+        let newer = inspect(r#"{"type":"excalidraw","version":3,"elements":[]}"#).unwrap();
+        assert!(newer.newer);
+        assert_eq!(newer.supported_version, SUPPORTED_SCENE_VERSION);
+
+        let unversioned = inspect(r#"{"elements":[]}"#).unwrap();
+        assert_eq!(unversioned.file_version, None);
+        assert!(!unversioned.newer);
+        assert_eq!(inspect("graph TD"), None);
+    }
+
+    #[test]
+    fn validates_the_scene_shape() {
+        assert_eq!(
+            validate_excalidraw(
+                r#"{"type":"excalidraw","version":2,"source":"https://excalidraw.com","elements":[],"appState":{}}"#
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate_excalidraw(include_str!("../templates/architecture.excalidraw")),
+            Ok(())
+        );
+
+        let truncated =
+            validate_excalidraw(r#"{"type":"excalidraw","version":2,"elements":[{"id":"#)
+                .unwrap_err();
+        assert_eq!(truncated.len(), 1);
+        assert!(
+            truncated[0].starts_with("not valid JSON: EOF"),
+            "{truncated:?}"
+        );
+
+        assert_eq!(
+            validate_excalidraw(r#"{"name":"excalibur","dependencies":{"react":"^18"}}"#),
+            Err(vec![
+                "`type` is missing".to_string(),
+                "`version` is missing".to_string(),
+                "`elements` is missing".to_string(),
+            ])
+        );
+        assert_eq!(
+            validate_excalidraw(r#"{"type":"excalidrawlib","version":"2","elements":{}}"#),
+            Err(vec![
+                r#"`type` is "excalidrawlib", not "excalidraw""#.to_string(),
+                r#"`version` is "2", not a number"#.to_string(),
+                "`elements` is not an array".to_string(),
+            ])
+        );
+        assert_eq!(
+            validate_excalidraw("[]"),
+            Err(vec!["not a JSON object".to_string()])
+        );
+    }
+
+    #[test]
+    fn refuses_to_save_invalid_scenes() {
+        let error = check_save(r#"{"elements":[]}"#).unwrap_err();
+        assert_eq!(error.code(), "invalid_document");
+        assert!(error
+            .to_string()
+            .contains("`type` is missing; `version` is missing"));
+        assert!(check_save(r#"{"type":"excalidraw","version":2,"elements":[]}"#).is_ok());
+    }
+
+    #[test]
+    fn overwriting_a_newer_scene_needs_acknowledgement() {
+        let dir = std::env::temp_dir().join(format!("excalibur-schema-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let newer = dir.join("newer.excalidraw");
+        fs::write(&newer, r#"{"type":"excalidraw","version":3,"elements":[]}"#).unwrap();
+        let current = dir.join("current.excalidraw");
+        fs::write(
+            &current,
+            r#"{"type":"excalidraw","version":2,"elements":[]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            check_overwrite(&newer, false).unwrap_err().code(),
+            "downgrade_required"
+        );
+        assert!(check_overwrite(&newer, true).is_ok());
+        assert!(check_overwrite(&current, false).is_ok());
+        assert!(check_overwrite(&dir.join("missing.excalidraw"), false).is_ok());
+    }
+}
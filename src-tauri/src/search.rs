@@ -0,0 +1,600 @@
+use crate::display_path::PathShortener;
+use crate::error::CommandError;
+use crate::operations::{cancel_operation, Operation};
+use crate::scan::scan_workspace;
+use crate::search_history::{find_saved_search, record_search};
+use crate::search_index::{registered_index, search_index, IndexStatus};
+use crate::{load_recents, now_epoch_millis};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter};
+
+/// Upper bound on files read at the same time during a search.
+const MAX_CONCURRENT_READS: usize = 8;
+/// Characters of context shown on each side of a match.
+const SNIPPET_RADIUS: usize = 40;
+
+/// Scene coordinates of an excalidraw element.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Bounds {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// A piece of searchable text, with the excalidraw element it came from.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TextEntry {
+    pub element_id: Option<String>,
+    #[serde(default)]
+    pub bounds: Option<Bounds>,
+    pub text: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ElementMatch {
+    id: String,
+    bounds: Option<Bounds>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SearchMatch {
+    pub path: String,
+    /// `path` shortened for display; filled in by `run_search`.
+    display_path: String,
+    kind: String,
+    snippet: String,
+    /// Ids of the excalidraw elements whose text matched. Empty for mermaid files.
+    element_ids: Vec<String>,
+    /// The matching excalidraw elements with their bounding boxes, for focusing them.
+    elements: Vec<ElementMatch>,
+    /// 1-based line and column of the first match in mermaid/text files.
+    line: Option<usize>,
+    column: Option<usize>,
+    match_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct SearchFileError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    search_id: String,
+    matches: Vec<SearchMatch>,
+    errors: Vec<SearchFileError>,
+    cancelled: bool,
+    /// Set when the results came from the persistent index of a registered workspace.
+    index: Option<IndexStatus>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SearchResultEvent {
+    search_id: String,
+    #[serde(rename = "match")]
+    result: SearchMatch,
+}
+
+pub struct Candidate {
+    pub path: PathBuf,
+    pub kind: String,
+}
+
+/// Pulls the user-visible text out of an excalidraw scene: element text and
+/// element/frame names. Deleted elements are ignored.
+pub fn extract_excalidraw_text(contents: &str) -> Result<Vec<TextEntry>, String> {
+    let scene: Value = serde_json::from_str(contents).map_err(|error| error.to_string())?;
+    let Some(elements) = scene.get("elements").and_then(Value::as_array) else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries = Vec::new();
+    for element in elements {
+        if element.get("isDeleted").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+        let element_id = element
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let bounds = element_bounds(element);
+        let text = ["text", "rawText", "originalText"]
+            .iter()
+            .find_map(|field| element.get(*field).and_then(Value::as_str));
+        for value in [text, element.get("name").and_then(Value::as_str)]
+            .into_iter()
+            .flatten()
+            .filter(|value| !value.is_empty())
+        {
+            entries.push(TextEntry {
+                element_id: element_id.clone(),
+                bounds,
+                text: value.to_string(),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn element_bounds(element: &Value) -> Option<Bounds> {
+    let number = |field: &str| element.get(field).and_then(Value::as_f64);
+    let (x, y, width, height) = (
+        number("x")?,
+        number("y")?,
+        number("width")?,
+        number("height")?,
+    );
+    // Lines and arrows can have negative extents; normalize to a top-left box.
+    Some(Bounds {
+        x: x.min(x + width),
+        y: y.min(y + height),
+        width: width.abs(),
+        height: height.abs(),
+    })
+}
+
+/// Converts a byte offset into a 1-based line and column (in characters).
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Lowercases `text` while remembering, for every byte of the result, which byte
+/// of the original it came from.
+fn fold_case(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (index, ch) in text.char_indices() {
+        for lower in ch.to_lowercase() {
+            offsets.extend(std::iter::repeat_n(index, lower.len_utf8()));
+            folded.push(lower);
+        }
+    }
+    offsets.push(text.len());
+    (folded, offsets)
+}
+
+pub struct Matcher {
+    needle: String,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str, case_sensitive: bool) -> Self {
+        let needle = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        Matcher {
+            needle,
+            case_sensitive,
+        }
+    }
+
+    /// Returns the byte range of the first match in `text` and the number of matches.
+    pub fn find(&self, text: &str) -> Option<(usize, usize, usize)> {
+        if self.case_sensitive {
+            let start = text.find(&self.needle)?;
+            let count = text.matches(&self.needle).count();
+            return Some((start, start + self.needle.len(), count));
+        }
+
+        let (folded, offsets) = fold_case(text);
+        let start = folded.find(&self.needle)?;
+        let count = folded.matches(&self.needle).count();
+        Some((offsets[start], offsets[start + self.needle.len()], count))
+    }
+}
+
+/// Returns the text around `start..end`, on one line, with ellipses where cut.
+pub fn snippet(text: &str, start: usize, end: usize) -> String {
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_RADIUS - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+    let to = text[end..]
+        .char_indices()
+        .nth(SNIPPET_RADIUS)
+        .map(|(index, _)| end + index)
+        .unwrap_or(text.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(
+        text[from..to]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .as_str(),
+    );
+    if to < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Extracts the searchable text of a document of `kind`.
+pub fn searchable_text(kind: &str, contents: String) -> Result<Vec<TextEntry>, String> {
+    if kind == "excalidraw" {
+        extract_excalidraw_text(&contents)
+    } else {
+        Ok(vec![TextEntry {
+            element_id: None,
+            bounds: None,
+            text: contents,
+        }])
+    }
+}
+
+/// Matches `entries` against `matcher`, returning `None` when nothing matched.
+pub fn match_entries(
+    path: &str,
+    kind: &str,
+    entries: &[TextEntry],
+    matcher: &Matcher,
+) -> Option<SearchMatch> {
+    let mut first_snippet = None;
+    let mut position = None;
+    let mut element_ids = Vec::new();
+    let mut elements = Vec::new();
+    let mut match_count = 0;
+    for entry in entries {
+        let Some((start, end, count)) = matcher.find(&entry.text) else {
+            continue;
+        };
+        match_count += count;
+        if first_snippet.is_none() {
+            first_snippet = Some(snippet(&entry.text, start, end));
+            if entry.element_id.is_none() {
+                position = Some(line_and_column(&entry.text, start));
+            }
+        }
+        if let Some(id) = &entry.element_id {
+            if !element_ids.contains(id) {
+                element_ids.push(id.clone());
+                elements.push(ElementMatch {
+                    id: id.clone(),
+                    bounds: entry.bounds,
+                });
+            }
+        }
+    }
+
+    first_snippet.map(|snippet| SearchMatch {
+        path: path.to_string(),
+        display_path: String::new(),
+        kind: kind.to_string(),
+        snippet,
+        element_ids,
+        elements,
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        match_count,
+    })
+}
+
+fn search_file(candidate: &Candidate, matcher: &Matcher) -> Result<Option<SearchMatch>, String> {
+    let contents = fs::read_to_string(&candidate.path).map_err(|error| error.to_string())?;
+    let entries = searchable_text(&candidate.kind, contents)?;
+    Ok(match_entries(
+        &candidate.path.to_string_lossy(),
+        &candidate.kind,
+        &entries,
+        matcher,
+    ))
+}
+
+/// Lists the files covered by a search `scope`: the recents list, or every diagram
+/// under `root` for a workspace.
+pub fn search_candidates(
+    app: &AppHandle,
+    scope: &str,
+    root: Option<&str>,
+) -> Result<Vec<Candidate>, CommandError> {
+    match scope {
+        // Encrypted documents can't be read without their password.
+        "recents" => Ok(load_recents(app)
+            .into_iter()
+            .filter(|item| !item.encrypted)
+            .map(|item| Candidate {
+                path: PathBuf::from(item.path),
+                kind: item.kind,
+            })
+            .collect()),
+        "workspace" => {
+            let root = root.ok_or_else(|| CommandError::InvalidArgument {
+                message: "A workspace search needs a root folder".to_string(),
+            })?;
+            Ok(scan_workspace(&PathBuf::from(root))
+                .into_iter()
+                .map(|file| Candidate {
+                    path: file.path,
+                    kind: file.kind.to_string(),
+                })
+                .collect())
+        }
+        _ => Err(CommandError::InvalidArgument {
+            message: format!("Unknown search scope \"{scope}\""),
+        }),
+    }
+}
+
+async fn run_search(
+    app: AppHandle,
+    query: String,
+    scope: String,
+    root: Option<String>,
+    case_sensitive: bool,
+    search_id: String,
+) -> Result<SearchResponse, CommandError> {
+    log::info!(
+        "search_files: id={}, scope={}, root={:?}",
+        search_id,
+        scope,
+        root
+    );
+
+    let shortener = PathShortener::new(&app);
+    if scope == "workspace" {
+        if let Some(index) = root
+            .as_deref()
+            .and_then(|root| registered_index(&app, root))
+        {
+            let matcher = Matcher::new(&query, case_sensitive);
+            let (mut matches, status) = search_index(&index, &query, &matcher);
+            for result in &mut matches {
+                result.display_path = shortener.shorten(Path::new(&result.path));
+                let _ = app.emit(
+                    "search-result",
+                    SearchResultEvent {
+                        search_id: search_id.clone(),
+                        result: result.clone(),
+                    },
+                );
+            }
+            return Ok(SearchResponse {
+                search_id,
+                matches,
+                errors: Vec::new(),
+                cancelled: false,
+                index: Some(status),
+            });
+        }
+    }
+
+    let candidates = search_candidates(&app, &scope, root.as_deref())?;
+
+    let operation = Operation::start(&app, "search", Some(search_id.clone()), true);
+    let worker_app = app.clone();
+    let worker_id = search_id.clone();
+    let outcome = tauri::async_runtime::spawn_blocking(move || {
+        let matcher = Matcher::new(&query, case_sensitive);
+        let total = candidates.len() as u64;
+        let next = AtomicUsize::new(0);
+        let searched = AtomicUsize::new(0);
+        let matches = Mutex::new(Vec::new());
+        let errors = Mutex::new(Vec::new());
+
+        thread::scope(|scope| {
+            for _ in 0..MAX_CONCURRENT_READS.min(candidates.len()) {
+                scope.spawn(|| loop {
+                    if operation.is_cancelled() {
+                        break;
+                    }
+                    let Some(candidate) = candidates.get(next.fetch_add(1, Ordering::Relaxed))
+                    else {
+                        break;
+                    };
+                    match search_file(candidate, &matcher) {
+                        Ok(Some(mut result)) => {
+                            result.display_path = shortener.shorten(&candidate.path);
+                            let _ = worker_app.emit(
+                                "search-result",
+                                SearchResultEvent {
+                                    search_id: worker_id.clone(),
+                                    result: result.clone(),
+                                },
+                            );
+                            matches.lock().unwrap().push(result);
+                        }
+                        Ok(None) => {}
+                        Err(message) => errors.lock().unwrap().push(SearchFileError {
+                            path: candidate.path.to_string_lossy().to_string(),
+                            message,
+                        }),
+                    }
+                    let done = searched.fetch_add(1, Ordering::Relaxed) as u64 + 1;
+                    operation.progress(
+                        "searching",
+                        done,
+                        Some(total),
+                        Some(&candidate.path.to_string_lossy()),
+                    );
+                });
+            }
+        });
+
+        let cancelled = operation.is_cancelled();
+        let outcome = if cancelled {
+            Err(CommandError::Cancelled)
+        } else {
+            Ok(())
+        };
+        operation.finish(&outcome, searched.into_inner() as u64);
+        (
+            matches.into_inner().unwrap(),
+            errors.into_inner().unwrap(),
+            cancelled,
+        )
+    })
+    .await;
+
+    let (mut matches, errors, cancelled) = outcome.map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(SearchResponse {
+        search_id,
+        matches,
+        errors,
+        cancelled,
+        index: None,
+    })
+}
+
+/// Searches diagram contents for `query`, or for the query stored under `saved_search`.
+///
+/// Mermaid files are searched as plain text and excalidraw scenes by the text of
+/// their elements. Each match is also emitted as a `search-result` event while the
+/// search runs; unreadable files are reported in `errors` rather than failing the
+/// whole search. Pass a `search_id` to be able to stop it with `cancel_search`.
+/// Completed searches are added to the search history unless disabled in settings.
+#[tauri::command]
+pub async fn search_files(
+    app: AppHandle,
+    query: Option<String>,
+    scope: Option<String>,
+    root: Option<String>,
+    case_sensitive: Option<bool>,
+    search_id: Option<String>,
+    saved_search: Option<String>,
+) -> Result<SearchResponse, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    let (query, scope, root, case_sensitive) = match saved_search {
+        Some(name) => {
+            let saved =
+                find_saved_search(&app, &name).ok_or_else(|| CommandError::InvalidArgument {
+                    message: format!("No saved search named \"{name}\""),
+                })?;
+            (saved.query, saved.scope, saved.root, saved.case_sensitive)
+        }
+        None => (
+            query.unwrap_or_default(),
+            scope.unwrap_or_else(|| "recents".to_string()),
+            root,
+            case_sensitive.unwrap_or(false),
+        ),
+    };
+    if query.trim().is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "Search query is empty".to_string(),
+        });
+    }
+    let search_id = search_id.unwrap_or_else(|| format!("search-{}", now_epoch_millis()));
+
+    let response = run_search(
+        app.clone(),
+        query.clone(),
+        scope.clone(),
+        root.clone(),
+        case_sensitive,
+        search_id,
+    )
+    .await?;
+    if !response.cancelled {
+        record_search(&app, query, scope, root, response.matches.len());
+    }
+    Ok(response)
+}
+
+/// Stops a running search. Returns false when no search with that id is running.
+///
+/// Searches are regular operations, so `cancel_operation` with the search id works too.
+#[tauri::command]
+pub fn cancel_search(app: AppHandle, search_id: String) -> bool {
+    cancel_operation(app, search_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_ignores_case_unless_asked_and_counts_every_hit() {
+        let text = "Straße to STRASSE, then straße again";
+        let (start, end, count) = Matcher::new("STRAßE", false).find(text).unwrap();
+        assert_eq!(&text[start..end], "Straße");
+        assert_eq!(count, 2);
+
+        let (start, _, count) = Matcher::new("straße", true).find(text).unwrap();
+        assert_eq!(start, text.rfind("straße").unwrap());
+        assert_eq!(count, 1);
+        assert!(Matcher::new("Payment", true).find(text).is_none());
+    }
+
+    #[test]
+    fn excalidraw_matches_name_their_elements() {
+        let scene = serde_json::json!({
+            "elements": [
+                {"id": "a", "type": "text", "text": "Pay invoice", "x": 10, "y": 20, "width": -4, "height": 8},
+                {"id": "b", "type": "frame", "name": "Invoices"},
+                {"id": "c", "type": "text", "text": "invoice draft", "isDeleted": true},
+                {"id": "d", "type": "rectangle"},
+            ]
+        });
+        let entries = searchable_text("excalidraw", scene.to_string()).unwrap();
+        assert_eq!(entries.len(), 2);
+        let found = match_entries(
+            "/work/billing.excalidraw",
+            "excalidraw",
+            &entries,
+            &Matcher::new("invoice", false),
+        )
+        .unwrap();
+
+        assert_eq!(found.element_ids, ["a", "b"]);
+        assert_eq!(found.match_count, 2);
+        assert_eq!(found.snippet, "Pay invoice");
+        assert_eq!(found.line, None);
+        let bounds = found.elements[0].bounds.unwrap();
+        assert_eq!((bounds.x, bounds.width), (6.0, 4.0));
+    }
+
+    #[test]
+    fn text_matches_carry_their_position_and_a_trimmed_snippet() {
+        let diagram = format!(
+            "flowchart TD\n  A[Start] --> B[{}Ölçü check]\n",
+            "x".repeat(SNIPPET_RADIUS + 10)
+        );
+        let entries = searchable_text("mermaid", diagram).unwrap();
+        let found = match_entries(
+            "/work/flow.mmd",
+            "mermaid",
+            &entries,
+            &Matcher::new("ölçü", false),
+        )
+        .unwrap();
+
+        assert_eq!(found.line, Some(2));
+        assert_eq!(
+            found.column,
+            Some("  A[Start] --> B[".len() + SNIPPET_RADIUS + 10 + 1)
+        );
+        assert!(found.snippet.starts_with('…'));
+        assert!(found.snippet.ends_with("Ölçü check]"));
+        assert!(found.element_ids.is_empty());
+        assert!(match_entries(
+            "/work/flow.mmd",
+            "mermaid",
+            &entries,
+            &Matcher::new("end", false)
+        )
+        .is_none());
+    }
+}
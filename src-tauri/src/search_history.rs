@@ -0,0 +1,142 @@
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{app_data_dir, now_epoch, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+const MAX_SEARCH_HISTORY: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SearchHistoryEntry {
+    query: String,
+    scope: String,
+    root: Option<String>,
+    timestamp: u64,
+    result_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub scope: String,
+    pub root: Option<String>,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    created_at: u64,
+}
+
+fn history_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("search-history.json")
+}
+
+fn saved_searches_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("saved-searches.json")
+}
+
+fn load_list<T: for<'de> Deserialize<'de>>(path: PathBuf) -> Vec<T> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_list<T: Serialize>(path: PathBuf, items: &[T]) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(items)?;
+    write_file(&path, &contents)
+}
+
+/// Adds a completed search to the history, newest first, unless history is disabled.
+/// Repeating a search moves it to the top instead of adding a duplicate.
+pub fn record_search(
+    app: &AppHandle,
+    query: String,
+    scope: String,
+    root: Option<String>,
+    result_count: usize,
+) {
+    if !load_settings(app).record_search_history {
+        return;
+    }
+    let mut history: Vec<SearchHistoryEntry> = load_list(history_path(app));
+    history.retain(|entry| !(entry.query == query && entry.scope == scope && entry.root == root));
+    history.insert(
+        0,
+        SearchHistoryEntry {
+            query,
+            scope,
+            root,
+            timestamp: now_epoch(),
+            result_count,
+        },
+    );
+    history.truncate(MAX_SEARCH_HISTORY);
+    if let Err(error) = save_list(history_path(app), &history) {
+        log::warn!("record_search: failed to save history: {}", error);
+    }
+}
+
+pub fn find_saved_search(app: &AppHandle, name: &str) -> Option<SavedSearch> {
+    load_list::<SavedSearch>(saved_searches_path(app))
+        .into_iter()
+        .find(|search| search.name == name)
+}
+
+#[tauri::command]
+pub fn get_search_history(app: AppHandle) -> Result<Vec<SearchHistoryEntry>, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    Ok(load_list(history_path(&app)))
+}
+
+#[tauri::command]
+pub fn clear_search_history(app: AppHandle) -> Result<(), CommandError> {
+    save_list::<SearchHistoryEntry>(history_path(&app), &[])
+}
+
+/// Saves (or replaces) a named search.
+#[tauri::command]
+pub fn save_search(
+    app: AppHandle,
+    name: String,
+    query: String,
+    scope: String,
+    root: Option<String>,
+    case_sensitive: Option<bool>,
+) -> Result<Vec<SavedSearch>, CommandError> {
+    let name = name.trim().to_string();
+    if name.is_empty() || query.trim().is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "A saved search needs a name and a query".to_string(),
+        });
+    }
+
+    let mut searches: Vec<SavedSearch> = load_list(saved_searches_path(&app));
+    searches.retain(|search| search.name != name);
+    searches.push(SavedSearch {
+        name,
+        query,
+        scope,
+        root,
+        case_sensitive: case_sensitive.unwrap_or(false),
+        created_at: now_epoch(),
+    });
+    searches.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    save_list(saved_searches_path(&app), &searches)?;
+    Ok(searches)
+}
+
+#[tauri::command]
+pub fn list_saved_searches(app: AppHandle) -> Result<Vec<SavedSearch>, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    Ok(load_list(saved_searches_path(&app)))
+}
+
+#[tauri::command]
+pub fn delete_saved_search(app: AppHandle, name: String) -> Result<Vec<SavedSearch>, CommandError> {
+    let mut searches: Vec<SavedSearch> = load_list(saved_searches_path(&app));
+    searches.retain(|search| search.name != name);
+    save_list(saved_searches_path(&app), &searches)?;
+    Ok(searches)
+}
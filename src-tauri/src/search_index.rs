@@ -0,0 +1,452 @@
+use crate::error::CommandError;
+use crate::operations::Operation;
+use crate::scan::{kind_for_extension, scan_workspace};
+use crate::search::{match_entries, searchable_text, Matcher, SearchMatch, TextEntry};
+use crate::{app_data_dir, content_hash, now_epoch, write_file};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+/// Bumped whenever the on-disk index layout changes; older indexes are rebuilt.
+const INDEX_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexedFile {
+    kind: String,
+    modified: u64,
+    size: u64,
+    hash: String,
+    entries: Vec<TextEntry>,
+    /// Lowercased words from `entries`, used to skip files that can't match.
+    tokens: Vec<String>,
+}
+
+/// Extracted text for every diagram under a workspace root.
+#[derive(Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    root: String,
+    built_at: u64,
+    updated_at: u64,
+    files: BTreeMap<String, IndexedFile>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct IndexStatus {
+    root: String,
+    files: usize,
+    built_at: u64,
+    updated_at: u64,
+    /// Seconds since the index last picked up a change.
+    age_secs: u64,
+}
+
+/// Loaded search indexes and the folder watchers keeping them current, keyed by root.
+#[derive(Default)]
+pub struct SearchIndexes {
+    indexes: Mutex<HashMap<String, Arc<Mutex<SearchIndex>>>>,
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+fn normalize_root(root: &str) -> String {
+    fs::canonicalize(root)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| root.to_string())
+}
+
+fn index_dir(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("search-index")
+}
+
+fn index_path(app: &AppHandle, root: &str) -> PathBuf {
+    let hash = content_hash(root.as_bytes());
+    index_dir(app).join(format!("{}.json", &hash[..16]))
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((modified, metadata.len()))
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Indexes a single file, reusing `previous` when its mtime/size or content hash
+/// shows it hasn't changed.
+fn index_file(
+    path: &Path,
+    kind: &str,
+    previous: Option<IndexedFile>,
+) -> Result<IndexedFile, String> {
+    let (modified, size) = file_stamp(path).ok_or_else(|| "File is not readable".to_string())?;
+    if let Some(previous) = &previous {
+        if previous.modified == modified && previous.size == size {
+            return Ok(previous.clone());
+        }
+    }
+
+    let bytes = fs::read(path).map_err(|error| error.to_string())?;
+    let hash = content_hash(&bytes);
+    if let Some(previous) = previous.filter(|previous| previous.hash == hash) {
+        return Ok(IndexedFile {
+            modified,
+            size,
+            ..previous
+        });
+    }
+
+    // Indexing has no settings to hand; the default limit still stops runaway streams.
+    let bytes = crate::gzip::decompress(path, bytes, crate::large_files::DEFAULT_MAX_BYTES)
+        .map_err(|error| error.to_string())?;
+    let contents = String::from_utf8(bytes).map_err(|error| error.to_string())?;
+    let entries = searchable_text(kind, contents)?;
+    let mut tokens: Vec<String> = entries
+        .iter()
+        .flat_map(|entry| tokenize(&entry.text))
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+
+    Ok(IndexedFile {
+        kind: kind.to_string(),
+        modified,
+        size,
+        hash,
+        entries,
+        tokens,
+    })
+}
+
+/// Brings `index` up to date with the files currently under its root.
+///
+/// When `operation` is cancelled midway, files not reached yet keep their previous
+/// entries and the index stays as it was apart from the files already refreshed.
+fn refresh_index(index: &mut SearchIndex, operation: &Operation) -> Result<(), CommandError> {
+    let mut previous = std::mem::take(&mut index.files);
+    operation.progress("scanning", 0, None, Some(&index.root));
+    let files = scan_workspace(Path::new(&index.root));
+    let total = files.len() as u64;
+    for (done, file) in files.into_iter().enumerate() {
+        let key = file.path.to_string_lossy().to_string();
+        if operation.is_cancelled() {
+            index.files.append(&mut previous);
+            return Err(CommandError::Cancelled);
+        }
+        operation.progress("indexing", done as u64, Some(total), Some(&key));
+        match index_file(&file.path, file.kind, previous.remove(&key)) {
+            Ok(indexed) => {
+                index.files.insert(key, indexed);
+            }
+            Err(error) => {
+                log::info!("refresh_index: skipping {}: {}", key, error);
+            }
+        }
+    }
+    index.updated_at = now_epoch();
+    Ok(())
+}
+
+fn save_index(app: &AppHandle, index: &SearchIndex) {
+    match serde_json::to_string(index) {
+        Ok(contents) => {
+            if let Err(error) = write_file(&index_path(app, &index.root), &contents) {
+                log::warn!("save_index: failed for {}: {}", index.root, error);
+            }
+        }
+        Err(error) => log::warn!("save_index: failed to serialize: {}", error),
+    }
+}
+
+fn load_index(app: &AppHandle, root: &str) -> Option<SearchIndex> {
+    let contents = fs::read_to_string(index_path(app, root)).ok()?;
+    let index: SearchIndex = serde_json::from_str(&contents).ok()?;
+    (index.version == INDEX_VERSION && index.root == root).then_some(index)
+}
+
+fn index_status(index: &SearchIndex) -> IndexStatus {
+    IndexStatus {
+        root: index.root.clone(),
+        files: index.files.len(),
+        built_at: index.built_at,
+        updated_at: index.updated_at,
+        age_secs: now_epoch().saturating_sub(index.updated_at),
+    }
+}
+
+/// Re-indexes a single path reported by the folder watcher.
+fn apply_change(app: &AppHandle, root: &str, path: &Path) {
+    let Some(kind) = kind_for_extension(path) else {
+        return;
+    };
+    let Some(index) = app
+        .state::<SearchIndexes>()
+        .indexes
+        .lock()
+        .unwrap()
+        .get(root)
+        .cloned()
+    else {
+        return;
+    };
+
+    let mut index = index.lock().unwrap();
+    update_file(&mut index, path, kind);
+    save_index(app, &index);
+}
+
+/// Re-indexes `path` in `index`, or drops it when the file is gone.
+fn update_file(index: &mut SearchIndex, path: &Path, kind: &str) {
+    let key = path.to_string_lossy().to_string();
+    let previous = index.files.remove(&key);
+    if path.is_file() {
+        match index_file(path, kind, previous) {
+            Ok(indexed) => {
+                index.files.insert(key, indexed);
+            }
+            Err(error) => log::info!("update_file: skipping {}: {}", key, error),
+        }
+    }
+    index.updated_at = now_epoch();
+}
+
+fn watch_root(app: &AppHandle, root: &str) {
+    let state = app.state::<SearchIndexes>();
+    let mut watchers = state.watchers.lock().unwrap();
+    if watchers.contains_key(root) {
+        return;
+    }
+
+    let handle = app.clone();
+    let watched_root = root.to_string();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        for path in &event.paths {
+            apply_change(&handle, &watched_root, path);
+        }
+    });
+    match watcher {
+        Ok(mut watcher) => match watcher.watch(Path::new(root), RecursiveMode::Recursive) {
+            Ok(()) => {
+                watchers.insert(root.to_string(), watcher);
+            }
+            Err(error) => log::warn!("watch_root: failed for {}: {}", root, error),
+        },
+        Err(error) => log::warn!("watch_root: failed for {}: {}", root, error),
+    }
+}
+
+fn register_index(app: &AppHandle, index: SearchIndex) -> Arc<Mutex<SearchIndex>> {
+    let root = index.root.clone();
+    let index = Arc::new(Mutex::new(index));
+    app.state::<SearchIndexes>()
+        .indexes
+        .lock()
+        .unwrap()
+        .insert(root.clone(), index.clone());
+    watch_root(app, &root);
+    index
+}
+
+/// Unloads every index and deletes their files, returning the bytes freed. Each
+/// index is locked before its file goes, so a refresh or watcher update that is
+/// saving it finishes first, and unloaded indexes get no further updates.
+pub fn clear_indexes(app: &AppHandle) -> u64 {
+    let state = app.state::<SearchIndexes>();
+    let mut indexes = state.indexes.lock().unwrap();
+    let guards: Vec<Arc<Mutex<SearchIndex>>> = indexes.drain().map(|(_, index)| index).collect();
+    let _locked: Vec<_> = guards.iter().map(|index| index.lock().unwrap()).collect();
+    state.watchers.lock().unwrap().clear();
+
+    let dir = index_dir(app);
+    let bytes = crate::app_info::directory_size(&dir, 1);
+    if let Err(error) = fs::remove_dir_all(&dir) {
+        if error.kind() != std::io::ErrorKind::NotFound {
+            log::info!("clear_indexes: {}", error);
+            return 0;
+        }
+    }
+    bytes
+}
+
+/// Returns the index for `root` if the workspace has one, loading it from disk on
+/// first use.
+pub fn registered_index(app: &AppHandle, root: &str) -> Option<Arc<Mutex<SearchIndex>>> {
+    let root = normalize_root(root);
+    if let Some(index) = app
+        .state::<SearchIndexes>()
+        .indexes
+        .lock()
+        .unwrap()
+        .get(&root)
+    {
+        return Some(index.clone());
+    }
+    let index = load_index(app, &root)?;
+    Some(register_index(app, index))
+}
+
+/// Searches an index without touching the files it describes.
+pub fn search_index(
+    index: &Mutex<SearchIndex>,
+    query: &str,
+    matcher: &Matcher,
+) -> (Vec<SearchMatch>, IndexStatus) {
+    let index = index.lock().unwrap();
+    let query_tokens: Vec<String> = tokenize(query).collect();
+
+    let matches = index
+        .files
+        .iter()
+        .filter(|(_, file)| {
+            query_tokens.iter().all(|query_token| {
+                file.tokens
+                    .iter()
+                    .any(|token| token.contains(query_token.as_str()))
+            })
+        })
+        .filter_map(|(path, file)| match_entries(path, &file.kind, &file.entries, matcher))
+        .collect();
+    (matches, index_status(&index))
+}
+
+/// Starts watching every workspace that already has an index on disk.
+pub fn watch_registered_roots(app: &AppHandle) {
+    let Ok(entries) = fs::read_dir(index_dir(app)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Some(index) = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<SearchIndex>(&contents).ok())
+        else {
+            continue;
+        };
+        if index.version == INDEX_VERSION {
+            register_index(app, index);
+        }
+    }
+}
+
+/// Builds (or refreshes) the persistent search index for a workspace root and
+/// registers the root so searches use the index and a folder watcher keeps it
+/// current. Files whose mtime/size or hash are unchanged are not re-extracted.
+/// Progress is reported as `operation-progress` events; a cancelled rebuild leaves
+/// the saved index untouched. `notify_when_done` overrides the setting of that name.
+#[tauri::command]
+pub async fn rebuild_search_index(
+    app: AppHandle,
+    root: String,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> Result<IndexStatus, CommandError> {
+    let root = normalize_root(&root);
+    if !Path::new(&root).is_dir() {
+        return Err(CommandError::InvalidArgument {
+            message: format!("\"{root}\" is not a folder"),
+        });
+    }
+    log::info!("rebuild_search_index: root={}", root);
+    crate::roots::register_root_path(&app, &root, None, None)?;
+
+    let existing = registered_index(&app, &root);
+    let worker_app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = existing.unwrap_or_else(|| {
+            let now = now_epoch();
+            register_index(
+                &worker_app,
+                SearchIndex {
+                    version: INDEX_VERSION,
+                    root: root.clone(),
+                    built_at: now,
+                    updated_at: now,
+                    files: BTreeMap::new(),
+                },
+            )
+        });
+        let operation = Operation::start(&worker_app, "index", operation_id, true)
+            .notify_when_done(notify_when_done);
+        let mut index = index.lock().unwrap();
+        let outcome = refresh_index(&mut index, &operation).map(|()| {
+            index.built_at = index.updated_at;
+            save_index(&worker_app, &index);
+            index_status(&index)
+        });
+        operation.finish(&outcome, index.files.len() as u64);
+        let summary = match &outcome {
+            Ok(status) => format!("Indexed {} diagrams in {}", status.files, status.root),
+            Err(CommandError::Cancelled) => format!("Indexing {} was cancelled", index.root),
+            Err(error) => format!("Indexing {} failed: {error}", index.root),
+        };
+        operation.notify_done(&summary, outcome.as_ref().ok());
+        outcome
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched_paths(index: &Mutex<SearchIndex>, query: &str) -> Vec<String> {
+        let (matches, _) = search_index(index, query, &Matcher::new(query, false));
+        matches.into_iter().map(|found| found.path).collect()
+    }
+
+    #[test]
+    fn changed_and_removed_files_are_reindexed() {
+        let dir =
+            std::env::temp_dir().join(format!("excalibur-search-index-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let flow = dir.join("flow.mmd");
+        let key = flow.to_string_lossy().to_string();
+        fs::write(&flow, "flowchart TD\n  A[Checkout] --> B[Payment]\n").unwrap();
+        let index = Mutex::new(SearchIndex {
+            version: INDEX_VERSION,
+            root: dir.to_string_lossy().to_string(),
+            built_at: 0,
+            updated_at: 0,
+            files: BTreeMap::new(),
+        });
+
+        update_file(&mut index.lock().unwrap(), &flow, "mermaid");
+        assert_eq!(matched_paths(&index, "payment"), [key.as_str()]);
+        let indexed = index.lock().unwrap().files[&key].clone();
+        // An unchanged file is not read again.
+        let reused = index_file(&flow, "mermaid", Some(indexed.clone())).unwrap();
+        assert_eq!(reused.hash, indexed.hash);
+
+        // Another size means a change even within the same second.
+        fs::write(&flow, "flowchart TD\n  A[Checkout] --> B[Refund flow]\n").unwrap();
+        update_file(&mut index.lock().unwrap(), &flow, "mermaid");
+        assert!(matched_paths(&index, "payment").is_empty());
+        assert_eq!(matched_paths(&index, "refund"), [key.as_str()]);
+        assert_ne!(index.lock().unwrap().files[&key].hash, indexed.hash);
+
+        fs::remove_file(&flow).unwrap();
+        update_file(&mut index.lock().unwrap(), &flow, "mermaid");
+        assert!(index.lock().unwrap().files.is_empty());
+        assert!(matched_paths(&index, "refund").is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
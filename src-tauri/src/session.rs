@@ -0,0 +1,185 @@
+use crate::error::CommandError;
+use crate::{app_data_dir, now_epoch, paths, write_file};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// Version of the `session.json` layout. Bump it when a field changes meaning;
+/// added fields only need `#[serde(default)]`, which older versions ignore.
+pub const SESSION_VERSION: u32 = 1;
+/// Longest `load_session` waits for a file to answer, e.g. on a sleeping network
+/// drive, before restoring it anyway.
+const EXISTS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A document that was open, as the frontend reports it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionItem {
+    kind: String,
+    path: String,
+    /// Scroll position, zoom and the like, kept as the frontend sent it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    view_state: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    version: u32,
+    saved_at: u64,
+    items: Vec<SessionItem>,
+}
+
+#[derive(Serialize, Default)]
+pub struct LoadSessionResponse {
+    /// The documents to reopen, in the order they were saved.
+    items: Vec<SessionItem>,
+    /// Documents whose files are gone, so the frontend can say what wasn't restored.
+    missing: Vec<SessionItem>,
+}
+
+fn session_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("session.json")
+}
+
+/// The items of a session file. A file written by a newer version is read as far as
+/// this version understands it; a corrupt one restores nothing rather than failing
+/// the launch.
+fn parse_session(contents: &str) -> Vec<SessionItem> {
+    match serde_json::from_str::<SessionFile>(contents) {
+        Ok(session) => {
+            if session.version > SESSION_VERSION {
+                log::info!(
+                    "session: session.json is version {}, reading what version {} understands",
+                    session.version,
+                    SESSION_VERSION
+                );
+            }
+            session.items
+        }
+        Err(error) => {
+            log::warn!("session: ignoring unreadable session.json: {error}");
+            Vec::new()
+        }
+    }
+}
+
+/// Splits `items` by whether their file still exists. Files that didn't answer in
+/// time are restored; opening them reports the problem if there is one.
+fn split_missing(
+    items: Vec<SessionItem>,
+    exists: Vec<Option<bool>>,
+) -> (Vec<SessionItem>, Vec<SessionItem>) {
+    let (missing, present): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .zip(exists)
+        .partition(|(_, exists)| *exists == Some(false));
+    (
+        present.into_iter().map(|(item, _)| item).collect(),
+        missing.into_iter().map(|(item, _)| item).collect(),
+    )
+}
+
+/// Remembers the documents open now, replacing the previous session.
+#[tauri::command]
+pub fn save_session(app: AppHandle, items: Vec<SessionItem>) -> Result<(), CommandError> {
+    let mut session = SessionFile {
+        version: SESSION_VERSION,
+        saved_at: now_epoch(),
+        items: Vec::with_capacity(items.len()),
+    };
+    for mut item in items {
+        if !matches!(item.kind.as_str(), "excalidraw" | "mermaid") {
+            return Err(CommandError::InvalidKind { kind: item.kind });
+        }
+        item.path = paths::normalize(Path::new(&item.path))
+            .to_string_lossy()
+            .to_string();
+        session.items.push(item);
+    }
+    write_file(
+        &session_path(&app),
+        &serde_json::to_string_pretty(&session)?,
+    )?;
+    Ok(())
+}
+
+/// The documents of the last session, with those whose files are gone reported
+/// separately. No session yet is an empty one.
+#[tauri::command]
+pub async fn load_session(app: AppHandle) -> Result<LoadSessionResponse, CommandError> {
+    crate::privacy::ensure_unlocked(&app)?;
+    let Ok(contents) = fs::read_to_string(session_path(&app)) else {
+        return Ok(LoadSessionResponse::default());
+    };
+    let items = parse_session(&contents);
+    let targets: Vec<PathBuf> = items.iter().map(|item| PathBuf::from(&item.path)).collect();
+    let exists =
+        tauri::async_runtime::spawn_blocking(move || paths::exist_within(&targets, EXISTS_TIMEOUT))
+            .await
+            .map_err(|error| CommandError::Io {
+                message: error.to_string(),
+            })?;
+    let (items, missing) = split_missing(items, exists);
+    if !missing.is_empty() {
+        log::info!(
+            "load_session: {} of {} files are gone",
+            missing.len(),
+            missing.len() + items.len()
+        );
+    }
+    Ok(LoadSessionResponse { items, missing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &str) -> SessionItem {
+        SessionItem {
+            kind: "mermaid".to_string(),
+            path: path.to_string(),
+            view_state: None,
+        }
+    }
+
+    #[test]
+    fn reads_current_and_newer_session_files() {
+        let current = parse_session(
+            r#"{"version":1,"saved_at":1,"items":[
+                {"kind":"excalidraw","path":"/work/a.excalidraw","view_state":{"zoom":1.5,"scroll_x":-20}},
+                {"kind":"mermaid","path":"/work/b.mmd"}
+            ]}"#,
+        );
+        assert_eq!(current.len(), 2);
+        assert_eq!(
+            current[0].view_state.as_ref().unwrap()["zoom"],
+            serde_json::json!(1.5)
+        );
+        assert_eq!(current[1], item("/work/b.mmd"));
+
+        // Fields added by a later version are skipped, not an error.
+        let newer = parse_session(
+            r#"{"version":2,"saved_at":1,"active":0,"items":[{"kind":"mermaid","path":"/work/b.mmd","window":"main"}]}"#,
+        );
+        assert_eq!(newer, vec![item("/work/b.mmd")]);
+
+        assert!(parse_session("{not json").is_empty());
+        assert!(parse_session(r#"{"items":[]}"#).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_files_separately() {
+        let (restored, missing) = split_missing(
+            vec![
+                item("/work/a.mmd"),
+                item("/work/gone.mmd"),
+                item("/net/slow.mmd"),
+            ],
+            vec![Some(true), Some(false), None],
+        );
+        assert_eq!(restored, vec![item("/work/a.mmd"), item("/net/slow.mmd")]);
+        assert_eq!(missing, vec![item("/work/gone.mmd")]);
+    }
+}
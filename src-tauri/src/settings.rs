@@ -0,0 +1,353 @@
+use crate::error::CommandError;
+use crate::{app_data_dir, write_file};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Template names to start new documents from, per kind. `None` means the built-in skeleton.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DefaultTemplates {
+    pub excalidraw: Option<String>,
+    pub mermaid: Option<String>,
+}
+
+impl DefaultTemplates {
+    pub fn for_kind(&self, kind: &str) -> Option<&str> {
+        match kind {
+            "excalidraw" => self.excalidraw.as_deref(),
+            "mermaid" => self.mermaid.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// User settings persisted to `settings.json` in the app data directory.
+///
+/// Every field has a default so older or partially written files still load.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub default_template: DefaultTemplates,
+    /// chrono format string used for the `{{date}}` template variable.
+    pub template_date_format: String,
+    /// Whether completed searches are added to the search history.
+    pub record_search_history: bool,
+    /// Proxy for outgoing HTTP requests, e.g. `http://proxy.local:3128`.
+    pub proxy_url: Option<String>,
+    /// Where `check_for_updates` fetches the version manifest from.
+    pub update_manifest_url: String,
+    /// How often to check for updates automatically: "never", "daily" or "weekly".
+    pub update_check_frequency: String,
+    /// Whether the start screen polls the files behind recents for outside changes.
+    pub watch_recent_files: bool,
+    /// How often the data directory is backed up: "never", "daily" or "weekly".
+    pub backup_frequency: String,
+    /// Folder for backup archives; `None` means next to the data directory.
+    pub backup_location: Option<String>,
+    /// How many backup archives to keep.
+    pub backup_keep: usize,
+    /// Whether keys for opened encrypted files are kept in memory for the session.
+    pub remember_encryption_keys: bool,
+    /// Whether saves write a `<name>.sha256` checksum sidecar next to the file.
+    pub write_checksums: bool,
+    /// Whether long operations that finish while the app is in the background post a
+    /// system notification. Operations can override this per call.
+    pub notify_when_done: bool,
+    /// Suppresses every system notification.
+    pub do_not_disturb: bool,
+    /// Keybinding overrides by action id; an empty string unbinds the action.
+    /// Actions without an entry use their default binding.
+    pub keybindings: BTreeMap<String, String>,
+    /// Days deleted templates and history snapshots stay in the trash.
+    pub trash_retention_days: u64,
+    /// How many folders above a missing file's old folder `relocate_file` starts
+    /// searching from.
+    pub relocate_search_depth: usize,
+    /// Longest `display_path` shown in lists before its middle is elided.
+    pub display_path_max_len: usize,
+    /// Whether the app hides its content when the session locks or after
+    /// `privacy_idle_minutes` without activity.
+    pub privacy_lock: bool,
+    /// Minutes without activity before the privacy lock engages; 0 only locks with
+    /// the session.
+    pub privacy_idle_minutes: u64,
+    /// Whether `privacy_unlock` asks for the password of an unlocked encrypted
+    /// document, when there is one.
+    pub privacy_unlock_password: bool,
+    /// Minutes without activity or a focused window before background tasks are
+    /// stretched or paused to save power; 0 keeps them on schedule.
+    pub background_idle_minutes: u64,
+    /// Seconds to wait for a native file dialog to answer before giving up on it and
+    /// asking the frontend for a path instead; 0 waits indefinitely.
+    pub dialog_timeout_secs: u64,
+    /// Whether the duration and outcome of recent commands are kept in memory for
+    /// `get_command_metrics` and the diagnostics in `get_app_info`.
+    pub collect_command_metrics: bool,
+    /// Folder the save dialog starts in; `None`, or a folder that no longer exists,
+    /// leaves it to the OS.
+    pub default_save_dir: Option<PathBuf>,
+    /// How many unpinned files recents keeps.
+    pub recents_limit: usize,
+    /// Seconds between the frontend's draft autosaves; `None` turns them off.
+    pub autosave_interval_secs: Option<u64>,
+    /// Name the save dialog suggests for a new scene, e.g. "drawing".
+    pub default_excalidraw_name: String,
+    /// Name the save dialog suggests for a new diagram, e.g. "diagram".
+    pub default_mermaid_name: String,
+    /// How many earlier versions local history keeps per file; older ones are pruned.
+    pub history_versions_limit: usize,
+    /// Files above this many bytes are read off the command thread and opened with
+    /// `large_file` set, so the frontend can show that it is busy.
+    pub large_file_threshold_bytes: u64,
+    /// Files above this many bytes are refused with `FileTooLarge` instead of read.
+    pub max_file_bytes: u64,
+    /// Whether `load_*_url` may fetch plain `http` URLs; otherwise only `https`.
+    pub allow_http_urls: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            default_template: DefaultTemplates::default(),
+            template_date_format: "%Y-%m-%d".to_string(),
+            record_search_history: true,
+            proxy_url: None,
+            update_manifest_url:
+                "https://github.com/ChrisMasterton/excalibur/releases/latest/download/latest.json"
+                    .to_string(),
+            update_check_frequency: "weekly".to_string(),
+            watch_recent_files: false,
+            backup_frequency: "never".to_string(),
+            backup_location: None,
+            backup_keep: 7,
+            remember_encryption_keys: true,
+            write_checksums: false,
+            notify_when_done: true,
+            do_not_disturb: false,
+            keybindings: BTreeMap::new(),
+            trash_retention_days: 30,
+            relocate_search_depth: 2,
+            display_path_max_len: 60,
+            privacy_lock: false,
+            privacy_idle_minutes: 10,
+            privacy_unlock_password: true,
+            background_idle_minutes: 5,
+            dialog_timeout_secs: 60,
+            collect_command_metrics: true,
+            default_save_dir: None,
+            recents_limit: crate::recent_pins::DEFAULT_RECENTS_LIMIT,
+            autosave_interval_secs: Some(30),
+            default_excalidraw_name: "drawing".to_string(),
+            default_mermaid_name: "diagram".to_string(),
+            history_versions_limit: crate::local_history::DEFAULT_VERSIONS_LIMIT,
+            large_file_threshold_bytes: crate::large_files::DEFAULT_THRESHOLD_BYTES,
+            max_file_bytes: crate::large_files::DEFAULT_MAX_BYTES,
+            allow_http_urls: false,
+        }
+    }
+}
+
+/// A partial settings update; only the fields that are present get overwritten.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsPatch {
+    default_template: Option<DefaultTemplates>,
+    template_date_format: Option<String>,
+    record_search_history: Option<bool>,
+    /// An empty string clears the proxy.
+    proxy_url: Option<String>,
+    update_manifest_url: Option<String>,
+    update_check_frequency: Option<String>,
+    watch_recent_files: Option<bool>,
+    backup_frequency: Option<String>,
+    /// An empty string resets to the default location.
+    backup_location: Option<String>,
+    backup_keep: Option<usize>,
+    remember_encryption_keys: Option<bool>,
+    write_checksums: Option<bool>,
+    notify_when_done: Option<bool>,
+    do_not_disturb: Option<bool>,
+    /// Replaces every override at once.
+    keybindings: Option<BTreeMap<String, String>>,
+    trash_retention_days: Option<u64>,
+    relocate_search_depth: Option<usize>,
+    display_path_max_len: Option<usize>,
+    privacy_lock: Option<bool>,
+    privacy_idle_minutes: Option<u64>,
+    privacy_unlock_password: Option<bool>,
+    background_idle_minutes: Option<u64>,
+    dialog_timeout_secs: Option<u64>,
+    collect_command_metrics: Option<bool>,
+    /// An empty string clears it.
+    default_save_dir: Option<String>,
+    recents_limit: Option<usize>,
+    /// 0 turns autosave off.
+    autosave_interval_secs: Option<u64>,
+    default_excalidraw_name: Option<String>,
+    default_mermaid_name: Option<String>,
+    /// 0 is refused; turning history off is not a setting.
+    history_versions_limit: Option<usize>,
+    large_file_threshold_bytes: Option<u64>,
+    /// 0 is refused.
+    max_file_bytes: Option<u64>,
+    allow_http_urls: Option<bool>,
+}
+
+fn settings_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("settings.json")
+}
+
+/// Loads the settings, falling back to defaults when the file is missing or corrupt.
+pub fn load_settings(app: &AppHandle) -> Settings {
+    let Ok(contents) = fs::read_to_string(settings_path(app)) else {
+        return Settings::default();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|error| {
+        log::warn!(
+            "load_settings: ignoring unreadable settings file: {}",
+            error
+        );
+        Settings::default()
+    })
+}
+
+fn save_settings(app: &AppHandle, settings: &Settings) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(settings)?;
+    write_file(&settings_path(app), &contents)
+}
+
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> Settings {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn update_settings(app: AppHandle, patch: SettingsPatch) -> Result<Settings, CommandError> {
+    let mut settings = load_settings(&app);
+    if let Some(default_template) = patch.default_template {
+        settings.default_template = default_template;
+    }
+    if let Some(template_date_format) = patch.template_date_format {
+        settings.template_date_format = template_date_format;
+    }
+    if let Some(record_search_history) = patch.record_search_history {
+        settings.record_search_history = record_search_history;
+    }
+    if let Some(proxy_url) = patch.proxy_url {
+        settings.proxy_url = Some(proxy_url).filter(|proxy_url| !proxy_url.trim().is_empty());
+    }
+    if let Some(update_manifest_url) = patch.update_manifest_url {
+        settings.update_manifest_url = update_manifest_url;
+    }
+    if let Some(update_check_frequency) = patch.update_check_frequency {
+        settings.update_check_frequency = update_check_frequency;
+    }
+    if let Some(watch_recent_files) = patch.watch_recent_files {
+        settings.watch_recent_files = watch_recent_files;
+    }
+    if let Some(backup_frequency) = patch.backup_frequency {
+        settings.backup_frequency = backup_frequency;
+    }
+    if let Some(backup_location) = patch.backup_location {
+        settings.backup_location =
+            Some(backup_location).filter(|backup_location| !backup_location.trim().is_empty());
+    }
+    if let Some(backup_keep) = patch.backup_keep {
+        settings.backup_keep = backup_keep;
+    }
+    if let Some(remember_encryption_keys) = patch.remember_encryption_keys {
+        settings.remember_encryption_keys = remember_encryption_keys;
+    }
+    if let Some(write_checksums) = patch.write_checksums {
+        settings.write_checksums = write_checksums;
+    }
+    if let Some(notify_when_done) = patch.notify_when_done {
+        settings.notify_when_done = notify_when_done;
+    }
+    if let Some(do_not_disturb) = patch.do_not_disturb {
+        settings.do_not_disturb = do_not_disturb;
+    }
+    if let Some(keybindings) = patch.keybindings {
+        crate::actions::validate_keybindings(&keybindings)
+            .map_err(|message| CommandError::InvalidArgument { message })?;
+        settings.keybindings = keybindings;
+    }
+    if let Some(trash_retention_days) = patch.trash_retention_days {
+        settings.trash_retention_days = trash_retention_days;
+    }
+    if let Some(relocate_search_depth) = patch.relocate_search_depth {
+        settings.relocate_search_depth = relocate_search_depth;
+    }
+    if let Some(display_path_max_len) = patch.display_path_max_len {
+        settings.display_path_max_len = display_path_max_len;
+    }
+    if let Some(privacy_lock) = patch.privacy_lock {
+        settings.privacy_lock = privacy_lock;
+    }
+    if let Some(privacy_idle_minutes) = patch.privacy_idle_minutes {
+        settings.privacy_idle_minutes = privacy_idle_minutes;
+    }
+    if let Some(privacy_unlock_password) = patch.privacy_unlock_password {
+        settings.privacy_unlock_password = privacy_unlock_password;
+    }
+    if let Some(background_idle_minutes) = patch.background_idle_minutes {
+        settings.background_idle_minutes = background_idle_minutes;
+    }
+    if let Some(dialog_timeout_secs) = patch.dialog_timeout_secs {
+        settings.dialog_timeout_secs = dialog_timeout_secs;
+    }
+    if let Some(collect_command_metrics) = patch.collect_command_metrics {
+        settings.collect_command_metrics = collect_command_metrics;
+    }
+    if let Some(default_save_dir) = patch.default_save_dir {
+        settings.default_save_dir = Some(default_save_dir)
+            .filter(|default_save_dir| !default_save_dir.trim().is_empty())
+            .map(PathBuf::from);
+    }
+    if let Some(recents_limit) = patch.recents_limit {
+        if recents_limit == 0 {
+            return Err(CommandError::InvalidArgument {
+                message: "Recents must keep at least one file".to_string(),
+            });
+        }
+        settings.recents_limit = recents_limit;
+    }
+    if let Some(autosave_interval_secs) = patch.autosave_interval_secs {
+        settings.autosave_interval_secs = Some(autosave_interval_secs).filter(|secs| *secs > 0);
+    }
+    if let Some(default_excalidraw_name) = patch.default_excalidraw_name {
+        settings.default_excalidraw_name = default_excalidraw_name;
+    }
+    if let Some(default_mermaid_name) = patch.default_mermaid_name {
+        settings.default_mermaid_name = default_mermaid_name;
+    }
+    if let Some(history_versions_limit) = patch.history_versions_limit {
+        if history_versions_limit == 0 {
+            return Err(CommandError::InvalidArgument {
+                message: "Local history must keep at least one version".to_string(),
+            });
+        }
+        settings.history_versions_limit = history_versions_limit;
+    }
+    if let Some(large_file_threshold_bytes) = patch.large_file_threshold_bytes {
+        settings.large_file_threshold_bytes = large_file_threshold_bytes;
+    }
+    if let Some(max_file_bytes) = patch.max_file_bytes {
+        if max_file_bytes == 0 {
+            return Err(CommandError::InvalidArgument {
+                message: "The largest file that can be opened must be above 0 bytes".to_string(),
+            });
+        }
+        settings.max_file_bytes = max_file_bytes;
+    }
+    if let Some(allow_http_urls) = patch.allow_http_urls {
+        settings.allow_http_urls = allow_http_urls;
+    }
+    save_settings(&app, &settings)?;
+    crate::metrics::apply_settings(&app, &settings);
+    crate::actions::notify_changed(&app);
+    Ok(settings)
+}
@@ -0,0 +1,48 @@
+use crate::launch_args;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+/// Starts a separate instance instead of handing the launch to the running one.
+pub const NEW_INSTANCE_FLAG: &str = "--new-instance";
+
+/// Whether this launch should hand itself to an already running instance. Headless
+/// exports and `--stdin` documents always run in their own process.
+pub fn is_enabled(args: &[String], headless: bool, stdin: bool) -> bool {
+    !headless && !stdin && !args.iter().any(|arg| arg == NEW_INSTANCE_FLAG)
+}
+
+/// Called in the running instance when another launch was handed to it: opens the files
+/// and links that launch was given, as it would have at startup, and brings the main
+/// window to the front. The other process exits on its own.
+pub fn on_second_launch(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    log::info!("single instance: second launch with {:?}", argv);
+    launch_args::open(app, &argv, Path::new(&cwd), true);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        if let Err(error) = window.set_focus() {
+            log::warn!("single instance: failed to focus: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn new_instance_flag_and_headless_launches_skip_forwarding() {
+        assert!(is_enabled(&args(&["excalibur", "a.mmd"]), false, false));
+        assert!(!is_enabled(
+            &args(&["excalibur", NEW_INSTANCE_FLAG]),
+            false,
+            false
+        ));
+        assert!(!is_enabled(&args(&["excalibur"]), true, false));
+        assert!(!is_enabled(&args(&["excalibur", "--stdin"]), false, true));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::app_data_dir;
+use crate::app_info::directory_size;
+use crate::error::CommandError;
+use crate::logging::LOG_FILE;
+use crate::trash::TRASH_DIR;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// How deep the walks totalling each category go.
+const MAX_WALK_DEPTH: usize = 8;
+
+/// The categories of the data directory, the entries each is made of, and whether
+/// `clean_storage` may clear it. Templates and drafts hold the user's own work.
+const CATEGORIES: [(&str, &[&str], bool); 8] = [
+    ("recents", &["recents.json", "recent-previews.json"], true),
+    ("thumbnails", &["thumbnails"], true),
+    ("drafts", &["drafts"], false),
+    ("history", &["history"], true),
+    ("templates", &["templates"], false),
+    ("search_index", &["search-index"], true),
+    ("logs", &["logs"], true),
+    ("trash", &[TRASH_DIR], true),
+];
+
+#[derive(Serialize, Debug)]
+pub struct StorageCategory {
+    id: &'static str,
+    bytes: u64,
+    cleanable: bool,
+}
+
+#[derive(Serialize)]
+pub struct StorageBreakdown {
+    categories: Vec<StorageCategory>,
+    /// Settings, favorites, workspaces and everything else not in a category.
+    other_bytes: u64,
+    total_bytes: u64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct CleanedCategory {
+    id: String,
+    bytes: u64,
+    /// Entries left alone, e.g. history of documents open right now.
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CleanReport {
+    dry_run: bool,
+    categories: Vec<CleanedCategory>,
+    bytes_reclaimed: u64,
+}
+
+fn entry_size(path: &Path) -> u64 {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => directory_size(path, MAX_WALK_DEPTH),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+fn breakdown(data_dir: &Path) -> StorageBreakdown {
+    let categories: Vec<StorageCategory> = CATEGORIES
+        .iter()
+        .map(|(id, entries, cleanable)| StorageCategory {
+            id,
+            bytes: entries
+                .iter()
+                .map(|entry| entry_size(&data_dir.join(entry)))
+                .sum(),
+            cleanable: *cleanable,
+        })
+        .collect();
+    let total_bytes = directory_size(data_dir, MAX_WALK_DEPTH);
+    let categorized: u64 = categories.iter().map(|category| category.bytes).sum();
+    StorageBreakdown {
+        categories,
+        other_bytes: total_bytes.saturating_sub(categorized),
+        total_bytes,
+    }
+}
+
+/// The files and folders cleaning `category` removes, and those it leaves alone.
+/// Recents keep their list and only lose the preview cache; history folders named in
+/// `keep_history` stay, and so does the log file currently written to.
+fn clean_targets(
+    data_dir: &Path,
+    category: &str,
+    keep_history: &HashSet<String>,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let children = |dir: PathBuf| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default()
+    };
+    match category {
+        "recents" => (vec![data_dir.join("recent-previews.json")], Vec::new()),
+        "history" => {
+            let (kept, removed): (Vec<PathBuf>, Vec<PathBuf>) = children(data_dir.join("history"))
+                .into_iter()
+                .partition(|path| {
+                    path.file_name()
+                        .is_some_and(|name| keep_history.contains(&*name.to_string_lossy()))
+                });
+            let skipped = kept
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            (removed, skipped)
+        }
+        "thumbnails" => (children(data_dir.join("thumbnails")), Vec::new()),
+        "logs" => {
+            let current = data_dir.join("logs").join(LOG_FILE);
+            let (kept, removed): (Vec<PathBuf>, Vec<PathBuf>) = children(data_dir.join("logs"))
+                .into_iter()
+                .partition(|path| *path == current);
+            let skipped = kept
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            (removed, skipped)
+        }
+        "search_index" => (children(data_dir.join("search-index")), Vec::new()),
+        "trash" => (vec![data_dir.join(TRASH_DIR)], Vec::new()),
+        _ => (Vec::new(), Vec::new()),
+    }
+}
+
+/// Removes `targets`, or with `dry_run` only measures them. Entries that can't be
+/// removed (e.g. a log file held open on Windows) are reported as skipped.
+fn remove_targets(targets: Vec<PathBuf>, dry_run: bool, cleaned: &mut CleanedCategory) {
+    for target in targets {
+        let bytes = entry_size(&target);
+        if dry_run {
+            cleaned.bytes += bytes;
+            continue;
+        }
+        let removed = if target.is_dir() {
+            fs::remove_dir_all(&target)
+        } else {
+            fs::remove_file(&target)
+        };
+        match removed {
+            Ok(()) => cleaned.bytes += bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                log::warn!(
+                    "clean_storage: failed to remove {}: {}",
+                    target.display(),
+                    error
+                );
+                cleaned.skipped.push(target.to_string_lossy().to_string());
+            }
+        }
+    }
+}
+
+/// Disk usage of the data directory by category.
+#[tauri::command]
+pub fn get_storage_breakdown(app: AppHandle) -> StorageBreakdown {
+    breakdown(&app_data_dir(&app))
+}
+
+/// Clears the selected categories and reports the bytes reclaimed; with `dry_run`
+/// nothing is removed. Only caches can be cleaned: documents, settings, templates
+/// and drafts never are. The history of documents open in this instance is kept,
+/// since a save may be writing to it, and so is that of pinned recents. Search
+/// indexes are unloaded under their locks so no half-written index survives.
+#[tauri::command]
+pub fn clean_storage(
+    app: AppHandle,
+    categories: Vec<String>,
+    dry_run: bool,
+) -> Result<CleanReport, CommandError> {
+    for category in &categories {
+        match CATEGORIES.iter().find(|(id, _, _)| id == category) {
+            None => {
+                return Err(CommandError::InvalidArgument {
+                    message: format!("Unknown storage category \"{category}\""),
+                })
+            }
+            Some((_, _, false)) => {
+                return Err(CommandError::InvalidArgument {
+                    message: format!("{category} holds your own work and can't be cleaned"),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    let data_dir = app_data_dir(&app);
+    let pinned: Vec<PathBuf> = crate::load_recents(&app)
+        .into_iter()
+        .filter(|item| item.pinned && !crate::remote::is_url(&item.path))
+        .map(|item| PathBuf::from(item.path))
+        .collect();
+    let keep_history: HashSet<String> = crate::locks::held_documents(&app)
+        .iter()
+        .chain(&pinned)
+        .map(|document| crate::local_history::history_key(document))
+        .collect();
+    let mut report = CleanReport {
+        dry_run,
+        categories: Vec::new(),
+        bytes_reclaimed: 0,
+    };
+    for category in categories {
+        let mut cleaned = CleanedCategory {
+            id: category.clone(),
+            ..Default::default()
+        };
+        if category == "search_index" && !dry_run {
+            cleaned.bytes = crate::search_index::clear_indexes(&app);
+        } else {
+            let (targets, skipped) = clean_targets(&data_dir, &category, &keep_history);
+            cleaned.skipped = skipped;
+            remove_targets(targets, dry_run, &mut cleaned);
+        }
+        report.bytes_reclaimed += cleaned.bytes;
+        report.categories.push(cleaned);
+    }
+    log::info!(
+        "clean_storage: {} bytes{}",
+        report.bytes_reclaimed,
+        if dry_run { " (dry run)" } else { "" }
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+    }
+
+    fn bytes_of(breakdown: &StorageBreakdown, id: &str) -> u64 {
+        breakdown
+            .categories
+            .iter()
+            .find(|category| category.id == id)
+            .unwrap()
+            .bytes
+    }
+
+    #[test]
+    fn breaks_usage_down_by_category() {
+        let dir = temp_dir("storage", "breakdown");
+        write(&dir.join("recents.json"), 10);
+        write(&dir.join("recent-previews.json"), 5);
+        write(&dir.join("history/abc/1.snapshot"), 100);
+        write(&dir.join("search-index/root.json"), 40);
+        write(&dir.join("settings.json"), 7);
+
+        let usage = breakdown(&dir);
+        assert_eq!(bytes_of(&usage, "recents"), 15);
+        assert_eq!(bytes_of(&usage, "history"), 100);
+        assert_eq!(bytes_of(&usage, "search_index"), 40);
+        assert_eq!(bytes_of(&usage, "templates"), 0);
+        assert_eq!(usage.other_bytes, 7);
+        assert_eq!(usage.total_bytes, 162);
+    }
+
+    #[test]
+    fn cleaning_keeps_recents_and_open_documents_history() {
+        let dir = temp_dir("storage", "clean");
+        write(&dir.join("recents.json"), 10);
+        write(&dir.join("recent-previews.json"), 5);
+        write(&dir.join("history/open/1.snapshot"), 100);
+        write(&dir.join("history/closed/1.snapshot"), 30);
+        let keep = HashSet::from(["open".to_string()]);
+
+        let mut dry = CleanedCategory::default();
+        let (targets, skipped) = clean_targets(&dir, "history", &keep);
+        assert_eq!(skipped, [dir.join("history/open").to_string_lossy()]);
+        remove_targets(targets, true, &mut dry);
+        assert_eq!(dry.bytes, 30);
+        assert!(dir.join("history/closed").exists());
+
+        let mut history = CleanedCategory::default();
+        remove_targets(clean_targets(&dir, "history", &keep).0, false, &mut history);
+        assert_eq!(history.bytes, 30);
+        assert!(!dir.join("history/closed").exists());
+        assert!(dir.join("history/open/1.snapshot").exists());
+
+        let mut recents = CleanedCategory::default();
+        remove_targets(clean_targets(&dir, "recents", &keep).0, false, &mut recents);
+        assert_eq!(recents.bytes, 5);
+        assert!(dir.join("recents.json").exists());
+    }
+
+    #[test]
+    fn cleaning_logs_keeps_the_current_file() {
+        let dir = temp_dir("storage", "logs");
+        write(&dir.join("logs").join(LOG_FILE), 20);
+        write(&dir.join("logs").join(format!("{LOG_FILE}.1")), 50);
+
+        let mut logs = CleanedCategory::default();
+        let (targets, skipped) = clean_targets(&dir, "logs", &HashSet::new());
+        assert_eq!(skipped, [dir.join("logs").join(LOG_FILE).to_string_lossy()]);
+        remove_targets(targets, false, &mut logs);
+        assert_eq!(logs.bytes, 50);
+        assert!(dir.join("logs").join(LOG_FILE).exists());
+        assert!(!dir.join("logs").join(format!("{LOG_FILE}.1")).exists());
+    }
+}
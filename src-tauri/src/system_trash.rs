@@ -0,0 +1,175 @@
+use crate::error::CommandError;
+use crate::{checksums, forget_recents, RecentEntry};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// What happened to a file the user asked to trash.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashOutcome {
+    /// Moved to the system trash or recycle bin.
+    Trashed,
+    /// Deleted for good, as confirmed after the trash turned out to be unavailable.
+    Deleted,
+    /// There was nothing to trash; only its recents entries were removed.
+    AlreadyGone,
+}
+
+#[derive(Serialize)]
+pub struct TrashFileResponse {
+    outcome: TrashOutcome,
+    recents: Vec<RecentEntry>,
+}
+
+/// Gets rid of the file at `path` with `move_to_trash`. When the trash can't take
+/// it, the file is only deleted permanently if `allow_permanent_delete` is set;
+/// otherwise `TrashUnavailable` asks the user first. Its checksum sidecar goes the
+/// same way.
+fn discard(
+    path: &Path,
+    allow_permanent_delete: bool,
+    move_to_trash: impl Fn(&Path) -> Result<(), String>,
+) -> Result<TrashOutcome, CommandError> {
+    let outcome = discard_file(path, allow_permanent_delete, &move_to_trash)?;
+    let sidecar = checksums::sidecar_path(path);
+    if outcome != TrashOutcome::AlreadyGone && sidecar.is_file() {
+        let removed = match outcome {
+            TrashOutcome::Trashed => move_to_trash(&sidecar),
+            _ => fs::remove_file(&sidecar).map_err(|error| error.to_string()),
+        };
+        if let Err(reason) = removed {
+            log::warn!(
+                "discard: left the checksum {} behind: {}",
+                sidecar.display(),
+                reason
+            );
+        }
+    }
+    Ok(outcome)
+}
+
+fn discard_file(
+    path: &Path,
+    allow_permanent_delete: bool,
+    move_to_trash: impl Fn(&Path) -> Result<(), String>,
+) -> Result<TrashOutcome, CommandError> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            return Err(CommandError::InvalidArgument {
+                message: format!("{} is a folder, not a document", path.display()),
+            })
+        }
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(TrashOutcome::AlreadyGone)
+        }
+        Err(error) => return Err(CommandError::from_io(error, path)),
+    }
+    let reason = match move_to_trash(path) {
+        Ok(()) => return Ok(TrashOutcome::Trashed),
+        Err(reason) => reason,
+    };
+    if !allow_permanent_delete {
+        return Err(CommandError::TrashUnavailable {
+            path: path.to_string_lossy().to_string(),
+            reason,
+        });
+    }
+    fs::remove_file(path).map_err(|error| CommandError::from_io(error, path))?;
+    Ok(TrashOutcome::Deleted)
+}
+
+/// Moves the file at `path` to the system trash, removes its recents entries and
+/// returns the updated recents. Where there is no trash the call fails with
+/// `TrashUnavailable`; repeating it with `allow_permanent_delete` deletes the file
+/// instead.
+#[tauri::command]
+pub fn trash_file(
+    app: AppHandle,
+    path: String,
+    allow_permanent_delete: Option<bool>,
+) -> Result<TrashFileResponse, CommandError> {
+    let outcome = discard(
+        Path::new(&path),
+        allow_permanent_delete.unwrap_or(false),
+        |path| ::trash::delete(path).map_err(|error| error.to_string()),
+    )?;
+    log::info!("trash_file: {} {:?}", path, outcome);
+    let recents = forget_recents(&app, |item| item.path == path)?;
+    Ok(TrashFileResponse { outcome, recents })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_deleting_only_when_allowed() {
+        let dir = std::env::temp_dir().join(format!("excalibur-trash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("scratch.mmd");
+        fs::write(&file, "graph TD").unwrap();
+        let no_trash = |_: &Path| Err("no trash directory".to_string());
+
+        assert_eq!(
+            discard(&file, false, |_| Ok(())).unwrap(),
+            TrashOutcome::Trashed
+        );
+        match discard(&file, false, no_trash) {
+            Err(CommandError::TrashUnavailable { reason, .. }) => {
+                assert_eq!(reason, "no trash directory")
+            }
+            other => panic!("expected TrashUnavailable, got {other:?}"),
+        }
+        assert!(file.exists());
+
+        assert_eq!(
+            discard(&file, true, no_trash).unwrap(),
+            TrashOutcome::Deleted
+        );
+        assert!(!file.exists());
+        assert_eq!(
+            discard(&file, false, no_trash).unwrap(),
+            TrashOutcome::AlreadyGone
+        );
+        assert_eq!(
+            discard(&dir, true, no_trash).unwrap_err().code(),
+            "invalid_argument"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn checksum_sidecars_go_with_their_document() {
+        let dir =
+            std::env::temp_dir().join(format!("excalibur-trash-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("flow.mmd");
+        let sidecar = checksums::sidecar_path(&file);
+        let trashed = std::cell::RefCell::new(Vec::new());
+        let to_trash = |path: &Path| {
+            trashed.borrow_mut().push(path.to_path_buf());
+            fs::remove_file(path).map_err(|error| error.to_string())
+        };
+
+        fs::write(&file, "graph TD").unwrap();
+        fs::write(&sidecar, "ab12  flow.mmd\n").unwrap();
+        assert_eq!(
+            discard(&file, false, to_trash).unwrap(),
+            TrashOutcome::Trashed
+        );
+        assert_eq!(*trashed.borrow(), [file.clone(), sidecar.clone()]);
+
+        fs::write(&file, "graph TD").unwrap();
+        fs::write(&sidecar, "ab12  flow.mmd\n").unwrap();
+        assert_eq!(
+            discard(&file, true, |_| Err("no trash directory".to_string())).unwrap(),
+            TrashOutcome::Deleted
+        );
+        assert!(!sidecar.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::CommandError;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Element fields that hold user-visible text in an excalidraw scene.
+const EXCALIDRAW_TEXT_FIELDS: [&str; 3] = ["text", "rawText", "originalText"];
+
+/// Replaces `{{ name }}` placeholders in `text` with values from `variables`.
+///
+/// Placeholders without a value are left in place and their names are added to
+/// `unresolved`.
+pub fn substitute_text(
+    text: &str,
+    variables: &HashMap<String, String>,
+    unresolved: &mut BTreeSet<String>,
+) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let key = rest[start + 2..start + 2 + length].trim();
+        let end = start + 2 + length + 2;
+
+        output.push_str(&rest[..start]);
+        let is_placeholder = !key.is_empty()
+            && key
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'));
+        match variables.get(key) {
+            Some(value) if is_placeholder => output.push_str(value),
+            _ => {
+                if is_placeholder {
+                    unresolved.insert(key.to_string());
+                }
+                output.push_str(&rest[start..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Substitutes placeholders inside the text fields of an excalidraw scene.
+///
+/// Only element text and element/frame names are touched, so braces that happen to
+/// appear anywhere else in the JSON can't be corrupted.
+pub fn substitute_excalidraw(
+    contents: &str,
+    variables: &HashMap<String, String>,
+    unresolved: &mut BTreeSet<String>,
+) -> Result<String, CommandError> {
+    let mut scene: Value = serde_json::from_str(contents)?;
+
+    if let Some(elements) = scene.get_mut("elements").and_then(Value::as_array_mut) {
+        for element in elements.iter_mut().filter_map(Value::as_object_mut) {
+            for field in EXCALIDRAW_TEXT_FIELDS.iter().chain(["name"].iter()) {
+                if let Some(Value::String(text)) = element.get_mut(*field) {
+                    *text = substitute_text(text, variables, unresolved);
+                }
+            }
+        }
+    }
+
+    serde_json::to_string_pretty(&scene).map_err(|error| CommandError::InvalidDocument {
+        message: error.to_string(),
+    })
+}
+
+/// Returns the name of the logged-in OS user, if it can be determined.
+pub fn os_username() -> Option<String> {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn variables() -> HashMap<String, String> {
+        HashMap::from([
+            ("date".to_string(), "2026-03-01".to_string()),
+            ("user".to_string(), "ada".to_string()),
+        ])
+    }
+
+    #[test]
+    fn substitutes_known_placeholders_and_reports_the_rest() {
+        let mut unresolved = BTreeSet::new();
+        let text = substitute_text(
+            "{{date}} by {{ user }} for {{ project }} and {{ owner.name }}",
+            &variables(),
+            &mut unresolved,
+        );
+        assert_eq!(
+            text,
+            "2026-03-01 by ada for {{ project }} and {{ owner.name }}"
+        );
+        assert_eq!(
+            unresolved.into_iter().collect::<Vec<_>>(),
+            ["owner.name", "project"]
+        );
+    }
+
+    #[test]
+    fn braces_that_are_not_placeholders_are_left_alone() {
+        let mut unresolved = BTreeSet::new();
+        for text in [
+            "fn main() { println!(\"{}\", x); }",
+            "{{}} and {{ two words }} and {{ a+b }}",
+            "set = {{1, 2}, {3}}",
+            "unclosed {{date",
+        ] {
+            assert_eq!(substitute_text(text, &variables(), &mut unresolved), text);
+        }
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn only_element_text_and_names_are_substituted() {
+        let scene = json!({
+            "type": "excalidraw",
+            "version": 2,
+            "elements": [{
+                "type": "text",
+                "x": 10.5,
+                "y": -3,
+                "text": "{{date}} {x: {{ missing }}}",
+                "originalText": "{{date}} {x: {{ missing }}}",
+                "link": "https://example.com/{{date}}",
+                "customData": {"note": "{{user}}"},
+            }],
+            "appState": {"name": "{{user}}"},
+        });
+        let mut unresolved = BTreeSet::new();
+        let output =
+            substitute_excalidraw(&scene.to_string(), &variables(), &mut unresolved).unwrap();
+        let output: Value = serde_json::from_str(&output).unwrap();
+
+        let element = &output["elements"][0];
+        assert_eq!(element["text"], "2026-03-01 {x: {{ missing }}}");
+        assert_eq!(element["originalText"], "2026-03-01 {x: {{ missing }}}");
+        assert_eq!(element["x"], 10.5);
+        assert_eq!(element["y"], -3);
+        assert_eq!(element["link"], "https://example.com/{{date}}");
+        assert_eq!(element["customData"]["note"], "{{user}}");
+        assert_eq!(output["appState"]["name"], "{{user}}");
+        assert_eq!(unresolved.into_iter().collect::<Vec<_>>(), ["missing"]);
+    }
+}
@@ -0,0 +1,701 @@
+use crate::dialogs::pick_save_path;
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::template_vars::{os_username, substitute_excalidraw, substitute_text};
+use crate::trash::TrashKind;
+use crate::{
+    app_data_dir, file_name, kind_dialog_filter, now_epoch, read_file, update_recents, write_file,
+    OpenFileResponse,
+};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Document kinds that can have templates, in the order they are listed.
+const TEMPLATE_KINDS: [&str; 2] = ["excalidraw", "mermaid"];
+
+const EMPTY_EXCALIDRAW_SCENE: &str = r##"{
+  "type": "excalidraw",
+  "version": 2,
+  "source": "excalibur",
+  "elements": [],
+  "appState": {
+    "viewBackgroundColor": "#ffffff"
+  },
+  "files": {}
+}"##;
+const EMPTY_MERMAID_DIAGRAM: &str = "flowchart TD\n";
+
+/// appState keys that describe a particular editing session rather than the drawing.
+const VOLATILE_APP_STATE_KEYS: [&str; 10] = [
+    "scrollX",
+    "scrollY",
+    "zoom",
+    "collaborators",
+    "selectedElementIds",
+    "selectedGroupIds",
+    "editingGroupId",
+    "cursorButton",
+    "openMenu",
+    "openDialog",
+];
+
+#[derive(Serialize, Clone)]
+pub struct TemplateInfo {
+    name: String,
+    kind: String,
+    path: String,
+    title: Option<String>,
+    description: Option<String>,
+    updated_at: u64,
+}
+
+/// Contents of the `<name>.meta.json` file stored next to a template.
+#[derive(Serialize, Deserialize, Default)]
+struct TemplateMetaFile {
+    title: Option<String>,
+    description: Option<String>,
+    created_at: u64,
+}
+
+/// A document created from a template that has not been written to disk yet.
+#[derive(Serialize)]
+pub struct UntitledDocument {
+    /// Identifies the document to the backend until its first save, e.g.
+    /// "untitled-mermaid-2".
+    id: String,
+    /// "Untitled Mermaid 2", numbered per kind by the backend.
+    display_name: String,
+    kind: String,
+    template: Option<String>,
+    contents: String,
+}
+
+impl UntitledDocument {
+    /// Registers the document with `untitled` so it gets an id and a name.
+    fn new(app: &AppHandle, kind: &str, template: Option<String>, contents: String) -> Self {
+        let (id, display_name) = crate::untitled::create(app, kind);
+        UntitledDocument {
+            id,
+            display_name,
+            kind: kind.to_string(),
+            template,
+            contents,
+        }
+    }
+}
+
+/// Default templates whose absence has already been reported during this session.
+pub struct ReportedMissingDefaults(pub Mutex<HashSet<String>>);
+
+#[derive(Serialize, Clone)]
+struct MissingDefaultTemplate {
+    kind: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TemplateDocument {
+    Untitled(UntitledDocument),
+    Saved(OpenFileResponse),
+}
+
+#[derive(Serialize)]
+pub struct NewFromTemplateResponse {
+    #[serde(flatten)]
+    document: TemplateDocument,
+    /// Placeholders left in the document because no value was available for them.
+    unresolved_placeholders: Vec<String>,
+}
+
+fn template_extension(kind: &str) -> Result<&'static str, CommandError> {
+    match kind {
+        "excalidraw" => Ok("excalidraw"),
+        "mermaid" => Ok("mmd"),
+        _ => Err(CommandError::InvalidKind {
+            kind: kind.to_string(),
+        }),
+    }
+}
+
+pub fn templates_dir(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("templates")
+}
+
+pub fn template_path(app: &AppHandle, kind: &str, name: &str) -> Result<PathBuf, CommandError> {
+    template_file(&templates_dir(app), kind, name)
+}
+
+/// Where template `name` of `kind` is stored under the templates directory `dir`.
+/// Names come from the frontend and from imported archives, so only names
+/// `sanitize_template_name` leaves as they are are accepted; anything else could
+/// point outside the templates directory.
+pub fn template_file(dir: &Path, kind: &str, name: &str) -> Result<PathBuf, CommandError> {
+    let extension = template_extension(kind)?;
+    let is_plain = !name.contains(['/', '\\'])
+        && !name.contains("..")
+        && sanitize_template_name(name).is_ok_and(|sanitized| sanitized == name);
+    if !is_plain {
+        return Err(CommandError::InvalidArgument {
+            message: format!("\"{name}\" is not a valid template name"),
+        });
+    }
+    Ok(dir.join(kind).join(format!("{name}.{extension}")))
+}
+
+/// Resolves a template by name, distinguishing "does not exist at all" from
+/// "exists, but under another kind".
+fn find_template(app: &AppHandle, kind: &str, name: &str) -> Result<PathBuf, CommandError> {
+    let path = template_path(app, kind, name)?;
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    for other in TEMPLATE_KINDS.iter().filter(|other| **other != kind) {
+        if template_path(app, other, name)?.is_file() {
+            return Err(CommandError::KindMismatch {
+                name: name.to_string(),
+                requested: kind.to_string(),
+                actual: other.to_string(),
+            });
+        }
+    }
+
+    Err(CommandError::TemplateNotFound {
+        kind: kind.to_string(),
+        name: name.to_string(),
+    })
+}
+
+pub fn meta_path(template: &Path) -> PathBuf {
+    template.with_extension("meta.json")
+}
+
+/// Writes the `meta.json` sidecar for the template stored at `template`.
+pub fn write_template_meta(
+    template: &Path,
+    title: &str,
+    description: Option<String>,
+) -> Result<(), CommandError> {
+    let meta = TemplateMetaFile {
+        title: Some(title.to_string()),
+        description: description
+            .map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty()),
+        created_at: now_epoch(),
+    };
+    let contents = serde_json::to_string_pretty(&meta).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    write_file(&meta_path(template), &contents)?;
+    Ok(())
+}
+
+fn read_meta(template: &Path) -> TemplateMetaFile {
+    fs::read_to_string(meta_path(template))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn template_info(kind: &str, path: &Path) -> Option<TemplateInfo> {
+    let name = path.file_stem()?.to_string_lossy().to_string();
+    let meta = read_meta(path);
+    Some(TemplateInfo {
+        name,
+        kind: kind.to_string(),
+        path: path.to_string_lossy().to_string(),
+        title: meta.title,
+        description: meta.description,
+        updated_at: modified_epoch(path),
+    })
+}
+
+fn modified_epoch(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn list_kind_templates(app: &AppHandle, kind: &str) -> Result<Vec<TemplateInfo>, CommandError> {
+    let extension = template_extension(kind)?;
+    let Ok(entries) = fs::read_dir(templates_dir(app).join(kind)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut templates: Vec<TemplateInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .filter_map(|path| template_info(kind, &path))
+        .collect();
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    Ok(templates)
+}
+
+#[tauri::command]
+pub fn list_templates(
+    app: AppHandle,
+    kind: Option<String>,
+) -> Result<Vec<TemplateInfo>, CommandError> {
+    match kind {
+        Some(kind) => list_kind_templates(&app, &kind),
+        None => {
+            let mut templates = Vec::new();
+            for kind in TEMPLATE_KINDS {
+                templates.extend(list_kind_templates(&app, kind)?);
+            }
+            Ok(templates)
+        }
+    }
+}
+
+/// Builds the variables available to every template: date, time, filename, title, author.
+///
+/// Caller-supplied `variables` are applied last so they can override the standard set.
+fn template_variables(
+    app: &AppHandle,
+    stem: &str,
+    title: Option<String>,
+    variables: Option<HashMap<String, String>>,
+) -> HashMap<String, String> {
+    let settings = load_settings(app);
+    let now = Local::now();
+    let mut date = String::new();
+    if write!(date, "{}", now.format(&settings.template_date_format)).is_err() {
+        log::warn!(
+            "template_variables: invalid date format {:?}, using the default",
+            settings.template_date_format
+        );
+        date = now.format("%Y-%m-%d").to_string();
+    }
+
+    let mut values = HashMap::from([
+        ("date".to_string(), date),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        ("filename".to_string(), stem.to_string()),
+        (
+            "title".to_string(),
+            title.unwrap_or_else(|| stem.to_string()),
+        ),
+    ]);
+    if let Some(author) = os_username() {
+        values.insert("author".to_string(), author);
+    }
+    values.extend(variables.unwrap_or_default());
+    values
+}
+
+/// The contents of a new document made from a template of `kind` holding
+/// `template_contents`, with its placeholders substituted from `values`.
+fn instantiate(
+    kind: &str,
+    template_contents: &str,
+    values: &HashMap<String, String>,
+    unresolved: &mut BTreeSet<String>,
+) -> Result<String, CommandError> {
+    if kind == "excalidraw" {
+        substitute_excalidraw(template_contents, values, unresolved)
+    } else {
+        Ok(substitute_text(template_contents, values, unresolved))
+    }
+}
+
+/// Creates a new document from a template.
+///
+/// Without a target (and without `choose_location`) the contents come back as an
+/// untitled document; otherwise they are written to the target and the new file
+/// is added to recents. The template file itself is only ever read.
+///
+/// `{{placeholder}}` variables are substituted on the way; for excalidraw templates
+/// only the text of elements is rewritten, never the surrounding JSON.
+#[tauri::command]
+pub async fn new_from_template(
+    app: AppHandle,
+    name: String,
+    kind: String,
+    target: Option<String>,
+    choose_location: Option<bool>,
+    title: Option<String>,
+    variables: Option<HashMap<String, String>>,
+) -> Result<NewFromTemplateResponse, CommandError> {
+    log::info!(
+        "new_from_template: name={}, kind={}, target={:?}",
+        name,
+        kind,
+        target
+    );
+    let source = find_template(&app, &kind, &name)?;
+    let template_contents = read_file(&source)?;
+
+    let path = match target {
+        Some(target) => Some(PathBuf::from(target)),
+        None if choose_location.unwrap_or(false) => {
+            let extension = template_extension(&kind)?;
+            let (filter_name, extensions) = kind_dialog_filter(&kind);
+            let path = pick_save_path(&app, filter_name, extensions, format!("{name}.{extension}"))
+                .await?
+                .ok_or(CommandError::Cancelled)?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    let stem = path
+        .as_deref()
+        .and_then(Path::file_stem)
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.clone());
+    let values = template_variables(&app, &stem, title, variables);
+    let mut unresolved = BTreeSet::new();
+    let contents = instantiate(&kind, &template_contents, &values, &mut unresolved)?;
+    let unresolved_placeholders = unresolved.into_iter().collect();
+
+    let Some(path) = path else {
+        return Ok(NewFromTemplateResponse {
+            document: TemplateDocument::Untitled(UntitledDocument::new(
+                &app,
+                &kind,
+                Some(name),
+                contents,
+            )),
+            unresolved_placeholders,
+        });
+    };
+
+    if fs::canonicalize(&path).ok() == Some(fs::canonicalize(&source)?) {
+        return Err(CommandError::Io {
+            message: "Cannot create a document on top of its own template".to_string(),
+        });
+    }
+
+    write_file(&path, &contents)?;
+    let document_name = file_name(&path);
+    let path_string = path.to_string_lossy().to_string();
+    update_recents(&app, &kind, &path_string, document_name.clone());
+
+    Ok(NewFromTemplateResponse {
+        document: TemplateDocument::Saved(OpenFileResponse {
+            path: path_string,
+            name: document_name,
+            contents,
+            mtime: crate::modified_millis(&path),
+            ..Default::default()
+        }),
+        unresolved_placeholders,
+    })
+}
+
+fn report_missing_default(app: &AppHandle, kind: &str, name: &str) {
+    let state = app.state::<ReportedMissingDefaults>();
+    let first_report = state.0.lock().unwrap().insert(format!("{kind}/{name}"));
+    if first_report {
+        let _ = app.emit(
+            "default-template-missing",
+            MissingDefaultTemplate {
+                kind: kind.to_string(),
+                name: name.to_string(),
+            },
+        );
+    }
+}
+
+/// Builds the contents of a new untitled document of `kind`.
+///
+/// Uses the default template from settings when one is configured, and falls back
+/// to the built-in empty skeleton (reporting the broken default once) when it isn't
+/// available.
+pub fn new_document(app: &AppHandle, kind: &str) -> Result<UntitledDocument, CommandError> {
+    let skeleton = match kind {
+        "excalidraw" => EMPTY_EXCALIDRAW_SCENE,
+        "mermaid" => EMPTY_MERMAID_DIAGRAM,
+        _ => {
+            return Err(CommandError::InvalidKind {
+                kind: kind.to_string(),
+            })
+        }
+    };
+
+    let settings = load_settings(app);
+    if let Some(name) = settings.default_template.for_kind(kind) {
+        let template = find_template(app, kind, name).and_then(|path| read_file(&path));
+        match template {
+            Ok(contents) => {
+                return Ok(UntitledDocument::new(
+                    app,
+                    kind,
+                    Some(name.to_string()),
+                    contents,
+                ));
+            }
+            Err(error) => {
+                log::warn!(
+                    "new_document: default {} template {:?} is unavailable, using the built-in skeleton: {}",
+                    kind, name, error
+                );
+                report_missing_default(app, kind, name);
+            }
+        }
+    }
+
+    Ok(UntitledDocument::new(app, kind, None, skeleton.to_string()))
+}
+
+/// Turns a user-supplied template name into something safe to use as a file stem.
+fn sanitize_template_name(name: &str) -> Result<String, CommandError> {
+    let mut sanitized = String::new();
+    for ch in name.trim().chars() {
+        let ch = if ch.is_alphanumeric() || matches!(ch, '-' | '_' | ' ') {
+            ch
+        } else {
+            '-'
+        };
+        if !(ch == '-' && sanitized.ends_with('-')) {
+            sanitized.push(ch);
+        }
+    }
+    let sanitized = sanitized
+        .trim_matches(|ch| ch == '-' || ch == ' ')
+        .to_string();
+
+    if sanitized.is_empty() {
+        return Err(CommandError::InvalidTemplateName {
+            name: name.to_string(),
+        });
+    }
+    Ok(sanitized)
+}
+
+/// Applies the same checks a save would and returns the contents to store.
+///
+/// Excalidraw scenes are re-serialized without session-specific appState so every
+/// document created from the template starts with a clean viewport.
+fn prepare_template_contents(kind: &str, contents: &str) -> Result<String, CommandError> {
+    match kind {
+        "excalidraw" => {
+            crate::scene_schema::check_save(contents)?;
+            let mut scene: Value = serde_json::from_str(contents)?;
+            let Some(object) = scene.as_object_mut() else {
+                return Err(CommandError::InvalidDocument {
+                    message: "expected a JSON object".to_string(),
+                });
+            };
+            if let Some(app_state) = object.get_mut("appState").and_then(Value::as_object_mut) {
+                for key in VOLATILE_APP_STATE_KEYS {
+                    app_state.remove(key);
+                }
+            }
+            serde_json::to_string_pretty(&scene).map_err(|error| CommandError::InvalidDocument {
+                message: error.to_string(),
+            })
+        }
+        "mermaid" => {
+            if contents.trim().is_empty() {
+                return Err(CommandError::InvalidDocument {
+                    message: "mermaid source is empty".to_string(),
+                });
+            }
+            Ok(contents.to_string())
+        }
+        _ => Err(CommandError::InvalidKind {
+            kind: kind.to_string(),
+        }),
+    }
+}
+
+/// The name a new template called `base_name` is stored under in `dir`. Taken names
+/// fail with `TemplateExists`, unless `auto_suffix` is set.
+fn free_template_name(
+    dir: &Path,
+    kind: &str,
+    base_name: &str,
+    auto_suffix: bool,
+) -> Result<String, CommandError> {
+    let mut stored_name = base_name.to_string();
+    let mut suffix = 2;
+    while template_file(dir, kind, &stored_name)?.exists() {
+        if !auto_suffix {
+            return Err(CommandError::TemplateExists {
+                kind: kind.to_string(),
+                name: stored_name,
+            });
+        }
+        stored_name = format!("{base_name} {suffix}");
+        suffix += 1;
+    }
+    Ok(stored_name)
+}
+
+/// Stores `contents` as a reusable template and returns its metadata.
+///
+/// When a template with the same name exists the call fails, unless `auto_suffix`
+/// is set, in which case the name gets a numeric suffix ("flow 2", "flow 3", ...).
+#[tauri::command]
+pub fn save_as_template(
+    app: AppHandle,
+    kind: String,
+    contents: String,
+    name: String,
+    description: Option<String>,
+    auto_suffix: Option<bool>,
+) -> Result<TemplateInfo, CommandError> {
+    log::info!("save_as_template: kind={}, name={}", kind, name);
+    let contents = prepare_template_contents(&kind, &contents)?;
+    let base_name = sanitize_template_name(&name)?;
+    let stored_name = free_template_name(
+        &templates_dir(&app),
+        &kind,
+        &base_name,
+        auto_suffix.unwrap_or(false),
+    )?;
+
+    let path = template_path(&app, &kind, &stored_name)?;
+    write_file(&path, &contents)?;
+
+    write_template_meta(&path, name.trim(), description)?;
+
+    template_info(&kind, &path).ok_or_else(|| CommandError::Io {
+        message: "Template was written but could not be read back".to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn delete_template(app: AppHandle, kind: String, name: String) -> Result<(), CommandError> {
+    log::info!("delete_template: kind={}, name={}", kind, name);
+    let path = find_template(&app, &kind, &name)?;
+    let mut files = vec![path.clone()];
+    let meta = meta_path(&path);
+    if meta.exists() {
+        files.push(meta);
+    }
+    crate::trash::move_to_trash(
+        &app,
+        TrashKind::Template,
+        &format!("{kind}/{name}"),
+        None,
+        &files,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn clashing_names_fail_or_get_the_next_free_suffix() {
+        let dir = temp_dir("templates", "names");
+        for name in ["flow", "flow 2"] {
+            let path = template_file(&dir, "mermaid", name).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "flowchart TD\n").unwrap();
+        }
+
+        let taken = free_template_name(&dir, "mermaid", "flow", false).unwrap_err();
+        assert_eq!(taken.code(), "template_exists");
+        assert_eq!(
+            free_template_name(&dir, "mermaid", "flow", true).unwrap(),
+            "flow 3"
+        );
+        // Each kind has names of its own.
+        assert_eq!(
+            free_template_name(&dir, "excalidraw", "flow", false).unwrap(),
+            "flow"
+        );
+        assert_eq!(sanitize_template_name(" ../flow? ").unwrap(), "flow");
+        assert_eq!(
+            sanitize_template_name("..").unwrap_err().code(),
+            "invalid_template_name"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn names_cannot_reach_outside_the_templates_folder() {
+        let dir = Path::new("/data/templates");
+        for name in [
+            "../../../Documents/plan",
+            "..",
+            "flow/../../plan",
+            "C:\\Users\\plan",
+            "/etc/plan",
+            " flow",
+            "flow.v2",
+            "",
+        ] {
+            let error = template_file(dir, "mermaid", name).unwrap_err();
+            assert_eq!(error.code(), "invalid_argument", "{name}");
+        }
+        assert_eq!(
+            template_file(dir, "mermaid", "flow 2").unwrap(),
+            dir.join("mermaid").join("flow 2.mmd")
+        );
+    }
+
+    #[test]
+    fn instantiating_substitutes_placeholders_into_a_copy() {
+        let values = HashMap::from([
+            ("title".to_string(), "Checkout".to_string()),
+            ("date".to_string(), "2026-03-01".to_string()),
+        ]);
+        let mut unresolved = BTreeSet::new();
+        let diagram = instantiate(
+            "mermaid",
+            "flowchart TD\n  A[{{title}}] --> B[{{ owner }}]\n",
+            &values,
+            &mut unresolved,
+        )
+        .unwrap();
+        assert_eq!(diagram, "flowchart TD\n  A[Checkout] --> B[{{ owner }}]\n");
+        assert_eq!(unresolved.iter().collect::<Vec<_>>(), ["owner"]);
+
+        let scene = serde_json::json!({
+            "type": "excalidraw",
+            "version": 2,
+            "elements": [{"type": "text", "text": "{{title}} ({{date}})"}],
+            "appState": {"scrollX": 120, "zoom": {"value": 2}, "viewBackgroundColor": "#fff"},
+        });
+        let template = prepare_template_contents("excalidraw", &scene.to_string()).unwrap();
+        let scene: Value = serde_json::from_str(
+            &instantiate("excalidraw", &template, &values, &mut unresolved).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(scene["elements"][0]["text"], "Checkout (2026-03-01)");
+        assert_eq!(
+            scene["appState"],
+            serde_json::json!({"viewBackgroundColor": "#fff"})
+        );
+    }
+
+    #[test]
+    fn templates_are_checked_like_saves() {
+        for contents in [r#"{"elements":[]}"#, r#"{"type":"excalidraw","version":2}"#] {
+            assert_eq!(
+                prepare_template_contents("excalidraw", contents)
+                    .unwrap_err()
+                    .code(),
+                "invalid_document",
+                "{contents}"
+            );
+        }
+        assert_eq!(
+            prepare_template_contents("mermaid", "  \n")
+                .unwrap_err()
+                .code(),
+            "invalid_document"
+        );
+    }
+}
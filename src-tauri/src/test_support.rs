@@ -0,0 +1,14 @@
+//! Helpers shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A fresh, empty directory for one test, named after the module's `prefix` and the
+/// test's `name` so tests running in parallel never share one.
+pub fn temp_dir(prefix: &str, name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("excalibur-{prefix}-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
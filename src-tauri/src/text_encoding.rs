@@ -0,0 +1,193 @@
+use crate::error::CommandError;
+use std::io::Read;
+use std::path::Path;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16_LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16_BE_BOM: &[u8] = b"\xfe\xff";
+const UTF32_LE_BOM: &[u8] = b"\xff\xfe\x00\x00";
+const UTF32_BE_BOM: &[u8] = b"\x00\x00\xfe\xff";
+
+/// Encodings text files are read in. Everything is saved back as plain UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+        }
+    }
+}
+
+/// The encoding a file starts with, by its byte order mark; UTF-32 is only
+/// recognised to be refused by name.
+fn sniff(bytes: &[u8]) -> Result<Encoding, &'static str> {
+    if bytes.starts_with(UTF32_LE_BOM) {
+        Err("UTF-32LE")
+    } else if bytes.starts_with(UTF32_BE_BOM) {
+        Err("UTF-32BE")
+    } else if bytes.starts_with(UTF8_BOM) {
+        Ok(Encoding::Utf8Bom)
+    } else if bytes.starts_with(UTF16_LE_BOM) {
+        Ok(Encoding::Utf16Le)
+    } else if bytes.starts_with(UTF16_BE_BOM) {
+        Ok(Encoding::Utf16Be)
+    } else {
+        Ok(Encoding::Utf8)
+    }
+}
+
+/// A name for bytes that aren't UTF-8 and carry no BOM. Text without a BOM but with
+/// a NUL in every other byte is almost certainly UTF-16.
+fn guess_unknown(bytes: &[u8]) -> &'static str {
+    let nul_at = |parity: usize| {
+        bytes.len() >= 2
+            && bytes
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|byte| **byte == 0)
+                .count()
+                * 4
+                >= bytes.len()
+    };
+    if nul_at(1) {
+        "UTF-16LE without a BOM"
+    } else if nul_at(0) {
+        "UTF-16BE without a BOM"
+    } else {
+        "an 8-bit encoding such as Windows-1252"
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let units = pairs.map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+/// Decodes a text file read from `path`: UTF-8 with or without a BOM, or UTF-16
+/// with one. The BOM is dropped from the text. Anything else fails with
+/// `InvalidEncoding`, naming the encoding the bytes look like.
+pub fn decode(path: &Path, bytes: Vec<u8>) -> Result<(String, Encoding), CommandError> {
+    let invalid = |encoding: &str| CommandError::InvalidEncoding {
+        path: path.to_string_lossy().to_string(),
+        encoding: encoding.to_string(),
+    };
+    let encoding = sniff(&bytes).map_err(invalid)?;
+    let text = match encoding {
+        Encoding::Utf8 => {
+            String::from_utf8(bytes).map_err(|error| invalid(guess_unknown(error.as_bytes())))?
+        }
+        Encoding::Utf8Bom => String::from_utf8(bytes[UTF8_BOM.len()..].to_vec())
+            .map_err(|_| invalid(encoding.name()))?,
+        Encoding::Utf16Le => decode_utf16(&bytes[UTF16_LE_BOM.len()..], u16::from_le_bytes)
+            .ok_or_else(|| invalid(encoding.name()))?,
+        Encoding::Utf16Be => decode_utf16(&bytes[UTF16_BE_BOM.len()..], u16::from_be_bytes)
+            .ok_or_else(|| invalid(encoding.name()))?,
+    };
+    Ok((text, encoding))
+}
+
+/// Text to detect the document kind from: decoded when it can be, lossily otherwise.
+pub fn decode_for_detection(bytes: &[u8]) -> String {
+    decode(Path::new(""), bytes.to_vec())
+        .map(|(text, _)| text)
+        .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The encoding to report when opening `path`, from its first bytes: `None` for
+/// plain UTF-8 (and encrypted containers), which saving leaves as it is.
+pub fn reported(path: &Path) -> Option<&'static str> {
+    let mut start = Vec::with_capacity(UTF32_LE_BOM.len());
+    crate::gzip::open(path)
+        .ok()?
+        .take(UTF32_LE_BOM.len() as u64)
+        .read_to_end(&mut start)
+        .ok()?;
+    match sniff(&start) {
+        Ok(Encoding::Utf8) => None,
+        Ok(encoding) => Some(encoding.name()),
+        Err(name) => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "flowchart LR\n  A[Café] --> B[naïve → done]\n";
+
+    fn read(fixture: &[u8]) -> Result<(String, Encoding), CommandError> {
+        decode(Path::new("/work/flow.mmd"), fixture.to_vec())
+    }
+
+    fn refused_as(fixture: &[u8]) -> String {
+        match read(fixture) {
+            Err(CommandError::InvalidEncoding { encoding, .. }) => encoding,
+            other => panic!("expected InvalidEncoding, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_utf8_with_and_without_a_bom() {
+        let (text, encoding) =
+            read(include_bytes!("../tests/fixtures/encodings/utf8.mmd")).unwrap();
+        assert_eq!((text.as_str(), encoding), (TEXT, Encoding::Utf8));
+
+        let (text, encoding) =
+            read(include_bytes!("../tests/fixtures/encodings/utf8-bom.mmd")).unwrap();
+        assert_eq!((text.as_str(), encoding), (TEXT, Encoding::Utf8Bom));
+    }
+
+    #[test]
+    fn transcodes_utf16_with_a_bom() {
+        // Saved by Notepad, so with CRLF line endings as well.
+        let (text, encoding) = read(include_bytes!(
+            "../tests/fixtures/encodings/utf16le-bom.mmd"
+        ))
+        .unwrap();
+        assert_eq!(encoding, Encoding::Utf16Le);
+        assert_eq!(text.replace("\r\n", "\n"), TEXT);
+
+        let (text, encoding) = read(include_bytes!(
+            "../tests/fixtures/encodings/utf16be-bom.mmd"
+        ))
+        .unwrap();
+        assert_eq!((text.as_str(), encoding), (TEXT, Encoding::Utf16Be));
+    }
+
+    #[test]
+    fn names_encodings_it_cant_read() {
+        assert_eq!(
+            refused_as(include_bytes!(
+                "../tests/fixtures/encodings/utf32le-bom.mmd"
+            )),
+            "UTF-32LE"
+        );
+        assert_eq!(
+            refused_as(include_bytes!(
+                "../tests/fixtures/encodings/windows-1252.mmd"
+            )),
+            "an 8-bit encoding such as Windows-1252"
+        );
+        let utf16_without_bom = &include_bytes!("../tests/fixtures/encodings/utf16le-bom.mmd")[2..];
+        // Plain ASCII UTF-16 is valid UTF-8 too; the é makes it not.
+        assert_eq!(refused_as(utf16_without_bom), "UTF-16LE without a BOM");
+        // A lone surrogate.
+        assert_eq!(refused_as(b"\xff\xfe\x00\xd8"), "UTF-16LE");
+        assert_eq!(read(b"\xff\xfe").unwrap().0, "");
+    }
+}
@@ -0,0 +1,372 @@
+use crate::background::{wait, IdlePolicy};
+use crate::error::CommandError;
+use crate::settings::load_settings;
+use crate::{app_data_dir, file_name, now_epoch, now_epoch_millis};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+pub const TRASH_DIR: &str = "trash";
+const ENTRY_FILE: &str = "entry.json";
+/// How often the background sweep looks for expired entries.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Template,
+    /// A captured save that fell off the undo stack or was discarded from redo.
+    HistorySnapshot,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TrashedFile {
+    /// Name inside the entry's folder.
+    stored: String,
+    /// Where the file was before it was deleted.
+    original: String,
+}
+
+/// Something deleted from the app data directory, kept in `trash/<id>/` until it
+/// expires.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    id: String,
+    kind: TrashKind,
+    /// What the user recognises it by, e.g. "mermaid/Sequence" for a template.
+    label: String,
+    /// Document a history snapshot was captured from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    document: Option<String>,
+    deleted_at: u64,
+    expires_at: u64,
+    bytes: u64,
+    #[serde(skip_serializing)]
+    files: Vec<TrashedFile>,
+}
+
+pub fn trash_dir(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join(TRASH_DIR)
+}
+
+/// Renames a file, falling back to copy and delete when the two paths are on
+/// different volumes.
+pub fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+fn read_entry(dir: &Path) -> Option<TrashEntry> {
+    let contents = fs::read_to_string(dir.join(ENTRY_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Moves `files` into a new trash entry that expires `retention_secs` after `now`.
+fn trash_files(
+    root: &Path,
+    kind: TrashKind,
+    label: &str,
+    document: Option<&Path>,
+    files: &[PathBuf],
+    now: u64,
+    retention_secs: u64,
+) -> Result<TrashEntry, CommandError> {
+    fs::create_dir_all(root)?;
+    let mut millis = now_epoch_millis();
+    while root.join(millis.to_string()).exists() {
+        millis += 1;
+    }
+    let id = millis.to_string();
+    let dir = root.join(&id);
+    fs::create_dir(&dir)?;
+
+    let mut entry = TrashEntry {
+        id,
+        kind,
+        label: label.to_string(),
+        document: document.map(|document| document.to_string_lossy().to_string()),
+        deleted_at: now,
+        expires_at: now.saturating_add(retention_secs),
+        bytes: 0,
+        files: Vec::new(),
+    };
+    for (index, file) in files.iter().enumerate() {
+        let stored = format!("{index}-{}", file_name(file).unwrap_or_default());
+        entry.bytes += fs::metadata(file)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if let Err(error) = move_file(file, &dir.join(&stored)) {
+            // Put back what was already moved so nothing is half deleted.
+            for moved in &entry.files {
+                let _ = move_file(&dir.join(&moved.stored), Path::new(&moved.original));
+            }
+            let _ = fs::remove_dir_all(&dir);
+            return Err(error.into());
+        }
+        entry.files.push(TrashedFile {
+            stored,
+            original: file.to_string_lossy().to_string(),
+        });
+    }
+    let contents = serde_json::to_string_pretty(&entry).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    fs::write(dir.join(ENTRY_FILE), contents)?;
+    Ok(entry)
+}
+
+/// Entries in the trash, most recently deleted first. Folders without a readable
+/// `entry.json` are skipped.
+fn list_entries(root: &Path) -> Vec<TrashEntry> {
+    let Ok(dirs) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<TrashEntry> = dirs
+        .flatten()
+        .filter_map(|dir| read_entry(&dir.path()))
+        .collect();
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(b.id.cmp(&a.id)));
+    entries
+}
+
+/// `design.excalidraw` -> `design (recovered).excalidraw`, numbered if taken.
+fn recovered_path(document: &Path) -> PathBuf {
+    let name = file_name(document).unwrap_or_default();
+    let (stem, extension) = match name.find('.') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ => (name.as_str(), ""),
+    };
+    let parent = document.parent().unwrap_or(Path::new(""));
+    let mut candidate = parent.join(format!("{stem} (recovered){extension}"));
+    let mut suffix = 2;
+    while candidate.exists() {
+        candidate = parent.join(format!("{stem} (recovered {suffix}){extension}"));
+        suffix += 1;
+    }
+    candidate
+}
+
+/// Moves an entry's files back and removes the entry. Templates go back where they
+/// were; history snapshots come back as a `(recovered)` copy next to their document,
+/// since the undo stack they belonged to has moved on. Returns the restored paths.
+fn restore_entry(root: &Path, id: &str) -> Result<Vec<String>, CommandError> {
+    let dir = root.join(id);
+    let entry = Some(id)
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|_| read_entry(&dir))
+        .ok_or_else(|| CommandError::TrashEntryNotFound { id: id.to_string() })?;
+
+    let targets: Vec<PathBuf> = match (entry.kind, &entry.document) {
+        (TrashKind::HistorySnapshot, Some(document)) => entry
+            .files
+            .iter()
+            .map(|_| recovered_path(Path::new(document)))
+            .collect(),
+        _ => entry
+            .files
+            .iter()
+            .map(|file| PathBuf::from(&file.original))
+            .collect(),
+    };
+    if let Some(taken) = targets.iter().find(|target| target.exists()) {
+        return Err(CommandError::RestoreConflict {
+            path: taken.to_string_lossy().to_string(),
+        });
+    }
+    for (file, target) in entry.files.iter().zip(&targets) {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(&dir.join(&file.stored), target)?;
+    }
+    fs::remove_dir_all(&dir)?;
+    Ok(targets
+        .iter()
+        .map(|target| target.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Permanently removes entries that expired by `now`. Returns how many were removed.
+fn sweep_expired(root: &Path, now: u64) -> usize {
+    list_entries(root)
+        .into_iter()
+        .filter(|entry| entry.expires_at <= now)
+        .filter(|entry| fs::remove_dir_all(root.join(&entry.id)).is_ok())
+        .count()
+}
+
+/// Moves files out of the data directory into the trash instead of deleting them.
+/// They are kept for the `trash_retention_days` setting.
+pub fn move_to_trash(
+    app: &AppHandle,
+    kind: TrashKind,
+    label: &str,
+    document: Option<&Path>,
+    files: &[PathBuf],
+) -> Result<TrashEntry, CommandError> {
+    let retention_secs = load_settings(app).trash_retention_days * 24 * 60 * 60;
+    trash_files(
+        &trash_dir(app),
+        kind,
+        label,
+        document,
+        files,
+        now_epoch(),
+        retention_secs,
+    )
+}
+
+/// Background task that permanently removes expired trash entries.
+pub fn run_trash_sweep(app: &AppHandle) {
+    loop {
+        let removed = sweep_expired(&trash_dir(app), now_epoch());
+        if removed > 0 {
+            log::info!("run_trash_sweep: removed {} expired entries", removed);
+        }
+        wait(app, "trash-sweep", SWEEP_INTERVAL, IdlePolicy::Stretch);
+    }
+}
+
+#[tauri::command]
+pub fn list_internal_trash(app: AppHandle) -> Vec<TrashEntry> {
+    list_entries(&trash_dir(&app))
+}
+
+#[tauri::command]
+pub fn restore_from_trash(app: AppHandle, id: String) -> Result<Vec<String>, CommandError> {
+    log::info!("restore_from_trash: {}", id);
+    restore_entry(&trash_dir(&app), &id)
+}
+
+/// Permanently deletes everything in the trash, expired or not.
+#[tauri::command]
+pub fn empty_internal_trash(app: AppHandle) -> Result<(), CommandError> {
+    log::info!("empty_internal_trash");
+    let dir = trash_dir(&app);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn trashed_templates_restore_to_their_original_paths() {
+        let dir = temp_dir("trash", "template");
+        let root = dir.join("trash");
+        let template = dir.join("templates/mermaid/Flow.mmd");
+        let meta = dir.join("templates/mermaid/Flow.mmd.meta.json");
+        fs::create_dir_all(template.parent().unwrap()).unwrap();
+        fs::write(&template, "graph TD").unwrap();
+        fs::write(&meta, "{}").unwrap();
+
+        let entry = trash_files(
+            &root,
+            TrashKind::Template,
+            "mermaid/Flow",
+            None,
+            &[template.clone(), meta.clone()],
+            1_000,
+            30 * DAY,
+        )
+        .unwrap();
+        assert!(!template.exists() && !meta.exists());
+        assert_eq!(entry.bytes, 10);
+        assert_eq!(entry.expires_at, 1_000 + 30 * DAY);
+        assert_eq!(list_entries(&root).len(), 1);
+
+        // A template re-created under the same name blocks the restore.
+        fs::write(&template, "graph LR").unwrap();
+        assert!(matches!(
+            restore_entry(&root, &entry.id),
+            Err(CommandError::RestoreConflict { .. })
+        ));
+        fs::remove_file(&template).unwrap();
+
+        let restored = restore_entry(&root, &entry.id).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(&template).unwrap(), "graph TD");
+        assert!(meta.exists());
+        assert!(list_entries(&root).is_empty());
+    }
+
+    #[test]
+    fn history_snapshots_restore_next_to_their_document() {
+        let dir = temp_dir("trash", "snapshot");
+        let root = dir.join("trash");
+        let document = dir.join("docs/design.excalidraw");
+        let snapshot = dir.join("history/abc/1.snap");
+        fs::create_dir_all(document.parent().unwrap()).unwrap();
+        fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+        fs::write(&document, "current").unwrap();
+        fs::write(&snapshot, "older").unwrap();
+        fs::write(dir.join("docs/design (recovered).excalidraw"), "taken").unwrap();
+
+        let entry = trash_files(
+            &root,
+            TrashKind::HistorySnapshot,
+            &document.to_string_lossy(),
+            Some(&document),
+            &[snapshot.clone()],
+            1_000,
+            DAY,
+        )
+        .unwrap();
+        let restored = restore_entry(&root, &entry.id).unwrap();
+        assert_eq!(
+            restored,
+            [dir.join("docs/design (recovered 2).excalidraw")
+                .to_string_lossy()
+                .to_string()]
+        );
+        assert_eq!(fs::read_to_string(&restored[0]).unwrap(), "older");
+        assert!(!snapshot.exists());
+    }
+
+    #[test]
+    fn sweep_removes_only_expired_entries() {
+        let dir = temp_dir("trash", "sweep");
+        let root = dir.join("trash");
+        let mut ids = Vec::new();
+        for (name, retention) in [("short", DAY), ("long", 30 * DAY)] {
+            let file = dir.join(name);
+            fs::write(&file, name).unwrap();
+            let entry = trash_files(
+                &root,
+                TrashKind::Template,
+                name,
+                None,
+                &[file],
+                0,
+                retention,
+            )
+            .unwrap();
+            ids.push(entry.id);
+        }
+
+        assert_eq!(sweep_expired(&root, DAY - 1), 0);
+        assert_eq!(sweep_expired(&root, DAY), 1);
+        let remaining = list_entries(&root);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, ids[1]);
+        assert!(matches!(
+            restore_entry(&root, &ids[0]),
+            Err(CommandError::TrashEntryNotFound { .. })
+        ));
+        assert!(matches!(
+            restore_entry(&root, ".."),
+            Err(CommandError::TrashEntryNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::error::CommandError;
+use crate::file_name;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+/// Title of a window with no document.
+const APP_TITLE: &str = "Excalibur";
+/// What a title starts with while its document has unsaved changes. macOS shows
+/// the dot in the close button instead, through `setDocumentEdited:`.
+#[cfg(target_os = "macos")]
+const DIRTY_PREFIX: &str = "";
+#[cfg(target_os = "windows")]
+const DIRTY_PREFIX: &str = "*";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const DIRTY_PREFIX: &str = "• ";
+
+/// New documents that haven't been saved yet, and which saved documents have unsaved
+/// changes.
+#[derive(Default)]
+pub struct UntitledDocuments(Mutex<Registry>);
+
+#[derive(Default)]
+struct Registry {
+    /// The last number handed out per kind. Numbers aren't reused within a session,
+    /// so a name always means the same document.
+    last_number: HashMap<String, u32>,
+    documents: HashMap<String, UntitledEntry>,
+    dirty_paths: HashSet<PathBuf>,
+}
+
+#[derive(Debug)]
+struct UntitledEntry {
+    kind: String,
+    display_name: String,
+    /// Label of the window showing the document, once it has set its title.
+    window: Option<String>,
+    dirty: bool,
+}
+
+/// A document either by its path or, before its first save, by its untitled id.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum DocumentRef {
+    Path { path: String },
+    Untitled { untitled_id: String },
+}
+
+/// Payload of `document-assigned-path`.
+#[derive(Serialize, Clone)]
+struct AssignedPath {
+    untitled_id: String,
+    kind: String,
+    path: String,
+}
+
+fn display_name(kind: &str, number: u32) -> String {
+    let kind_name = match kind {
+        "mermaid" => "Mermaid",
+        _ => "Excalidraw",
+    };
+    match number {
+        1 => format!("Untitled {kind_name}"),
+        _ => format!("Untitled {kind_name} {number}"),
+    }
+}
+
+fn window_title(name: Option<&str>, dirty: bool) -> String {
+    match name {
+        Some(name) if dirty => format!("{DIRTY_PREFIX}{name} — {APP_TITLE}"),
+        Some(name) => format!("{name} — {APP_TITLE}"),
+        None => APP_TITLE.to_string(),
+    }
+}
+
+fn unknown(id: &str) -> CommandError {
+    CommandError::InvalidArgument {
+        message: format!("No untitled document \"{id}\""),
+    }
+}
+
+impl Registry {
+    fn create(&mut self, kind: &str) -> (String, String) {
+        let number = self.last_number.entry(kind.to_string()).or_insert(0);
+        *number += 1;
+        let id = format!("untitled-{kind}-{number}");
+        let name = display_name(kind, *number);
+        self.documents.insert(
+            id.clone(),
+            UntitledEntry {
+                kind: kind.to_string(),
+                display_name: name.clone(),
+                window: None,
+                dirty: false,
+            },
+        );
+        (id, name)
+    }
+
+    fn set_dirty(&mut self, document: &DocumentRef, dirty: bool) -> Result<(), CommandError> {
+        match document {
+            DocumentRef::Untitled { untitled_id } => {
+                self.documents
+                    .get_mut(untitled_id)
+                    .ok_or_else(|| unknown(untitled_id))?
+                    .dirty = dirty;
+            }
+            DocumentRef::Path { path } if dirty => {
+                self.dirty_paths.insert(PathBuf::from(path));
+            }
+            DocumentRef::Path { path } => {
+                self.dirty_paths.remove(Path::new(path));
+            }
+        }
+        Ok(())
+    }
+
+    /// The name and dirty flag to show for `document`.
+    fn describe(&self, document: &DocumentRef) -> Result<(String, bool), CommandError> {
+        match document {
+            DocumentRef::Untitled { untitled_id } => {
+                let entry = self
+                    .documents
+                    .get(untitled_id)
+                    .ok_or_else(|| unknown(untitled_id))?;
+                Ok((entry.display_name.clone(), entry.dirty))
+            }
+            DocumentRef::Path { path } => {
+                let path = Path::new(path);
+                let name = file_name(path).unwrap_or_else(|| path.to_string_lossy().to_string());
+                Ok((name, self.dirty_paths.contains(path)))
+            }
+        }
+    }
+
+    /// Moves an untitled document over to the path it was first saved to, which is
+    /// clean now that it has been written.
+    fn assign_path(&mut self, id: &str, path: &Path) -> Option<UntitledEntry> {
+        let entry = self.documents.remove(id)?;
+        self.dirty_paths.remove(path);
+        Some(entry)
+    }
+
+    fn forget(&mut self, id: &str) {
+        self.documents.remove(id);
+    }
+}
+
+/// Registers a new untitled document of `kind`, returning its id and display name,
+/// e.g. ("untitled-mermaid-2", "Untitled Mermaid 2").
+pub fn create(app: &AppHandle, kind: &str) -> (String, String) {
+    app.state::<UntitledDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .create(kind)
+}
+
+/// Called after every successful save to `path`. A saved document is clean again;
+/// on the first save of an untitled document its state moves to the path under one
+/// lock, its window is retitled, and `document-assigned-path` tells the frontend to
+/// switch from the id to the path.
+pub fn after_save(app: &AppHandle, untitled_id: Option<&str>, path: &Path) {
+    let state = app.state::<UntitledDocuments>();
+    let Some(id) = untitled_id else {
+        state.0.lock().unwrap().dirty_paths.remove(path);
+        return;
+    };
+    let Some(entry) = state.0.lock().unwrap().assign_path(id, path) else {
+        log::info!("after_save: no untitled document {}", id);
+        return;
+    };
+    let path_string = path.to_string_lossy().to_string();
+    log::info!(
+        "after_save: {} ({}) is now {}",
+        id,
+        entry.display_name,
+        path_string
+    );
+    if let Some(window) = entry
+        .window
+        .as_deref()
+        .and_then(|label| app.get_webview_window(label))
+    {
+        let title = window_title(file_name(path).as_deref(), false);
+        let _ = retitle(&window, &title, Some(path_string.clone()), false);
+    }
+    let _ = app.emit(
+        "document-assigned-path",
+        AssignedPath {
+            untitled_id: id.to_string(),
+            kind: entry.kind,
+            path: path_string,
+        },
+    );
+}
+
+/// Records whether a document has unsaved changes.
+#[tauri::command]
+pub fn set_dirty(app: AppHandle, document: DocumentRef, dirty: bool) -> Result<(), CommandError> {
+    app.state::<UntitledDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .set_dirty(&document, dirty)
+}
+
+/// Titles the calling window after `document`, e.g. "• Untitled Mermaid 2 — Excalibur"
+/// while it has unsaved changes, or plain "Excalibur" with no document. An untitled
+/// document is linked to the window, so its first save can retitle it.
+#[tauri::command]
+pub fn update_window_title(
+    app: AppHandle,
+    window: WebviewWindow,
+    document: Option<DocumentRef>,
+) -> Result<(), CommandError> {
+    let path = match &document {
+        Some(DocumentRef::Path { path }) => Some(path.clone()),
+        _ => None,
+    };
+    let (title, dirty) = {
+        let mut registry = app.state::<UntitledDocuments>().0.lock().unwrap();
+        match &document {
+            Some(document) => {
+                if let DocumentRef::Untitled { untitled_id } = document {
+                    if let Some(entry) = registry.documents.get_mut(untitled_id) {
+                        entry.window = Some(window.label().to_string());
+                    }
+                }
+                let (name, dirty) = registry.describe(document)?;
+                (window_title(Some(&name), dirty), dirty)
+            }
+            None => (window_title(None, false), false),
+        }
+    };
+    retitle(&window, &title, path, dirty)
+}
+
+/// Titles the calling window after a document the frontend describes itself:
+/// `name`, or else the file name of `path`, marked while `dirty`. With neither the
+/// title goes back to plain "Excalibur". On macOS the window also represents `path`,
+/// so Cmd-clicking the title shows its folders.
+#[tauri::command]
+pub fn set_window_title(
+    window: WebviewWindow,
+    name: Option<String>,
+    path: Option<String>,
+    dirty: bool,
+) -> Result<(), CommandError> {
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| path.as_deref().and_then(|path| file_name(Path::new(path))));
+    let dirty = dirty && name.is_some();
+    retitle(&window, &window_title(name.as_deref(), dirty), path, dirty)
+}
+
+fn retitle(
+    window: &WebviewWindow,
+    title: &str,
+    path: Option<String>,
+    dirty: bool,
+) -> Result<(), CommandError> {
+    window.set_title(title).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    represent_file(window, path, dirty);
+    Ok(())
+}
+
+/// Sets the file the window represents (the proxy icon in its title) and its edited
+/// dot. AppKit wants this on the main thread.
+#[cfg(target_os = "macos")]
+fn represent_file(window: &WebviewWindow, path: Option<String>, dirty: bool) {
+    use objc2_app_kit::NSWindow;
+    use objc2_foundation::NSString;
+
+    let target = window.clone();
+    let _ = window.run_on_main_thread(move || {
+        let Ok(ns_window) = target.ns_window() else {
+            return;
+        };
+        // SAFETY: Tauri hands out the window's live NSWindow, and we're on the main
+        // thread.
+        let ns_window = unsafe { &*ns_window.cast::<NSWindow>() };
+        ns_window.setRepresentedFilename(&NSString::from_str(path.as_deref().unwrap_or("")));
+        ns_window.setDocumentEdited(dirty);
+    });
+}
+
+#[cfg(not(target_os = "macos"))]
+fn represent_file(_window: &WebviewWindow, _path: Option<String>, _dirty: bool) {}
+
+/// Drops an untitled document that was closed without saving.
+#[tauri::command]
+pub fn discard_untitled(app: AppHandle, untitled_id: String) {
+    app.state::<UntitledDocuments>()
+        .0
+        .lock()
+        .unwrap()
+        .forget(&untitled_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untitled_documents_are_numbered_per_kind() {
+        let mut registry = Registry::default();
+        assert_eq!(
+            registry.create("mermaid"),
+            (
+                "untitled-mermaid-1".to_string(),
+                "Untitled Mermaid".to_string()
+            )
+        );
+        assert_eq!(registry.create("excalidraw").1, "Untitled Excalidraw");
+        let (second, name) = registry.create("mermaid");
+        assert_eq!(name, "Untitled Mermaid 2");
+
+        // Numbers aren't reused after a document goes away.
+        registry.forget(&second);
+        assert_eq!(registry.create("mermaid").1, "Untitled Mermaid 3");
+    }
+
+    #[test]
+    fn first_save_moves_state_to_the_path() {
+        let mut registry = Registry::default();
+        let (id, _) = registry.create("excalidraw");
+        let untitled = DocumentRef::Untitled {
+            untitled_id: id.clone(),
+        };
+        registry.set_dirty(&untitled, true).unwrap();
+        assert_eq!(
+            registry.describe(&untitled).unwrap(),
+            ("Untitled Excalidraw".to_string(), true)
+        );
+
+        let path = Path::new("/work/board.excalidraw");
+        let entry = registry.assign_path(&id, path).unwrap();
+        assert_eq!(entry.kind, "excalidraw");
+        assert!(registry.describe(&untitled).is_err());
+        let saved = DocumentRef::Path {
+            path: "/work/board.excalidraw".to_string(),
+        };
+        assert_eq!(
+            registry.describe(&saved).unwrap(),
+            ("board.excalidraw".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn titles_mark_unsaved_changes() {
+        assert_eq!(
+            window_title(Some("flow.mmd"), true),
+            format!("{DIRTY_PREFIX}flow.mmd — Excalibur")
+        );
+        assert_eq!(
+            window_title(Some("flow.mmd"), false),
+            "flow.mmd — Excalibur"
+        );
+        assert_eq!(window_title(None, true), "Excalibur");
+    }
+}
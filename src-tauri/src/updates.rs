@@ -0,0 +1,208 @@
+use crate::http::http_client;
+use crate::settings::load_settings;
+use crate::{app_data_dir, now_epoch, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The version manifest published alongside each release.
+#[derive(Deserialize)]
+struct VersionManifest {
+    version: String,
+    notes_url: Option<String>,
+    download_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct UpdateCheckState {
+    last_checked: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    UpToDate,
+    UpdateAvailable,
+    /// The manifest could not be fetched or parsed; says nothing about whether
+    /// an update exists.
+    CheckFailed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UpdateCheckResponse {
+    status: UpdateStatus,
+    current: String,
+    latest: Option<String>,
+    notes_url: Option<String>,
+    download_url: Option<String>,
+    error: Option<String>,
+}
+
+fn state_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("update-check.json")
+}
+
+fn load_state(app: &AppHandle) -> UpdateCheckState {
+    fs::read_to_string(state_path(app))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(app: &AppHandle, state: &UpdateCheckState) {
+    if let Ok(contents) = serde_json::to_string_pretty(state) {
+        let _ = write_file(&state_path(app), &contents);
+    }
+}
+
+/// Parses the numeric part of a version ("v1.2.3-beta" → [1, 2, 3]).
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(|ch| ch == '-' || ch == '+')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Whether `version` has a pre-release suffix, as in "1.2.0-beta.2".
+fn is_prerelease(version: &str) -> bool {
+    version
+        .trim()
+        .split('+')
+        .next()
+        .is_some_and(|version| version.contains('-'))
+}
+
+/// Compares the numeric parts, padding the shorter with zeros. A release is newer
+/// than its own pre-releases.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let (mut latest_parts, mut current_parts) = (version_parts(latest), version_parts(current));
+    let length = latest_parts.len().max(current_parts.len());
+    latest_parts.resize(length, 0);
+    current_parts.resize(length, 0);
+    latest_parts > current_parts
+        || (latest_parts == current_parts && is_prerelease(current) && !is_prerelease(latest))
+}
+
+async fn fetch_manifest(app: &AppHandle) -> Result<VersionManifest, String> {
+    let settings = load_settings(app);
+    let client = http_client(&settings, UPDATE_CHECK_TIMEOUT)?;
+    let response = client
+        .get(&settings.update_manifest_url)
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Update server returned {}", response.status()));
+    }
+    response
+        .json::<VersionManifest>()
+        .await
+        .map_err(|error| format!("Invalid version manifest: {error}"))
+}
+
+async fn run_update_check(app: &AppHandle) -> UpdateCheckResponse {
+    let current = app.package_info().version.to_string();
+    match fetch_manifest(app).await {
+        Ok(manifest) => {
+            save_state(
+                app,
+                &UpdateCheckState {
+                    last_checked: now_epoch(),
+                },
+            );
+            let status = if is_newer(&manifest.version, &current) {
+                UpdateStatus::UpdateAvailable
+            } else {
+                UpdateStatus::UpToDate
+            };
+            UpdateCheckResponse {
+                status,
+                current,
+                latest: Some(manifest.version),
+                notes_url: manifest.notes_url,
+                download_url: manifest.download_url,
+                error: None,
+            }
+        }
+        Err(error) => {
+            log::warn!("check_for_updates: check failed: {}", error);
+            UpdateCheckResponse {
+                status: UpdateStatus::CheckFailed,
+                current,
+                latest: None,
+                notes_url: None,
+                download_url: None,
+                error: Some(error),
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> UpdateCheckResponse {
+    run_update_check(&app).await
+}
+
+/// Runs the automatic update check at startup when the configured frequency says
+/// one is due, and emits `update-available` when a newer version exists.
+pub fn spawn_startup_update_check(app: &AppHandle) {
+    let interval = match load_settings(app).update_check_frequency.as_str() {
+        "daily" => 24 * 60 * 60,
+        "weekly" => 7 * 24 * 60 * 60,
+        _ => return,
+    };
+    if now_epoch().saturating_sub(load_state(app).last_checked) < interval {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let response = run_update_check(&app).await;
+        if matches!(response.status, UpdateStatus::UpdateAvailable) {
+            let _ = app.emit("update-available", response);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_past_prefixes_and_suffixes() {
+        assert_eq!(version_parts("v1.2.3"), [1, 2, 3]);
+        assert_eq!(version_parts(" 1.2.3-beta.1 "), [1, 2, 3]);
+        assert_eq!(version_parts("1.2.3+build.7"), [1, 2, 3]);
+        assert_eq!(version_parts("1.x"), [1, 0]);
+    }
+
+    #[test]
+    fn compares_numerically_whatever_the_component_count() {
+        assert!(is_newer("1.10.0", "1.9.9"));
+        assert!(!is_newer("1.9.9", "1.10.0"));
+        assert!(!is_newer("1.2", "1.2.0"));
+        assert!(!is_newer("1.2.0", "1.2"));
+        assert!(is_newer("1.2.0.1", "1.2"));
+        assert!(is_newer("v2.0.0", "1.99.99"));
+        assert!(!is_newer("v1.2.3", "1.2.3"));
+    }
+
+    #[test]
+    fn releases_are_newer_than_their_pre_releases() {
+        assert!(is_newer("1.2.0", "1.2.0-beta.2"));
+        assert!(!is_newer("1.2.0-beta.2", "1.2.0"));
+        assert!(!is_newer("1.2.0-rc.1", "1.2.0-beta.2"));
+        assert!(is_newer("1.3.0-beta.1", "1.2.0"));
+        assert!(!is_newer("1.2.0+build.9", "1.2.0"));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::background::{wait, IdlePolicy};
+use crate::dialogs::{pick_save_path, suggested_file_name};
+use crate::error::CommandError;
+use crate::{file_name, kind_dialog_filter, update_recents, SaveFileResponse};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often a disconnected volume is checked for having come back.
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The volumes open documents live on, and which of them have gone away.
+#[derive(Default)]
+pub struct Volumes {
+    documents: Mutex<HashMap<PathBuf, TrackedDocument>>,
+    polling: AtomicBool,
+}
+
+struct TrackedDocument {
+    /// Mount point (Unix) or drive/share root (Windows) the document lives on.
+    volume: PathBuf,
+    disconnected: bool,
+}
+
+/// Payload of the `volume-disconnected` and `volume-reconnected` events.
+#[derive(Serialize, Clone)]
+struct VolumeEvent {
+    path: String,
+    volume: String,
+}
+
+/// The mount point `path` lives on: the topmost ancestor on the same device. `None`
+/// for the system root, which can't be ejected, or when `path` can't be read.
+#[cfg(unix)]
+fn volume_root(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    let device = std::fs::metadata(path).ok()?.dev();
+    let mut root = path;
+    for ancestor in path.ancestors().skip(1) {
+        match std::fs::metadata(ancestor) {
+            Ok(metadata) if metadata.dev() == device => root = ancestor,
+            _ => break,
+        }
+    }
+    (root.parent().is_some()).then(|| root.to_path_buf())
+}
+
+/// The drive (`E:\`) or share (`\\server\share\`) root of `path`.
+#[cfg(not(unix))]
+fn volume_root(path: &Path) -> Option<PathBuf> {
+    use std::path::Component;
+    let mut components = path.components();
+    match (components.next(), components.next()) {
+        (Some(Component::Prefix(prefix)), Some(Component::RootDir)) => {
+            let mut root = PathBuf::from(prefix.as_os_str());
+            root.push(Component::RootDir.as_os_str());
+            Some(root)
+        }
+        _ => None,
+    }
+}
+
+/// A mount point is mounted while it sits on a different device than its parent.
+#[cfg(unix)]
+fn is_mounted(volume: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let Ok(metadata) = std::fs::metadata(volume) else {
+        return false;
+    };
+    volume
+        .parent()
+        .and_then(|parent| std::fs::metadata(parent).ok())
+        .is_none_or(|parent| parent.dev() != metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn is_mounted(volume: &Path) -> bool {
+    volume.exists()
+}
+
+/// Remembers which volume an opened document is on, so a later failure can be told
+/// apart from an ordinary I/O error. Documents on the system volume aren't tracked.
+pub fn track(app: &AppHandle, path: &Path) {
+    let Some(volume) = volume_root(path) else {
+        return;
+    };
+    app.state::<Volumes>().documents.lock().unwrap().insert(
+        path.to_path_buf(),
+        TrackedDocument {
+            volume,
+            disconnected: false,
+        },
+    );
+}
+
+/// True while the volume `path` lives on is known to be gone. Saves and watchers
+/// skip such documents instead of failing over and over.
+pub fn is_disconnected(app: &AppHandle, path: &Path) -> bool {
+    app.state::<Volumes>()
+        .documents
+        .lock()
+        .unwrap()
+        .get(path)
+        .is_some_and(|document| document.disconnected)
+}
+
+/// Called after an operation on `path` failed. When its volume is no longer mounted
+/// the document is marked disconnected, `volume-disconnected` is emitted once, and a
+/// poller waits for the volume to return. Returns whether the volume is gone.
+pub fn check_failure(app: &AppHandle, path: &Path) -> bool {
+    let volumes = app.state::<Volumes>();
+    let newly_disconnected = {
+        let mut documents = volumes.documents.lock().unwrap();
+        let Some(document) = documents.get_mut(path) else {
+            return false;
+        };
+        if document.disconnected {
+            return true;
+        }
+        if is_mounted(&document.volume) {
+            return false;
+        }
+        document.disconnected = true;
+        document.volume.clone()
+    };
+
+    log::warn!(
+        "check_failure: volume {} of {} is gone",
+        newly_disconnected.display(),
+        path.display()
+    );
+    let _ = app.emit(
+        "volume-disconnected",
+        VolumeEvent {
+            path: path.to_string_lossy().to_string(),
+            volume: newly_disconnected.to_string_lossy().to_string(),
+        },
+    );
+    if !volumes.polling.swap(true, Ordering::SeqCst) {
+        let poller_app = app.clone();
+        std::thread::spawn(move || run_reconnect_poller(poller_app));
+    }
+    true
+}
+
+/// Error for an operation refused because the document's volume is gone.
+pub fn disconnected_error(path: &Path) -> CommandError {
+    CommandError::VolumeDisconnected {
+        path: path.to_string_lossy().to_string(),
+    }
+}
+
+/// Checks disconnected volumes every `RECONNECT_POLL_INTERVAL`, emitting
+/// `volume-reconnected` for each document whose volume is mounted again. Stops once
+/// nothing is disconnected.
+fn run_reconnect_poller(app: AppHandle) {
+    loop {
+        wait(
+            &app,
+            "volume-reconnect",
+            RECONNECT_POLL_INTERVAL,
+            IdlePolicy::Stretch,
+        );
+        let volumes = app.state::<Volumes>();
+        let mut reconnected = Vec::new();
+        {
+            let mut documents = volumes.documents.lock().unwrap();
+            for (path, document) in documents.iter_mut() {
+                if document.disconnected && is_mounted(&document.volume) {
+                    document.disconnected = false;
+                    reconnected.push(VolumeEvent {
+                        path: path.to_string_lossy().to_string(),
+                        volume: document.volume.to_string_lossy().to_string(),
+                    });
+                }
+            }
+            if !documents.values().any(|document| document.disconnected) {
+                volumes.polling.store(false, Ordering::SeqCst);
+            }
+        }
+        for event in reconnected {
+            log::info!("run_reconnect_poller: {} is back", event.volume);
+            let _ = app.emit("volume-reconnected", event);
+        }
+        if !volumes.polling.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+}
+
+/// Saves a document whose volume went away: goes straight to the save dialog and
+/// writes the contents wherever the user picks. `previous_path`, the unreachable
+/// original, stops being tracked and its lock is dropped.
+#[tauri::command]
+pub async fn save_elsewhere(
+    app: AppHandle,
+    kind: String,
+    contents: String,
+    name: Option<String>,
+    previous_path: Option<String>,
+) -> Result<SaveFileResponse, CommandError> {
+    if !matches!(kind.as_str(), "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind { kind });
+    }
+    let (filter_name, extensions) = kind_dialog_filter(&kind);
+    let suggested_name = suggested_file_name(name.as_deref(), "Untitled", extensions);
+    let path = pick_save_path(&app, filter_name, extensions, suggested_name)
+        .await?
+        .ok_or(CommandError::Cancelled)?;
+    let compress = crate::gzip::has_gz_suffix(&path);
+    crate::crypto::write_document(&app, &kind, &path, &contents, compress).await?;
+    let path_string = path.to_string_lossy().to_string();
+    let name = file_name(&path);
+    update_recents(&app, &kind, &path_string, name.clone());
+    track(&app, &path);
+    if let Some(previous) = previous_path.map(PathBuf::from) {
+        app.state::<Volumes>()
+            .documents
+            .lock()
+            .unwrap()
+            .remove(&previous);
+        crate::locks::release(&app, &previous);
+    }
+    log::info!("save_elsewhere: saved to {}", path_string);
+    Ok(SaveFileResponse {
+        path: path_string,
+        name,
+        mtime: crate::modified_millis(&path),
+        open_in_other_windows: Vec::new(),
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_system_root_is_not_a_removable_volume() {
+        assert_eq!(volume_root(Path::new("/")), None);
+        assert!(is_mounted(Path::new("/")));
+    }
+
+    #[test]
+    fn documents_resolve_to_their_mount_point() {
+        let dir = std::env::temp_dir().join(format!("excalibur-volumes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let document = dir.join("flow.mmd");
+        std::fs::write(&document, "graph TD").unwrap();
+
+        // The temp folder may itself be on the system root volume.
+        if let Some(volume) = volume_root(&document) {
+            assert!(document.starts_with(&volume));
+            assert!(is_mounted(&volume));
+            assert!(!is_mounted(&document));
+        }
+        assert!(!is_mounted(&dir.join("missing")));
+    }
+}
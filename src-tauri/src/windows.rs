@@ -0,0 +1,65 @@
+use crate::error::CommandError;
+use crate::event_queue::emit_when_ready;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+/// Label of the first free `document-<n>` window.
+fn free_label(taken: impl Fn(&str) -> bool) -> String {
+    (1..)
+        .map(|number| format!("document-{number}"))
+        .find(|label| !taken(label))
+        .expect("window labels are endless")
+}
+
+/// Creates a window labelled `label` that loads the frontend, sized like the main
+/// window. It gets its events once it calls `frontend_ready`.
+pub fn create_window(app: &AppHandle, label: &str) -> Result<(), CommandError> {
+    WebviewWindowBuilder::new(app, label, WebviewUrl::default())
+        .title("Excalibur")
+        .inner_size(1280.0, 820.0)
+        .build()
+        .map(|_| ())
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        })
+}
+
+/// Opens a new window with the document at `path`, or a new untitled document of
+/// `kind` without one, and returns the window's label. The document reaches the
+/// window as an `open-file` or `new-document` event, queued until it has loaded.
+#[tauri::command]
+pub async fn open_in_new_window(
+    app: AppHandle,
+    kind: String,
+    path: Option<String>,
+) -> Result<String, CommandError> {
+    if !matches!(kind.as_str(), "excalidraw" | "mermaid") {
+        return Err(CommandError::InvalidKind { kind });
+    }
+    let label = free_label(|label| app.get_webview_window(label).is_some());
+    create_window(&app, &label)?;
+    log::info!(
+        "open_in_new_window: {} in {}",
+        path.as_deref().unwrap_or(&kind),
+        label
+    );
+    match path {
+        Some(path) => emit_when_ready(&app, Some(&label), "open-file", path),
+        None => {
+            let document = crate::templates::new_document(&app, &kind)?;
+            emit_when_ready(&app, Some(&label), "new-document", document);
+        }
+    }
+    Ok(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_fill_the_first_gap() {
+        assert_eq!(free_label(|_| false), "document-1");
+        let open = ["main", "document-1", "document-3"];
+        assert_eq!(free_label(|label| open.contains(&label)), "document-2");
+    }
+}
@@ -0,0 +1,354 @@
+use crate::detect::mermaid_diagram_type;
+use crate::dialogs::pick_save_path;
+use crate::error::CommandError;
+use crate::file_info::{scene_stats, SceneStats, MAX_STATS_BYTES};
+use crate::operations::Operation;
+use crate::recent_previews::{build_preview, read_head};
+use crate::scan::scan_workspace;
+use crate::{file_status, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// One diagram in the report.
+#[derive(Serialize, Debug)]
+struct ReportRow {
+    /// Relative to the workspace root, with `/` separators.
+    path: String,
+    kind: &'static str,
+    /// Mermaid diagram type, e.g. "sequenceDiagram".
+    diagram_type: Option<&'static str>,
+    size: Option<u64>,
+    modified_at: Option<u64>,
+    /// Only for plain excalidraw scenes up to `MAX_STATS_BYTES`.
+    scene_stats: Option<SceneStats>,
+    /// The same preview the start screen shows for recents.
+    preview: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    root: &'a str,
+    generated_at: u64,
+    files: &'a [ReportRow],
+}
+
+#[derive(Serialize)]
+pub struct ReportResponse {
+    path: String,
+    files: usize,
+}
+
+fn report_row(root: &Path, path: &Path, kind: &'static str) -> ReportRow {
+    let status = file_status(path);
+    let diagram_type = match kind {
+        "mermaid" => read_head(path)
+            .and_then(|(bytes, _)| mermaid_diagram_type(&String::from_utf8_lossy(&bytes))),
+        _ => None,
+    };
+    let stats = match (kind, status.size) {
+        ("excalidraw", Some(size)) if size <= MAX_STATS_BYTES => fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| scene_stats(&contents)),
+        _ => None,
+    };
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    ReportRow {
+        path: relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        kind,
+        diagram_type,
+        size: status.size,
+        modified_at: status.modified_at,
+        scene_stats: stats,
+        preview: build_preview(kind, path),
+    }
+}
+
+/// ISO 8601 in UTC, so the column sorts correctly as text.
+fn format_time(epoch: Option<u64>) -> String {
+    epoch
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// The cells shared by the CSV and markdown formats.
+fn row_cells(row: &ReportRow) -> [String; 7] {
+    let optional = |value: Option<usize>| value.map(|value| value.to_string()).unwrap_or_default();
+    [
+        row.path.clone(),
+        row.kind.to_string(),
+        row.diagram_type.unwrap_or_default().to_string(),
+        row.size.map(|size| size.to_string()).unwrap_or_default(),
+        format_time(row.modified_at),
+        optional(row.scene_stats.as_ref().map(|stats| stats.elements)),
+        row.preview.clone().unwrap_or_default(),
+    ]
+}
+
+const COLUMNS: [&str; 7] = [
+    "path",
+    "kind",
+    "diagram_type",
+    "size",
+    "modified_at",
+    "elements",
+    "preview",
+];
+
+/// Quotes a CSV field when it contains a separator, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) || value.starts_with(' ') || value.ends_with(' ') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(rows: &[ReportRow]) -> String {
+    let mut out = COLUMNS.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let cells: Vec<String> = row_cells(row).iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&cells.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Escapes a value for a markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+/// A short summary and one table. Columns hold plain values (byte counts, ISO
+/// dates) so viewers with sortable tables order them correctly.
+fn to_markdown(root: &str, generated_at: u64, rows: &[ReportRow]) -> String {
+    let mut out = format!(
+        "# Diagram report\n\n- Root: `{}`\n- Diagrams: {}\n- Generated: {}\n\n",
+        root.replace('`', "'"),
+        rows.len(),
+        format_time(Some(generated_at))
+    );
+    out.push_str(&format!("| {} |\n", COLUMNS.join(" | ")));
+    out.push_str(&format!("|{}\n", " --- |".repeat(COLUMNS.len())));
+    for row in rows {
+        let cells: Vec<String> = row_cells(row)
+            .iter()
+            .map(|cell| markdown_cell(cell))
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+fn render(
+    format: ReportFormat,
+    root: &str,
+    generated_at: u64,
+    rows: &[ReportRow],
+) -> Result<String, CommandError> {
+    match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&JsonReport {
+            root,
+            generated_at,
+            files: rows,
+        })
+        .map_err(|error| CommandError::Io {
+            message: error.to_string(),
+        }),
+        ReportFormat::Csv => Ok(to_csv(rows)),
+        ReportFormat::Markdown => Ok(to_markdown(root, generated_at, rows)),
+    }
+}
+
+fn collect_rows(root: &Path, operation: &Operation) -> Result<Vec<ReportRow>, CommandError> {
+    operation.progress("scanning", 0, None, None);
+    let files = scan_workspace(root);
+    let total = files.len() as u64;
+    let mut rows = Vec::with_capacity(files.len());
+    for (index, file) in files.iter().enumerate() {
+        operation.check()?;
+        operation.progress(
+            "reading",
+            index as u64,
+            Some(total),
+            Some(&file.path.to_string_lossy()),
+        );
+        rows.push(report_row(root, &file.path, file.kind));
+    }
+    Ok(rows)
+}
+
+/// Writes an inventory of every diagram under `root` to `target`, or to a file the
+/// user picks. Reports progress as `operation-progress` events and stops on
+/// `cancel_operation`, in which case nothing is written.
+#[tauri::command]
+pub async fn export_workspace_report(
+    app: AppHandle,
+    root: String,
+    format: ReportFormat,
+    target: Option<String>,
+    operation_id: Option<String>,
+    notify_when_done: Option<bool>,
+) -> Result<ReportResponse, CommandError> {
+    let root_path = PathBuf::from(&root);
+    if !root_path.is_dir() {
+        return Err(CommandError::InvalidArgument {
+            message: format!("{root} is not a folder"),
+        });
+    }
+    let target = match target {
+        Some(target) => PathBuf::from(target),
+        None => {
+            let suggested_name = format!(
+                "diagram-report-{}.{}",
+                chrono::Local::now().format("%Y-%m-%d"),
+                format.extension()
+            );
+            pick_save_path(&app, "Report", &[format.extension()], suggested_name)
+                .await?
+                .ok_or(CommandError::Cancelled)?
+        }
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = Operation::start(&app, "workspace-report", operation_id, true)
+            .notify_when_done(notify_when_done);
+        let outcome = collect_rows(&root_path, &operation).and_then(|rows| {
+            let contents = render(format, &root, crate::now_epoch(), &rows)?;
+            write_file(&target, &contents)?;
+            Ok(rows.len())
+        });
+        operation.finish(&outcome, *outcome.as_ref().unwrap_or(&0) as u64);
+        let summary = match &outcome {
+            Ok(files) => format!("Wrote a report of {files} diagrams to {}", target.display()),
+            Err(CommandError::Cancelled) => "Workspace report was cancelled".to_string(),
+            Err(error) => format!("Workspace report failed: {error}"),
+        };
+        operation.notify_done(&summary, target.to_string_lossy());
+        log::info!("export_workspace_report: {}", summary);
+        Ok(ReportResponse {
+            path: target.to_string_lossy().to_string(),
+            files: outcome?,
+        })
+    })
+    .await
+    .map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::CancellationToken;
+    use crate::test_support::temp_dir;
+
+    fn row(path: &str, preview: Option<&str>) -> ReportRow {
+        ReportRow {
+            path: path.to_string(),
+            kind: "mermaid",
+            diagram_type: Some("flowchart"),
+            size: Some(12),
+            modified_at: Some(0),
+            scene_stats: None,
+            preview: preview.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn collects_kind_type_and_stats() {
+        let dir = temp_dir("report", "collect");
+        fs::create_dir_all(dir.join("flows")).unwrap();
+        fs::write(
+            dir.join("flows/login.mmd"),
+            "sequenceDiagram\n  A->>B: hi\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("board.excalidraw"),
+            r#"{"type":"excalidraw","elements":[{"type":"arrow"},{"type":"text"}]}"#,
+        )
+        .unwrap();
+        let operation =
+            Operation::detached("workspace-report", CancellationToken::default(), |_| {});
+
+        let rows = collect_rows(&dir, &operation).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].path, "board.excalidraw");
+        assert_eq!(rows[0].scene_stats.as_ref().unwrap().elements, 2);
+        assert_eq!(rows[1].path, "flows/login.mmd");
+        assert_eq!(rows[1].diagram_type, Some("sequenceDiagram"));
+        assert_eq!(rows[1].preview.as_deref(), Some("sequenceDiagram"));
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let csv = to_csv(&[row("a, \"b\".mmd", Some("graph TD\nA"))]);
+        let mut lines = csv.split("\r\n");
+        assert_eq!(
+            lines.next(),
+            Some("path,kind,diagram_type,size,modified_at,elements,preview")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("\"a, \"\"b\"\".mmd\",mermaid,flowchart,12,1970-01-01T00:00:00Z,,\"graph TD\nA\"")
+        );
+        assert_eq!(csv_field("plain/path.mmd"), "plain/path.mmd");
+        assert_eq!(csv_field(" padded"), "\" padded\"");
+    }
+
+    #[test]
+    fn markdown_escapes_table_cells() {
+        let markdown = to_markdown("/work", 0, &[row("a|b.mmd", None)]);
+        assert!(markdown.starts_with(
+            "# Diagram report\n\n- Root: `/work`\n- Diagrams: 1\n- Generated: 1970-01-01T00:00:00Z\n\n"
+        ));
+        assert!(markdown.contains(
+            "| path | kind | diagram_type | size | modified_at | elements | preview |\n"
+        ));
+        assert!(markdown.contains("| --- | --- | --- | --- | --- | --- | --- |\n"));
+        assert!(markdown
+            .contains("| a\\|b.mmd | mermaid | flowchart | 12 | 1970-01-01T00:00:00Z |  |  |\n"));
+    }
+
+    #[test]
+    fn cancelled_reports_collect_nothing() {
+        let dir = temp_dir("report", "cancel");
+        fs::write(dir.join("a.mmd"), "graph TD").unwrap();
+        let token = CancellationToken::default();
+        token.cancel();
+        let operation = Operation::detached("workspace-report", token, |_| {});
+        assert!(matches!(
+            collect_rows(&dir, &operation),
+            Err(CommandError::Cancelled)
+        ));
+    }
+}
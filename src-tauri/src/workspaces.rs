@@ -0,0 +1,238 @@
+use crate::error::CommandError;
+use crate::event_queue::emit_when_ready;
+use crate::{app_data_dir, now_epoch, write_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Version of the `workspaces.json` layout.
+pub const WORKSPACES_VERSION: u32 = 1;
+const MAIN_WINDOW: &str = "main";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WorkspaceDocument {
+    kind: String,
+    path: String,
+    /// Label of the window the document was open in; `None` means the main window.
+    #[serde(default)]
+    window: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    name: String,
+    documents: Vec<WorkspaceDocument>,
+    created_at: u64,
+    updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WorkspaceStore {
+    version: u32,
+    workspaces: Vec<Workspace>,
+}
+
+#[derive(Serialize)]
+pub struct MissingDocument {
+    path: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct OpenWorkspaceResponse {
+    name: String,
+    opened: Vec<WorkspaceDocument>,
+    missing: Vec<MissingDocument>,
+    windows_created: Vec<String>,
+}
+
+fn workspaces_path(app: &AppHandle) -> PathBuf {
+    app_data_dir(app).join("workspaces.json")
+}
+
+fn load_store(app: &AppHandle) -> Result<WorkspaceStore, CommandError> {
+    let Ok(contents) = fs::read_to_string(workspaces_path(app)) else {
+        return Ok(WorkspaceStore {
+            version: WORKSPACES_VERSION,
+            workspaces: Vec::new(),
+        });
+    };
+    let store: WorkspaceStore =
+        serde_json::from_str(&contents).map_err(|error| CommandError::InvalidDocument {
+            message: format!("workspaces.json: {error}"),
+        })?;
+    // Refuse rather than overwrite a file written by a newer version.
+    if store.version > WORKSPACES_VERSION {
+        return Err(CommandError::InvalidDocument {
+            message: format!(
+                "workspaces.json was written by a newer version of Excalibur (v{})",
+                store.version
+            ),
+        });
+    }
+    Ok(store)
+}
+
+fn save_store(app: &AppHandle, store: &WorkspaceStore) -> Result<(), CommandError> {
+    let contents = serde_json::to_string_pretty(store).map_err(|error| CommandError::Io {
+        message: error.to_string(),
+    })?;
+    write_file(&workspaces_path(app), &contents)?;
+    Ok(())
+}
+
+fn find_workspace(store: &WorkspaceStore, name: &str) -> Result<Workspace, CommandError> {
+    store
+        .workspaces
+        .iter()
+        .find(|workspace| workspace.name == name)
+        .cloned()
+        .ok_or_else(|| CommandError::WorkspaceNotFound {
+            name: name.to_string(),
+        })
+}
+
+/// Window labels may only contain alphanumerics, `-`, `/`, `:` and `_`.
+fn window_label(document: &WorkspaceDocument) -> String {
+    document
+        .window
+        .as_deref()
+        .filter(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '/' | ':' | '_'))
+        })
+        .unwrap_or(MAIN_WINDOW)
+        .to_string()
+}
+
+fn check_document(document: &WorkspaceDocument) -> Result<(), String> {
+    let path = Path::new(&document.path);
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Ok(()),
+        Ok(_) => Err("Not a file".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Saves (or replaces) a workspace from the documents the frontend reports as open.
+#[tauri::command]
+pub fn save_workspace(
+    app: AppHandle,
+    name: String,
+    documents: Vec<WorkspaceDocument>,
+) -> Result<Vec<Workspace>, CommandError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(CommandError::InvalidArgument {
+            message: "A workspace needs a name".to_string(),
+        });
+    }
+
+    let mut store = load_store(&app)?;
+    let now = now_epoch();
+    let created_at = store
+        .workspaces
+        .iter()
+        .find(|workspace| workspace.name == name)
+        .map_or(now, |workspace| workspace.created_at);
+    store.workspaces.retain(|workspace| workspace.name != name);
+    store.workspaces.push(Workspace {
+        name,
+        documents,
+        created_at,
+        updated_at: now,
+    });
+    store
+        .workspaces
+        .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    store.version = WORKSPACES_VERSION;
+    save_store(&app, &store)?;
+    Ok(store.workspaces)
+}
+
+/// Points saved workspaces at a document's new location.
+pub fn replace_document_path(app: &AppHandle, old: &str, new: &str) -> Result<(), CommandError> {
+    let mut store = load_store(app)?;
+    let mut changed = false;
+    for document in store
+        .workspaces
+        .iter_mut()
+        .flat_map(|workspace| workspace.documents.iter_mut())
+        .filter(|document| document.path == old)
+    {
+        document.path = new.to_string();
+        changed = true;
+    }
+    if changed {
+        save_store(app, &store)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_workspaces(app: AppHandle) -> Result<Vec<Workspace>, CommandError> {
+    Ok(load_store(&app)?.workspaces)
+}
+
+/// Opens every document in a workspace, in order, by emitting `open-file` to the
+/// window it belongs to.
+///
+/// Documents whose file has gone missing are reported in `missing` and skipped.
+/// Documents assigned to a window that isn't open go to the main window, unless
+/// `spawn_windows` is set, in which case the window is created and its `open-file`
+/// events wait in the event queue until it has loaded.
+#[tauri::command]
+pub fn open_workspace(
+    app: AppHandle,
+    name: String,
+    spawn_windows: Option<bool>,
+) -> Result<OpenWorkspaceResponse, CommandError> {
+    let workspace = find_workspace(&load_store(&app)?, &name)?;
+    let spawn_windows = spawn_windows.unwrap_or(false);
+    let mut response = OpenWorkspaceResponse {
+        name: workspace.name.clone(),
+        opened: Vec::new(),
+        missing: Vec::new(),
+        windows_created: Vec::new(),
+    };
+
+    for document in workspace.documents {
+        if let Err(message) = check_document(&document) {
+            log::info!("open_workspace: skipping {}: {}", document.path, message);
+            response.missing.push(MissingDocument {
+                path: document.path,
+                message,
+            });
+            continue;
+        }
+
+        let mut label = window_label(&document);
+        if app.get_webview_window(&label).is_none() {
+            if spawn_windows && label != MAIN_WINDOW {
+                if !response.windows_created.contains(&label) {
+                    crate::windows::create_window(&app, &label)?;
+                    response.windows_created.push(label.clone());
+                }
+            } else {
+                label = MAIN_WINDOW.to_string();
+            }
+        }
+
+        emit_when_ready(&app, Some(&label), "open-file", document.path.clone());
+        response.opened.push(document);
+    }
+
+    Ok(response)
+}
+
+#[tauri::command]
+pub fn delete_workspace(app: AppHandle, name: String) -> Result<Vec<Workspace>, CommandError> {
+    let mut store = load_store(&app)?;
+    find_workspace(&store, &name)?;
+    store.workspaces.retain(|workspace| workspace.name != name);
+    save_store(&app, &store)?;
+    Ok(store.workspaces)
+}